@@ -0,0 +1,194 @@
+//! Reading throughput of synthetic files: metadata parsing, sorted and unsorted data
+//! decoding, DZ decompression, conversions per data type, projection of wide records and
+//! column storage. Synthetic files need the test-utils feature: cargo bench --features test-utils
+use std::collections::HashSet;
+
+use arrow::datatypes::DataType;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use mdfr::mdfreader::Mdf;
+use mdfr::mdfwriter::mdfwriter4::Compression;
+use mdfr::test_utils::{DataLayout, SyntheticMdf};
+
+/// number of records of each channel group of the data benchmarks
+const RECORDS: usize = 100_000;
+/// data types of the channels
+const DATA_TYPES: [DataType; 10] = [
+    DataType::UInt8,
+    DataType::Int8,
+    DataType::UInt16,
+    DataType::Int16,
+    DataType::UInt32,
+    DataType::Int32,
+    DataType::UInt64,
+    DataType::Int64,
+    DataType::Float32,
+    DataType::Float64,
+];
+
+/// parses metadata and loads all channels data
+fn load(bytes: &[u8]) -> Mdf {
+    let mut mdf = Mdf::from_bytes(bytes, "synthetic.mf4").expect("failed parsing metadata");
+    let channel_names: HashSet<String> = mdf.get_channel_names_set();
+    mdf.load_channels_data_from_bytes(bytes, channel_names)
+        .expect("failed loading data");
+    mdf
+}
+
+fn header_parse(c: &mut Criterion) {
+    let mut file = SyntheticMdf::mdf4().with_records(10);
+    for _ in 0..100 {
+        file = file.with_group(&DATA_TYPES);
+    }
+    let bytes = file.to_bytes().expect("failed building file");
+    let mut group = c.benchmark_group("header_parse");
+    group.bench_function("mdf4_100_groups", |b| {
+        b.iter(|| Mdf::from_bytes(&bytes, "synthetic.mf4").expect("failed parsing metadata"))
+    });
+    group.finish();
+}
+
+fn data_blocks(c: &mut Criterion) {
+    let files = [
+        ("sorted_dt", SyntheticMdf::mdf4()),
+        (
+            "sorted_dl",
+            SyntheticMdf::mdf4().with_layout(DataLayout::Dl {
+                records_per_block: 10_000,
+            }),
+        ),
+        ("dz", SyntheticMdf::mdf4().with_layout(DataLayout::Dz)),
+        (
+            "hl_dz",
+            SyntheticMdf::mdf4().with_layout(DataLayout::Hl {
+                records_per_block: 10_000,
+            }),
+        ),
+        ("unsorted_dt", SyntheticMdf::mdf4().unsorted()),
+        (
+            "unsorted_dz",
+            SyntheticMdf::mdf4().unsorted().with_layout(DataLayout::Dz),
+        ),
+        ("mdf3_sorted", SyntheticMdf::mdf3()),
+        ("mdf3_unsorted", SyntheticMdf::mdf3().unsorted()),
+    ];
+    let mut group = c.benchmark_group("data_blocks");
+    group.sample_size(20);
+    for (name, file) in files {
+        let file = file
+            .with_group(&DATA_TYPES)
+            .with_group(&DATA_TYPES[..5])
+            .with_records(RECORDS);
+        let bytes = file.to_bytes().expect("failed building file");
+        group.throughput(Throughput::Bytes(bytes.len() as u64));
+        group.bench_function(name, |b| b.iter(|| load(&bytes)));
+    }
+    group.finish();
+}
+
+fn conversions(c: &mut Criterion) {
+    let mut group = c.benchmark_group("conversions");
+    group.sample_size(20);
+    group.throughput(Throughput::Elements((8 * RECORDS) as u64));
+    for data_type in DATA_TYPES {
+        let file = SyntheticMdf::mdf4()
+            .with_group(&vec![data_type.clone(); 8])
+            .with_records(RECORDS);
+        let raw = file.to_bytes().expect("failed building file");
+        let linear = file
+            .with_linear_conversion(-3.5, 0.125)
+            .to_bytes()
+            .expect("failed building file");
+        group.bench_with_input(BenchmarkId::new("raw", &data_type), &raw, |b, bytes| {
+            b.iter(|| load(bytes))
+        });
+        group.bench_with_input(
+            BenchmarkId::new("linear", &data_type),
+            &linear,
+            |b, bytes| b.iter(|| load(bytes)),
+        );
+    }
+    group.finish();
+}
+
+/// loads some channels of wide records: 4 and 16 channels are extracted together by record
+/// walk, a single channel and all the channels by per channel loops
+fn projection(c: &mut Criterion) {
+    let file = SyntheticMdf::mdf4()
+        .with_group(&[&DATA_TYPES[..]; 10].concat())
+        .with_records(RECORDS);
+    let bytes = file.to_bytes().expect("failed building file");
+    let mut group = c.benchmark_group("projection");
+    group.sample_size(20);
+    for n_channels in [1, 4, 16, 100] {
+        let channel_names: HashSet<String> = (0..n_channels)
+            .map(|i| SyntheticMdf::channel_name(0, i * 100 / n_channels))
+            .collect();
+        group.throughput(Throughput::Elements((n_channels * RECORDS) as u64));
+        group.bench_with_input(
+            BenchmarkId::new("channels", n_channels),
+            &channel_names,
+            |b, channel_names| {
+                b.iter(|| {
+                    let mut projected =
+                        Mdf::from_bytes(&bytes, "synthetic.mf4").expect("failed parsing metadata");
+                    projected
+                        .load_channels_data_from_bytes(&bytes, channel_names.clone())
+                        .expect("failed loading data");
+                    projected
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+/// loads channel groups of a single channel, as written by mdfr in DV blocks or LD lists of
+/// DZ blocks, and master only channel groups in DL lists of DT blocks
+fn column_storage(c: &mut Criterion) {
+    let file = SyntheticMdf::mdf4()
+        .with_group(&DATA_TYPES)
+        .with_records(RECORDS);
+    let mut rows = load(&file.to_bytes().expect("failed building file"));
+    let output = std::env::temp_dir().join("mdfr_column_storage_bench.mf4");
+    let output = output.to_str().expect("invalid temp path");
+    let mut written = |compression: Compression| {
+        rows.write(output, compression)
+            .expect("failed writing column file");
+        std::fs::read(output).expect("failed reading column file")
+    };
+    let mut masters = SyntheticMdf::mdf4();
+    for _ in 0..DATA_TYPES.len() {
+        masters = masters.with_group(&[]);
+    }
+    let files = [
+        ("dv", written(Compression::None)),
+        ("ld_dz", written(Compression::Zstd { level: 3 })),
+        (
+            "dl",
+            masters
+                .with_records(RECORDS)
+                .with_layout(DataLayout::Dl {
+                    records_per_block: 10_000,
+                })
+                .to_bytes()
+                .expect("failed building file"),
+        ),
+    ];
+    let mut group = c.benchmark_group("column_storage");
+    group.sample_size(20);
+    for (name, bytes) in files {
+        group.throughput(Throughput::Bytes(bytes.len() as u64));
+        group.bench_function(name, |b| b.iter(|| load(&bytes)));
+    }
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    header_parse,
+    data_blocks,
+    conversions,
+    projection,
+    column_storage
+);
+criterion_main!(benches);
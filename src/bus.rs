@@ -0,0 +1,184 @@
+//! Bus logging decoding: CAN frames stored in CAN_DataFrame channels (ASAM MDF bus logging)
+//! are decoded with a DBC file into physical signal channels.
+pub mod dbc;
+
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use anyhow::{bail, Context, Result};
+use arrow::array::{Array, AsArray, Float64Builder};
+use arrow::compute::cast;
+use arrow::datatypes::{DataType, Float64Type, UInt64Type};
+use log::info;
+
+use crate::bus::dbc::{Dbc, Message, Multiplexing, CAN_ID_MASK};
+use crate::mdfreader::Mdf;
+
+/// name of the channel containing the CAN frame identifier
+const CAN_ID: &str = "CAN_DataFrame.ID";
+/// name of the channel containing the CAN frame payload
+const CAN_DATA_BYTES: &str = "CAN_DataFrame.DataBytes";
+
+/// returns name if not existing in names, otherwise name with suffix and counter
+fn unique_name(names: &HashSet<String>, name: String, suffix: &str) -> String {
+    if !names.contains(&name) {
+        return name;
+    }
+    let mut new_name = format!("{name} {suffix}");
+    let mut counter = 1;
+    while names.contains(&new_name) {
+        new_name = format!("{name} {suffix}_{counter}");
+        counter += 1;
+    }
+    new_name
+}
+
+/// returns the payload of each frame, None for invalid frames
+fn payloads(data: &dyn Array) -> Result<Vec<Option<&[u8]>>> {
+    match data.data_type() {
+        DataType::FixedSizeBinary(_) => Ok(data.as_fixed_size_binary().iter().collect()),
+        DataType::LargeBinary => Ok(data.as_binary::<i64>().iter().collect()),
+        DataType::Binary => Ok(data.as_binary::<i32>().iter().collect()),
+        data_type => bail!("CAN payload channel has unexpected type {data_type}"),
+    }
+}
+
+/// decodes the signals of a message from the given frames,
+/// values are invalid when payload is too short or multiplexer value not matching
+fn decode_message(
+    message: &Message,
+    frames: &[usize],
+    payloads: &[Option<&[u8]>],
+) -> Vec<Float64Builder> {
+    let multiplexer = message
+        .signals
+        .iter()
+        .find(|s| s.multiplexing == Multiplexing::Multiplexer);
+    message
+        .signals
+        .iter()
+        .map(|signal| {
+            let mut values = Float64Builder::with_capacity(frames.len());
+            for frame in frames {
+                let payload = payloads[*frame].unwrap_or_default();
+                let selected = match (&signal.multiplexing, multiplexer) {
+                    (Multiplexing::Multiplexed(value), Some(multiplexer)) => {
+                        multiplexer.raw_value(payload) == Some(*value)
+                    }
+                    _ => true,
+                };
+                match signal.raw_value(payload).filter(|_| selected) {
+                    Some(raw) => values.append_value(signal.physical_value(raw)),
+                    None => values.append_null(),
+                }
+            }
+            values
+        })
+        .collect()
+}
+
+/// decodes the CAN frames of all the bus logging channel groups with the DBC database.
+/// For each message found, a master channel and its signals channels are added.
+/// Returns the names of the added channels
+pub fn decode_can_frames(mdf: &mut Mdf, dbc: &Dbc) -> Result<Vec<String>> {
+    let mut added: Vec<String> = Vec::new();
+    for (master, channel_names) in mdf.get_master_channel_names_set() {
+        let Some(master) = master else {
+            continue;
+        };
+        let Some(id_name) = channel_names.iter().find(|n| n.starts_with(CAN_ID)) else {
+            continue;
+        };
+        // duplicated names are suffixed by the channel group source
+        let suffix = id_name[CAN_ID.len()..].trim().to_string();
+        let Some(bytes_name) = channel_names
+            .iter()
+            .find(|n| n.starts_with(CAN_DATA_BYTES) && n[CAN_DATA_BYTES.len()..].trim() == suffix)
+        else {
+            continue;
+        };
+        let frame_channels = HashSet::from([master.clone(), id_name.clone(), bytes_name.clone()]);
+        if frame_channels
+            .iter()
+            .any(|name| mdf.get_channel_data(name).is_none_or(|d| d.is_empty()))
+        {
+            mdf.load_channels_data_in_memory(frame_channels)
+                .context("failed loading CAN frames")?;
+        }
+        let (Some(time), Some(ids), Some(bytes)) = (
+            mdf.get_channel_data(&master),
+            mdf.get_channel_data(id_name),
+            mdf.get_channel_data(bytes_name),
+        ) else {
+            continue;
+        };
+        let time = cast(&time.as_ref(), &DataType::Float64)
+            .context("failed casting CAN frames master into f64")?;
+        let time = time.as_primitive::<Float64Type>();
+        let ids = cast(&ids.as_ref(), &DataType::UInt64)
+            .context("failed casting CAN frames identifier into u64")?;
+        let ids = ids.as_primitive::<UInt64Type>();
+        let bytes = bytes.as_ref();
+        let payloads = payloads(&bytes)?;
+
+        let mut names = mdf.get_channel_names_set();
+        let mut channels = Vec::new();
+        for message in dbc.messages.values() {
+            let frames: Vec<usize> = ids
+                .iter()
+                .enumerate()
+                .filter(|(_, id)| id.map(|id| id & CAN_ID_MASK) == Some(message.id))
+                .map(|(index, _)| index)
+                .collect();
+            if frames.is_empty() {
+                continue;
+            }
+            let mut message_time = Float64Builder::with_capacity(frames.len());
+            frames
+                .iter()
+                .for_each(|frame| message_time.append_value(time.value(*frame)));
+            let master_name = unique_name(&names, format!("{} time", message.name), &suffix);
+            names.insert(master_name.clone());
+            let signals: Vec<(String, Float64Builder)> = message
+                .signals
+                .iter()
+                .zip(decode_message(message, &frames, &payloads))
+                .map(|(signal, values)| {
+                    let name = unique_name(&names, signal.name.clone(), &suffix);
+                    names.insert(name.clone());
+                    (name, values)
+                })
+                .collect();
+            channels.push((message, master_name, message_time, signals));
+        }
+
+        for (message, master_name, mut message_time, signals) in channels {
+            mdf.add_channel(
+                master_name.clone(),
+                Arc::new(message_time.finish()),
+                Some(master_name.clone()),
+                Some(1),
+                true,
+                Some("s".to_string()),
+                Some(format!("time of CAN message {}", message.name)),
+            )
+            .with_context(|| format!("failed adding master of message {}", message.name))?;
+            added.push(master_name.clone());
+            for (signal, (name, mut values)) in message.signals.iter().zip(signals) {
+                mdf.add_channel(
+                    name.clone(),
+                    Arc::new(values.finish()),
+                    Some(master_name.clone()),
+                    Some(1),
+                    false,
+                    Some(signal.unit.clone()),
+                    signal.comment.clone(),
+                )
+                .with_context(|| format!("failed adding signal {}", signal.name))?;
+                added.push(name);
+            }
+        }
+    }
+    info!("Decoded {} channels from CAN frames", added.len());
+    Ok(added)
+}
@@ -0,0 +1,235 @@
+//! Minimal DBC file parser: messages, signals with their scaling, units and comments
+use anyhow::{bail, Context, Result};
+use std::collections::HashMap;
+use std::fs;
+
+/// mask of the 29 bits CAN identifier
+pub const CAN_ID_MASK: u64 = 0x1FFF_FFFF;
+
+/// CAN database content
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Dbc {
+    /// messages by CAN identifier, extended flag removed
+    pub messages: HashMap<u64, Message>,
+}
+
+/// CAN message definition
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Message {
+    /// CAN identifier, extended flag removed
+    pub id: u64,
+    /// message name
+    pub name: String,
+    /// payload length in bytes
+    pub size: usize,
+    /// signals contained in the message payload
+    pub signals: Vec<Signal>,
+}
+
+/// multiplexing role of a signal
+#[derive(Debug, Default, Clone, PartialEq)]
+pub enum Multiplexing {
+    /// always present in the message
+    #[default]
+    None,
+    /// multiplexer switch
+    Multiplexer,
+    /// present only when multiplexer switch has the given value
+    Multiplexed(u64),
+}
+
+/// CAN signal definition
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Signal {
+    /// signal name
+    pub name: String,
+    /// start bit, LSB for Intel and MSB for Motorola byte order
+    pub start_bit: usize,
+    /// number of bits
+    pub bit_count: usize,
+    /// true for Intel (little endian), false for Motorola (big endian)
+    pub little_endian: bool,
+    /// true if raw value is signed
+    pub signed: bool,
+    /// physical = raw * factor + offset
+    pub factor: f64,
+    /// physical = raw * factor + offset
+    pub offset: f64,
+    /// physical unit
+    pub unit: String,
+    /// signal comment
+    pub comment: Option<String>,
+    /// multiplexing role
+    pub multiplexing: Multiplexing,
+}
+
+impl Dbc {
+    /// reads and parses a DBC file
+    pub fn from_file(file_name: &str) -> Result<Dbc> {
+        let content =
+            fs::read(file_name).with_context(|| format!("could not read DBC file {file_name}"))?;
+        // DBC files are often not utf8 encoded
+        let content = String::from_utf8_lossy(&content);
+        Dbc::parse(&content).with_context(|| format!("could not parse DBC file {file_name}"))
+    }
+
+    /// parses DBC content, unknown keywords are ignored
+    pub fn parse(content: &str) -> Result<Dbc> {
+        let mut dbc = Dbc::default();
+        let mut current: Option<u64> = None;
+        for (line_number, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if let Some(rest) = line.strip_prefix("BO_ ") {
+                let message = parse_message(rest)
+                    .with_context(|| format!("invalid message at line {}", line_number + 1))?;
+                current = Some(message.id);
+                dbc.messages.insert(message.id, message);
+            } else if let Some(rest) = line.strip_prefix("SG_ ") {
+                let signal = parse_signal(rest)
+                    .with_context(|| format!("invalid signal at line {}", line_number + 1))?;
+                match current.and_then(|id| dbc.messages.get_mut(&id)) {
+                    Some(message) => message.signals.push(signal),
+                    None => bail!("signal outside of message at line {}", line_number + 1),
+                }
+            } else if let Some(rest) = line.strip_prefix("CM_ SG_ ") {
+                if let Some((id, name, comment)) = parse_signal_comment(rest) {
+                    if let Some(signal) = dbc
+                        .messages
+                        .get_mut(&(id & CAN_ID_MASK))
+                        .and_then(|m| m.signals.iter_mut().find(|s| s.name == name))
+                    {
+                        signal.comment = Some(comment);
+                    }
+                }
+            } else if !line.starts_with("SG_") {
+                current = None;
+            }
+        }
+        Ok(dbc)
+    }
+}
+
+/// parses "id name: size transmitter"
+fn parse_message(line: &str) -> Result<Message> {
+    let mut parts = line.split_whitespace();
+    let id: u64 = parts
+        .next()
+        .context("missing message id")?
+        .parse()
+        .context("invalid message id")?;
+    let name = parts
+        .next()
+        .context("missing message name")?
+        .trim_end_matches(':')
+        .to_string();
+    let size = parts
+        .find(|p| *p != ":")
+        .context("missing message size")?
+        .parse()
+        .context("invalid message size")?;
+    Ok(Message {
+        id: id & CAN_ID_MASK,
+        name,
+        size,
+        signals: Vec::new(),
+    })
+}
+
+/// parses "name [M|mX] : start|length@order+ (factor,offset) [min|max] "unit" receivers"
+fn parse_signal(line: &str) -> Result<Signal> {
+    let (head, body) = line.split_once(':').context("missing ':' in signal")?;
+    let mut head = head.split_whitespace();
+    let name = head.next().context("missing signal name")?.to_string();
+    let multiplexing = match head.next() {
+        Some("M") => Multiplexing::Multiplexer,
+        Some(mux) if mux.starts_with('m') => {
+            let value = mux[1..].trim_end_matches('M');
+            Multiplexing::Multiplexed(value.parse().context("invalid multiplexer value")?)
+        }
+        _ => Multiplexing::None,
+    };
+    let body = body.trim();
+    let (layout, body) = body.split_once(' ').context("missing signal scaling")?;
+    let (start_bit, layout) = layout.split_once('|').context("missing signal length")?;
+    let (bit_count, layout) = layout
+        .split_once('@')
+        .context("missing signal byte order")?;
+    let mut layout = layout.chars();
+    let little_endian = layout.next() == Some('1');
+    let signed = layout.next() == Some('-');
+    let (_, body) = body.split_once('(').context("missing signal factor")?;
+    let (scaling, body) = body.split_once(')').context("missing signal factor")?;
+    let (factor, offset) = scaling.split_once(',').context("missing signal offset")?;
+    let unit = body
+        .split('"')
+        .nth(1)
+        .map(|u| u.to_string())
+        .unwrap_or_default();
+    Ok(Signal {
+        name,
+        start_bit: start_bit.trim().parse().context("invalid start bit")?,
+        bit_count: bit_count.trim().parse().context("invalid bit count")?,
+        little_endian,
+        signed,
+        factor: factor.trim().parse().context("invalid factor")?,
+        offset: offset.trim().parse().context("invalid offset")?,
+        unit,
+        comment: None,
+        multiplexing,
+    })
+}
+
+/// parses "id name "comment";"
+fn parse_signal_comment(line: &str) -> Option<(u64, String, String)> {
+    let mut parts = line.splitn(3, ' ');
+    let id: u64 = parts.next()?.parse().ok()?;
+    let name = parts.next()?.to_string();
+    let comment = parts.next()?.split('"').nth(1)?.to_string();
+    Some((id, name, comment))
+}
+
+impl Signal {
+    /// extracts the raw value from the payload, None if payload is too short
+    pub fn raw_value(&self, payload: &[u8]) -> Option<u64> {
+        if self.bit_count == 0 || self.bit_count > 64 {
+            return None;
+        }
+        let bit = |position: usize| -> Option<u64> {
+            payload
+                .get(position / 8)
+                .map(|byte| ((byte >> (position % 8)) & 1) as u64)
+        };
+        let mut value: u64 = 0;
+        if self.little_endian {
+            for i in 0..self.bit_count {
+                value |= bit(self.start_bit + i)? << i;
+            }
+        } else {
+            // Motorola start bit is the MSB, bits numbering follows a sawtooth
+            let mut position = self.start_bit;
+            for i in 0..self.bit_count {
+                value = (value << 1) | bit(position)?;
+                if i + 1 < self.bit_count {
+                    if position & 0b111 == 0 {
+                        position += 15;
+                    } else {
+                        position -= 1;
+                    }
+                }
+            }
+        }
+        Some(value)
+    }
+
+    /// converts the raw value into physical value
+    pub fn physical_value(&self, raw: u64) -> f64 {
+        let raw = if self.signed && self.bit_count < 64 && (raw >> (self.bit_count - 1)) & 1 == 1 {
+            (raw | (u64::MAX << self.bit_count)) as i64 as f64
+        } else if self.signed {
+            raw as i64 as f64
+        } else {
+            raw as f64
+        };
+        raw * self.factor + self.offset
+    }
+}
@@ -1,7 +1,13 @@
 pub mod arrow_helpers;
 pub mod channel_data;
 pub mod complex_arrow;
+pub mod computed;
+pub mod decimate;
 #[cfg(feature = "numpy")]
 pub mod dtype;
-
+pub mod filter;
+pub mod resample;
+pub mod samples;
+pub mod spill;
+pub mod stats;
 pub mod tensor_arrow;
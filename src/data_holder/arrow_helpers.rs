@@ -1,5 +1,5 @@
 //! helpers for arrow
-use arrow::array::{Array, BinaryArray, LargeBinaryArray, LargeStringArray, StringArray};
+use arrow::array::{Array, AsArray, BinaryArray, LargeBinaryArray, LargeStringArray, StringArray};
 use arrow::datatypes::DataType;
 
 /// returns the number of bits corresponding to the array's datatype
@@ -82,6 +82,9 @@ fn bit_count(array: &dyn Array, data_type: &DataType) -> u32 {
             DataType::Float64 => 64 * *size as u32,
             _ => panic!("unsupported type"),
         },
+        DataType::Dictionary(_, value_type) => {
+            bit_count(array.as_any_dictionary().values().as_ref(), value_type)
+        }
         _ => panic!("unsupported type"),
     }
 }
@@ -161,6 +164,9 @@ fn byte_count(array: &dyn Array, data_type: &DataType) -> u32 {
             DataType::Float64 => 8 * *size as u32,
             _ => panic!("unsupported type"),
         },
+        DataType::Dictionary(_, value_type) => {
+            byte_count(array.as_any_dictionary().values().as_ref(), value_type)
+        }
         _ => panic!("unsupported type"),
     }
 }
@@ -202,6 +208,7 @@ fn mdf_data_type(data_type: &DataType, endian: bool) -> u8 {
             DataType::List(_) => 16,
             DataType::FixedSizeList(_, _) => 16,
             DataType::LargeList(_) => 16,
+            DataType::Dictionary(_, value_type) => mdf_data_type(value_type, endian),
             _ => panic!("unsupported type"),
         }
     } else {
@@ -235,6 +242,7 @@ fn mdf_data_type(data_type: &DataType, endian: bool) -> u8 {
             DataType::List(_) => 15,
             DataType::FixedSizeList(_, _) => 15,
             DataType::LargeList(_) => 15,
+            DataType::Dictionary(_, value_type) => mdf_data_type(value_type, endian),
             _ => panic!("unsupported type"),
         }
     }
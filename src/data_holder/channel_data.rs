@@ -1,22 +1,24 @@
 //! this module holds the channel data enum and related implementations
 
-use anyhow::{bail, Context, Error, Result};
+use anyhow::{anyhow, bail, Context, Error, Result};
 use arrow::array::{
-    as_primitive_array, Array, ArrayBuilder, ArrayData, ArrayRef, BinaryArray,
-    BooleanBufferBuilder, FixedSizeBinaryArray, FixedSizeBinaryBuilder, FixedSizeListArray,
-    Int8Builder, LargeBinaryArray, LargeBinaryBuilder, LargeStringArray, LargeStringBuilder,
-    PrimitiveBuilder, StringArray,
+    as_primitive_array, Array, ArrayBuilder, ArrayData, ArrayRef, AsArray, BinaryArray,
+    BooleanArray, BooleanBufferBuilder, DictionaryArray, FixedSizeBinaryArray,
+    FixedSizeBinaryBuilder, FixedSizeListArray, Int8Builder, LargeBinaryArray, LargeBinaryBuilder,
+    LargeStringArray, LargeStringBuilder, PrimitiveArray, PrimitiveBuilder, StringArray,
 };
-use arrow::buffer::{MutableBuffer, NullBuffer};
+use arrow::buffer::{BooleanBuffer, MutableBuffer, NullBuffer};
 use arrow::datatypes::{
-    DataType, Float32Type, Float64Type, Int16Type, Int32Type, Int64Type, Int8Type, UInt16Type,
-    UInt32Type, UInt64Type, UInt8Type,
+    ArrowPrimitiveType, DataType, Float32Type, Float64Type, Int16Type, Int32Type, Int64Type,
+    Int8Type, UInt16Type, UInt32Type, UInt64Type, UInt8Type,
 };
+use arrow::util::bit_util;
 use arrow::util::display::{ArrayFormatter, FormatOptions};
 use itertools::Itertools;
 
 use std::fmt;
 use std::mem::size_of;
+use std::ops::{Shl, Shr};
 use std::sync::Arc;
 
 use crate::data_holder::complex_arrow::ComplexArrow;
@@ -56,6 +58,10 @@ pub enum ChannelData {
     ArrayDInt64(TensorArrow<Int64Type>),
     ArrayDUInt64(TensorArrow<UInt64Type>),
     ArrayDFloat64(TensorArrow<Float64Type>),
+    /// text labels of text table conversions, as codes and dictionary of labels
+    Dictionary(DictionaryArray<UInt32Type>),
+    /// single bit channels, values packed in bits
+    Boolean(BooleanArray),
 }
 
 impl PartialEq for ChannelData {
@@ -90,6 +96,8 @@ impl PartialEq for ChannelData {
             (Self::ArrayDInt64(l0), Self::ArrayDInt64(r0)) => l0 == r0,
             (Self::ArrayDUInt64(l0), Self::ArrayDUInt64(r0)) => l0 == r0,
             (Self::ArrayDFloat64(l0), Self::ArrayDFloat64(r0)) => l0 == r0,
+            (Self::Dictionary(l0), Self::Dictionary(r0)) => l0 == r0,
+            (Self::Boolean(l0), Self::Boolean(r0)) => l0 == r0,
             _ => false,
         }
     }
@@ -201,6 +209,8 @@ impl Clone for ChannelData {
             Self::ArrayDInt64(arg0) => Self::ArrayDInt64(arg0.clone()),
             Self::ArrayDUInt64(arg0) => Self::ArrayDUInt64(arg0.clone()),
             Self::ArrayDFloat64(arg0) => Self::ArrayDFloat64(arg0.clone()),
+            Self::Dictionary(arg0) => Self::Dictionary(arg0.clone()),
+            Self::Boolean(arg0) => Self::Boolean(arg0.clone()),
         }
     }
 }
@@ -361,11 +371,19 @@ impl ChannelData {
                 }
                 ChannelData::ArrayDFloat64(_) => {
                     Ok(ChannelData::ArrayDFloat64(TensorArrow::new_from_buffer(
-                        vec![0f64; cycle_count as usize * shape.0.iter().product::<usize>()].into(),
+                        vec![0f64; shape.0.iter().product::<usize>()].into(),
                         shape.0,
                         shape.1,
                     )))
                 }
+                ChannelData::Dictionary(_) => Ok(ChannelData::Dictionary(empty_dictionary())),
+                // single bits are read as bytes, packed once masked
+                ChannelData::Boolean(_) => {
+                    Ok(ChannelData::UInt8(PrimitiveBuilder::new_from_buffer(
+                        MutableBuffer::from_len_zeroed(cycle_count as usize),
+                        None,
+                    )))
+                }
             }
         }
     }
@@ -397,6 +415,8 @@ impl ChannelData {
             ChannelData::ArrayDInt64(data) => data.is_empty(),
             ChannelData::ArrayDUInt64(data) => data.is_empty(),
             ChannelData::ArrayDFloat64(data) => data.is_empty(),
+            ChannelData::Dictionary(data) => data.is_empty(),
+            ChannelData::Boolean(data) => data.is_empty(),
         }
     }
     /// flatten length of tensor
@@ -427,6 +447,8 @@ impl ChannelData {
             ChannelData::ArrayDInt64(data) => data.len(),
             ChannelData::ArrayDUInt64(data) => data.len(),
             ChannelData::ArrayDFloat64(data) => data.len(),
+            ChannelData::Dictionary(data) => data.len(),
+            ChannelData::Boolean(data) => data.len(),
         }
     }
     /// returns the max bit count of each values in array
@@ -477,6 +499,8 @@ impl ChannelData {
             ChannelData::ArrayDInt64(_) => 64,
             ChannelData::ArrayDUInt64(_) => 64,
             ChannelData::ArrayDFloat64(_) => 64,
+            ChannelData::Dictionary(data) => (dictionary_max_length(data) * 8) as u32,
+            ChannelData::Boolean(_) => 1,
         }
     }
     /// returns the max byte count of each values in array
@@ -523,6 +547,8 @@ impl ChannelData {
             ChannelData::ArrayDInt64(_) => 8,
             ChannelData::ArrayDUInt64(_) => 8,
             ChannelData::ArrayDFloat64(_) => 8,
+            ChannelData::Dictionary(data) => dictionary_max_length(data) as u32,
+            ChannelData::Boolean(_) => 1,
         }
     }
     /// returns mdf4 data type
@@ -555,6 +581,8 @@ impl ChannelData {
                 ChannelData::ArrayDUInt64(_) => 1,
                 ChannelData::ArrayDFloat64(_) => 5,
                 ChannelData::Utf8(_) => 7,
+                ChannelData::Dictionary(_) => 7,
+                ChannelData::Boolean(_) => 1,
             }
         } else {
             // LE
@@ -584,10 +612,13 @@ impl ChannelData {
                 ChannelData::ArrayDUInt64(_) => 0,
                 ChannelData::ArrayDFloat64(_) => 4,
                 ChannelData::Utf8(_) => 7,
+                ChannelData::Dictionary(_) => 7,
+                ChannelData::Boolean(_) => 0,
             }
         }
     }
-    /// returns the arrow DataType equivalent to the ChannelData
+    /// returns the arrow DataType equivalent to the ChannelData,
+    /// nested fixed size lists for arrays and pairs of real and imaginary parts for complex
     pub fn arrow_data_type(&self) -> DataType {
         match self {
             ChannelData::Int8(_) => DataType::Int8,
@@ -600,23 +631,25 @@ impl ChannelData {
             ChannelData::Int64(_) => DataType::Int64,
             ChannelData::UInt64(_) => DataType::UInt64,
             ChannelData::Float64(_) => DataType::Float64,
-            ChannelData::Complex32(_) => DataType::Float32,
-            ChannelData::Complex64(_) => DataType::Float64,
+            ChannelData::Complex32(a) => a.list_data_type(),
+            ChannelData::Complex64(a) => a.list_data_type(),
             ChannelData::VariableSizeByteArray(_) => DataType::LargeBinary,
             ChannelData::FixedSizeByteArray(a) => {
                 DataType::FixedSizeBinary(a.finish_cloned().value_length())
             }
-            ChannelData::ArrayDInt8(_a) => DataType::Int8,
-            ChannelData::ArrayDUInt8(_a) => DataType::UInt8,
-            ChannelData::ArrayDInt16(_a) => DataType::Int16,
-            ChannelData::ArrayDUInt16(_a) => DataType::UInt16,
-            ChannelData::ArrayDInt32(_a) => DataType::Int32,
-            ChannelData::ArrayDUInt32(_a) => DataType::UInt32,
-            ChannelData::ArrayDFloat32(_a) => DataType::Float32,
-            ChannelData::ArrayDInt64(_a) => DataType::Int64,
-            ChannelData::ArrayDUInt64(_a) => DataType::UInt64,
-            ChannelData::ArrayDFloat64(_a) => DataType::Float64,
+            ChannelData::ArrayDInt8(a) => a.nested_data_type(),
+            ChannelData::ArrayDUInt8(a) => a.nested_data_type(),
+            ChannelData::ArrayDInt16(a) => a.nested_data_type(),
+            ChannelData::ArrayDUInt16(a) => a.nested_data_type(),
+            ChannelData::ArrayDInt32(a) => a.nested_data_type(),
+            ChannelData::ArrayDUInt32(a) => a.nested_data_type(),
+            ChannelData::ArrayDFloat32(a) => a.nested_data_type(),
+            ChannelData::ArrayDInt64(a) => a.nested_data_type(),
+            ChannelData::ArrayDUInt64(a) => a.nested_data_type(),
+            ChannelData::ArrayDFloat64(a) => a.nested_data_type(),
             ChannelData::Utf8(_) => DataType::LargeUtf8,
+            ChannelData::Dictionary(a) => a.data_type().clone(),
+            ChannelData::Boolean(_) => DataType::Boolean,
         }
     }
     /// returns raw bytes vectors from ndarray
@@ -749,6 +782,25 @@ impl ChannelData {
                 .iter()
                 .flat_map(|x| x.to_ne_bytes())
                 .collect()),
+            ChannelData::Dictionary(a) => {
+                let nbytes = self.byte_count() as usize;
+                let labels = a
+                    .downcast_dict::<StringArray>()
+                    .context("dictionary values are not strings")?;
+                Ok(labels
+                    .into_iter()
+                    .flat_map(|x| {
+                        let str_bytes = x.unwrap_or("").as_bytes();
+                        let n_str_bytes = str_bytes.len();
+                        if nbytes > n_str_bytes {
+                            [str_bytes, &vec![0u8; nbytes - n_str_bytes]].concat()
+                        } else {
+                            str_bytes.to_vec()
+                        }
+                    })
+                    .collect())
+            }
+            ChannelData::Boolean(a) => Ok(a.values().iter().map(u8::from).collect()),
         }
     }
     /// returns the number of dimensions of the channel
@@ -779,6 +831,39 @@ impl ChannelData {
             ChannelData::ArrayDUInt64(a) => a.ndim(),
             ChannelData::ArrayDFloat64(a) => a.ndim(),
             ChannelData::Utf8(_) => 1,
+            ChannelData::Dictionary(_) => 1,
+            ChannelData::Boolean(_) => 1,
+        }
+    }
+    /// keeps the bit_count bits of the integer values starting at bit_offset, moved to the
+    /// least significant bits. Signed values are sign extended
+    pub fn apply_bit_mask_offset(&mut self, bit_offset: u32, bit_count: u32) {
+        match self {
+            ChannelData::Int8(a) => mask_bits(a.values_slice_mut(), bit_offset, bit_count),
+            ChannelData::UInt8(a) => mask_bits(a.values_slice_mut(), bit_offset, bit_count),
+            ChannelData::Int16(a) => mask_bits(a.values_slice_mut(), bit_offset, bit_count),
+            ChannelData::UInt16(a) => mask_bits(a.values_slice_mut(), bit_offset, bit_count),
+            ChannelData::Int32(a) => mask_bits(a.values_slice_mut(), bit_offset, bit_count),
+            ChannelData::UInt32(a) => mask_bits(a.values_slice_mut(), bit_offset, bit_count),
+            ChannelData::Int64(a) => mask_bits(a.values_slice_mut(), bit_offset, bit_count),
+            ChannelData::UInt64(a) => mask_bits(a.values_slice_mut(), bit_offset, bit_count),
+            ChannelData::ArrayDInt8(a) => mask_bits(a.values_slice_mut(), bit_offset, bit_count),
+            ChannelData::ArrayDUInt8(a) => mask_bits(a.values_slice_mut(), bit_offset, bit_count),
+            ChannelData::ArrayDInt16(a) => mask_bits(a.values_slice_mut(), bit_offset, bit_count),
+            ChannelData::ArrayDUInt16(a) => mask_bits(a.values_slice_mut(), bit_offset, bit_count),
+            ChannelData::ArrayDInt32(a) => mask_bits(a.values_slice_mut(), bit_offset, bit_count),
+            ChannelData::ArrayDUInt32(a) => mask_bits(a.values_slice_mut(), bit_offset, bit_count),
+            ChannelData::ArrayDInt64(a) => mask_bits(a.values_slice_mut(), bit_offset, bit_count),
+            ChannelData::ArrayDUInt64(a) => mask_bits(a.values_slice_mut(), bit_offset, bit_count),
+            _ => (),
+        }
+    }
+    /// packs the bytes of a single bit channel into booleans
+    pub(crate) fn bits_into_boolean(&mut self) {
+        if let ChannelData::UInt8(a) = self {
+            let bytes = a.finish();
+            let values: BooleanBuffer = bytes.values().iter().map(|v| *v != 0).collect();
+            *self = ChannelData::Boolean(BooleanArray::new(values, bytes.nulls().cloned()));
         }
     }
     /// returns the shape of channel
@@ -809,6 +894,8 @@ impl ChannelData {
             ChannelData::ArrayDInt64(a) => (a.shape().clone(), a.order().clone()),
             ChannelData::ArrayDUInt64(a) => (a.shape().clone(), a.order().clone()),
             ChannelData::ArrayDFloat64(a) => (a.shape().clone(), a.order().clone()),
+            ChannelData::Dictionary(a) => (vec![a.len(); 1], Order::RowMajor),
+            ChannelData::Boolean(a) => (vec![a.len(); 1], Order::RowMajor),
         }
     }
     /// returns optional tuple of minimum and maximum values contained in the channel
@@ -951,6 +1038,54 @@ impl ChannelData {
                 (min, max)
             }
             ChannelData::Utf8(_) => (None, None),
+            ChannelData::Dictionary(_) => (None, None),
+            ChannelData::Boolean(a) => {
+                if a.is_empty() {
+                    (None, None)
+                } else {
+                    let min = if a.false_count() > 0 { 0.0 } else { 1.0 };
+                    let max = if a.true_count() > 0 { 1.0 } else { 0.0 };
+                    (Some(min), Some(max))
+                }
+            }
+        }
+    }
+    /// returns the values of a f64 channel, None for other data types
+    pub fn as_f64_slice(&self) -> Option<&[f64]> {
+        match self {
+            ChannelData::Float64(a) => Some(a.values_slice()),
+            _ => None,
+        }
+    }
+    /// returns the values of a i64 channel, None for other data types
+    pub fn as_i64_slice(&self) -> Option<&[i64]> {
+        match self {
+            ChannelData::Int64(a) => Some(a.values_slice()),
+            _ => None,
+        }
+    }
+    /// returns an iterator over the texts of a string or text table converted channel,
+    /// None for null values. None for other data types
+    pub fn str_iter(&self) -> Option<Box<dyn Iterator<Item = Option<&str>> + '_>> {
+        match self {
+            ChannelData::Utf8(a) => {
+                let values = a.values_slice();
+                let validity = a.validity_slice();
+                Some(Box::new(a.offsets_slice().windows(2).enumerate().map(
+                    move |(i, offsets)| {
+                        if validity.is_some_and(|validity| !bit_util::get_bit(validity, i)) {
+                            None
+                        } else {
+                            std::str::from_utf8(&values[offsets[0] as usize..offsets[1] as usize])
+                                .ok()
+                        }
+                    },
+                )))
+            }
+            ChannelData::Dictionary(a) => a
+                .downcast_dict::<StringArray>()
+                .map(|labels| Box::new(labels.into_iter()) as Box<dyn Iterator<Item = _>>),
+            _ => None,
         }
     }
     /// convert channel arrow data into dyn Array
@@ -966,21 +1101,23 @@ impl ChannelData {
             ChannelData::Int64(a) => Arc::new(a.finish_cloned()) as ArrayRef,
             ChannelData::UInt64(a) => Arc::new(a.finish_cloned()) as ArrayRef,
             ChannelData::Float64(a) => Arc::new(a.finish_cloned()) as ArrayRef,
-            ChannelData::Complex32(a) => Arc::new(a.finish_cloned()) as ArrayRef,
-            ChannelData::Complex64(a) => Arc::new(a.finish_cloned()) as ArrayRef,
+            ChannelData::Complex32(a) => a.finish_cloned_list(),
+            ChannelData::Complex64(a) => a.finish_cloned_list(),
             ChannelData::Utf8(a) => Arc::new(a.finish_cloned()) as ArrayRef,
             ChannelData::VariableSizeByteArray(a) => Arc::new(a.finish_cloned()) as ArrayRef,
             ChannelData::FixedSizeByteArray(a) => Arc::new(a.finish_cloned()) as ArrayRef,
-            ChannelData::ArrayDInt8(a) => Arc::new(a.finish_cloned()) as ArrayRef,
-            ChannelData::ArrayDUInt8(a) => Arc::new(a.finish_cloned()) as ArrayRef,
-            ChannelData::ArrayDInt16(a) => Arc::new(a.finish_cloned()) as ArrayRef,
-            ChannelData::ArrayDUInt16(a) => Arc::new(a.finish_cloned()) as ArrayRef,
-            ChannelData::ArrayDInt32(a) => Arc::new(a.finish_cloned()) as ArrayRef,
-            ChannelData::ArrayDUInt32(a) => Arc::new(a.finish_cloned()) as ArrayRef,
-            ChannelData::ArrayDFloat32(a) => Arc::new(a.finish_cloned()) as ArrayRef,
-            ChannelData::ArrayDInt64(a) => Arc::new(a.finish_cloned()) as ArrayRef,
-            ChannelData::ArrayDUInt64(a) => Arc::new(a.finish_cloned()) as ArrayRef,
-            ChannelData::ArrayDFloat64(a) => Arc::new(a.finish_cloned()) as ArrayRef,
+            ChannelData::ArrayDInt8(a) => a.finish_cloned_nested(),
+            ChannelData::ArrayDUInt8(a) => a.finish_cloned_nested(),
+            ChannelData::ArrayDInt16(a) => a.finish_cloned_nested(),
+            ChannelData::ArrayDUInt16(a) => a.finish_cloned_nested(),
+            ChannelData::ArrayDInt32(a) => a.finish_cloned_nested(),
+            ChannelData::ArrayDUInt32(a) => a.finish_cloned_nested(),
+            ChannelData::ArrayDFloat32(a) => a.finish_cloned_nested(),
+            ChannelData::ArrayDInt64(a) => a.finish_cloned_nested(),
+            ChannelData::ArrayDUInt64(a) => a.finish_cloned_nested(),
+            ChannelData::ArrayDFloat64(a) => a.finish_cloned_nested(),
+            ChannelData::Dictionary(a) => Arc::new(a.clone()) as ArrayRef,
+            ChannelData::Boolean(a) => Arc::new(a.clone()) as ArrayRef,
         }
     }
     /// convert channel arrow data into dyn Array
@@ -996,21 +1133,27 @@ impl ChannelData {
             ChannelData::Int64(a) => Arc::new(a.finish()) as ArrayRef,
             ChannelData::UInt64(a) => Arc::new(a.finish()) as ArrayRef,
             ChannelData::Float64(a) => Arc::new(a.finish()) as ArrayRef,
-            ChannelData::Complex32(a) => Arc::new(a.finish()) as ArrayRef,
-            ChannelData::Complex64(a) => Arc::new(a.finish()) as ArrayRef,
+            ChannelData::Complex32(a) => a.finish_list(),
+            ChannelData::Complex64(a) => a.finish_list(),
             ChannelData::Utf8(a) => Arc::new(a.finish()) as ArrayRef,
             ChannelData::VariableSizeByteArray(a) => Arc::new(a.finish()) as ArrayRef,
             ChannelData::FixedSizeByteArray(a) => Arc::new(a.finish()) as ArrayRef,
-            ChannelData::ArrayDInt8(a) => Arc::new(a.finish()) as ArrayRef,
-            ChannelData::ArrayDUInt8(a) => Arc::new(a.finish()) as ArrayRef,
-            ChannelData::ArrayDInt16(a) => Arc::new(a.finish()) as ArrayRef,
-            ChannelData::ArrayDUInt16(a) => Arc::new(a.finish()) as ArrayRef,
-            ChannelData::ArrayDInt32(a) => Arc::new(a.finish()) as ArrayRef,
-            ChannelData::ArrayDUInt32(a) => Arc::new(a.finish()) as ArrayRef,
-            ChannelData::ArrayDFloat32(a) => Arc::new(a.finish()) as ArrayRef,
-            ChannelData::ArrayDInt64(a) => Arc::new(a.finish()) as ArrayRef,
-            ChannelData::ArrayDUInt64(a) => Arc::new(a.finish()) as ArrayRef,
-            ChannelData::ArrayDFloat64(a) => Arc::new(a.finish()) as ArrayRef,
+            ChannelData::ArrayDInt8(a) => a.finish_nested(),
+            ChannelData::ArrayDUInt8(a) => a.finish_nested(),
+            ChannelData::ArrayDInt16(a) => a.finish_nested(),
+            ChannelData::ArrayDUInt16(a) => a.finish_nested(),
+            ChannelData::ArrayDInt32(a) => a.finish_nested(),
+            ChannelData::ArrayDUInt32(a) => a.finish_nested(),
+            ChannelData::ArrayDFloat32(a) => a.finish_nested(),
+            ChannelData::ArrayDInt64(a) => a.finish_nested(),
+            ChannelData::ArrayDUInt64(a) => a.finish_nested(),
+            ChannelData::ArrayDFloat64(a) => a.finish_nested(),
+            ChannelData::Dictionary(a) => {
+                Arc::new(std::mem::replace(a, empty_dictionary())) as ArrayRef
+            }
+            ChannelData::Boolean(a) => {
+                Arc::new(std::mem::replace(a, BooleanArray::from(Vec::<bool>::new()))) as ArrayRef
+            }
         }
     }
     /// Convert ChannelData into ArrayData
@@ -1026,56 +1169,38 @@ impl ChannelData {
             ChannelData::Int64(a) => a.finish_cloned().to_data(),
             ChannelData::UInt64(a) => a.finish_cloned().to_data(),
             ChannelData::Float64(a) => a.finish_cloned().to_data(),
-            ChannelData::Complex32(a) => a.finish_cloned().to_data(),
-            ChannelData::Complex64(a) => a.finish_cloned().to_data(),
+            ChannelData::Complex32(a) => a.finish_cloned_list().to_data(),
+            ChannelData::Complex64(a) => a.finish_cloned_list().to_data(),
             ChannelData::Utf8(a) => a.finish_cloned().to_data(),
             ChannelData::VariableSizeByteArray(a) => a.finish_cloned().to_data(),
             ChannelData::FixedSizeByteArray(a) => a.finish_cloned().to_data(),
-            ChannelData::ArrayDInt8(a) => a.finish_cloned().to_data(),
-            ChannelData::ArrayDUInt8(a) => a.finish_cloned().to_data(),
-            ChannelData::ArrayDInt16(a) => a.finish_cloned().to_data(),
-            ChannelData::ArrayDUInt16(a) => a.finish_cloned().to_data(),
-            ChannelData::ArrayDInt32(a) => a.finish_cloned().to_data(),
-            ChannelData::ArrayDUInt32(a) => a.finish_cloned().to_data(),
-            ChannelData::ArrayDFloat32(a) => a.finish_cloned().to_data(),
-            ChannelData::ArrayDInt64(a) => a.finish_cloned().to_data(),
-            ChannelData::ArrayDUInt64(a) => a.finish_cloned().to_data(),
-            ChannelData::ArrayDFloat64(a) => a.finish_cloned().to_data(),
+            ChannelData::ArrayDInt8(a) => a.finish_cloned_nested().to_data(),
+            ChannelData::ArrayDUInt8(a) => a.finish_cloned_nested().to_data(),
+            ChannelData::ArrayDInt16(a) => a.finish_cloned_nested().to_data(),
+            ChannelData::ArrayDUInt16(a) => a.finish_cloned_nested().to_data(),
+            ChannelData::ArrayDInt32(a) => a.finish_cloned_nested().to_data(),
+            ChannelData::ArrayDUInt32(a) => a.finish_cloned_nested().to_data(),
+            ChannelData::ArrayDFloat32(a) => a.finish_cloned_nested().to_data(),
+            ChannelData::ArrayDInt64(a) => a.finish_cloned_nested().to_data(),
+            ChannelData::ArrayDUInt64(a) => a.finish_cloned_nested().to_data(),
+            ChannelData::ArrayDFloat64(a) => a.finish_cloned_nested().to_data(),
+            ChannelData::Dictionary(a) => a.to_data(),
+            ChannelData::Boolean(a) => a.to_data(),
         }
     }
     /// Change the validity mask of the channel
     pub fn set_validity(&mut self, mask: &mut BooleanBufferBuilder) -> Result<(), Error> {
         match self {
-            ChannelData::Int8(a) => {
-                let _ = a.validity_slice_mut().insert(mask.as_slice_mut());
-            }
-            ChannelData::UInt8(a) => {
-                let _ = a.validity_slice_mut().insert(mask.as_slice_mut());
-            }
-            ChannelData::Int16(a) => {
-                let _ = a.validity_slice_mut().insert(mask.as_slice_mut());
-            }
-            ChannelData::UInt16(a) => {
-                let _ = a.validity_slice_mut().insert(mask.as_slice_mut());
-            }
-            ChannelData::Int32(a) => {
-                let _ = a.validity_slice_mut().insert(mask.as_slice_mut());
-            }
-            ChannelData::UInt32(a) => {
-                let _ = a.validity_slice_mut().insert(mask.as_slice_mut());
-            }
-            ChannelData::Float32(a) => {
-                let _ = a.validity_slice_mut().insert(mask.as_slice_mut());
-            }
-            ChannelData::Int64(a) => {
-                let _ = a.validity_slice_mut().insert(mask.as_slice_mut());
-            }
-            ChannelData::UInt64(a) => {
-                let _ = a.validity_slice_mut().insert(mask.as_slice_mut());
-            }
-            ChannelData::Float64(a) => {
-                let _ = a.validity_slice_mut().insert(mask.as_slice_mut());
-            }
+            ChannelData::Int8(a) => primitive_set_validity(a, mask)?,
+            ChannelData::UInt8(a) => primitive_set_validity(a, mask)?,
+            ChannelData::Int16(a) => primitive_set_validity(a, mask)?,
+            ChannelData::UInt16(a) => primitive_set_validity(a, mask)?,
+            ChannelData::Int32(a) => primitive_set_validity(a, mask)?,
+            ChannelData::UInt32(a) => primitive_set_validity(a, mask)?,
+            ChannelData::Float32(a) => primitive_set_validity(a, mask)?,
+            ChannelData::Int64(a) => primitive_set_validity(a, mask)?,
+            ChannelData::UInt64(a) => primitive_set_validity(a, mask)?,
+            ChannelData::Float64(a) => primitive_set_validity(a, mask)?,
             ChannelData::Complex32(a) => {
                 a.set_validity(mask);
             }
@@ -1083,10 +1208,46 @@ impl ChannelData {
                 a.set_validity(mask);
             }
             ChannelData::Utf8(a) => {
-                let _ = a.validity_slice_mut().insert(mask.as_slice_mut());
+                let array = a.finish();
+                let validity = mask.finish();
+                if validity.len() != array.len() {
+                    bail!(
+                        "validity mask length {} different from array length {}",
+                        validity.len(),
+                        array.len()
+                    );
+                }
+                let mut new_array =
+                    LargeStringBuilder::with_capacity(array.len(), array.value_data().len());
+                array
+                    .iter()
+                    .zip(validity.iter())
+                    .for_each(|(value, valid)| match value {
+                        Some(value) if valid => new_array.append_value(value),
+                        _ => new_array.append_null(),
+                    });
+                *a = new_array;
             }
             ChannelData::VariableSizeByteArray(a) => {
-                let _ = a.validity_slice_mut().insert(mask.as_slice_mut());
+                let array = a.finish();
+                let validity = mask.finish();
+                if validity.len() != array.len() {
+                    bail!(
+                        "validity mask length {} different from array length {}",
+                        validity.len(),
+                        array.len()
+                    );
+                }
+                let mut new_array =
+                    LargeBinaryBuilder::with_capacity(array.len(), array.value_data().len());
+                array
+                    .iter()
+                    .zip(validity.iter())
+                    .for_each(|(value, valid)| match value {
+                        Some(value) if valid => new_array.append_value(value),
+                        _ => new_array.append_null(),
+                    });
+                *a = new_array;
             }
             ChannelData::FixedSizeByteArray(a) => {
                 let array = a.finish();
@@ -1096,15 +1257,16 @@ impl ChannelData {
                     .values()
                     .chunks(array.value_length() as usize)
                     .zip(mask.finish().iter())
-                    .for_each(|(value, mask)| {
+                    .try_for_each(|(value, mask)| -> Result<(), Error> {
                         if mask {
                             new_array
                                 .append_value(value)
-                                .expect("failed appending new fixed binary value");
+                                .context("failed appending new fixed binary value")?;
                         } else {
                             new_array.append_null();
                         }
-                    });
+                        Ok(())
+                    })?;
                 *a = new_array;
             }
             ChannelData::ArrayDInt8(a) => {
@@ -1137,6 +1299,18 @@ impl ChannelData {
             ChannelData::ArrayDFloat64(a) => {
                 a.set_validity(mask);
             }
+            ChannelData::Dictionary(a) => {
+                let keys = PrimitiveArray::<UInt32Type>::try_new(
+                    a.keys().values().clone(),
+                    Some(NullBuffer::new(mask.finish())),
+                )
+                .context("failed applying validity mask to dictionary keys")?;
+                *a = DictionaryArray::try_new(keys, a.values().clone())
+                    .context("failed rebuilding dictionary with validity")?;
+            }
+            ChannelData::Boolean(a) => {
+                *a = BooleanArray::new(a.values().clone(), Some(NullBuffer::new(mask.finish())));
+            }
         }
         Ok(())
     }
@@ -1153,21 +1327,23 @@ impl ChannelData {
             ChannelData::Int64(a) => a.finish_cloned().nulls().cloned(),
             ChannelData::UInt64(a) => a.finish_cloned().nulls().cloned(),
             ChannelData::Float64(a) => a.finish_cloned().nulls().cloned(),
-            ChannelData::Complex32(a) => a.finish_cloned().nulls().cloned(),
-            ChannelData::Complex64(a) => a.finish_cloned().nulls().cloned(),
+            ChannelData::Complex32(a) => a.nulls().cloned().map(NullBuffer::new),
+            ChannelData::Complex64(a) => a.nulls().cloned().map(NullBuffer::new),
             ChannelData::Utf8(a) => a.finish_cloned().nulls().cloned(),
             ChannelData::VariableSizeByteArray(a) => a.finish_cloned().nulls().cloned(),
             ChannelData::FixedSizeByteArray(a) => a.finish_cloned().nulls().cloned(),
-            ChannelData::ArrayDInt8(a) => a.finish_cloned().nulls().cloned(),
-            ChannelData::ArrayDUInt8(a) => a.finish_cloned().nulls().cloned(),
-            ChannelData::ArrayDInt16(a) => a.finish_cloned().nulls().cloned(),
-            ChannelData::ArrayDUInt16(a) => a.finish_cloned().nulls().cloned(),
-            ChannelData::ArrayDInt32(a) => a.finish_cloned().nulls().cloned(),
-            ChannelData::ArrayDUInt32(a) => a.finish_cloned().nulls().cloned(),
-            ChannelData::ArrayDFloat32(a) => a.finish_cloned().nulls().cloned(),
-            ChannelData::ArrayDInt64(a) => a.finish_cloned().nulls().cloned(),
-            ChannelData::ArrayDUInt64(a) => a.finish_cloned().nulls().cloned(),
-            ChannelData::ArrayDFloat64(a) => a.finish_cloned().nulls().cloned(),
+            ChannelData::ArrayDInt8(a) => a.nulls().cloned().map(NullBuffer::new),
+            ChannelData::ArrayDUInt8(a) => a.nulls().cloned().map(NullBuffer::new),
+            ChannelData::ArrayDInt16(a) => a.nulls().cloned().map(NullBuffer::new),
+            ChannelData::ArrayDUInt16(a) => a.nulls().cloned().map(NullBuffer::new),
+            ChannelData::ArrayDInt32(a) => a.nulls().cloned().map(NullBuffer::new),
+            ChannelData::ArrayDUInt32(a) => a.nulls().cloned().map(NullBuffer::new),
+            ChannelData::ArrayDFloat32(a) => a.nulls().cloned().map(NullBuffer::new),
+            ChannelData::ArrayDInt64(a) => a.nulls().cloned().map(NullBuffer::new),
+            ChannelData::ArrayDUInt64(a) => a.nulls().cloned().map(NullBuffer::new),
+            ChannelData::ArrayDFloat64(a) => a.nulls().cloned().map(NullBuffer::new),
+            ChannelData::Dictionary(a) => a.nulls().cloned(),
+            ChannelData::Boolean(a) => a.nulls().cloned(),
         }
     }
     /// Returns the channel's validity mask as a slice
@@ -1198,6 +1374,8 @@ impl ChannelData {
             ChannelData::ArrayDInt64(a) => a.validity_slice(),
             ChannelData::ArrayDUInt64(a) => a.validity_slice(),
             ChannelData::ArrayDFloat64(a) => a.validity_slice(),
+            ChannelData::Dictionary(a) => a.nulls().map(|nulls| nulls.validity()),
+            ChannelData::Boolean(a) => a.nulls().map(|nulls| nulls.validity()),
         }
     }
     /// returns True if a validity mask is existing for the channel
@@ -1228,6 +1406,8 @@ impl ChannelData {
             ChannelData::ArrayDInt64(a) => a.nulls().is_some(),
             ChannelData::ArrayDUInt64(a) => a.nulls().is_some(),
             ChannelData::ArrayDFloat64(a) => a.nulls().is_some(),
+            ChannelData::Dictionary(a) => a.nulls().is_some(),
+            ChannelData::Boolean(a) => a.nulls().is_some(),
         }
     }
     /// converts the ChannelData into a ArrayRef
@@ -1243,21 +1423,23 @@ impl ChannelData {
             ChannelData::Int64(a) => Arc::new(a.finish_cloned()) as ArrayRef,
             ChannelData::UInt64(a) => Arc::new(a.finish_cloned()) as ArrayRef,
             ChannelData::Float64(a) => Arc::new(a.finish_cloned()) as ArrayRef,
-            ChannelData::Complex32(a) => Arc::new(a.finish_cloned()) as ArrayRef,
-            ChannelData::Complex64(a) => Arc::new(a.finish_cloned()) as ArrayRef,
+            ChannelData::Complex32(a) => a.finish_cloned_list(),
+            ChannelData::Complex64(a) => a.finish_cloned_list(),
             ChannelData::Utf8(a) => Arc::new(a.finish_cloned()) as ArrayRef,
             ChannelData::VariableSizeByteArray(a) => Arc::new(a.finish_cloned()) as ArrayRef,
             ChannelData::FixedSizeByteArray(a) => Arc::new(a.finish_cloned()) as ArrayRef,
-            ChannelData::ArrayDInt8(a) => Arc::new(a.finish_cloned()) as ArrayRef,
-            ChannelData::ArrayDUInt8(a) => Arc::new(a.finish_cloned()) as ArrayRef,
-            ChannelData::ArrayDInt16(a) => Arc::new(a.finish_cloned()) as ArrayRef,
-            ChannelData::ArrayDUInt16(a) => Arc::new(a.finish_cloned()) as ArrayRef,
-            ChannelData::ArrayDInt32(a) => Arc::new(a.finish_cloned()) as ArrayRef,
-            ChannelData::ArrayDUInt32(a) => Arc::new(a.finish_cloned()) as ArrayRef,
-            ChannelData::ArrayDFloat32(a) => Arc::new(a.finish_cloned()) as ArrayRef,
-            ChannelData::ArrayDInt64(a) => Arc::new(a.finish_cloned()) as ArrayRef,
-            ChannelData::ArrayDUInt64(a) => Arc::new(a.finish_cloned()) as ArrayRef,
-            ChannelData::ArrayDFloat64(a) => Arc::new(a.finish_cloned()) as ArrayRef,
+            ChannelData::ArrayDInt8(a) => a.finish_cloned_nested(),
+            ChannelData::ArrayDUInt8(a) => a.finish_cloned_nested(),
+            ChannelData::ArrayDInt16(a) => a.finish_cloned_nested(),
+            ChannelData::ArrayDUInt16(a) => a.finish_cloned_nested(),
+            ChannelData::ArrayDInt32(a) => a.finish_cloned_nested(),
+            ChannelData::ArrayDUInt32(a) => a.finish_cloned_nested(),
+            ChannelData::ArrayDFloat32(a) => a.finish_cloned_nested(),
+            ChannelData::ArrayDInt64(a) => a.finish_cloned_nested(),
+            ChannelData::ArrayDUInt64(a) => a.finish_cloned_nested(),
+            ChannelData::ArrayDFloat64(a) => a.finish_cloned_nested(),
+            ChannelData::Dictionary(a) => Arc::new(a.clone()) as ArrayRef,
+            ChannelData::Boolean(a) => Arc::new(a.clone()) as ArrayRef,
         }
     }
     #[cfg(feature = "numpy")]
@@ -1365,6 +1547,14 @@ impl ChannelData {
                 shape: a.shape().to_vec(),
                 kind: "f8".to_string(),
             },
+            ChannelData::Dictionary(a) => NumpyDType {
+                shape: vec![a.len()],
+                kind: format!("U{}", self.byte_count()),
+            },
+            ChannelData::Boolean(a) => NumpyDType {
+                shape: vec![a.len()],
+                kind: "?".to_string(),
+            },
         }
     }
 }
@@ -1375,6 +1565,24 @@ impl Default for ChannelData {
     }
 }
 
+/// shifts the bits above the field out of the values, then the field down to the least
+/// significant bit, arithmetic shift extending the sign of signed values
+fn mask_bits<T>(values: &mut [T], bit_offset: u32, bit_count: u32)
+where
+    T: Copy + Shl<u32, Output = T> + Shr<u32, Output = T>,
+{
+    let width = 8 * size_of::<T>() as u32;
+    let Some(left_shift) = width.checked_sub(bit_offset + bit_count) else {
+        return;
+    };
+    let right_shift = left_shift + bit_offset;
+    if right_shift > 0 && right_shift < width {
+        values
+            .iter_mut()
+            .for_each(|x| *x = (*x << left_shift) >> right_shift);
+    }
+}
+
 /// Initialises a channel array type depending of cn_type, cn_data_type and if array
 pub fn data_type_init(
     cn_type: u8,
@@ -1433,8 +1641,8 @@ pub fn data_type_init(
                 }
                 _ => {
                     // bytearray
-                    if cn_type == 1 {
-                        // VLSD
+                    if cn_type == 1 || cn_type == 5 {
+                        // VLSD or MLSD
                         Ok(ChannelData::VariableSizeByteArray(LargeBinaryBuilder::new()))
                     } else {
                         Ok(ChannelData::FixedSizeByteArray(
@@ -1523,12 +1731,6 @@ pub fn try_from(value: &dyn Array) -> Result<ChannelData, Error> {
             data.iter().for_each(|v| new_data.append_option(v));
             Ok(ChannelData::UInt8(new_data))
         }
-        DataType::Boolean => {
-            let data = as_primitive_array::<UInt8Type>(value);
-            let mut new_data = PrimitiveBuilder::with_capacity(data.len());
-            data.iter().for_each(|v| new_data.append_option(v));
-            Ok(ChannelData::UInt8(new_data))
-        }
         DataType::Int8 => {
             let data = as_primitive_array::<Int8Type>(value);
             let mut new_data = PrimitiveBuilder::with_capacity(data.len());
@@ -1668,6 +1870,17 @@ pub fn try_from(value: &dyn Array) -> Result<ChannelData, Error> {
                     .expect("could not convert large utf8 into mutable array"),
             ))
         }
+        DataType::Dictionary(_, _) => {
+            let array = value
+                .as_any()
+                .downcast_ref::<DictionaryArray<UInt32Type>>()
+                .context("dictionary shall have u32 keys")?;
+            if array.values().data_type() != &DataType::Utf8 {
+                bail!("dictionary shall have Utf8 values")
+            }
+            Ok(ChannelData::Dictionary(array.clone()))
+        }
+        DataType::Boolean => Ok(ChannelData::Boolean(value.as_boolean().clone())),
         DataType::FixedSizeList(_, size) => {
             // used for complex number, size of 2
             let array = value
@@ -1675,18 +1888,13 @@ pub fn try_from(value: &dyn Array) -> Result<ChannelData, Error> {
                 .downcast_ref::<FixedSizeListArray>()
                 .context("could not downcast to fixed size list array, used for complex")?;
             if *size == 2 {
+                // real and imaginary pairs interleaved back
                 match array.value_type() {
                     DataType::Float32 => {
-                        let data = as_primitive_array::<Float32Type>(value);
-                        let mut new_data = PrimitiveBuilder::with_capacity(data.len());
-                        data.iter().for_each(|v| new_data.append_option(v));
-                        Ok(ChannelData::Float32(new_data))
+                        Ok(ChannelData::Complex32(ComplexArrow::new_from_list(array)))
                     }
                     DataType::Float64 => {
-                        let data = as_primitive_array::<Float64Type>(value);
-                        let mut new_data = PrimitiveBuilder::with_capacity(data.len());
-                        data.iter().for_each(|v| new_data.append_option(v));
-                        Ok(ChannelData::Float64(new_data))
+                        Ok(ChannelData::Complex64(ComplexArrow::new_from_list(array)))
                     }
                     _ => bail!("FixedSizeList shall be either f23 or f64 to be used for complex"),
                 }
@@ -1698,6 +1906,39 @@ pub fn try_from(value: &dyn Array) -> Result<ChannelData, Error> {
     }
 }
 
+/// returns an empty dictionary of text labels
+fn empty_dictionary() -> DictionaryArray<UInt32Type> {
+    DictionaryArray::new(
+        PrimitiveArray::<UInt32Type>::from(Vec::<u32>::new()),
+        Arc::new(StringArray::from(Vec::<&str>::new())),
+    )
+}
+
+/// returns the largest byte length of the dictionary labels
+fn dictionary_max_length(array: &DictionaryArray<UInt32Type>) -> usize {
+    array
+        .values()
+        .as_any()
+        .downcast_ref::<StringArray>()
+        .and_then(|labels| labels.iter().map(|l| l.map_or(0, |l| l.len())).max())
+        .unwrap_or(0)
+}
+
+/// replaces the validity of a primitive builder by the given mask, bit set is valid
+fn primitive_set_validity<T: ArrowPrimitiveType>(
+    builder: &mut PrimitiveBuilder<T>,
+    mask: &mut BooleanBufferBuilder,
+) -> Result<(), Error> {
+    let (data_type, values, _nulls) = builder.finish().into_parts();
+    let array = PrimitiveArray::<T>::try_new(values, Some(NullBuffer::new(mask.finish())))
+        .context("failed applying validity mask to array")?
+        .with_data_type(data_type);
+    *builder = array
+        .into_builder()
+        .map_err(|_| anyhow!("failed converting array with validity into builder"))?;
+    Ok(())
+}
+
 impl fmt::Display for ChannelData {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let format_option = FormatOptions::new();
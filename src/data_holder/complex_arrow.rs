@@ -2,12 +2,16 @@
 #[cfg(feature = "ndarray")]
 use anyhow::{Context, Error, Result};
 use arrow::{
-    array::{ArrayBuilder, BooleanBufferBuilder, PrimitiveArray, PrimitiveBuilder},
-    buffer::{BooleanBuffer, MutableBuffer},
-    datatypes::{ArrowPrimitiveType, Float32Type, Float64Type},
+    array::{
+        Array as _, ArrayBuilder, ArrayRef, AsArray, BooleanBufferBuilder, FixedSizeListArray,
+        PrimitiveArray, PrimitiveBuilder,
+    },
+    buffer::{BooleanBuffer, MutableBuffer, NullBuffer},
+    datatypes::{ArrowPrimitiveType, DataType, Field, Float32Type, Float64Type},
 };
 #[cfg(feature = "ndarray")]
 use ndarray::{Array, Ix2};
+use std::sync::Arc;
 
 /// Complex struct
 #[derive(Debug)]
@@ -36,8 +40,8 @@ impl<T: ArrowPrimitiveType> ComplexArrow<T> {
     }
     /// create new complex array from a MutableBuffer
     pub fn new_from_buffer(values_buffer: MutableBuffer) -> Self {
-        let length = values_buffer.len() / 2;
-        let values_builder = PrimitiveBuilder::new_from_buffer(values_buffer, None);
+        let values_builder = PrimitiveBuilder::<T>::new_from_buffer(values_buffer, None);
+        let length = values_builder.len() / 2;
         Self {
             null_buffer_builder: None,
             values_builder,
@@ -88,6 +92,40 @@ impl<T: ArrowPrimitiveType> ComplexArrow<T> {
     pub fn finish(&mut self) -> PrimitiveArray<T> {
         self.values_builder.finish()
     }
+    /// Create a new complex array from a fixed size list array of real and imaginary pairs
+    pub fn new_from_list(array: &FixedSizeListArray) -> Self {
+        let mut values_builder = PrimitiveBuilder::<T>::with_capacity(array.len() * 2);
+        let values = array.values().as_primitive::<T>();
+        let offset = array.value_offset(0) as usize;
+        values_builder.append_slice(&values.values()[offset..offset + array.len() * 2]);
+        Self {
+            null_buffer_builder: array.nulls().map(|nulls| nulls.inner().clone()),
+            values_builder,
+            len: array.len(),
+        }
+    }
+    /// returns the arrow data type of the real and imaginary pairs
+    pub fn list_data_type(&self) -> DataType {
+        DataType::FixedSizeList(Arc::new(Field::new("item", T::DATA_TYPE, false)), 2)
+    }
+    /// groups the interleaved values into real and imaginary pairs
+    fn pair(&self, values: PrimitiveArray<T>) -> ArrayRef {
+        Arc::new(FixedSizeListArray::new(
+            Arc::new(Field::new("item", T::DATA_TYPE, false)),
+            2,
+            Arc::new(values),
+            self.null_buffer_builder.clone().map(NullBuffer::new),
+        ))
+    }
+    /// returns a finished cloned fixed size list array of real and imaginary pairs
+    pub fn finish_cloned_list(&self) -> ArrayRef {
+        self.pair(self.finish_cloned())
+    }
+    /// returns a finished fixed size list array of real and imaginary pairs
+    pub fn finish_list(&mut self) -> ArrayRef {
+        let values = self.finish();
+        self.pair(values)
+    }
     /// overwrite the validity array
     pub fn set_validity(&mut self, mask: &mut BooleanBufferBuilder) {
         self.null_buffer_builder = Some(mask.finish());
@@ -0,0 +1,121 @@
+//! Virtual channels computed from an algebraic expression of channels in memory, evaluated
+//! with the expression engine of the algebraic conversions.
+//! Channels are referenced by their name, or between braces like {Engine.Speed} for names
+//! that are not identifiers, and are resampled onto the chosen master channel if needed.
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+use anyhow::{anyhow, bail, Context, Result};
+use arrow::array::{Array, ArrayRef, Float64Array, Float64Builder};
+use fasteval::{Compiler, Evaler};
+use log::warn;
+
+use crate::data_holder::resample::{resample_channel, to_f64};
+use crate::mdfreader::Mdf;
+
+/// prefix of the variables replacing channel names between braces
+const BRACED_CHANNEL: &str = "__channel_";
+
+/// expression without its optional "name =" assignment, channel names between braces
+/// being replaced by variables. Returns the channel names of the variables
+fn prepare_expression(name: &str, expression: &str) -> Result<(String, HashMap<String, String>)> {
+    let expression = expression
+        .trim()
+        .strip_prefix(name)
+        .map(str::trim_start)
+        .and_then(|rest| rest.strip_prefix('='))
+        .filter(|rest| !rest.starts_with('='))
+        .unwrap_or(expression);
+    let mut prepared = String::with_capacity(expression.len());
+    let mut channels = HashMap::new();
+    let mut rest = expression;
+    while let Some(start) = rest.find('{') {
+        let end = rest[start..]
+            .find('}')
+            .with_context(|| format!("unclosed brace in expression {expression}"))?;
+        let variable = format!("{BRACED_CHANNEL}{}", channels.len());
+        prepared.push_str(&rest[..start]);
+        prepared.push_str(&variable);
+        channels.insert(variable, rest[start + 1..start + end].to_string());
+        rest = &rest[start + end + 1..];
+    }
+    prepared.push_str(rest);
+    Ok((prepared, channels))
+}
+
+/// channel data in memory in f64, resampled onto target master values if its master differs
+fn aligned_channel(
+    mdf: &Mdf,
+    channel_name: &str,
+    master: &str,
+    target: &[f64],
+) -> Result<Float64Array> {
+    let Some(data) = mdf
+        .get_channel_data(channel_name)
+        .filter(|data| !data.is_empty())
+    else {
+        bail!("channel {channel_name} not found or not loaded in memory");
+    };
+    let data = data.as_ref();
+    let channel_master = mdf.get_channel_master(channel_name);
+    if channel_master.as_deref() == Some(master) || channel_name == master {
+        return to_f64(&data);
+    }
+    let channel_master =
+        channel_master.with_context(|| format!("channel {channel_name} has no master channel"))?;
+    let Some(master_data) = mdf
+        .get_channel_data(&channel_master)
+        .filter(|data| !data.is_empty())
+    else {
+        bail!("master channel {channel_master} not loaded in memory");
+    };
+    let master_values = to_f64(&master_data.as_ref())?;
+    to_f64(&resample_channel(&data, master_values.values(), target)?)
+}
+
+/// evaluates the expression for each value of the master channel, invalid where one of the
+/// channels is invalid or out of its master range
+pub fn computed_channel(mdf: &Mdf, name: &str, expression: &str, master: &str) -> Result<ArrayRef> {
+    let (prepared, braced) = prepare_expression(name, expression)?;
+    let parser = fasteval::Parser::new();
+    let mut slab = fasteval::Slab::new();
+    let compiled = parser
+        .parse(&prepared, &mut slab.ps)
+        .map_err(|error| anyhow!("failed parsing expression {expression}: {error}"))?
+        .from(&slab.ps)
+        .compile(&slab.ps, &mut slab.cs);
+    let Some(master_data) = mdf.get_channel_data(master).filter(|data| !data.is_empty()) else {
+        bail!("master channel {master} not found or not loaded in memory");
+    };
+    let target = to_f64(&master_data.as_ref())?;
+    let mut variables: Vec<(String, Float64Array)> = Vec::new();
+    for variable in compiled.var_names(&slab) {
+        let channel_name = braced.get(&variable).unwrap_or(&variable);
+        let values = aligned_channel(mdf, channel_name, master, target.values())
+            .with_context(|| format!("failed aligning channel {channel_name} of expression"))?;
+        variables.push((variable, values));
+    }
+    let mut builder = Float64Builder::with_capacity(target.len());
+    let mut map = BTreeMap::new();
+    let mut errors = 0usize;
+    for index in 0..target.len() {
+        if variables.iter().any(|(_, values)| values.is_null(index)) {
+            builder.append_null();
+            continue;
+        }
+        for (variable, values) in variables.iter() {
+            map.insert(variable.clone(), values.value(index));
+        }
+        match compiled.eval(&slab, &mut map) {
+            Ok(value) => builder.append_value(value),
+            Err(_) => {
+                errors += 1;
+                builder.append_null();
+            }
+        }
+    }
+    if errors > 0 {
+        warn!("expression {expression} could not be evaluated for {errors} values");
+    }
+    Ok(Arc::new(builder.finish()))
+}
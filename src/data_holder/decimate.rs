@@ -0,0 +1,117 @@
+//! Decimation of channels data for display: the samples are split into buckets of
+//! consecutive records, each bucket being reduced to its extrema, its mean or its first sample.
+use std::sync::Arc;
+
+use crate::parallel::prelude::*;
+use anyhow::{bail, Context, Result};
+use arrow::array::{Array, ArrayRef, AsArray, Float64Array, UInt64Array};
+use arrow::compute::{cast, take};
+use arrow::datatypes::{DataType, Float64Type};
+
+/// bucket reduction method
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DecimationMethod {
+    /// minimum and maximum of each bucket, in records order, keeping peaks visible
+    #[default]
+    MinMax,
+    /// mean of each bucket in f64, invalid values ignored
+    Mean,
+    /// first sample of each bucket
+    Nth,
+}
+
+/// number of records in each bucket
+fn bucket_size(len: usize, n_buckets: usize) -> usize {
+    len.div_ceil(n_buckets).max(1)
+}
+
+/// returns the channel data in f64
+fn to_f64(data: &ArrayRef) -> Result<Float64Array> {
+    let data = cast(data, &DataType::Float64).context("failed casting channel into f64")?;
+    Ok(data.as_primitive::<Float64Type>().clone())
+}
+
+/// indexes of the minimum and maximum of each bucket, invalid and NaN values ignored.
+/// Buckets without valid value give a single invalid index
+fn min_max_indexes(values: &Float64Array, size: usize) -> UInt64Array {
+    let indexes: Vec<Vec<Option<u64>>> = (0..values.len())
+        .step_by(size)
+        .collect::<Vec<usize>>()
+        .par_iter()
+        .map(|start| {
+            let end = (start + size).min(values.len());
+            let mut min: Option<(usize, f64)> = None;
+            let mut max: Option<(usize, f64)> = None;
+            for i in *start..end {
+                if values.is_null(i) || values.value(i).is_nan() {
+                    continue;
+                }
+                let value = values.value(i);
+                if min.is_none_or(|(_, m)| value < m) {
+                    min = Some((i, value));
+                }
+                if max.is_none_or(|(_, m)| value > m) {
+                    max = Some((i, value));
+                }
+            }
+            match (min, max) {
+                (Some((i_min, _)), Some((i_max, _))) if i_min == i_max => vec![Some(i_min as u64)],
+                (Some((i_min, _)), Some((i_max, _))) => {
+                    vec![Some(i_min.min(i_max) as u64), Some(i_min.max(i_max) as u64)]
+                }
+                _ => vec![None],
+            }
+        })
+        .collect();
+    indexes.into_iter().flatten().collect()
+}
+
+/// mean of each bucket, invalid and NaN values ignored
+fn means(values: &Float64Array, size: usize) -> Float64Array {
+    let means: Vec<Option<f64>> = (0..values.len())
+        .step_by(size)
+        .collect::<Vec<usize>>()
+        .par_iter()
+        .map(|start| {
+            let end = (start + size).min(values.len());
+            let (sum, count) = (*start..end)
+                .filter(|i| !values.is_null(*i) && !values.value(*i).is_nan())
+                .fold((0.0, 0usize), |(sum, count), i| {
+                    (sum + values.value(i), count + 1)
+                });
+            (count > 0).then(|| sum / count as f64)
+        })
+        .collect();
+    Float64Array::from(means)
+}
+
+/// reduces the data to at most max_points samples. MinMax and Nth keep the data type,
+/// Mean returns f64 values. Data shorter than max_points is returned unchanged
+pub fn decimate(data: &ArrayRef, max_points: usize, method: DecimationMethod) -> Result<ArrayRef> {
+    if max_points == 0 || (method == DecimationMethod::MinMax && max_points < 2) {
+        bail!("decimation into {max_points} points not possible with {method:?} method");
+    }
+    if data.len() <= max_points {
+        return Ok(data.clone());
+    }
+    match method {
+        DecimationMethod::MinMax => {
+            let values = to_f64(data)?;
+            let indexes = min_max_indexes(&values, bucket_size(data.len(), max_points / 2));
+            take(data.as_ref(), &indexes, None).context("failed taking buckets extrema")
+        }
+        DecimationMethod::Mean => {
+            let values = to_f64(data)?;
+            Ok(Arc::new(means(
+                &values,
+                bucket_size(data.len(), max_points),
+            )))
+        }
+        DecimationMethod::Nth => {
+            let indexes: UInt64Array = (0..data.len() as u64)
+                .step_by(bucket_size(data.len(), max_points))
+                .collect();
+            take(data.as_ref(), &indexes, None).context("failed taking buckets first sample")
+        }
+    }
+}
@@ -0,0 +1,211 @@
+//! Digital filters smoothing channels data before export: centered moving average and median
+//! over a window of samples, and Butterworth low-pass applied forward and backward for zero
+//! phase shift. Filtered channels are in f64, invalid samples staying invalid.
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+use crate::parallel::prelude::*;
+use anyhow::{bail, Context, Result};
+use arrow::array::{Array, ArrayRef, AsArray, Float64Array};
+use arrow::compute::cast;
+use arrow::datatypes::{DataType, Float64Type};
+
+/// filter applied to a channel
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Filter {
+    /// mean of the window of samples centered on each sample
+    MovingAverage { window: usize },
+    /// Butterworth low-pass of cutoff frequency in Hz, the sample rate being derived from the
+    /// master channel
+    LowPass { cutoff: f64, order: usize },
+    /// median of the window of samples centered on each sample, removing spikes
+    Median { window: usize },
+}
+
+/// returns the channel data in f64
+fn to_f64(data: &ArrayRef) -> Result<Float64Array> {
+    let data = cast(data, &DataType::Float64).context("failed casting channel into f64")?;
+    Ok(data.as_primitive::<Float64Type>().clone())
+}
+
+/// range of the window of samples centered on index
+fn window_range(index: usize, window: usize, len: usize) -> std::ops::Range<usize> {
+    let start = index.saturating_sub((window - 1) / 2);
+    start..(start + window).min(len)
+}
+
+/// valid and not NaN values of the window centered on each sample reduced with reduce,
+/// invalid where the window has no such value
+fn window_reduce(
+    values: &Float64Array,
+    window: usize,
+    reduce: impl Fn(&mut [f64]) -> f64 + Sync,
+) -> Float64Array {
+    let reduced: Vec<Option<f64>> = (0..values.len())
+        .collect::<Vec<usize>>()
+        .par_iter()
+        .map(|index| {
+            if values.is_null(*index) {
+                return None;
+            }
+            let mut window: Vec<f64> = window_range(*index, window, values.len())
+                .filter(|i| values.is_valid(*i) && !values.value(*i).is_nan())
+                .map(|i| values.value(i))
+                .collect();
+            (!window.is_empty()).then(|| reduce(&mut window))
+        })
+        .collect();
+    Float64Array::from(reduced)
+}
+
+/// mean of values
+fn mean(values: &mut [f64]) -> f64 {
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+/// median of values, mean of the two middle values for even length
+fn median(values: &mut [f64]) -> f64 {
+    values.sort_by(f64::total_cmp);
+    let middle = values.len() / 2;
+    if values.len().is_multiple_of(2) {
+        (values[middle - 1] + values[middle]) / 2.0
+    } else {
+        values[middle]
+    }
+}
+
+/// second order section of a filter, coefficients normalised by a0
+#[derive(Debug, Clone, Copy)]
+struct Biquad {
+    b: [f64; 3],
+    a: [f64; 2],
+}
+
+impl Biquad {
+    /// filters values in place, the state starting as if the first value was constant before
+    fn filter(&self, values: &mut [f64]) {
+        let Some(first) = values.first().copied() else {
+            return;
+        };
+        let mut z1 = (1.0 - self.b[0]) * first;
+        let mut z2 = (self.b[2] - self.a[1]) * first;
+        for value in values.iter_mut() {
+            let x = *value;
+            let y = self.b[0] * x + z1;
+            z1 = self.b[1] * x - self.a[0] * y + z2;
+            z2 = self.b[2] * x - self.a[1] * y;
+            *value = y;
+        }
+    }
+}
+
+/// second order sections of the Butterworth low-pass, bilinear transform prewarped at cutoff
+fn butterworth_sections(cutoff: f64, sample_rate: f64, order: usize) -> Vec<Biquad> {
+    let k = (PI * cutoff / sample_rate).tan();
+    let mut sections: Vec<Biquad> = (0..order / 2)
+        .map(|i| {
+            // angle of the pole pair from the negative real axis
+            let angle = if order.is_multiple_of(2) {
+                PI * (2 * i + 1) as f64 / (2 * order) as f64
+            } else {
+                PI * (i + 1) as f64 / order as f64
+            };
+            let q = 1.0 / (2.0 * angle.cos());
+            let norm = 1.0 / (1.0 + k / q + k * k);
+            let b0 = k * k * norm;
+            Biquad {
+                b: [b0, 2.0 * b0, b0],
+                a: [2.0 * (k * k - 1.0) * norm, (1.0 - k / q + k * k) * norm],
+            }
+        })
+        .collect();
+    if !order.is_multiple_of(2) {
+        let b0 = k / (1.0 + k);
+        sections.push(Biquad {
+            b: [b0, b0, 0.0],
+            a: [(k - 1.0) / (k + 1.0), 0.0],
+        });
+    }
+    sections
+}
+
+/// mean sample rate of the master channel in Hz
+fn sample_rate(master: &Float64Array) -> Result<f64> {
+    let valid: Vec<f64> = master.iter().flatten().collect();
+    match (valid.first(), valid.last()) {
+        (Some(first), Some(last)) if valid.len() > 1 && last > first => {
+            Ok((valid.len() - 1) as f64 / (last - first))
+        }
+        _ => bail!("sample rate can not be derived from master channel"),
+    }
+}
+
+/// Butterworth low-pass applied forward and backward, invalid and NaN values being held from
+/// the previous valid value while filtering
+fn low_pass(
+    values: &Float64Array,
+    master: &Float64Array,
+    cutoff: f64,
+    order: usize,
+) -> Result<Float64Array> {
+    if order == 0 {
+        bail!("low-pass filter order should be at least 1");
+    }
+    let sample_rate = sample_rate(master)?;
+    let nyquist = sample_rate / 2.0;
+    if !(cutoff > 0.0 && cutoff < nyquist) {
+        bail!("cutoff frequency {cutoff} Hz should be between 0 and {nyquist} Hz");
+    }
+    let mut held: Vec<f64> = Vec::with_capacity(values.len());
+    let mut last = values
+        .iter()
+        .flatten()
+        .find(|value| !value.is_nan())
+        .unwrap_or_default();
+    for value in values.iter() {
+        if let Some(value) = value.filter(|value| !value.is_nan()) {
+            last = value;
+        }
+        held.push(last);
+    }
+    for section in butterworth_sections(cutoff, sample_rate, order) {
+        section.filter(&mut held);
+        held.reverse();
+        section.filter(&mut held);
+        held.reverse();
+    }
+    Ok(held
+        .into_iter()
+        .enumerate()
+        .map(|(i, value)| values.is_valid(i).then_some(value))
+        .collect())
+}
+
+/// filters the channel data, master data being needed by the low-pass for its sample rate
+pub fn filter_channel(
+    data: &ArrayRef,
+    master: Option<&ArrayRef>,
+    filter: Filter,
+) -> Result<ArrayRef> {
+    let values = to_f64(data)?;
+    let filtered = match filter {
+        Filter::MovingAverage { window } | Filter::Median { window } if window == 0 => {
+            bail!("filter window should be at least 1 sample")
+        }
+        Filter::MovingAverage { window } => window_reduce(&values, window, mean),
+        Filter::Median { window } => window_reduce(&values, window, median),
+        Filter::LowPass { cutoff, order } => {
+            let master = master.context("low-pass filter needs the master channel")?;
+            if master.len() != values.len() {
+                bail!(
+                    "master length {} differs from channel length {}",
+                    master.len(),
+                    values.len()
+                );
+            }
+            low_pass(&values, &to_f64(master)?, cutoff, order)?
+        }
+    };
+    Ok(Arc::new(filtered))
+}
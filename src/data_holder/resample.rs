@@ -0,0 +1,208 @@
+//! Resampling of channels onto a common master raster:
+//! floating point channels are linearly interpolated, other data types use zero-order hold.
+//! Values outside of the channel's master range are not extrapolated and set invalid.
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use anyhow::{bail, Context, Result};
+use arrow::array::{Array, ArrayRef, AsArray, Float64Array, Float64Builder, UInt64Array};
+use arrow::compute::{cast, take};
+use arrow::datatypes::{DataType, Float64Type};
+use log::info;
+
+use crate::mdfinfo::mdfinfo4::{FhBlock, MdfInfo4};
+use crate::mdfinfo::MdfInfo;
+use crate::mdfreader::Mdf;
+use crate::mdfwriter::merge4::start_time_ns;
+
+/// name of the master channel created by raster resampling
+const RASTER_MASTER: &str = "time";
+
+/// returns the channel data in f64
+pub(crate) fn to_f64(data: &ArrayRef) -> Result<Float64Array> {
+    let data = cast(data, &DataType::Float64).context("failed casting channel into f64")?;
+    Ok(data.as_primitive::<Float64Type>().clone())
+}
+
+/// for each target value, index of the last master value lower or equal,
+/// None when out of master range. Master is expected to be monotonic
+fn hold_indexes(master: &[f64], target: &[f64]) -> UInt64Array {
+    target
+        .iter()
+        .map(|x| match (master.first(), master.last()) {
+            (Some(first), Some(last)) if first <= x && x <= last => {
+                Some((master.partition_point(|t| t <= x) - 1) as u64)
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// linear interpolation of floating point values, invalid if one of the surrounding values is
+fn interpolate(
+    data: &ArrayRef,
+    master: &[f64],
+    target: &[f64],
+    indexes: &UInt64Array,
+) -> Result<ArrayRef> {
+    let values = to_f64(data)?;
+    let mut builder = Float64Builder::with_capacity(target.len());
+    for (x, index) in target.iter().zip(indexes.iter()) {
+        let Some(i) = index.map(|i| i as usize) else {
+            builder.append_null();
+            continue;
+        };
+        if values.is_null(i) {
+            builder.append_null();
+        } else if i + 1 < master.len() && master[i + 1] > master[i] && *x > master[i] {
+            if values.is_null(i + 1) {
+                builder.append_null();
+            } else {
+                let ratio = (x - master[i]) / (master[i + 1] - master[i]);
+                builder.append_value(
+                    values.value(i) + (values.value(i + 1) - values.value(i)) * ratio,
+                );
+            }
+        } else {
+            builder.append_value(values.value(i));
+        }
+    }
+    let resampled: ArrayRef = Arc::new(builder.finish());
+    cast(&resampled, data.data_type()).context("failed casting back interpolated channel")
+}
+
+/// resamples the channel data from its master values onto target values
+pub(crate) fn resample_channel(
+    data: &ArrayRef,
+    master: &[f64],
+    target: &[f64],
+) -> Result<ArrayRef> {
+    let indexes = hold_indexes(master, target);
+    match data.data_type() {
+        DataType::Float16 | DataType::Float32 | DataType::Float64 => {
+            interpolate(data, master, target, &indexes)
+        }
+        _ => take(data.as_ref(), &indexes, None).context("failed zero-order hold resampling"),
+    }
+}
+
+/// resamples all the channels having a master of master_type onto the target master values.
+/// Returns a new Mdf with a single channel group, channels data must be loaded in memory
+fn resample(
+    mdf: &Mdf,
+    target: Float64Array,
+    master_name: &str,
+    master_type: u8,
+    master_unit: Option<String>,
+    master_desc: Option<String>,
+) -> Result<Mdf> {
+    let target_values = target.values();
+    let mut groups: HashMap<String, Vec<String>> = HashMap::new();
+    for name in mdf.get_channel_names_set() {
+        if let Some(master) = mdf.get_channel_master(&name) {
+            if master != name && mdf.get_channel_master_type(&master) == master_type {
+                groups.entry(master).or_default().push(name);
+            }
+        }
+    }
+    let mut info = MdfInfo4::new(&mdf.get_file_name(), groups.values().map(|g| g.len()).sum());
+    info.fh.push(FhBlock::default());
+    info.hd_block.hd_start_time_ns = start_time_ns(mdf);
+    let mut resampled = Mdf {
+        mdf_info: MdfInfo::V4(Box::new(info)),
+        stats: HashMap::new(),
+        master_fixes: Vec::new(),
+    };
+    resampled.add_channel(
+        master_name.to_string(),
+        Arc::new(target.clone()),
+        Some(master_name.to_string()),
+        Some(master_type),
+        true,
+        master_unit,
+        master_desc,
+    )?;
+    for (master, channel_names) in groups {
+        let Some(master_data) = mdf.get_channel_data(&master).filter(|d| !d.is_empty()) else {
+            continue;
+        };
+        let master_values = to_f64(&master_data.as_ref())?;
+        for name in channel_names {
+            if name == master_name {
+                continue;
+            }
+            let Some(data) = mdf.get_channel_data(&name).filter(|d| !d.is_empty()) else {
+                continue;
+            };
+            let data = resample_channel(&data.as_ref(), master_values.values(), target_values)
+                .with_context(|| format!("failed resampling channel {name}"))?;
+            resampled
+                .add_channel(
+                    name.clone(),
+                    data,
+                    Some(master_name.to_string()),
+                    None,
+                    false,
+                    mdf.get_channel_unit(&name)?,
+                    mdf.get_channel_desc(&name)?,
+                )
+                .with_context(|| format!("failed adding resampled channel {name}"))?;
+        }
+    }
+    info!("Resampled channels on {} master values", target.len());
+    Ok(resampled)
+}
+
+/// resamples all the time based channels onto a raster in seconds
+/// covering the time range of all the time master channels
+pub fn resample_on_raster(mdf: &Mdf, raster: f64) -> Result<Mdf> {
+    if raster.is_nan() || raster <= 0.0 {
+        bail!("raster must be strictly positive, got {raster}");
+    }
+    let mut start = f64::INFINITY;
+    let mut end = f64::NEG_INFINITY;
+    for master in mdf.get_master_channel_names_set().into_keys().flatten() {
+        if mdf.get_channel_master_type(&master) != 1 {
+            continue;
+        }
+        if let Some(data) = mdf.get_channel_data(&master).filter(|d| !d.is_empty()) {
+            let values = to_f64(&data.as_ref())?;
+            values.iter().flatten().for_each(|t| {
+                start = start.min(t);
+                end = end.max(t);
+            });
+        }
+    }
+    if start > end {
+        bail!("no time master channel data in memory to resample");
+    }
+    let n_samples = ((end - start) / raster).floor() as usize + 1;
+    let target: Float64Array = (0..n_samples).map(|k| start + k as f64 * raster).collect();
+    resample(
+        mdf,
+        target,
+        RASTER_MASTER,
+        1,
+        Some("s".to_string()),
+        Some(format!("resampled time raster of {raster}s")),
+    )
+}
+
+/// resamples all the channels having the same master type onto the given master channel values
+pub fn resample_on_master(mdf: &Mdf, master_channel: &str) -> Result<Mdf> {
+    let Some(data) = mdf
+        .get_channel_data(master_channel)
+        .filter(|d| !d.is_empty())
+    else {
+        bail!("master channel {master_channel} has no data in memory");
+    };
+    let target = to_f64(&data.as_ref())?;
+    resample(
+        mdf,
+        target,
+        master_channel,
+        mdf.get_channel_master_type(master_channel),
+        mdf.get_channel_unit(master_channel)?,
+        mdf.get_channel_desc(master_channel)?,
+    )
+}
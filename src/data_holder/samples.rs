@@ -0,0 +1,185 @@
+//! Iteration over the (time, value) samples of a channel in memory, zipping the channel
+//! data with its master channel, and lookup of the channel value at any master value
+use std::sync::Arc;
+
+use anyhow::{bail, Context, Result};
+use arrow::array::{Array, ArrayRef, AsArray, Float64Array};
+use arrow::compute::cast;
+use arrow::datatypes::{DataType, Float64Type, Int64Type, UInt64Type};
+
+/// value of a channel sample
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    /// sample flagged invalid by the channel validity mask
+    Invalid,
+    Bool(bool),
+    UInt(u64),
+    Int(i64),
+    Float(f64),
+    Str(String),
+    Bytes(Vec<u8>),
+}
+
+#[allow(dead_code)]
+impl Value {
+    /// returns the numeric value in f64, None for invalid, text and bytes samples
+    pub fn as_f64(&self) -> Option<f64> {
+        match *self {
+            Value::Bool(v) => Some(v as u8 as f64),
+            Value::UInt(v) => Some(v as f64),
+            Value::Int(v) => Some(v as f64),
+            Value::Float(v) => Some(v),
+            _ => None,
+        }
+    }
+}
+
+/// interpolation of the channel value between two samples
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Interpolation {
+    /// value of the last sample at or before the time, zero-order hold
+    #[default]
+    Previous,
+    /// value of the sample nearest in time, the previous one when equidistant
+    Nearest,
+    /// linear interpolation in f64 of numeric values, previous value for other data types
+    Linear,
+}
+
+/// data type the channel data is cast into for iteration
+fn sample_data_type(data_type: &DataType) -> Result<DataType> {
+    Ok(match data_type {
+        DataType::Boolean => DataType::Boolean,
+        DataType::UInt8 | DataType::UInt16 | DataType::UInt32 | DataType::UInt64 => {
+            DataType::UInt64
+        }
+        DataType::Int8 | DataType::Int16 | DataType::Int32 | DataType::Int64 => DataType::Int64,
+        DataType::Float16 | DataType::Float32 | DataType::Float64 => DataType::Float64,
+        DataType::Utf8 | DataType::LargeUtf8 | DataType::Dictionary(_, _) => DataType::LargeUtf8,
+        DataType::Binary | DataType::LargeBinary => DataType::LargeBinary,
+        DataType::FixedSizeBinary(size) => DataType::FixedSizeBinary(*size),
+        data_type => bail!("samples of data type {data_type} can not be iterated"),
+    })
+}
+
+/// Iterator over the (time, value) samples of a channel.
+/// Invalid samples are returned as Value::Invalid, samples with invalid master are skipped
+pub struct SampleIterator {
+    /// master channel values
+    master: Float64Array,
+    /// channel values cast into the data type of their Value
+    values: ArrayRef,
+    /// index of next sample
+    index: usize,
+}
+
+impl SampleIterator {
+    /// creates the iterator from the master and channel data of same length
+    pub fn new(master: &ArrayRef, data: &ArrayRef) -> Result<Self> {
+        if master.len() != data.len() {
+            bail!(
+                "master length {} differs from channel length {}",
+                master.len(),
+                data.len()
+            );
+        }
+        let master = cast(master, &DataType::Float64).context("failed casting master into f64")?;
+        let data_type = sample_data_type(data.data_type())?;
+        let values = cast(data, &data_type)
+            .with_context(|| format!("failed casting channel data into {data_type}"))?;
+        Ok(SampleIterator {
+            master: master.as_primitive::<Float64Type>().clone(),
+            values,
+            index: 0,
+        })
+    }
+    /// value of the sample at index
+    fn value(&self, index: usize) -> Value {
+        if self.values.is_null(index) {
+            return Value::Invalid;
+        }
+        match self.values.data_type() {
+            DataType::Boolean => Value::Bool(self.values.as_boolean().value(index)),
+            DataType::UInt64 => Value::UInt(self.values.as_primitive::<UInt64Type>().value(index)),
+            DataType::Int64 => Value::Int(self.values.as_primitive::<Int64Type>().value(index)),
+            DataType::Float64 => {
+                Value::Float(self.values.as_primitive::<Float64Type>().value(index))
+            }
+            DataType::LargeUtf8 => {
+                Value::Str(self.values.as_string::<i64>().value(index).to_string())
+            }
+            DataType::LargeBinary => {
+                Value::Bytes(self.values.as_binary::<i64>().value(index).to_vec())
+            }
+            _ => Value::Bytes(self.values.as_fixed_size_binary().value(index).to_vec()),
+        }
+    }
+}
+
+impl Iterator for SampleIterator {
+    type Item = (f64, Value);
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.index < self.master.len() {
+            let index = self.index;
+            self.index += 1;
+            if self.master.is_valid(index) {
+                return Some((self.master.value(index), self.value(index)));
+            }
+        }
+        None
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.master.len() - self.index))
+    }
+}
+
+/// value of the channel at time, master being monotonic. None when time is out of the master
+/// range, Value::Invalid if a sample needed is invalid
+pub fn value_at(
+    master: &ArrayRef,
+    data: &ArrayRef,
+    time: f64,
+    method: Interpolation,
+) -> Result<Option<Value>> {
+    if master.len() != data.len() {
+        bail!(
+            "master length {} differs from channel length {}",
+            master.len(),
+            data.len()
+        );
+    }
+    let master = cast(master, &DataType::Float64).context("failed casting master into f64")?;
+    let master = master.as_primitive::<Float64Type>();
+    let times = master.values();
+    let (Some(first), Some(last)) = (times.first(), times.last()) else {
+        return Ok(None);
+    };
+    if time.is_nan() || time < *first || time > *last {
+        return Ok(None);
+    }
+    // last sample at or before time and the following one, only these samples being cast
+    let index = times.partition_point(|t| *t <= time) - 1;
+    let len = (times.len() - index).min(2);
+    let mut samples = SampleIterator::new(
+        &(Arc::new(master.slice(index, len)) as ArrayRef),
+        &data.slice(index, len),
+    )?;
+    let Some((t0, v0)) = samples.next() else {
+        return Ok(Some(Value::Invalid));
+    };
+    let Some((t1, v1)) = samples.next().filter(|(t1, _)| *t1 > t0 && time > t0) else {
+        return Ok(Some(v0));
+    };
+    Ok(Some(match method {
+        Interpolation::Previous => v0,
+        Interpolation::Nearest if time - t0 <= t1 - time => v0,
+        Interpolation::Nearest => v1,
+        Interpolation::Linear => match (&v0, v0.as_f64(), v1.as_f64()) {
+            (Value::Bool(_), _, _) => v0,
+            (_, Some(y0), Some(y1)) => Value::Float(y0 + (y1 - y0) * (time - t0) / (t1 - t0)),
+            (_, Some(_), None) => Value::Invalid,
+            _ => v0,
+        },
+    }))
+}
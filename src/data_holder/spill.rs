@@ -0,0 +1,143 @@
+//! Spill of channels data into arrow IPC temporary files, for machines with less memory
+//! than the data of the file. Spilled data is read back from disk at first access, or at
+//! each access without being kept in memory for the passes over all channels of exports
+//! and writing
+use std::borrow::Cow;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, OnceLock};
+
+use anyhow::{Context, Error, Result};
+use arrow::datatypes::{Field, Schema};
+use arrow::ipc::reader::FileReader;
+use arrow::ipc::writer::FileWriter;
+use arrow::record_batch::RecordBatch;
+use log::warn;
+
+use crate::data_holder::channel_data::{try_from, ChannelData};
+use crate::error::MdfError;
+
+/// counter of spill files created by the process, for unique file names
+static SPILL_FILES: AtomicUsize = AtomicUsize::new(0);
+
+/// temporary arrow IPC file, removed when no channel refers to it anymore
+#[derive(Debug)]
+struct SpillFile {
+    path: PathBuf,
+}
+
+impl Drop for SpillFile {
+    fn drop(&mut self) {
+        if let Err(error) = fs::remove_file(&self.path) {
+            warn!(
+                "failed removing spill file {}: {error}",
+                self.path.display()
+            );
+        }
+    }
+}
+
+/// channel data stored in a column of a spill file
+#[derive(Debug, Clone)]
+pub struct SpilledData {
+    file: Arc<SpillFile>,
+    column: usize,
+    /// data read back from the spill file
+    cache: OnceLock<ChannelData>,
+}
+
+impl SpilledData {
+    /// returns the channel data, read back from the spill file at first call and kept in memory
+    pub fn data(&self) -> Result<&ChannelData, Error> {
+        if let Some(data) = self.cache.get() {
+            return Ok(data);
+        }
+        let data = self.read()?;
+        Ok(self.cache.get_or_init(|| data))
+    }
+    /// returns the channel data, from memory if already read back, otherwise read from the
+    /// spill file without being kept in memory
+    pub fn read_uncached(&self) -> Result<Cow<'_, ChannelData>, Error> {
+        match self.cache.get() {
+            Some(data) => Ok(Cow::Borrowed(data)),
+            None => Ok(Cow::Owned(self.read()?)),
+        }
+    }
+    /// true if the channel data has been read back and is kept in memory
+    #[cfg(test)]
+    pub(crate) fn is_cached(&self) -> bool {
+        self.cache.get().is_some()
+    }
+    /// reads the channel data from the spill file
+    fn read(&self) -> Result<ChannelData, Error> {
+        let path = &self.file.path;
+        let file = File::open(path)
+            .map_err(MdfError::Io)
+            .with_context(|| format!("Cannot open the spill file {}", path.display()))?;
+        let mut reader = FileReader::try_new(BufReader::new(file), Some(vec![self.column]))
+            .with_context(|| format!("failed reading spill file {}", path.display()))?;
+        let batch = reader
+            .next()
+            .context("spill file without record batch")?
+            .with_context(|| format!("failed reading spill file {}", path.display()))?;
+        try_from(batch.column(0).as_ref())
+            .context("failed converting spilled array into ChannelData")
+    }
+}
+
+/// true if the channel data can be read back from a spill file
+pub(crate) fn is_spillable(data: &ChannelData) -> bool {
+    !matches!(
+        data,
+        ChannelData::ArrayDInt8(_)
+            | ChannelData::ArrayDUInt8(_)
+            | ChannelData::ArrayDInt16(_)
+            | ChannelData::ArrayDUInt16(_)
+            | ChannelData::ArrayDInt32(_)
+            | ChannelData::ArrayDUInt32(_)
+            | ChannelData::ArrayDFloat32(_)
+            | ChannelData::ArrayDInt64(_)
+            | ChannelData::ArrayDUInt64(_)
+            | ChannelData::ArrayDFloat64(_)
+    ) && !data.is_empty()
+}
+
+/// writes the channels data of same length in a new spill file of directory,
+/// one column per channel, and returns their spilled data in the same order
+pub(crate) fn spill(directory: &Path, channels: &[&ChannelData]) -> Result<Vec<SpilledData>> {
+    let arrays: Vec<_> = channels.iter().map(|data| data.as_ref()).collect();
+    let schema = Arc::new(Schema::new(
+        arrays
+            .iter()
+            .enumerate()
+            .map(|(column, array)| Field::new(column.to_string(), array.data_type().clone(), true))
+            .collect::<Vec<Field>>(),
+    ));
+    let batch = RecordBatch::try_new(schema.clone(), arrays)
+        .context("failed building record batch of spilled channels")?;
+    let path = directory.join(format!(
+        "mdfr_spill_{}_{}.arrow",
+        std::process::id(),
+        SPILL_FILES.fetch_add(1, Ordering::Relaxed)
+    ));
+    let output = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&path)
+        .map_err(MdfError::Io)
+        .with_context(|| format!("Cannot create the spill file {}", path.display()))?;
+    let file = Arc::new(SpillFile { path });
+    let mut writer = FileWriter::try_new(BufWriter::new(output), &schema)
+        .with_context(|| format!("failed writing spill file {}", file.path.display()))?;
+    writer.write(&batch)?;
+    writer.finish()?;
+    Ok((0..channels.len())
+        .map(|column| SpilledData {
+            file: file.clone(),
+            column,
+            cache: OnceLock::new(),
+        })
+        .collect())
+}
@@ -0,0 +1,133 @@
+//! Summary statistics of channels data, computed in parallel over chunks of values
+use std::fmt;
+
+use crate::parallel::prelude::*;
+use anyhow::{Context, Result};
+use arrow::array::{Array, ArrayRef, AsArray};
+use arrow::compute::cast;
+use arrow::datatypes::{DataType, Float64Type};
+
+/// number of values per parallel chunk
+const CHUNK_SIZE: usize = 65536;
+
+/// channel summary statistics, invalid and NaN values being ignored
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Stats {
+    /// minimum value, None without valid value
+    pub min: Option<f64>,
+    /// maximum value, None without valid value
+    pub max: Option<f64>,
+    /// mean value, None without valid value
+    pub mean: Option<f64>,
+    /// population standard deviation, None without valid value
+    pub std: Option<f64>,
+    /// number of valid values, NaN excluded
+    pub count: usize,
+    /// number of NaN values
+    pub nan_count: usize,
+}
+
+/// running statistics of a chunk, merged pairwise
+#[derive(Clone, Copy)]
+struct Accumulator {
+    count: usize,
+    nan_count: usize,
+    min: f64,
+    max: f64,
+    mean: f64,
+    /// sum of squared differences to the mean
+    m2: f64,
+}
+
+impl Accumulator {
+    fn new() -> Self {
+        Self {
+            count: 0,
+            nan_count: 0,
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+            mean: 0.0,
+            m2: 0.0,
+        }
+    }
+    /// adds a value, Welford's algorithm
+    fn push(mut self, value: f64) -> Self {
+        if value.is_nan() {
+            self.nan_count += 1;
+            return self;
+        }
+        self.count += 1;
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+        let delta = value - self.mean;
+        self.mean += delta / self.count as f64;
+        self.m2 += delta * (value - self.mean);
+        self
+    }
+    /// merges the statistics of two chunks, Chan's algorithm
+    fn merge(self, other: Self) -> Self {
+        if self.count == 0 || other.count == 0 {
+            let mut merged = if self.count == 0 { other } else { self };
+            merged.nan_count = self.nan_count + other.nan_count;
+            return merged;
+        }
+        let count = self.count + other.count;
+        let delta = other.mean - self.mean;
+        Self {
+            count,
+            nan_count: self.nan_count + other.nan_count,
+            min: self.min.min(other.min),
+            max: self.max.max(other.max),
+            mean: self.mean + delta * other.count as f64 / count as f64,
+            m2: self.m2
+                + other.m2
+                + delta * delta * (self.count as f64 * other.count as f64) / count as f64,
+        }
+    }
+}
+
+/// computes the statistics of numeric or boolean data
+pub fn stats(data: &ArrayRef) -> Result<Stats> {
+    let values = cast(data, &DataType::Float64).context("failed casting channel into f64")?;
+    let values = values.as_primitive::<Float64Type>();
+    let acc = (0..values.len())
+        .step_by(CHUNK_SIZE)
+        .collect::<Vec<usize>>()
+        .par_iter()
+        .map(|start| {
+            (*start..(start + CHUNK_SIZE).min(values.len()))
+                .filter(|i| values.is_valid(*i))
+                .fold(Accumulator::new(), |acc, i| acc.push(values.value(i)))
+        })
+        .reduce_with(Accumulator::merge)
+        .unwrap_or_else(Accumulator::new);
+    let valid = acc.count > 0;
+    Ok(Stats {
+        min: valid.then_some(acc.min),
+        max: valid.then_some(acc.max),
+        mean: valid.then_some(acc.mean),
+        std: valid.then(|| (acc.m2 / acc.count as f64).sqrt()),
+        count: acc.count,
+        nan_count: acc.nan_count,
+    })
+}
+
+/// displays an optional value or - if not available
+fn format_option(value: Option<f64>) -> String {
+    value.map_or_else(|| "-".to_string(), |v| format!("{v}"))
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "min {} max {} mean {} std {} count {} nan {}",
+            format_option(self.min),
+            format_option(self.max),
+            format_option(self.mean),
+            format_option(self.std),
+            self.count,
+            self.nan_count
+        )
+    }
+}
@@ -2,15 +2,19 @@
 #[cfg(feature = "ndarray")]
 use anyhow::{Context, Error, Result};
 use arrow::{
-    array::{ArrayBuilder, BooleanBufferBuilder, PrimitiveArray, PrimitiveBuilder},
-    buffer::{BooleanBuffer, MutableBuffer},
+    array::{
+        ArrayBuilder, ArrayRef, BooleanBufferBuilder, FixedSizeListArray, PrimitiveArray,
+        PrimitiveBuilder,
+    },
+    buffer::{BooleanBuffer, MutableBuffer, NullBuffer},
     datatypes::{
-        ArrowPrimitiveType, Float32Type, Float64Type, Int16Type, Int32Type, Int64Type, Int8Type,
-        UInt16Type, UInt32Type, UInt64Type, UInt8Type,
+        ArrowPrimitiveType, DataType, Field, Float32Type, Float64Type, Int16Type, Int32Type,
+        Int64Type, Int8Type, UInt16Type, UInt32Type, UInt64Type, UInt8Type,
     },
 };
 #[cfg(feature = "ndarray")]
 use ndarray::{Array, IxDyn};
+use std::sync::Arc;
 
 /// Tensor with innner arrow primitive builder
 #[derive(Debug)]
@@ -52,8 +56,8 @@ impl<T: ArrowPrimitiveType> TensorArrow<T> {
     }
     /// Create new tensor from mutable buffer
     pub fn new_from_buffer(values_buffer: MutableBuffer, shape: Vec<usize>, order: Order) -> Self {
-        let length = values_buffer.len() / shape.iter().product::<usize>();
-        let values_builder = PrimitiveBuilder::new_from_buffer(values_buffer, None);
+        let values_builder = PrimitiveBuilder::<T>::new_from_buffer(values_buffer, None);
+        let length = values_builder.len() / shape.iter().skip(1).product::<usize>().max(1);
         Self {
             null_buffer_builder: None,
             values_builder,
@@ -69,12 +73,9 @@ impl<T: ArrowPrimitiveType> TensorArrow<T> {
         shape: Vec<usize>,
         order: Order,
     ) -> Self {
-        let length = primitive_builder.len() / shape.iter().product::<usize>();
+        let length = primitive_builder.len() / shape.iter().skip(1).product::<usize>().max(1);
         if let Some(null_buffer_builder) = null_buffer {
-            assert_eq!(
-                null_buffer_builder.len() * shape.iter().product::<usize>(),
-                primitive_builder.len()
-            )
+            assert_eq!(null_buffer_builder.len(), length)
         };
         let null_buffer_builder = null_buffer.cloned();
         Self {
@@ -130,6 +131,49 @@ impl<T: ArrowPrimitiveType> TensorArrow<T> {
     pub fn finish(&mut self) -> PrimitiveArray<T> {
         self.values_builder.finish()
     }
+    /// sample dimensions from outermost to innermost list, the last dimension
+    /// varying fastest in row major order and the first one in column major order
+    fn list_sizes(&self) -> Vec<usize> {
+        let mut sizes: Vec<usize> = self.shape.iter().skip(1).copied().collect();
+        if self.order == Order::ColumnMajor {
+            sizes.reverse();
+        }
+        sizes
+    }
+    /// returns the arrow data type of the samples as nested fixed size lists
+    pub fn nested_data_type(&self) -> DataType {
+        self.list_sizes()
+            .iter()
+            .rev()
+            .fold(T::DATA_TYPE, |inner, size| {
+                DataType::FixedSizeList(Arc::new(Field::new("item", inner, false)), *size as i32)
+            })
+    }
+    /// nests the values into fixed size lists, one level per sample dimension,
+    /// validity being applied to the samples
+    fn nest(&self, values: PrimitiveArray<T>) -> ArrayRef {
+        let sizes = self.list_sizes();
+        let mut array: ArrayRef = Arc::new(values);
+        for (level, size) in sizes.iter().enumerate().rev() {
+            let nulls = if level == 0 {
+                self.null_buffer_builder.clone().map(NullBuffer::new)
+            } else {
+                None
+            };
+            let field = Arc::new(Field::new("item", array.data_type().clone(), false));
+            array = Arc::new(FixedSizeListArray::new(field, *size as i32, array, nulls));
+        }
+        array
+    }
+    /// returns a finished cloned array of nested fixed size lists, one list per sample
+    pub fn finish_cloned_nested(&self) -> ArrayRef {
+        self.nest(self.finish_cloned())
+    }
+    /// returns a finished array of nested fixed size lists, one list per sample
+    pub fn finish_nested(&mut self) -> ArrayRef {
+        let values = self.finish();
+        self.nest(values)
+    }
     /// overwrite the validity array
     pub fn set_validity(&mut self, mask: &mut BooleanBufferBuilder) {
         self.null_buffer_builder = Some(mask.finish());
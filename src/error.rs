@@ -0,0 +1,52 @@
+//! mdfr error types
+//! Functions are returning anyhow Result with context, MdfError is the root cause
+//! that can be retrieved with downcast_ref::<MdfError>() to identify the kind of failure
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+/// Error kinds of file reading and parsing, a corrupt file shall not abort the process
+#[derive(Debug)]
+pub enum MdfError {
+    /// input/output error of the underlying file
+    Io(io::Error),
+    /// file or block could not be parsed, not an mdf file or unexpected structure
+    Parse(String),
+    /// block type not supported or unknown, containing the block id
+    UnsupportedBlock(String),
+    /// data block content is corrupted (decompression, checksum, lengths, etc.)
+    CorruptData(String),
+}
+
+impl fmt::Display for MdfError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MdfError::Io(e) => write!(f, "io error: {e}"),
+            MdfError::Parse(msg) => write!(f, "parse error: {msg}"),
+            MdfError::UnsupportedBlock(id) => write!(f, "unsupported block: {id}"),
+            MdfError::CorruptData(msg) => write!(f, "corrupt data: {msg}"),
+        }
+    }
+}
+
+impl Error for MdfError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            MdfError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for MdfError {
+    fn from(e: io::Error) -> Self {
+        MdfError::Io(e)
+    }
+}
+
+/// formats a block id into a readable string for error messages
+pub(crate) fn block_id_string(id: &[u8]) -> String {
+    String::from_utf8_lossy(id)
+        .trim_end_matches('\0')
+        .to_string()
+}
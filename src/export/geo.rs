@@ -0,0 +1,246 @@
+//! Exporting GPS positions of a recording as a track in GPX or KML files.
+//! Latitude, longitude and optional altitude channels are given explicitly or detected from
+//! their names and units, positions being read from channel data in memory.
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+use anyhow::{bail, Context, Result};
+use arrow::array::{Array, AsArray, Float64Array};
+use arrow::compute::cast;
+use arrow::datatypes::{DataType, Float64Type};
+use chrono::{DateTime, SecondsFormat};
+
+use crate::error::MdfError;
+use crate::mdfinfo::mdfinfo4::xml_escape;
+use crate::mdfreader::Mdf;
+
+/// latitude, longitude and altitude channels of GPS positions
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GeoChannels {
+    /// latitude in degrees
+    pub latitude: String,
+    /// longitude in degrees
+    pub longitude: String,
+    /// altitude in meters
+    pub altitude: Option<String>,
+}
+
+/// position of a track
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrackPoint {
+    /// latitude in degrees
+    pub latitude: f64,
+    /// longitude in degrees
+    pub longitude: f64,
+    /// altitude in meters
+    pub altitude: Option<f64>,
+    /// absolute time in nanoseconds since epoch, None without time master channel
+    pub time_ns: Option<i64>,
+}
+
+/// lower case words of a channel name, split on non alphanumeric characters
+fn name_words(name: &str) -> Vec<String> {
+    name.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| word.to_lowercase())
+        .collect()
+}
+
+/// score of a channel being the coordinate named full or abbreviated as one of short words,
+/// with unit among units. 0 if the name does not match
+fn coordinate_score(name: &str, unit: &str, full: &str, short: &[&str], units: &[&str]) -> u8 {
+    let words = name_words(name);
+    let name_score = if words.iter().any(|word| word.contains(full)) {
+        2
+    } else if words.iter().any(|word| short.contains(&word.as_str())) {
+        1
+    } else {
+        return 0;
+    };
+    let unit = unit.trim().to_lowercase();
+    name_score + units.contains(&unit.as_str()) as u8 * 2
+}
+
+/// best matching channel among channels with their unit and master, sharing master if given
+fn best_channel<'a>(
+    channels: &'a [(String, String, Option<String>)],
+    master: Option<&Option<String>>,
+    score: impl Fn(&str, &str) -> u8,
+) -> Option<&'a (String, String, Option<String>)> {
+    channels
+        .iter()
+        .filter(|(_, _, channel_master)| master.is_none_or(|master| master == channel_master))
+        .map(|channel| (score(&channel.0, &channel.1), channel))
+        .filter(|(score, _)| *score > 0)
+        // highest score, then first name in alphabetical order
+        .max_by(|(a, channel_a), (b, channel_b)| a.cmp(b).then(channel_b.0.cmp(&channel_a.0)))
+        .map(|(_, channel)| channel)
+}
+
+/// detects the GPS channels from their names like GPS_Latitude or gps.lon and their units
+/// in degrees and meters, latitude and longitude sharing the same master channel
+pub fn detect_geo_channels(mdf: &Mdf) -> Option<GeoChannels> {
+    let mut channels: Vec<(String, String, Option<String>)> = mdf
+        .get_channel_names_set()
+        .into_iter()
+        .map(|name| {
+            let unit = mdf
+                .get_channel_unit(&name)
+                .ok()
+                .flatten()
+                .unwrap_or_default();
+            let master = mdf.get_channel_master(&name);
+            (name, unit, master)
+        })
+        .collect();
+    channels.sort();
+    let degrees = ["deg", "°", "degree", "degrees", "decimal degrees"];
+    let (latitude, _, master) = best_channel(&channels, None, |name, unit| {
+        coordinate_score(name, unit, "latitude", &["lat"], &degrees)
+    })?;
+    let (longitude, _, _) = best_channel(&channels, Some(master), |name, unit| {
+        coordinate_score(name, unit, "longitude", &["lon", "long", "lng"], &degrees)
+    })?;
+    let altitude = best_channel(&channels, Some(master), |name, unit| {
+        coordinate_score(name, unit, "altitude", &["alt", "height"], &["m"])
+    })
+    .map(|(altitude, _, _)| altitude.clone());
+    Some(GeoChannels {
+        latitude: latitude.clone(),
+        longitude: longitude.clone(),
+        altitude,
+    })
+}
+
+/// channel data in memory in f64
+fn channel_f64(mdf: &Mdf, channel_name: &str) -> Result<Float64Array> {
+    let Some(data) = mdf
+        .get_channel_data(channel_name)
+        .filter(|data| !data.is_empty())
+    else {
+        bail!("channel {channel_name} not found or not loaded in memory");
+    };
+    let data = cast(&data.as_ref(), &DataType::Float64)
+        .with_context(|| format!("failed casting channel {channel_name} into f64"))?;
+    Ok(data.as_primitive::<Float64Type>().clone())
+}
+
+/// positions of the track, invalid positions being skipped
+pub fn track_points(mdf: &Mdf, channels: &GeoChannels) -> Result<Vec<TrackPoint>> {
+    let latitude = channel_f64(mdf, &channels.latitude)?;
+    let longitude = channel_f64(mdf, &channels.longitude)?;
+    let altitude = channels
+        .altitude
+        .as_ref()
+        .map(|altitude| channel_f64(mdf, altitude))
+        .transpose()?;
+    if longitude.len() != latitude.len()
+        || altitude
+            .as_ref()
+            .is_some_and(|altitude| altitude.len() != latitude.len())
+    {
+        bail!("GPS channels have different lengths");
+    }
+    // positions without time if master is not a time channel
+    let times = mdf.get_channel_absolute_time(&channels.latitude).ok();
+    Ok((0..latitude.len())
+        .filter(|&i| {
+            latitude.is_valid(i)
+                && longitude.is_valid(i)
+                && latitude.value(i).is_finite()
+                && longitude.value(i).is_finite()
+        })
+        .map(|i| TrackPoint {
+            latitude: latitude.value(i),
+            longitude: longitude.value(i),
+            altitude: altitude
+                .as_ref()
+                .filter(|altitude| altitude.is_valid(i) && altitude.value(i).is_finite())
+                .map(|altitude| altitude.value(i)),
+            time_ns: times
+                .as_ref()
+                .filter(|times| times.is_valid(i))
+                .map(|times| times.value(i)),
+        })
+        .collect())
+}
+
+/// creates the export file
+fn create_file(file_name: &str) -> Result<BufWriter<File>> {
+    let file = File::create(file_name)
+        .map_err(MdfError::Io)
+        .with_context(|| format!("Cannot create the file {file_name}"))?;
+    Ok(BufWriter::new(file))
+}
+
+/// track name, the recording file name
+fn track_name(mdf: &Mdf) -> String {
+    xml_escape(&mdf.get_file_name())
+}
+
+/// GPS channels given or detected
+fn geo_channels(mdf: &Mdf, channels: Option<&GeoChannels>) -> Result<GeoChannels> {
+    match channels {
+        Some(channels) => Ok(channels.clone()),
+        None => detect_geo_channels(mdf).context("no latitude and longitude channels found"),
+    }
+}
+
+/// exports the GPS positions as a GPX track, GPS channels being detected if not given
+pub fn export_to_gpx(mdf: &Mdf, file_name: &str, channels: Option<&GeoChannels>) -> Result<()> {
+    let points = track_points(mdf, &geo_channels(mdf, channels)?)?;
+    let mut writer = create_file(file_name)?;
+    writeln!(writer, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(
+        writer,
+        r#"<gpx version="1.1" creator="mdfr" xmlns="http://www.topografix.com/GPX/1/1">"#
+    )?;
+    writeln!(writer, "<trk><name>{}</name><trkseg>", track_name(mdf))?;
+    for point in &points {
+        write!(
+            writer,
+            r#"<trkpt lat="{}" lon="{}">"#,
+            point.latitude, point.longitude
+        )?;
+        if let Some(altitude) = point.altitude {
+            write!(writer, "<ele>{altitude}</ele>")?;
+        }
+        if let Some(time) = point.time_ns {
+            write!(
+                writer,
+                "<time>{}</time>",
+                DateTime::from_timestamp_nanos(time).to_rfc3339_opts(SecondsFormat::AutoSi, true)
+            )?;
+        }
+        writeln!(writer, "</trkpt>")?;
+    }
+    writeln!(writer, "</trkseg></trk>")?;
+    writeln!(writer, "</gpx>")?;
+    writer
+        .flush()
+        .with_context(|| format!("failed writing file {file_name}"))
+}
+
+/// exports the GPS positions as a KML line, GPS channels being detected if not given
+pub fn export_to_kml(mdf: &Mdf, file_name: &str, channels: Option<&GeoChannels>) -> Result<()> {
+    let points = track_points(mdf, &geo_channels(mdf, channels)?)?;
+    let mut writer = create_file(file_name)?;
+    writeln!(writer, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(writer, r#"<kml xmlns="http://www.opengis.net/kml/2.2">"#)?;
+    writeln!(writer, "<Placemark><name>{}</name>", track_name(mdf))?;
+    writeln!(writer, "<LineString><coordinates>")?;
+    for point in &points {
+        match point.altitude {
+            Some(altitude) => {
+                writeln!(writer, "{},{},{altitude}", point.longitude, point.latitude)?
+            }
+            None => writeln!(writer, "{},{}", point.longitude, point.latitude)?,
+        }
+    }
+    writeln!(writer, "</coordinates></LineString>")?;
+    writeln!(writer, "</Placemark>")?;
+    writeln!(writer, "</kml>")?;
+    writer
+        .flush()
+        .with_context(|| format!("failed writing file {file_name}"))
+}
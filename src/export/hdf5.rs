@@ -1,6 +1,6 @@
 //! Exporting mdf to hdf5 files.
 use anyhow::{Context, Error, Result};
-use arrow::array::Array;
+use arrow::array::{Array, StringArray};
 use hdf5::{
     file::File,
     types::{VarLenArray, VarLenUnicode},
@@ -10,6 +10,8 @@ use log::info;
 use ndarray::{Array as NdArray, IxDyn};
 
 use crate::mdfreader::Mdf;
+#[cfg(feature = "hdf5-mpio")]
+use crate::parallel::prelude::*;
 use crate::{
     data_holder::channel_data::ChannelData,
     mdfinfo::{
@@ -18,8 +20,6 @@ use crate::{
         MdfInfo,
     },
 };
-#[cfg(feature = "hdf5-mpio")]
-use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
 
 /// writes mdf into hdf5 file
 pub fn export_to_hdf5(mdf: &Mdf, file_name: &str, compression: Option<&str>) -> Result<(), Error> {
@@ -115,7 +115,7 @@ pub fn mdf4_cg_to_hdf5(
     cg.cn
         .par_iter()
         .try_for_each(|(_rec_pos, cn): (&i32, &Cn4)| -> Result<(), Error> {
-            if !cn.data.is_empty() {
+            if cn.is_loaded() {
                 mdf4_cn_to_hdf5(mdfinfo4, cn, compression, &group)
                     .context("failed writing dataset")?;
             }
@@ -126,7 +126,7 @@ pub fn mdf4_cg_to_hdf5(
     cg.cn
         .iter()
         .try_for_each(|(_rec_pos, cn): (&i32, &Cn4)| -> Result<(), Error> {
-            if !cn.data.is_empty() {
+            if cn.is_loaded() {
                 mdf4_cn_to_hdf5(mdfinfo4, cn, compression, &group)
                     .context("failed writing dataset")?;
             }
@@ -148,7 +148,8 @@ pub fn mdf4_cn_to_hdf5(
         Hdf5Compression::Lzf => group.new_dataset_builder().shuffle().lzf(),
         Hdf5Compression::Uncompressed => group.new_dataset_builder(),
     };
-    let dataset = convert_channel_data_into_ndarray(builder, &cn.data, &cn.unique_name)
+    let data = cn.physical_data_uncached(&mdfinfo4.sharable)?;
+    let dataset = convert_channel_data_into_ndarray(builder, &data, &cn.unique_name)
         .with_context(|| format!("failed writing channel {} dataset", cn.unique_name))?;
     // writing channel unit if existing
     if let Ok(Some(unit)) = mdfinfo4.sharable.get_tx(cn.block.cn_md_unit) {
@@ -234,7 +235,8 @@ fn mdf3_cn_to_hdf5(
         Hdf5Compression::Lzf => group.new_dataset_builder().shuffle().lzf(),
         Hdf5Compression::Uncompressed => group.new_dataset_builder(),
     };
-    let dataset = convert_channel_data_into_ndarray(builder, &cn.data, &cn.unique_name)
+    let data = cn.physical_data(&mdfinfo3.sharable)?;
+    let dataset = convert_channel_data_into_ndarray(builder, data, &cn.unique_name)
         .with_context(|| format!("failed writing channel {} dataset", cn.unique_name))?;
     // writing channel unit if existing
     if let Some(unit) = mdfinfo3._get_unit(&cn.block1.cn_cc_conversion) {
@@ -394,6 +396,9 @@ fn convert_channel_data_into_ndarray(
     match data {
         ChannelData::Int8(data) => Ok(builder.with_data(data.values_slice()).create(name)?),
         ChannelData::UInt8(data) => Ok(builder.with_data(data.values_slice()).create(name)?),
+        ChannelData::Boolean(data) => Ok(builder
+            .with_data(&data.values().iter().collect::<Vec<bool>>())
+            .create(name)?),
         ChannelData::Int16(data) => Ok(builder.with_data(data.values_slice()).create(name)?),
         ChannelData::UInt16(data) => Ok(builder.with_data(data.values_slice()).create(name)?),
         ChannelData::Int32(data) => Ok(builder.with_data(data.values_slice()).create(name)?),
@@ -433,6 +438,24 @@ fn convert_channel_data_into_ndarray(
                 .collect();
             Ok(builder.with_data(&string_vect).create(name)?)
         }
+        ChannelData::Dictionary(data) => {
+            let string_vect: Vec<VarLenUnicode> = data
+                .downcast_dict::<StringArray>()
+                .context("dictionary values are not strings")?
+                .into_iter()
+                .map(|x| match x {
+                    Some(x) => match x.parse() {
+                        Ok(s) => s,
+                        Err(e) => {
+                            info!("failed parsing value {:?}, error {}", x, e);
+                            "null".parse().unwrap()
+                        }
+                    },
+                    None => "null".parse().unwrap(),
+                })
+                .collect();
+            Ok(builder.with_data(&string_vect).create(name)?)
+        }
         ChannelData::VariableSizeByteArray(data) => {
             let bytes_vect: Vec<VarLenArray<u8>> = data
                 .finish_cloned()
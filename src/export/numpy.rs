@@ -2,7 +2,8 @@
 
 use arrow::array::{
     make_array, Array, ArrayData, Float32Builder, Float64Builder, Int16Builder, Int32Builder,
-    Int64Builder, Int8Builder, UInt16Builder, UInt32Builder, UInt64Builder, UInt8Builder,
+    Int64Builder, Int8Builder, StringArray, UInt16Builder, UInt32Builder, UInt64Builder,
+    UInt8Builder,
 };
 use arrow::pyarrow::PyArrowType;
 
@@ -43,6 +44,12 @@ impl IntoPy<PyObject> for ChannelData {
         match self {
             ChannelData::Int8(array) => array.values_slice().to_pyarray(py).into_py(py),
             ChannelData::UInt8(array) => array.values_slice().to_pyarray(py).into_py(py),
+            ChannelData::Boolean(array) => array
+                .values()
+                .iter()
+                .collect::<Vec<bool>>()
+                .to_pyarray(py)
+                .into_py(py),
             ChannelData::Int16(array) => array.values_slice().to_pyarray(py).into_py(py),
             ChannelData::UInt16(array) => array.values_slice().to_pyarray(py).into_py(py),
             ChannelData::Int32(array) => array.values_slice().to_pyarray(py).into_py(py),
@@ -128,6 +135,10 @@ impl IntoPy<PyObject> for ChannelData {
                 .iter()
                 .collect::<Option<String>>()
                 .into_py(py),
+            ChannelData::Dictionary(array) => array
+                .downcast_dict::<StringArray>()
+                .map(|labels| labels.into_iter().collect::<Vec<Option<&str>>>())
+                .into_py(py),
         }
     }
 }
@@ -138,6 +149,12 @@ impl ToPyObject for ChannelData {
         match self {
             ChannelData::Int8(array) => array.values_slice().to_pyarray(py).into_py(py),
             ChannelData::UInt8(array) => array.values_slice().to_pyarray(py).into_py(py),
+            ChannelData::Boolean(array) => array
+                .values()
+                .iter()
+                .collect::<Vec<bool>>()
+                .to_pyarray(py)
+                .into_py(py),
             ChannelData::Int16(array) => array.values_slice().to_pyarray(py).into_py(py),
             ChannelData::UInt16(array) => array.values_slice().to_pyarray(py).into_py(py),
             ChannelData::Int32(array) => array.values_slice().to_pyarray(py).into_py(py),
@@ -153,6 +170,10 @@ impl ToPyObject for ChannelData {
                 .iter()
                 .collect::<Option<String>>()
                 .to_object(py),
+            ChannelData::Dictionary(array) => array
+                .downcast_dict::<StringArray>()
+                .map(|labels| labels.into_iter().collect::<Vec<Option<&str>>>())
+                .to_object(py),
             ChannelData::VariableSizeByteArray(array) => array
                 .finish_cloned()
                 .iter()
@@ -1,8 +1,12 @@
 //! Exporting mdf to Parquet files.
-use anyhow::{Context, Error, Result};
+use crate::parallel::prelude::*;
+use anyhow::{bail, Context, Error, Result};
 use arrow::{
-    array::{Array, RecordBatch},
-    datatypes::{Field, Schema, SchemaBuilder},
+    array::{
+        Array, ArrayRef, AsArray, FixedSizeListArray, LargeListArray, RecordBatch, UInt8Array,
+    },
+    buffer::ScalarBuffer,
+    datatypes::{DataType, Field, Schema, SchemaBuilder},
 };
 use codepage::to_encoding;
 use encoding_rs::Encoding as EncodingRs;
@@ -11,15 +15,18 @@ use parquet::{
     basic::{BrotliLevel, Compression, Encoding, GzipLevel, ZstdLevel},
     file::{
         metadata::KeyValue,
-        properties::{WriterProperties, WriterVersion},
+        properties::{
+            EnabledStatistics, WriterProperties, WriterVersion, DEFAULT_MAX_ROW_GROUP_SIZE,
+        },
     },
+    schema::types::ColumnPath,
 };
-use rayon::iter::ParallelExtend;
 
 use crate::{
+    data_holder::channel_data::ChannelData,
     mdfinfo::{
         mdfinfo3::{Cg3, Cn3, MdfInfo3},
-        mdfinfo4::{Cg4, Cn4, Dg4, MdfInfo4},
+        mdfinfo4::{Cg4, Cn4, ConversionInfo, Dg4, MdfInfo4},
         MdfInfo,
     },
     mdfreader::Mdf,
@@ -33,13 +40,60 @@ use std::{
     sync::Arc,
 };
 
+/// tuning of the parquet files written
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParquetOptions {
+    /// compression: snappy, gzip, lzo, brotli, lz4, lz4raw or zstd, None for uncompressed
+    pub compression: Option<String>,
+    /// files of channel groups are written in file_name directory as <master>.parquet,
+    /// instead of next to it as <file_name>_<master>.parquet
+    pub dataset_directory: bool,
+    /// maximum number of rows of the row groups
+    pub max_row_group_size: usize,
+    /// text channels are dictionary encoded, other channels being plain encoded
+    pub dictionary_text: bool,
+    /// min and max statistics are written for each column page and chunk
+    pub statistics: bool,
+    /// byte array channels are written as lists of bytes, LIST for variable length
+    /// and FIXED_SIZE_LIST for fixed length, instead of plain binary columns
+    pub byte_arrays_as_lists: bool,
+}
+
+impl Default for ParquetOptions {
+    fn default() -> Self {
+        ParquetOptions {
+            compression: None,
+            dataset_directory: false,
+            max_row_group_size: DEFAULT_MAX_ROW_GROUP_SIZE,
+            dictionary_text: true,
+            statistics: true,
+            byte_arrays_as_lists: true,
+        }
+    }
+}
+
 /// writes mdf into parquet file
 pub fn export_to_parquet(
     mdf: &Mdf,
     file_name: &str,
     compression: Option<&str>,
 ) -> Result<(), Error> {
-    let parquet_compression = parquet_compression_from_string(compression);
+    let options = ParquetOptions {
+        compression: compression.map(String::from),
+        ..Default::default()
+    };
+    export_to_parquet_with_options(mdf, file_name, &options)
+}
+
+/// writes mdf into parquet files, one per channel group, tuned by options
+pub fn export_to_parquet_with_options(
+    mdf: &Mdf,
+    file_name: &str,
+    options: &ParquetOptions,
+) -> Result<(), Error> {
+    if options.max_row_group_size == 0 {
+        bail!("parquet maximum row group size must be strictly positive");
+    }
     match &mdf.mdf_info {
         MdfInfo::V4(mdfinfo4) => {
             mdfinfo4.dg.iter().try_for_each(
@@ -52,14 +106,8 @@ pub fn export_to_parquet(
                     if !channel_names_present_in_dg.is_empty() {
                         dg.cg.iter().try_for_each(
                             |(rec_id, cg): (&u64, &Cg4)| -> Result<(), Error> {
-                                mdf4_cg_to_parquet(
-                                    file_name,
-                                    mdfinfo4,
-                                    rec_id,
-                                    cg,
-                                    parquet_compression,
-                                )
-                                .context("failed converting Channel Group 4 to parquet")?;
+                                mdf4_cg_to_parquet(file_name, mdfinfo4, rec_id, cg, options)
+                                    .context("failed converting Channel Group 4 to parquet")?;
                                 Ok(())
                             },
                         )?;
@@ -71,7 +119,7 @@ pub fn export_to_parquet(
         MdfInfo::V3(mdfinfo3) => {
             for (_dg_block_position, dg) in mdfinfo3.dg.iter() {
                 for (rec_id, cg) in dg.cg.iter() {
-                    mdf3_cg_to_parquet(file_name, mdfinfo3, rec_id, cg, parquet_compression)
+                    mdf3_cg_to_parquet(file_name, mdfinfo3, rec_id, cg, options)
                         .context("failed converting Channel Group 3 to parquet")?;
                 }
             }
@@ -87,7 +135,10 @@ pub fn export_dataframe_to_parquet(
     file_name: &str,
     compression: Option<&str>,
 ) -> Result<(), Error> {
-    let parquet_compression = parquet_compression_from_string(compression);
+    let options = ParquetOptions {
+        compression: compression.map(String::from),
+        ..Default::default()
+    };
     match &mdf.mdf_info {
         MdfInfo::V4(mdfinfo4) => {
             if let Some((_master, dg_pos, (_cg_pos, rec_id), (_cn_pos, _rec_pos))) =
@@ -95,10 +146,9 @@ pub fn export_dataframe_to_parquet(
             {
                 if let Some(dg) = mdfinfo4.dg.get(dg_pos) {
                     if let Some(cg) = dg.cg.get(rec_id) {
-                        mdf4_cg_to_parquet(file_name, mdfinfo4, rec_id, cg, parquet_compression)
-                            .context(
-                                "failed converting Channel Group 4 to parquet containing channel",
-                            )?;
+                        mdf4_cg_to_parquet(file_name, mdfinfo4, rec_id, cg, &options).context(
+                            "failed converting Channel Group 4 to parquet containing channel",
+                        )?;
                     }
                 }
             }
@@ -109,10 +159,9 @@ pub fn export_dataframe_to_parquet(
             {
                 if let Some(dg) = mdfinfo3.dg.get(dg_pos) {
                     if let Some(cg) = dg.cg.get(rec_id) {
-                        mdf3_cg_to_parquet(file_name, mdfinfo3, rec_id, cg, parquet_compression)
-                            .context(
-                                "failed converting Channel Group 3 to parquet containing channel",
-                            )?;
+                        mdf3_cg_to_parquet(file_name, mdfinfo3, rec_id, cg, &options).context(
+                            "failed converting Channel Group 3 to parquet containing channel",
+                        )?;
                     }
                 }
             }
@@ -127,16 +176,24 @@ pub fn mdf4_cg_to_parquet(
     mdfinfo4: &MdfInfo4,
     rec_id: &u64,
     cg: &Cg4,
-    parquet_compression: Compression,
+    options: &ParquetOptions,
 ) -> Result<()> {
     let mut columns = Vec::<Arc<dyn Array>>::with_capacity(cg.channel_names.len());
     let mut fields = SchemaBuilder::with_capacity(cg.channel_names.len());
     cg.cn
         .iter()
         .try_for_each(|(_rec_pos, cn): (&i32, &Cn4)| -> Result<(), Error> {
-            if !cn.data.is_empty() {
-                fields.push(mdf4_field(mdfinfo4, cn));
-                columns.push(cn.data.finish_cloned());
+            if cn.is_loaded() {
+                let data = cn.physical_data_uncached(&mdfinfo4.sharable)?;
+                let mut column = data.finish_cloned();
+                if options.byte_arrays_as_lists {
+                    column = byte_array_to_list(column);
+                }
+                fields.push(
+                    mdf4_field(mdfinfo4, cn, &data, cg.master_channel_name.as_deref())
+                        .with_data_type(column.data_type().clone()),
+                );
+                columns.push(column);
             }
             Ok(())
         })
@@ -148,12 +205,13 @@ pub fn mdf4_cg_to_parquet(
                 .metadata_mut()
                 .insert("master_channel".to_owned(), master_channel.to_string());
         }
+        fields.metadata_mut().extend(mdf4_header_metadata(mdfinfo4));
         let finalised_arrow_schema = fields.finish();
         write_data(
             cg.master_channel_name.clone(),
             rec_id,
             file_name,
-            parquet_compression,
+            options,
             finalised_arrow_schema,
             columns,
         )
@@ -173,7 +231,7 @@ pub fn mdf3_cg_to_parquet(
     mdfinfo3: &MdfInfo3,
     rec_id: &u16,
     cg: &Cg3,
-    parquet_compression: Compression,
+    options: &ParquetOptions,
 ) -> Result<()> {
     let mut columns = Vec::<Arc<dyn Array>>::with_capacity(cg.channel_names.len());
     let mut fields = SchemaBuilder::with_capacity(cg.channel_names.len());
@@ -181,8 +239,16 @@ pub fn mdf3_cg_to_parquet(
         .iter()
         .try_for_each(|(_rec_pos, cn): (&u32, &Cn3)| -> Result<(), Error> {
             if !cn.data.is_empty() {
-                fields.push(mdf3_field(mdfinfo3, cn));
-                columns.push(cn.data.finish_cloned());
+                let data = cn.physical_data(&mdfinfo3.sharable)?;
+                let mut column = data.finish_cloned();
+                if options.byte_arrays_as_lists {
+                    column = byte_array_to_list(column);
+                }
+                fields.push(
+                    mdf3_field(mdfinfo3, cn, data, cg.master_channel_name.as_deref())
+                        .with_data_type(column.data_type().clone()),
+                );
+                columns.push(column);
             }
             Ok(())
         })
@@ -194,12 +260,13 @@ pub fn mdf3_cg_to_parquet(
                 .metadata_mut()
                 .insert("master_channel".to_owned(), master_channel.to_string());
         }
+        fields.metadata_mut().extend(mdf3_header_metadata(mdfinfo3));
         let finalised_arrow_schema = fields.finish();
         write_data(
             cg.master_channel_name.clone(),
             &(*rec_id as u64),
             file_name,
-            parquet_compression,
+            options,
             finalised_arrow_schema,
             columns,
         )
@@ -213,6 +280,30 @@ pub fn mdf3_cg_to_parquet(
     Ok(())
 }
 
+/// converts byte arrays into lists of u8, other arrays being returned unchanged
+fn byte_array_to_list(array: ArrayRef) -> ArrayRef {
+    let item = Arc::new(Field::new("item", DataType::UInt8, false));
+    if let Some(bytes) = array.as_binary_opt::<i64>() {
+        let values = UInt8Array::new(ScalarBuffer::from(bytes.values().clone()), None);
+        Arc::new(LargeListArray::new(
+            item,
+            bytes.offsets().clone(),
+            Arc::new(values),
+            bytes.nulls().cloned(),
+        ))
+    } else if let Some(bytes) = array.as_fixed_size_binary_opt() {
+        let values = UInt8Array::new(ScalarBuffer::from(bytes.values().clone()), None);
+        Arc::new(FixedSizeListArray::new(
+            item,
+            bytes.value_length(),
+            Arc::new(values),
+            bytes.nulls().cloned(),
+        ))
+    } else {
+        array
+    }
+}
+
 /// converts a clap compression string into a CompressionOptions enum
 pub fn parquet_compression_from_string(compression_option: Option<&str>) -> Compression {
     match compression_option {
@@ -232,11 +323,12 @@ pub fn parquet_compression_from_string(compression_option: Option<&str>) -> Comp
 
 /// Create parquet file name appending Channel Group's master channel
 /// Or if no master existing, add.
-/// Appending at the end of name the . parquet file extension
+/// Appending at the end of name the . parquet file extension.
+/// In dataset directory, files are only named by master channel
 #[inline]
 fn create_parquet_writer(
     file: &str,
-    compression: Compression,
+    options: &ParquetOptions,
     finalised_arrow_schema: Schema,
     master_channel: Option<String>,
     rec_id: &u64,
@@ -246,51 +338,95 @@ fn create_parquet_writer(
         Some(name) => name,
         None => rec_id.to_string(),
     };
-    master_channel_name.insert(0, '_');
-    let mut file_name = base_path
-        .file_name()
-        .context("no given file name")?
-        .to_os_string();
-    file_name.push(master_channel_name);
-    let mut buf_path = base_path.with_file_name(file_name.as_os_str());
+    let (file_name, mut buf_path) = if options.dataset_directory {
+        std::fs::create_dir_all(base_path)
+            .with_context(|| format!("Failed to create directory {:?}", base_path))?;
+        let file_name = std::ffi::OsString::from(&master_channel_name);
+        let buf_path = base_path.join(&file_name);
+        (file_name, buf_path)
+    } else {
+        master_channel_name.insert(0, '_');
+        let mut file_name = base_path
+            .file_name()
+            .context("no given file name")?
+            .to_os_string();
+        file_name.push(master_channel_name);
+        let buf_path = base_path.with_file_name(file_name.as_os_str());
+        (file_name, buf_path)
+    };
     buf_path.set_extension("parquet");
     let path = buf_path.into_boxed_path();
     let file = std::io::BufWriter::new(
         std::fs::File::create(path.clone())
             .with_context(|| format!("Failed to create file {:?}", path))?,
     );
-    let options = WriterProperties::builder()
-        .set_compression(compression)
+    // schema metadata also readable by non arrow parquet readers
+    let mut key_value_metadata = vec![KeyValue::new(
+        "file_name".to_string(),
+        file_name
+            .into_string()
+            .expect("file name contains invalid Unicode data"),
+    )];
+    let mut schema_metadata: Vec<_> = finalised_arrow_schema.metadata().iter().collect();
+    schema_metadata.sort();
+    key_value_metadata.extend(
+        schema_metadata
+            .into_iter()
+            .map(|(key, value)| KeyValue::new(key.clone(), value.clone())),
+    );
+    let statistics = if options.statistics {
+        EnabledStatistics::Page
+    } else {
+        EnabledStatistics::None
+    };
+    let mut properties = WriterProperties::builder()
+        .set_compression(parquet_compression_from_string(
+            options.compression.as_deref(),
+        ))
         .set_writer_version(WriterVersion::PARQUET_1_0)
         .set_encoding(Encoding::PLAIN)
-        .set_key_value_metadata(Some(vec![KeyValue::new(
-            "file_name".to_string(),
-            file_name
-                .into_string()
-                .expect("file name contains invalid Unicode data"),
-        )]))
-        .build();
+        .set_dictionary_enabled(false)
+        .set_max_row_group_size(options.max_row_group_size)
+        .set_statistics_enabled(statistics)
+        .set_key_value_metadata(Some(key_value_metadata));
+    if options.dictionary_text {
+        for field in finalised_arrow_schema.fields() {
+            if matches!(
+                field.data_type(),
+                DataType::Utf8 | DataType::LargeUtf8 | DataType::Dictionary(_, _)
+            ) {
+                properties = properties
+                    .set_column_dictionary_enabled(ColumnPath::from(field.name().as_str()), true);
+            }
+        }
+    }
+    let properties = properties.build();
 
     ArrowWriter::try_new(
         file,
         Arc::new(finalised_arrow_schema.clone()),
-        Some(options.clone()),
+        Some(properties.clone()),
     )
     .with_context(|| {
         format!(
             "Failed to write parquet file with schema {:?} and options {:?}",
-            finalised_arrow_schema, options
+            finalised_arrow_schema, properties
         )
     })
 }
 
 /// create mdf4 channel field
 #[inline]
-fn mdf4_field(mdfinfo4: &MdfInfo4, cn: &Cn4) -> Field {
+fn mdf4_field(
+    mdfinfo4: &MdfInfo4,
+    cn: &Cn4,
+    data: &ChannelData,
+    master_channel: Option<&str>,
+) -> Field {
     let field = Field::new(
         cn.unique_name.clone(),
-        cn.data.arrow_data_type().clone(),
-        cn.data.validity().is_some(),
+        data.arrow_data_type().clone(),
+        data.validity().is_some(),
     );
     let mut metadata = HashMap::<String, String>::new();
     if let Ok(Some(unit)) = mdfinfo4.sharable.get_tx(cn.block.cn_md_unit) {
@@ -303,21 +439,48 @@ fn mdf4_field(mdfinfo4: &MdfInfo4, cn: &Cn4) -> Field {
             metadata.insert("description".to_string(), desc);
         }
     };
+    if let Some(master_channel) = master_channel {
+        metadata.insert("master_channel".to_string(), master_channel.to_string());
+    }
+    if let Some(cc) = mdfinfo4.sharable.cc.get(&cn.block.cn_cc_conversion) {
+        if let Ok(conversion) = cc.conversion_info(&mdfinfo4.sharable) {
+            conversion_metadata(&mut metadata, &conversion);
+        }
+    }
     if cn.block.cn_type == 4 {
         metadata.insert(
             "sync_channel".to_string(),
             cn.block.cn_sync_type.to_string(),
         );
     }
+    // array dimensions of each sample
+    if data.ndim() > 1 {
+        let (shape, order) = data.shape();
+        metadata.insert(
+            "shape".to_string(),
+            shape
+                .iter()
+                .skip(1)
+                .map(|size| size.to_string())
+                .collect::<Vec<_>>()
+                .join(","),
+        );
+        metadata.insert("order".to_string(), format!("{order:?}"));
+    }
     field.with_metadata(metadata)
 }
 
 /// create mdf3 channel field
 #[inline]
-fn mdf3_field(mdfinfo3: &MdfInfo3, cn: &Cn3) -> Field {
+fn mdf3_field(
+    mdfinfo3: &MdfInfo3,
+    cn: &Cn3,
+    data: &ChannelData,
+    master_channel: Option<&str>,
+) -> Field {
     let field = Field::new(
         cn.unique_name.clone(),
-        cn.data.arrow_data_type().clone(),
+        data.arrow_data_type().clone(),
         false,
     );
     let mut metadata = HashMap::<String, String>::new();
@@ -334,16 +497,103 @@ fn mdf3_field(mdfinfo3: &MdfInfo3, cn: &Cn3) -> Field {
     if !cn.description.is_empty() {
         metadata.insert("description".to_string(), cn.description.clone());
     }
+    if let Some(master_channel) = master_channel {
+        metadata.insert("master_channel".to_string(), master_channel.to_string());
+    }
+    if let Some(conversion) = mdfinfo3.get_channel_conversion(&cn.unique_name) {
+        conversion_metadata(&mut metadata, &conversion);
+    }
     field.with_metadata(metadata)
 }
 
+/// inserts channel conversion description in field metadata: type, name,
+/// parameters or table values separated by commas and texts separated by new lines
+fn conversion_metadata(metadata: &mut HashMap<String, String>, conversion: &ConversionInfo) {
+    if conversion.conversion_type == 0 {
+        return;
+    }
+    metadata.insert(
+        "conversion_type".to_string(),
+        conversion.conversion_type.to_string(),
+    );
+    if let Some(name) = conversion.name.as_ref().filter(|name| !name.is_empty()) {
+        metadata.insert("conversion_name".to_string(), name.clone());
+    }
+    if !conversion.values.is_empty() {
+        metadata.insert(
+            "conversion_values".to_string(),
+            conversion
+                .values
+                .iter()
+                .map(|value| value.to_string())
+                .collect::<Vec<_>>()
+                .join(","),
+        );
+    }
+    if !conversion.texts.is_empty() {
+        metadata.insert(
+            "conversion_texts".to_string(),
+            conversion
+                .texts
+                .iter()
+                .map(|text| text.clone().unwrap_or_default())
+                .collect::<Vec<_>>()
+                .join("\n"),
+        );
+    }
+}
+
+/// mdf4 file header metadata: version, start time and header comments prefixed by "header."
+fn mdf4_header_metadata(mdfinfo4: &MdfInfo4) -> HashMap<String, String> {
+    let mut metadata: HashMap<String, String> = mdfinfo4
+        .sharable
+        .get_hd_comments(mdfinfo4.hd_block.hd_md_comment)
+        .into_iter()
+        .filter(|(_, value)| !value.is_empty())
+        .map(|(key, value)| (format!("header.{key}"), value))
+        .collect();
+    metadata.insert(
+        "mdf_version".to_string(),
+        mdfinfo4.id_block.id_ver.to_string(),
+    );
+    if let Ok(start_time) = mdfinfo4.hd_block.start_time() {
+        metadata.insert("start_time".to_string(), start_time.to_rfc3339());
+    }
+    metadata
+}
+
+/// mdf3 file header metadata: version, start time, author, organization, project, subject
+/// and comment prefixed by "header."
+fn mdf3_header_metadata(mdfinfo3: &MdfInfo3) -> HashMap<String, String> {
+    let hd = &mdfinfo3.hd_block;
+    let mut metadata: HashMap<String, String> = [
+        ("author", &hd.hd_author),
+        ("organization", &hd.hd_organization),
+        ("project", &hd.hd_project),
+        ("subject", &hd.hd_subject),
+        ("comment", &mdfinfo3.hd_comment),
+    ]
+    .into_iter()
+    .filter(|(_, value)| !value.is_empty())
+    .map(|(key, value)| (format!("header.{key}"), value.clone()))
+    .collect();
+    metadata.insert(
+        "mdf_version".to_string(),
+        mdfinfo3.id_block.id_ver.to_string(),
+    );
+    if let Ok(start_time) = hd.start_time() {
+        metadata.insert("start_time".to_string(), start_time.to_rfc3339());
+    }
+    metadata
+}
+
 /// Write columns and fields in parquet file
 #[inline]
 fn write_data(
     master_channel_name: Option<String>,
     rec_id: &u64,
     file_name: &str,
-    compression: Compression,
+    options: &ParquetOptions,
     fields: Schema,
     columns: Vec<Arc<dyn Array>>,
 ) -> Result<(), Error> {
@@ -351,7 +601,7 @@ fn write_data(
         .context("Failed creating recordbatch")?;
     let mut writer = create_parquet_writer(
         file_name,
-        compression,
+        options,
         fields,
         master_channel_name.clone(),
         rec_id,
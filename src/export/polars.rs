@@ -1,7 +1,14 @@
 //! this module provides methods to get directly from arrow into polars (rust or python)
 use std::sync::Arc;
 
-use arrow::array::Array;
+use anyhow::{Context, Result};
+use arrow::array::{Array, RecordBatch};
+use arrow::ffi::to_ffi;
+use polars::export::arrow::ffi::{
+    import_array_from_c, import_field_from_c, ArrowArray, ArrowSchema,
+};
+use polars::frame::DataFrame;
+use polars::series::Series;
 use pyo3::{types::PyList, PyObject, PyResult, ToPyObject};
 
 use crate::export::numpy::to_py_array;
@@ -27,3 +34,23 @@ pub fn rust_arrow_to_py_series(array: Arc<dyn Array>, name: String) -> PyResult<
         Ok(out.to_object(py))
     })
 }
+
+/// converts rust arrow record batch into rust polars DataFrame through the arrow C data interface
+pub fn record_batch_to_dataframe(batch: &RecordBatch) -> Result<DataFrame> {
+    let mut columns = Vec::with_capacity(batch.num_columns());
+    for (field, array) in batch.schema().fields().iter().zip(batch.columns()) {
+        let (ffi_array, ffi_schema) = to_ffi(&array.to_data())
+            .with_context(|| format!("failed exporting channel {} to C data", field.name()))?;
+        // SAFETY: both structs follow the same C data interface layout, ownership of the
+        // exported buffers is moved into polars that calls their release callback
+        let series = unsafe {
+            let ffi_array: ArrowArray = std::mem::transmute(ffi_array);
+            let ffi_schema: ArrowSchema = std::mem::transmute(ffi_schema);
+            let polars_field = import_field_from_c(&ffi_schema)?;
+            let polars_array = import_array_from_c(ffi_array, polars_field.data_type().clone())?;
+            Series::from_arrow(field.name(), polars_array)?
+        };
+        columns.push(series);
+    }
+    DataFrame::new(columns).context("failed building polars DataFrame")
+}
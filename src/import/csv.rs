@@ -0,0 +1,63 @@
+//! Creating mdf from csv files.
+use std::fs::File;
+use std::io::{Seek, SeekFrom};
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use arrow::array::RecordBatch;
+use arrow::csv::reader::Format;
+use arrow::csv::ReaderBuilder;
+
+use crate::import::batches_to_mdf;
+use crate::mdfreader::Mdf;
+
+/// options of csv files reading
+#[derive(Debug, Clone, PartialEq)]
+pub struct CsvOptions {
+    /// column separator
+    pub delimiter: u8,
+    /// first line contains the column names, else columns are named column_1, column_2...
+    pub has_header: bool,
+    /// column used as time master channel of the other columns, None for no master
+    pub master_channel: Option<String>,
+    /// number of records read to infer the column data types, all records if None
+    pub max_inference_records: Option<usize>,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        CsvOptions {
+            delimiter: b',',
+            has_header: true,
+            master_channel: None,
+            max_inference_records: Some(1000),
+        }
+    }
+}
+
+/// creates a mdf4 in memory with one channel per column of the csv file
+pub fn csv_to_mdf(file_name: &str, options: &CsvOptions) -> Result<Mdf> {
+    let mut file =
+        File::open(file_name).with_context(|| format!("Cannot find the file {file_name}"))?;
+    let format = Format::default()
+        .with_header(options.has_header)
+        .with_delimiter(options.delimiter);
+    let (schema, _) = format
+        .infer_schema(&mut file, options.max_inference_records)
+        .with_context(|| format!("failed inferring columns data type of {file_name}"))?;
+    file.seek(SeekFrom::Start(0))
+        .context("failed rewinding csv file")?;
+    let schema = Arc::new(schema);
+    let batches = ReaderBuilder::new(schema.clone())
+        .with_format(format)
+        .build(file)
+        .with_context(|| format!("failed reading csv file {file_name}"))?
+        .collect::<Result<Vec<RecordBatch>, _>>()
+        .with_context(|| format!("failed parsing csv file {file_name}"))?;
+    batches_to_mdf(
+        file_name,
+        schema,
+        &batches,
+        options.master_channel.as_deref(),
+    )
+}
@@ -0,0 +1,112 @@
+//! Module to create mdf files from other file formats.
+pub mod csv;
+#[cfg(feature = "parquet")]
+pub mod parquet;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use anyhow::{bail, Context, Result};
+use arrow::array::{Array, ArrayRef, AsArray, FixedSizeBinaryArray, LargeBinaryArray, RecordBatch};
+use arrow::compute::{cast, concat_batches};
+use arrow::datatypes::{DataType, Float64Type, Int64Type, Schema, TimeUnit, UInt8Type};
+
+use crate::mdfinfo::mdfinfo4::{FhBlock, MdfInfo4};
+use crate::mdfinfo::MdfInfo;
+use crate::mdfreader::Mdf;
+
+/// creates a mdf4 in memory with one channel per column of the record batches, all sharing
+/// the master channel if given. Field metadata unit and description are kept
+pub(crate) fn batches_to_mdf(
+    file_name: &str,
+    schema: Arc<Schema>,
+    batches: &[RecordBatch],
+    master_channel: Option<&str>,
+) -> Result<Mdf> {
+    if let Some(master) = master_channel {
+        if schema.field_with_name(master).is_err() {
+            bail!("master channel {master} is not a column of {file_name}");
+        }
+    }
+    let batch = concat_batches(&schema, batches)
+        .with_context(|| format!("failed concatenating record batches of {file_name}"))?;
+    let mut info = MdfInfo4::new(file_name, schema.fields().len());
+    info.fh.push(FhBlock::default());
+    let mut mdf = Mdf {
+        mdf_info: MdfInfo::V4(Box::new(info)),
+        stats: HashMap::new(),
+        master_fixes: Vec::new(),
+    };
+    // master channel first so that the other channels can refer to it
+    let mut fields: Vec<_> = schema.fields().iter().enumerate().collect();
+    fields.sort_by_key(|(_, field)| Some(field.name().as_str()) != master_channel);
+    for (index, field) in fields {
+        let name = field.name();
+        let is_master = Some(name.as_str()) == master_channel;
+        let data = to_mdf_array(batch.column(index).clone())
+            .with_context(|| format!("column {name} can not be stored in mdf"))?;
+        mdf.add_channel(
+            name.clone(),
+            data,
+            master_channel.map(String::from),
+            is_master.then_some(1),
+            is_master,
+            field.metadata().get("unit").cloned(),
+            field.metadata().get("description").cloned(),
+        )
+        .with_context(|| format!("failed adding channel {name}"))?;
+    }
+    Ok(mdf)
+}
+
+/// converts arrays into the closest data type stored by mdf: timestamps into seconds since
+/// epoch, dictionaries into their values and lists of bytes into byte arrays
+fn to_mdf_array(array: ArrayRef) -> Result<ArrayRef> {
+    let array = match array.data_type() {
+        DataType::Timestamp(unit, _) => {
+            let per_second = match unit {
+                TimeUnit::Second => 1.0,
+                TimeUnit::Millisecond => 1e3,
+                TimeUnit::Microsecond => 1e6,
+                TimeUnit::Nanosecond => 1e9,
+            };
+            let ticks = cast(&array, &DataType::Int64)?;
+            Arc::new(
+                ticks
+                    .as_primitive::<Int64Type>()
+                    .unary::<_, Float64Type>(|tick| tick as f64 / per_second),
+            )
+        }
+        DataType::Dictionary(key, value)
+            if !(**key == DataType::UInt32 && **value == DataType::Utf8) =>
+        {
+            cast(&array, value)?
+        }
+        DataType::LargeList(item) if item.data_type() == &DataType::UInt8 => {
+            let list = array.as_list::<i64>();
+            Arc::new(LargeBinaryArray::try_new(
+                list.offsets().clone(),
+                list.values()
+                    .as_primitive::<UInt8Type>()
+                    .values()
+                    .inner()
+                    .clone(),
+                list.nulls().cloned(),
+            )?)
+        }
+        DataType::FixedSizeList(item, size) if item.data_type() == &DataType::UInt8 => {
+            let list = array.as_fixed_size_list();
+            Arc::new(FixedSizeBinaryArray::try_new(
+                *size,
+                list.values()
+                    .as_primitive::<UInt8Type>()
+                    .values()
+                    .inner()
+                    .clone(),
+                list.nulls().cloned(),
+            )?)
+        }
+        _ => array,
+    };
+    Ok(array)
+}
@@ -0,0 +1,28 @@
+//! Creating mdf from Parquet files.
+use std::fs::File;
+
+use anyhow::{Context, Result};
+use arrow::array::RecordBatch;
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+use crate::import::batches_to_mdf;
+use crate::mdfreader::Mdf;
+
+/// creates a mdf4 in memory with one channel per column of the parquet file. Without
+/// given master, the master_channel of the file metadata written by mdfr is used if any
+pub fn parquet_to_mdf(file_name: &str, master_channel: Option<&str>) -> Result<Mdf> {
+    let file =
+        File::open(file_name).with_context(|| format!("Cannot find the file {file_name}"))?;
+    let builder = ParquetRecordBatchReaderBuilder::try_new(file)
+        .with_context(|| format!("failed reading parquet file {file_name}"))?;
+    let schema = builder.schema().clone();
+    let master_channel = master_channel
+        .map(String::from)
+        .or_else(|| schema.metadata().get("master_channel").cloned());
+    let batches = builder
+        .build()
+        .with_context(|| format!("failed reading parquet file {file_name}"))?
+        .collect::<Result<Vec<RecordBatch>, _>>()
+        .with_context(|| format!("failed decoding parquet file {file_name}"))?;
+    batches_to_mdf(file_name, schema, &batches, master_channel.as_deref())
+}
@@ -1,11 +1,20 @@
 //#![forbid(unsafe_code)]
+extern crate alloc;
+
+pub mod bus;
 mod c_api;
 pub mod data_holder;
+pub mod error;
 pub mod export;
+pub mod import;
 pub mod mdfinfo;
 #[cfg(feature = "numpy")]
 pub mod mdfr;
 
 pub mod mdfreader;
 pub mod mdfwriter;
+mod parallel;
+pub mod plot;
+#[cfg(any(test, feature = "test-utils"))]
+pub mod test_utils;
 mod tests;
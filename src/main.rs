@@ -1,15 +1,29 @@
 //! command line interface to load mdf file and manipulate it.
+extern crate alloc;
 extern crate clap;
 
-use clap::{Arg, Command};
+use clap::{Arg, ArgMatches, Command};
+mod bus;
 mod data_holder;
+mod error;
 mod export;
+mod import;
 mod mdfinfo;
 mod mdfreader;
 mod mdfwriter;
-use anyhow::{Context, Error, Result};
+mod parallel;
+mod plot;
+use anyhow::{bail, Context, Error, Result};
+use arrow::array::Array;
+use arrow::util::display::{ArrayFormatter, FormatOptions};
+use data_holder::decimate::DecimationMethod;
 use env_logger::Env;
-use log::info;
+use log::{info, warn};
+use mdfreader::monotonic::MonotonicFix;
+use mdfwriter::mdfwriter4::{Compression, DEFAULT_DEFLATE_LEVEL, DEFAULT_ZSTD_LEVEL};
+use std::collections::HashSet;
+use std::fs;
+use std::path::PathBuf;
 
 fn init() {
     let _ = env_logger::Builder::from_env(Env::default().default_filter_or("warn"))
@@ -17,6 +31,230 @@ fn init() {
         .try_init();
 }
 
+/// reading options from the chunk size, threads, memory budget, lazy strings, string policy,
+/// spill directory and monotonic masters arguments
+fn read_options(matches: &ArgMatches) -> mdfreader::ReadOptions {
+    let mut options = mdfreader::ReadOptions::default();
+    if let Some(chunk_size) = matches.get_one::<usize>("chunk_size") {
+        options.chunk_size = *chunk_size;
+    }
+    options.max_parallelism = matches.get_one::<usize>("threads").copied();
+    if let Some(memory_budget) = matches.get_one::<usize>("memory_budget") {
+        options.memory_budget = *memory_budget;
+    }
+    options.lazy_strings = matches.get_flag("lazy_strings");
+    options.spill_directory = matches.get_one::<String>("spill_dir").map(PathBuf::from);
+    options.string_policy = match matches
+        .get_one::<String>("string_policy")
+        .map(String::as_str)
+    {
+        Some("lossy") => mdfreader::StringPolicy::Lossy,
+        Some("latin1") => mdfreader::StringPolicy::Latin1Fallback,
+        _ => mdfreader::StringPolicy::Strict,
+    };
+    options.monotonic_masters = match matches
+        .get_one::<String>("monotonic_masters")
+        .map(String::as_str)
+    {
+        Some("sort") => Some(MonotonicFix::Sort),
+        Some("offset") => Some(MonotonicFix::Offset),
+        _ => None,
+    };
+    options
+}
+
+/// bucket reduction method of the decimate argument, min and max by default
+fn decimation_method(matches: &ArgMatches) -> DecimationMethod {
+    match matches.get_one::<String>("decimation").map(String::as_str) {
+        Some("mean") => DecimationMethod::Mean,
+        Some("nth") => DecimationMethod::Nth,
+        _ => DecimationMethod::MinMax,
+    }
+}
+
+/// channel name followed by its display name if different
+fn channel_label(mdf_file: &mdfreader::Mdf, name: &str) -> Result<String, Error> {
+    Ok(match mdf_file.get_channel_display_name(name)? {
+        Some(display_name) if display_name != name => format!("{name} ({display_name})"),
+        _ => name.to_string(),
+    })
+}
+
+/// metadata parsing options from the recover and duplicates arguments
+fn parse_options(matches: &ArgMatches) -> mdfinfo::ParseOptions {
+    mdfinfo::ParseOptions {
+        recovery: matches.get_flag("recover"),
+        duplicate_policy: match matches.get_one::<String>("duplicates").map(String::as_str) {
+            Some("error") => mdfinfo::DuplicatePolicy::Error,
+            Some("suffix-group") => mdfinfo::DuplicatePolicy::SuffixGroup,
+            Some("keep-first") => mdfinfo::DuplicatePolicy::KeepFirst,
+            _ => mdfinfo::DuplicatePolicy::SuffixSource,
+        },
+    }
+}
+
+/// data blocks compression from the compress, transpose and zstd arguments
+fn compression(matches: &ArgMatches) -> Compression {
+    if matches.get_flag("zstd") {
+        let level = DEFAULT_ZSTD_LEVEL;
+        if matches.get_flag("transpose") {
+            Compression::TransposeZstd { level }
+        } else {
+            Compression::Zstd { level }
+        }
+    } else if matches.get_flag("transpose") {
+        Compression::TransposeDeflate {
+            level: DEFAULT_DEFLATE_LEVEL,
+        }
+    } else {
+        matches.get_flag("compress").into()
+    }
+}
+
+/// converts the input file into a mdf4.2 file, mdf3 channel extensions becoming source information
+fn convert(matches: &ArgMatches) -> Result<(), Error> {
+    let input = matches
+        .get_one::<String>("input")
+        .context("Input file name missing")?;
+    let output = matches
+        .get_one::<String>("output")
+        .context("Output file name missing")?;
+    let compression = compression(matches);
+    let mut mdf_file = mdfreader::Mdf::new(input)
+        .with_context(|| format!("failed reading metadata from file {}", input))?;
+    mdf_file
+        .load_all_channels_data_in_memory()
+        .with_context(|| format!("failed reading channels data from file {}", input))?;
+    mdf_file
+        .write(output, compression)
+        .with_context(|| format!("failed converting file {} into {}", input, output))?;
+    info!(
+        "Converted file {} version {} into mdf4 file {}",
+        input,
+        mdf_file.get_version(),
+        output
+    );
+    Ok(())
+}
+
+/// plots the channels against their master in terminal or into a png file
+fn plot(matches: &ArgMatches) -> Result<(), Error> {
+    let file_name = matches
+        .get_one::<String>("file")
+        .context("File name missing")?;
+    let channel_names: Vec<String> = matches
+        .get_many::<String>("channels")
+        .context("Channel names missing")?
+        .map(|name| name.trim().to_string())
+        .collect();
+    let mut mdf_file = mdfreader::Mdf::new(file_name)
+        .with_context(|| format!("failed reading metadata from file {}", file_name))?;
+    let available_channels = mdf_file.get_channel_names_set();
+    let mut loaded_channels: HashSet<String> = HashSet::new();
+    for name in channel_names.iter() {
+        if !available_channels.contains(name) {
+            bail!("channel {} not found in file {}", name, file_name);
+        }
+        loaded_channels.insert(name.clone());
+        if let Some(master) = mdf_file.get_channel_master(name) {
+            loaded_channels.insert(master);
+        }
+    }
+    mdf_file
+        .load_channels_data_in_memory(loaded_channels)
+        .with_context(|| format!("failed reading channels data from file {}", file_name))?;
+    if let Some(output) = matches.get_one::<String>("output") {
+        let width = *matches.get_one::<usize>("width").unwrap_or(&800);
+        let height = *matches.get_one::<usize>("height").unwrap_or(&600);
+        let legend = plot::plot_png(&mdf_file, &channel_names, output, width, height)
+            .with_context(|| format!("failed plotting into file {}", output))?;
+        print!("{legend}");
+        info!("Wrote plot into file {}", output);
+    } else {
+        let width = *matches.get_one::<usize>("width").unwrap_or(&100);
+        let height = *matches.get_one::<usize>("height").unwrap_or(&30);
+        print!(
+            "{}",
+            plot::plot_terminal(&mdf_file, &channel_names, width, height)?
+        );
+    }
+    Ok(())
+}
+
+/// prints the statistics of the channels, all channels by default skipping non numeric ones
+fn stats(matches: &ArgMatches) -> Result<(), Error> {
+    let file_name = matches
+        .get_one::<String>("file")
+        .context("File name missing")?;
+    let mut mdf_file = mdfreader::Mdf::new(file_name)
+        .with_context(|| format!("failed reading metadata from file {}", file_name))?;
+    let available_channels = mdf_file.get_channel_names_set();
+    let channel_names: Vec<String> = match matches.get_many::<String>("channels") {
+        Some(names) => names.map(|name| name.trim().to_string()).collect(),
+        None => {
+            mdf_file
+                .load_all_channels_data_in_memory()
+                .with_context(|| format!("failed reading channels data from file {}", file_name))?;
+            let mut names: Vec<String> = available_channels.iter().cloned().collect();
+            names.sort();
+            names
+        }
+    };
+    let all_channels = matches.get_many::<String>("channels").is_none();
+    for name in channel_names {
+        if !available_channels.contains(&name) {
+            bail!("channel {} not found in file {}", name, file_name);
+        }
+        match mdf_file.get_channel_stats(&name) {
+            Ok(stats) => println!("{name}: {stats}"),
+            Err(error) if all_channels => info!("skipped channel {name}: {error}"),
+            Err(error) => return Err(error),
+        }
+    }
+    Ok(())
+}
+
+/// compares two files channels, metadata and data, printing the differences found
+fn diff(matches: &ArgMatches) -> Result<(), Error> {
+    let mut files = Vec::with_capacity(2);
+    for argument in ["left", "right"] {
+        let file_name = matches
+            .get_one::<String>(argument)
+            .context("File name missing")?;
+        let mut mdf_file = mdfreader::Mdf::new(file_name)
+            .with_context(|| format!("failed reading metadata from file {}", file_name))?;
+        mdf_file
+            .load_all_channels_data_in_memory()
+            .with_context(|| format!("failed reading channels data from file {}", file_name))?;
+        files.push(mdf_file);
+    }
+    let tolerance = matches.get_one::<f64>("tolerance").copied().unwrap_or(0.0);
+    let report = files[0].diff(&files[1], tolerance)?;
+    print!("{report}");
+    if !report.is_identical() {
+        bail!("files {} and {} differ", report.left, report.right);
+    }
+    Ok(())
+}
+
+/// checks the blocks structure of the file and prints the problems found
+fn check(matches: &ArgMatches) -> Result<(), Error> {
+    let file_name = matches
+        .get_one::<String>("file")
+        .context("File name missing")?;
+    let report = mdfinfo::validate::validate_file(file_name)
+        .with_context(|| format!("failed validating file {}", file_name))?;
+    print!("{report}");
+    if !report.is_valid() {
+        bail!(
+            "file {} has {} structural problems",
+            file_name,
+            report.problems.len()
+        );
+    }
+    Ok(())
+}
+
 fn main() -> Result<(), Error> {
     init();
     let matches = Command::new("mdfr")
@@ -24,6 +262,140 @@ fn main() -> Result<(), Error> {
         .version("0.1.0")
         .author("Aymeric Rateau <aymeric.rateau@gmail.com>")
         .about("reads ASAM mdf file")
+        .args_conflicts_with_subcommands(true)
+        .subcommand_negates_reqs(true)
+        .subcommand(
+            Command::new("convert")
+                .about("converts mdf file, for instance legacy mdf3, into a new mdf4.2 file")
+                .arg(
+                    Arg::new("input")
+                        .help("mdf file to convert")
+                        .required(true)
+                        .value_name("INPUT_FILE")
+                        .index(1),
+                )
+                .arg(
+                    Arg::new("output")
+                        .help("mdf4.2 file to write")
+                        .required(true)
+                        .value_name("OUTPUT_FILE")
+                        .index(2),
+                )
+                .arg(
+                    Arg::new("compress")
+                        .long("compress")
+                        .short('z')
+                        .action(clap::ArgAction::SetTrue)
+                        .help("compress data when writing the mdf4.2 file"),
+                )
+                .arg(
+                    Arg::new("transpose")
+                        .long("transpose")
+                        .action(clap::ArgAction::SetTrue)
+                        .help("compress data transposed by value size for better ratios"),
+                )
+                .arg(
+                    Arg::new("zstd")
+                        .long("zstd")
+                        .action(clap::ArgAction::SetTrue)
+                        .help("compress data with zstandard instead of deflate"),
+                ),
+        )
+        .subcommand(
+            Command::new("check")
+                .about("checks the file structure: links, block lengths, cycle counts, overlaps and texts")
+                .arg(
+                    Arg::new("file")
+                        .help("mdf file to check")
+                        .required(true)
+                        .value_name("FILE_NAME")
+                        .index(1),
+                ),
+        )
+        .subcommand(
+            Command::new("diff")
+                .about("compares channels, metadata and data of two files")
+                .arg(
+                    Arg::new("left")
+                        .help("first mdf file")
+                        .required(true)
+                        .value_name("LEFT_FILE")
+                        .index(1),
+                )
+                .arg(
+                    Arg::new("right")
+                        .help("second mdf file")
+                        .required(true)
+                        .value_name("RIGHT_FILE")
+                        .index(2),
+                )
+                .arg(
+                    Arg::new("tolerance")
+                        .long("tolerance")
+                        .num_args(1)
+                        .value_parser(clap::value_parser!(f64))
+                        .help("absolute tolerance of numeric values, 0 by default"),
+                ),
+        )
+        .subcommand(
+            Command::new("stats")
+                .about("prints channels minimum, maximum, mean, standard deviation and counts")
+                .arg(
+                    Arg::new("file")
+                        .help("mdf file to read")
+                        .required(true)
+                        .value_name("FILE_NAME")
+                        .index(1),
+                )
+                .arg(
+                    Arg::new("channels")
+                        .help("channel names, all numeric channels by default")
+                        .num_args(1..)
+                        .value_name("CHANNELS")
+                        .index(2),
+                ),
+        )
+        .subcommand(
+            Command::new("plot")
+                .about("plots channels against their master in terminal or into a png file")
+                .arg(
+                    Arg::new("file")
+                        .help("mdf file to read")
+                        .required(true)
+                        .value_name("FILE_NAME")
+                        .index(1),
+                )
+                .arg(
+                    Arg::new("channels")
+                        .help("channel names to plot")
+                        .required(true)
+                        .num_args(1..)
+                        .value_name("CHANNELS")
+                        .index(2),
+                )
+                .arg(
+                    Arg::new("output")
+                        .long("output")
+                        .short('o')
+                        .num_args(1)
+                        .value_name("PNG_FILE")
+                        .help("writes the plot into a png file instead of terminal"),
+                )
+                .arg(
+                    Arg::new("width")
+                        .long("width")
+                        .num_args(1)
+                        .value_parser(clap::value_parser!(usize))
+                        .help("plot width in characters or pixels"),
+                )
+                .arg(
+                    Arg::new("height")
+                        .long("height")
+                        .num_args(1)
+                        .value_parser(clap::value_parser!(usize))
+                        .help("plot height in characters or pixels"),
+                ),
+        )
         .arg(
             Arg::new("file")
                 .help("Sets the input file to use")
@@ -48,6 +420,18 @@ fn main() -> Result<(), Error> {
                 .action(clap::ArgAction::SetTrue)
                 .help("compress data when writing into a new mdf4.2 file"),
         )
+        .arg(
+            Arg::new("transpose")
+                .long("transpose")
+                .action(clap::ArgAction::SetTrue)
+                .help("compress data transposed by value size for better ratios"),
+        )
+        .arg(
+            Arg::new("zstd")
+                .long("zstd")
+                .action(clap::ArgAction::SetTrue)
+                .help("compress data with zstandard instead of deflate"),
+        )
         .arg(
             Arg::new("export_to_parquet")
                 .long("export_to_parquet")
@@ -82,6 +466,24 @@ fn main() -> Result<(), Error> {
                 .value_name("FILTER")
                 .help("Compression algorithm for writing data in hdf5 file, valid values are deflate and lzf. Default is uncompressed"),
         )
+        .arg(
+            Arg::new("channels")
+                .long("channels")
+                .short('c')
+                .required(false)
+                .num_args(1)
+                .value_delimiter(',')
+                .value_name("CHANNEL_NAMES")
+                .help("loads only the given comma separated channels and prints them"),
+        )
+        .arg(
+            Arg::new("channel_file")
+                .long("channel-file")
+                .required(false)
+                .num_args(1)
+                .value_name("FILE_NAME")
+                .help("loads only the channels listed in file, one name per line, and prints them"),
+        )
         .arg(
             Arg::new("info")
                 .short('i')
@@ -89,35 +491,266 @@ fn main() -> Result<(), Error> {
                 .action(clap::ArgAction::SetTrue)
                 .help("prints file information"),
         )
+        .arg(
+            Arg::new("recover")
+                .long("recover")
+                .action(clap::ArgAction::SetTrue)
+                .help("skips mdf4 blocks with broken links, sweeping file for data groups"),
+        )
+        .arg(
+            Arg::new("mapping")
+                .long("mapping")
+                .num_args(1)
+                .value_name("CSV_FILE")
+                .help("renames channels from a csv file of current and new names, written with the file"),
+        )
+        .arg(
+            Arg::new("search")
+                .long("search")
+                .short('s')
+                .num_args(1)
+                .value_name("PATTERN")
+                .help("prints the channels with name, unit, description or source matching the wildcard pattern"),
+        )
+        .arg(
+            Arg::new("sources")
+                .long("sources")
+                .action(clap::ArgAction::SetTrue)
+                .help("prints the channels grouped by source, ECU or bus"),
+        )
+        .arg(
+            Arg::new("decimate")
+                .long("decimate")
+                .num_args(1)
+                .value_name("POINTS")
+                .value_parser(clap::value_parser!(usize))
+                .help("prints the selected channels reduced to at most POINTS samples"),
+        )
+        .arg(
+            Arg::new("decimation")
+                .long("decimation")
+                .num_args(1)
+                .value_parser(["minmax", "mean", "nth"])
+                .help("reduction of the samples buckets with --decimate, minmax by default"),
+        )
+        .arg(
+            Arg::new("alignment")
+                .long("alignment")
+                .action(clap::ArgAction::SetTrue)
+                .help("prints the master channels ranges, sample rates, gaps and time jumps"),
+        )
+        .arg(
+            Arg::new("regex")
+                .long("regex")
+                .action(clap::ArgAction::SetTrue)
+                .help("search pattern is a regular expression instead of wildcards"),
+        )
+        .arg(
+            Arg::new("chunk_size")
+                .long("chunk-size")
+                .num_args(1)
+                .value_name("BYTES")
+                .value_parser(clap::value_parser!(usize))
+                .help("size of the data chunks read at once, default 524288"),
+        )
+        .arg(
+            Arg::new("threads")
+                .long("threads")
+                .num_args(1)
+                .value_parser(clap::value_parser!(usize))
+                .help("maximum number of threads reading data, default all cores"),
+        )
+        .arg(
+            Arg::new("memory_budget")
+                .long("memory-budget")
+                .num_args(1)
+                .value_name("BYTES")
+                .value_parser(clap::value_parser!(usize))
+                .help("maximum size of the mdf4 data groups read at the same time, default 1GiB"),
+        )
+        .arg(
+            Arg::new("lazy_strings")
+                .long("lazy-strings")
+                .action(clap::ArgAction::SetTrue)
+                .help("reads and decodes string channels after the other channels"),
+        )
+        .arg(
+            Arg::new("string_policy")
+                .long("string-policy")
+                .num_args(1)
+                .value_parser(["strict", "lossy", "latin1"])
+                .help("decoding of strings with invalid UTF-8, error by default"),
+        )
+        .arg(
+            Arg::new("monotonic_masters")
+                .long("monotonic-masters")
+                .num_args(1)
+                .value_parser(["sort", "offset"])
+                .help("fixes the non monotonic master channels read by sorting records or offsetting values"),
+        )
+        .arg(
+            Arg::new("spill_dir")
+                .long("spill-dir")
+                .num_args(1)
+                .value_name("DIR")
+                .help("directory of temporary files holding the data read, for files bigger than memory"),
+        )
+        .arg(
+            Arg::new("duplicates")
+                .long("duplicates")
+                .num_args(1)
+                .value_parser(["error", "suffix-source", "suffix-group", "keep-first"])
+                .help("naming of channels with duplicate names, suffixed by source by default"),
+        )
         .get_matches();
 
+    if let Some(("convert", convert_matches)) = matches.subcommand() {
+        return convert(convert_matches);
+    }
+    if let Some(("check", check_matches)) = matches.subcommand() {
+        return check(check_matches);
+    }
+    if let Some(("diff", diff_matches)) = matches.subcommand() {
+        return diff(diff_matches);
+    }
+    if let Some(("stats", stats_matches)) = matches.subcommand() {
+        return stats(stats_matches);
+    }
+    if let Some(("plot", plot_matches)) = matches.subcommand() {
+        return plot(plot_matches);
+    }
+
     let file_name = matches
         .get_one::<String>("file")
         .context("File name missing")?;
 
-    let mut mdf_file = mdfreader::Mdf::new(file_name)
+    let mut mdf_file = mdfreader::Mdf::new_with_options(file_name, &parse_options(&matches))
         .with_context(|| format!("failed reading metadata from file {}", file_name))?;
 
+    if let Some(mapping_file) = matches.get_one::<String>("mapping") {
+        mdf_file.apply_channel_mapping_file(mapping_file)?;
+    }
+
     if matches.get_flag("info") {
         println!("{:?}", mdf_file.get_master_channel_names_set());
+        for duplicate in mdf_file.get_duplicate_channels() {
+            match &duplicate.unique_name {
+                Some(unique_name) => println!(
+                    "duplicate channel {} renamed {unique_name}, {}",
+                    duplicate.name, duplicate.reason
+                ),
+                None => println!("duplicate channel {} {}", duplicate.name, duplicate.reason),
+            }
+        }
     }
 
+    if let Some(pattern) = matches.get_one::<String>("search") {
+        let found = if matches.get_flag("regex") {
+            mdf_file.search_channels_regex(pattern)?
+        } else {
+            mdf_file.search_channels(pattern)?
+        };
+        for name in found.iter() {
+            println!("{}", channel_label(&mdf_file, name)?);
+        }
+    }
+
+    if matches.get_flag("sources") {
+        for group in mdf_file.get_channels_by_source()? {
+            match &group.source {
+                Some(source) => println!("{source}: {} channels", group.channels.len()),
+                None => println!("without source: {} channels", group.channels.len()),
+            }
+            for name in group.channels.iter() {
+                println!("  {name}");
+            }
+        }
+    }
+
+    if matches.get_flag("alignment") {
+        print!("{}", mdf_file.check_alignment(10.0)?);
+    }
+
+    let mut channel_names: HashSet<String> = matches
+        .get_many::<String>("channels")
+        .map(|names| names.map(|name| name.trim().to_string()).collect())
+        .unwrap_or_default();
+    if let Some(channel_file) = matches.get_one::<String>("channel_file") {
+        let content = fs::read_to_string(channel_file)
+            .with_context(|| format!("failed reading channel list file {}", channel_file))?;
+        channel_names.extend(
+            content
+                .lines()
+                .map(|line| line.trim())
+                .filter(|line| !line.is_empty())
+                .map(|line| line.to_string()),
+        );
+    }
+    channel_names.retain(|name| !name.is_empty());
+    let selected_channels = !channel_names.is_empty();
+
+    let options = read_options(&matches);
     let mdf4_file_name = matches.get_one::<String>("write");
     let parquet_file_name = matches.get_one::<String>("export_to_parquet");
     let hdf5_file_name = matches.get_one::<String>("export_to_hdf5");
 
-    if mdf4_file_name.is_some() || parquet_file_name.is_some() || hdf5_file_name.is_some() {
+    if selected_channels {
+        let available_channels = mdf_file.get_channel_names_set();
+        for name in channel_names.difference(&available_channels) {
+            warn!("channel {} not found in file {}", name, file_name);
+        }
+        channel_names.retain(|name| available_channels.contains(name));
         mdf_file
-            .load_all_channels_data_in_memory()
+            .load_channels_data_in_memory_with_options(channel_names.clone(), &options)
+            .with_context(|| format!("failed reading channels data from file {}", file_name))?;
+        info!(
+            "loaded {} channels data in memory from file {}",
+            channel_names.len(),
+            file_name
+        );
+        let mut sorted_names: Vec<&String> = channel_names.iter().collect();
+        sorted_names.sort();
+        for name in sorted_names {
+            let unit = mdf_file.get_channel_unit(name)?.unwrap_or_default();
+            print!("{} [{unit}]:", channel_label(&mdf_file, name)?);
+            match (
+                mdf_file.get_channel_data(name),
+                matches.get_one::<usize>("decimate"),
+            ) {
+                (Some(data), Some(max_points)) if !data.is_empty() => {
+                    let data = mdf_file.get_channel_data_decimated(
+                        name,
+                        *max_points,
+                        decimation_method(&matches),
+                    )?;
+                    let displayer = ArrayFormatter::try_new(&data, &FormatOptions::new())?;
+                    for i in 0..data.len() {
+                        print!(" {}", displayer.value(i));
+                    }
+                }
+                (Some(data), _) => print!("{data}"),
+                (None, _) => {}
+            }
+            println!();
+        }
+    } else if mdf4_file_name.is_some() || parquet_file_name.is_some() || hdf5_file_name.is_some() {
+        mdf_file
+            .load_channels_data_in_memory_with_options(mdf_file.get_channel_names_set(), &options)
             .with_context(|| format!("failed reading channels data from file {}", file_name))?;
         info!("loaded all channels data in memory from file {}", file_name);
     }
+    for fix in mdf_file.get_master_fixes() {
+        warn!("fixed non monotonic master channel {fix}");
+    }
 
-    let compression = matches.get_flag("compress");
+    let compression = compression(&matches);
     if let Some(file_name) = mdf4_file_name {
         mdf_file.write(file_name, compression)?;
-        if compression {
-            info!("Wrote mdf4 file {} with compression", file_name);
+        if compression != Compression::None {
+            info!(
+                "Wrote mdf4 file {} with compression {:?}",
+                file_name, compression
+            );
         } else {
             info!("Wrote mdf4 file {} without compression", file_name);
         }
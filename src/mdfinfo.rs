@@ -4,37 +4,50 @@
 use anyhow::Error;
 use anyhow::{bail, Context, Result};
 use arrow::array::Array;
-use binrw::{binrw, BinReaderExt};
+use binrw::binrw;
+use chrono::{DateTime, FixedOffset};
 use codepage::to_encoding;
 use encoding_rs::Encoding;
-use log::info;
+use log::{info, warn};
+use regex::Regex;
+use std::borrow::Cow;
 use std::collections::HashMap;
 use std::collections::HashSet;
 use std::fmt;
 use std::fs::{File, OpenOptions};
-use std::io::Read;
+use std::io::{Read, Seek, SeekFrom};
 use std::path::PathBuf;
 use std::str;
 use std::sync::Arc;
 
+pub mod core4;
 pub mod mdfinfo3;
 pub mod mdfinfo4;
+pub mod recovery4;
 pub mod sym_buf_reader;
+pub mod validate;
 
 use binrw::io::Cursor;
-use mdfinfo3::{hd3_comment_parser, hd3_parser, parse_dg3, MdfInfo3, SharableBlocks3};
+use mdfinfo3::{
+    hd3_comment_parser, hd3_parser, hd3_program_parser, parse_dg3, MdfInfo3, SharableBlocks3,
+};
 use mdfinfo4::{
-    build_channel_db, hd4_parser, parse_at4, parse_dg4, parse_ev4, parse_fh, MdfInfo4,
-    SharableBlocks,
+    build_channel_db, hd4_parser, parse_at4, parse_ch4, parse_dg4, parse_ev4, parse_fh,
+    ConversionInfo, MdfInfo4, SharableBlocks, SourceInfo,
 };
 
 use crate::data_holder::channel_data::ChannelData;
+use crate::error::{block_id_string, MdfError};
 use crate::mdfwriter::mdfwriter3::convert3to4;
 
+use self::core4::id_block;
 use self::mdfinfo3::build_channel_db3;
-use self::mdfinfo4::{At4Block, Ev4Block, FhBlock};
+use self::mdfinfo4::{
+    At4Block, ChannelTreeNode, Ev4Block, Event, FhBlock, FileHistory, SyncChannel,
+};
+use self::recovery4::recover_dg4;
 use self::sym_buf_reader::SymBufReader;
-use crate::mdfreader::{DataSignature, MasterSignature};
+use crate::mdfreader::{AnonymizeOptions, DataSignature, HeaderOptions, MasterSignature};
 
 /// joins mdf versions 3.x and 4.x
 #[derive(Debug)]
@@ -44,6 +57,69 @@ pub enum MdfInfo {
     V4(Box<MdfInfo4>), // version 4.x
 }
 
+/// on disk layout of the records of a channel group
+#[derive(Debug, Clone, PartialEq)]
+pub struct GroupLayout {
+    /// record length in bytes, including record id and invalidation bytes
+    pub record_length: u64,
+    /// number of records of the channel group
+    pub cycle_count: u64,
+    /// channels sorted by their position in record
+    pub channels: Vec<ChannelLayout>,
+}
+
+/// position and encoding of a channel in the channel group records
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChannelLayout {
+    /// unique channel name
+    pub name: String,
+    /// offset in bytes of the channel from the start of record, after the record id
+    pub byte_offset: u32,
+    /// offset of the first bit of the channel in its first byte
+    pub bit_offset: u8,
+    /// number of bits of the channel value
+    pub bit_count: u32,
+    /// data type code of the channel block, as specified by the file mdf version
+    pub data_type: u8,
+}
+
+/// naming of the channels whose name is already used by another channel of the file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DuplicatePolicy {
+    /// metadata parsing fails
+    Error,
+    /// name suffixed by the channel sources, completed by the channel block position
+    /// if still not unique
+    #[default]
+    SuffixSource,
+    /// name suffixed by the channel group acquisition name or block position
+    SuffixGroup,
+    /// only the first channel is listed, the next ones are dropped. Master channels are
+    /// suffixed by source instead as their channel group depends on them
+    KeepFirst,
+}
+
+/// channel renamed or dropped because its name is used by another channel
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuplicateChannel {
+    /// channel name in the file
+    pub name: String,
+    /// name under which the channel is listed, None if dropped
+    pub unique_name: Option<String>,
+    /// how the unique name was made
+    pub reason: String,
+}
+
+/// metadata parsing options
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ParseOptions {
+    /// mdf4 blocks with broken links are skipped and data groups rebuilt by sweeping
+    /// the file if their list is corrupted
+    pub recovery: bool,
+    /// naming of the channels with duplicate names
+    pub duplicate_policy: DuplicatePolicy,
+}
+
 /// Common Id block structure for both versions 2 and 3
 #[derive(Debug, PartialEq, Eq, Clone)]
 #[binrw]
@@ -91,6 +167,37 @@ impl Default for IdBlock {
     }
 }
 
+impl IdBlock {
+    /// sets the program identifier, padded with spaces
+    pub fn set_program(&mut self, program: &str) -> Result<()> {
+        if program.len() > 8 || !program.is_ascii() {
+            bail!("program identifier {program} is not up to 8 ascii characters");
+        }
+        self.id_prog = [b' '; 8];
+        self.id_prog[..program.len()].copy_from_slice(program.as_bytes());
+        Ok(())
+    }
+}
+
+/// in recovery mode, a block that failed parsing is replaced by its default
+/// and the reader rewound to its start
+fn recover<T: Default, R: Read + Seek>(
+    parsed: Result<(T, i64)>,
+    rdr: &mut SymBufReader<R>,
+    recovery: bool,
+) -> Result<(T, i64)> {
+    match parsed {
+        Ok(parsed) => Ok(parsed),
+        Err(error) if recovery => {
+            warn!("skipped corrupted blocks: {error:#}");
+            rdr.seek(SeekFrom::Start(0))
+                .context("Could not reach start of file")?;
+            Ok((T::default(), 0))
+        }
+        Err(error) => Err(error),
+    }
+}
+
 /// implements MdfInfo creation and manipulation functions
 #[allow(dead_code)]
 impl MdfInfo {
@@ -100,18 +207,72 @@ impl MdfInfo {
             .read(true)
             .write(false)
             .open(file_name)
+            .map_err(MdfError::Io)
             .with_context(|| format!("Cannot find the file {file_name}"))?;
         info!("Opened file {}", file_name);
-
-        let mut rdr = SymBufReader::new(&f);
+        MdfInfo::from_reader(&f, file_name)
+    }
+    /// creates new MdfInfo from file, mdf4 blocks with broken links being skipped and
+    /// data groups rebuilt by sweeping the file if their list is corrupted
+    pub fn new_with_recovery(file_name: &str) -> Result<MdfInfo, Error> {
+        let options = ParseOptions {
+            recovery: true,
+            ..Default::default()
+        };
+        MdfInfo::new_with_options(file_name, &options)
+    }
+    /// creates new MdfInfo from file with parsing options
+    pub fn new_with_options(file_name: &str, options: &ParseOptions) -> Result<MdfInfo, Error> {
+        let f: File = OpenOptions::new()
+            .read(true)
+            .write(false)
+            .open(file_name)
+            .map_err(MdfError::Io)
+            .with_context(|| format!("Cannot find the file {file_name}"))?;
+        info!("Opened file {} with options {:?}", file_name, options);
+        MdfInfo::parse(&f, file_name, options)
+    }
+    /// creates new MdfInfo from a mdf file content in memory,
+    /// file_name is only used to identify the content
+    pub fn from_bytes(bytes: &[u8], file_name: &str) -> Result<MdfInfo, Error> {
+        MdfInfo::from_reader(Cursor::new(bytes), file_name)
+    }
+    /// creates new MdfInfo from any reader of mdf file content
+    pub fn from_reader<R: Read + Seek>(reader: R, file_name: &str) -> Result<MdfInfo, Error> {
+        MdfInfo::parse(reader, file_name, &ParseOptions::default())
+    }
+    /// creates new MdfInfo from any reader of mdf file content with parsing options
+    pub fn from_reader_with_options<R: Read + Seek>(
+        reader: R,
+        file_name: &str,
+        options: &ParseOptions,
+    ) -> Result<MdfInfo, Error> {
+        MdfInfo::parse(reader, file_name, options)
+    }
+    /// parses the mdf file content, skipping corrupted mdf4 blocks in recovery mode
+    fn parse<R: Read + Seek>(
+        reader: R,
+        file_name: &str,
+        options: &ParseOptions,
+    ) -> Result<MdfInfo> {
+        let recovery = options.recovery;
+        let mut rdr = SymBufReader::new(reader);
         // Read beginning of ID Block
         let mut buf = [0u8; 64]; // reserved
         rdr.read_exact(&mut buf)
             .context("Could not read IdBlock buffer")?;
-        let mut block = Cursor::new(buf);
-        let id: IdBlock = block
-            .read_le()
-            .context("Could not parse buffer into IdBlock structure")?;
+        let id = id_block(&buf).context("Could not parse buffer into IdBlock structure")?;
+        if &id.id_file_id[..5] == "UnFin".as_bytes() {
+            bail!(MdfError::Parse(format!(
+                "{file_name} is an unfinalized mdf file, it can be finalized with Mdf::repair_unfinalized"
+            )));
+        }
+        if &id.id_file_id[..3] != "MDF".as_bytes() {
+            bail!(MdfError::Parse(format!(
+                "{file_name} is not a mdf file, file identifier is {:?}",
+                block_id_string(&id.id_file_id)
+            )));
+        }
         info!("Read IdBlock");
 
         // Depending of version different blocks
@@ -129,6 +290,8 @@ impl MdfInfo {
                 hd3_parser(&mut rdr, id.id_ver, encoding).context("failed parsing HD3 block")?;
             let (hd_comment, position) = hd3_comment_parser(&mut rdr, &hd, position, encoding)
                 .context("failed parsing HD3 block comments")?;
+            let (program_block, position) = hd3_program_parser(&mut rdr, &hd, position)
+                .context("failed parsing HD3 program block")?;
 
             // Read DG Block
             let (mut dg, _, n_cg, n_cn) = parse_dg3(
@@ -142,7 +305,9 @@ impl MdfInfo {
             .context("failed parsing mdf3 data")?;
 
             // make channel names unique, list channels and create master dictionnary
-            let channel_names_set = build_channel_db3(&mut dg, &sharable, n_cg, n_cn);
+            let (channel_names_set, duplicate_channels) =
+                build_channel_db3(&mut dg, &sharable, n_cg, n_cn, options.duplicate_policy)
+                    .context("failed listing channels")?;
 
             MdfInfo::V3(Box::new(MdfInfo3 {
                 file_name: file_name.to_string(),
@@ -150,9 +315,11 @@ impl MdfInfo {
                 encoding,
                 hd_block: hd,
                 hd_comment,
+                program_block,
                 dg,
                 sharable,
                 channel_names_set,
+                duplicate_channels,
             }))
         } else {
             let mut sharable: SharableBlocks = SharableBlocks {
@@ -162,30 +329,51 @@ impl MdfInfo {
             };
 
             // Read HD block
-            let (hd, position) =
-                hd4_parser(&mut rdr, &mut sharable).context("failed parsing HD4 block")?;
+            let parsed = hd4_parser(&mut rdr, &mut sharable).context("failed parsing HD4 block");
+            let (hd, position) = recover(parsed, &mut rdr, recovery)?;
             // parse HD metadata
             sharable.parse_hd_comments(hd.hd_md_comment);
 
             // FH block
-            let (fh, position) = parse_fh(&mut rdr, &mut sharable, hd.hd_fh_first, position)
-                .context("failed parsing File History")?;
+            let parsed = parse_fh(&mut rdr, &mut sharable, hd.hd_fh_first, position)
+                .context("failed parsing File History");
+            let (fh, position) = recover(parsed, &mut rdr, recovery)?;
 
             // AT Block read
-            let (at, position) = parse_at4(&mut rdr, &mut sharable, hd.hd_at_first, position)
-                .context("failed parsing attachments")?;
+            let parsed = parse_at4(&mut rdr, &mut sharable, hd.hd_at_first, position)
+                .context("failed parsing attachments");
+            let (at, position) = recover(parsed, &mut rdr, recovery)?;
 
             // EV Block read
-            let (ev, position) = parse_ev4(&mut rdr, &mut sharable, hd.hd_ev_first, position)
-                .context("failed parsing events")?;
+            let parsed = parse_ev4(&mut rdr, &mut sharable, hd.hd_ev_first, position)
+                .context("failed parsing events");
+            let (ev, position) = recover(parsed, &mut rdr, recovery)?;
 
-            // Read DG Block
-            let (mut dg, _, n_cg, n_cn) =
-                parse_dg4(&mut rdr, hd.hd_dg_first, position, &mut sharable)
-                    .context("failed parsing mdf4 data")?;
+            // CH Block read
+            let parsed = parse_ch4(&mut rdr, &mut sharable, hd.hd_ch_first, position)
+                .context("failed parsing channel hierarchy");
+            let (ch, position) = recover(parsed, &mut rdr, recovery)?;
+
+            // Read DG Block, swept from file in recovery mode if list is corrupted or empty
+            let (mut dg, n_cg, n_cn) =
+                match parse_dg4(&mut rdr, hd.hd_dg_first, position, &mut sharable) {
+                    Ok((dg, _, n_cg, n_cn)) if !recovery || !dg.is_empty() => (dg, n_cg, n_cn),
+                    Err(error) if !recovery => {
+                        return Err(error.context("failed parsing mdf4 data"))
+                    }
+                    parsed => {
+                        if let Err(error) = parsed {
+                            warn!("data groups list corrupted, sweeping file: {error:#}");
+                        }
+                        recover_dg4(&mut rdr, &mut sharable)
+                            .context("failed recovering mdf4 data groups")?
+                    }
+                };
 
             // make channel names unique, list channels and create master dictionnary
-            let channel_names_set = build_channel_db(&mut dg, &sharable, n_cg, n_cn);
+            let (channel_names_set, duplicate_channels) =
+                build_channel_db(&mut dg, &sharable, n_cg, n_cn, options.duplicate_policy)
+                    .context("failed listing channels")?;
 
             MdfInfo::V4(Box::new(MdfInfo4 {
                 file_name: file_name.to_string(),
@@ -194,14 +382,23 @@ impl MdfInfo {
                 fh,
                 at,
                 ev,
+                ch,
                 dg,
                 sharable,
                 channel_names_set,
+                duplicate_channels,
             }))
         };
         info!("Finished reading metadata");
         Ok(mdf_info)
     }
+    /// returns the channels renamed or dropped at parsing because of duplicate names
+    pub fn get_duplicate_channels(&self) -> &[DuplicateChannel] {
+        match self {
+            MdfInfo::V3(mdfinfo3) => &mdfinfo3.duplicate_channels,
+            MdfInfo::V4(mdfinfo4) => &mdfinfo4.duplicate_channels,
+        }
+    }
     /// gets the version of mdf file
     pub fn get_version(&self) -> u16 {
         match self {
@@ -209,6 +406,13 @@ impl MdfInfo {
             MdfInfo::V4(mdfinfo4) => mdfinfo4.id_block.id_ver,
         }
     }
+    /// returns the recording start time with its time offset
+    pub fn get_start_time(&self) -> Result<DateTime<FixedOffset>> {
+        match self {
+            MdfInfo::V3(mdfinfo3) => mdfinfo3.hd_block.start_time(),
+            MdfInfo::V4(mdfinfo4) => mdfinfo4.hd_block.start_time(),
+        }
+    }
     /// returns channel's unit string
     pub fn get_channel_unit(&self, channel_name: &str) -> Result<Option<String>> {
         let unit: Option<String> = match self {
@@ -229,6 +433,132 @@ impl MdfInfo {
         };
         Ok(desc)
     }
+    /// returns channel's display name
+    pub fn get_channel_display_name(&self, channel_name: &str) -> Result<Option<String>> {
+        match self {
+            MdfInfo::V3(mdfinfo3) => Ok(mdfinfo3.get_channel_display_name(channel_name)),
+            MdfInfo::V4(mdfinfo4) => mdfinfo4
+                .get_channel_display_name(channel_name)
+                .context("failed getting channel display name"),
+        }
+    }
+    /// returns channel's source information
+    pub fn get_channel_source(&self, channel_name: &str) -> Result<Option<SourceInfo>> {
+        match self {
+            MdfInfo::V3(mdfinfo3) => Ok(mdfinfo3.get_channel_source(channel_name)),
+            MdfInfo::V4(mdfinfo4) => mdfinfo4
+                .get_channel_source(channel_name)
+                .context("failed getting channel source"),
+        }
+    }
+    /// returns channel's conversion description
+    pub fn get_channel_conversion(&self, channel_name: &str) -> Result<Option<ConversionInfo>> {
+        match self {
+            MdfInfo::V3(mdfinfo3) => Ok(mdfinfo3.get_channel_conversion(channel_name)),
+            MdfInfo::V4(mdfinfo4) => mdfinfo4
+                .get_channel_conversion(channel_name)
+                .context("failed getting channel conversion"),
+        }
+    }
+    /// returns the bit masks and flag names of channel's bitfield text table conversion
+    pub fn get_channel_bitfield_flags(
+        &self,
+        channel_name: &str,
+    ) -> Result<Option<Vec<(u64, String)>>> {
+        match self {
+            MdfInfo::V3(_) => Ok(None),
+            MdfInfo::V4(mdfinfo4) => mdfinfo4
+                .get_channel_bitfield_flags(channel_name)
+                .context("failed getting channel bitfield flags"),
+        }
+    }
+    /// returns the record layout of the channel group containing the channel
+    pub fn get_group_layout(&self, channel_name: &str) -> Option<GroupLayout> {
+        match self {
+            MdfInfo::V3(mdfinfo3) => mdfinfo3.get_group_layout(channel_name),
+            MdfInfo::V4(mdfinfo4) => mdfinfo4.get_group_layout(channel_name),
+        }
+    }
+    /// returns the unique names of the members of a structure channel
+    pub fn get_channel_struct_members(&self, channel_name: &str) -> Option<Vec<String>> {
+        match self {
+            MdfInfo::V3(_) => None,
+            MdfInfo::V4(mdfinfo4) => mdfinfo4.get_channel_struct_members(channel_name),
+        }
+    }
+    /// returns channel's value to text table, raw value to label
+    pub fn get_channel_enum_map(&self, channel_name: &str) -> Option<HashMap<i64, String>> {
+        match self.get_channel_conversion(channel_name) {
+            Ok(conversion) => conversion?.enum_map(),
+            Err(error) => {
+                warn!("{error:#}");
+                None
+            }
+        }
+    }
+    /// adds time master channels to the channel groups without master, from their record
+    /// index and the sample rate in Hz, by default the mdf3 channels sampling rate.
+    /// Returns the names of the master channels synthesized
+    pub fn synthesize_masters(&mut self, sample_rate: Option<f64>) -> Result<Vec<String>> {
+        if let Some(rate) = sample_rate {
+            if !(rate.is_finite() && rate > 0.0) {
+                bail!(MdfError::Parse(format!("invalid sample rate {rate}")));
+            }
+        }
+        match self {
+            MdfInfo::V3(mdfinfo3) => mdfinfo3
+                .synthesize_masters(sample_rate.map(|rate| 1.0 / rate))
+                .context("failed synthesizing master channels"),
+            MdfInfo::V4(mdfinfo4) => {
+                let Some(rate) = sample_rate else {
+                    bail!(MdfError::Parse(
+                        "sample rate required to synthesize mdf4 master channels".to_string()
+                    ));
+                };
+                mdfinfo4
+                    .synthesize_masters(1.0 / rate)
+                    .context("failed synthesizing master channels")
+            }
+        }
+    }
+    /// sets in memory the header properties, comment, start time and program identifier
+    pub fn set_header(&mut self, header: &HeaderOptions) -> Result<()> {
+        match self {
+            MdfInfo::V3(mdfinfo3) => mdfinfo3.set_header(header),
+            MdfInfo::V4(mdfinfo4) => mdfinfo4.set_header(header),
+        }
+    }
+    /// strips or replaces in memory header, comments, sources and attachments metadata
+    pub fn anonymize(&mut self, options: &AnonymizeOptions) {
+        match self {
+            MdfInfo::V3(mdfinfo3) => mdfinfo3.anonymize(options),
+            MdfInfo::V4(mdfinfo4) => mdfinfo4.anonymize(options),
+        }
+    }
+    /// returns the sorted channel names for which name, unit, description or source matches
+    pub fn search_channels(&self, regex: &Regex) -> Result<Vec<String>> {
+        let mut matched: Vec<String> = Vec::new();
+        for name in self.get_channel_names_set() {
+            let found = regex.is_match(&name)
+                || self
+                    .get_channel_unit(&name)?
+                    .is_some_and(|unit| regex.is_match(&unit))
+                || self
+                    .get_channel_desc(&name)?
+                    .is_some_and(|desc| regex.is_match(&desc))
+                || self.get_channel_source(&name)?.is_some_and(|source| {
+                    [source.name, source.path]
+                        .iter()
+                        .flatten()
+                        .any(|text| regex.is_match(text))
+                });
+            if found {
+                matched.push(name);
+            }
+        }
+        matched.sort();
+        Ok(matched)
+    }
     /// returns channel's associated master channel name string
     pub fn get_channel_master(&self, channel_name: &str) -> Option<String> {
         let master: Option<String> = match self {
@@ -237,6 +567,20 @@ impl MdfInfo {
         };
         master
     }
+    /// true if the channel raw data are strings to be decoded
+    pub fn is_string_channel(&self, channel_name: &str) -> bool {
+        match self {
+            MdfInfo::V3(mdfinfo3) => mdfinfo3.is_string_channel(channel_name),
+            MdfInfo::V4(mdfinfo4) => mdfinfo4.is_string_channel(channel_name),
+        }
+    }
+    /// returns the names of the channels composing the channel, mdf3 dependency only
+    pub fn get_channel_dependencies(&self, channel_name: &str) -> Vec<String> {
+        match self {
+            MdfInfo::V3(mdfinfo3) => mdfinfo3.get_channel_dependencies(channel_name),
+            MdfInfo::V4(_) => Vec::new(),
+        }
+    }
     /// returns channel's associated master channel type string
     /// 0 = None (normal data channels), 1 = Time (seconds), 2 = Angle (radians),
     /// 3 = Distance (meters), 4 = Index (zero-based index values)
@@ -304,6 +648,27 @@ impl MdfInfo {
             MdfInfo::V4(mdfinfo4) => mdfinfo4.get_channel_data(channel_name),
         }
     }
+    /// returns channel's data, spilled data being read back without being kept in memory
+    pub fn get_channel_data_uncached(&self, channel_name: &str) -> Option<Cow<'_, ChannelData>> {
+        match self {
+            MdfInfo::V3(mdfinfo3) => mdfinfo3.get_channel_data(channel_name).map(Cow::Borrowed),
+            MdfInfo::V4(mdfinfo4) => mdfinfo4.get_channel_data_uncached(channel_name),
+        }
+    }
+    /// returns channel's raw data before conversion, if kept when reading
+    pub fn get_channel_raw_data(&self, channel_name: &str) -> Option<&ChannelData> {
+        match self {
+            MdfInfo::V3(mdfinfo3) => mdfinfo3.get_channel_raw_data(channel_name),
+            MdfInfo::V4(mdfinfo4) => mdfinfo4.get_channel_raw_data(channel_name),
+        }
+    }
+    /// returns channel's text statuses of partial conversion, only existing for mdf4
+    pub fn get_channel_status(&self, channel_name: &str) -> Option<&ChannelData> {
+        match self {
+            MdfInfo::V3(_) => None,
+            MdfInfo::V4(mdfinfo4) => mdfinfo4.get_channel_status(channel_name),
+        }
+    }
     /// Adds a new channel in memory (no file modification)
     pub fn add_channel(
         &mut self,
@@ -482,6 +847,48 @@ impl MdfInfo {
             MdfInfo::V4(mdfinfo4) => Some(mdfinfo4.get_event_blocks()),
         }
     }
+    /// get events with their name, comment and scope, triggers for mdf3
+    pub fn get_events(&self) -> Result<Vec<Event>> {
+        match self {
+            MdfInfo::V3(mdfinfo3) => Ok(mdfinfo3.get_events()),
+            MdfInfo::V4(mdfinfo4) => mdfinfo4.get_events(),
+        }
+    }
+    /// returns the synchronisation channels referencing attachments, empty for mdf3
+    pub fn get_sync_channels(&self) -> Result<Vec<SyncChannel>> {
+        match self {
+            MdfInfo::V3(_) => Ok(Vec::new()),
+            MdfInfo::V4(mdfinfo4) => mdfinfo4.get_sync_channels(),
+        }
+    }
+    /// returns the channel hierarchy tree, empty for mdf3
+    pub fn get_channel_tree(&self) -> Result<Vec<ChannelTreeNode>> {
+        match self {
+            MdfInfo::V3(_) => Ok(Vec::new()),
+            MdfInfo::V4(mdfinfo4) => mdfinfo4.get_channel_tree(),
+        }
+    }
+    /// adds an event in memory
+    pub fn add_event(&mut self, event: Event) -> Result<()> {
+        match self {
+            MdfInfo::V3(_) => bail!("events are only available for mdf4 files"),
+            MdfInfo::V4(mdfinfo4) => mdfinfo4.add_event(event),
+        }
+    }
+    /// get the program block data of mdf3 files
+    pub fn get_program_block(&self) -> Option<Vec<u8>> {
+        match self {
+            MdfInfo::V3(mdfinfo3) => mdfinfo3.program_block.clone(),
+            MdfInfo::V4(_) => None,
+        }
+    }
+    /// returns the file history entries, empty for mdf3
+    pub fn get_file_history(&self) -> Result<Vec<FileHistory>> {
+        match self {
+            MdfInfo::V3(_) => Ok(Vec::new()),
+            MdfInfo::V4(mdfinfo4) => mdfinfo4.get_file_history(),
+        }
+    }
     /// get file history blocks
     pub fn get_file_history_blocks(&self) -> Option<Vec<FhBlock>> {
         match self {
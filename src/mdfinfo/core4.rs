@@ -0,0 +1,279 @@
+//! Core of mdf4 blocks structure parsing from the bytes of a file, without reader nor file
+//! access: ID, HD, DG, CG, CN and CC blocks and texts are decoded from a byte slice starting at
+//! the beginning of the file into the mdfinfo4 block types, so that tools receiving a file over
+//! a transport can decode its blocks as soon as their bytes are received.
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+
+use binrw::io::Cursor;
+use binrw::{BinRead, BinReaderExt};
+
+use crate::mdfinfo::mdfinfo4::{Blockheader4, Cc4Block, Cg4Block, Cn4Block, Dg4Block, Hd4};
+use crate::mdfinfo::IdBlock;
+
+/// position of the HD block, following the ID block
+pub const HD_POSITION: u64 = 64;
+
+/// error decoding blocks from the bytes of a file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BlockError {
+    /// the file bytes up to end are needed to decode the block
+    Incomplete { end: u64 },
+    /// block at position has an unexpected identifier
+    UnexpectedBlock { position: u64, id: [u8; 4] },
+    /// block at position can not be decoded
+    Invalid { position: u64, reason: String },
+}
+
+impl fmt::Display for BlockError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BlockError::Incomplete { end } => write!(f, "file bytes up to {end} are needed"),
+            BlockError::UnexpectedBlock { position, id } => write!(
+                f,
+                "unexpected block {} at position {position}",
+                String::from_utf8_lossy(id)
+            ),
+            BlockError::Invalid { position, reason } => {
+                write!(f, "invalid block at position {position}: {reason}")
+            }
+        }
+    }
+}
+
+impl core::error::Error for BlockError {}
+
+/// bytes of the file from position, length bytes long
+fn block_bytes(bytes: &[u8], position: u64, length: u64) -> Result<&[u8], BlockError> {
+    let end = position
+        .checked_add(length)
+        .ok_or_else(|| BlockError::Invalid {
+            position,
+            reason: "block length overflows".to_string(),
+        })?;
+    if end > bytes.len() as u64 {
+        return Err(BlockError::Incomplete { end });
+    }
+    Ok(&bytes[position as usize..end as usize])
+}
+
+/// decodes little endian structure from bytes of block at position
+fn decode<T>(bytes: &[u8], position: u64) -> Result<T, BlockError>
+where
+    T: BinRead,
+    for<'a> T::Args<'a>: Default,
+{
+    Cursor::new(bytes)
+        .read_le()
+        .map_err(|error| BlockError::Invalid {
+            position,
+            reason: error.to_string(),
+        })
+}
+
+/// block at position, with expected identifier and at least min_length bytes long, and its
+/// members: links count, links and data
+fn checked_block<'a>(
+    bytes: &'a [u8],
+    position: u64,
+    id: &[u8; 4],
+    min_length: u64,
+) -> Result<(Blockheader4, &'a [u8]), BlockError> {
+    let header = block_header4(bytes, position)?;
+    if &header.hdr_id != id {
+        return Err(BlockError::UnexpectedBlock {
+            position,
+            id: header.hdr_id,
+        });
+    }
+    if header.hdr_len < min_length {
+        return Err(BlockError::Invalid {
+            position,
+            reason: alloc::format!("block length {} is too short", header.hdr_len),
+        });
+    }
+    let block = block_bytes(bytes, position, header.hdr_len)?;
+    Ok((header, &block[16..]))
+}
+
+/// ID block of mdf3 and mdf4 files
+pub fn id_block(bytes: &[u8]) -> Result<IdBlock, BlockError> {
+    decode(block_bytes(bytes, 0, 64)?, 0)
+}
+
+/// header of the block at position
+pub fn block_header4(bytes: &[u8], position: u64) -> Result<Blockheader4, BlockError> {
+    decode(block_bytes(bytes, position, 24)?, position)
+}
+
+/// HD block at position, HD_POSITION in a file
+pub fn hd4_block(bytes: &[u8], position: u64) -> Result<Hd4, BlockError> {
+    checked_block(bytes, position, b"##HD", 104)?;
+    decode(block_bytes(bytes, position, 104)?, position)
+}
+
+/// DG block at position
+pub fn dg4_block(bytes: &[u8], position: u64) -> Result<Dg4Block, BlockError> {
+    checked_block(bytes, position, b"##DG", 64)?;
+    decode(block_bytes(bytes, position, 64)?, position)
+}
+
+/// CG block at position
+#[allow(dead_code)]
+pub fn cg4_block(bytes: &[u8], position: u64) -> Result<Cg4Block, BlockError> {
+    let (_header, members) = checked_block(bytes, position, b"##CG", 104)?;
+    cg4_members(members, position)
+}
+
+/// CG block of position from its links count, links and data
+pub(crate) fn cg4_members(members: &[u8], position: u64) -> Result<Cg4Block, BlockError> {
+    decode(members, position)
+}
+
+/// CN block at position
+#[allow(dead_code)]
+pub fn cn4_block(bytes: &[u8], position: u64) -> Result<Cn4Block, BlockError> {
+    let (_header, members) = checked_block(bytes, position, b"##CN", 160)?;
+    cn4_members(members, position)
+}
+
+/// CN block of position from its links count, links and data
+pub(crate) fn cn4_members(members: &[u8], position: u64) -> Result<Cn4Block, BlockError> {
+    decode(members, position)
+}
+
+/// CC block at position
+#[allow(dead_code)]
+pub fn cc4_block(bytes: &[u8], position: u64) -> Result<Cc4Block, BlockError> {
+    let (_header, members) = checked_block(bytes, position, b"##CC", 80)?;
+    cc4_members(members, position)
+}
+
+/// CC block of position from its links count, links and data
+pub(crate) fn cc4_members(members: &[u8], position: u64) -> Result<Cc4Block, BlockError> {
+    decode(members, position)
+}
+
+/// text of the TX or MD block at position, empty for NIL link
+#[allow(dead_code)]
+pub fn text4(bytes: &[u8], position: u64) -> Result<String, BlockError> {
+    if position == 0 {
+        return Ok(String::new());
+    }
+    let header = block_header4(bytes, position)?;
+    if &header.hdr_id != b"##TX" && &header.hdr_id != b"##MD" {
+        return Err(BlockError::UnexpectedBlock {
+            position,
+            id: header.hdr_id,
+        });
+    }
+    let block = block_bytes(bytes, position, header.hdr_len.max(24))?;
+    let text = &block[24..];
+    let end = text
+        .iter()
+        .position(|byte| *byte == 0)
+        .unwrap_or(text.len());
+    Ok(String::from_utf8_lossy(&text[..end]).into_owned())
+}
+
+/// channel of the blocks structure
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct Channel4 {
+    /// CN block position
+    pub position: u64,
+    /// channel name
+    pub name: String,
+    pub block: Cn4Block,
+}
+
+/// channel group of the blocks structure
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct ChannelGroup4 {
+    /// CG block position
+    pub position: u64,
+    pub block: Cg4Block,
+    pub channels: Vec<Channel4>,
+}
+
+/// data group of the blocks structure
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct DataGroup4 {
+    /// DG block position
+    pub position: u64,
+    pub block: Dg4Block,
+    pub channel_groups: Vec<ChannelGroup4>,
+}
+
+/// positions of a linked list of blocks starting at first, next giving the link to the
+/// following block. Lists are bounded by the number of blocks fitting in the bytes to
+/// stop on cyclic links
+#[allow(dead_code)]
+fn linked_list<F>(bytes: &[u8], first: i64, mut next: F) -> Result<Vec<u64>, BlockError>
+where
+    F: FnMut(u64) -> Result<i64, BlockError>,
+{
+    let mut positions = Vec::new();
+    let mut position = first;
+    while position > 0 {
+        if positions.len() > bytes.len() / 24 {
+            return Err(BlockError::Invalid {
+                position: position as u64,
+                reason: "cyclic list of blocks".to_string(),
+            });
+        }
+        positions.push(position as u64);
+        position = next(position as u64)?;
+    }
+    Ok(positions)
+}
+
+/// data groups, channel groups and channels of a mdf4 file, with the channel names
+#[allow(dead_code)]
+pub fn structure4(bytes: &[u8]) -> Result<Vec<DataGroup4>, BlockError> {
+    let id = id_block(bytes)?;
+    if &id.id_file_id[..3] != b"MDF" || id.id_ver < 400 {
+        return Err(BlockError::Invalid {
+            position: 0,
+            reason: "not a mdf4 file".to_string(),
+        });
+    }
+    let hd = hd4_block(bytes, HD_POSITION)?;
+    let mut data_groups = Vec::new();
+    for position in linked_list(bytes, hd.hd_dg_first, |position| {
+        Ok(dg4_block(bytes, position)?.dg_dg_next)
+    })? {
+        let block = dg4_block(bytes, position)?;
+        let mut channel_groups = Vec::new();
+        for position in linked_list(bytes, block.dg_cg_first, |position| {
+            Ok(cg4_block(bytes, position)?.cg_cg_next)
+        })? {
+            let block = cg4_block(bytes, position)?;
+            let mut channels = Vec::new();
+            for position in linked_list(bytes, block.cg_cn_first, |position| {
+                Ok(cn4_block(bytes, position)?.cn_cn_next)
+            })? {
+                let block = cn4_block(bytes, position)?;
+                channels.push(Channel4 {
+                    position,
+                    name: text4(bytes, block.cn_tx_name as u64)?,
+                    block,
+                });
+            }
+            channel_groups.push(ChannelGroup4 {
+                position,
+                block,
+                channels,
+            });
+        }
+        data_groups.push(DataGroup4 {
+            position,
+            block,
+            channel_groups,
+        });
+    }
+    Ok(data_groups)
+}
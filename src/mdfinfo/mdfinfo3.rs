@@ -1,22 +1,31 @@
 //! Parsing of file metadata into MdfInfo3 struct
-use anyhow::{Context, Error, Result};
-use arrow::array::{UInt16Builder, UInt32Builder, UInt8Builder};
+use anyhow::{bail, Context, Error, Result};
+use arrow::array::{AsArray, BooleanArray, UInt16Builder, UInt32Builder, UInt8Builder};
+use arrow::compute::{cast, filter};
+use arrow::datatypes::{DataType, Float64Type};
 use binrw::{BinRead, BinReaderExt};
 use byteorder::{LittleEndian, ReadBytesExt};
-use chrono::NaiveDate;
+use chrono::{DateTime, Datelike, FixedOffset, NaiveDate, Timelike};
 use encoding_rs::Encoding;
-use log::info;
+use log::{info, warn};
 use std::cmp::Ordering;
 use std::collections::{BTreeMap, HashMap, HashSet};
 use std::default::Default;
 use std::fmt;
-use std::fs::File;
 use std::io::{prelude::*, Cursor};
+use std::sync::OnceLock;
 
-use crate::data_holder::channel_data::{data_type_init, ChannelData};
+use crate::data_holder::channel_data::{data_type_init, try_from, ChannelData};
 use crate::data_holder::tensor_arrow::Order;
-use crate::mdfinfo::IdBlock;
+use crate::error::MdfError;
+use crate::mdfinfo::{ChannelLayout, DuplicateChannel, DuplicatePolicy, GroupLayout, IdBlock};
+use crate::mdfreader::conversions3::convert_channel;
+use crate::mdfreader::{AnonymizeOptions, HeaderOptions};
 
+use super::mdfinfo4::{
+    position_generator, time_data, ConversionInfo, Event, EventScope, EventType, SharableBlocks,
+    Si4Block, SourceInfo,
+};
 use super::sym_buf_reader::SymBufReader;
 
 /// Specific to version 3.x mdf metadata structure
@@ -33,15 +42,21 @@ pub struct MdfInfo3 {
     pub hd_block: Hd3,
     /// Header comments
     pub hd_comment: String,
+    /// program specific data of the PR block, logger configuration for instance
+    pub program_block: Option<Vec<u8>>,
     /// data group block linking channel group/channel/conversion/..etc. and data block
     pub dg: BTreeMap<u32, Dg3>,
     /// Conversion and CE blocks
     pub sharable: SharableBlocks3,
     /// set of all channel names
     pub channel_names_set: ChannelNamesSet3,
+    /// channels renamed or dropped because of duplicate names
+    pub duplicate_channels: Vec<DuplicateChannel>,
 }
 
 pub(crate) type ChannelId3 = (Option<String>, u32, (u32, u16), u32);
+/// channel type of synthesized time master channels, not in records
+pub(crate) const VIRTUAL_MASTER: u16 = 3;
 pub(crate) type ChannelNamesSet3 = HashMap<String, ChannelId3>;
 
 /// MdfInfo3's implementation
@@ -91,6 +106,99 @@ impl MdfInfo3 {
         }
         desc
     }
+    /// returns the channel's display name, None if not defined
+    pub fn get_channel_display_name(&self, channel_name: &str) -> Option<String> {
+        let (_master, dg_pos, (_cg_pos, rec_id), cn_pos) = self.get_channel_id(channel_name)?;
+        let cn = self.dg.get(dg_pos)?.cg.get(rec_id)?.cn.get(cn_pos)?;
+        Some(cn.display_name.clone()).filter(|name| !name.is_empty())
+    }
+    /// returns the channel's source information from its extension block
+    pub fn get_channel_source(&self, channel_name: &str) -> Option<SourceInfo> {
+        let (_master, dg_pos, (_cg_pos, rec_id), cn_pos) = self.get_channel_id(channel_name)?;
+        let cn = self.dg.get(dg_pos)?.cg.get(rec_id)?.cn.get(cn_pos)?;
+        let ce = self.sharable.ce.get(&cn.block1.cn_ce_source)?;
+        match ce.ce_extension {
+            CeSupplement::None => None,
+            _ => Some(ce.source_info()),
+        }
+    }
+    /// returns the channel's raw data before conversion, if kept when reading
+    pub fn get_channel_raw_data(&self, channel_name: &str) -> Option<&ChannelData> {
+        let (_master, dg_pos, (_cg_pos, rec_id), cn_pos) = self.get_channel_id(channel_name)?;
+        let cn = self.dg.get(dg_pos)?.cg.get(rec_id)?.cn.get(cn_pos)?;
+        if cn.conversion_cache.is_some() {
+            Some(&cn.data)
+        } else {
+            cn.raw_data.as_ref()
+        }
+    }
+    /// returns the description of the channel's conversion, None if channel has no conversion
+    pub fn get_channel_conversion(&self, channel_name: &str) -> Option<ConversionInfo> {
+        let (_master, dg_pos, (_cg_pos, rec_id), cn_pos) = self.get_channel_id(channel_name)?;
+        let cn = self.dg.get(dg_pos)?.cg.get(rec_id)?.cn.get(cn_pos)?;
+        let (block, conversion) = self.sharable.cc.get(&cn.block1.cn_cc_conversion)?;
+        let (conversion_type, values, texts) = match conversion {
+            Conversion::Identity => (0, Vec::new(), Vec::new()),
+            Conversion::Linear(values) => (1, values.clone(), Vec::new()),
+            Conversion::Rational(values) => (2, values.clone(), Vec::new()),
+            Conversion::Formula(formula) => (3, Vec::new(), vec![Some(formula.clone())]),
+            Conversion::TabularInterpolation(values) => (4, values.clone(), Vec::new()),
+            Conversion::Tabular(values) => (5, values.clone(), Vec::new()),
+            Conversion::TextTable(table) => (
+                7,
+                table.iter().map(|(value, _)| *value).collect(),
+                table.iter().map(|(_, text)| Some(text.clone())).collect(),
+            ),
+            Conversion::TextRangeTable((table, default)) => (
+                8,
+                table
+                    .iter()
+                    .flat_map(|(low, high, _)| [*low, *high])
+                    .collect(),
+                table
+                    .iter()
+                    .map(|(_, _, text)| Some(text.clone()))
+                    .chain([Some(default.clone())])
+                    .collect(),
+            ),
+            Conversion::Polynomial(values) => (12, values.clone(), Vec::new()),
+            Conversion::Exponential(values) => (13, values.clone(), Vec::new()),
+            Conversion::Logarithmic(values) => (14, values.clone(), Vec::new()),
+        };
+        Some(ConversionInfo {
+            conversion_type,
+            name: None,
+            unit: self._get_unit(&cn.block1.cn_cc_conversion),
+            values,
+            texts,
+            phy_range: (block.cc_valid_range_flags != 0)
+                .then_some((block.cc_val_range_min, block.cc_val_range_max)),
+        })
+    }
+    /// returns the record layout of the channel group containing the channel
+    pub fn get_group_layout(&self, channel_name: &str) -> Option<GroupLayout> {
+        let (_master, dg_pos, (_cg_pos, rec_id), _cn_pos) = self.get_channel_id(channel_name)?;
+        let cg = self.dg.get(dg_pos)?.cg.get(rec_id)?;
+        let mut channels: Vec<ChannelLayout> = cg
+            .cn
+            .values()
+            .map(|cn| ChannelLayout {
+                name: cn.unique_name.clone(),
+                byte_offset: (cn.block2.cn_bit_offset / 8).into(),
+                bit_offset: (cn.block2.cn_bit_offset % 8) as u8,
+                bit_count: cn.block2.cn_bit_count.into(),
+                data_type: cn.block2.cn_data_type as u8,
+            })
+            .collect();
+        channels.sort_by(|a, b| {
+            (a.byte_offset, a.bit_offset, &a.name).cmp(&(b.byte_offset, b.bit_offset, &b.name))
+        });
+        Some(GroupLayout {
+            record_length: cg.record_length.into(),
+            cycle_count: cg.block.cg_cycle_count.into(),
+            channels,
+        })
+    }
     /// returns the master channel associated to the input channel name
     pub fn get_channel_master(&self, channel_name: &str) -> Option<String> {
         let mut master = None;
@@ -100,6 +208,41 @@ impl MdfInfo3 {
         }
         master
     }
+    /// true if the channel raw data are strings to be decoded
+    pub fn is_string_channel(&self, channel_name: &str) -> bool {
+        self.get_channel_id(channel_name)
+            .and_then(|(_master, dg_pos, (_cg_pos, rec_id), cn_pos)| {
+                self.dg.get(dg_pos)?.cg.get(rec_id)?.cn.get(cn_pos)
+            })
+            .is_some_and(|cn| matches!(cn.data, ChannelData::Utf8(_)))
+    }
+    /// returns the names of the channels the channel depends on from its CD block,
+    /// in row major order. Empty if the channel has no dependency
+    pub fn get_channel_dependencies(&self, channel_name: &str) -> Vec<String> {
+        let Some(cd) = self
+            .get_channel_id(channel_name)
+            .and_then(|(_master, dg_pos, (_cg_pos, rec_id), cn_pos)| {
+                self.dg.get(dg_pos)?.cg.get(rec_id)?.cn.get(cn_pos)
+            })
+            .and_then(|cn| cn.dependency.as_ref())
+        else {
+            return Vec::new();
+        };
+        cd.cd_dependencies
+            .iter()
+            .filter_map(|(dg_pos, cg_pos, cn_pos)| {
+                self.dg
+                    .values()
+                    .find(|dg| dg.block_position == *dg_pos)?
+                    .cg
+                    .values()
+                    .find(|cg| cg.block_position == *cg_pos)?
+                    .cn
+                    .get(cn_pos)
+                    .map(|cn| cn.unique_name.clone())
+            })
+            .collect()
+    }
     /// returns type of master channel link to channel input in parameter:
     /// 0 = None (normal data channels), 1 = Time (seconds),
     pub fn get_channel_master_type(&self, channel_name: &str) -> u8 {
@@ -110,7 +253,10 @@ impl MdfInfo3 {
             if let Some(dg) = self.dg.get(dg_pos) {
                 if let Some(cg) = dg.cg.get(rec_id) {
                     if let Some(cn) = cg.cn.get(cn_pos) {
-                        master_type = cn.block1.cn_type;
+                        master_type = match cn.block1.cn_type {
+                            VIRTUAL_MASTER => 1, // time
+                            cn_type => cn_type,
+                        };
                     }
                 }
             }
@@ -168,6 +314,8 @@ impl MdfInfo3 {
                             if !cn.data.is_empty() {
                                 cn.data = cn.data.zeros(0, 0, 0, (Vec::new(), Order::RowMajor))?;
                             }
+                            cn.raw_data = None;
+                            cn.conversion_cache = None;
                         }
                     }
                 }
@@ -185,7 +333,10 @@ impl MdfInfo3 {
                 if let Some(cg) = dg.cg.get(rec_id) {
                     if let Some(cn) = cg.cn.get(cn_pos) {
                         if !cn.data.is_empty() {
-                            data = Some(&cn.data);
+                            match cn.physical_data(&self.sharable) {
+                                Ok(physical) => data = Some(physical),
+                                Err(error) => warn!("{error:#}"),
+                            }
                         }
                     }
                 }
@@ -229,7 +380,7 @@ impl MdfInfo3 {
     }
     /// Renames a channel's name in memory
     pub fn rename_channel(&mut self, channel_name: &str, new_name: &str) {
-        if let Some((master, dg_pos, (cg_pos, rec_id), cn_pos)) =
+        if let Some((mut master, dg_pos, (cg_pos, rec_id), cn_pos)) =
             self.channel_names_set.remove(channel_name)
         {
             if let Some(dg) = self.dg.get_mut(&dg_pos) {
@@ -238,18 +389,14 @@ impl MdfInfo3 {
                         cn.unique_name = new_name.to_string();
                         cg.channel_names.remove(channel_name);
                         cg.channel_names.insert(new_name.to_string());
-                        if let Some(master_name) = &master {
-                            if master_name == channel_name {
-                                cg.master_channel_name = Some(new_name.to_string());
-                                cg.channel_names.iter().for_each(|channel| {
-                                    if let Some(val) = self.channel_names_set.get_mut(channel) {
-                                        val.0 = Some(new_name.to_string());
-                                        val.1 = dg_pos;
-                                        val.2 = (cg_pos, rec_id);
-                                        val.3 = cn_pos;
-                                    }
-                                });
-                            }
+                        if master.as_deref() == Some(channel_name) {
+                            master = Some(new_name.to_string());
+                            cg.master_channel_name.clone_from(&master);
+                            cg.channel_names.iter().for_each(|channel| {
+                                if let Some(val) = self.channel_names_set.get_mut(channel) {
+                                    val.0.clone_from(&master);
+                                }
+                            });
                         }
 
                         self.channel_names_set.insert(
@@ -319,6 +466,253 @@ impl MdfInfo3 {
     }
 }
 
+/// MdfInfo3 anonymization
+impl MdfInfo3 {
+    /// sets in memory the header properties, comment, start time and program identifier
+    pub fn set_header(&mut self, header: &HeaderOptions) -> Result<()> {
+        if let Some(program) = &header.program {
+            self.id_block.set_program(program)?;
+        }
+        for (property, value) in [
+            (&mut self.hd_block.hd_author, &header.author),
+            (&mut self.hd_block.hd_project, &header.project),
+            (&mut self.hd_block.hd_subject, &header.subject),
+            (&mut self.hd_comment, &header.comment),
+        ] {
+            if let Some(value) = value {
+                property.clone_from(value);
+            }
+        }
+        if let Some(start_time) = header.start_time {
+            // local date and time with offset in hours
+            let local = start_time.naive_local();
+            self.hd_block.hd_date = (local.day(), local.month(), local.year());
+            self.hd_block.hd_time = (local.hour(), local.minute(), local.second());
+            self.hd_block.hd_start_time_ns =
+                local.and_utc().timestamp_nanos_opt().map(|t| t as u64);
+            self.hd_block.hd_time_offset =
+                Some((start_time.offset().local_minus_utc() / 3600) as i16);
+        }
+        Ok(())
+    }
+    /// strips or replaces in memory header properties, comments and channel extensions
+    pub fn anonymize(&mut self, options: &AnonymizeOptions) {
+        let replacement = options.replacement.clone().unwrap_or_default();
+        for property in [
+            &mut self.hd_block.hd_author,
+            &mut self.hd_block.hd_organization,
+            &mut self.hd_block.hd_project,
+            &mut self.hd_block.hd_subject,
+        ] {
+            property.clone_from(&replacement);
+        }
+        if options.comments {
+            self.hd_comment.clear();
+            for cn in self
+                .dg
+                .values_mut()
+                .flat_map(|dg| dg.cg.values_mut())
+                .flat_map(|cg| cg.cn.values_mut())
+            {
+                cn.comment.clear();
+                cn.description.clear();
+            }
+        }
+        if options.source_paths {
+            self.sharable.ce.clear();
+        }
+    }
+}
+
+/// MdfInfo3 triggers
+impl MdfInfo3 {
+    /// returns the trigger events of the data groups, sorted by trigger time. Pre and post
+    /// trigger times, if any, are also given as beginning and end of range events
+    pub fn get_events(&self) -> Vec<Event> {
+        let mut events: Vec<Event> = Vec::new();
+        for dg in self.dg.values() {
+            let Some(tr) = &dg.tr else {
+                continue;
+            };
+            let scope: Vec<EventScope> = dg
+                .cg
+                .values()
+                .filter_map(|cg| {
+                    cg.master_channel_name
+                        .clone()
+                        .or_else(|| cg.channel_names.iter().min().cloned())
+                })
+                .map(EventScope::ChannelGroup)
+                .collect();
+            let comment = (!tr.comment.is_empty()).then(|| tr.comment.clone());
+            for (index, trigger) in tr.events.iter().enumerate() {
+                let event = Event {
+                    name: Some(format!("trigger {index}")),
+                    comment: comment.clone(),
+                    event_type: EventType::Trigger,
+                    sync_type: 1,
+                    sync_value: trigger.time,
+                    scope: scope.clone(),
+                    ..Default::default()
+                };
+                if trigger.pre_time != 0.0 || trigger.post_time != 0.0 {
+                    let (begin, end) = trigger.window();
+                    events.push(Event {
+                        range_type: 1,
+                        sync_value: begin,
+                        ..event.clone()
+                    });
+                    events.push(Event {
+                        range_type: 2,
+                        sync_value: end,
+                        ..event.clone()
+                    });
+                }
+                events.push(event);
+            }
+        }
+        events.sort_by(|a, b| a.sync_value.total_cmp(&b.sync_value));
+        events
+    }
+    /// keeps in memory only the records with master values between t_start and t_stop of the
+    /// channel groups containing the given channels, groups without loaded master being left untouched
+    pub(crate) fn retain_time_range(
+        &mut self,
+        channel_names: &HashSet<String>,
+        t_start: f64,
+        t_stop: f64,
+    ) -> Result<()> {
+        for cg in self
+            .dg
+            .values_mut()
+            .flat_map(|dg| dg.cg.values_mut())
+            .filter(|cg| !cg.channel_names.is_disjoint(channel_names))
+        {
+            let Some(master) = cg
+                .cn
+                .values()
+                .find(|cn| Some(&cn.unique_name) == cg.master_channel_name.as_ref())
+                .filter(|cn| !cn.data.is_empty())
+            else {
+                continue;
+            };
+            let time = cast(&master.data.as_ref(), &DataType::Float64)
+                .context("failed casting master channel into float")?;
+            let mask: BooleanArray = time
+                .as_primitive::<Float64Type>()
+                .iter()
+                .map(|t| t.map(|t| t >= t_start && t <= t_stop))
+                .collect();
+            for cn in cg.cn.values_mut().filter(|cn| !cn.data.is_empty()) {
+                let data = filter(&cn.data.as_ref(), &mask).with_context(|| {
+                    format!("failed filtering channel {} in time range", cn.unique_name)
+                })?;
+                cn.data = try_from(data.as_ref())?;
+                cn.raw_data = None;
+                cn.conversion_cache = None;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// MdfInfo3 synthesized master channels
+impl MdfInfo3 {
+    /// adds a virtual time master channel to the channel groups without master, values being
+    /// the record index times the sampling period, by default the channels sampling rate.
+    /// Returns the names of the master channels synthesized
+    pub fn synthesize_masters(&mut self, sampling_period: Option<f64>) -> Result<Vec<String>> {
+        let mut synthesized: Vec<String> = Vec::new();
+        for (dg_position, dg) in self.dg.iter_mut() {
+            for (rec_id, cg) in dg.cg.iter_mut() {
+                if cg.master_channel_name.is_some() || cg.cn.is_empty() {
+                    continue;
+                }
+                let Some(period) = sampling_period.or_else(|| {
+                    cg.cn
+                        .values()
+                        .map(|cn| cn.block2.cn_sampling_rate)
+                        .find(|rate| *rate > 0.0 && rate.is_finite())
+                }) else {
+                    warn!(
+                        "no sampling rate to synthesize master of channel group {}",
+                        cg.block_position
+                    );
+                    continue;
+                };
+                let mut cc_position = u32::MAX;
+                while self.sharable.cc.contains_key(&cc_position) {
+                    cc_position -= 1;
+                }
+                let mut cc_unit = [0u8; 20];
+                cc_unit[0] = b's';
+                let cc = Cc3Block {
+                    cc_unit,
+                    cc_type: 0, // linear
+                    cc_size: 2,
+                    ..Default::default()
+                };
+                self.sharable
+                    .cc
+                    .insert(cc_position, (cc, Conversion::Linear(vec![0.0, period])));
+                let mut name = String::from("time");
+                let mut index = 1;
+                while self.channel_names_set.contains_key(&name) {
+                    name = format!("time_{index}");
+                    index += 1;
+                }
+                let loaded = cg.cn.values().any(|cn| !cn.data.is_empty());
+                let cn = Cn3 {
+                    block1: Cn3Block1 {
+                        cn_type: VIRTUAL_MASTER,
+                        cn_cc_conversion: cc_position,
+                        ..Default::default()
+                    },
+                    block2: Cn3Block2 {
+                        cn_bit_count: 64,
+                        cn_data_type: 3, // float64
+                        cn_sampling_rate: period,
+                        ..Default::default()
+                    },
+                    unique_name: name.clone(),
+                    description: String::from("synthesized time"),
+                    n_bytes: 8,
+                    data: if loaded {
+                        time_data(cg.block.cg_cycle_count as u64, period)
+                    } else {
+                        data_type_init(3, 0, 0, 1)?
+                    },
+                    channel_data_valid: loaded,
+                    ..Default::default()
+                };
+                let mut cn_position = u32::MAX;
+                while cg.cn.contains_key(&cn_position) {
+                    cn_position -= 1;
+                }
+                for channel in cg.channel_names.iter() {
+                    if let Some(id) = self.channel_names_set.get_mut(channel) {
+                        id.0 = Some(name.clone());
+                    }
+                }
+                self.channel_names_set.insert(
+                    name.clone(),
+                    (
+                        Some(name.clone()),
+                        *dg_position,
+                        (cg.block_position, *rec_id),
+                        cn_position,
+                    ),
+                );
+                cg.cn.insert(cn_position, cn);
+                cg.channel_names.insert(name.clone());
+                cg.master_channel_name = Some(name.clone());
+                synthesized.push(name);
+            }
+        }
+        Ok(synthesized)
+    }
+}
+
 /// MdfInfo3 display implementation
 impl fmt::Display for MdfInfo3 {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -351,7 +745,7 @@ pub struct Blockheader3 {
 }
 
 /// Generic block header parser
-pub fn parse_block_header(rdr: &mut SymBufReader<&File>) -> Result<Blockheader3> {
+pub fn parse_block_header<R: Read + Seek>(rdr: &mut SymBufReader<R>) -> Result<Blockheader3> {
     let header: Blockheader3 = rdr
         .read_le()
         .context("Could not read Blockheader3 struct")?;
@@ -399,6 +793,21 @@ pub struct Hd3 {
     hd_time_identifier: Option<String>,
 }
 
+impl Hd3 {
+    /// recording start time. From version 3.2, time stamp is local time with
+    /// its UTC offset in hours, before it is the date and time without offset
+    pub fn start_time(&self) -> Result<DateTime<FixedOffset>> {
+        let offset_hours = self.hd_time_offset.unwrap_or(0) as i32;
+        let offset = FixedOffset::east_opt(offset_hours * 3600)
+            .with_context(|| format!("invalid time offset of {offset_hours} hours"))?;
+        let local_ns = self.hd_start_time_ns.unwrap_or(0) as i64;
+        Ok(
+            DateTime::from_timestamp_nanos(local_ns - offset_hours as i64 * 3_600_000_000_000)
+                .with_timezone(&offset),
+        )
+    }
+}
+
 /// HD3 block structure
 #[derive(Debug, PartialEq, Eq, Default, BinRead)]
 #[repr(C)]
@@ -428,8 +837,8 @@ pub struct Hd3Block32 {
 }
 
 /// Header block parser
-pub fn hd3_parser(
-    rdr: &mut SymBufReader<&File>,
+pub fn hd3_parser<R: Read + Seek>(
+    rdr: &mut SymBufReader<R>,
     ver: u16,
     encoding: &'static Encoding,
 ) -> Result<(Hd3, i64)> {
@@ -561,20 +970,42 @@ impl fmt::Display for Hd3 {
 }
 
 /// Header comment parser
-pub fn hd3_comment_parser(
-    rdr: &mut SymBufReader<&File>,
+pub fn hd3_comment_parser<R: Read + Seek>(
+    rdr: &mut SymBufReader<R>,
     hd3_block: &Hd3,
     mut position: i64,
     encoding: &'static Encoding,
 ) -> Result<(String, i64)> {
+    if hd3_block.hd_md_comment == 0 {
+        return Ok((String::new(), position));
+    }
     let (_, comment, pos) = parse_tx(rdr, hd3_block.hd_md_comment, position, encoding)?;
     position = pos;
     Ok((comment, position))
 }
 
+/// Header program block parser, returns the program specific data if any
+pub fn hd3_program_parser<R: Read + Seek>(
+    rdr: &mut SymBufReader<R>,
+    hd3_block: &Hd3,
+    position: i64,
+) -> Result<(Option<Vec<u8>>, i64)> {
+    if hd3_block.hd_pr == 0 {
+        return Ok((None, position));
+    }
+    rdr.seek_relative(hd3_block.hd_pr as i64 - position)
+        .context("Could not reach position of PR block")?;
+    let block_header: Blockheader3 = parse_block_header(rdr)?;
+    let mut program = vec![0u8; (block_header.hdr_len as usize).saturating_sub(4)];
+    rdr.read_exact(&mut program)
+        .context("Could not read PR block data")?;
+    let position = hd3_block.hd_pr as i64 + 4 + program.len() as i64;
+    Ok((Some(program), position))
+}
+
 /// TX text block parser, contexting ISO_8859_1 encoded text
-pub fn parse_tx(
-    rdr: &mut SymBufReader<&File>,
+pub fn parse_tx<R: Read + Seek>(
+    rdr: &mut SymBufReader<R>,
     target: u32,
     position: i64,
     encoding: &'static Encoding,
@@ -616,14 +1047,14 @@ pub struct Dg3Block {
     pub dg_data: u32,
     /// number of channel groups
     dg_n_cg: u16,
-    /// number of record ids
-    dg_n_record_ids: u16,
+    /// number of record ids, 0 sorted, 1 before each record, 2 before and after each record
+    pub dg_n_record_ids: u16,
     // reserved: u32, // reserved
 }
 
 /// Data Group block parser
-pub fn parse_dg3_block(
-    rdr: &mut SymBufReader<&File>,
+pub fn parse_dg3_block<R: Read + Seek>(
+    rdr: &mut SymBufReader<R>,
     target: u32,
     position: i64,
 ) -> Result<(Dg3Block, i64)> {
@@ -639,6 +1070,97 @@ pub fn parse_dg3_block(
     Ok((block, (target + 24).into()))
 }
 
+/// Trigger block structure
+#[derive(Debug, BinRead, Clone, Default)]
+#[br(little)]
+#[allow(dead_code)]
+#[repr(C)]
+pub struct Tr3Block {
+    /// TR
+    tr_id: [u8; 2],
+    /// Length of block in bytes
+    tr_len: u16,
+    /// Pointer to trigger comment text (TXBLOCK) (can be NIL)
+    tr_tx_comment: u32,
+    /// number of trigger events
+    pub tr_n_events: u16,
+}
+
+/// Trigger event, times in seconds
+#[derive(Debug, BinRead, Clone, Copy, Default, PartialEq)]
+#[br(little)]
+#[repr(C)]
+pub struct Tr3Event {
+    /// trigger time
+    pub time: f64,
+    /// pre trigger time
+    pub pre_time: f64,
+    /// post trigger time
+    pub post_time: f64,
+}
+
+impl Tr3Event {
+    /// time window recorded around the trigger, from pre to post trigger times
+    pub fn window(&self) -> (f64, f64) {
+        (self.time - self.pre_time, self.time + self.post_time)
+    }
+}
+
+/// Tr3 struct wrapping block, comment and trigger events
+#[derive(Debug, Clone, Default)]
+#[allow(dead_code)]
+pub struct Tr3 {
+    /// TR block
+    pub block: Tr3Block,
+    /// trigger comment
+    pub comment: String,
+    /// trigger events
+    pub events: Vec<Tr3Event>,
+}
+
+/// Trigger block parser, None if target is NIL
+pub fn parse_tr3<R: Read + Seek>(
+    rdr: &mut SymBufReader<R>,
+    target: u32,
+    mut position: i64,
+    encoding: &'static Encoding,
+) -> Result<(Option<Tr3>, i64)> {
+    if target == 0 {
+        return Ok((None, position));
+    }
+    rdr.seek_relative(target as i64 - position)
+        .context("Could not reach position of Tr3 block")?;
+    let mut buf = [0u8; 10];
+    rdr.read_exact(&mut buf)
+        .context("Could not read Tr3 Block buffer")?;
+    let block: Tr3Block = Cursor::new(buf)
+        .read_le()
+        .context("Could not read buffer into Tr3Block structure")?;
+    let mut buf = vec![0u8; 24 * block.tr_n_events as usize];
+    rdr.read_exact(&mut buf)
+        .context("Could not read Tr3 events buffer")?;
+    let mut events_buf = Cursor::new(buf);
+    let events = (0..block.tr_n_events)
+        .map(|_| events_buf.read_le::<Tr3Event>())
+        .collect::<Result<Vec<Tr3Event>, _>>()
+        .context("Could not read buffer into Tr3Event structures")?;
+    position = target as i64 + 10 + 24 * block.tr_n_events as i64;
+    let mut comment = String::new();
+    if block.tr_tx_comment != 0 {
+        let (_, tx, pos) = parse_tx(rdr, block.tr_tx_comment, position, encoding)?;
+        comment = tx;
+        position = pos;
+    }
+    Ok((
+        Some(Tr3 {
+            block,
+            comment,
+            events,
+        }),
+        position,
+    ))
+}
+
 /// Dg3 struct wrapping block, comments and linked CG
 #[derive(Debug)]
 #[repr(C)]
@@ -647,13 +1169,15 @@ pub struct Dg3 {
     pub block: Dg3Block,
     /// position of block in file
     pub block_position: u32,
+    /// trigger block
+    pub tr: Option<Tr3>,
     /// CG Block
     pub cg: HashMap<u16, Cg3>,
 }
 
 /// Parser for Dg3 and all linked blocks (cg, cn, cc)
-pub fn parse_dg3(
-    rdr: &mut SymBufReader<&File>,
+pub fn parse_dg3<R: Read + Seek>(
+    rdr: &mut SymBufReader<R>,
     target: u32,
     mut position: i64,
     sharable: &mut SharableBlocks3,
@@ -665,6 +1189,7 @@ pub fn parse_dg3(
     let mut n_cg: u16 = 0;
     if target > 0 {
         let (block, pos) = parse_dg3_block(rdr, target, position)?;
+        let (tr, pos) = parse_tr3(rdr, block.dg_tr, pos, encoding)?;
         position = pos;
         let mut next_pointer = block.dg_dg_next;
         let (cg, pos, num_cn) = parse_cg3(
@@ -681,6 +1206,7 @@ pub fn parse_dg3(
         let dg_struct = Dg3 {
             block,
             block_position: target,
+            tr,
             cg,
         };
         dg.insert(dg_struct.block.dg_data, dg_struct);
@@ -689,6 +1215,7 @@ pub fn parse_dg3(
             let block_start = next_pointer;
             let (block, pos) = parse_dg3_block(rdr, next_pointer, position)?;
             next_pointer = block.dg_dg_next;
+            let (tr, pos) = parse_tr3(rdr, block.dg_tr, pos, encoding)?;
             position = pos;
             let (cg, pos, num_cn) = parse_cg3(
                 rdr,
@@ -704,6 +1231,7 @@ pub fn parse_dg3(
             let dg_struct = Dg3 {
                 block,
                 block_position: block_start,
+                tr,
                 cg,
             };
             dg.insert(dg_struct.block.dg_data, dg_struct);
@@ -742,8 +1270,8 @@ pub struct Cg3Block {
 }
 
 /// Cg3 (Channel Group) block struct parser with linked comments Source Information in sharable blocks
-fn parse_cg3_block(
-    rdr: &mut SymBufReader<&File>,
+fn parse_cg3_block<R: Read + Seek>(
+    rdr: &mut SymBufReader<R>,
     target: u32,
     mut position: i64,
     sharable: &mut SharableBlocks3,
@@ -803,8 +1331,8 @@ pub struct Cg3 {
 }
 
 /// Cg3 blocks and linked blocks parsing
-pub fn parse_cg3(
-    rdr: &mut SymBufReader<&File>,
+pub fn parse_cg3<R: Read + Seek>(
+    rdr: &mut SymBufReader<R>,
     target: u32,
     mut position: i64,
     sharable: &mut SharableBlocks3,
@@ -863,6 +1391,8 @@ pub struct Cn3 {
     pub comment: String,
     // channel description
     pub description: String,
+    /// channel display name, empty if not defined
+    pub display_name: String,
     /// beginning position of channel in record
     pub pos_byte_beg: u16,
     /// number of bytes taken by channel in record
@@ -873,11 +1403,40 @@ pub struct Cn3 {
     pub endian: bool,
     /// True if channel is valid = contains data converted
     pub channel_data_valid: bool,
+    /// raw channel data before conversion, kept if requested in read options
+    pub raw_data: Option<ChannelData>,
+    /// physical data converted at first access, if conversion was deferred when reading
+    pub(crate) conversion_cache: Option<OnceLock<ChannelData>>,
+    /// channels composing this vector, matrix or N-dimensional signal
+    pub dependency: Option<Cd3Block>,
+}
+
+impl Cn3 {
+    /// returns the channel physical data, raw data being converted and cached at first call
+    /// if conversion was deferred when reading
+    pub fn physical_data(&self, sharable: &SharableBlocks3) -> Result<&ChannelData> {
+        let Some(cache) = &self.conversion_cache else {
+            return Ok(&self.data);
+        };
+        if let Some(data) = cache.get() {
+            return Ok(data);
+        }
+        let mut cn = Cn3 {
+            block1: self.block1.clone(),
+            block2: self.block2.clone(),
+            unique_name: self.unique_name.clone(),
+            data: self.data.clone(),
+            ..Default::default()
+        };
+        convert_channel(&mut cn, sharable, self.data.len() as u32)
+            .with_context(|| format!("failed converting channel {}", self.unique_name))?;
+        Ok(cache.get_or_init(|| cn.data))
+    }
 }
 
 /// creates recursively in the channel group the CN blocks and all its other linked blocks (CC, TX, CE, CD)
-pub fn parse_cn3(
-    rdr: &mut SymBufReader<&File>,
+pub fn parse_cn3<R: Read + Seek>(
+    rdr: &mut SymBufReader<R>,
     mut target: u32,
     mut position: i64,
     sharable: &mut SharableBlocks3,
@@ -938,7 +1497,7 @@ pub struct Cn3Block1 {
     /// Pointer to the conversion formula (CCBLOCK) (can be NIL)
     pub cn_cc_conversion: u32,
     /// Pointer to the source-depending extensions (CEBLOCK) of this signal (can be NIL)
-    cn_ce_source: u32,
+    pub cn_ce_source: u32,
     /// Pointer to the dependency block (CDBLOCK) of this signal (NIL allowed)
     cn_cd_source: u32,
     /// Pointer to the channel comment (TXBLOCK) of this signal (NIL allowed)
@@ -971,8 +1530,8 @@ pub struct Cn3Block2 {
 
 /// CN3 Block parsing
 #[allow(clippy::too_many_arguments)]
-fn parse_cn3_block(
-    rdr: &mut SymBufReader<&File>,
+fn parse_cn3_block<R: Read + Seek>(
+    rdr: &mut SymBufReader<R>,
     target: u32,
     mut position: i64,
     sharable: &mut SharableBlocks3,
@@ -998,11 +1557,10 @@ fn parse_cn3_block(
     let block2: Cn3Block2 = block
         .read_le()
         .context("Could not read buffer into Cn3Block2 struct")?;
-    let pos_byte_beg = block2.cn_bit_offset / 8 + record_id_size;
-    let mut n_bytes = block2.cn_bit_count / 8u16;
-    if (block2.cn_bit_count % 8) != 0 {
-        n_bytes += 1;
-    }
+    // only the record id before the record shifts the channels, the second one being after
+    let pos_byte_beg = block2.cn_bit_offset / 8 + record_id_size.min(1);
+    // bytes spanned by the bit field, starting from the byte of its first bit
+    let n_bytes = (block2.cn_bit_offset % 8 + block2.cn_bit_count).div_ceil(8);
 
     let (name, _encoding, error_flag) = encoding.decode(&block1.cn_short_name);
     let mut unique_name = name.to_string().trim_end_matches(char::from(0)).to_string();
@@ -1022,6 +1580,14 @@ fn parse_cn3_block(
         info!("errors reading channel description {}", description);
     }
 
+    let mut display_name = String::new();
+    if block2.cn_tx_display_name != 0 {
+        // Reads TX display name
+        let (_, name, pos) = parse_tx(rdr, block2.cn_tx_display_name, position, encoding)?;
+        display_name = name;
+        position = pos;
+    }
+
     let mut comment = String::new();
     if block1.cn_tx_comment != 0 {
         // Reads TX comment
@@ -1030,8 +1596,8 @@ fn parse_cn3_block(
         position = pos;
     }
 
-    // Reads CC block
-    if !sharable.cc.contains_key(&block1.cn_cc_conversion) {
+    // Reads CC block, link can be NIL for channels without conversion
+    if block1.cn_cc_conversion != 0 && !sharable.cc.contains_key(&block1.cn_cc_conversion) {
         let (pos, cc_block) =
             parse_cc3_block(rdr, block1.cn_cc_conversion, position, sharable, encoding)?;
         position = pos;
@@ -1058,6 +1624,15 @@ fn parse_cn3_block(
         position = parse_ce(rdr, block1.cn_ce_source, position, sharable, encoding)?;
     }
 
+    // Reads CD block
+    let mut dependency: Option<Cd3Block> = None;
+    if block1.cn_cd_source != 0 {
+        let (cd_block, pos) = parse_cd3(rdr, block1.cn_cd_source, position)
+            .with_context(|| format!("failed reading dependency of channel {unique_name}"))?;
+        position = pos;
+        dependency = Some(cd_block);
+    }
+
     let mut endian: bool = false; // Little endian by default
     if block2.cn_data_type >= 13 {
         endian = false; // little endian
@@ -1077,17 +1652,75 @@ fn parse_cn3_block(
         block2,
         description,
         comment,
+        display_name,
         unique_name,
         pos_byte_beg,
         n_bytes,
         data: data_type_init(0, data_type, n_bytes as u32, 1)?,
         endian,
         channel_data_valid: false,
+        raw_data: None,
+        conversion_cache: None,
+        dependency,
     };
 
     Ok((cn_struct, position))
 }
 
+/// Cd3 dependency block struct, listing the channels composing a vector, matrix
+/// or N-dimensional signal
+#[derive(Debug, PartialEq, Eq, Default, Clone, BinRead)]
+#[br(little)]
+#[repr(C)]
+pub struct Cd3Block {
+    /// CD
+    cd_id: [u8; 2],
+    /// Length of block in bytes
+    cd_len: u16,
+    /// Dependency type, 1 vector, 2 matrix in row order, 256 + N for N dimensions
+    pub cd_dependency_type: u16,
+    /// Number of dependent channels
+    pub cd_n_dependencies: u16,
+    /// DG, CG and CN blocks positions of the dependent channels
+    #[br(count = cd_n_dependencies)]
+    pub cd_dependencies: Vec<(u32, u32, u32)>,
+    /// Size of each dimension of N-dimensional dependency
+    #[br(count = cd_dependency_type.saturating_sub(256))]
+    pub cd_dims: Vec<u16>,
+}
+
+impl Cd3Block {
+    /// shape of a sample of the composed signal, dependent channels being in row order
+    pub fn shape(&self) -> Vec<usize> {
+        if self.cd_dims.is_empty() {
+            vec![self.cd_dependencies.len()]
+        } else {
+            self.cd_dims.iter().map(|dim| *dim as usize).collect()
+        }
+    }
+}
+
+/// CD3 block parsing
+fn parse_cd3<R: Read + Seek>(
+    rdr: &mut SymBufReader<R>,
+    target: u32,
+    position: i64,
+) -> Result<(Cd3Block, i64)> {
+    rdr.seek_relative(target as i64 - position)
+        .context("Could not reach position of CD Block")?;
+    let mut buf = vec![0u8; 4];
+    rdr.read_exact(&mut buf)
+        .context("Could not read Cd3 block header")?;
+    let cd_len = u16::from_le_bytes([buf[2], buf[3]]).max(4);
+    buf.resize(cd_len as usize, 0);
+    rdr.read_exact(&mut buf[4..])
+        .context("Could not read Cd3 block buffer")?;
+    let block: Cd3Block = Cursor::new(buf)
+        .read_le()
+        .context("Could not read buffer into Cd3Block structure")?;
+    Ok((block, target as i64 + cd_len as i64))
+}
+
 /// Converter of data type from 3.x to 4.x
 pub fn convert_data_type_3to4(mdf3_datatype: u16) -> u8 {
     match mdf3_datatype {
@@ -1129,11 +1762,15 @@ fn can_open_date(pos_byte_beg: u16, cn_bit_offset: u16) -> (Cn3, Cn3, Cn3, Cn3,
         unique_name: String::from("ms"),
         comment: String::new(),
         description: String::from("Milliseconds"),
+        display_name: String::new(),
         pos_byte_beg,
         n_bytes: 2,
         data: ChannelData::UInt16(UInt16Builder::new()),
         endian: false,
         channel_data_valid: false,
+        raw_data: None,
+        conversion_cache: None,
+        dependency: None,
     };
     let block2 = Cn3Block2 {
         cn_data_type: 13,
@@ -1148,11 +1785,15 @@ fn can_open_date(pos_byte_beg: u16, cn_bit_offset: u16) -> (Cn3, Cn3, Cn3, Cn3,
         unique_name: String::from("min"),
         comment: String::new(),
         description: String::from("Minutes"),
+        display_name: String::new(),
         pos_byte_beg: pos_byte_beg + 2,
         n_bytes: 1,
         data: ChannelData::UInt8(UInt8Builder::new()),
         endian: false,
         channel_data_valid: false,
+        raw_data: None,
+        conversion_cache: None,
+        dependency: None,
     };
     let block2 = Cn3Block2 {
         cn_data_type: 13,
@@ -1167,11 +1808,15 @@ fn can_open_date(pos_byte_beg: u16, cn_bit_offset: u16) -> (Cn3, Cn3, Cn3, Cn3,
         unique_name: String::from("hour"),
         comment: String::new(),
         description: String::from("Hours"),
+        display_name: String::new(),
         pos_byte_beg: pos_byte_beg + 3,
         n_bytes: 1,
         data: ChannelData::UInt8(UInt8Builder::new()),
         endian: false,
         channel_data_valid: false,
+        raw_data: None,
+        conversion_cache: None,
+        dependency: None,
     };
     let block2 = Cn3Block2 {
         cn_data_type: 13,
@@ -1186,11 +1831,15 @@ fn can_open_date(pos_byte_beg: u16, cn_bit_offset: u16) -> (Cn3, Cn3, Cn3, Cn3,
         unique_name: String::from("day"),
         comment: String::new(),
         description: String::from("Days"),
+        display_name: String::new(),
         pos_byte_beg: pos_byte_beg + 4,
         n_bytes: 1,
         data: ChannelData::UInt8(UInt8Builder::new()),
         endian: false,
         channel_data_valid: false,
+        raw_data: None,
+        conversion_cache: None,
+        dependency: None,
     };
     let block2 = Cn3Block2 {
         cn_data_type: 13,
@@ -1205,11 +1854,15 @@ fn can_open_date(pos_byte_beg: u16, cn_bit_offset: u16) -> (Cn3, Cn3, Cn3, Cn3,
         unique_name: String::from("month"),
         comment: String::new(),
         description: String::from("Month"),
+        display_name: String::new(),
         pos_byte_beg: pos_byte_beg + 5,
         n_bytes: 1,
         data: ChannelData::UInt8(UInt8Builder::new()),
         endian: false,
         channel_data_valid: false,
+        raw_data: None,
+        conversion_cache: None,
+        dependency: None,
     };
     let block2 = Cn3Block2 {
         cn_data_type: 13,
@@ -1224,11 +1877,15 @@ fn can_open_date(pos_byte_beg: u16, cn_bit_offset: u16) -> (Cn3, Cn3, Cn3, Cn3,
         unique_name: String::from("year"),
         comment: String::new(),
         description: String::from("Years"),
+        display_name: String::new(),
         pos_byte_beg: pos_byte_beg + 7,
         n_bytes: 1,
         data: ChannelData::UInt8(UInt8Builder::new()),
         endian: false,
         channel_data_valid: false,
+        raw_data: None,
+        conversion_cache: None,
+        dependency: None,
     };
     (date_ms, min, hour, day, month, year)
 }
@@ -1253,11 +1910,15 @@ fn can_open_time(pos_byte_beg: u16, cn_bit_offset: u16) -> (Cn3, Cn3) {
         unique_name: String::from("ms"),
         comment: String::new(),
         description: String::from("Milliseconds"),
+        display_name: String::new(),
         pos_byte_beg,
         n_bytes: 4,
         data: ChannelData::UInt32(UInt32Builder::new()),
         endian: false,
         channel_data_valid: false,
+        raw_data: None,
+        conversion_cache: None,
+        dependency: None,
     };
     let block2 = Cn3Block2 {
         cn_data_type: 13,
@@ -1272,11 +1933,15 @@ fn can_open_time(pos_byte_beg: u16, cn_bit_offset: u16) -> (Cn3, Cn3) {
         unique_name: String::from("day"),
         comment: String::new(),
         description: String::from("Days"),
+        display_name: String::new(),
         pos_byte_beg: pos_byte_beg + 4,
         n_bytes: 2,
         data: ChannelData::UInt16(UInt16Builder::new()),
         endian: false,
         channel_data_valid: false,
+        raw_data: None,
+        conversion_cache: None,
+        dependency: None,
     };
     (ms, days)
 }
@@ -1352,8 +2017,8 @@ impl Default for Conversion {
 }
 
 /// Parser for channel conversion blocks
-pub fn parse_cc3_block(
-    rdr: &mut SymBufReader<&File>,
+pub fn parse_cc3_block<R: Read + Seek>(
+    rdr: &mut SymBufReader<R>,
     target: u32,
     mut position: i64,
     sharable: &mut SharableBlocks3,
@@ -1546,9 +2211,61 @@ pub struct CanBlock {
     ce_sender_name: String,
 }
 
+impl CeBlock {
+    /// returns the extension as source information, DIM being an ECU and CAN a bus source
+    pub fn source_info(&self) -> SourceInfo {
+        match &self.ce_extension {
+            CeSupplement::Dim(dim) => SourceInfo {
+                name: non_empty(&dim.ce_ecu_id),
+                path: non_empty(&dim.ce_desc),
+                comment: Some(format!(
+                    "module number {} address {}",
+                    dim.ce_module_number, dim.ce_address
+                )),
+                source_type: 1, // ECU, no bus
+                ..Default::default()
+            },
+            CeSupplement::Can(can) => SourceInfo {
+                name: non_empty(&can.ce_message_name),
+                path: non_empty(&can.ce_sender_name),
+                comment: Some(format!(
+                    "CAN identifier {} channel index {}",
+                    can.ce_can_id, can.ce_can_index
+                )),
+                source_type: 2, // bus, CAN
+                bus_type: 2,
+                ..Default::default()
+            },
+            CeSupplement::None => SourceInfo::default(),
+        }
+    }
+    /// converts the extension into a mdf4 source information block,
+    /// creating its name, path and comment text blocks in sharable
+    pub(crate) fn to_si4(&self, sharable: &mut SharableBlocks) -> Si4Block {
+        let source = self.source_info();
+        let mut si = Si4Block::new(source.source_type, source.bus_type);
+        for (link, text) in [
+            (&mut si.si_tx_name, source.name),
+            (&mut si.si_tx_path, source.path),
+            (&mut si.si_md_comment, source.comment),
+        ] {
+            if let Some(text) = text {
+                *link = position_generator();
+                sharable.create_tx(*link, text);
+            }
+        }
+        si
+    }
+}
+
+/// None for empty extension texts
+fn non_empty(text: &str) -> Option<String> {
+    (!text.is_empty()).then(|| text.to_string())
+}
+
 /// parses Channel Extension block
-fn parse_ce(
-    rdr: &mut SymBufReader<&File>,
+fn parse_ce<R: Read + Seek>(
+    rdr: &mut SymBufReader<R>,
     target: u32,
     mut position: i64,
     sharable: &mut SharableBlocks3,
@@ -1637,45 +2354,77 @@ fn parse_ce(
     Ok(position)
 }
 
-/// parses mdfinfo structure to make channel names unique
-/// creates channel names set and links master channels to set of channels
+/// parses mdfinfo structure to make channel names unique according to the duplicate policy
+/// creates channel names set and links master channels to set of channels.
+/// Returns also the channels renamed or dropped
 pub fn build_channel_db3(
     dg: &mut BTreeMap<u32, Dg3>,
     sharable: &SharableBlocks3,
     n_cg: u16,
     n_cn: u16,
-) -> ChannelNamesSet3 {
+    policy: DuplicatePolicy,
+) -> Result<(ChannelNamesSet3, Vec<DuplicateChannel>)> {
     let mut channel_list: ChannelNamesSet3 = HashMap::with_capacity(n_cn as usize);
     let mut master_channel_list: HashMap<u32, String> = HashMap::with_capacity(n_cg as usize);
+    let mut duplicates: Vec<DuplicateChannel> = Vec::new();
     // creating channel list for whole file and making channel names unique
     for (dg_position, dg) in dg.iter_mut() {
         for (record_id, cg) in dg.cg.iter_mut() {
+            let mut dropped: Vec<u32> = Vec::new();
             for (cn_position, cn) in cg.cn.iter_mut() {
                 if channel_list.contains_key(&cn.unique_name) {
-                    let mut changed: bool = false;
-                    let space_char = String::from(" ");
-                    // create unique channel name
-                    if let Some(ce) = sharable.ce.get(&cn.block1.cn_ce_source) {
-                        match &ce.ce_extension {
-                            CeSupplement::Dim(dim) => {
-                                cn.unique_name.push_str(&space_char);
-                                cn.unique_name.push_str(&dim.ce_ecu_id);
-                                changed = true;
-                            }
-                            CeSupplement::Can(can) => {
-                                cn.unique_name.push_str(&space_char);
-                                cn.unique_name.push_str(&can.ce_message_name);
-                                changed = true;
+                    let name = cn.unique_name.clone();
+                    let mut suffix: Option<String> = None;
+                    let reason = match policy {
+                        DuplicatePolicy::Error => {
+                            bail!(MdfError::Parse(format!("channel {name} is not unique")))
+                        }
+                        DuplicatePolicy::KeepFirst if cn.block1.cn_type == 0 => {
+                            dropped.push(*cn_position);
+                            duplicates.push(DuplicateChannel {
+                                name,
+                                unique_name: None,
+                                reason: String::from("dropped, first channel kept"),
+                            });
+                            continue;
+                        }
+                        DuplicatePolicy::SuffixGroup => {
+                            suffix = Some(cg.block_position.to_string());
+                            "suffixed by channel group"
+                        }
+                        _ => {
+                            // create unique channel name
+                            if let Some(ce) = sharable.ce.get(&cn.block1.cn_ce_source) {
+                                match &ce.ce_extension {
+                                    CeSupplement::Dim(dim) => suffix = Some(dim.ce_ecu_id.clone()),
+                                    CeSupplement::Can(can) => {
+                                        suffix = Some(can.ce_message_name.clone())
+                                    }
+                                    _ => {}
+                                }
                             }
-                            _ => {}
+                            "suffixed by source"
                         }
-                    }
-                    // No souce name to make channel unique
-                    if !changed {
-                        // extend name with channel block position, unique
-                        cn.unique_name.push_str(&space_char);
-                        cn.unique_name.push_str(&cn_position.to_string());
-                    }
+                    };
+                    let reason = match suffix {
+                        Some(suffix) => {
+                            cn.unique_name.push(' ');
+                            cn.unique_name.push_str(&suffix);
+                            reason
+                        }
+                        // No souce name to make channel unique
+                        None => {
+                            // extend name with channel block position, unique
+                            cn.unique_name.push(' ');
+                            cn.unique_name.push_str(&cn_position.to_string());
+                            "suffixed by block position"
+                        }
+                    };
+                    duplicates.push(DuplicateChannel {
+                        name,
+                        unique_name: Some(cn.unique_name.clone()),
+                        reason: reason.to_string(),
+                    });
                 };
                 channel_list.insert(
                     cn.unique_name.clone(),
@@ -1691,6 +2440,9 @@ pub fn build_channel_db3(
                     master_channel_list.insert(cg.block_position, cn.unique_name.clone());
                 }
             }
+            for cn_position in dropped {
+                cg.cn.remove(&cn_position);
+            }
         }
     }
     // identifying master channels
@@ -1712,5 +2464,5 @@ pub fn build_channel_db3(
             cg.master_channel_name = master_channel_name;
         }
     }
-    channel_list
+    Ok((channel_list, duplicates))
 }
@@ -1,26 +1,34 @@
 //! Parsing of file metadata into MdfInfo4 struct
-use crate::mdfreader::{DataSignature, MasterSignature};
-use anyhow::{anyhow, Context, Error, Result};
-use arrow::array::{Array, BooleanBufferBuilder, UInt16Builder, UInt32Builder, UInt8Builder};
+use crate::mdfreader::{AnonymizeOptions, DataSignature, HeaderOptions, MasterSignature};
+use crate::parallel::prelude::*;
+use anyhow::{bail, Context, Error, Result};
+use arrow::array::{
+    Array, BooleanBufferBuilder, Float64Builder, UInt16Builder, UInt32Builder, UInt8Builder,
+};
 use binrw::{binrw, BinReaderExt, BinWriterExt};
 use byteorder::{LittleEndian, ReadBytesExt};
-use chrono::{DateTime, Local};
+use chrono::{DateTime, FixedOffset, Local};
 use log::warn;
 use md5::{Digest, Md5};
-use rayon::prelude::*;
+use parking_lot::RwLock;
+use std::borrow::Cow;
 use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
 use std::default::Default;
 use std::fmt::Debug;
-use std::fs::File;
 use std::io::{BufReader, Cursor, Read, Seek, Write};
-use std::sync::Arc;
+use std::path::Path;
+use std::sync::{Arc, LazyLock, OnceLock};
 use std::{fmt, str};
 use yazi::{decompress, Adler32, Format};
 
 use crate::data_holder::channel_data::{data_type_init, try_from, ChannelData};
+use crate::data_holder::spill::SpilledData;
 use crate::data_holder::tensor_arrow::Order;
-use crate::mdfinfo::IdBlock;
+use crate::error::MdfError;
+use crate::mdfinfo::{ChannelLayout, DuplicateChannel, DuplicatePolicy, GroupLayout, IdBlock};
+use crate::mdfreader::conversions4::convert_channel;
 
+use super::core4::{block_header4, cc4_members, cg4_members, cn4_members, dg4_block, hd4_block};
 use super::sym_buf_reader::SymBufReader;
 
 /// ChannelId : (Option<master_channelname>, dg_pos, (cg_pos, rec_id), (cn_pos, rec_pos))
@@ -51,12 +59,16 @@ pub struct MdfInfo4 {
     pub at: At, // attachments
     /// event blocks
     pub ev: HashMap<i64, Ev4Block>, // events
+    /// channel hierarchy blocks
+    pub ch: Vec<Ch4>,
     /// data group block linking channel group/channel/conversion/compostion/..etc. and data block
     pub dg: BTreeMap<i64, Dg4>, // contains most of the file structure
     /// cc, md, tx and si blocks that can be referenced by several blocks
     pub sharable: SharableBlocks,
     /// set of all channel names
     pub channel_names_set: ChannelNamesSet, // set of channel names
+    /// channels renamed or dropped because of duplicate names
+    pub duplicate_channels: Vec<DuplicateChannel>,
 }
 
 /// MdfInfo4's implementation
@@ -79,8 +91,11 @@ impl MdfInfo4 {
             if let Some(dg) = self.dg.get(dg_pos) {
                 if let Some(cg) = dg.cg.get(rec_id) {
                     if let Some(cn) = cg.cn.get(rec_pos) {
-                        if !cn.data.is_empty() {
-                            data = Some(&cn.data);
+                        if cn.is_loaded() {
+                            match cn.physical_data(&self.sharable) {
+                                Ok(physical) => data = Some(physical),
+                                Err(error) => warn!("{error:#}"),
+                            }
                         }
                     }
                 }
@@ -88,6 +103,145 @@ impl MdfInfo4 {
         }
         data
     }
+    /// Returns the channel's vector data if present in memory or spilled, otherwise None.
+    /// Spilled data is read back without being kept in memory
+    pub fn get_channel_data_uncached(&self, channel_name: &str) -> Option<Cow<'_, ChannelData>> {
+        let (_master, dg_pos, (_cg_pos, rec_id), (_cn_pos, rec_pos)) =
+            self.get_channel_id(channel_name)?;
+        let cn = self.dg.get(dg_pos)?.cg.get(rec_id)?.cn.get(rec_pos)?;
+        if !cn.is_loaded() {
+            return None;
+        }
+        match cn.physical_data_uncached(&self.sharable) {
+            Ok(physical) => Some(physical),
+            Err(error) => {
+                warn!("{error:#}");
+                None
+            }
+        }
+    }
+    /// Returns the channel's raw data before conversion, if kept when reading
+    pub fn get_channel_raw_data(&self, channel_name: &str) -> Option<&ChannelData> {
+        let (_master, dg_pos, (_cg_pos, rec_id), (_cn_pos, rec_pos)) =
+            self.get_channel_id(channel_name)?;
+        let cn = self.dg.get(dg_pos)?.cg.get(rec_id)?.cn.get(rec_pos)?;
+        if cn.conversion_cache.is_some() {
+            Some(&cn.data)
+        } else {
+            cn.raw_data.as_ref()
+        }
+    }
+    /// Returns the channel's text statuses of partial conversion if present in memory
+    pub fn get_channel_status(&self, channel_name: &str) -> Option<&ChannelData> {
+        let (_master, dg_pos, (_cg_pos, rec_id), (_cn_pos, rec_pos)) =
+            self.get_channel_id(channel_name)?;
+        let cn = self.dg.get(dg_pos)?.cg.get(rec_id)?.cn.get(rec_pos)?;
+        match cn.status_data(&self.sharable) {
+            Ok(status) => status,
+            Err(error) => {
+                warn!("{error:#}");
+                None
+            }
+        }
+    }
+    /// Returns the bit masks and flag names of the channel's bitfield text table conversion,
+    /// None if channel has no such conversion. Unnamed flags are named by their mask
+    pub fn get_channel_bitfield_flags(
+        &self,
+        channel_name: &str,
+    ) -> Result<Option<Vec<(u64, String)>>> {
+        let Some(cc) = self
+            .get_channel_id(channel_name)
+            .and_then(|(_master, dg_pos, (_cg_pos, rec_id), (_cn_pos, rec_pos))| {
+                self.dg.get(dg_pos)?.cg.get(rec_id)?.cn.get(rec_pos)
+            })
+            .and_then(|cn| self.sharable.cc.get(&cn.block.cn_cc_conversion))
+            .filter(|cc| cc.cc_type == 11)
+        else {
+            return Ok(None);
+        };
+        let CcVal::Uint(masks) = &cc.cc_val else {
+            return Ok(None);
+        };
+        masks
+            .iter()
+            .zip(cc.cc_ref.iter())
+            .map(|(mask, link)| {
+                let name = match self.sharable.cc.get(link) {
+                    Some(flag) => self.sharable.get_tx(flag.cc_tx_name)?,
+                    None => None,
+                };
+                Ok((*mask, name.unwrap_or_else(|| format!("{mask:#x}"))))
+            })
+            .collect::<Result<Vec<(u64, String)>>>()
+            .map(Some)
+    }
+    /// Returns the record layout of the channel group containing the channel
+    pub fn get_group_layout(&self, channel_name: &str) -> Option<GroupLayout> {
+        let (_master, dg_pos, (_cg_pos, rec_id), _cn_pos) = self.get_channel_id(channel_name)?;
+        let cg = self.dg.get(dg_pos)?.cg.get(rec_id)?;
+        let mut channels: Vec<ChannelLayout> = cg
+            .cn
+            .values()
+            .map(|cn| ChannelLayout {
+                name: cn.unique_name.clone(),
+                byte_offset: cn.block.cn_byte_offset,
+                bit_offset: cn.block.cn_bit_offset,
+                bit_count: cn.block.cn_bit_count,
+                data_type: cn.block.cn_data_type,
+            })
+            .collect();
+        channels.sort_by(|a, b| {
+            (a.byte_offset, a.bit_offset, &a.name).cmp(&(b.byte_offset, b.bit_offset, &b.name))
+        });
+        Some(GroupLayout {
+            record_length: cg.record_length.into(),
+            cycle_count: cg.block.cg_cycle_count,
+            channels,
+        })
+    }
+    /// Returns the unique names of the members of a structure channel in their block order,
+    /// None if channel is not a structure
+    pub fn get_channel_struct_members(&self, channel_name: &str) -> Option<Vec<String>> {
+        let (_master, dg_pos, (_cg_pos, rec_id), (_cn_pos, rec_pos)) =
+            self.get_channel_id(channel_name)?;
+        let cg = self.dg.get(dg_pos)?.cg.get(rec_id)?;
+        let Some(Composition {
+            block: Compo::CN(first),
+            ..
+        }) = &cg.cn.get(rec_pos)?.composition
+        else {
+            return None;
+        };
+        let mut members = Vec::new();
+        let mut next_pointer = first.block_position;
+        while next_pointer != 0 {
+            let member = cg
+                .cn
+                .values()
+                .find(|cn| cn.block_position == next_pointer)?;
+            members.push(member.unique_name.clone());
+            next_pointer = member.block.cn_cn_next;
+        }
+        Some(members)
+    }
+    /// Returns the description of the channel's conversion, None if channel has no conversion
+    pub fn get_channel_conversion(&self, channel_name: &str) -> Result<Option<ConversionInfo>> {
+        if let Some((_master, dg_pos, (_cg_pos, rec_id), (_cn_pos, rec_pos))) =
+            self.get_channel_id(channel_name)
+        {
+            if let Some(cc) = self
+                .dg
+                .get(dg_pos)
+                .and_then(|dg| dg.cg.get(rec_id))
+                .and_then(|cg| cg.cn.get(rec_pos))
+                .and_then(|cn| self.sharable.cc.get(&cn.block.cn_cc_conversion))
+            {
+                return Ok(Some(cc.conversion_info(&self.sharable)?));
+            }
+        }
+        Ok(None)
+    }
     /// Returns the channel's unit string. If it does not exist, it is an empty string.
     pub fn get_channel_unit(&self, channel_name: &str) -> Result<Option<String>> {
         let mut unit: Option<String> = None;
@@ -120,6 +274,44 @@ impl MdfInfo4 {
         }
         Ok(desc)
     }
+    /// returns the channel's display name from the names of its xml comment
+    pub fn get_channel_display_name(&self, channel_name: &str) -> Result<Option<String>> {
+        let Some((_master, dg_pos, (_cg_pos, rec_id), (_cn_pos, rec_pos))) =
+            self.get_channel_id(channel_name)
+        else {
+            return Ok(None);
+        };
+        let Some(cn) = self
+            .dg
+            .get(dg_pos)
+            .and_then(|dg| dg.cg.get(rec_id))
+            .and_then(|cg| cg.cn.get(rec_pos))
+        else {
+            return Ok(None);
+        };
+        match self.sharable.md_tx.get(&cn.block.cn_md_comment) {
+            Some(md) => md.get_tag("display"),
+            None => Ok(None),
+        }
+    }
+    /// returns the channel's source information, or its channel group acquisition source if not defined
+    pub fn get_channel_source(&self, channel_name: &str) -> Result<Option<SourceInfo>> {
+        if let Some((_master, dg_pos, (_cg_pos, rec_id), (_cn_pos, rec_pos))) =
+            self.get_channel_id(channel_name)
+        {
+            if let Some(cg) = self.dg.get(dg_pos).and_then(|dg| dg.cg.get(rec_id)) {
+                let si = cg
+                    .cn
+                    .get(rec_pos)
+                    .and_then(|cn| self.sharable.si.get(&cn.block.cn_si_source))
+                    .or_else(|| self.sharable.si.get(&cg.block.cg_si_acq_source));
+                if let Some(si) = si {
+                    return Ok(Some(si.source_info(&self.sharable)?));
+                }
+            }
+        }
+        Ok(None)
+    }
     /// returns the master channel associated to the input channel name
     pub fn get_channel_master(&self, channel_name: &str) -> Option<String> {
         let mut master: Option<String> = None;
@@ -130,6 +322,14 @@ impl MdfInfo4 {
         }
         master
     }
+    /// true if the channel raw data are strings to be decoded
+    pub fn is_string_channel(&self, channel_name: &str) -> bool {
+        self.get_channel_id(channel_name)
+            .and_then(|(_master, dg_pos, (_cg_pos, rec_id), (_cn_pos, rec_pos))| {
+                self.dg.get(dg_pos)?.cg.get(rec_id)?.cn.get(rec_pos)
+            })
+            .is_some_and(|cn| matches!(cn.data, ChannelData::Utf8(_)))
+    }
     /// returns type of master channel link to channel input in parameter:
     /// 0 = None (normal data channels), 1 = Time (seconds), 2 = Angle (radians),
     /// 3 = Distance (meters), 4 = Index (zero-based index values)
@@ -201,6 +401,10 @@ impl MdfInfo4 {
                                     (Vec::new(), Order::RowMajor),
                                 )?;
                             }
+                            cn.raw_data = None;
+                            cn.status = None;
+                            cn.conversion_cache = None;
+                            cn.spilled = None;
                         }
                     }
                 }
@@ -215,10 +419,12 @@ impl MdfInfo4 {
             dg: BTreeMap::new(),
             sharable: SharableBlocks::new(n_channels),
             channel_names_set: HashMap::with_capacity(n_channels),
+            duplicate_channels: Vec::new(),
             id_block: IdBlock::default(),
             fh: Vec::new(),
             at: HashMap::new(),
             ev: HashMap::new(),
+            ch: Vec::new(),
             hd_block: Hd4::default(),
         }
     }
@@ -251,8 +457,9 @@ impl MdfInfo4 {
             .create_tx(channel_name_position, channel_name.to_string());
 
         // Channel array
-        let mut list_size = data_signature.shape.0.iter().product(); // primitive list size is 1
-        if data_signature.data_type == 15 | 16 {
+        // first dimension is the number of records, primitive list size is 1
+        let mut list_size = data_signature.shape.0.iter().skip(1).product();
+        if matches!(data_signature.data_type, 15 | 16) {
             //complex
             list_size *= 2;
         }
@@ -332,6 +539,10 @@ impl MdfInfo4 {
             list_size,
             shape: data_signature.shape,
             invalid_mask: None,
+            raw_data: None,
+            status: None,
+            conversion_cache: None,
+            spilled: None,
         };
 
         // CG
@@ -383,7 +594,7 @@ impl MdfInfo4 {
     }
     /// Renames a channel's name in memory
     pub fn rename_channel(&mut self, channel_name: &str, new_name: &str) {
-        if let Some((master, dg_pos, (cg_pos, rec_id), (cn_pos, rec_pos))) =
+        if let Some((mut master, dg_pos, (cg_pos, rec_id), (cn_pos, rec_pos))) =
             self.channel_names_set.remove(channel_name)
         {
             if let Some(dg) = self.dg.get_mut(&dg_pos) {
@@ -392,18 +603,14 @@ impl MdfInfo4 {
                         cn.unique_name = new_name.to_string();
                         cg.channel_names.remove(channel_name);
                         cg.channel_names.insert(new_name.to_string());
-                        if let Some(master_name) = &master {
-                            if master_name == channel_name {
-                                cg.master_channel_name = Some(new_name.to_string());
-                                cg.channel_names.iter().for_each(|channel| {
-                                    if let Some(val) = self.channel_names_set.get_mut(channel) {
-                                        val.0 = Some(new_name.to_string());
-                                        val.1 = dg_pos;
-                                        val.2 = (cg_pos, rec_id);
-                                        val.3 = (cn_pos, rec_pos);
-                                    }
-                                });
-                            }
+                        if master.as_deref() == Some(channel_name) {
+                            master = Some(new_name.to_string());
+                            cg.master_channel_name.clone_from(&master);
+                            cg.channel_names.iter().for_each(|channel| {
+                                if let Some(val) = self.channel_names_set.get_mut(channel) {
+                                    val.0.clone_from(&master);
+                                }
+                            });
                         }
 
                         self.channel_names_set.insert(
@@ -429,6 +636,10 @@ impl MdfInfo4 {
                     if let Some(cn) = cg.cn.get_mut(rec_pos) {
                         cn.data = try_from(&data)
                             .context("failed converting dyn array to ChannelData")?;
+                        cn.raw_data = None;
+                        cn.status = None;
+                        cn.conversion_cache = None;
+                        cn.spilled = None;
                     }
                 }
             }
@@ -482,6 +693,208 @@ impl MdfInfo4 {
             }
         }
     }
+    /// adds a virtual time master channel to the channel groups without master, values being
+    /// the record index times the sampling period, and converts into time the virtual masters
+    /// without conversion. Returns the names of the master channels synthesized
+    pub fn synthesize_masters(&mut self, sampling_period: f64) -> Result<Vec<String>> {
+        let mut synthesized: Vec<String> = Vec::new();
+        for (dg_position, dg) in self.dg.iter_mut() {
+            for (rec_id, cg) in dg.cg.iter_mut() {
+                if cg.block.cg_flags & 0b1 != 0 || cg.cn.is_empty() {
+                    continue; // VLSD channel group
+                }
+                let loaded = cg.cn.values().any(|cn| !cn.data.is_empty());
+                let time = || time_data(cg.block.cg_cycle_count, sampling_period);
+                let master = cg
+                    .master_channel_name
+                    .as_ref()
+                    .and_then(|name| cg.cn.values().find(|cn| &cn.unique_name == name));
+                let master_position = match master {
+                    Some(cn) if cn.block.cn_type == 3 && cn.block.cn_cc_conversion == 0 => {
+                        cg.cn.iter().find_map(|(rec_pos, c)| {
+                            (c.unique_name == cn.unique_name).then_some(*rec_pos)
+                        })
+                    }
+                    Some(_) => continue,
+                    None => None,
+                };
+                let cc_position = position_generator();
+                self.sharable
+                    .cc
+                    .insert(cc_position, Cc4Block::linear(0.0, sampling_period));
+                if let Some(cn) = master_position.and_then(|rec_pos| cg.cn.get_mut(&rec_pos)) {
+                    // existing virtual master with record index values
+                    cn.block.cn_cc_conversion = cc_position;
+                    cn.block.cn_sync_type = 1;
+                    if !cn.data.is_empty() {
+                        cn.data = time();
+                    }
+                    synthesized.push(cn.unique_name.clone());
+                    continue;
+                }
+                let mut name = String::from("time");
+                let mut index = 1;
+                while self.channel_names_set.contains_key(&name) {
+                    name = format!("time_{index}");
+                    index += 1;
+                }
+                let mut block = Cn4Block {
+                    cn_type: 3, // virtual master
+                    cn_sync_type: 1,
+                    cn_cc_conversion: cc_position,
+                    ..Default::default()
+                };
+                block.cn_tx_name = position_generator();
+                self.sharable.create_tx(block.cn_tx_name, name.clone());
+                block.cn_md_unit = position_generator();
+                self.sharable.create_tx(block.cn_md_unit, "s".to_string());
+                let cn = Cn4 {
+                    header: default_short_header(BlockType::CN),
+                    block,
+                    unique_name: name.clone(),
+                    block_position: position_generator(),
+                    data: if loaded {
+                        time()
+                    } else {
+                        data_type_init(3, 0, 0, 1)?
+                    },
+                    list_size: 1,
+                    ..Default::default()
+                };
+                // virtual channel, position in record negative
+                let mut rec_pos = -1;
+                while cg.cn.contains_key(&rec_pos) {
+                    rec_pos -= 1;
+                }
+                for channel in cg.channel_names.iter() {
+                    if let Some(id) = self.channel_names_set.get_mut(channel) {
+                        id.0 = Some(name.clone());
+                    }
+                }
+                self.channel_names_set.insert(
+                    name.clone(),
+                    (
+                        Some(name.clone()),
+                        *dg_position,
+                        (cg.block_position, *rec_id),
+                        (cn.block_position, rec_pos),
+                    ),
+                );
+                cg.cn.insert(rec_pos, cn);
+                cg.channel_names.insert(name.clone());
+                cg.master_channel_name = Some(name.clone());
+                synthesized.push(name);
+            }
+        }
+        Ok(synthesized)
+    }
+    /// sets in memory the header properties, comment, start time and program identifier
+    pub fn set_header(&mut self, header: &HeaderOptions) -> Result<()> {
+        if let Some(program) = &header.program {
+            self.id_block.set_program(program)?;
+        }
+        if let Some(start_time) = header.start_time {
+            self.hd_block.set_start_time(start_time)?;
+        }
+        let properties = [
+            ("author", &header.author),
+            ("project", &header.project),
+            ("subject", &header.subject),
+            ("TX", &header.comment),
+        ];
+        if properties.iter().all(|(_, value)| value.is_none()) {
+            return Ok(());
+        }
+        if self.hd_block.hd_md_comment == 0 {
+            self.hd_block.hd_md_comment = position_generator();
+        }
+        let md = self
+            .sharable
+            .md_tx
+            .entry(self.hd_block.hd_md_comment)
+            .or_insert_with(|| MetaData::new(MetaDataBlockType::MdParsed, BlockType::HD));
+        if md.block_type != MetaDataBlockType::MdParsed {
+            md.parse_hd_xml()?;
+        }
+        for (key, value) in properties {
+            if let Some(value) = value {
+                md.comments.insert(key.to_string(), value.clone());
+            }
+        }
+        Ok(())
+    }
+    /// returns the header comment and properties, TX key being the comment
+    pub(crate) fn header_comments(&self) -> Result<Option<HashMap<String, String>>> {
+        match self.sharable.md_tx.get(&self.hd_block.hd_md_comment) {
+            Some(md) if md.block_type == MetaDataBlockType::MdParsed => {
+                Ok(Some(md.comments.clone()))
+            }
+            Some(md) => {
+                let mut md = md.clone();
+                md.parse_hd_xml()?;
+                Ok(Some(md.comments))
+            }
+            None => Ok(None),
+        }
+    }
+    /// strips or replaces in memory header properties, file history, comments,
+    /// sources paths and attachments
+    pub fn anonymize(&mut self, options: &AnonymizeOptions) {
+        if let Some(md) = self.sharable.md_tx.get_mut(&self.hd_block.hd_md_comment) {
+            md.comments
+                .retain(|key, _| !HEADER_PROPERTIES.contains(&key.to_lowercase().as_str()));
+            if options.comments {
+                md.comments.remove("TX");
+            }
+            if let Some(replacement) = &options.replacement {
+                for key in HEADER_PROPERTIES {
+                    md.comments.insert(key.to_string(), replacement.clone());
+                }
+            }
+        }
+        // file history comments contain user names
+        for fh in self.fh.iter_mut() {
+            self.sharable.md_tx.remove(&fh.fh_md_comment);
+            fh.fh_md_comment = 0;
+        }
+        if options.comments {
+            let mut links: Vec<i64> = Vec::new();
+            for dg in self.dg.values_mut() {
+                links.push(std::mem::take(&mut dg.block.dg_md_comment));
+                for cg in dg.cg.values_mut() {
+                    links.push(std::mem::take(&mut cg.block.cg_md_comment));
+                    for cn in cg.cn.values_mut() {
+                        links.push(std::mem::take(&mut cn.block.cn_md_comment));
+                    }
+                }
+            }
+            for ev in self.ev.values_mut() {
+                links.push(std::mem::take(&mut ev.ev_md_comment));
+            }
+            links.iter().for_each(|link| {
+                self.sharable.md_tx.remove(link);
+            });
+        }
+        if options.source_paths {
+            let mut links: Vec<i64> = Vec::new();
+            for si in self.sharable.si.values_mut() {
+                links.push(std::mem::take(&mut si.si_tx_path));
+                links.push(std::mem::take(&mut si.si_md_comment));
+            }
+            links.iter().for_each(|link| {
+                self.sharable.md_tx.remove(link);
+            });
+        }
+        if options.attachments {
+            for (at, _data) in self.at.values() {
+                for link in [at.at_tx_filename, at.at_tx_mimetype, at.at_md_comment] {
+                    self.sharable.md_tx.remove(&link);
+                }
+            }
+            self.at.clear();
+            self.hd_block.hd_at_first = 0;
+        }
+    }
     /// list attachments
     pub fn list_attachments(&mut self) -> String {
         let mut output = String::new();
@@ -507,8 +920,13 @@ impl MdfInfo4 {
                     if (at.0.at_flags & 0b10) > 0 {
                         // Compressed data
                         let checksum: Option<u32>;
-                        (data, checksum) = decompress(embedded_data, Format::Zlib)
-                            .expect("Could not decompress attached embedded data");
+                        (data, checksum) = match decompress(embedded_data, Format::Zlib) {
+                            Ok(decompressed) => decompressed,
+                            Err(e) => {
+                                warn!("Could not decompress attached embedded data: {:?}", e);
+                                return None;
+                            }
+                        };
                         // is checksum valid
                         if (at.0.at_flags & 0b100) > 0 {
                             // verify data integrity
@@ -575,8 +993,8 @@ impl MdfInfo4 {
                 key,
                 self.sharable.get_tx(block.ev_tx_name),
                 self.sharable.get_comments(block.ev_md_comment),
-                block.links[0..block.ev_scope_count as usize].to_vec(),
-                block.links[block.ev_scope_count as usize.. block.ev_attachment_count as usize].to_vec(),
+                block.scope(),
+                block.attachments(),
                 block.ev_type,
             ))
         }
@@ -590,7 +1008,174 @@ impl MdfInfo4 {
     pub fn get_event_blocks(&self) -> HashMap<i64, Ev4Block> {
         self.ev.clone()
     }
-    // TODO Extract CH
+    /// returns the events sorted by sync value, with name, comment and scope resolved
+    pub fn get_events(&self) -> Result<Vec<Event>> {
+        let mut events: Vec<Event> = Vec::with_capacity(self.ev.len());
+        for block in self.ev.values() {
+            let scope = block
+                .scope()
+                .iter()
+                .filter_map(|position| self.event_scope(*position))
+                .collect();
+            events.push(Event {
+                name: self.sharable.get_tx(block.ev_tx_name)?,
+                comment: self.sharable.get_tx(block.ev_md_comment)?,
+                event_type: block.ev_type.into(),
+                sync_type: block.ev_sync_type,
+                range_type: block.ev_range_type,
+                cause: block.ev_cause,
+                sync_value: block.sync_value(),
+                scope,
+                attachments: block.attachments().to_vec(),
+            });
+        }
+        events.sort_by(|a, b| a.sync_value.total_cmp(&b.sync_value));
+        Ok(events)
+    }
+    /// returns the synchronisation channels (cn_type 4) with the attachment they reference,
+    /// sorted by name
+    pub fn get_sync_channels(&self) -> Result<Vec<SyncChannel>> {
+        let mut channels: Vec<SyncChannel> = Vec::new();
+        for (name, (master, dg_pos, (_cg_pos, rec_id), (_cn_pos, rec_pos))) in
+            self.channel_names_set.iter()
+        {
+            let Some(cn) = self
+                .dg
+                .get(dg_pos)
+                .and_then(|dg| dg.cg.get(rec_id))
+                .and_then(|cg| cg.cn.get(rec_pos))
+            else {
+                continue;
+            };
+            if cn.block.cn_type != 4 {
+                continue;
+            }
+            let Some((at, _embedded_data)) = self.at.get(&cn.block.cn_data) else {
+                warn!("synchronisation channel {name} does not reference an attachment");
+                continue;
+            };
+            channels.push(SyncChannel {
+                name: name.clone(),
+                master: master.clone(),
+                sync_type: cn.block.cn_sync_type,
+                attachment: cn.block.cn_data,
+                file_name: self.sharable.get_tx(at.at_tx_filename)?,
+                mime_type: self.sharable.get_tx(at.at_tx_mimetype)?,
+            });
+        }
+        channels.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(channels)
+    }
+    /// resolves a CN or CG block position of an event scope into a channel name,
+    /// a channel group being referenced by its master channel name or its first channel name
+    fn event_scope(&self, position: i64) -> Option<EventScope> {
+        let mut group: Option<&String> = None;
+        for (name, (master, _dg_pos, (cg_pos, _rec_id), (cn_pos, _rec_pos))) in
+            self.channel_names_set.iter()
+        {
+            if *cn_pos == position {
+                return Some(EventScope::Channel(name.clone()));
+            } else if *cg_pos == position {
+                if master.as_ref() == Some(name) {
+                    return Some(EventScope::ChannelGroup(name.clone()));
+                }
+                group = group.filter(|g| *g < name).or(Some(name));
+            }
+        }
+        group.map(|name| EventScope::ChannelGroup(name.clone()))
+    }
+    /// adds an event in memory (no file modification), attachments references are ignored
+    pub fn add_event(&mut self, event: Event) -> Result<()> {
+        let mut scope: Vec<i64> = Vec::with_capacity(event.scope.len());
+        for reference in event.scope.iter() {
+            match reference {
+                EventScope::Channel(name) => match self.get_channel_id(name) {
+                    Some((_master, _dg_pos, _cg, (cn_pos, _rec_pos))) => scope.push(*cn_pos),
+                    None => bail!("channel {} of event scope not found", name),
+                },
+                EventScope::ChannelGroup(name) => match self.get_channel_id(name) {
+                    Some((_master, _dg_pos, (cg_pos, _rec_id), _cn)) => scope.push(*cg_pos),
+                    None => bail!("channel {} of event scope not found", name),
+                },
+            }
+        }
+        let mut ev_tx_name = 0;
+        if let Some(name) = &event.name {
+            ev_tx_name = position_generator();
+            self.sharable.create_tx(ev_tx_name, name.clone());
+        }
+        let mut ev_md_comment = 0;
+        if let Some(comment) = &event.comment {
+            ev_md_comment = position_generator();
+            self.sharable.create_tx(ev_md_comment, comment.clone());
+        }
+        self.ev.insert(
+            position_generator(),
+            Ev4Block::new(&event, ev_tx_name, ev_md_comment, scope),
+        );
+        Ok(())
+    }
+    /// returns the file history entries, oldest first
+    pub fn get_file_history(&self) -> Result<Vec<FileHistory>> {
+        self.fh
+            .iter()
+            .map(|fh| {
+                let md = self.sharable.md_tx.get(&fh.fh_md_comment);
+                let tag = |tag: &str| -> Result<Option<String>> {
+                    Ok(md.map(|md| md.get_tag(tag)).transpose()?.flatten())
+                };
+                Ok(FileHistory {
+                    time: fh.time()?,
+                    comment: self.sharable.get_tx(fh.fh_md_comment)?,
+                    tool_id: tag("tool_id")?,
+                    tool_vendor: tag("tool_vendor")?,
+                    tool_version: tag("tool_version")?,
+                    user_name: tag("user_name")?,
+                })
+            })
+            .collect()
+    }
+    /// returns the channel hierarchy as a tree, channels referenced by their names
+    pub fn get_channel_tree(&self) -> Result<Vec<ChannelTreeNode>> {
+        let names: HashMap<i64, &String> = self
+            .channel_names_set
+            .iter()
+            .map(|(name, (_master, _dg_pos, _cg, (cn_pos, _rec_pos)))| (*cn_pos, name))
+            .collect();
+        self.ch
+            .iter()
+            .map(|ch| self.channel_tree_node(ch, &names))
+            .collect()
+    }
+    fn channel_tree_node(
+        &self,
+        ch: &Ch4,
+        names: &HashMap<i64, &String>,
+    ) -> Result<ChannelTreeNode> {
+        Ok(ChannelTreeNode {
+            name: self.sharable.get_tx(ch.block.ch_tx_name)?,
+            comment: self.sharable.get_tx(ch.block.ch_md_comment)?,
+            hierarchy_type: ch.block.ch_type,
+            channels: ch
+                .block
+                .channels()
+                .filter_map(|position| names.get(&position).map(|name| (*name).clone()))
+                .collect(),
+            children: ch
+                .children
+                .iter()
+                .map(|child| self.channel_tree_node(child, names))
+                .collect::<Result<Vec<_>>>()?,
+        })
+    }
+}
+
+/// time values of a synthesized master channel, record index times sampling period
+pub(crate) fn time_data(cycle_count: u64, sampling_period: f64) -> ChannelData {
+    let values: Vec<f64> = (0..cycle_count)
+        .map(|index| index as f64 * sampling_period)
+        .collect();
+    ChannelData::Float64(Float64Builder::new_from_buffer(values.into(), None))
 }
 
 /// creates random negative position
@@ -624,6 +1209,9 @@ impl fmt::Display for MdfInfo4 {
                 let unit = self.get_channel_unit(channel);
                 let desc = self.get_channel_desc(channel);
                 writeln!(f, " {channel} {unit:?} {desc:?} \n")?;
+                if let Ok(Some(source)) = self.get_channel_source(channel) {
+                    writeln!(f, " {source} \n")?;
+                }
             }
         }
         writeln!(f, "\n")
@@ -659,15 +1247,11 @@ impl Default for Blockheader4 {
 
 /// parse the block header and its fields id, (reserved), length and number of links
 #[inline]
-pub fn parse_block_header(rdr: &mut SymBufReader<&File>) -> Result<Blockheader4> {
+pub fn parse_block_header<R: Read + Seek>(rdr: &mut SymBufReader<R>) -> Result<Blockheader4> {
     let mut buf = [0u8; 24];
     rdr.read_exact(&mut buf)
         .context("could not read blockheader4 Id")?;
-    let mut block = Cursor::new(buf);
-    let header: Blockheader4 = block
-        .read_le()
-        .context("binread could not parse blockheader4")?;
-    Ok(header)
+    block_header4(&buf, 0).context("binread could not parse blockheader4")
 }
 
 /// MDF4 - common block Header without the number of links
@@ -707,6 +1291,21 @@ pub fn default_short_header(variant: BlockType) -> Blockheader4Short {
             hdr_gap: [0u8; 4],
             hdr_len: 160,
         },
+        BlockType::SR => Blockheader4Short {
+            hdr_id: [35, 35, 83, 82], // ##SR
+            hdr_gap: [0u8; 4],
+            hdr_len: 64,
+        },
+        BlockType::SI => Blockheader4Short {
+            hdr_id: [35, 35, 83, 73], // ##SI
+            hdr_gap: [0u8; 4],
+            hdr_len: 56,
+        },
+        BlockType::EV => Blockheader4Short {
+            hdr_id: [35, 35, 69, 86], // ##EV
+            hdr_gap: [0u8; 4],
+            hdr_len: 96, // without scope and attachment links
+        },
         _ => Blockheader4Short {
             hdr_id: [35, 35, 67, 78], // ##CN
             hdr_gap: [0u8; 4],
@@ -717,7 +1316,9 @@ pub fn default_short_header(variant: BlockType) -> Blockheader4Short {
 
 /// parse the block header and its fields id, (reserved), length except the number of links
 #[inline]
-fn parse_block_header_short(rdr: &mut SymBufReader<&File>) -> Result<Blockheader4Short> {
+fn parse_block_header_short<R: Read + Seek>(
+    rdr: &mut SymBufReader<R>,
+) -> Result<Blockheader4Short> {
     let mut buf = [0u8; 16];
     rdr.read_exact(&mut buf)
         .context("could not read short blockheader4 Id")?;
@@ -730,8 +1331,8 @@ fn parse_block_header_short(rdr: &mut SymBufReader<&File>) -> Result<Blockheader
 
 /// reads generically a block header and return links and members section part into a Seek buffer for further processing
 #[inline]
-fn parse_block(
-    rdr: &mut SymBufReader<&File>,
+fn parse_block<R: Read + Seek>(
+    rdr: &mut SymBufReader<R>,
     target: i64,
     mut position: i64,
 ) -> Result<(Cursor<Vec<u8>>, Blockheader4, i64)> {
@@ -751,8 +1352,8 @@ fn parse_block(
 
 /// reads generically a block header wihtout the number of links and returns links and members section part into a Seek buffer for further processing
 #[inline]
-fn parse_block_short(
-    rdr: &mut SymBufReader<&File>,
+fn parse_block_short<R: Read + Seek>(
+    rdr: &mut SymBufReader<R>,
     target: i64,
     mut position: i64,
 ) -> Result<(Cursor<Vec<u8>>, Blockheader4Short, i64)> {
@@ -797,6 +1398,8 @@ pub enum BlockType {
     CN,
     CC,
     SI,
+    SR,
+    CH,
 }
 
 /// struct linking MD or TX block with
@@ -816,8 +1419,8 @@ pub struct MetaData {
 }
 
 /// Parses the MD or TX block
-fn read_meta_data(
-    rdr: &mut SymBufReader<&File>,
+fn read_meta_data<R: Read + Seek>(
+    rdr: &mut SymBufReader<R>,
     sharable: &mut SharableBlocks,
     target: i64,
     mut position: i64,
@@ -886,16 +1489,30 @@ impl MetaData {
     pub fn get_tx(&self) -> Result<Option<String>, Error> {
         match self.block_type {
             MetaDataBlockType::MdParsed => Ok(self.comments.get("TX").cloned()),
+            MetaDataBlockType::MdBlock => self.get_tag("TX"),
+            MetaDataBlockType::TX => {
+                let comment = str::from_utf8(&self.raw_data).with_context(|| {
+                    format!("Invalid UTF-8 sequence in metadata: {:?}", self.raw_data)
+                })?;
+                let c: String = comment.trim_end_matches(char::from(0)).into();
+                Ok(Some(c))
+            }
+        }
+    }
+    /// Returns the text of the first non empty tag element of MD Block, None for TX Block
+    pub fn get_tag(&self, tag: &str) -> Result<Option<String>, Error> {
+        match self.block_type {
+            MetaDataBlockType::MdParsed => Ok(self.comments.get(tag).cloned()),
             MetaDataBlockType::MdBlock => {
-                // extract TX tag from xml
+                // extract tag from xml
                 let comment: String = self
                     .get_data_string()
-                    .context("failed getting data string to extract TX tag")?
+                    .with_context(|| format!("failed getting data string to extract {tag} tag"))?
                     .trim_end_matches(|c| c == '\n' || c == '\r' || c == ' ')
                     .into(); // removes ending spaces
                 match roxmltree::Document::parse(&comment) {
                     Ok(md) => {
-                        let mut tx: Option<String> = None;
+                        let mut value: Option<String> = None;
                         for node in md.root().descendants() {
                             let text = match node.text() {
                                 Some(text) => text.to_string(),
@@ -903,13 +1520,13 @@ impl MetaData {
                             };
                             if node.is_element()
                                 && !text.is_empty()
-                                && node.tag_name().name() == r"TX"
+                                && node.tag_name().name() == tag
                             {
-                                tx = Some(text);
+                                value = Some(text);
                                 break;
                             }
                         }
-                        Ok(tx)
+                        Ok(value)
                     }
                     Err(e) => {
                         warn!("Error parsing comment : \n{}\n{}", comment, e);
@@ -917,13 +1534,7 @@ impl MetaData {
                     }
                 }
             }
-            MetaDataBlockType::TX => {
-                let comment = str::from_utf8(&self.raw_data).with_context(|| {
-                    format!("Invalid UTF-8 sequence in metadata: {:?}", self.raw_data)
-                })?;
-                let c: String = comment.trim_end_matches(char::from(0)).into();
-                Ok(Some(c))
-            }
+            MetaDataBlockType::TX => Ok(None),
         }
     }
     /// Returns the bytes of the text from TX Block or TX's tag text from MD Block
@@ -966,6 +1577,17 @@ impl MetaData {
                         comments.insert(value.to_string(), text.to_string());
                     }
                 }
+                if let Some(text) = md
+                    .root_element()
+                    .children()
+                    .find(|node| node.has_tag_name("TX"))
+                    .and_then(|node| node.text())
+                {
+                    comments.insert("TX".to_string(), text.to_string());
+                }
+            }
+            Err(_) if self.block_type == MetaDataBlockType::TX => {
+                comments.insert("TX".to_string(), comment.clone());
             }
             Err(e) => {
                 warn!("Could not parse HD MD comment : \n{}\n{}", comment, e);
@@ -976,17 +1598,55 @@ impl MetaData {
         self.raw_data = vec![]; // empty the data from block as already parsed
         Ok(())
     }
-    /// Creates File History MetaData
-    pub fn create_fh(&mut self) {
-        let user_name = whoami::username();
+    /// Creates Header MetaData from its comment and properties, TX key being the comment
+    pub fn create_hd(&mut self, comments: &HashMap<String, String>) {
+        let mut properties: Vec<(&String, &String)> =
+            comments.iter().filter(|(key, _)| *key != "TX").collect();
+        properties.sort();
+        let mut xml = format!(
+            "<HDcomment>\n<TX>{}</TX>\n",
+            xml_escape(comments.get("TX").map(|tx| tx.as_str()).unwrap_or_default())
+        );
+        if !properties.is_empty() {
+            xml.push_str("<common_properties>\n");
+            for (name, value) in properties {
+                xml.push_str(&format!(
+                    "<e name=\"{}\">{}</e>\n",
+                    xml_escape(name).replace('"', "&quot;"),
+                    xml_escape(value)
+                ));
+            }
+            xml.push_str("</common_properties>\n");
+        }
+        xml.push_str("</HDcomment>");
+        let raw_comments = format!("{:\0<width$}", xml, width = (xml.len() / 8 + 1) * 8);
+        self.block.hdr_len = raw_comments.len() as u64 + 24;
+        self.raw_data = raw_comments.into_bytes();
+    }
+    /// Creates File History MetaData describing the file modification
+    pub fn create_fh(&mut self, description: &str) {
+        self.create_fh_history(&FileHistory::new(description));
+    }
+    /// Creates File History MetaData from a file history entry
+    pub fn create_fh_history(&mut self, history: &FileHistory) {
+        let tag = |name: &str, value: &Option<String>| {
+            format!(
+                "<{name}>{}</{name}>\n",
+                xml_escape(value.as_deref().unwrap_or_default())
+            )
+        };
+        // user name is the only optional element
+        let user_name = history
+            .user_name
+            .as_ref()
+            .map(|_| tag("user_name", &history.user_name))
+            .unwrap_or_default();
         let comments = format!(
-            "<FHcomment>
-<TX>created</TX>
-<tool_id>mdfr</tool_id>
-<tool_vendor>ratalco</tool_vendor>
-<tool_version>0.1</tool_version>
-<user_name>{user_name}</user_name>
-</FHcomment>"
+            "<FHcomment>\n{}{}{}{}{user_name}</FHcomment>",
+            tag("TX", &history.comment),
+            tag("tool_id", &history.tool_id),
+            tag("tool_vendor", &history.tool_vendor),
+            tag("tool_version", &history.tool_version),
         );
         let raw_comments = format!(
             "{:\0<width$}",
@@ -1091,7 +1751,7 @@ pub struct Hd4 {
     /// There must be at least one FHBLOCK with information about the application which created the MDF file.
     pub hd_fh_first: i64,
     /// Pointer to first channel hierarchy block (CHBLOCK) (can be NIL).
-    hd_ch_first: i64,
+    pub hd_ch_first: i64,
     /// Pointer to first attachment block (ATBLOCK) (can be NIL)
     pub hd_at_first: i64,
     /// Pointer to first event block (EVBLOCK) (can be NIL)
@@ -1148,6 +1808,45 @@ impl Default for Hd4 {
     }
 }
 
+impl Hd4 {
+    /// recording start time, in its time zone and daylight saving offset if valid.
+    /// Start time stored as local time is returned as is with a zero offset
+    pub fn start_time(&self) -> Result<DateTime<FixedOffset>> {
+        let offset_min = if self.hd_time_flags & 0b10 != 0 {
+            // time offsets valid
+            self.hd_tz_offset_min as i32 + self.hd_dst_offset_min as i32
+        } else {
+            0
+        };
+        let offset = FixedOffset::east_opt(offset_min * 60)
+            .with_context(|| format!("invalid time offset of {offset_min} minutes"))?;
+        Ok(DateTime::from_timestamp_nanos(self.hd_start_time_ns as i64).with_timezone(&offset))
+    }
+    /// sets the recording start time with its time offset
+    pub fn set_start_time(&mut self, start_time: DateTime<FixedOffset>) -> Result<()> {
+        self.hd_start_time_ns = start_time
+            .timestamp_nanos_opt()
+            .with_context(|| format!("start time {start_time} out of range"))?
+            as u64;
+        self.hd_tz_offset_min = (start_time.offset().local_minus_utc() / 60) as i16;
+        self.hd_dst_offset_min = 0;
+        self.hd_time_flags = 0b10; // time offsets valid
+        Ok(())
+    }
+    /// copy of the header data members, without links to other blocks
+    pub(crate) fn without_links(&self) -> Hd4 {
+        Hd4 {
+            hd_dg_first: 0,
+            hd_fh_first: 0,
+            hd_ch_first: 0,
+            hd_at_first: 0,
+            hd_ev_first: 0,
+            hd_md_comment: 0,
+            ..*self
+        }
+    }
+}
+
 /// Hd4 display implementation
 impl fmt::Display for Hd4 {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -1159,17 +1858,14 @@ impl fmt::Display for Hd4 {
 }
 
 /// Hd4 block struct parser
-pub fn hd4_parser(
-    rdr: &mut SymBufReader<&File>,
+pub fn hd4_parser<R: Read + Seek>(
+    rdr: &mut SymBufReader<R>,
     sharable: &mut SharableBlocks,
 ) -> Result<(Hd4, i64)> {
     let mut buf = [0u8; 104];
     rdr.read_exact(&mut buf)
         .context("could not read HD block buffer")?;
-    let mut block = Cursor::new(buf);
-    let hd: Hd4 = block
-        .read_le()
-        .context("Could not parse HD block buffer into Hd4 struct")?;
+    let hd = hd4_block(&buf, 0).context("Could not parse HD block buffer into Hd4 struct")?;
     let position = read_meta_data(rdr, sharable, hd.hd_md_comment, 168, BlockType::HD)?;
     Ok((hd, position))
 }
@@ -1226,9 +1922,64 @@ impl Default for FhBlock {
     }
 }
 
+impl FhBlock {
+    /// time of the file change, in its time zone and daylight saving offset if valid
+    pub fn time(&self) -> Result<DateTime<FixedOffset>> {
+        let offset_min = if self.fh_time_flags & 0b10 != 0 {
+            // time offsets valid
+            self.fh_tz_offset_min as i32 + self.fh_dst_offset_min as i32
+        } else {
+            0
+        };
+        let offset = FixedOffset::east_opt(offset_min * 60)
+            .with_context(|| format!("invalid time offset of {offset_min} minutes"))?;
+        Ok(DateTime::from_timestamp_nanos(self.fh_time_ns as i64).with_timezone(&offset))
+    }
+    /// creates a file history block at the time of the entry, with time offsets
+    pub(crate) fn from_history(history: &FileHistory) -> Self {
+        FhBlock {
+            fh_time_ns: history.time.timestamp_nanos_opt().unwrap_or(0) as u64,
+            fh_tz_offset_min: (history.time.offset().local_minus_utc() / 60) as i16,
+            fh_time_flags: 0b10,
+            ..Default::default()
+        }
+    }
+}
+
+/// file history entry, describing a creation or modification of the file
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileHistory {
+    /// time of the change
+    pub time: DateTime<FixedOffset>,
+    /// description of the change
+    pub comment: Option<String>,
+    /// name of the tool that changed the file
+    pub tool_id: Option<String>,
+    /// vendor of the tool
+    pub tool_vendor: Option<String>,
+    /// version of the tool
+    pub tool_version: Option<String>,
+    /// name of the user who changed the file
+    pub user_name: Option<String>,
+}
+
+impl FileHistory {
+    /// entry of a change made now by mdfr
+    pub fn new(comment: &str) -> Self {
+        FileHistory {
+            time: Local::now().fixed_offset(),
+            comment: Some(comment.to_string()),
+            tool_id: Some("mdfr".to_string()),
+            tool_vendor: Some("ratalco".to_string()),
+            tool_version: Some(env!("CARGO_PKG_VERSION").to_string()),
+            user_name: Some(whoami::username()),
+        }
+    }
+}
+
 /// Fh4 (File History) block struct parser
-fn parse_fh_block(
-    rdr: &mut SymBufReader<&File>,
+fn parse_fh_block<R: Read + Seek>(
+    rdr: &mut SymBufReader<R>,
     target: i64,
     position: i64,
 ) -> Result<(FhBlock, i64)> {
@@ -1247,8 +1998,8 @@ fn parse_fh_block(
 type Fh = Vec<FhBlock>;
 
 /// parses File History blocks along with its linked comments returns a vect of Fh4 block with comments
-pub fn parse_fh(
-    rdr: &mut SymBufReader<&File>,
+pub fn parse_fh<R: Read + Seek>(
+    rdr: &mut SymBufReader<R>,
     sharable: &mut SharableBlocks,
     target: i64,
     mut position: i64,
@@ -1306,9 +2057,41 @@ pub struct At4Block {
     // followed by embedded data depending of flag
 }
 
+impl At4Block {
+    /// creates an embedded attachment block of uncompressed data with its MD5 checksum
+    pub(crate) fn new_embedded(data: &[u8]) -> Self {
+        let mut hasher = Md5::new();
+        hasher.update(data);
+        At4Block {
+            at_id: [35, 35, 65, 84], // ##AT
+            reserved: [0u8; 4],
+            at_len: 96 + data.len() as u64,
+            at_links: 4,
+            at_at_next: 0,
+            at_tx_filename: 0,
+            at_tx_mimetype: 0,
+            at_md_comment: 0,
+            at_flags: 0b101, // embedded with valid MD5 checksum
+            at_creator_index: 0,
+            at_reserved: [0u8; 4],
+            at_md5_checksum: hasher.finalize().into(),
+            at_original_size: data.len() as u64,
+            at_embedded_size: data.len() as u64,
+        }
+    }
+    /// length of the block in bytes, embedded data included
+    pub(crate) fn len(&self) -> u64 {
+        self.at_len
+    }
+    /// sets the link to next attachment block
+    pub(crate) fn set_next(&mut self, at_at_next: i64) {
+        self.at_at_next = at_at_next;
+    }
+}
+
 /// At4 (Attachment) block struct parser
-fn parser_at4_block(
-    rdr: &mut SymBufReader<&File>,
+fn parser_at4_block<R: Read + Seek>(
+    rdr: &mut SymBufReader<R>,
     target: i64,
     mut position: i64,
 ) -> Result<(At4Block, Option<Vec<u8>>, i64)> {
@@ -1339,8 +2122,8 @@ fn parser_at4_block(
 type At = HashMap<i64, (At4Block, Option<Vec<u8>>)>;
 
 /// parses Attachment blocks along with its linked comments, returns a hashmap of At4 block and attached data in a vect
-pub fn parse_at4(
-    rdr: &mut SymBufReader<&File>,
+pub fn parse_at4<R: Read + Seek>(
+    rdr: &mut SymBufReader<R>,
     sharable: &mut SharableBlocks,
     target: i64,
     mut position: i64,
@@ -1427,9 +2210,185 @@ pub struct Ev4Block {
     pub ev_sync_factor: f64,
 }
 
+impl Ev4Block {
+    /// creates an event block from an event, with positions of its name, comment and scope blocks
+    pub(crate) fn new(event: &Event, ev_tx_name: i64, ev_md_comment: i64, scope: Vec<i64>) -> Self {
+        // sync value = base * factor, integer values are kept in base
+        let (ev_sync_base_value, ev_sync_factor) =
+            if event.sync_value.fract() == 0.0 && event.sync_value.abs() < i64::MAX as f64 {
+                (event.sync_value as i64, 1.0)
+            } else {
+                (1, event.sync_value)
+            };
+        Ev4Block {
+            ev_links: 5 + scope.len() as u64,
+            ev_ev_next: 0,
+            ev_ev_parent: 0,
+            ev_ev_range: 0,
+            ev_tx_name,
+            ev_md_comment,
+            ev_scope_count: scope.len() as u32,
+            links: scope,
+            ev_type: event.event_type.into(),
+            ev_sync_type: event.sync_type,
+            ev_range_type: event.range_type,
+            ev_cause: event.cause,
+            ev_flags: 0,
+            ev_reserved: [0u8; 3],
+            ev_attachment_count: 0,
+            ev_creator_index: 0,
+            ev_sync_base_value,
+            ev_sync_factor,
+        }
+    }
+    /// length of the block in bytes, header included
+    pub(crate) fn len(&self) -> u64 {
+        24 + 8 * self.ev_links + 32
+    }
+    /// sets the link to next event block
+    pub(crate) fn set_next(&mut self, ev_ev_next: i64) {
+        self.ev_ev_next = ev_ev_next;
+    }
+    /// positions of the blocks in the event scope (CG or CN blocks)
+    pub fn scope(&self) -> &[i64] {
+        let start = (self.ev_scope_count as usize).min(self.links.len());
+        &self.links[..start]
+    }
+    /// positions of the attachment blocks referenced by the event
+    pub fn attachments(&self) -> &[i64] {
+        let start = (self.ev_scope_count as usize).min(self.links.len());
+        let end = (start + self.ev_attachment_count as usize).min(self.links.len());
+        &self.links[start..end]
+    }
+    /// synchronisation value of the event
+    pub fn sync_value(&self) -> f64 {
+        self.ev_sync_base_value as f64 * self.ev_sync_factor
+    }
+}
+
+/// Event type (ev_type)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EventType {
+    /// recording period
+    Recording,
+    /// recording interrupt
+    RecordingInterrupt,
+    /// acquisition interrupt
+    AcquisitionInterrupt,
+    /// start recording trigger
+    StartRecordingTrigger,
+    /// stop recording trigger
+    StopRecordingTrigger,
+    /// trigger
+    Trigger,
+    /// marker
+    #[default]
+    Marker,
+    /// unknown event type
+    Other(u8),
+}
+
+impl From<u8> for EventType {
+    fn from(ev_type: u8) -> Self {
+        match ev_type {
+            0 => EventType::Recording,
+            1 => EventType::RecordingInterrupt,
+            2 => EventType::AcquisitionInterrupt,
+            3 => EventType::StartRecordingTrigger,
+            4 => EventType::StopRecordingTrigger,
+            5 => EventType::Trigger,
+            6 => EventType::Marker,
+            other => EventType::Other(other),
+        }
+    }
+}
+
+impl From<EventType> for u8 {
+    fn from(event_type: EventType) -> Self {
+        match event_type {
+            EventType::Recording => 0,
+            EventType::RecordingInterrupt => 1,
+            EventType::AcquisitionInterrupt => 2,
+            EventType::StartRecordingTrigger => 3,
+            EventType::StopRecordingTrigger => 4,
+            EventType::Trigger => 5,
+            EventType::Marker => 6,
+            EventType::Other(other) => other,
+        }
+    }
+}
+
+/// Block referenced in the event scope
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EventScope {
+    /// channel, by its name
+    Channel(String),
+    /// channel group, by the name of one of its channels
+    ChannelGroup(String),
+}
+
+/// Event with its references resolved
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Event {
+    /// event name
+    pub name: Option<String>,
+    /// event comment
+    pub comment: Option<String>,
+    /// event type
+    pub event_type: EventType,
+    /// sync type: 1 time (s), 2 angle (rad), 3 distance (m), 4 index
+    pub sync_type: u8,
+    /// range type: 0 point, 1 beginning of range, 2 end of range
+    pub range_type: u8,
+    /// cause: 0 other, 1 error, 2 tool, 3 script, 4 user
+    pub cause: u8,
+    /// synchronisation value, in unit of sync type
+    pub sync_value: f64,
+    /// channels or channel groups the event applies to, whole file if empty
+    pub scope: Vec<EventScope>,
+    /// positions of the referenced attachment blocks, not written back
+    pub attachments: Vec<i64>,
+}
+
+/// Synchronisation channel, its values locating the records in an attached file like the
+/// frames of a video
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyncChannel {
+    /// channel name
+    pub name: String,
+    /// master channel name
+    pub master: Option<String>,
+    /// sync type of the values: 1 time (s), 2 angle (rad), 3 distance (m), 4 index
+    pub sync_type: u8,
+    /// position of the referenced attachment block
+    pub attachment: i64,
+    /// path and name of the attached file
+    pub file_name: Option<String>,
+    /// MIME content type of the attached file
+    pub mime_type: Option<String>,
+}
+
+impl SyncChannel {
+    /// true if the attached file is a video, by its MIME type or file extension
+    pub fn is_video(&self) -> bool {
+        if let Some(mime_type) = &self.mime_type {
+            return mime_type.starts_with("video/");
+        }
+        self.file_name.as_ref().is_some_and(|file_name| {
+            Path::new(file_name)
+                .extension()
+                .and_then(|extension| extension.to_str())
+                .is_some_and(|extension| {
+                    ["mp4", "avi", "mkv", "mov", "webm", "mpg", "mpeg", "wmv"]
+                        .contains(&extension.to_lowercase().as_str())
+                })
+        })
+    }
+}
+
 /// Ev4 (Event) block struct parser
-fn parse_ev4_block(
-    rdr: &mut SymBufReader<&File>,
+fn parse_ev4_block<R: Read + Seek>(
+    rdr: &mut SymBufReader<R>,
     target: i64,
     mut position: i64,
 ) -> Result<(Ev4Block, i64)> {
@@ -1441,8 +2400,8 @@ fn parse_ev4_block(
 }
 
 /// parses Event blocks along with its linked comments, returns a hashmap of Ev4 block with position as key
-pub fn parse_ev4(
-    rdr: &mut SymBufReader<&File>,
+pub fn parse_ev4<R: Read + Seek>(
+    rdr: &mut SymBufReader<R>,
     sharable: &mut SharableBlocks,
     target: i64,
     mut position: i64,
@@ -1473,6 +2432,118 @@ pub fn parse_ev4(
     Ok((ev, position))
 }
 
+/// Ch4 Channel hierarchy block struct
+#[derive(Debug, Clone)]
+#[binrw]
+#[br(little)]
+#[allow(dead_code)]
+#[repr(C)]
+pub struct Ch4Block {
+    //ch_id: [u8; 4],  // CH
+    //reserved: [u8; 4],  // reserved
+    //ch_len: u64,      // Length of block in bytes
+    /// # of links
+    ch_links: u64,
+    /// Link to next sibling CHBLOCK (can be NIL)
+    pub ch_ch_next: i64,
+    /// Link to first child CHBLOCK (can be NIL)
+    pub ch_ch_first: i64,
+    /// Link to TXBLOCK with the name of the hierarchy level (can be NIL)
+    pub ch_tx_name: i64,
+    /// Link to TX/MDBLOCK with comment and other information for the hierarchy level (can be NIL)
+    pub ch_md_comment: i64,
+    #[br(if(ch_links > 4), little, count = ch_links - 4)]
+    /// references to the elements, triplets of DG, CG and CN blocks positions
+    ch_element: Vec<i64>,
+
+    /// Number of referenced elements
+    pub ch_element_count: u32,
+    /// Hierarchy type (see CH_T_xxx)
+    pub ch_type: u8,
+    /// Reserved
+    ch_reserved: [u8; 3],
+}
+
+impl Ch4Block {
+    /// positions of the referenced channel blocks
+    pub fn channels(&self) -> impl Iterator<Item = i64> + '_ {
+        self.ch_element
+            .chunks_exact(3)
+            .take(self.ch_element_count as usize)
+            .map(|triplet| triplet[2])
+    }
+}
+
+/// channel hierarchy block with its child blocks
+#[derive(Debug, Clone)]
+pub struct Ch4 {
+    /// the CH block
+    pub block: Ch4Block,
+    /// child hierarchy levels
+    pub children: Vec<Ch4>,
+}
+
+/// node of the channel tree, with its name, comment and channels resolved
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ChannelTreeNode {
+    /// name of the hierarchy level
+    pub name: Option<String>,
+    /// comment of the hierarchy level
+    pub comment: Option<String>,
+    /// hierarchy type: group, function, structure, map list, variables or calibration objects
+    pub hierarchy_type: u8,
+    /// names of the channels referenced by this level
+    pub channels: Vec<String>,
+    /// child hierarchy levels
+    pub children: Vec<ChannelTreeNode>,
+}
+
+impl ChannelTreeNode {
+    /// returns the node found following the path of hierarchy level names from this node
+    pub fn find(&self, path: &[&str]) -> Option<&ChannelTreeNode> {
+        match path.split_first() {
+            None => Some(self),
+            Some((name, rest)) => self
+                .children
+                .iter()
+                .find(|child| child.name.as_deref() == Some(*name))
+                .and_then(|child| child.find(rest)),
+        }
+    }
+    /// returns the names of the channels referenced by this node and all its descendants
+    pub fn all_channels(&self) -> HashSet<String> {
+        let mut channels: HashSet<String> = self.channels.iter().cloned().collect();
+        for child in self.children.iter() {
+            channels.extend(child.all_channels());
+        }
+        channels
+    }
+}
+
+/// parses the list of sibling CH blocks along with their names, comments and children
+pub fn parse_ch4<R: Read + Seek>(
+    rdr: &mut SymBufReader<R>,
+    sharable: &mut SharableBlocks,
+    mut target: i64,
+    mut position: i64,
+) -> Result<(Vec<Ch4>, i64)> {
+    let mut ch: Vec<Ch4> = Vec::new();
+    while target > 0 {
+        let (mut block, _header, pos) = parse_block_short(rdr, target, position)?;
+        position = pos;
+        let block: Ch4Block = block.read_le().context("Error parsing ch block")?;
+        // Reads MD
+        position = read_meta_data(rdr, sharable, block.ch_md_comment, position, BlockType::CH)?;
+        // reads TX hierarchy level name
+        position = read_meta_data(rdr, sharable, block.ch_tx_name, position, BlockType::CH)?;
+        let (children, pos) = parse_ch4(rdr, sharable, block.ch_ch_first, position)?;
+        position = pos;
+        target = block.ch_ch_next;
+        ch.push(Ch4 { block, children });
+    }
+    Ok((ch, position))
+}
+
 /// Dg4 Data Group block struct
 #[derive(Debug, Copy, Clone)]
 #[binrw]
@@ -1519,9 +2590,29 @@ impl Default for Dg4Block {
     }
 }
 
+impl Dg4Block {
+    /// true if the block length, links count and links are consistent with a file of size
+    pub(crate) fn is_plausible(&self, file_size: u64) -> bool {
+        let in_file = |link: i64| (0..file_size as i64).contains(&link);
+        &self.dg_id == b"##DG"
+            && self.dg_len == 64
+            && self.dg_links == 4
+            && self.dg_cg_first > 0
+            && in_file(self.dg_cg_first)
+            && in_file(self.dg_dg_next)
+            && in_file(self.dg_data)
+            && in_file(self.dg_md_comment)
+            && matches!(self.dg_rec_id_size, 0 | 1 | 2 | 4 | 8)
+    }
+}
+
+/// header properties identifying people or projects, anonymized
+pub(crate) const HEADER_PROPERTIES: [&str; 5] =
+    ["author", "department", "organization", "project", "subject"];
+
 /// Dg4 (Data Group) block struct parser with comments
-fn parse_dg4_block(
-    rdr: &mut SymBufReader<&File>,
+fn parse_dg4_block<R: Read + Seek>(
+    rdr: &mut SymBufReader<R>,
     sharable: &mut SharableBlocks,
     target: i64,
     mut position: i64,
@@ -1531,10 +2622,8 @@ fn parse_dg4_block(
     let mut buf = [0u8; 64];
     rdr.read_exact(&mut buf)
         .context("Could not read Dg4Blcok buffer")?;
-    let mut block = Cursor::new(buf);
-    let dg: Dg4Block = block
-        .read_le()
-        .context("Could not parse Dg4Block buffer into Dg4Block struct")?;
+    let dg = dg4_block(&buf, 0)
+        .with_context(|| format!("Could not parse Dg4Block buffer at position {target}"))?;
     position = target + 64;
 
     // Reads MD
@@ -1555,8 +2644,8 @@ pub struct Dg4 {
 }
 
 /// Parser for Dg4 and all linked blocks (cg, cn, cc, ca, si)
-pub fn parse_dg4(
-    rdr: &mut SymBufReader<&File>,
+pub fn parse_dg4<R: Read + Seek>(
+    rdr: &mut SymBufReader<R>,
     target: i64,
     mut position: i64,
     sharable: &mut SharableBlocks,
@@ -1564,46 +2653,39 @@ pub fn parse_dg4(
     let mut dg: BTreeMap<i64, Dg4> = BTreeMap::new();
     let mut n_cn: usize = 0;
     let mut n_cg: usize = 0;
-    if target > 0 {
-        let (block, pos) = parse_dg4_block(rdr, sharable, target, position)?;
-        position = pos;
-        let mut next_pointer = block.dg_dg_next;
-        let (mut cg, pos, num_cg, num_cn) = parse_cg4(
-            rdr,
-            block.dg_cg_first,
-            position,
-            sharable,
-            block.dg_rec_id_size,
-        )?;
+    let mut next_pointer = target;
+    while next_pointer > 0 {
+        let block_start = next_pointer;
+        let (dg_struct, pos, num_cg, num_cn) =
+            parse_dg4_tree(rdr, next_pointer, position, sharable)?;
+        next_pointer = dg_struct.block.dg_dg_next;
         n_cg += num_cg;
         n_cn += num_cn;
-        identify_vlsd_cg(&mut cg);
-        let dg_struct = Dg4 { block, cg };
-        dg.insert(target, dg_struct);
+        dg.insert(block_start, dg_struct);
         position = pos;
-        while next_pointer > 0 {
-            let block_start = next_pointer;
-            let (block, pos) = parse_dg4_block(rdr, sharable, next_pointer, position)?;
-            next_pointer = block.dg_dg_next;
-            position = pos;
-            let (mut cg, pos, num_cg, num_cn) = parse_cg4(
-                rdr,
-                block.dg_cg_first,
-                position,
-                sharable,
-                block.dg_rec_id_size,
-            )?;
-            n_cg += num_cg;
-            n_cn += num_cn;
-            identify_vlsd_cg(&mut cg);
-            let dg_struct = Dg4 { block, cg };
-            dg.insert(block_start, dg_struct);
-            position = pos;
-        }
     }
     Ok((dg, position, n_cg, n_cn))
 }
 
+/// Parser for a single Dg4 and its linked blocks, not following the next data group link
+pub(crate) fn parse_dg4_tree<R: Read + Seek>(
+    rdr: &mut SymBufReader<R>,
+    target: i64,
+    position: i64,
+    sharable: &mut SharableBlocks,
+) -> Result<(Dg4, i64, usize, usize)> {
+    let (block, position) = parse_dg4_block(rdr, sharable, target, position)?;
+    let (mut cg, position, n_cg, n_cn) = parse_cg4(
+        rdr,
+        block.dg_cg_first,
+        position,
+        sharable,
+        block.dg_rec_id_size,
+    )?;
+    identify_vlsd_cg(&mut cg);
+    Ok((Dg4 { block, cg }, position, n_cg, n_cn))
+}
+
 /// Try to link VLSD Channel Groups with matching channel in other groups
 fn identify_vlsd_cg(cg: &mut HashMap<u64, Cg4>) {
     // First find all VLSD Channel Groups
@@ -1762,7 +2844,7 @@ pub struct Cg4Block {
     /// Pointer to acquisition source (SIBLOCK) (can be NIL, must be NIL for VLSD CGBLOCK) See also rules for uniqueness explained in 4.4.3 Identification of Channels.
     cg_si_acq_source: i64,
     /// Pointer to first sample reduction block (SRBLOCK) (can be NIL, must be NIL for VLSD CGBLOCK)
-    cg_sr_first: i64,
+    pub cg_sr_first: i64,
     ///Pointer to comment and additional information (TXBLOCK or MDBLOCK) (can be NIL, must be NIL for VLSD CGBLOCK)
     cg_md_comment: i64,
     #[br(if(cg_links > 6))]
@@ -1808,18 +2890,47 @@ impl Default for Cg4Block {
     }
 }
 
+/// Sr4 Sample Reduction block struct
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+#[binrw]
+#[br(little)]
+#[repr(C)]
+pub struct Sr4Block {
+    /// ##SR
+    // sr_id: [u8; 4],
+    /// reserved
+    // reserved: [u8; 4],
+    /// Length of block in bytes
+    // sr_len: u64,
+    /// # of links
+    pub sr_links: u64,
+    /// Pointer to next sample reduction block (SRBLOCK) (can be NIL)
+    pub sr_sr_next: i64,
+    /// Pointer to reduction data block (RDBLOCK or DZBLOCK or DLBLOCK)
+    pub sr_data: i64,
+    /// Number of cycles, i.e. number of sample intervals in this sample reduction block
+    pub sr_cycle_count: u64,
+    /// Length of sample interval used to calculate the sample reduction records, unit given by sr_sync_type
+    pub sr_interval: f64,
+    /// Sync type: 1 time (s), 2 angle (rad), 3 distance (m), 4 index
+    pub sr_sync_type: u8,
+    /// Flags, bit 0 invalidation Bytes present, bit 1 dominant invalidation bit
+    pub sr_flags: u8,
+    /// Reserved
+    sr_reserved: [u8; 6],
+}
+
 /// Cg4 (Channel Group) block struct parser with linked comments Source Information in sharable blocks
-fn parse_cg4_block(
-    rdr: &mut SymBufReader<&File>,
+fn parse_cg4_block<R: Read + Seek>(
+    rdr: &mut SymBufReader<R>,
     target: i64,
     mut position: i64,
     sharable: &mut SharableBlocks,
     record_id_size: u8,
 ) -> Result<(Cg4, i64, usize)> {
-    let (mut block, header, pos) = parse_block_short(rdr, target, position)?;
+    let (block, header, pos) = parse_block_short(rdr, target, position)?;
     position = pos;
-    let cg: Cg4Block = block
-        .read_le()
+    let cg = cg4_members(block.get_ref(), target as u64)
         .context("Could not read buffer into Cg4Block struct")?;
 
     // Reads MD
@@ -1922,6 +3033,8 @@ impl Cg4 {
     pub fn process_all_channel_invalid_bits(&mut self) -> Result<(), Error> {
         // get invalid bytes
         let cg_inval_bytes = self.block.cg_inval_bytes as usize;
+        // position of the invalid bytes in record
+        let inval_offset = (self.record_length as usize).saturating_sub(cg_inval_bytes);
         if let Some(invalid_bytes) = &self.invalid_bytes {
             // To extract invalidity for each channel from invalid_bytes
             self.cn
@@ -1937,7 +3050,9 @@ impl Cg4 {
                                 // arrow considers bit set as valid while mdf spec considers bit set as invalid
                                 mask.set_bit(
                                     index,
-                                    (record[*invalid_byte_position] & *invalid_byte_mask) == 0,
+                                    (record[invalid_byte_position.saturating_sub(inval_offset)]
+                                        & *invalid_byte_mask)
+                                        == 0,
                                 );
                             },
                         );
@@ -1979,8 +3094,8 @@ impl Cg4 {
 }
 
 /// Cg4 blocks and linked blocks parsing
-pub fn parse_cg4(
-    rdr: &mut SymBufReader<&File>,
+pub fn parse_cg4<R: Read + Seek>(
+    rdr: &mut SymBufReader<R>,
     target: i64,
     mut position: i64,
     sharable: &mut SharableBlocks,
@@ -2028,13 +3143,13 @@ pub struct Cn4Block {
     /// # of links
     cn_links: u64,
     /// Pointer to next channel block (CNBLOCK) (can be NIL)
-    cn_cn_next: i64,
+    pub cn_cn_next: i64,
     /// Composition of channels: Pointer to channel array block (CABLOCK) or channel block (CNBLOCK) (can be NIL). Details see 4.18 Composition of Channels      
     pub cn_composition: i64,
     /// Pointer to TXBLOCK with name (identification) of channel. Name must be according to naming rules stated in 4.4.2 Naming Rules.
     pub cn_tx_name: i64,
     /// Pointer to channel source (SIBLOCK) (can be NIL) Must be NIL for component channels (members of a structure or array elements) because they all must have the same source and thus simply use the SIBLOCK of their parent CNBLOCK (direct child of CGBLOCK).
-    cn_si_source: i64,
+    pub cn_si_source: i64,
     /// Pointer to the conversion formula (CCBLOCK) (can be NIL, must be NIL for complex channel data types, i.e. for cn_data_type ≥ 10). If the pointer is NIL, this means that a 1:1 conversion is used (phys = int).  };
     pub cn_cc_conversion: i64,
     /// Pointer to channel type specific signal data For variable length data channel (cn_type = 1): unique link to signal data block (SDBLOCK) or data list block (DLBLOCK) or, only for unsorted data groups, referencing link to a VLSD channel group block (CGBLOCK). Can only be NIL if SDBLOCK would be empty. For synchronization channel (cn_type = 4): referencing link to attachment block (ATBLOCK) in global linked list of ATBLOCKs starting at hd_at_first. Cannot be NIL.
@@ -2056,11 +3171,11 @@ pub struct Cn4Block {
     /// Bit offset (0-7): first bit (=LSB) of signal value after Byte offset has been applied (see 4.21.4.2 Reading the Signal Value). If zero, the signal value is 1-Byte aligned. A value different to zero is only allowed for Integer data types (cn_data_type ≤ 3) and if the Integer signal value fits into 8 contiguous Bytes (cn_bit_count + cn_bit_offset ≤ 64). For all other cases, cn_bit_offset must be zero.
     pub cn_bit_offset: u8,
     /// Offset to first Byte in the data record that contains bits of the signal value. The offset is applied to the plain record data, i.e. skipping the record ID.
-    cn_byte_offset: u32,
+    pub cn_byte_offset: u32,
     /// Number of bits for signal value in record
     pub cn_bit_count: u32,
     /// Flags (see CN_F_xxx)
-    cn_flags: u32,
+    pub cn_flags: u32,
     /// Position of invalidation bit.
     cn_inval_bit_pos: u32,
     /// Precision for display of floating point values. 0xFF means unrestricted precision (infinite). Any other value specifies the number of decimal places to use for display of floating point values. Only valid if "precision valid" flag (bit 2) is set
@@ -2144,6 +3259,68 @@ pub struct Cn4 {
     pub shape: (Vec<usize>, Order),
     /// optional invalid mask array, invalid byte position in record, invalid byte mask
     pub invalid_mask: Option<(Option<BooleanBufferBuilder>, usize, u8)>,
+    /// raw channel data before conversion, kept if requested in read options
+    pub raw_data: Option<ChannelData>,
+    /// text statuses of partial conversions, null where conversion gave a numeric value
+    pub status: Option<ChannelData>,
+    /// physical data and status converted at first access, if conversion was deferred
+    pub(crate) conversion_cache: Option<OnceLock<(ChannelData, Option<ChannelData>)>>,
+    /// converted data moved into a spill file when reading, data being then empty
+    pub(crate) spilled: Option<SpilledData>,
+}
+
+impl Cn4 {
+    /// returns the channel physical data, raw data being converted and cached at first call
+    /// if conversion was deferred when reading
+    pub fn physical_data(&self, sharable: &SharableBlocks) -> Result<&ChannelData> {
+        match (&self.conversion_cache, &self.spilled) {
+            (Some(cache), _) => Ok(&self.converted(cache, sharable)?.0),
+            (None, Some(spilled)) => spilled
+                .data()
+                .with_context(|| format!("failed reading back channel {}", self.unique_name)),
+            (None, None) => Ok(&self.data),
+        }
+    }
+    /// returns the channel physical data like physical_data, spilled data not already in
+    /// memory being read back without being kept, for single passes over all channels
+    pub fn physical_data_uncached(
+        &self,
+        sharable: &SharableBlocks,
+    ) -> Result<Cow<'_, ChannelData>> {
+        match (&self.conversion_cache, &self.spilled) {
+            (None, Some(spilled)) => spilled
+                .read_uncached()
+                .with_context(|| format!("failed reading back channel {}", self.unique_name)),
+            _ => self.physical_data(sharable).map(Cow::Borrowed),
+        }
+    }
+    /// true if the channel data is in memory or spilled to disk
+    pub fn is_loaded(&self) -> bool {
+        !self.data.is_empty() || self.spilled.is_some()
+    }
+    /// returns the text statuses of a partial conversion, raw data being converted and
+    /// cached at first call if conversion was deferred when reading
+    pub fn status_data(&self, sharable: &SharableBlocks) -> Result<Option<&ChannelData>> {
+        match &self.conversion_cache {
+            Some(cache) => Ok(self.converted(cache, sharable)?.1.as_ref()),
+            None => Ok(self.status.as_ref()),
+        }
+    }
+    /// converts a copy of raw data into the cache if not already done
+    fn converted<'a>(
+        &self,
+        cache: &'a OnceLock<(ChannelData, Option<ChannelData>)>,
+        sharable: &SharableBlocks,
+    ) -> Result<&'a (ChannelData, Option<ChannelData>)> {
+        if let Some(converted) = cache.get() {
+            return Ok(converted);
+        }
+        let mut cn = self.clone();
+        cn.data = self.data.clone();
+        convert_channel(&mut cn, sharable)
+            .with_context(|| format!("failed converting channel {}", self.unique_name))?;
+        Ok(cache.get_or_init(|| (cn.data, cn.status)))
+    }
 }
 
 impl Clone for Cn4 {
@@ -2171,6 +3348,10 @@ impl Clone for Cn4 {
             list_size: self.list_size,
             shape: self.shape.clone(),
             invalid_mask,
+            raw_data: None,
+            status: None,
+            conversion_cache: None,
+            spilled: None,
         }
     }
 }
@@ -2182,8 +3363,8 @@ pub(crate) type CnType = HashMap<i32, Cn4>;
 type RecordLayout = (u8, u32, u32);
 
 /// creates recursively in the channel group the CN blocks and all its other linked blocks (CC, MD, TX, CA, etc.)
-pub fn parse_cn4(
-    rdr: &mut SymBufReader<&File>,
+pub fn parse_cn4<R: Read + Seek>(
+    rdr: &mut SymBufReader<R>,
     target: i64,
     mut position: i64,
     sharable: &mut SharableBlocks,
@@ -2232,8 +3413,12 @@ pub fn parse_cn4(
             cn.insert(first_rec_pos, ms);
             cn.insert(first_rec_pos + 32, days);
         } else {
-            if cn_struct.block.cn_type == 3 || cn_struct.block.cn_type == 6 {
-                // virtual channel, position in record negative
+            if cn_struct.block.cn_type == 3
+                || cn_struct.block.cn_type == 6
+                || (cn_struct.is_structure() && cn.contains_key(&first_rec_pos))
+            {
+                // virtual channel or structure overlapping its first member,
+                // position in record negative
                 first_rec_pos = -1;
                 while cn.contains_key(&first_rec_pos) {
                     first_rec_pos -= 1;
@@ -2280,8 +3465,12 @@ pub fn parse_cn4(
                 cn.insert(rec_pos, ms);
                 cn.insert(rec_pos + 32, days);
             } else {
-                if cn_struct.block.cn_type == 3 || cn_struct.block.cn_type == 6 {
-                    // virtual channel, position in record negative
+                if cn_struct.block.cn_type == 3
+                    || cn_struct.block.cn_type == 6
+                    || (cn_struct.is_structure() && cn.contains_key(&rec_pos))
+                {
+                    // virtual channel or structure overlapping its first member,
+                    // position in record negative
                     rec_pos = -1;
                     while cn.contains_key(&rec_pos) {
                         rec_pos -= 1;
@@ -2319,6 +3508,10 @@ fn can_open_date(
         list_size: 1,
         shape: (vec![1], Order::RowMajor),
         invalid_mask: None,
+        raw_data: None,
+        status: None,
+        conversion_cache: None,
+        spilled: None,
     };
     let block = Cn4Block {
         cn_links: 8,
@@ -2339,6 +3532,10 @@ fn can_open_date(
         list_size: 1,
         shape: (vec![1], Order::RowMajor),
         invalid_mask: None,
+        raw_data: None,
+        status: None,
+        conversion_cache: None,
+        spilled: None,
     };
     let block = Cn4Block {
         cn_links: 8,
@@ -2359,6 +3556,10 @@ fn can_open_date(
         list_size: 1,
         shape: (vec![1], Order::RowMajor),
         invalid_mask: None,
+        raw_data: None,
+        status: None,
+        conversion_cache: None,
+        spilled: None,
     };
     let block = Cn4Block {
         cn_links: 8,
@@ -2379,6 +3580,10 @@ fn can_open_date(
         list_size: 1,
         shape: (vec![1], Order::RowMajor),
         invalid_mask: None,
+        raw_data: None,
+        status: None,
+        conversion_cache: None,
+        spilled: None,
     };
     let block = Cn4Block {
         cn_links: 8,
@@ -2399,6 +3604,10 @@ fn can_open_date(
         list_size: 1,
         shape: (vec![1], Order::RowMajor),
         invalid_mask: None,
+        raw_data: None,
+        status: None,
+        conversion_cache: None,
+        spilled: None,
     };
     let block = Cn4Block {
         cn_links: 8,
@@ -2419,6 +3628,10 @@ fn can_open_date(
         list_size: 1,
         shape: (vec![1], Order::RowMajor),
         invalid_mask: None,
+        raw_data: None,
+        status: None,
+        conversion_cache: None,
+        spilled: None,
     };
     (date_ms, min, hour, day, month, year)
 }
@@ -2444,6 +3657,10 @@ fn can_open_time(block_position: i64, pos_byte_beg: u32, cn_byte_offset: u32) ->
         list_size: 1,
         shape: (vec![1], Order::RowMajor),
         invalid_mask: None,
+        raw_data: None,
+        status: None,
+        conversion_cache: None,
+        spilled: None,
     };
     let block = Cn4Block {
         cn_links: 8,
@@ -2464,10 +3681,21 @@ fn can_open_time(block_position: i64, pos_byte_beg: u32, cn_byte_offset: u32) ->
         list_size: 1,
         shape: (vec![1], Order::RowMajor),
         invalid_mask: None,
+        raw_data: None,
+        status: None,
+        conversion_cache: None,
+        spilled: None,
     };
     (ms, days)
 }
 
+/// escapes the xml special characters of a text element
+pub(crate) fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
 /// Simple calculation to convert bit count into equivalent bytes count
 fn calc_n_bytes_not_aligned(bitcount: u32) -> u32 {
     let mut n_bytes = bitcount / 8u32;
@@ -2478,6 +3706,16 @@ fn calc_n_bytes_not_aligned(bitcount: u32) -> u32 {
 }
 
 impl Cn4 {
+    /// true if channel is a structure composed of member channels
+    pub fn is_structure(&self) -> bool {
+        matches!(
+            self.composition,
+            Some(Composition {
+                block: Compo::CN(_),
+                ..
+            })
+        )
+    }
     /// Returns the channel source name
     fn get_cn_source_name(&self, sharable: &SharableBlocks) -> Result<Option<String>> {
         let si = sharable.si.get(&self.block.cn_si_source);
@@ -2497,34 +3735,38 @@ impl Cn4 {
 }
 
 /// Channel block parser
-fn parse_cn4_block(
-    rdr: &mut SymBufReader<&File>,
+fn parse_cn4_block<R: Read + Seek>(
+    rdr: &mut SymBufReader<R>,
     target: i64,
     mut position: i64,
     sharable: &mut SharableBlocks,
     record_layout: RecordLayout,
     cg_cycle_count: u64,
 ) -> Result<(Cn4, i64, usize, CnType)> {
-    let (record_id_size, _cg_data_bytes, cg_inval_bytes) = record_layout;
+    let (record_id_size, cg_data_bytes, cg_inval_bytes) = record_layout;
     let mut n_cn: usize = 1;
     let mut cns: HashMap<i32, Cn4> = HashMap::new();
-    let (mut block, cnheader, pos) = parse_block_short(rdr, target, position)?;
+    let (block, cnheader, pos) = parse_block_short(rdr, target, position)?;
     position = pos;
-    let block: Cn4Block = block
-        .read_le()
+    let block = cn4_members(block.get_ref(), target as u64)
         .context("Could not read buffer into Cn4Block struct")?;
 
     let pos_byte_beg = block.cn_byte_offset + record_id_size as u32;
     let n_bytes = calc_n_bytes_not_aligned(block.cn_bit_count + (block.cn_bit_offset as u32));
-    let invalid_mask: Option<(Option<BooleanBufferBuilder>, usize, u8)> = if cg_inval_bytes != 0 {
-        let invalid_byte_position = (block.cn_inval_bit_pos >> 3) as usize;
-        let invalid_byte_mask = 1 << (block.cn_inval_bit_pos & 0x07);
-        let mut buffer = BooleanBufferBuilder::new(cg_cycle_count as usize);
-        buffer.advance(cg_cycle_count as usize);
-        Some((Some(buffer), invalid_byte_position, invalid_byte_mask))
-    } else {
-        None
-    };
+    // invalidation bit is only used if flag "invalidation bit valid" (bit 1) is set
+    let invalid_mask: Option<(Option<BooleanBufferBuilder>, usize, u8)> =
+        if cg_inval_bytes != 0 && (block.cn_flags & 0b10) != 0 {
+            // invalid bytes follow the record id and data bytes
+            let invalid_byte_position = record_id_size as usize
+                + cg_data_bytes as usize
+                + (block.cn_inval_bit_pos >> 3) as usize;
+            let invalid_byte_mask = 1 << (block.cn_inval_bit_pos & 0x07);
+            let mut buffer = BooleanBufferBuilder::new(cg_cycle_count as usize);
+            buffer.advance(cg_cycle_count as usize);
+            Some((Some(buffer), invalid_byte_position, invalid_byte_mask))
+        } else {
+            None
+        };
 
     // Reads TX name
     position = read_meta_data(rdr, sharable, block.cn_tx_name, position, BlockType::CN)?;
@@ -2627,21 +3869,24 @@ fn parse_cn4_block(
         list_size,
         shape,
         invalid_mask,
+        raw_data: None,
+        status: None,
+        conversion_cache: None,
+        spilled: None,
     };
 
     Ok((cn_struct, position, n_cn, cns))
 }
 
 /// reads pointed TX or CC Block(s) pointed by cc_ref in CCBlock
-fn read_cc(
-    rdr: &mut SymBufReader<&File>,
+fn read_cc<R: Read + Seek>(
+    rdr: &mut SymBufReader<R>,
     target: &i64,
     mut position: i64,
-    mut block: Cursor<Vec<u8>>,
+    block: Cursor<Vec<u8>>,
     sharable: &mut SharableBlocks,
 ) -> Result<i64> {
-    let cc_block: Cc4Block = block
-        .read_le()
+    let cc_block = cc4_members(block.get_ref(), *target as u64)
         .context("Could nto read buffer into Cc4Block struct")?;
     position = read_meta_data(rdr, sharable, cc_block.cc_md_unit, position, BlockType::CC)?;
     position = read_meta_data(rdr, sharable, cc_block.cc_tx_name, position, BlockType::CC)?;
@@ -2723,6 +3968,89 @@ pub enum CcVal {
     Uint(#[br(count = count)] Vec<u64>),
 }
 
+impl Cc4Block {
+    /// linear conversion block, physical value = offset + factor * raw value
+    pub fn linear(offset: f64, factor: f64) -> Self {
+        Cc4Block {
+            cc_links: 4,
+            cc_tx_name: 0,
+            cc_md_unit: 0,
+            cc_md_comment: 0,
+            cc_cc_inverse: 0,
+            cc_ref: Vec::new(),
+            cc_type: 1,
+            cc_precision: 0,
+            cc_flags: 0,
+            cc_ref_count: 0,
+            cc_val_count: 2,
+            cc_phy_range_min: 0.0,
+            cc_phy_range_max: 0.0,
+            cc_val: CcVal::Real(vec![offset, factor]),
+        }
+    }
+}
+
+impl Cc4Block {
+    /// returns the description of the conversion
+    pub fn conversion_info(&self, sharable: &SharableBlocks) -> Result<ConversionInfo> {
+        let values = match &self.cc_val {
+            CcVal::Real(values) => values.clone(),
+            CcVal::Uint(masks) => masks.iter().map(|mask| *mask as f64).collect(),
+        };
+        let texts = self
+            .cc_ref
+            .iter()
+            .map(|link| sharable.get_tx(*link))
+            .collect::<Result<Vec<Option<String>>>>()?;
+        Ok(ConversionInfo {
+            conversion_type: self.cc_type,
+            name: sharable.get_tx(self.cc_tx_name)?,
+            unit: sharable.get_tx(self.cc_md_unit)?,
+            values,
+            texts,
+            phy_range: (self.cc_flags & 0b10 != 0)
+                .then_some((self.cc_phy_range_min, self.cc_phy_range_max)),
+        })
+    }
+}
+
+/// description of a channel conversion from raw to physical values
+#[derive(Debug, PartialEq, Default, Clone)]
+pub struct ConversionInfo {
+    /// conversion type: 0 identity, 1 linear, 2 rational, 3 algebraic, 4 value to value
+    /// with interpolation, 5 value to value, 6 value range to value, 7 value to text,
+    /// 8 value range to text, 9 text to value, 10 text to text, 11 bitfield text table.
+    /// mdf3 specific: 12 polynomial, 13 exponential, 14 logarithmic
+    pub conversion_type: u8,
+    /// conversion name
+    pub name: Option<String>,
+    /// physical unit
+    pub unit: Option<String>,
+    /// parameters or table values as stored in block, bit masks for bitfield text table
+    pub values: Vec<f64>,
+    /// texts or formula referenced by conversion, None for nested conversion or empty link
+    pub texts: Vec<Option<String>>,
+    /// physical values range minimum and maximum, if valid
+    pub phy_range: Option<(f64, f64)>,
+}
+
+impl ConversionInfo {
+    /// returns the value to text table as raw value to label map,
+    /// None if the conversion is not a value to text conversion
+    pub fn enum_map(&self) -> Option<HashMap<i64, String>> {
+        if self.conversion_type != 7 {
+            return None;
+        }
+        Some(
+            self.values
+                .iter()
+                .zip(self.texts.iter())
+                .filter_map(|(value, text)| Some((value.round() as i64, text.clone()?)))
+                .collect(),
+        )
+    }
+}
+
 /// Si4 Source Information block struct
 #[derive(Debug, PartialEq, Eq, Default, Copy, Clone)]
 #[binrw]
@@ -2735,25 +4063,34 @@ pub struct Si4Block {
     /// # of links
     si_links: u64,
     /// Pointer to TXBLOCK with name (identification) of source (must not be NIL). The source name must be according to naming rules stated in 4.4.2 Naming Rules.
-    si_tx_name: i64,
+    pub si_tx_name: i64,
     /// Pointer to TXBLOCK with (tool-specific) path of source (can be NIL). The path string must be according to naming rules stated in 4.4.2 Naming Rules.
-    si_tx_path: i64,
+    pub si_tx_path: i64,
     // Each tool may generate a different path string. The only purpose is to ensure uniqueness as explained in section 4.4.3 Identification of Channels. As a recommendation, the path should be a human readable string containing additional information about the source. However, the path string should not be used to store this information in order to retrieve it later by parsing the string. Instead, additional source information should be stored in generic or custom XML fields in the comment MDBLOCK si_md_comment.
     /// Pointer to source comment and additional information (TXBLOCK or MDBLOCK) (can be NIL)
-    si_md_comment: i64,
+    pub si_md_comment: i64,
 
     // Data Members
     /// Source type additional classification of source (see SI_T_xxx)
-    si_type: u8,
+    pub si_type: u8,
     /// Bus type additional classification of used bus (should be 0 for si_type ≥ 3) (see SI_BUS_xxx)
-    si_bus_type: u8,
+    pub si_bus_type: u8,
     /// Flags The value contains the following bit flags (see SI_F_xxx)):
-    si_flags: u8,
+    pub si_flags: u8,
     /// reserved
     si_reserved: [u8; 5],
 }
 
 impl Si4Block {
+    /// creates a source information block with given source and bus types, without links
+    pub fn new(si_type: u8, si_bus_type: u8) -> Self {
+        Si4Block {
+            si_links: 3,
+            si_type,
+            si_bus_type,
+            ..Default::default()
+        }
+    }
     /// returns the source name
     fn get_si_source_name(&self, sharable: &SharableBlocks) -> Result<Option<String>> {
         sharable.get_tx(self.si_tx_name)
@@ -2762,6 +4099,83 @@ impl Si4Block {
     fn get_si_path_name(&self, sharable: &SharableBlocks) -> Result<Option<String>> {
         sharable.get_tx(self.si_tx_path)
     }
+    /// returns the source information with its texts
+    pub fn source_info(&self, sharable: &SharableBlocks) -> Result<SourceInfo> {
+        Ok(SourceInfo {
+            name: self.get_si_source_name(sharable)?,
+            path: self.get_si_path_name(sharable)?,
+            comment: sharable.get_tx(self.si_md_comment)?,
+            source_type: self.si_type,
+            bus_type: self.si_bus_type,
+            flags: self.si_flags,
+        })
+    }
+}
+
+/// source information of a channel: ECU, bus, tool, etc.
+#[derive(Debug, PartialEq, Eq, Default, Clone)]
+pub struct SourceInfo {
+    /// source name
+    pub name: Option<String>,
+    /// tool specific path of source
+    pub path: Option<String>,
+    /// source comment
+    pub comment: Option<String>,
+    /// source type: 0 other, 1 ECU, 2 bus, 3 I/O, 4 tool, 5 user
+    pub source_type: u8,
+    /// bus type: 0 none, 1 other, 2 CAN, 3 LIN, 4 MOST, 5 FlexRay, 6 K-Line, 7 Ethernet, 8 USB
+    pub bus_type: u8,
+    /// flags, bit 0 set for simulated source
+    pub flags: u8,
+}
+
+impl SourceInfo {
+    /// source type name
+    pub fn source_type_name(&self) -> &'static str {
+        match self.source_type {
+            1 => "ECU",
+            2 => "bus",
+            3 => "I/O",
+            4 => "tool",
+            5 => "user",
+            _ => "other",
+        }
+    }
+    /// bus type name
+    pub fn bus_type_name(&self) -> &'static str {
+        match self.bus_type {
+            0 => "none",
+            2 => "CAN",
+            3 => "LIN",
+            4 => "MOST",
+            5 => "FlexRay",
+            6 => "K-Line",
+            7 => "Ethernet",
+            8 => "USB",
+            _ => "other",
+        }
+    }
+    /// true if source is simulated
+    pub fn is_simulated(&self) -> bool {
+        self.flags & 1 != 0
+    }
+}
+
+impl fmt::Display for SourceInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "source {} {} ({}, bus {}",
+            self.name.as_deref().unwrap_or_default(),
+            self.path.as_deref().unwrap_or_default(),
+            self.source_type_name(),
+            self.bus_type_name()
+        )?;
+        if self.is_simulated() {
+            write!(f, ", simulated")?;
+        }
+        write!(f, ")")
+    }
 }
 
 /// Ca4 Channel Array block struct
@@ -2876,6 +4290,20 @@ impl Default for Ca4BlockMembers {
     }
 }
 
+impl From<&Ca4Block> for Ca4BlockMembers {
+    fn from(block: &Ca4Block) -> Self {
+        Self {
+            ca_type: block.ca_type,
+            ca_storage: block.ca_storage,
+            ca_ndim: block.ca_ndim,
+            ca_flags: block.ca_flags,
+            ca_byte_offset_base: block.ca_byte_offset_base,
+            ca_inval_bit_pos_base: block.ca_inval_bit_pos_base,
+            ca_dim_size: block.ca_dim_size.clone(),
+        }
+    }
+}
+
 /// Channel Array block parser
 fn parse_ca_block(
     ca_block: &mut Cursor<Vec<u8>>,
@@ -3054,14 +4482,13 @@ pub struct Composition {
 #[repr(C)]
 pub enum Compo {
     CA(Box<Ca4Block>),
-    #[allow(dead_code)]
     CN(Box<Cn4>),
 }
 
 /// parses CN (structure) of CA (Array) blocks
 /// CN (structures of composed channels )and CA (array of arrays) blocks can be nested or vene CA and CN nested and mixed: this is not supported, very complicated
-fn parse_composition(
-    rdr: &mut SymBufReader<&File>,
+fn parse_composition<R: Read + Seek>(
+    rdr: &mut SymBufReader<R>,
     target: i64,
     mut position: i64,
     sharable: &mut SharableBlocks,
@@ -3165,58 +4592,85 @@ fn parse_composition(
     }
 }
 
-/// parses mdfinfo structure to make channel names unique
-/// creates channel names set and links master channels to set of channels
+/// parses mdfinfo structure to make channel names unique according to the duplicate policy
+/// creates channel names set and links master channels to set of channels.
+/// Returns also the channels renamed or dropped
 pub fn build_channel_db(
     dg: &mut BTreeMap<i64, Dg4>,
     sharable: &SharableBlocks,
     n_cg: usize,
     n_cn: usize,
-) -> ChannelNamesSet {
+    policy: DuplicatePolicy,
+) -> Result<(ChannelNamesSet, Vec<DuplicateChannel>)> {
     let mut channel_list: ChannelNamesSet = HashMap::with_capacity(n_cn);
     let mut master_channel_list: HashMap<i64, String> = HashMap::with_capacity(n_cg);
+    let mut duplicates: Vec<DuplicateChannel> = Vec::new();
     // creating channel list for whole file and making channel names unique
-    dg.iter_mut().for_each(|(dg_position, dg)| {
-        dg.cg.iter_mut().for_each(|(record_id, cg)| {
+    for (dg_position, dg) in dg.iter_mut() {
+        for (record_id, cg) in dg.cg.iter_mut() {
             let gn = cg.get_cg_name(sharable);
             let gs = cg.get_cg_source_name(sharable);
             let gp = cg.get_cg_source_path(sharable);
-            cg.cn.iter_mut().for_each(|(cn_record_position, cn)| {
+            let mut dropped: Vec<i32> = Vec::new();
+            for (cn_record_position, cn) in cg.cn.iter_mut() {
                 if channel_list.contains_key(&cn.unique_name) {
-                    let mut changed: bool = false;
-                    let space_char = String::from(" ");
-                    // create unique channel name
-                    if let Ok(Some(cs)) = cn.get_cn_source_name(sharable) {
-                        cn.unique_name.push_str(&space_char);
-                        cn.unique_name.push_str(&cs);
-                        changed = true;
-                    }
-                    if let Ok(Some(cp)) = cn.get_cn_source_path(sharable) {
-                        cn.unique_name.push_str(&space_char);
-                        cn.unique_name.push_str(&cp);
-                        changed = true;
-                    }
-                    if let Ok(Some(name)) = &gn {
-                        cn.unique_name.push_str(&space_char);
-                        cn.unique_name.push_str(name);
-                        changed = true;
-                    }
-                    if let Ok(Some(source)) = &gs {
-                        cn.unique_name.push_str(&space_char);
-                        cn.unique_name.push_str(source);
-                        changed = true;
-                    }
-                    if let Ok(Some(path)) = &gp {
-                        cn.unique_name.push_str(&space_char);
-                        cn.unique_name.push_str(path);
-                        changed = true;
+                    let name = cn.unique_name.clone();
+                    let is_master = cn.block.cn_type == 2 || cn.block.cn_type == 3;
+                    let mut suffixes: Vec<String> = Vec::new();
+                    let mut reason = match policy {
+                        DuplicatePolicy::Error => {
+                            bail!(MdfError::Parse(format!("channel {name} is not unique")))
+                        }
+                        DuplicatePolicy::KeepFirst if !is_master => {
+                            dropped.push(*cn_record_position);
+                            duplicates.push(DuplicateChannel {
+                                name,
+                                unique_name: None,
+                                reason: String::from("dropped, first channel kept"),
+                            });
+                            continue;
+                        }
+                        DuplicatePolicy::SuffixGroup => {
+                            match &gn {
+                                Ok(Some(group_name)) => suffixes.push(group_name.clone()),
+                                _ => suffixes.push(cg.block_position.to_string()),
+                            }
+                            String::from("suffixed by channel group")
+                        }
+                        _ => {
+                            // create unique channel name
+                            if let Ok(Some(cs)) = cn.get_cn_source_name(sharable) {
+                                suffixes.push(cs);
+                            }
+                            if let Ok(Some(cp)) = cn.get_cn_source_path(sharable) {
+                                suffixes.push(cp);
+                            }
+                            for group_name in [&gn, &gs, &gp].into_iter().flatten().flatten() {
+                                suffixes.push(group_name.clone());
+                            }
+                            String::from("suffixed by source")
+                        }
+                    };
+                    for suffix in suffixes.iter() {
+                        cn.unique_name.push(' ');
+                        cn.unique_name.push_str(suffix);
                     }
                     // No souce or path name to make channel unique
-                    if !changed || channel_list.contains_key(&cn.unique_name) {
+                    if suffixes.is_empty() || channel_list.contains_key(&cn.unique_name) {
                         // extend name with channel block position, unique
-                        cn.unique_name.push_str(&space_char);
+                        cn.unique_name.push(' ');
                         cn.unique_name.push_str(&cn.block_position.to_string());
+                        reason = if suffixes.is_empty() {
+                            String::from("suffixed by block position")
+                        } else {
+                            format!("{reason} and block position")
+                        };
                     }
+                    duplicates.push(DuplicateChannel {
+                        name,
+                        unique_name: Some(cn.unique_name.clone()),
+                        reason,
+                    });
                 };
                 channel_list.insert(
                     cn.unique_name.clone(),
@@ -3231,11 +4685,14 @@ pub fn build_channel_db(
                     // Master channel
                     master_channel_list.insert(cg.block_position, cn.unique_name.clone());
                 }
-            });
-        });
-    });
+            }
+            for rec_pos in dropped {
+                cg.cn.remove(&rec_pos);
+            }
+        }
+    }
     // identifying master channels
-    let avg_ncn_per_cg = n_cn / n_cg;
+    let avg_ncn_per_cg = n_cn.checked_div(n_cg).unwrap_or_default();
     dg.iter_mut().for_each(|(_dg_position, dg)| {
         dg.cg.iter_mut().for_each(|(_record_id, cg)| {
             let mut cg_channel_list: HashSet<String> = HashSet::with_capacity(avg_ncn_per_cg);
@@ -3259,7 +4716,7 @@ pub fn build_channel_db(
             cg.master_channel_name = master_channel_name;
         });
     });
-    channel_list
+    Ok((channel_list, duplicates))
 }
 
 /// DT4 Data List block struct, without the Id
@@ -3315,10 +4772,33 @@ pub struct Dl4Block {
     dl_distance_values: Vec<i64>,
 }
 
+#[allow(dead_code)]
+impl Dl4Block {
+    /// data list of the blocks at positions with their offsets in the data stream
+    pub fn new(dl_data: Vec<i64>, dl_offset: Vec<u64>) -> Self {
+        let count = dl_data.len();
+        Dl4Block {
+            reserved: [0; 4],
+            dl_len: 24 + 8 * (count as u64 + 1) + 8 + 8 * count as u64,
+            dl_links: count as u64 + 1,
+            dl_dl_next: 0,
+            dl_data,
+            dl_flags: 0,
+            dl_reserved: [0; 3],
+            dl_count: count as u32,
+            dl_equal_length: None,
+            dl_offset,
+            dl_time_values: Vec::new(),
+            dl_angle_values: Vec::new(),
+            dl_distance_values: Vec::new(),
+        }
+    }
+}
+
 /// parses Data List block
 /// pointing to DT, SD, RD or DZ blocks
-pub fn parser_dl4_block(
-    rdr: &mut BufReader<&File>,
+pub fn parser_dl4_block<R: Read + Seek>(
+    rdr: &mut BufReader<R>,
     target: i64,
     mut position: i64,
 ) -> Result<(Dl4Block, i64)> {
@@ -3331,33 +4811,92 @@ pub fn parser_dl4_block(
     Ok((block, position))
 }
 
+/// DZ zip type of deflate
+pub const DZ_DEFLATE: u8 = 0;
+/// DZ zip type of deflate of the transposed data
+pub const DZ_TRANSPOSED_DEFLATE: u8 = 1;
+/// DZ zip type of zstandard, as written by other tools anticipating next standard versions
+pub const DZ_ZSTD: u8 = 4;
+/// DZ zip type of zstandard of the transposed data
+pub const DZ_TRANSPOSED_ZSTD: u8 = 5;
+
+/// decompresses the data of a DZ block, given its uncompressed length
+pub type Decompressor = fn(&[u8], usize) -> Result<Vec<u8>>;
+
+/// decompressors of the DZ blocks by zip type, with the flag of transposed data
+static DECOMPRESSORS: LazyLock<RwLock<HashMap<u8, (Decompressor, bool)>>> = LazyLock::new(|| {
+    RwLock::new(HashMap::from([
+        (DZ_DEFLATE, (inflate as Decompressor, false)),
+        (DZ_TRANSPOSED_DEFLATE, (inflate as Decompressor, true)),
+        (DZ_ZSTD, (zstd_decompress as Decompressor, false)),
+        (DZ_TRANSPOSED_ZSTD, (zstd_decompress as Decompressor, true)),
+    ]))
+});
+
+/// registers the decompressor of the DZ blocks of zip_type, replacing any existing one.
+/// Transposed data is transposed back after decompression with dz_zip_parameter columns
+#[allow(dead_code)]
+pub fn register_decompressor(zip_type: u8, decompressor: Decompressor, transposed: bool) {
+    DECOMPRESSORS
+        .write()
+        .insert(zip_type, (decompressor, transposed));
+}
+
+/// zlib inflate, checking adler32 checksum
+fn inflate(compressed: &[u8], _length: usize) -> Result<Vec<u8>> {
+    let (data, checksum) = decompress(compressed, Format::Zlib)
+        .map_err(|e| MdfError::CorruptData(format!("Could not decompress data: {:?}", e)))?;
+    if Some(Adler32::from_buf(&data).finish()) != checksum {
+        return Err(MdfError::CorruptData("DZ block checksum not ok".to_string()).into());
+    }
+    Ok(data)
+}
+
+/// zstandard decompression
+fn zstd_decompress(compressed: &[u8], length: usize) -> Result<Vec<u8>> {
+    Ok(zstd::bulk::decompress(compressed, length)
+        .map_err(|e| MdfError::CorruptData(format!("Could not decompress zstd data: {e}")))?)
+}
+
 /// parses DZBlock
-pub fn parse_dz(rdr: &mut BufReader<&File>) -> Result<(Vec<u8>, Dz4Block)> {
+pub fn parse_dz<R: Read + Seek>(rdr: &mut BufReader<R>) -> Result<(Vec<u8>, Dz4Block)> {
     let block: Dz4Block = rdr
         .read_le()
         .context("Could not read into Dz4Block struct")?;
     let mut buf = vec![0u8; block.dz_data_length as usize];
     rdr.read_exact(&mut buf).context("Could not read Dz data")?;
-    let mut data: Vec<u8>;
-    let checksum: Option<u32>;
-    (data, checksum) = decompress(&buf, Format::Zlib).expect("Could not decompress data");
-    if Some(Adler32::from_buf(&data).finish()) != checksum {
-        return Err(anyhow!("Checksum not ok"));
-    }
-    if block.dz_zip_type == 1 {
-        let m = block.dz_org_data_length / block.dz_zip_parameter as u64;
-        let tail: Vec<u8> = data.split_off((m * block.dz_zip_parameter as u64) as usize);
-        let mut output = vec![0u8; (m * block.dz_zip_parameter as u64) as usize];
-        transpose::transpose(
-            &data,
-            &mut output,
-            m as usize,
-            block.dz_zip_parameter as usize,
-        );
-        data = output;
-        if !tail.is_empty() {
-            data.extend(tail);
+    let Some((decompressor, transposed)) = DECOMPRESSORS.read().get(&block.dz_zip_type).copied()
+    else {
+        return Err(MdfError::UnsupportedBlock(format!(
+            "DZ block with zip type {}",
+            block.dz_zip_type
+        ))
+        .into());
+    };
+    let mut data = decompressor(&buf, block.dz_org_data_length as usize)?;
+    if data.len() as u64 != block.dz_org_data_length {
+        return Err(MdfError::CorruptData(format!(
+            "DZ block uncompressed length {} differs from expected {}",
+            data.len(),
+            block.dz_org_data_length
+        ))
+        .into());
+    }
+    if transposed {
+        if block.dz_zip_parameter == 0 {
+            return Err(MdfError::CorruptData(
+                "DZ block transposition with zero columns".to_string(),
+            )
+            .into());
         }
+        // data stored as zip_parameter rows of m bytes, the tail not fitting is not transposed
+        let columns = block.dz_zip_parameter as usize;
+        let m = data.len() / columns;
+        let tail: Vec<u8> = data.split_off(m * columns);
+        let mut output = vec![0u8; m * columns];
+        transpose::transpose(&data, &mut output, m, columns);
+        data = output;
+        data.extend(tail);
     }
     Ok((data, block))
 }
@@ -3378,12 +4917,12 @@ pub struct Dz4Block {
     // members
     /// "DT", "SD", "RD" or "DV", "DI", "RV", "RI"
     pub dz_org_block_type: [u8; 2],
-    /// Zip algorithm, 0 deflate, 1 transpose + deflate
-    dz_zip_type: u8,
+    /// Zip algorithm, 0 deflate, 1 transpose + deflate, 4 zstd, 5 transpose + zstd
+    pub dz_zip_type: u8,
     /// reserved
     dz_reserved: u8,
-    /// Zip algorithm parameter
-    dz_zip_parameter: u32, //
+    /// Zip algorithm parameter, number of columns of transposition
+    pub dz_zip_parameter: u32, //
     /// length of uncompressed data
     pub dz_org_data_length: u64,
     /// length of compressed data
@@ -3486,8 +5025,8 @@ impl Ld4Block {
 /// parse List Data block
 /// equivalent ot DLBlock but unsorted data is not allowed
 /// pointing to DV/DI and RV/RI blocks
-pub fn parser_ld4_block(
-    rdr: &mut BufReader<&File>,
+pub fn parser_ld4_block<R: Read + Seek>(
+    rdr: &mut BufReader<R>,
     target: i64,
     mut position: i64,
 ) -> Result<(Ld4Block, i64)> {
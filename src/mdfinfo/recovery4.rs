@@ -0,0 +1,84 @@
+//! Recovery of mdf4 files with broken links: the file is swept for data group blocks
+//! that are parsed one by one, data groups with corrupted blocks being skipped.
+use std::collections::BTreeMap;
+use std::io::{Cursor, Read, Seek, SeekFrom};
+
+use anyhow::{Context, Result};
+use binrw::BinReaderExt;
+use log::{info, warn};
+
+use super::mdfinfo4::{parse_dg4_tree, Dg4, Dg4Block, SharableBlocks};
+use super::sym_buf_reader::SymBufReader;
+
+/// size of the chunks read when sweeping the file, multiple of the blocks alignment
+const SCAN_CHUNK: usize = 1 << 20;
+
+/// returns the 8 bytes aligned positions of the blocks with identifier id
+pub fn scan_blocks4<R: Read + Seek>(rdr: &mut SymBufReader<R>, id: &[u8; 4]) -> Result<Vec<u64>> {
+    let file_size = rdr
+        .seek(SeekFrom::End(0))
+        .context("Could not reach end of file")?;
+    rdr.seek(SeekFrom::Start(0))
+        .context("Could not reach start of file")?;
+    let mut positions: Vec<u64> = Vec::new();
+    let mut buf = vec![0u8; SCAN_CHUNK];
+    let mut offset: u64 = 0;
+    while offset < file_size {
+        let length = SCAN_CHUNK.min((file_size - offset) as usize);
+        rdr.read_exact(&mut buf[..length])
+            .with_context(|| format!("Could not read file at {offset}"))?;
+        positions.extend(
+            buf[..length]
+                .chunks(8)
+                .enumerate()
+                .filter(|(_, bytes)| bytes.starts_with(id))
+                .map(|(index, _)| offset + 8 * index as u64),
+        );
+        offset += length as u64;
+    }
+    Ok(positions)
+}
+
+/// rebuilds the data groups from the DG blocks found in file, without following the
+/// data groups list. Returns data groups, number of channel groups and channels
+pub fn recover_dg4<R: Read + Seek>(
+    rdr: &mut SymBufReader<R>,
+    sharable: &mut SharableBlocks,
+) -> Result<(BTreeMap<i64, Dg4>, usize, usize)> {
+    let file_size = rdr
+        .seek(SeekFrom::End(0))
+        .context("Could not reach end of file")?;
+    let candidates = scan_blocks4(rdr, b"##DG")?;
+    let mut dg: BTreeMap<i64, Dg4> = BTreeMap::new();
+    let mut n_cg: usize = 0;
+    let mut n_cn: usize = 0;
+    for target in candidates {
+        let mut buf = [0u8; 64];
+        rdr.seek(SeekFrom::Start(target))
+            .context("Could not reach DG block candidate")?;
+        if rdr.read_exact(&mut buf).is_err() {
+            continue;
+        }
+        let block: Dg4Block = Cursor::new(buf)
+            .read_le()
+            .context("Could not parse Dg4Block buffer into Dg4Block struct")?;
+        if !block.is_plausible(file_size) {
+            continue;
+        }
+        // parsers track position relatively from start
+        rdr.seek(SeekFrom::Start(0))
+            .context("Could not reach start of file")?;
+        match parse_dg4_tree(rdr, target as i64, 0, sharable) {
+            Ok((dg_struct, _, num_cg, num_cn)) => {
+                n_cg += num_cg;
+                n_cn += num_cn;
+                dg.insert(target as i64, dg_struct);
+            }
+            Err(error) => warn!("skipped corrupted data group at {target}: {error:#}"),
+        }
+    }
+    rdr.seek(SeekFrom::Start(0))
+        .context("Could not reach start of file")?;
+    info!("Recovered {} data groups by sweeping file", dg.len());
+    Ok((dg, n_cg, n_cn))
+}
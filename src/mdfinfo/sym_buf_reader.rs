@@ -194,7 +194,8 @@ where
                     self.rewind()?;
                     let n_read = self.reader.read(&mut self.buf)?;
                     self.cap = n_read;
-                    self.pos = stream_position as usize;
+                    // position beyond end of stream gives empty buffer
+                    self.pos = cmp::min(stream_position as usize, n_read);
                     return Ok(self.buffer());
                 }
             }
@@ -205,7 +206,7 @@ where
                 Ok(_) => {
                     let n_read = self.reader.read(&mut self.buf)?;
                     self.cap = n_read;
-                    self.pos = middle_of_buffer as usize;
+                    self.pos = cmp::min(middle_of_buffer as usize, n_read);
                 }
                 Err(e) => return Err(e),
             }
@@ -0,0 +1,608 @@
+//! Structural validation of mdf files without parsing them into MdfInfo:
+//! blocks graph is walked from the header to report broken links, wrong block lengths,
+//! cycle counts not matching data, overlapping blocks or records and invalid UTF-8 texts.
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs::OpenOptions;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+
+use anyhow::{bail, Context, Result};
+
+use crate::error::{block_id_string, MdfError};
+
+/// mdf4 block identifiers
+const BLOCKS4: [&[u8; 4]; 25] = [
+    b"##HD", b"##MD", b"##TX", b"##FH", b"##CH", b"##AT", b"##EV", b"##DG", b"##CG", b"##SI",
+    b"##CN", b"##CC", b"##CA", b"##DT", b"##SR", b"##RD", b"##SD", b"##DL", b"##LD", b"##DZ",
+    b"##HL", b"##DV", b"##DI", b"##RV", b"##RI",
+];
+/// number of bytes kept from mdf4 blocks data section, enough for DG, CG and DZ members
+const DATA_PREFIX4: usize = 32;
+
+/// kind of structural problem
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProblemKind {
+    /// link pointing outside of file or to a position without the expected block
+    BrokenLink,
+    /// block identifier not defined by the standard
+    UnknownBlock,
+    /// block length too short for its content or exceeding file size
+    BlockLength,
+    /// records count of channel groups not matching data size
+    CycleCount,
+    /// blocks or data records sharing the same file bytes
+    Overlap,
+    /// text or metadata block not encoded in UTF-8
+    InvalidUtf8,
+}
+
+/// structural problem found at a file position
+#[derive(Debug, Clone)]
+pub struct Problem {
+    pub kind: ProblemKind,
+    /// position in file of the block concerned
+    pub position: u64,
+    pub message: String,
+}
+
+/// result of the file validation
+#[derive(Debug, Clone)]
+pub struct ValidationReport {
+    pub file_name: String,
+    pub version: u16,
+    /// number of blocks checked
+    pub n_blocks: usize,
+    pub problems: Vec<Problem>,
+}
+
+impl ValidationReport {
+    /// true if no problem was found
+    pub fn is_valid(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+impl fmt::Display for ValidationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "file {} version {}: {} blocks checked, {} problems",
+            self.file_name,
+            self.version,
+            self.n_blocks,
+            self.problems.len()
+        )?;
+        for problem in self.problems.iter() {
+            writeln!(
+                f,
+                "{:?} at {}: {}",
+                problem.kind, problem.position, problem.message
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// validates the structure of a mdf file
+pub fn validate_file(file_name: &str) -> Result<ValidationReport> {
+    let f = OpenOptions::new()
+        .read(true)
+        .write(false)
+        .open(file_name)
+        .map_err(MdfError::Io)
+        .with_context(|| format!("Cannot find the file {file_name}"))?;
+    validate_reader(BufReader::new(f), file_name)
+}
+
+/// validates the structure of any reader of mdf file content
+pub fn validate_reader<R: Read + Seek>(reader: R, file_name: &str) -> Result<ValidationReport> {
+    let mut validator = Validator::new(reader)?;
+    let mut id = [0u8; 64];
+    if validator.size < 64 {
+        bail!(MdfError::Parse(format!(
+            "{file_name} is too small to be a mdf file"
+        )));
+    }
+    validator.read_at(0, &mut id)?;
+    if &id[..3] != "MDF".as_bytes() {
+        bail!(MdfError::Parse(format!(
+            "{file_name} is not a mdf file, file identifier is {:?}",
+            block_id_string(&id[..8])
+        )));
+    }
+    let version = u16::from_le_bytes([id[28], id[29]]);
+    let n_blocks = if version < 400 {
+        validator.validate3()?
+    } else {
+        validator.validate4()?
+    };
+    Ok(ValidationReport {
+        file_name: file_name.to_string(),
+        version,
+        n_blocks,
+        problems: validator.problems,
+    })
+}
+
+/// mdf4 block with its links and beginning of data section
+struct Block4 {
+    id: [u8; 4],
+    len: u64,
+    links: Vec<u64>,
+    data: Vec<u8>,
+}
+
+impl Block4 {
+    fn u64_at(&self, offset: usize) -> u64 {
+        self.data
+            .get(offset..offset + 8)
+            .map_or(0, |b| u64::from_le_bytes(b.try_into().unwrap_or_default()))
+    }
+    fn u32_at(&self, offset: usize) -> u32 {
+        self.data
+            .get(offset..offset + 4)
+            .map_or(0, |b| u32::from_le_bytes(b.try_into().unwrap_or_default()))
+    }
+    fn link(&self, index: usize) -> u64 {
+        self.links.get(index).copied().unwrap_or(0)
+    }
+}
+
+/// file range occupied by a block or data records, used to find overlaps
+struct Range {
+    start: u64,
+    len: u64,
+    label: String,
+}
+
+struct Validator<R> {
+    rdr: R,
+    size: u64,
+    /// positions of the blocks already checked
+    visited: HashSet<u64>,
+    problems: Vec<Problem>,
+}
+
+impl<R: Read + Seek> Validator<R> {
+    fn new(mut rdr: R) -> Result<Self> {
+        let size = rdr
+            .seek(SeekFrom::End(0))
+            .context("Could not reach end of file")?;
+        Ok(Validator {
+            rdr,
+            size,
+            visited: HashSet::new(),
+            problems: Vec::new(),
+        })
+    }
+    fn read_at(&mut self, position: u64, buf: &mut [u8]) -> Result<()> {
+        self.rdr
+            .seek(SeekFrom::Start(position))
+            .context("Could not reach block position")?;
+        self.rdr
+            .read_exact(buf)
+            .with_context(|| format!("Could not read {} bytes at {position}", buf.len()))
+    }
+    fn problem(&mut self, kind: ProblemKind, position: u64, message: String) {
+        self.problems.push(Problem {
+            kind,
+            position,
+            message,
+        });
+    }
+
+    /// reads a mdf4 block linked from origin, None if it is not valid
+    fn block4(&mut self, target: u64, origin: u64) -> Result<Option<Block4>> {
+        if target.saturating_add(24) > self.size {
+            self.problem(
+                ProblemKind::BrokenLink,
+                origin,
+                format!("link to {target} is beyond file size {}", self.size),
+            );
+            return Ok(None);
+        }
+        let mut header = [0u8; 24];
+        self.read_at(target, &mut header)?;
+        let mut id = [0u8; 4];
+        id.copy_from_slice(&header[..4]);
+        let len = u64::from_le_bytes(header[8..16].try_into().unwrap_or_default());
+        let n_links = u64::from_le_bytes(header[16..24].try_into().unwrap_or_default());
+        if &id[..2] != b"##" {
+            self.problem(
+                ProblemKind::BrokenLink,
+                origin,
+                format!("link to {target} is not pointing to a block header"),
+            );
+            return Ok(None);
+        }
+        let name = block_id_string(&id);
+        if !BLOCKS4.contains(&&id) {
+            self.problem(
+                ProblemKind::UnknownBlock,
+                target,
+                format!("unknown block {name}"),
+            );
+            return Ok(None);
+        }
+        if n_links.saturating_mul(8).saturating_add(24) > len {
+            self.problem(
+                ProblemKind::BlockLength,
+                target,
+                format!("block {name} length {len} is too short for its {n_links} links"),
+            );
+            return Ok(None);
+        }
+        if target.saturating_add(len) > self.size {
+            self.problem(
+                ProblemKind::BlockLength,
+                target,
+                format!("block {name} length {len} exceeds file size {}", self.size),
+            );
+            return Ok(None);
+        }
+        let mut links = vec![0u8; n_links as usize * 8];
+        self.read_at(target + 24, &mut links)?;
+        let links = links
+            .chunks(8)
+            .map(|l| u64::from_le_bytes(l.try_into().unwrap_or_default()))
+            .collect();
+        let data_len = (len - 24 - n_links * 8) as usize;
+        let mut data;
+        if &id == b"##TX" || &id == b"##MD" {
+            data = vec![0u8; data_len];
+            self.read_at(target + 24 + n_links * 8, &mut data)?;
+            let text = data.split(|b| *b == 0).next().unwrap_or_default();
+            if let Err(e) = std::str::from_utf8(text) {
+                self.problem(
+                    ProblemKind::InvalidUtf8,
+                    target,
+                    format!("block {name} has invalid UTF-8 at byte {}", e.valid_up_to()),
+                );
+            }
+            data.clear();
+        } else {
+            data = vec![0u8; data_len.min(DATA_PREFIX4)];
+            self.read_at(target + 24 + n_links * 8, &mut data)?;
+        }
+        Ok(Some(Block4 {
+            id,
+            len,
+            links,
+            data,
+        }))
+    }
+
+    /// walks all mdf4 blocks linked from header, returns the number of blocks
+    fn validate4(&mut self) -> Result<usize> {
+        let mut blocks: HashMap<u64, Block4> = HashMap::new();
+        let mut stack: Vec<(u64, u64)> = vec![(64, 0)];
+        while let Some((target, origin)) = stack.pop() {
+            if !self.visited.insert(target) {
+                continue;
+            }
+            if let Some(block) = self.block4(target, origin)? {
+                block
+                    .links
+                    .iter()
+                    .rev()
+                    .filter(|link| **link != 0)
+                    .for_each(|link| stack.push((*link, target)));
+                blocks.insert(target, block);
+            }
+        }
+        if blocks.get(&64).map(|b| &b.id) != Some(b"##HD") {
+            self.problem(
+                ProblemKind::BrokenLink,
+                0,
+                "no header block after identification block".to_string(),
+            );
+            return Ok(blocks.len());
+        }
+        let mut ranges: Vec<Range> = blocks
+            .iter()
+            .map(|(position, block)| Range {
+                start: *position,
+                len: block.len,
+                label: format!("block {} at {position}", block_id_string(&block.id)),
+            })
+            .collect();
+        ranges.push(Range {
+            start: 0,
+            len: 64,
+            label: "identification block".to_string(),
+        });
+        self.overlaps(ranges);
+        self.cycle_counts4(&blocks);
+        Ok(blocks.len())
+    }
+
+    /// compares channel groups records size with the data blocks size
+    fn cycle_counts4(&mut self, blocks: &HashMap<u64, Block4>) {
+        let mut dg_position = blocks.get(&64).map_or(0, |hd| hd.link(0));
+        let mut dg_visited = HashSet::new();
+        while let Some(dg) = blocks.get(&dg_position).filter(|b| &b.id == b"##DG") {
+            if !dg_visited.insert(dg_position) {
+                break;
+            }
+            let record_id_size = dg.data.first().copied().unwrap_or(0) as u64;
+            let mut records_size = 0u64;
+            let mut n_cg = 0;
+            let mut vlsd = false;
+            let mut cg_position = dg.link(1);
+            let mut cg_visited = HashSet::new();
+            while let Some(cg) = blocks.get(&cg_position).filter(|b| &b.id == b"##CG") {
+                if !cg_visited.insert(cg_position) {
+                    break;
+                }
+                let cycle_count = cg.u64_at(8);
+                let flags = cg.data.get(16).copied().unwrap_or(0);
+                if flags & 1 == 1 {
+                    vlsd = true;
+                } else {
+                    let record_size = record_id_size + cg.u32_at(24) as u64 + cg.u32_at(28) as u64;
+                    records_size = records_size.saturating_add(cycle_count * record_size);
+                }
+                n_cg += 1;
+                cg_position = cg.link(0);
+            }
+            let data = dg.link(2);
+            let data_size = if data == 0 {
+                Some(0)
+            } else {
+                data_length4(blocks, data)
+            };
+            if let Some(data_size) = data_size {
+                if n_cg == 1 && !vlsd && data_size != records_size {
+                    self.problem(
+                        ProblemKind::CycleCount,
+                        dg_position,
+                        format!("channel group records need {records_size} bytes but data blocks contain {data_size} bytes"),
+                    );
+                } else if data_size < records_size {
+                    self.problem(
+                        ProblemKind::CycleCount,
+                        dg_position,
+                        format!("channel groups records need at least {records_size} bytes but data blocks contain {data_size} bytes"),
+                    );
+                }
+            }
+            dg_position = dg.link(0);
+        }
+    }
+
+    /// reports ranges sharing file bytes
+    fn overlaps(&mut self, mut ranges: Vec<Range>) {
+        ranges.sort_by_key(|r| r.start);
+        let mut previous: Option<&Range> = None;
+        let mut overlaps = Vec::new();
+        for range in ranges.iter() {
+            if let Some(previous) = previous {
+                if previous.start + previous.len > range.start {
+                    overlaps.push((
+                        range.start,
+                        format!("{} overlaps {}", range.label, previous.label),
+                    ));
+                    if previous.start + previous.len >= range.start + range.len {
+                        continue;
+                    }
+                }
+            }
+            previous = Some(range);
+        }
+        for (position, message) in overlaps {
+            self.problem(ProblemKind::Overlap, position, message);
+        }
+    }
+
+    /// reads a mdf3 block linked from origin, None if it is not the expected block
+    fn block3(
+        &mut self,
+        target: u64,
+        origin: u64,
+        expected: &[u8; 2],
+        min_len: u64,
+    ) -> Result<Option<Vec<u8>>> {
+        let name = block_id_string(expected);
+        if target + 4 > self.size {
+            self.problem(
+                ProblemKind::BrokenLink,
+                origin,
+                format!(
+                    "link to {name} block at {target} is beyond file size {}",
+                    self.size
+                ),
+            );
+            return Ok(None);
+        }
+        let mut header = [0u8; 4];
+        self.read_at(target, &mut header)?;
+        if &header[..2] != expected {
+            self.problem(
+                ProblemKind::BrokenLink,
+                origin,
+                format!(
+                    "link to {name} block at {target} is pointing to {:?}",
+                    block_id_string(&header[..2])
+                ),
+            );
+            return Ok(None);
+        }
+        let len = u16::from_le_bytes([header[2], header[3]]) as u64;
+        if len < min_len {
+            self.problem(
+                ProblemKind::BlockLength,
+                target,
+                format!("block {name} length {len} is shorter than {min_len}"),
+            );
+            return Ok(None);
+        }
+        if target + len > self.size {
+            self.problem(
+                ProblemKind::BlockLength,
+                target,
+                format!("block {name} length {len} exceeds file size {}", self.size),
+            );
+            return Ok(None);
+        }
+        let mut block = vec![0u8; len as usize];
+        self.read_at(target, &mut block)?;
+        Ok(Some(block))
+    }
+
+    /// checks an optional mdf3 block and records its range
+    fn linked3(
+        &mut self,
+        target: u32,
+        origin: u64,
+        expected: &[u8; 2],
+        ranges: &mut Vec<Range>,
+    ) -> Result<()> {
+        if target != 0 && self.visited.insert(target as u64) {
+            if let Some(block) = self.block3(target as u64, origin, expected, 4)? {
+                ranges.push(Range {
+                    start: target as u64,
+                    len: block.len() as u64,
+                    label: format!("block {} at {target}", block_id_string(expected)),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// walks the mdf3 data groups, channel groups and channels, returns the number of blocks
+    fn validate3(&mut self) -> Result<usize> {
+        let mut ranges: Vec<Range> = vec![Range {
+            start: 0,
+            len: 64,
+            label: "identification block".to_string(),
+        }];
+        let mut n_blocks = 0;
+        let Some(hd) = self.block3(64, 0, b"HD", 164)? else {
+            return Ok(n_blocks);
+        };
+        n_blocks += 1;
+        ranges.push(Range {
+            start: 64,
+            len: hd.len() as u64,
+            label: "block HD at 64".to_string(),
+        });
+        self.linked3(u32_at(&hd, 8), 64, b"TX", &mut ranges)?;
+        self.linked3(u32_at(&hd, 12), 64, b"PR", &mut ranges)?;
+        let mut data_ranges: Vec<Range> = Vec::new();
+        let mut origin = 64u64;
+        let mut dg_position = u32_at(&hd, 4) as u64;
+        while dg_position != 0 && self.visited.insert(dg_position) {
+            let Some(dg) = self.block3(dg_position, origin, b"DG", 24)? else {
+                break;
+            };
+            ranges.push(Range {
+                start: dg_position,
+                len: dg.len() as u64,
+                label: format!("block DG at {dg_position}"),
+            });
+            self.linked3(u32_at(&dg, 12), dg_position, b"TR", &mut ranges)?;
+            let n_record_ids = u16_at(&dg, 22) as u64;
+            let mut records_size = 0u64;
+            let mut cg_origin = dg_position;
+            let mut cg_position = u32_at(&dg, 8) as u64;
+            while cg_position != 0 && self.visited.insert(cg_position) {
+                let Some(cg) = self.block3(cg_position, cg_origin, b"CG", 26)? else {
+                    break;
+                };
+                ranges.push(Range {
+                    start: cg_position,
+                    len: cg.len() as u64,
+                    label: format!("block CG at {cg_position}"),
+                });
+                self.linked3(u32_at(&cg, 12), cg_position, b"TX", &mut ranges)?;
+                let record_size = u16_at(&cg, 20) as u64 + n_record_ids;
+                records_size += u32_at(&cg, 22) as u64 * record_size;
+                let mut cn_origin = cg_position;
+                let mut cn_position = u32_at(&cg, 8) as u64;
+                while cn_position != 0 && self.visited.insert(cn_position) {
+                    let Some(cn) = self.block3(cn_position, cn_origin, b"CN", 218)? else {
+                        break;
+                    };
+                    ranges.push(Range {
+                        start: cn_position,
+                        len: cn.len() as u64,
+                        label: format!("block CN at {cn_position}"),
+                    });
+                    self.linked3(u32_at(&cn, 8), cn_position, b"CC", &mut ranges)?;
+                    self.linked3(u32_at(&cn, 12), cn_position, b"CE", &mut ranges)?;
+                    self.linked3(u32_at(&cn, 16), cn_position, b"CD", &mut ranges)?;
+                    self.linked3(u32_at(&cn, 20), cn_position, b"TX", &mut ranges)?;
+                    if cn.len() >= 226 {
+                        self.linked3(u32_at(&cn, 218), cn_position, b"TX", &mut ranges)?;
+                        self.linked3(u32_at(&cn, 222), cn_position, b"TX", &mut ranges)?;
+                    }
+                    cn_origin = cn_position;
+                    cn_position = u32_at(&cn, 4) as u64;
+                }
+                cg_origin = cg_position;
+                cg_position = u32_at(&cg, 4) as u64;
+            }
+            let data = u32_at(&dg, 16) as u64;
+            if records_size > 0 {
+                if data == 0 || data + records_size > self.size {
+                    self.problem(
+                        ProblemKind::CycleCount,
+                        dg_position,
+                        format!("channel groups records need {records_size} bytes from position {data}, beyond file size {}", self.size),
+                    );
+                } else {
+                    data_ranges.push(Range {
+                        start: data,
+                        len: records_size,
+                        label: format!("records of DG at {dg_position}"),
+                    });
+                }
+            }
+            origin = dg_position;
+            dg_position = u32_at(&dg, 4) as u64;
+        }
+        n_blocks += ranges.len() - 2;
+        ranges.extend(data_ranges);
+        self.overlaps(ranges);
+        Ok(n_blocks)
+    }
+}
+
+/// size of the data contained in a data block or list of data blocks,
+/// None for column storage or unexpected blocks
+fn data_length4(blocks: &HashMap<u64, Block4>, position: u64) -> Option<u64> {
+    let block = blocks.get(&position)?;
+    match &block.id {
+        b"##DT" | b"##RD" | b"##SD" => Some(block.len - 24 - 8 * block.links.len() as u64),
+        b"##DZ" => Some(block.u64_at(8)),
+        b"##HL" => data_length4(blocks, block.link(0)),
+        b"##DL" => {
+            let mut length = 0u64;
+            let mut dl = Some(block);
+            let mut visited = HashSet::new();
+            while let Some(list) = dl.filter(|b| &b.id == b"##DL") {
+                for data in list.links.iter().skip(1).filter(|l| **l != 0) {
+                    if !visited.insert(*data) {
+                        return None;
+                    }
+                    length += data_length4(blocks, *data)?;
+                }
+                dl = blocks.get(&list.link(0));
+            }
+            Some(length)
+        }
+        _ => None,
+    }
+}
+
+fn u16_at(bytes: &[u8], offset: usize) -> u16 {
+    bytes
+        .get(offset..offset + 2)
+        .map_or(0, |b| u16::from_le_bytes([b[0], b[1]]))
+}
+
+fn u32_at(bytes: &[u8], offset: usize) -> u32 {
+    bytes
+        .get(offset..offset + 4)
+        .map_or(0, |b| u32::from_le_bytes(b.try_into().unwrap_or_default()))
+}
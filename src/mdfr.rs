@@ -1,21 +1,22 @@
 //! This module provides python interface using pyo3s
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::fmt::Write;
 
 use crate::data_holder::channel_data::ChannelData;
+use crate::data_holder::decimate::DecimationMethod;
 
 use crate::mdfinfo::MdfInfo;
-use crate::mdfreader::MasterSignature;
 use crate::mdfreader::Mdf;
+use crate::mdfreader::{AnonymizeOptions, MasterSignature};
 use anyhow::Context;
-use arrow::array::ArrayData;
+use arrow::array::{Array, ArrayData, RecordBatch};
 use arrow::pyarrow::PyArrowType;
 use arrow::util::display::{ArrayFormatter, FormatOptions};
 
 use crate::export::numpy::array_to_rust;
 #[cfg(feature = "polars")]
 use crate::export::polars::rust_arrow_to_py_series;
-use pyo3::exceptions::PyUnicodeDecodeError;
+use pyo3::exceptions::{PyUnicodeDecodeError, PyValueError};
 use pyo3::prelude::*;
 use pyo3::types::{IntoPyDict, PyBytes, PyDict, PyList};
 
@@ -94,7 +95,7 @@ impl Mdfr {
                     if let Some(dg) = mdfinfo3.dg.get(dg_pos) {
                         if let Some(cg) = dg.cg.get(rec_id) {
                             if let Some(cn) = cg.cn.get(cn_pos) {
-                                data = Some(&cn.data);
+                                data = cn.physical_data(&mdfinfo3.sharable).ok();
                             }
                         }
                     }
@@ -107,7 +108,7 @@ impl Mdfr {
                     if let Some(dg) = mdfinfo4.dg.get(dg_pos) {
                         if let Some(cg) = dg.cg.get(rec_id) {
                             if let Some(cn) = cg.cn.get(rec_pos) {
-                                data = Some(&cn.data);
+                                data = cn.physical_data(&mdfinfo4.sharable).ok();
                             }
                         }
                     }
@@ -169,6 +170,80 @@ df=polars.DataFrame(series)
             Ok(py_dataframe)
         })
     }
+    /// returns pyarrow record batch of the channels in memory of master's channel group
+    fn get_channel_group(&self, master_name: &str) -> PyResult<PyArrowType<RecordBatch>> {
+        let Mdfr(mdf) = self;
+        Ok(PyArrowType(mdf.get_channel_group(master_name)?))
+    }
+    /// returns the channel's data reduced to at most max_points for display, buckets of records
+    /// reduced by method "minmax", "mean" or "nth"
+    #[pyo3(signature = (channel_name, max_points, method="minmax"))]
+    fn get_channel_data_decimated(
+        &self,
+        channel_name: &str,
+        max_points: usize,
+        method: &str,
+    ) -> PyResult<PyArrowType<ArrayData>> {
+        let Mdfr(mdf) = self;
+        let method = match method.to_lowercase().as_str() {
+            "minmax" => DecimationMethod::MinMax,
+            "mean" => DecimationMethod::Mean,
+            "nth" => DecimationMethod::Nth,
+            _ => {
+                return Err(PyValueError::new_err(format!(
+                    "unknown decimation method {method}, expected minmax, mean or nth"
+                )))
+            }
+        };
+        let data = mdf.get_channel_data_decimated(channel_name, max_points, method)?;
+        Ok(PyArrowType(data.to_data()))
+    }
+    /// returns the channel's statistics as a dict with min, max, mean, std, count and nan_count
+    fn get_channel_stats(&mut self, channel_name: &str) -> PyResult<Py<PyAny>> {
+        let Mdfr(mdf) = self;
+        let stats = mdf.get_channel_stats(channel_name)?;
+        pyo3::Python::with_gil(|py| -> PyResult<Py<PyAny>> {
+            let dict = PyDict::new(py);
+            dict.set_item("min", stats.min)?;
+            dict.set_item("max", stats.max)?;
+            dict.set_item("mean", stats.mean)?;
+            dict.set_item("std", stats.std)?;
+            dict.set_item("count", stats.count)?;
+            dict.set_item("nan_count", stats.nan_count)?;
+            Ok(dict.into())
+        })
+    }
+    /// returns sorted channel names with name, unit, description or source matching
+    /// the wildcard pattern, or the regular expression if regex is true
+    #[pyo3(signature = (pattern, regex=false))]
+    fn search_channels(&self, pattern: &str, regex: bool) -> PyResult<Vec<String>> {
+        let Mdfr(mdf) = self;
+        if regex {
+            Ok(mdf.search_channels_regex(pattern)?)
+        } else {
+            Ok(mdf.search_channels(pattern)?)
+        }
+    }
+    /// returns channel's source information dict with name, path, comment, type, bus and flags
+    fn get_channel_source(&self, channel_name: &str) -> PyResult<Py<PyAny>> {
+        let Mdfr(mdf) = self;
+        let source = mdf.get_channel_source(channel_name)?;
+        Python::with_gil(|py| {
+            Ok(match source {
+                Some(source) => {
+                    let dict = PyDict::new(py);
+                    dict.set_item("name", source.name.clone())?;
+                    dict.set_item("path", source.path.clone())?;
+                    dict.set_item("comment", source.comment.clone())?;
+                    dict.set_item("type", source.source_type_name())?;
+                    dict.set_item("bus", source.bus_type_name())?;
+                    dict.set_item("flags", source.flags)?;
+                    dict.into()
+                }
+                None => Python::None(py),
+            })
+        })
+    }
     /// returns channel's unit string
     fn get_channel_unit(&self, channel_name: String) -> PyResult<Option<String>> {
         let Mdfr(mdf) = self;
@@ -265,6 +340,17 @@ df=polars.DataFrame(series)
         let Mdfr(mdf) = self;
         Ok(Mdfr(mdf.write(file_name, compression)?))
     }
+    /// writes mdf3 file from mdf3 input, keeping conversions and raw data
+    pub fn write_mdf3(&self, file_name: &str) -> PyResult<Mdfr> {
+        let Mdfr(mdf) = self;
+        Ok(Mdfr(mdf.write_mdf3(file_name)?))
+    }
+    /// decodes CAN frames with a DBC file, adding the signals channels in memory.
+    /// Returns the list of added channel names
+    pub fn decode_can_logging(&mut self, dbc_file: &str) -> PyResult<Vec<String>> {
+        let Mdfr(mdf) = self;
+        Ok(mdf.decode_can_logging(dbc_file)?)
+    }
     /// Adds a new channel in memory (no file modification)
     /// Master must be a dict with keys name, type and flag
     /// Data  has to be a PyArrow
@@ -323,6 +409,17 @@ df=polars.DataFrame(series)
         let Mdfr(mdf) = self;
         mdf.rename_channel(channel_name, new_name);
     }
+    /// renames in memory the channels from a dict of current to new names,
+    /// returns the number of channels renamed
+    pub fn apply_channel_mapping(&mut self, mapping: HashMap<String, String>) -> PyResult<usize> {
+        let Mdfr(mdf) = self;
+        Ok(mdf.apply_channel_mapping(&mapping)?)
+    }
+    /// renames in memory the channels from a csv file of current and new names per line
+    pub fn apply_channel_mapping_file(&mut self, file_name: &str) -> PyResult<usize> {
+        let Mdfr(mdf) = self;
+        Ok(mdf.apply_channel_mapping_file(file_name)?)
+    }
     /// Sets the channel unit in memory
     pub fn set_channel_unit(&mut self, channel_name: &str, unit: &str) {
         let Mdfr(mdf) = self;
@@ -333,6 +430,42 @@ df=polars.DataFrame(series)
         let Mdfr(mdf) = self;
         mdf.set_channel_desc(channel_name, desc);
     }
+    /// saves in place the channels metadata modified in memory into the mdf4 file
+    pub fn save_metadata(&mut self) -> PyResult<usize> {
+        let Mdfr(mdf) = self;
+        Ok(mdf.save_metadata()?)
+    }
+    /// adds time master channels to the channel groups without master from the sample rate
+    /// in Hz, by default the mdf3 channels sampling rate, returns the masters names
+    #[pyo3(signature = (sample_rate=None))]
+    pub fn synthesize_masters(&mut self, sample_rate: Option<f64>) -> PyResult<Vec<String>> {
+        let Mdfr(mdf) = self;
+        Ok(mdf.synthesize_masters(sample_rate)?)
+    }
+    /// strips or replaces header properties, comments, sources paths, gps channels and
+    /// attachments before sharing the file, returns the names of the channels removed
+    #[pyo3(signature = (replacement=None, comments=true, source_paths=true, gps_patterns=None, attachments=true))]
+    pub fn anonymize(
+        &mut self,
+        replacement: Option<String>,
+        comments: bool,
+        source_paths: bool,
+        gps_patterns: Option<Vec<String>>,
+        attachments: bool,
+    ) -> Vec<String> {
+        let Mdfr(mdf) = self;
+        let mut options = AnonymizeOptions {
+            replacement,
+            comments,
+            source_paths,
+            attachments,
+            ..Default::default()
+        };
+        if let Some(gps_patterns) = gps_patterns {
+            options.gps_patterns = gps_patterns;
+        }
+        mdf.anonymize(&options)
+    }
     /// list attachments
     pub fn list_attachments(&mut self) -> PyResult<String> {
         let Mdfr(mdf) = self;
@@ -1,34 +1,91 @@
 //! This module contains the data reading features
+pub mod alignment;
 pub mod conversions3;
 pub mod conversions4;
 pub mod data_read3;
 pub mod data_read4;
+pub mod diff;
+pub mod index4;
+pub mod iterator4;
 pub mod mdfreader3;
 pub mod mdfreader4;
+pub mod monotonic;
+#[cfg(feature = "object-store")]
+pub mod range_reader;
+pub mod record4;
+pub mod segments;
+use std::borrow::Cow;
 use std::collections::{HashMap, HashSet};
 use std::fmt;
-use std::fs::{File, OpenOptions};
-use std::io::BufReader;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufReader, Cursor, Read, Seek};
+use std::path::PathBuf;
+use std::str;
 use std::sync::Arc;
 
-use anyhow::{Context, Error, Result};
-use arrow::array::Array;
+use anyhow::{bail, Context, Error, Result};
+use arrow::array::{
+    Array, ArrayRef, AsArray, RecordBatch, StructArray, TimestampNanosecondArray, UInt8Array,
+};
+use arrow::buffer::NullBuffer;
+use arrow::compute::cast;
+use arrow::datatypes::{DataType, Field, Fields, Float64Type, Schema, UInt64Type};
 use arrow::util::display::{ArrayFormatter, FormatOptions};
+use chrono::{DateTime, FixedOffset};
+use encoding_rs::WINDOWS_1252;
 use log::info;
 #[cfg(feature = "numpy")]
 use pyo3::prelude::*;
+use regex::Regex;
 
 //use crate::export::parquet::export_to_parquet;
+use crate::bus::dbc::Dbc;
+use crate::bus::decode_can_frames;
 use crate::data_holder::channel_data::try_from;
-use crate::mdfinfo::MdfInfo;
+use crate::data_holder::computed::computed_channel;
+use crate::data_holder::decimate::{decimate, DecimationMethod};
+use crate::data_holder::filter::{filter_channel, Filter};
+use crate::data_holder::resample::{resample_on_master, resample_on_raster};
+use crate::data_holder::samples::{value_at, Interpolation, SampleIterator, Value};
+use crate::data_holder::stats::{stats, Stats};
+use crate::export::geo::{detect_geo_channels, export_to_gpx, export_to_kml, GeoChannels};
+use crate::mdfinfo::mdfinfo4::{
+    ChannelTreeNode, ConversionInfo, Event, FileHistory, SourceInfo, SyncChannel,
+};
+use crate::mdfinfo::validate::{validate_file, ValidationReport};
+use crate::mdfinfo::{DuplicateChannel, GroupLayout, MdfInfo, ParseOptions};
+use crate::mdfreader::alignment::{check_alignment, AlignmentReport};
+use crate::mdfreader::diff::{diff, DiffReport};
+use crate::mdfreader::index4::build_index4;
+use crate::mdfreader::iterator4::ChannelGroupIterator;
 use crate::mdfreader::mdfreader3::mdfreader3;
-use crate::mdfreader::mdfreader4::mdfreader4;
-use crate::mdfwriter::mdfwriter4::mdfwriter4;
+use crate::mdfreader::mdfreader4::{
+    mdfreader4, mdfreader4_between, mdfreader4_between_from_reader, mdfreader4_parallel,
+    CHUNK_SIZE_READING_4, MEMORY_BUDGET_READING_4,
+};
+use crate::mdfreader::monotonic::{enforce_monotonic, MasterFix, MonotonicFix};
+use crate::mdfreader::record4::{FromRecord, TypedRecordIterator};
+use crate::mdfreader::segments::{detect_gaps, split_on_gaps};
+use crate::mdfwriter::finalize4::finalize4;
+use crate::mdfwriter::integrity4::{store_file_hash4, verify_file_hash4};
+use crate::mdfwriter::mdfwriter3::mdfwriter3;
+use crate::mdfwriter::mdfwriter4::{mdfwriter4, Compression};
+use crate::mdfwriter::merge4::{concatenate4, merge4, sync_files, SyncOptions};
+use crate::mdfwriter::metadata4::save_metadata4;
+use crate::parallel::install;
 
 #[cfg(feature = "parquet")]
 use crate::export::parquet::export_dataframe_to_parquet;
 #[cfg(feature = "parquet")]
-use crate::export::parquet::export_to_parquet;
+use crate::export::parquet::{export_to_parquet, export_to_parquet_with_options, ParquetOptions};
+use crate::import::csv::{csv_to_mdf, CsvOptions};
+#[cfg(feature = "parquet")]
+use crate::import::parquet::parquet_to_mdf;
+
+#[cfg(feature = "polars")]
+use crate::export::polars::record_batch_to_dataframe;
+#[cfg(feature = "polars")]
+use polars::frame::DataFrame;
 
 #[cfg(feature = "hdf5")]
 use crate::export::hdf5::export_dataframe_to_hdf5;
@@ -40,6 +97,7 @@ use crate::data_holder::arrow_helpers::{
 };
 use crate::data_holder::channel_data::ChannelData;
 use crate::data_holder::tensor_arrow::Order;
+use crate::error::MdfError;
 
 /// Main Mdf struct holding mdfinfo, arrow data and schema
 #[derive(Debug)]
@@ -47,6 +105,10 @@ use crate::data_holder::tensor_arrow::Order;
 pub struct Mdf {
     /// MdfInfo enum
     pub mdf_info: MdfInfo,
+    /// channels statistics computed, kept until the channel data is modified or reloaded
+    pub(crate) stats: HashMap<String, Stats>,
+    /// changes made to non monotonic master channels, by loading or enforcement
+    pub(crate) master_fixes: Vec<MasterFix>,
 }
 
 /// data generic description
@@ -74,15 +136,296 @@ pub struct MasterSignature {
     pub(crate) master_flag: bool,
 }
 
+/// tuning of data reading for the memory and cores available
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReadOptions {
+    /// size in bytes of the data chunks read and processed at once
+    pub chunk_size: usize,
+    /// maximum number of threads reading and converting data, None for all cores.
+    /// Data is read in the calling thread with the single-thread feature
+    pub max_parallelism: Option<usize>,
+    /// maximum estimated size in bytes of the mdf4 data groups read at the same time
+    pub memory_budget: usize,
+    /// string channels are read and decoded after the other channels, in a sequential pass
+    pub lazy_strings: bool,
+    /// decoding of UTF-8 string channels containing invalid bytes
+    pub string_policy: StringPolicy,
+    /// raw values are kept in memory alongside the converted physical values
+    pub keep_raw: bool,
+    /// channels are converted into physical values at first access of their data
+    pub lazy_conversion: bool,
+    /// conversions of channels with raw data of 32 bits or less produce f32 instead of f64
+    /// values, for channels converted when read
+    pub float32_conversions: bool,
+    /// data types forced for some channels at conversion, by channel name.
+    /// These channels are converted when read, even with lazy conversion
+    pub dtype_overrides: HashMap<String, DataTypeOverride>,
+    /// directory of temporary files into which the converted data of each mdf4 data group is
+    /// moved once read, to load files bigger than memory. Data is read back at first access
+    pub spill_directory: Option<PathBuf>,
+    /// fix of the non monotonic master channels read, like duplicate timestamps or clock
+    /// resets. Masters of the channels are then read along with them
+    pub monotonic_masters: Option<MonotonicFix>,
+}
+
+/// data type forced for a channel instead of the one resulting from its conversion
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DataTypeOverride {
+    /// floating point physical values are stored as f32 instead of f64
+    Float32,
+    /// integer raw values stay integers, possible only for linear conversions with
+    /// integer factor and offset, a warning being logged otherwise
+    Integer,
+}
+
+/// decoding policy of UTF-8 strings with invalid bytes, from dirty logger data for instance
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StringPolicy {
+    /// reading fails on invalid UTF-8
+    #[default]
+    Strict,
+    /// invalid bytes are replaced by the replacement character
+    Lossy,
+    /// strings with invalid UTF-8 are decoded as ISO-8859-1 (Windows-1252)
+    Latin1Fallback,
+}
+
+impl StringPolicy {
+    /// decodes UTF-8 bytes according to the policy
+    pub(crate) fn decode_utf8<'a>(&self, bytes: &'a [u8]) -> Result<Cow<'a, str>> {
+        match str::from_utf8(bytes) {
+            Ok(text) => Ok(Cow::Borrowed(text)),
+            Err(error) => match self {
+                StringPolicy::Strict => Err(error).context("Found invalid UTF-8"),
+                StringPolicy::Lossy => Ok(String::from_utf8_lossy(bytes)),
+                StringPolicy::Latin1Fallback => {
+                    Ok(WINDOWS_1252.decode_without_bom_handling(bytes).0)
+                }
+            },
+        }
+    }
+}
+
+impl Default for ReadOptions {
+    fn default() -> Self {
+        ReadOptions {
+            chunk_size: CHUNK_SIZE_READING_4,
+            max_parallelism: None,
+            memory_budget: MEMORY_BUDGET_READING_4,
+            lazy_strings: false,
+            string_policy: StringPolicy::default(),
+            keep_raw: false,
+            lazy_conversion: false,
+            float32_conversions: false,
+            dtype_overrides: HashMap::new(),
+            spill_directory: None,
+            monotonic_masters: None,
+        }
+    }
+}
+
+/// reads the csv mapping of current to new channel names, one pair per line separated by
+/// comma or semicolon, names can be double quoted and lines starting with # are ignored
+pub fn read_channel_mapping(file_name: &str) -> Result<HashMap<String, String>> {
+    let content = fs::read_to_string(file_name)
+        .map_err(MdfError::Io)
+        .with_context(|| format!("Cannot read channel mapping file {file_name}"))?;
+    let mut mapping: HashMap<String, String> = HashMap::new();
+    for (index, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let Some((name, new_name)) = line.split_once(',').or_else(|| line.split_once(';')) else {
+            bail!(MdfError::Parse(format!(
+                "line {} of channel mapping file {file_name} has no separator",
+                index + 1
+            )));
+        };
+        let unquote = |text: &str| text.trim().trim_matches('"').to_string();
+        if mapping.insert(unquote(name), unquote(new_name)).is_some() {
+            bail!(MdfError::Parse(format!(
+                "channel {} mapped twice in file {file_name}",
+                unquote(name)
+            )));
+        }
+    }
+    Ok(mapping)
+}
+
+/// frame of an attached video file synchronised with the recording
+#[derive(Debug, Clone, PartialEq)]
+pub struct VideoFrame {
+    /// master value of the frame, time in s for a time master
+    pub time: f64,
+    /// path and name of the video file
+    pub file_name: String,
+    /// sync type of frame: 1 time (s) in the video, 4 frame index
+    pub sync_type: u8,
+    /// frame time or index in the video file
+    pub frame: f64,
+}
+
+/// channels sharing the same source, ECU or bus
+#[derive(Debug, Clone, PartialEq)]
+pub struct SourceChannels {
+    /// source of the channels, None for channels without source
+    pub source: Option<SourceInfo>,
+    /// names of the channels, sorted
+    pub channels: Vec<String>,
+}
+
+/// metadata removed or replaced by Mdf::anonymize before sharing files
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnonymizeOptions {
+    /// text replacing the header author, organization, department, project and subject,
+    /// removed if None. File history user names are always removed
+    pub replacement: Option<String>,
+    /// removes header, data groups, channel groups, channels and events comments
+    pub comments: bool,
+    /// removes sources paths and comments, mdf3 channel extensions
+    pub source_paths: bool,
+    /// channels with name containing one of these case insensitive patterns are removed
+    pub gps_patterns: Vec<String>,
+    /// removes attachments
+    pub attachments: bool,
+}
+
+impl Default for AnonymizeOptions {
+    fn default() -> Self {
+        AnonymizeOptions {
+            replacement: None,
+            comments: true,
+            source_paths: true,
+            gps_patterns: ["gps", "latitude", "longitude"].map(String::from).to_vec(),
+            attachments: true,
+        }
+    }
+}
+
+/// header and identification set by Mdf::set_header before writing, None keeping the current value
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HeaderOptions {
+    /// program identifier of the ID block, up to 8 ascii characters
+    pub program: Option<String>,
+    /// author of the measurement
+    pub author: Option<String>,
+    /// project name
+    pub project: Option<String>,
+    /// subject or measurement object
+    pub subject: Option<String>,
+    /// comment of the measurement
+    pub comment: Option<String>,
+    /// recording start time with its time offset
+    pub start_time: Option<DateTime<FixedOffset>>,
+}
+
 #[allow(dead_code)]
 impl Mdf {
     /// returns Mdf with metadata but no data
     pub fn new(file_name: &str) -> Result<Mdf> {
         let mdf = Mdf {
             mdf_info: MdfInfo::new(file_name)?,
+            stats: HashMap::new(),
+            master_fixes: Vec::new(),
+        };
+        Ok(mdf)
+    }
+    /// finalizes in place a mdf4 file left unfinalized by its logger (cycle counts,
+    /// last data blocks lengths, file history and identifier) and returns Mdf with its metadata
+    pub fn repair_unfinalized(file_name: &str) -> Result<Mdf> {
+        finalize4(file_name).with_context(|| format!("failed finalizing file {file_name}"))?;
+        Mdf::new(file_name)
+    }
+    /// returns Mdf with data of files recorded sequentially appended one after the other.
+    /// Files must have the same channels, time master channels are offset by the recording start time
+    pub fn concatenate(files: &[&str]) -> Result<Mdf> {
+        Mdf::concatenate_with_options(files, &SyncOptions::default())
+    }
+    /// returns Mdf with data of files recorded sequentially appended one after the other,
+    /// time master channels being aligned according to options
+    pub fn concatenate_with_options(files: &[&str], options: &SyncOptions) -> Result<Mdf> {
+        concatenate4(files, options).context("failed concatenating files")
+    }
+    /// returns Mdf with the union of the channel groups of the files.
+    /// Time master channels are offset by the recording start time of the first file
+    pub fn merge(files: &[&str]) -> Result<Mdf> {
+        Mdf::merge_with_options(files, &SyncOptions::default())
+    }
+    /// returns Mdf with the union of the channel groups of the files,
+    /// time master channels being aligned according to options
+    pub fn merge_with_options(files: &[&str], options: &SyncOptions) -> Result<Mdf> {
+        merge4(files, options).context("failed merging files")
+    }
+    /// returns the time offsets in seconds of the files relative to the first one, from their
+    /// absolute start times or from sync_channel recording a time common to all files
+    pub fn sync_files(files: &[Mdf], sync_channel: Option<&str>) -> Result<Vec<f64>> {
+        sync_files(files, sync_channel).context("failed synchronizing files")
+    }
+    /// returns Mdf with metadata but no data, mdf4 corrupted blocks being skipped and
+    /// data groups recovered by sweeping the file for their blocks if links are broken
+    pub fn new_with_recovery(file_name: &str) -> Result<Mdf> {
+        let mdf = Mdf {
+            mdf_info: MdfInfo::new_with_recovery(file_name)?,
+            stats: HashMap::new(),
+            master_fixes: Vec::new(),
         };
         Ok(mdf)
     }
+    /// returns Mdf with metadata but no data, parsed with options
+    pub fn new_with_options(file_name: &str, options: &ParseOptions) -> Result<Mdf> {
+        let mdf = Mdf {
+            mdf_info: MdfInfo::new_with_options(file_name, options)?,
+            stats: HashMap::new(),
+            master_fixes: Vec::new(),
+        };
+        Ok(mdf)
+    }
+    /// returns Mdf with metadata but no data from the mdf file content in memory,
+    /// file_name is only used to identify the content
+    pub fn from_bytes(bytes: &[u8], file_name: &str) -> Result<Mdf> {
+        let mdf = Mdf {
+            mdf_info: MdfInfo::from_bytes(bytes, file_name)?,
+            stats: HashMap::new(),
+            master_fixes: Vec::new(),
+        };
+        Ok(mdf)
+    }
+    /// returns Mdf with metadata but no data from any reader of the mdf file content,
+    /// a RangeReader of remote storage for instance. file_name is only used to identify
+    /// the content, data being then loaded with load_channels_data_from_reader
+    pub fn from_reader<R: Read + Seek>(reader: R, file_name: &str) -> Result<Mdf> {
+        let mdf = Mdf {
+            mdf_info: MdfInfo::from_reader(reader, file_name)?,
+            stats: HashMap::new(),
+            master_fixes: Vec::new(),
+        };
+        Ok(mdf)
+    }
+    /// returns Mdf with metadata but no data from any reader of the mdf file content,
+    /// parsed with options
+    pub fn from_reader_with_options<R: Read + Seek>(
+        reader: R,
+        file_name: &str,
+        options: &ParseOptions,
+    ) -> Result<Mdf> {
+        let mdf = Mdf {
+            mdf_info: MdfInfo::from_reader_with_options(reader, file_name, options)?,
+            stats: HashMap::new(),
+            master_fixes: Vec::new(),
+        };
+        Ok(mdf)
+    }
+    /// returns Mdf in memory with one channel per column of the parquet file, all sharing
+    /// the given master channel or else the master channel of mdfr exported files
+    #[cfg(feature = "parquet")]
+    pub fn from_parquet(file_name: &str, master_channel: Option<&str>) -> Result<Mdf> {
+        parquet_to_mdf(file_name, master_channel)
+    }
+    /// returns Mdf in memory with one channel per column of the csv file
+    pub fn from_csv(file_name: &str, options: &CsvOptions) -> Result<Mdf> {
+        csv_to_mdf(file_name, options)
+    }
     pub fn get_file_name(&self) -> String {
         match &self.mdf_info {
             MdfInfo::V3(mdfinfo3) => mdfinfo3.file_name.clone(),
@@ -105,6 +448,82 @@ impl Mdf {
     pub fn get_channel_desc(&self, channel_name: &str) -> Result<Option<String>> {
         self.mdf_info.get_channel_desc(channel_name)
     }
+    /// returns the channels renamed or dropped at parsing because their name was already used
+    pub fn get_duplicate_channels(&self) -> &[DuplicateChannel] {
+        self.mdf_info.get_duplicate_channels()
+    }
+    /// returns channel's display name, None if not defined
+    pub fn get_channel_display_name(&self, channel_name: &str) -> Result<Option<String>> {
+        self.mdf_info.get_channel_display_name(channel_name)
+    }
+    /// returns channel's source information, None if not defined
+    pub fn get_channel_source(&self, channel_name: &str) -> Result<Option<SourceInfo>> {
+        self.mdf_info.get_channel_source(channel_name)
+    }
+    /// returns the channel names grouped by source, ECU or bus from the channel or its
+    /// channel group acquisition source, sorted by source name, channels without source last
+    pub fn get_channels_by_source(&self) -> Result<Vec<SourceChannels>> {
+        let mut groups: Vec<SourceChannels> = Vec::new();
+        let mut channel_names: Vec<String> = self.get_channel_names_set().into_iter().collect();
+        channel_names.sort();
+        for channel_name in channel_names {
+            let source = self.get_channel_source(&channel_name)?;
+            match groups.iter_mut().find(|group| group.source == source) {
+                Some(group) => group.channels.push(channel_name),
+                None => groups.push(SourceChannels {
+                    source,
+                    channels: vec![channel_name],
+                }),
+            }
+        }
+        groups.sort_by(|a, b| match (&a.source, &b.source) {
+            (Some(a), Some(b)) => a.to_string().cmp(&b.to_string()),
+            (a, b) => a.is_none().cmp(&b.is_none()),
+        });
+        Ok(groups)
+    }
+    /// returns channel's conversion description
+    pub fn get_channel_conversion(&self, channel_name: &str) -> Result<Option<ConversionInfo>> {
+        self.mdf_info.get_channel_conversion(channel_name)
+    }
+    /// returns the on disk record layout of the channel group containing the channel,
+    /// typically its master: record length, number of records and channels position
+    pub fn get_group_layout(&self, channel_name: &str) -> Option<GroupLayout> {
+        self.mdf_info.get_group_layout(channel_name)
+    }
+    /// returns channel's value to text table, raw value to label
+    pub fn get_channel_enum_map(&self, channel_name: &str) -> Option<HashMap<i64, String>> {
+        self.mdf_info.get_channel_enum_map(channel_name)
+    }
+    /// adds time master channels to the channel groups without master, from their record
+    /// index and the sample rate in Hz, by default the mdf3 channels sampling rate.
+    /// Returns the names of the master channels synthesized
+    pub fn synthesize_masters(&mut self, sample_rate: Option<f64>) -> Result<Vec<String>> {
+        self.mdf_info.synthesize_masters(sample_rate)
+    }
+    /// returns the sorted channel names for which name, unit, description or source matches
+    /// the case insensitive wildcard pattern, * for any characters and ? for one character
+    pub fn search_channels(&self, pattern: &str) -> Result<Vec<String>> {
+        let mut regex = String::from("(?i)^");
+        for c in pattern.chars() {
+            match c {
+                '*' => regex.push_str(".*"),
+                '?' => regex.push('.'),
+                _ => regex.push_str(&regex::escape(c.encode_utf8(&mut [0; 4]))),
+            }
+        }
+        regex.push('$');
+        let regex = Regex::new(&regex)
+            .with_context(|| format!("invalid channel search pattern {pattern}"))?;
+        self.mdf_info.search_channels(&regex)
+    }
+    /// returns the sorted channel names for which name, unit, description or source
+    /// contains a match of the regular expression
+    pub fn search_channels_regex(&self, pattern: &str) -> Result<Vec<String>> {
+        let regex = Regex::new(pattern)
+            .with_context(|| format!("invalid channel search regular expression {pattern}"))?;
+        self.mdf_info.search_channels(&regex)
+    }
     /// Sets the channel description in memory
     pub fn set_channel_desc(&mut self, channel_name: &str, desc: &str) {
         self.mdf_info.set_channel_desc(channel_name, desc)
@@ -113,12 +532,100 @@ impl Mdf {
     pub fn get_channel_master(&self, channel_name: &str) -> Option<String> {
         self.mdf_info.get_channel_master(channel_name)
     }
+    /// returns the names of the channels composing the channel, mdf3 dependency only
+    pub fn get_channel_dependencies(&self, channel_name: &str) -> Vec<String> {
+        self.mdf_info.get_channel_dependencies(channel_name)
+    }
     /// returns channel's associated master channel type string
     /// 0 = None (normal data channels), 1 = Time (seconds), 2 = Angle (radians),
     /// 3 = Distance (meters), 4 = Index (zero-based index values)
     pub fn get_channel_master_type(&self, channel_name: &str) -> u8 {
         self.mdf_info.get_channel_master_type(channel_name)
     }
+    /// compares with other file the channels, metadata and data loaded in memory,
+    /// numeric values being equal within absolute tolerance
+    pub fn diff(&self, other: &Mdf, tolerance: f64) -> Result<DiffReport> {
+        diff(self, other, tolerance)
+    }
+    /// checks the alignment of the channel groups from their master channels, loaded in memory
+    /// if needed: ranges, sample rates, duplicates, non monotonic masters and gaps, steps
+    /// longer than gap_factor times the median step
+    pub fn check_alignment(&mut self, gap_factor: f64) -> Result<AlignmentReport> {
+        self.load_master_channels()?;
+        check_alignment(self, gap_factor)
+    }
+    /// returns the time intervals longer than threshold without any sample of the time master
+    /// channels, like logger pauses, master channels being loaded in memory if needed
+    pub fn detect_recording_gaps(&mut self, threshold: f64) -> Result<Vec<(f64, f64)>> {
+        self.load_master_channels()?;
+        detect_gaps(self, threshold)
+            .with_context(|| format!("failed detecting gaps longer than {threshold}s"))
+    }
+    /// returns one new Mdf per continuous section between recording gaps longer than threshold,
+    /// with the time based channels keeping their master values. Data must be in memory
+    pub fn split_on_gaps(&self, threshold: f64) -> Result<Vec<Mdf>> {
+        split_on_gaps(self, threshold)
+            .with_context(|| format!("failed splitting on gaps longer than {threshold}s"))
+    }
+    /// makes monotonic the master channels in memory by sorting the records of their group
+    /// or offsetting their values, returning the changes made
+    pub fn enforce_monotonic_masters(&mut self, method: MonotonicFix) -> Result<Vec<MasterFix>> {
+        let fixes = enforce_monotonic(self, None, method)
+            .context("failed enforcing monotonic master channels")?;
+        self.master_fixes.extend(fixes.iter().cloned());
+        Ok(fixes)
+    }
+    /// returns the changes made to non monotonic master channels since the file was opened
+    pub fn get_master_fixes(&self) -> &[MasterFix] {
+        &self.master_fixes
+    }
+    /// loads in memory the master channels not already loaded
+    fn load_master_channels(&mut self) -> Result<()> {
+        let masters: HashSet<String> = self
+            .get_master_channel_names_set()
+            .into_keys()
+            .flatten()
+            .filter(|master| {
+                self.get_channel_data(master)
+                    .is_none_or(|data| data.is_empty())
+            })
+            .collect();
+        if !masters.is_empty() {
+            self.load_channels_data_in_memory(masters)
+                .context("failed loading master channels")?;
+        }
+        Ok(())
+    }
+    /// returns the recording start time with its time offset
+    pub fn get_start_time(&self) -> Result<DateTime<FixedOffset>> {
+        self.mdf_info.get_start_time()
+    }
+    /// returns the absolute timestamps of the channel samples in memory: the recording
+    /// start time plus the values of its time master channel, in the start time offset
+    pub fn get_channel_absolute_time(
+        &self,
+        channel_name: &str,
+    ) -> Result<TimestampNanosecondArray> {
+        let master = self
+            .get_channel_master(channel_name)
+            .with_context(|| format!("channel {channel_name} has no master channel"))?;
+        if self.get_channel_master_type(&master) != 1 {
+            bail!("master channel {master} of channel {channel_name} is not a time channel");
+        }
+        let data = self
+            .get_channel_data(&master)
+            .with_context(|| format!("master channel {master} data not in memory"))?;
+        let times = cast(&data.as_ref(), &DataType::Float64)
+            .with_context(|| format!("failed casting master channel {master} into f64"))?;
+        let start_time = self.get_start_time()?;
+        let start_ns = start_time
+            .timestamp_nanos_opt()
+            .context("start time out of range")?;
+        let timestamps: TimestampNanosecondArray = times
+            .as_primitive::<Float64Type>()
+            .unary(|t| start_ns + (t * 1e9).round() as i64);
+        Ok(timestamps.with_timezone(start_time.offset().to_string()))
+    }
     /// Sets the channel's related master channel type in memory
     pub fn set_channel_master_type(&mut self, master_name: &str, master_type: u8) -> Result<()> {
         self.mdf_info
@@ -140,14 +647,272 @@ impl Mdf {
             MdfInfo::V4(mdfinfo4) => mdfinfo4.get_channel_data(channel_name),
         }
     }
+    /// returns the values of a f64 channel in memory, None if not loaded or of another data type
+    pub fn get_channel_f64(&self, channel_name: &str) -> Option<&[f64]> {
+        self.get_channel_data(channel_name)
+            .and_then(|data| data.as_f64_slice())
+    }
+    /// returns the values of a i64 channel in memory, None if not loaded or of another data type
+    pub fn get_channel_i64(&self, channel_name: &str) -> Option<&[i64]> {
+        self.get_channel_data(channel_name)
+            .and_then(|data| data.as_i64_slice())
+    }
+    /// returns an iterator over the texts of a string or text table converted channel in
+    /// memory, items being None for null values
+    pub fn get_channel_str_iter(
+        &self,
+        channel_name: &str,
+    ) -> Option<impl Iterator<Item = Option<&str>> + '_> {
+        self.get_channel_data(channel_name)
+            .and_then(|data| data.str_iter())
+    }
+    /// returns channel's raw data before conversion, only kept in memory if requested in read options
+    pub fn get_channel_raw_data(&self, channel_name: &str) -> Option<&ChannelData> {
+        self.mdf_info.get_channel_raw_data(channel_name)
+    }
+    /// returns the channel's text statuses when its value to text conversion is partial:
+    /// some table entries are converted into numeric values in channel data, the others
+    /// into text statuses. Status is null where channel data is numeric and conversely
+    pub fn get_channel_status(&self, channel_name: &str) -> Option<&ChannelData> {
+        self.mdf_info.get_channel_status(channel_name)
+    }
+    /// returns the channel's data in memory reduced to at most max_points samples for display,
+    /// buckets of consecutive records being reduced with method
+    pub fn get_channel_data_decimated(
+        &self,
+        channel_name: &str,
+        max_points: usize,
+        method: DecimationMethod,
+    ) -> Result<ArrayRef> {
+        let Some(data) = self
+            .get_channel_data(channel_name)
+            .filter(|data| !data.is_empty())
+        else {
+            bail!("channel {channel_name} not found or not loaded in memory");
+        };
+        decimate(&data.as_ref(), max_points, method)
+            .with_context(|| format!("failed decimating channel {channel_name}"))
+    }
+    /// returns an iterator over the (time, value) samples of a channel in memory, zipped with
+    /// its master channel also in memory. Invalid samples are returned as Value::Invalid
+    pub fn iter_channel(&self, channel_name: &str) -> Result<SampleIterator> {
+        let Some(data) = self
+            .get_channel_data(channel_name)
+            .filter(|data| !data.is_empty())
+        else {
+            bail!("channel {channel_name} not found or not loaded in memory");
+        };
+        let master = self
+            .get_channel_master(channel_name)
+            .with_context(|| format!("channel {channel_name} has no master channel"))?;
+        let Some(master_data) = self
+            .get_channel_data(&master)
+            .filter(|data| !data.is_empty())
+        else {
+            bail!("master channel {master} not loaded in memory");
+        };
+        SampleIterator::new(&master_data.as_ref(), &data.as_ref())
+            .with_context(|| format!("failed iterating samples of channel {channel_name}"))
+    }
+    /// returns the value of a channel in memory at time of its master channel, found by binary
+    /// search and interpolated with method. None when time is out of the master range
+    pub fn get_value_at(
+        &self,
+        channel_name: &str,
+        time: f64,
+        method: Interpolation,
+    ) -> Result<Option<Value>> {
+        let Some(data) = self
+            .get_channel_data(channel_name)
+            .filter(|data| !data.is_empty())
+        else {
+            bail!("channel {channel_name} not found or not loaded in memory");
+        };
+        let master = self
+            .get_channel_master(channel_name)
+            .with_context(|| format!("channel {channel_name} has no master channel"))?;
+        let Some(master_data) = self
+            .get_channel_data(&master)
+            .filter(|data| !data.is_empty())
+        else {
+            bail!("master channel {master} not loaded in memory");
+        };
+        value_at(&master_data.as_ref(), &data.as_ref(), time, method)
+            .with_context(|| format!("failed reading channel {channel_name} at {time}"))
+    }
+    /// returns the channel's statistics, computed at first request and kept until the channel
+    /// data is modified or reloaded. Channel data is loaded if not already in memory
+    pub fn get_channel_stats(&mut self, channel_name: &str) -> Result<Stats> {
+        if let Some(stats) = self.stats.get(channel_name) {
+            return Ok(*stats);
+        }
+        if !self.get_channel_names_set().contains(channel_name) {
+            bail!("channel {channel_name} not found");
+        }
+        if self
+            .get_channel_data(channel_name)
+            .is_none_or(|data| data.is_empty())
+        {
+            self.load_channels_data_in_memory(HashSet::from([channel_name.to_string()]))
+                .with_context(|| format!("failed loading channel {channel_name}"))?;
+        }
+        let Some(data) = self.get_channel_data(channel_name) else {
+            bail!("channel {channel_name} has no data");
+        };
+        let stats = stats(&data.as_ref())
+            .with_context(|| format!("failed computing statistics of channel {channel_name}"))?;
+        self.stats.insert(channel_name.to_string(), stats);
+        Ok(stats)
+    }
+    /// returns the channels of the group of master_name loaded in memory as a record batch,
+    /// master first then channels sorted by name, with unit and description field metadata
+    pub fn get_channel_group(&self, master_name: &str) -> Result<RecordBatch> {
+        let mut channel_names: Vec<String> = self
+            .mdf_info
+            .get_channel_names_cg_set(master_name)
+            .into_iter()
+            .filter(|name| name != master_name)
+            .collect();
+        if channel_names.is_empty() && self.get_channel_data(master_name).is_none() {
+            bail!("channel {master_name} not found or not loaded in memory");
+        }
+        channel_names.sort();
+        channel_names.insert(0, master_name.to_string());
+        let mut fields = Vec::with_capacity(channel_names.len());
+        let mut columns = Vec::with_capacity(channel_names.len());
+        for name in channel_names {
+            let Some(data) = self.get_channel_data(&name) else {
+                continue;
+            };
+            let array = data.as_ref();
+            let mut metadata = HashMap::new();
+            if let Some(unit) = self.get_channel_unit(&name)?.filter(|u| !u.is_empty()) {
+                metadata.insert("unit".to_string(), unit);
+            }
+            if let Some(desc) = self.get_channel_desc(&name)?.filter(|d| !d.is_empty()) {
+                metadata.insert("description".to_string(), desc);
+            }
+            fields.push(
+                Field::new(name, array.data_type().clone(), array.null_count() > 0)
+                    .with_metadata(metadata),
+            );
+            columns.push(array);
+        }
+        RecordBatch::try_new(Arc::new(Schema::new(fields)), columns).with_context(|| {
+            format!("failed building record batch of channel group of {master_name}")
+        })
+    }
+    /// returns the channels of the group of master_name loaded in memory as a polars DataFrame
+    #[cfg(feature = "polars")]
+    pub fn get_channel_group_dataframe(&self, master_name: &str) -> Result<DataFrame> {
+        record_batch_to_dataframe(&self.get_channel_group(master_name)?)
+    }
+    /// Returns the channel's validity, bit set for valid values, None if all values are valid
+    pub fn get_channel_validity(&self, channel_name: &str) -> Option<NullBuffer> {
+        self.get_channel_data(channel_name)
+            .and_then(|data| data.validity())
+    }
     /// defines channel's data in memory
     pub fn set_channel_data(&mut self, channel_name: &str, data: Arc<dyn Array>) -> Result<()> {
+        self.stats.remove(channel_name);
         self.mdf_info.set_channel_data(channel_name, data)
     }
     /// Renames a channel's name in memory
     pub fn rename_channel(&mut self, channel_name: &str, new_name: &str) {
+        self.stats.remove(channel_name);
+        self.stats.remove(new_name);
         self.mdf_info.rename_channel(channel_name, new_name)
     }
+    /// Renames in memory the channels found in mapping of current to new names, names being
+    /// written with the file. Returns the number of channels renamed
+    pub fn apply_channel_mapping(&mut self, mapping: &HashMap<String, String>) -> Result<usize> {
+        let channel_names = self.get_channel_names_set();
+        let renamed: Vec<(&String, &String)> = mapping
+            .iter()
+            .filter(|(name, new_name)| channel_names.contains(*name) && name != new_name)
+            .collect();
+        let sources: HashSet<&String> = renamed.iter().map(|(name, _)| *name).collect();
+        let mut new_names: HashSet<&String> = HashSet::with_capacity(renamed.len());
+        for (name, new_name) in renamed.iter() {
+            if !new_names.insert(new_name) {
+                bail!("several channels mapped to {new_name}, {name} cannot be renamed");
+            }
+            if channel_names.contains(*new_name) && !sources.contains(new_name) {
+                bail!("channel {name} cannot be renamed into existing channel {new_name}");
+            }
+        }
+        // temporary names allow swapping or chaining names
+        let temporary = |index: usize| format!("\u{0}mapping_{index}");
+        for (index, (name, _new_name)) in renamed.iter().enumerate() {
+            self.rename_channel(name, &temporary(index));
+        }
+        for (index, (_name, new_name)) in renamed.iter().enumerate() {
+            self.rename_channel(&temporary(index), new_name);
+        }
+        info!("Renamed {} channels from mapping", renamed.len());
+        Ok(renamed.len())
+    }
+    /// Renames in memory the channels from a csv file with current and new names per line,
+    /// separated by comma or semicolon, lines starting with # being ignored
+    pub fn apply_channel_mapping_file(&mut self, file_name: &str) -> Result<usize> {
+        let mapping = read_channel_mapping(file_name)?;
+        self.apply_channel_mapping(&mapping)
+            .with_context(|| format!("failed applying channel mapping of file {file_name}"))
+    }
+    /// Adds in memory a channel computed from an algebraic expression of channels like
+    /// "Power = Torque * Speed / 9.55", aligned on master_channel. Names that are not
+    /// identifiers are written between braces like {Engine.Speed}, channels of other masters
+    /// being resampled onto master_channel
+    pub fn add_computed_channel(
+        &mut self,
+        name: &str,
+        expression: &str,
+        master_channel: &str,
+    ) -> Result<()> {
+        let data = computed_channel(self, name, expression, master_channel)
+            .with_context(|| format!("failed computing channel {name}"))?;
+        self.add_channel(
+            name.to_string(),
+            data,
+            Some(master_channel.to_string()),
+            None,
+            false,
+            None,
+            Some(expression.to_string()),
+        )
+    }
+    /// Adds in memory the channel filtered with filter as a new channel of f64 named
+    /// filtered_name, sharing the channel's master and unit
+    pub fn add_filtered_channel(
+        &mut self,
+        channel_name: &str,
+        filtered_name: &str,
+        filter: Filter,
+    ) -> Result<()> {
+        let Some(data) = self
+            .get_channel_data(channel_name)
+            .filter(|data| !data.is_empty())
+        else {
+            bail!("channel {channel_name} not found or not loaded in memory");
+        };
+        let master = self.get_channel_master(channel_name);
+        let master_data = master
+            .as_ref()
+            .and_then(|master| self.get_channel_data(master))
+            .map(|master_data| master_data.as_ref());
+        let filtered = filter_channel(&data.as_ref(), master_data.as_ref(), filter)
+            .with_context(|| format!("failed filtering channel {channel_name}"))?;
+        let unit = self.get_channel_unit(channel_name)?;
+        self.add_channel(
+            filtered_name.to_string(),
+            filtered,
+            master,
+            None,
+            false,
+            unit,
+            Some(format!("{channel_name} filtered with {filter:?}")),
+        )
+    }
     /// Adds a new channel in memory (no file modification)
     #[allow(clippy::too_many_arguments)]
     pub fn add_channel(
@@ -161,6 +926,7 @@ impl Mdf {
         description: Option<String>,
     ) -> Result<()> {
         // mdfinfo metadata but no data
+        self.stats.remove(&channel_name);
         let machine_endian: bool = cfg!(target_endian = "big");
         let data_signature = DataSignature {
             len: data.len(),
@@ -185,10 +951,181 @@ impl Mdf {
         )?;
         Ok(())
     }
+    /// adds in memory one channel per flag of the channel's bitfield text table conversion,
+    /// valued 1 where flag bits are set in raw value, 0 otherwise. New channels are named
+    /// channel.flag and share the channel's master. Returns the names of channels added
+    pub fn explode_bitfield_channel(&mut self, channel_name: &str) -> Result<Vec<String>> {
+        let flags = self
+            .mdf_info
+            .get_channel_bitfield_flags(channel_name)?
+            .with_context(|| {
+                format!("channel {channel_name} has no bitfield text table conversion")
+            })?;
+        if self.get_channel_raw_data(channel_name).is_none() {
+            if self.get_channel_data(channel_name).is_some() {
+                bail!(
+                    "raw data of channel {channel_name} not in memory, load it with keep_raw read option"
+                );
+            }
+            let options = ReadOptions {
+                keep_raw: true,
+                ..Default::default()
+            };
+            self.load_channels_data_in_memory_with_options(
+                HashSet::from([channel_name.to_string()]),
+                &options,
+            )?;
+        }
+        let raw = self
+            .get_channel_raw_data(channel_name)
+            .with_context(|| format!("raw data of channel {channel_name} could not be read"))?;
+        let raw = cast(&raw.as_ref(), &DataType::UInt64)
+            .with_context(|| format!("raw data of channel {channel_name} is not integer"))?;
+        let raw = raw.as_primitive::<UInt64Type>().clone();
+        let master = self.get_channel_master(channel_name);
+        let master_type = self.get_channel_master_type(channel_name);
+        let mut names = Vec::with_capacity(flags.len());
+        for (mask, flag) in flags {
+            let name = format!("{channel_name}.{flag}");
+            let data: UInt8Array = raw.unary(|value| u8::from(value & mask != 0));
+            self.add_channel(
+                name.clone(),
+                Arc::new(data),
+                master.clone(),
+                Some(master_type),
+                false,
+                None,
+                Some(flag),
+            )?;
+            names.push(name);
+        }
+        Ok(names)
+    }
+    /// returns a structure channel as an arrow StructArray with one field per member channel,
+    /// nested structures becoming nested StructArrays. Members not yet in memory are loaded.
+    /// None if channel is not a structure
+    pub fn get_channel_struct(&mut self, channel_name: &str) -> Result<Option<StructArray>> {
+        let Some(members) = self.mdf_info.get_channel_struct_members(channel_name) else {
+            return Ok(None);
+        };
+        let missing: HashSet<String> = members
+            .iter()
+            .filter(|member| self.get_channel_data(member).is_none())
+            .cloned()
+            .collect();
+        if !missing.is_empty() {
+            self.load_channels_data_in_memory(missing)
+                .with_context(|| format!("failed loading members of structure {channel_name}"))?;
+        }
+        let mut fields = Vec::with_capacity(members.len());
+        let mut arrays = Vec::with_capacity(members.len());
+        for member in members {
+            let array: ArrayRef = match self.get_channel_struct(&member)? {
+                Some(nested) => Arc::new(nested),
+                None => self
+                    .get_channel_data(&member)
+                    .with_context(|| format!("data of structure member {member} not in memory"))?
+                    .as_ref(),
+            };
+            fields.push(Field::new(member, array.data_type().clone(), true));
+            arrays.push(array);
+        }
+        StructArray::try_new(Fields::from(fields), arrays, None)
+            .map(Some)
+            .with_context(|| format!("failed building structure {channel_name}"))
+    }
+    /// sets in memory the header and program identifier, written with the file by write().
+    /// write_mdf3 copies the header of the source file as is
+    pub fn set_header(&mut self, header: &HeaderOptions) -> Result<()> {
+        self.mdf_info.set_header(header)
+    }
+    /// strips or replaces in memory the metadata identifying people, projects or places
+    /// before writing the file for sharing. Returns the names of the channels removed
+    pub fn anonymize(&mut self, options: &AnonymizeOptions) -> Vec<String> {
+        let patterns: Vec<String> = options
+            .gps_patterns
+            .iter()
+            .map(|pattern| pattern.to_lowercase())
+            .collect();
+        let mut removed: Vec<String> = self
+            .get_channel_names_set()
+            .into_iter()
+            .filter(|name| {
+                let name = name.to_lowercase();
+                patterns.iter().any(|pattern| name.contains(pattern))
+            })
+            .collect();
+        removed.sort();
+        removed.iter().for_each(|name| self.remove_channel(name));
+        self.mdf_info.anonymize(options);
+        info!("Anonymized file, removed channels {:?}", removed);
+        removed
+    }
     /// Removes a channel in memory (no file modification)
     pub fn remove_channel(&mut self, channel_name: &str) {
+        self.stats.remove(channel_name);
         self.mdf_info.remove_channel(channel_name);
     }
+    /// returns the events of the file sorted by sync value
+    pub fn get_events(&self) -> Result<Vec<Event>> {
+        self.mdf_info.get_events()
+    }
+    /// returns the synchronisation channels, their values locating the records in the
+    /// attached files like videos
+    pub fn get_sync_channels(&self) -> Result<Vec<SyncChannel>> {
+        self.mdf_info.get_sync_channels()
+    }
+    /// returns the frames of the attached video files sorted by time, from the video
+    /// synchronisation channels in memory, for players to align videos with signals
+    pub fn get_video_frames(&self) -> Result<Vec<VideoFrame>> {
+        let mut frames: Vec<VideoFrame> = Vec::new();
+        for channel in self.get_sync_channels()? {
+            if !channel.is_video() {
+                continue;
+            }
+            let file_name = channel.file_name.unwrap_or_default();
+            for (time, value) in self.iter_channel(&channel.name)? {
+                if let Some(frame) = value.as_f64() {
+                    frames.push(VideoFrame {
+                        time,
+                        file_name: file_name.clone(),
+                        sync_type: channel.sync_type,
+                        frame,
+                    });
+                }
+            }
+        }
+        frames.sort_by(|a, b| a.time.total_cmp(&b.time));
+        Ok(frames)
+    }
+    /// returns the program block data of mdf3 files, logger specific configuration. It is kept
+    /// as an embedded attachment when converting into mdf4
+    pub fn get_program_block(&self) -> Option<Vec<u8>> {
+        self.mdf_info.get_program_block()
+    }
+    /// returns the file history entries of mdf4 files, oldest first
+    pub fn get_file_history(&self) -> Result<Vec<FileHistory>> {
+        self.mdf_info.get_file_history()
+    }
+    /// returns the channel hierarchy of mdf4 files as a tree, for instance for GUI tree views
+    pub fn get_channel_tree(&self) -> Result<Vec<ChannelTreeNode>> {
+        self.mdf_info.get_channel_tree()
+    }
+    /// returns the names of the channels below the hierarchy level found following the path of
+    /// level names from the tree roots, None if no level matches
+    pub fn get_hierarchy_channels(&self, path: &[&str]) -> Result<Option<HashSet<String>>> {
+        let tree = self.get_channel_tree()?;
+        let node = path.split_first().and_then(|(name, rest)| {
+            tree.iter()
+                .find(|node| node.name.as_deref() == Some(*name))
+                .and_then(|node| node.find(rest))
+        });
+        Ok(node.map(|node| node.all_channels()))
+    }
+    /// adds an event in memory (no file modification), written with the file
+    pub fn add_event(&mut self, event: Event) -> Result<()> {
+        self.mdf_info.add_event(event)
+    }
     /// load all channels data in memory
     pub fn load_all_channels_data_in_memory(&mut self) -> Result<(), Error> {
         let channel_names = self.get_channel_names_set();
@@ -196,22 +1133,118 @@ impl Mdf {
             .context("failed loading channels data from file to memory")?;
         Ok(())
     }
-    /// load a set of channels data in memory
+    /// load a set of channels data in memory, mdf4 data groups being read in parallel
     pub fn load_channels_data_in_memory(
         &mut self,
         channel_names: HashSet<String>,
     ) -> Result<(), Error> {
-        let f: File = OpenOptions::new()
-            .read(true)
-            .write(false)
-            .open(self.get_file_name())
-            .with_context(|| format!("Cannot find the file {}", self.get_file_name()))?;
-        let mut rdr = BufReader::new(&f);
-        info!("Opened file {}", self.get_file_name());
-
+        self.load_channels_data_in_memory_with_options(channel_names, &ReadOptions::default())
+    }
+    /// load a set of channels data in memory with chunk size, threads, memory budget
+    /// and strings decoding tuned by options
+    pub fn load_channels_data_in_memory_with_options(
+        &mut self,
+        channel_names: HashSet<String>,
+        options: &ReadOptions,
+    ) -> Result<(), Error> {
+        if options.chunk_size == 0 {
+            bail!("read options chunk size must be strictly positive");
+        }
+        let mut channel_names = channel_names;
+        if options.monotonic_masters.is_some() {
+            let masters: Vec<String> = channel_names
+                .iter()
+                .filter_map(|name| self.get_channel_master(name))
+                .collect();
+            channel_names.extend(masters);
+        }
+        let fixed_names = options.monotonic_masters.map(|_| channel_names.clone());
+        match options.max_parallelism {
+            Some(0) => bail!("read options maximum parallelism must be strictly positive"),
+            n_threads => install(n_threads, || {
+                self.read_channels_data(channel_names, options)
+            })?,
+        }
+        if let (Some(method), Some(names)) = (options.monotonic_masters, fixed_names) {
+            let fixes = enforce_monotonic(self, Some(&names), method)
+                .context("failed enforcing monotonic master channels")?;
+            self.master_fixes.extend(fixes);
+        }
+        Ok(())
+    }
+    /// reads the channels from file, string channels in a second sequential pass if lazy
+    fn read_channels_data(
+        &mut self,
+        channel_names: HashSet<String>,
+        options: &ReadOptions,
+    ) -> Result<(), Error> {
+        self.stats.retain(|name, _| !channel_names.contains(name));
+        let (string_channels, channel_names): (HashSet<String>, HashSet<String>) =
+            if options.lazy_strings {
+                channel_names
+                    .into_iter()
+                    .partition(|name| self.mdf_info.is_string_channel(name))
+            } else {
+                (HashSet::new(), channel_names)
+            };
+        // without budget, data groups are read one by one
+        let string_options = ReadOptions {
+            memory_budget: 0,
+            ..options.clone()
+        };
+        for (channel_names, options) in
+            [(channel_names, options), (string_channels, &string_options)]
+        {
+            if channel_names.is_empty() {
+                continue;
+            }
+            if let MdfInfo::V4(mdfinfo4) = &mut self.mdf_info {
+                mdfreader4_parallel(mdfinfo4, &channel_names, options).with_context(|| {
+                    format!("failed reading data from mdf4 file {}", mdfinfo4.file_name)
+                })?;
+                info!("Loaded all channels data into memory");
+            } else {
+                let f: File = OpenOptions::new()
+                    .read(true)
+                    .write(false)
+                    .open(self.get_file_name())
+                    .map_err(MdfError::Io)
+                    .with_context(|| format!("Cannot find the file {}", self.get_file_name()))?;
+                info!("Opened file {}", self.get_file_name());
+                self.read_channels_data_from_reader(&f, channel_names, options)?;
+            }
+        }
+        Ok(())
+    }
+    /// load a set of channels data in memory from the mdf file content in memory
+    pub fn load_channels_data_from_bytes(
+        &mut self,
+        bytes: &[u8],
+        channel_names: HashSet<String>,
+    ) -> Result<(), Error> {
+        self.load_channels_data_from_reader(Cursor::new(bytes), channel_names)
+    }
+    /// load a set of channels data in memory from any reader of the mdf file content
+    pub fn load_channels_data_from_reader<R: Read + Seek>(
+        &mut self,
+        reader: R,
+        channel_names: HashSet<String>,
+    ) -> Result<(), Error> {
+        self.read_channels_data_from_reader(reader, channel_names, &ReadOptions::default())
+    }
+    /// reads sequentially the channels data from reader
+    fn read_channels_data_from_reader<R: Read + Seek>(
+        &mut self,
+        reader: R,
+        channel_names: HashSet<String>,
+        options: &ReadOptions,
+    ) -> Result<(), Error> {
+        let mut rdr = BufReader::new(reader);
+        // readers track positions relatively to the start of file
+        rdr.rewind().context("Could not reach start of file")?;
         match &mut self.mdf_info {
             MdfInfo::V3(_mdfinfo3) => {
-                mdfreader3(&mut rdr, self, &channel_names).with_context(|| {
+                mdfreader3(&mut rdr, self, &channel_names, options).with_context(|| {
                     format!(
                         "failed reading data from mdf3 file {}",
                         self.get_file_name()
@@ -219,7 +1252,7 @@ impl Mdf {
                 })?;
             }
             MdfInfo::V4(_mdfinfo4) => {
-                mdfreader4(&mut rdr, self, &channel_names).with_context(|| {
+                mdfreader4(&mut rdr, self, &channel_names, options).with_context(|| {
                     format!(
                         "failed reading data from mdf4 file {}",
                         self.get_file_name()
@@ -231,6 +1264,175 @@ impl Mdf {
 
         Ok(())
     }
+    /// load in memory only the records of the given channels with master values between t_start and t_stop.
+    /// Data blocks out of the time range are skipped, master channels are expected to be monotonic.
+    /// mdf3 files are read entirely and filtered in memory, trigger windows being given by events
+    pub fn load_channels_data_in_memory_between(
+        &mut self,
+        channel_names: HashSet<String>,
+        t_start: f64,
+        t_stop: f64,
+    ) -> Result<(), Error> {
+        self.stats.retain(|name, _| !channel_names.contains(name));
+        match &mut self.mdf_info {
+            MdfInfo::V3(_) => {
+                // records are read entirely along with their masters, then filtered in memory
+                let mut names = channel_names.clone();
+                names.extend(
+                    channel_names
+                        .iter()
+                        .filter_map(|channel_name| self.get_channel_master(channel_name)),
+                );
+                self.read_channels_data(names, &ReadOptions::default())?;
+                if let MdfInfo::V3(mdfinfo3) = &mut self.mdf_info {
+                    mdfinfo3
+                        .retain_time_range(&channel_names, t_start, t_stop)
+                        .with_context(|| {
+                            format!(
+                                "failed filtering time range data from mdf3 file {}",
+                                mdfinfo3.file_name
+                            )
+                        })?;
+                }
+            }
+            MdfInfo::V4(mdfinfo4) => {
+                mdfreader4_between(mdfinfo4, &channel_names, t_start, t_stop).with_context(
+                    || {
+                        format!(
+                            "failed reading time range data from mdf4 file {}",
+                            mdfinfo4.file_name
+                        )
+                    },
+                )?;
+            }
+        }
+        info!("Loaded channels data between {} and {}", t_start, t_stop);
+        Ok(())
+    }
+    /// load in memory from any reader of the mdf file content, a RangeReader of object storage
+    /// for instance, only the records of the given channels with master values between t_start
+    /// and t_stop. Data blocks out of the time range are skipped without using the index file
+    pub fn load_channels_data_from_reader_between<R: Read + Seek>(
+        &mut self,
+        reader: R,
+        channel_names: HashSet<String>,
+        t_start: f64,
+        t_stop: f64,
+    ) -> Result<(), Error> {
+        self.stats.retain(|name, _| !channel_names.contains(name));
+        match &mut self.mdf_info {
+            MdfInfo::V3(_) => {
+                // records are read entirely along with their masters, then filtered in memory
+                let mut names = channel_names.clone();
+                names.extend(
+                    channel_names
+                        .iter()
+                        .filter_map(|channel_name| self.get_channel_master(channel_name)),
+                );
+                self.read_channels_data_from_reader(reader, names, &ReadOptions::default())?;
+                if let MdfInfo::V3(mdfinfo3) = &mut self.mdf_info {
+                    mdfinfo3
+                        .retain_time_range(&channel_names, t_start, t_stop)
+                        .with_context(|| {
+                            format!(
+                                "failed filtering time range data from mdf3 file {}",
+                                mdfinfo3.file_name
+                            )
+                        })?;
+                }
+            }
+            MdfInfo::V4(mdfinfo4) => {
+                mdfreader4_between_from_reader(mdfinfo4, reader, &channel_names, t_start, t_stop)
+                    .with_context(|| {
+                    format!(
+                        "failed reading time range data from mdf4 content {}",
+                        mdfinfo4.file_name
+                    )
+                })?;
+            }
+        }
+        info!("Loaded channels data between {} and {}", t_start, t_stop);
+        Ok(())
+    }
+    /// builds the index of the data blocks (positions, number of records and master values range)
+    /// and saves it next to the file. It is then used automatically by time range loading
+    /// to skip data blocks without reading them
+    pub fn build_index(&self) -> Result<(), Error> {
+        match &self.mdf_info {
+            MdfInfo::V3(_mdfinfo3) => {
+                bail!("index is only available for mdf4 files")
+            }
+            MdfInfo::V4(mdfinfo4) => {
+                build_index4(mdfinfo4).with_context(|| {
+                    format!("failed building index of file {}", mdfinfo4.file_name)
+                })?;
+            }
+        }
+        Ok(())
+    }
+    /// checks the blocks structure of the file (links, block lengths, cycle counts, overlaps, texts)
+    /// and returns the report of problems found
+    pub fn validate(&self) -> Result<ValidationReport, Error> {
+        let file_name = self.get_file_name();
+        validate_file(&file_name).with_context(|| format!("failed validating file {file_name}"))
+    }
+    /// decodes the CAN frames of bus logging channel groups with the given DBC file
+    /// and adds the signals as new channels, returning their names
+    pub fn decode_can_logging(&mut self, dbc_file: &str) -> Result<Vec<String>, Error> {
+        let dbc = Dbc::from_file(dbc_file)?;
+        decode_can_frames(self, &dbc)
+            .with_context(|| format!("failed decoding CAN frames with DBC file {}", dbc_file))
+    }
+    /// returns a new Mdf with all the time based channels resampled onto a common raster in seconds,
+    /// linearly interpolated for floats and zero-order hold for other types. Data must be in memory
+    pub fn resample(&self, raster_seconds: f64) -> Result<Mdf, Error> {
+        resample_on_raster(self, raster_seconds)
+            .with_context(|| format!("failed resampling on raster of {raster_seconds}s"))
+    }
+    /// returns a new Mdf with all the channels having the same master type
+    /// resampled onto the given master channel values. Data must be in memory
+    pub fn resample_on(&self, master_channel: &str) -> Result<Mdf, Error> {
+        resample_on_master(self, master_channel)
+            .with_context(|| format!("failed resampling on master channel {master_channel}"))
+    }
+    /// returns an iterator lazily reading the records of the channel group containing channel_name,
+    /// chunk by chunk, without loading the channels data in memory
+    pub fn iter_records(&self, channel_name: &str) -> Result<ChannelGroupIterator<'_>, Error> {
+        match &self.mdf_info {
+            MdfInfo::V3(_mdfinfo3) => bail!("record iterator is only available for mdf4 files"),
+            MdfInfo::V4(mdfinfo4) => ChannelGroupIterator::new(mdfinfo4, channel_name)
+                .with_context(|| {
+                    format!(
+                        "failed creating record iterator for channel {}",
+                        channel_name
+                    )
+                }),
+        }
+    }
+    /// returns an iterator lazily reading the records of the channel group containing channel_name
+    /// from any reader of the mdf4 file content
+    pub fn iter_records_from_reader<R: Read + Seek>(
+        &self,
+        channel_name: &str,
+        reader: R,
+    ) -> Result<ChannelGroupIterator<'_, R>, Error> {
+        match &self.mdf_info {
+            MdfInfo::V3(_mdfinfo3) => bail!("record iterator is only available for mdf4 files"),
+            MdfInfo::V4(mdfinfo4) => {
+                ChannelGroupIterator::from_reader(mdfinfo4, channel_name, reader).with_context(
+                    || format!("failed creating record iterator for channel {channel_name}"),
+                )
+            }
+        }
+    }
+    /// returns an iterator lazily reading the records of the channel group containing
+    /// channel_name, each record being mapped onto the user struct T
+    pub fn iter_typed_records<T: FromRecord>(
+        &self,
+        channel_name: &str,
+    ) -> Result<TypedRecordIterator<'_, T, File>, Error> {
+        Ok(TypedRecordIterator::new(self.iter_records(channel_name)?))
+    }
     /// Clears all data arrays
     pub fn clear_all_channel_data_from_memory(&mut self) -> Result<()> {
         let channel_names = self.get_channel_names_set();
@@ -251,6 +1453,16 @@ impl Mdf {
     pub fn export_to_parquet(&self, file_name: &str, compression: Option<&str>) -> Result<()> {
         export_to_parquet(self, file_name, compression)
     }
+    /// export to Parquet files, one for each channel group, with files location, row groups
+    /// size, encoding and statistics tuned by options
+    #[cfg(feature = "parquet")]
+    pub fn export_to_parquet_with_options(
+        &self,
+        file_name: &str,
+        options: &ParquetOptions,
+    ) -> Result<()> {
+        export_to_parquet_with_options(self, file_name, options)
+    }
     /// export a dataframe including a given channel to a Parquet file
     #[cfg(feature = "parquet")]
     pub fn export_dataframe_to_parquet(
@@ -276,9 +1488,129 @@ impl Mdf {
     pub fn export_to_hdf5(&self, file_name: &str, compression: Option<&str>) -> Result<()> {
         export_to_hdf5(self, file_name, compression)
     }
-    /// Writes mdf4 file
-    pub fn write(&mut self, file_name: &str, compression: bool) -> Result<Mdf> {
-        mdfwriter4(self, file_name, compression)
+    /// returns the GPS latitude, longitude and altitude channels detected from their names
+    /// and units, None if not found
+    pub fn get_geo_channels(&self) -> Option<GeoChannels> {
+        detect_geo_channels(self)
+    }
+    /// export the GPS positions in memory as a GPX track, GPS channels being detected if None
+    pub fn export_to_gpx(&self, file_name: &str, channels: Option<&GeoChannels>) -> Result<()> {
+        export_to_gpx(self, file_name, channels)
+    }
+    /// export the GPS positions in memory as a KML line, GPS channels being detected if None
+    pub fn export_to_kml(&self, file_name: &str, channels: Option<&GeoChannels>) -> Result<()> {
+        export_to_kml(self, file_name, channels)
+    }
+    /// Writes mdf4 file, compression being a Compression or a bool for default deflate
+    pub fn write(&mut self, file_name: &str, compression: impl Into<Compression>) -> Result<Mdf> {
+        mdfwriter4(
+            self,
+            file_name,
+            compression.into(),
+            &[],
+            &HashSet::new(),
+            "created",
+        )
+    }
+    /// Writes mdf4 file, with comment describing the change in the appended file history entry
+    pub fn write_with_comment(
+        &mut self,
+        file_name: &str,
+        compression: impl Into<Compression>,
+        comment: &str,
+    ) -> Result<Mdf> {
+        mdfwriter4(
+            self,
+            file_name,
+            compression.into(),
+            &[],
+            &HashSet::new(),
+            comment,
+        )
+    }
+    /// Writes mdf4 file then re-opens it to verify its blocks links, lengths and cycle counts
+    pub fn write_verified(
+        &mut self,
+        file_name: &str,
+        compression: impl Into<Compression>,
+    ) -> Result<Mdf> {
+        let mdf = self.write(file_name, compression)?;
+        let report = mdf.validate()?;
+        if !report.is_valid() {
+            bail!(MdfError::CorruptData(format!(
+                "written file {file_name} is not valid:\n{report}"
+            )));
+        }
+        Ok(mdf)
+    }
+    /// Writes mdf4 file with sample reduction blocks, one per reduction factor,
+    /// holding mean, min and max values of each interval of reduction factor samples
+    pub fn write_with_sample_reduction(
+        &mut self,
+        file_name: &str,
+        compression: impl Into<Compression>,
+        reduction_factors: &[usize],
+    ) -> Result<Mdf> {
+        mdfwriter4(
+            self,
+            file_name,
+            compression.into(),
+            reduction_factors,
+            &HashSet::new(),
+            "created",
+        )
+    }
+    /// Writes mdf4 file, the data groups of uncompressed_channels being written without
+    /// compression like already compressed byte arrays
+    pub fn write_with_uncompressed_channels(
+        &mut self,
+        file_name: &str,
+        compression: impl Into<Compression>,
+        uncompressed_channels: &HashSet<String>,
+    ) -> Result<Mdf> {
+        mdfwriter4(
+            self,
+            file_name,
+            compression.into(),
+            &[],
+            uncompressed_channels,
+            "created",
+        )
+    }
+    /// Saves in place into the mdf4 file the channels units, descriptions, sources and master
+    /// types modified in memory, without rewriting data blocks. Returns the number of channels patched
+    pub fn save_metadata(&mut self) -> Result<usize> {
+        match &mut self.mdf_info {
+            MdfInfo::V3(_) => bail!(MdfError::UnsupportedBlock(
+                "saving metadata in place of mdf3 file, use write_mdf3 instead".to_string()
+            )),
+            MdfInfo::V4(mdfinfo4) => {
+                save_metadata4(mdfinfo4).context("failed saving metadata in file")
+            }
+        }
+    }
+    /// Computes the MD5 hash of the whole mdf4 file and stores it in place in the header comment,
+    /// for traceability. Returns the hash in hexadecimal
+    pub fn store_file_hash(&mut self) -> Result<String> {
+        match &mut self.mdf_info {
+            MdfInfo::V3(_) => bail!(MdfError::UnsupportedBlock(
+                "storing file hash in mdf3 file".to_string()
+            )),
+            MdfInfo::V4(mdfinfo4) => store_file_hash4(mdfinfo4).context("failed storing file hash"),
+        }
+    }
+    /// Verifies the file against the hash stored in its header comment, None if no hash is stored
+    pub fn verify_file_hash(&self) -> Result<Option<bool>> {
+        match &self.mdf_info {
+            MdfInfo::V3(_) => Ok(None),
+            MdfInfo::V4(mdfinfo4) => {
+                verify_file_hash4(mdfinfo4).context("failed verifying file hash")
+            }
+        }
+    }
+    /// Writes mdf3 file from mdf3 input, keeping blocks and raw records of the source file
+    pub fn write_mdf3(&self, file_name: &str) -> Result<Mdf> {
+        mdfwriter3(self, file_name)
     }
 }
 
@@ -306,30 +1638,30 @@ impl fmt::Display for Mdf {
                 writeln!(f, "Comments: {}", mdfinfo3.hd_comment)?;
                 for (master, list) in self.get_master_channel_names_set().iter() {
                     if let Some(master_name) = master {
-                        writeln!(f, "\nMaster: {master_name}")
-                            .expect("cannot print master channel name");
+                        writeln!(f, "\nMaster: {master_name}")?;
                     } else {
-                        writeln!(f, "\nWithout Master channel")
-                            .expect("cannot print thre is no master channel");
+                        writeln!(f, "\nWithout Master channel")?;
                     }
                     for channel in list.iter() {
-                        writeln!(f, " {channel} ").expect("cannot print channel name");
+                        writeln!(f, " {channel} ")?;
                         if let Some(data) = self.get_channel_data(channel) {
                             if !data.is_empty() {
                                 let array = &data.as_ref();
                                 let displayer = ArrayFormatter::try_new(array, &format_option)
                                     .map_err(|_| std::fmt::Error)?;
-                                write!(f, "{}", displayer.value(0)).expect("cannot channel data");
-                                write!(f, " ").expect("cannot print simple space character");
-                                write!(f, "{}", displayer.value(data.len() - 1))
-                                    .expect("cannot channel data");
+                                write!(f, "{}", displayer.value(0))?;
+                                write!(f, " ")?;
+                                write!(f, "{}", displayer.value(data.len() - 1))?;
                             }
                         }
                         if let Ok(Some(unit)) = self.get_channel_unit(channel) {
-                            writeln!(f, " {unit} ").expect("cannot print channel unit");
+                            writeln!(f, " {unit} ")?;
                         }
                         if let Ok(Some(desc)) = self.get_channel_desc(channel) {
-                            writeln!(f, " {desc} ").expect("cannot print channel desc");
+                            writeln!(f, " {desc} ")?;
+                        }
+                        if let Ok(Some(source)) = self.get_channel_source(channel) {
+                            writeln!(f, " {source} ")?;
                         }
                     }
                 }
@@ -346,30 +1678,30 @@ impl fmt::Display for Mdf {
                 }
                 for (master, list) in self.get_master_channel_names_set().iter() {
                     if let Some(master_name) = master {
-                        writeln!(f, "\nMaster: {master_name}")
-                            .expect("cannot print master channel name");
+                        writeln!(f, "\nMaster: {master_name}")?;
                     } else {
-                        writeln!(f, "\nWithout Master channel")
-                            .expect("cannot print thre is no master channel");
+                        writeln!(f, "\nWithout Master channel")?;
                     }
                     for channel in list.iter() {
-                        writeln!(f, " {channel} ").expect("cannot print channel name");
+                        writeln!(f, " {channel} ")?;
                         if let Some(data) = self.get_channel_data(channel) {
                             if !data.is_empty() {
                                 let array = &data.as_ref();
                                 let displayer = ArrayFormatter::try_new(array, &format_option)
                                     .map_err(|_| std::fmt::Error)?;
-                                write!(f, "{}", displayer.value(0)).expect("cannot channel data");
-                                write!(f, " ").expect("cannot print simple space character");
-                                write!(f, "{}", displayer.value(data.len() - 1))
-                                    .expect("cannot channel data");
+                                write!(f, "{}", displayer.value(0))?;
+                                write!(f, " ")?;
+                                write!(f, "{}", displayer.value(data.len() - 1))?;
                             }
                         }
                         if let Ok(Some(unit)) = self.get_channel_unit(channel) {
-                            writeln!(f, " {unit} ").expect("cannot print channel unit");
+                            writeln!(f, " {unit} ")?;
                         }
                         if let Ok(Some(desc)) = self.get_channel_desc(channel) {
-                            writeln!(f, " {desc} ").expect("cannot print channel desc");
+                            writeln!(f, " {desc} ")?;
+                        }
+                        if let Ok(Some(source)) = self.get_channel_source(channel) {
+                            writeln!(f, " {source} ")?;
                         }
                     }
                 }
@@ -0,0 +1,199 @@
+//! Alignment check of the channel groups: range, sample rate and gaps of each master channel,
+//! with non monotonic masters and time jumps, before resampling or merging groups
+use std::fmt;
+
+use anyhow::{bail, Context, Result};
+use arrow::array::AsArray;
+use arrow::compute::cast;
+use arrow::datatypes::{DataType, Float64Type};
+
+use crate::mdfreader::Mdf;
+
+/// step between two consecutive valid samples of a master channel
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimeJump {
+    /// index of the sample after the step
+    pub index: usize,
+    /// master value before the step
+    pub from: f64,
+    /// master value after the step
+    pub to: f64,
+}
+
+/// alignment of a channel group from its master channel
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GroupAlignment {
+    pub master: String,
+    pub n_samples: usize,
+    /// number of invalid or NaN master values, ignored
+    pub n_invalid: usize,
+    /// first valid master value
+    pub start: Option<f64>,
+    /// last valid master value
+    pub end: Option<f64>,
+    /// median step between consecutive samples
+    pub period: Option<f64>,
+    /// mean number of samples per master unit
+    pub sample_rate: Option<f64>,
+    /// forward steps longer than the gap factor times the median step
+    pub gaps: Vec<TimeJump>,
+    /// backward steps of a non monotonic master, like clock resets
+    pub backward: Vec<TimeJump>,
+    /// number of samples with the same master value as the previous sample
+    pub n_duplicates: usize,
+}
+
+impl GroupAlignment {
+    /// true if master values never decrease
+    pub fn is_monotonic(&self) -> bool {
+        self.backward.is_empty()
+    }
+}
+
+/// alignment of all the channel groups
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AlignmentReport {
+    /// groups sorted by master channel name
+    pub groups: Vec<GroupAlignment>,
+    /// master range covered by all groups, None if groups do not overlap
+    pub common_range: Option<(f64, f64)>,
+}
+
+#[allow(dead_code)]
+impl AlignmentReport {
+    /// true if a master is not monotonic, has duplicates or gaps, or groups do not overlap
+    pub fn has_issues(&self) -> bool {
+        self.common_range.is_none()
+            || self.groups.iter().any(|group| {
+                !group.is_monotonic() || group.n_duplicates > 0 || !group.gaps.is_empty()
+            })
+    }
+}
+
+impl fmt::Display for AlignmentReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for group in self.groups.iter() {
+            write!(f, "{}: {} samples", group.master, group.n_samples)?;
+            if let (Some(start), Some(end)) = (group.start, group.end) {
+                write!(f, " from {start} to {end}")?;
+            }
+            if let Some(rate) = group.sample_rate {
+                write!(f, ", rate {rate:.6}")?;
+            }
+            if let Some(period) = group.period {
+                write!(f, ", period {period:.6}")?;
+            }
+            writeln!(f)?;
+            if group.n_invalid > 0 {
+                writeln!(f, "  {} invalid master values", group.n_invalid)?;
+            }
+            if group.n_duplicates > 0 {
+                writeln!(f, "  {} duplicate master values", group.n_duplicates)?;
+            }
+            for jump in group.backward.iter() {
+                writeln!(
+                    f,
+                    "  not monotonic at sample {}: {} -> {}",
+                    jump.index, jump.from, jump.to
+                )?;
+            }
+            for jump in group.gaps.iter() {
+                writeln!(
+                    f,
+                    "  gap at sample {}: {} -> {}",
+                    jump.index, jump.from, jump.to
+                )?;
+            }
+        }
+        match self.common_range {
+            Some((start, end)) => writeln!(f, "common range from {start} to {end}"),
+            None => writeln!(f, "groups do not overlap"),
+        }
+    }
+}
+
+/// alignment of the group from its master values
+fn group_alignment(master: String, values: &[Option<f64>], gap_factor: f64) -> GroupAlignment {
+    let valid: Vec<(usize, f64)> = values
+        .iter()
+        .enumerate()
+        .filter_map(|(i, value)| {
+            value
+                .filter(|value| !value.is_nan())
+                .map(|value| (i, value))
+        })
+        .collect();
+    let mut group = GroupAlignment {
+        master,
+        n_samples: values.len(),
+        n_invalid: values.len() - valid.len(),
+        start: valid.first().map(|(_, value)| *value),
+        end: valid.last().map(|(_, value)| *value),
+        ..Default::default()
+    };
+    let mut steps: Vec<f64> = valid
+        .windows(2)
+        .map(|pair| pair[1].1 - pair[0].1)
+        .filter(|step| *step > 0.0)
+        .collect();
+    steps.sort_by(f64::total_cmp);
+    group.period = steps.get(steps.len() / 2).copied();
+    if let (Some(start), Some(end)) = (group.start, group.end) {
+        if end > start {
+            group.sample_rate = Some((valid.len() - 1) as f64 / (end - start));
+        }
+    }
+    for pair in valid.windows(2) {
+        let ((_, from), (index, to)) = (pair[0], pair[1]);
+        let jump = TimeJump { index, from, to };
+        if to < from {
+            group.backward.push(jump);
+        } else if to == from {
+            group.n_duplicates += 1;
+        } else if group
+            .period
+            .is_some_and(|period| to - from > gap_factor * period)
+        {
+            group.gaps.push(jump);
+        }
+    }
+    group
+}
+
+/// checks the alignment of the channel groups whose master channel is in memory, gaps being
+/// steps longer than gap_factor times the median step of their master
+pub fn check_alignment(mdf: &Mdf, gap_factor: f64) -> Result<AlignmentReport> {
+    if gap_factor.is_nan() || gap_factor <= 1.0 {
+        bail!("gap factor {gap_factor} should be greater than 1");
+    }
+    let mut masters: Vec<String> = mdf
+        .get_master_channel_names_set()
+        .into_keys()
+        .flatten()
+        .collect();
+    masters.sort();
+    let mut report = AlignmentReport::default();
+    for master in masters {
+        let Some(data) = mdf
+            .get_channel_data(&master)
+            .filter(|data| !data.is_empty())
+        else {
+            continue;
+        };
+        let values = cast(&data.as_ref(), &DataType::Float64)
+            .with_context(|| format!("failed casting master channel {master} into f64"))?;
+        let values: Vec<Option<f64>> = values.as_primitive::<Float64Type>().iter().collect();
+        report
+            .groups
+            .push(group_alignment(master, &values, gap_factor));
+    }
+    report.common_range = report
+        .groups
+        .iter()
+        .map(|group| group.start.zip(group.end))
+        .try_fold((f64::NEG_INFINITY, f64::INFINITY), |(start, end), range| {
+            range.map(|(group_start, group_end)| (start.max(group_start), end.min(group_end)))
+        })
+        .filter(|(start, end)| start <= end && start.is_finite());
+    Ok(report)
+}
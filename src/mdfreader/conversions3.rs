@@ -1,99 +1,157 @@
 //! this modules implements functions to convert arrays into physical arrays using CCBlock
 use anyhow::{Context, Error, Result};
-use arrow::array::{Float64Array, Float64Builder, LargeStringBuilder, PrimitiveBuilder};
-use arrow::datatypes::{ArrowPrimitiveType, Float64Type};
+use arrow::array::{
+    DictionaryArray, Float64Array, Float64Builder, PrimitiveBuilder, StringDictionaryBuilder,
+};
+use arrow::datatypes::{ArrowPrimitiveType, Float64Type, UInt32Type};
 use arrow::error::ArrowError;
 use itertools::Itertools;
 use num::abs;
 use num::cast::AsPrimitive;
 use num::NumCast;
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashSet};
+use std::sync::OnceLock;
 
 use crate::data_holder::channel_data::ChannelData;
 use crate::mdfinfo::mdfinfo3::{Cn3, Conversion, Dg3, SharableBlocks3};
-use crate::mdfreader::conversions4::{linear_calculation, rational_calculation};
+use crate::mdfreader::conversions4::{
+    cast_to_float32, integer_linear_conversion, keep_integer, linear_calculation,
+    rational_calculation,
+};
+use crate::mdfreader::ReadOptions;
+use crate::parallel::prelude::*;
 use fasteval::Evaler;
 use fasteval::{Compiler, Instruction, Slab};
 use log::warn;
-use rayon::prelude::*;
 
-/// convert all channel arrays into physical values as required by CCBlock content
-pub fn convert_all_channels(dg: &mut Dg3, sharable: &SharableBlocks3) -> Result<(), Error> {
+/// convert all channel arrays into physical values as required by CCBlock content,
+/// only channels just read from channel_names are converted.
+/// Raw arrays are kept or their conversion deferred as requested in options
+pub fn convert_all_channels(
+    dg: &mut Dg3,
+    sharable: &SharableBlocks3,
+    channel_names: &HashSet<String>,
+    options: &ReadOptions,
+) -> Result<(), Error> {
     for channel_group in dg.cg.values_mut() {
         let cycle_count = channel_group.block.cg_cycle_count;
         channel_group
             .cn
             .par_iter_mut()
-            .filter(|(_cn_record_position, cn)| !cn.data.is_empty())
+            .filter(|(_cn_record_position, cn)| {
+                !cn.data.is_empty() && channel_names.contains(&cn.unique_name)
+            })
             .try_for_each(|(_rec_pos, cn): (&u32, &mut Cn3)| -> Result<(), Error> {
-                // Could be empty if only initialised
-                if let Some((_block, conv)) = sharable.cc.get(&cn.block1.cn_cc_conversion) {
-                    match conv {
-                        Conversion::Linear(cc_val) => linear_conversion(cn, cc_val)
-                            .with_context(|| {
-                            format!("linear conversion failed for {}", cn.unique_name)
-                        })?,
-                        Conversion::TabularInterpolation(cc_val) => {
-                            value_to_value_with_interpolation(cn, cc_val.clone(), &cycle_count).with_context(|| {
-                                format!("value to value with interpolation conversion failed for {}", cn.unique_name)
-                            })?
-                        }
-                        Conversion::Tabular(cc_val) => {
-                            value_to_value_without_interpolation(cn, cc_val.clone(), &cycle_count).with_context(|| {
-                                format!("value to value without interpolation conversion failed for {}", cn.unique_name)
-                            })?
-                        }
-                        Conversion::Rational(cc_val) => {
-                            rational_conversion(cn, cc_val).with_context(|| {
-                                format!("rational conversion failed for {}", cn.unique_name)
-                            })?
-                        }
-                        Conversion::Formula(formula) => {
-                            algebraic_conversion(cn, formula, &cycle_count).with_context(|| {
-                                format!("algebraic conversion failed for {}", cn.unique_name)
-                            })?
-                        }
-                        Conversion::Identity => {}
-                        Conversion::Polynomial(cc_val) => {
-                            polynomial_conversion(cn, cc_val).with_context(|| {
-                                format!("polynomial conversion failed for {}", cn.unique_name)
-                            })?
-                        }
-                        Conversion::Exponential(cc_val) => {
-                            exponential_conversion(cn, cc_val).with_context(|| {
-                                format!("exponential conversion failed for {}", cn.unique_name)
-                            })?
-                        }
-                        Conversion::Logarithmic(cc_val) => {
-                            logarithmic_conversion(cn, cc_val).with_context(|| {
-                                format!("logarithmic conversion failed for {}", cn.unique_name)
-                            })?
-                        }
-                        Conversion::TextTable(cc_val_ref) => {
-                            if !cc_val_ref.is_empty() {
-                                value_to_text(cn, cc_val_ref, &cycle_count).with_context(|| {
-                                    format!("value to text conversion failed for {}", cn.unique_name)
-                                })?
-                            }
-                        }
-                        Conversion::TextRangeTable(cc_val_ref) => {
-                            value_range_to_text(cn, cc_val_ref, &cycle_count).with_context(|| {
-                                format!("text range table conversion failed for {}", cn.unique_name)
-                            })?
-                        }
+                cn.raw_data = None;
+                cn.conversion_cache = None;
+                let dtype_override = options.dtype_overrides.get(&cn.unique_name);
+                if options.lazy_conversion && dtype_override.is_none() {
+                    // converted at first access of channel data
+                    if sharable.cc.contains_key(&cn.block1.cn_cc_conversion) {
+                        cn.conversion_cache = Some(OnceLock::new());
                     }
+                    return Ok(());
+                }
+                if options.keep_raw {
+                    cn.raw_data = Some(cn.data.clone());
                 }
-                Ok(())
+                let linear =
+                    sharable
+                        .cc
+                        .get(&cn.block1.cn_cc_conversion)
+                        .and_then(|(_block, conv)| match conv {
+                            Conversion::Linear(cc_val) => Some((cc_val[0], cc_val[1])),
+                            _ => None,
+                        });
+                // precision of raw data, before conversion
+                let single_precision = options.float32_conversions
+                    && cn
+                        .data
+                        .arrow_data_type()
+                        .primitive_width()
+                        .is_some_and(|width| width <= 4);
+                if !keep_integer(&mut cn.data, &cn.unique_name, dtype_override, linear)? {
+                    convert_channel(cn, sharable, cycle_count)?;
+                }
+                cast_to_float32(
+                    &mut cn.data,
+                    &cn.unique_name,
+                    dtype_override,
+                    single_precision,
+                )
             })?
     }
     Ok(())
 }
 
+/// convert channel array into physical values as required by its CCBlock
+pub(crate) fn convert_channel(
+    cn: &mut Cn3,
+    sharable: &SharableBlocks3,
+    cycle_count: u32,
+) -> Result<(), Error> {
+    // Could be empty if only initialised
+    if let Some((_block, conv)) = sharable.cc.get(&cn.block1.cn_cc_conversion) {
+        match conv {
+            Conversion::Linear(cc_val) => linear_conversion(cn, cc_val)
+                .with_context(|| format!("linear conversion failed for {}", cn.unique_name))?,
+            Conversion::TabularInterpolation(cc_val) => {
+                value_to_value_with_interpolation(cn, cc_val.clone(), &cycle_count).with_context(
+                    || {
+                        format!(
+                            "value to value with interpolation conversion failed for {}",
+                            cn.unique_name
+                        )
+                    },
+                )?
+            }
+            Conversion::Tabular(cc_val) => {
+                value_to_value_without_interpolation(cn, cc_val.clone(), &cycle_count)
+                    .with_context(|| {
+                        format!(
+                            "value to value without interpolation conversion failed for {}",
+                            cn.unique_name
+                        )
+                    })?
+            }
+            Conversion::Rational(cc_val) => rational_conversion(cn, cc_val)
+                .with_context(|| format!("rational conversion failed for {}", cn.unique_name))?,
+            Conversion::Formula(formula) => algebraic_conversion(cn, formula, &cycle_count)
+                .with_context(|| format!("algebraic conversion failed for {}", cn.unique_name))?,
+            Conversion::Identity => {}
+            Conversion::Polynomial(cc_val) => polynomial_conversion(cn, cc_val)
+                .with_context(|| format!("polynomial conversion failed for {}", cn.unique_name))?,
+            Conversion::Exponential(cc_val) => exponential_conversion(cn, cc_val)
+                .with_context(|| format!("exponential conversion failed for {}", cn.unique_name))?,
+            Conversion::Logarithmic(cc_val) => logarithmic_conversion(cn, cc_val)
+                .with_context(|| format!("logarithmic conversion failed for {}", cn.unique_name))?,
+            Conversion::TextTable(cc_val_ref) => {
+                if !cc_val_ref.is_empty() {
+                    value_to_text(cn, cc_val_ref, &cycle_count).with_context(|| {
+                        format!("value to text conversion failed for {}", cn.unique_name)
+                    })?
+                }
+            }
+            Conversion::TextRangeTable(cc_val_ref) => {
+                value_range_to_text(cn, cc_val_ref, &cycle_count).with_context(|| {
+                    format!("text range table conversion failed for {}", cn.unique_name)
+                })?
+            }
+        }
+    }
+    Ok(())
+}
+
 /// Apply linear conversion to get physical data
 fn linear_conversion(cn: &mut Cn3, cc_val: &[f64]) -> Result<(), Error> {
     let p1 = cc_val[0];
     let p2 = cc_val[1];
     if !(p1 == 0.0 && abs(p2 - 1.0) < 1e-12) {
+        if let Some(data) = integer_linear_conversion(&cn.data, p1, p2)? {
+            // integer factor and offset keep integer data
+            cn.data = data;
+            return Ok(());
+        }
         match &mut cn.data {
             ChannelData::UInt8(a) => {
                 cn.data = ChannelData::Float64(
@@ -812,9 +870,7 @@ where
         .iter_mut()
         .zip(array_f64.values())
         .for_each(|(new_array, a)| {
-            *new_array = match val
-                .binary_search_by(|&(xi, _)| xi.partial_cmp(a).expect("Could not compare values"))
-            {
+            *new_array = match val.binary_search_by(|&(xi, _)| xi.total_cmp(a)) {
                 Ok(idx) => *val[idx].1,
                 Err(0) => *val[0].1,
                 Err(idx) if idx >= val.len() => *val[idx - 1].1,
@@ -918,9 +974,7 @@ where
         .iter_mut()
         .zip(array_f64.values())
         .for_each(|(new_array, a)| {
-            *new_array = match val
-                .binary_search_by(|&(xi, _)| xi.partial_cmp(a).expect("Could not compare values"))
-            {
+            *new_array = match val.binary_search_by(|&(xi, _)| xi.total_cmp(a)) {
                 Ok(idx) => *val[idx].1,
                 Err(0) => *val[0].1,
                 Err(idx) if idx >= val.len() => *val[idx - 1].1,
@@ -1009,12 +1063,16 @@ fn value_to_text_calculation<T: ArrowPrimitiveType>(
     array: &mut PrimitiveBuilder<T>,
     cc_val_ref: &[(f64, String)],
     cycle_count: usize,
-) -> Result<LargeStringBuilder, Error>
+) -> Result<DictionaryArray<UInt32Type>, Error>
 where
     <T as ArrowPrimitiveType>::Native: AsPrimitive<f64>,
     T::Native: NumCast,
 {
-    let mut new_array = LargeStringBuilder::with_capacity(cycle_count, 32);
+    let mut new_array = StringDictionaryBuilder::<UInt32Type>::with_capacity(
+        cycle_count,
+        cc_val_ref.len(),
+        32 * cc_val_ref.len(),
+    );
     let array_f64: Float64Array = array
         .finish()
         .try_unary(|value| {
@@ -1031,7 +1089,7 @@ where
             new_array.append_value(cc_val_ref[0].1.clone());
         }
     });
-    Ok(new_array)
+    Ok(new_array.finish())
 }
 
 /// Apply value to text or scale conversion to get physical data
@@ -1043,61 +1101,61 @@ fn value_to_text(
     // identify max string length in cc_val_ref
     match &mut cn.data {
         ChannelData::Int8(a) => {
-            cn.data = ChannelData::Utf8(
+            cn.data = ChannelData::Dictionary(
                 value_to_text_calculation(a, cc_val_ref, *cycle_count as usize)
                     .context("value to text conversion of i8 channel")?,
             );
         }
         ChannelData::UInt8(a) => {
-            cn.data = ChannelData::Utf8(
+            cn.data = ChannelData::Dictionary(
                 value_to_text_calculation(a, cc_val_ref, *cycle_count as usize)
                     .context("value to text conversion of u8 channel")?,
             );
         }
         ChannelData::Int16(a) => {
-            cn.data = ChannelData::Utf8(
+            cn.data = ChannelData::Dictionary(
                 value_to_text_calculation(a, cc_val_ref, *cycle_count as usize)
                     .context("value to text conversion of i16 channel")?,
             );
         }
         ChannelData::UInt16(a) => {
-            cn.data = ChannelData::Utf8(
+            cn.data = ChannelData::Dictionary(
                 value_to_text_calculation(a, cc_val_ref, *cycle_count as usize)
                     .context("value to text conversion of u16 channel")?,
             );
         }
         ChannelData::Int32(a) => {
-            cn.data = ChannelData::Utf8(
+            cn.data = ChannelData::Dictionary(
                 value_to_text_calculation(a, cc_val_ref, *cycle_count as usize)
                     .context("value to text conversion of i32 channel")?,
             );
         }
         ChannelData::UInt32(a) => {
-            cn.data = ChannelData::Utf8(
+            cn.data = ChannelData::Dictionary(
                 value_to_text_calculation(a, cc_val_ref, *cycle_count as usize)
                     .context("value to text conversion of u32 channel")?,
             );
         }
         ChannelData::Float32(a) => {
-            cn.data = ChannelData::Utf8(
+            cn.data = ChannelData::Dictionary(
                 value_to_text_calculation(a, cc_val_ref, *cycle_count as usize)
                     .context("value to text conversion of f32 channel")?,
             );
         }
         ChannelData::Int64(a) => {
-            cn.data = ChannelData::Utf8(
+            cn.data = ChannelData::Dictionary(
                 value_to_text_calculation(a, cc_val_ref, *cycle_count as usize)
                     .context("value to text conversion of i64 channel")?,
             );
         }
         ChannelData::UInt64(a) => {
-            cn.data = ChannelData::Utf8(
+            cn.data = ChannelData::Dictionary(
                 value_to_text_calculation(a, cc_val_ref, *cycle_count as usize)
                     .context("value to text conversion of u64 channel")?,
             );
         }
         ChannelData::Float64(a) => {
-            cn.data = ChannelData::Utf8(
+            cn.data = ChannelData::Dictionary(
                 value_to_text_calculation(a, cc_val_ref, *cycle_count as usize)
                     .context("value to text conversion of f64 channel")?,
             );
@@ -1116,12 +1174,16 @@ fn value_range_to_text_calculation<T: ArrowPrimitiveType>(
     array: &mut PrimitiveBuilder<T>,
     cc_val_ref: &(Vec<(f64, f64, String)>, String),
     cycle_count: usize,
-) -> Result<LargeStringBuilder, Error>
+) -> Result<DictionaryArray<UInt32Type>, Error>
 where
     <T as ArrowPrimitiveType>::Native: AsPrimitive<f64>,
     T::Native: NumCast,
 {
-    let mut new_array = LargeStringBuilder::with_capacity(cycle_count, 32);
+    let mut new_array = StringDictionaryBuilder::<UInt32Type>::with_capacity(
+        cycle_count,
+        cc_val_ref.0.len() + 1,
+        32 * (cc_val_ref.0.len() + 1),
+    );
     let array_f64: Float64Array = array
         .finish()
         .try_unary(|value| {
@@ -1142,7 +1204,7 @@ where
             new_array.append_value(cc_val_ref.1.clone());
         }
     });
-    Ok(new_array)
+    Ok(new_array.finish())
 }
 
 /// Apply value range to text or scale conversion to get physical data
@@ -1153,61 +1215,61 @@ fn value_range_to_text(
 ) -> Result<(), Error> {
     match &mut cn.data {
         ChannelData::Int8(a) => {
-            cn.data = ChannelData::Utf8(
+            cn.data = ChannelData::Dictionary(
                 value_range_to_text_calculation(a, cc_val_ref, *cycle_count as usize)
                     .context("value range to text conversion of i8 channel")?,
             );
         }
         ChannelData::UInt8(a) => {
-            cn.data = ChannelData::Utf8(
+            cn.data = ChannelData::Dictionary(
                 value_range_to_text_calculation(a, cc_val_ref, *cycle_count as usize)
                     .context("value range to text conversion of u8 channel")?,
             );
         }
         ChannelData::Int16(a) => {
-            cn.data = ChannelData::Utf8(
+            cn.data = ChannelData::Dictionary(
                 value_range_to_text_calculation(a, cc_val_ref, *cycle_count as usize)
                     .context("value range to text conversion of i16 channel")?,
             );
         }
         ChannelData::UInt16(a) => {
-            cn.data = ChannelData::Utf8(
+            cn.data = ChannelData::Dictionary(
                 value_range_to_text_calculation(a, cc_val_ref, *cycle_count as usize)
                     .context("value range to text conversion of u16 channel")?,
             );
         }
         ChannelData::Int32(a) => {
-            cn.data = ChannelData::Utf8(
+            cn.data = ChannelData::Dictionary(
                 value_range_to_text_calculation(a, cc_val_ref, *cycle_count as usize)
                     .context("value range to text conversion of i32 channel")?,
             );
         }
         ChannelData::UInt32(a) => {
-            cn.data = ChannelData::Utf8(
+            cn.data = ChannelData::Dictionary(
                 value_range_to_text_calculation(a, cc_val_ref, *cycle_count as usize)
                     .context("value range to text conversion of u32 channel")?,
             );
         }
         ChannelData::Float32(a) => {
-            cn.data = ChannelData::Utf8(
+            cn.data = ChannelData::Dictionary(
                 value_range_to_text_calculation(a, cc_val_ref, *cycle_count as usize)
                     .context("value range to text conversion of f32 channel")?,
             );
         }
         ChannelData::Int64(a) => {
-            cn.data = ChannelData::Utf8(
+            cn.data = ChannelData::Dictionary(
                 value_range_to_text_calculation(a, cc_val_ref, *cycle_count as usize)
                     .context("value range to text conversion of i64 channel")?,
             );
         }
         ChannelData::UInt64(a) => {
-            cn.data = ChannelData::Utf8(
+            cn.data = ChannelData::Dictionary(
                 value_range_to_text_calculation(a, cc_val_ref, *cycle_count as usize)
                     .context("value range to text conversion of u64 channel")?,
             );
         }
         ChannelData::Float64(a) => {
-            cn.data = ChannelData::Utf8(
+            cn.data = ChannelData::Dictionary(
                 value_range_to_text_calculation(a, cc_val_ref, *cycle_count as usize)
                     .context("value range to text conversion of f64 channel")?,
             );
@@ -1,12 +1,13 @@
 //! this modules implements functions to convert arrays into physical arrays using CCBlock
-use anyhow::{bail, Context, Error, Result};
+use anyhow::{anyhow, bail, Context, Error, Result};
 use arrow::array::{
-    Array, ArrayBuilder, AsArray, Float64Array, Float64Builder, LargeStringBuilder,
-    PrimitiveBuilder,
+    Array, ArrayBuilder, AsArray, BooleanBufferBuilder, Float64Array, Float64Builder, Int64Array,
+    LargeStringBuilder, PrimitiveBuilder, StringDictionaryBuilder,
 };
-use arrow::compute::cast;
-use arrow::datatypes::{ArrowPrimitiveType, Float32Type, Float64Type};
-use arrow::datatypes::{DataType, Int64Type};
+use arrow::buffer::MutableBuffer;
+use arrow::compute::{cast, cast_with_options, CastOptions};
+use arrow::datatypes::{ArrowPrimitiveType, Float64Type};
+use arrow::datatypes::{DataType, Int64Type, UInt32Type};
 use arrow::error::ArrowError;
 use itertools::Itertools;
 use log::warn;
@@ -15,155 +16,318 @@ use num::cast::AsPrimitive;
 use num::{NumCast, ToPrimitive};
 
 use std::cmp::Ordering;
-use std::collections::{BTreeMap, HashMap};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::OnceLock;
 
-use crate::data_holder::channel_data::ChannelData;
+use crate::data_holder::channel_data::{try_from, ChannelData};
 use crate::data_holder::tensor_arrow::TensorArrow;
 use crate::mdfinfo::mdfinfo4::{Cc4Block, CcVal, Cn4, Dg4, SharableBlocks};
+use crate::mdfreader::{DataTypeOverride, ReadOptions};
+use crate::parallel::prelude::*;
 use fasteval::{Compiler, Evaler, Instruction, Slab};
-use rayon::prelude::*;
 
 use crate::data_holder::complex_arrow::ComplexArrow;
 
-/// convert all channel arrays into physical values as required by CCBlock content
-pub fn convert_all_channels(dg: &mut Dg4, sharable: &SharableBlocks) -> Result<(), Error> {
+/// convert all channel arrays into physical values as required by CCBlock content,
+/// only channels just read from channel_names are converted.
+/// Raw arrays are kept or their conversion deferred as requested in options
+pub fn convert_all_channels(
+    dg: &mut Dg4,
+    sharable: &SharableBlocks,
+    channel_names: &HashSet<String>,
+    options: &ReadOptions,
+) -> Result<(), Error> {
     for channel_group in dg.cg.values_mut() {
         channel_group
             .cn
             .par_iter_mut()
-            .filter(|(_cn_record_position, cn)| !cn.data.is_empty())
+            .filter(|(_cn_record_position, cn)| {
+                !cn.data.is_empty() && channel_names.contains(&cn.unique_name)
+            })
             .try_for_each(|(_rec_pos, cn): (&i32, &mut Cn4)| -> Result<(), Error> {
-                // Could be empty if only initialised
-                if let Some(conv) = sharable.cc.get(&cn.block.cn_cc_conversion) {
-                    match conv.cc_type {
-                        1 => match &conv.cc_val {
-                            CcVal::Real(cc_val) => {
-                                linear_conversion(cn, cc_val).with_context(|| {
-                                    format!("linear conversion failed for {}", cn.unique_name)
-                                })?
-                            }
-                            CcVal::Uint(_) => (),
-                        },
-                        2 => match &conv.cc_val {
-                            CcVal::Real(cc_val) => {
-                                rational_conversion(cn, cc_val).with_context(|| {
-                                    format!("rational conversion failed for {}", cn.unique_name)
-                                })?
-                            }
-                            CcVal::Uint(_) => (),
-                        },
-                        3 => {
-                            if !&conv.cc_ref.is_empty() {
-                                if let Ok(Some(conv)) = sharable.get_tx(conv.cc_ref[0]) {
-                                    algebraic_conversion(cn, &conv).with_context(|| {
-                                        format!(
-                                            "algebraic conversion failed for {}",
-                                            cn.unique_name
-                                        )
-                                    })?
-                                }
-                            }
-                        }
-                        4 => match &conv.cc_val {
-                            CcVal::Real(cc_val) => {
-                                value_to_value_with_interpolation(cn, cc_val.clone()).with_context(
-                                    || {
-                                        format!(
-                                    "value to value conversion with interpolation failed for {}",
-                                    cn.unique_name
-                                )
-                                    },
-                                )?
-                            }
-                            CcVal::Uint(_) => (),
-                        },
-                        5 => match &conv.cc_val {
-                            CcVal::Real(cc_val) => {
-                                value_to_value_without_interpolation(cn, cc_val.clone())
-                                    .with_context(|| {
-                                        format!(
-                                    "value to value conversion without interpolation failed for {}",
-                                    cn.unique_name
-                                )
-                                    })?
-                            }
-                            CcVal::Uint(_) => (),
-                        },
-                        6 => match &conv.cc_val {
-                            CcVal::Real(cc_val) => value_range_to_value_table(cn, cc_val.clone())
-                                .with_context(|| {
-                                format!(
-                                    "value range to value table conversion failed for {}",
-                                    cn.unique_name
-                                )
-                            })?,
-                            CcVal::Uint(_) => (),
-                        },
-                        7 => match &conv.cc_val {
-                            CcVal::Real(cc_val) => value_to_text(
-                                cn,
-                                cc_val,
-                                &conv.cc_ref,
-                                sharable,
-                            )
-                            .with_context(|| {
-                                format!("value to text conversion failed for {}", cn.unique_name)
-                            })?,
-                            CcVal::Uint(_) => (),
-                        },
-                        8 => match &conv.cc_val {
-                            CcVal::Real(cc_val) => {
-                                value_range_to_text(cn, cc_val, &conv.cc_ref, sharable)
-                                    .with_context(|| {
-                                        format!(
-                                            "value range to text conversion failed for {}",
-                                            cn.unique_name
-                                        )
-                                    })?
-                            }
-                            CcVal::Uint(_) => (),
-                        },
-                        9 => match &conv.cc_val {
-                            CcVal::Real(cc_val) => text_to_value(
-                                cn,
-                                cc_val,
-                                &conv.cc_ref,
-                                sharable,
-                            )
-                            .with_context(|| {
-                                format!("text to value conversion failed for {}", cn.unique_name)
-                            })?,
-                            CcVal::Uint(_) => (),
-                        },
-                        10 => text_to_text(cn, &conv.cc_ref, sharable).with_context(|| {
-                            format!("text to text conversion failed for {}", cn.unique_name)
-                        })?,
-                        11 => match &conv.cc_val {
-                            CcVal::Real(_) => (),
-                            CcVal::Uint(cc_val) => {
-                                bitfield_text_table(cn, cc_val, &conv.cc_ref, sharable)
-                                    .with_context(|| {
-                                        format!(
-                                            "bitfield text table conversion failed for {}",
-                                            cn.unique_name
-                                        )
-                                    })?
-                            }
-                        },
-                        0 => (),
-                        _ => bail!(
-                            "conversion type not recognised for channel {} not possible, type {}",
-                            cn.unique_name,
-                            conv.cc_type,
-                        ),
+                cn.raw_data = None;
+                cn.status = None;
+                cn.conversion_cache = None;
+                cn.spilled = None;
+                let dtype_override = options.dtype_overrides.get(&cn.unique_name);
+                if options.lazy_conversion && dtype_override.is_none() {
+                    // converted at first access of channel data
+                    if sharable.cc.contains_key(&cn.block.cn_cc_conversion) {
+                        cn.conversion_cache = Some(OnceLock::new());
                     }
+                    return Ok(());
                 }
-                Ok(())
+                if options.keep_raw {
+                    cn.raw_data = Some(cn.data.clone());
+                }
+                let linear =
+                    sharable
+                        .cc
+                        .get(&cn.block.cn_cc_conversion)
+                        .and_then(|cc| match &cc.cc_val {
+                            CcVal::Real(cc_val) if cc.cc_type == 1 => Some((cc_val[0], cc_val[1])),
+                            _ => None,
+                        });
+                // precision of raw data, before conversion
+                let single_precision = options.float32_conversions
+                    && cn
+                        .data
+                        .arrow_data_type()
+                        .primitive_width()
+                        .is_some_and(|width| width <= 4);
+                if !keep_integer(&mut cn.data, &cn.unique_name, dtype_override, linear)? {
+                    convert_channel(cn, sharable)?;
+                }
+                cast_to_float32(
+                    &mut cn.data,
+                    &cn.unique_name,
+                    dtype_override,
+                    single_precision,
+                )
             })?
     }
     Ok(())
 }
 
+/// converts integer data with its linear conversion into i64 if requested by the data
+/// type override, returns false if data still has to be converted
+pub(crate) fn keep_integer(
+    data: &mut ChannelData,
+    name: &str,
+    dtype_override: Option<&DataTypeOverride>,
+    linear: Option<(f64, f64)>,
+) -> Result<bool, Error> {
+    if dtype_override != Some(&DataTypeOverride::Integer) {
+        return Ok(false);
+    }
+    if let Some((p1, p2)) = linear {
+        if let Some(converted) = integer_linear_conversion(data, p1, p2)
+            .with_context(|| format!("integer linear conversion failed for {name}"))?
+        {
+            *data = converted;
+            return Ok(true);
+        }
+    }
+    warn!("channel {name} can not be kept as integer, converted with its conversion");
+    Ok(false)
+}
+
+/// casts f64 physical data into f32 if requested by the data type override, or by the
+/// f32 conversions read option when raw data has single precision
+pub(crate) fn cast_to_float32(
+    data: &mut ChannelData,
+    name: &str,
+    dtype_override: Option<&DataTypeOverride>,
+    single_precision: bool,
+) -> Result<(), Error> {
+    let forced = dtype_override == Some(&DataTypeOverride::Float32);
+    if !forced && !single_precision {
+        return Ok(());
+    }
+    match data {
+        ChannelData::Float64(a) => {
+            let array = cast(&a.finish_cloned(), &DataType::Float32)
+                .with_context(|| format!("failed casting channel {name} into f32"))?;
+            *data = try_from(&array)?;
+        }
+        ChannelData::Float32(_) => (),
+        _ if forced => warn!("channel {name} is not floating point, not cast into f32"),
+        _ => (),
+    }
+    Ok(())
+}
+
+/// returns the range of values of an integer data type
+fn integer_range(data_type: &DataType) -> Option<(i128, i128)> {
+    match data_type {
+        DataType::Int8 => Some((i8::MIN.into(), i8::MAX.into())),
+        DataType::UInt8 => Some((u8::MIN.into(), u8::MAX.into())),
+        DataType::Int16 => Some((i16::MIN.into(), i16::MAX.into())),
+        DataType::UInt16 => Some((u16::MIN.into(), u16::MAX.into())),
+        DataType::Int32 => Some((i32::MIN.into(), i32::MAX.into())),
+        DataType::UInt32 => Some((u32::MIN.into(), u32::MAX.into())),
+        DataType::Int64 => Some((i64::MIN.into(), i64::MAX.into())),
+        DataType::UInt64 => Some((u64::MIN.into(), u64::MAX.into())),
+        _ => None,
+    }
+}
+
+/// linear conversion of integer data keeping integers, None if data, factor or offset
+/// are not integers. The data type is kept if it can hold all converted values of its
+/// range, otherwise the smallest signed integer type holding them is used
+pub(crate) fn integer_linear_conversion(
+    data: &ChannelData,
+    p1: f64,
+    p2: f64,
+) -> Result<Option<ChannelData>, Error> {
+    let data_type = data.arrow_data_type();
+    let Some((min, max)) = integer_range(&data_type) else {
+        return Ok(None);
+    };
+    let integer = |value: f64| value.fract() == 0.0 && value.abs() <= i64::MAX as f64;
+    if !integer(p1) || !integer(p2) {
+        return Ok(None);
+    }
+    let (p1, p2) = (p1 as i64, p2 as i64);
+    if p1 == 0 && p2 == 1 {
+        return Ok(Some(data.clone()));
+    }
+    // conversion computed in i64
+    if max > i64::MAX as i128 {
+        return Ok(None);
+    }
+    let bounds = [min * p2 as i128 + p1 as i128, max * p2 as i128 + p1 as i128];
+    let (low, high) = (bounds[0].min(bounds[1]), bounds[0].max(bounds[1]));
+    let Some(target) = [data_type, DataType::Int16, DataType::Int32, DataType::Int64]
+        .into_iter()
+        .find(|target| integer_range(target).is_some_and(|(min, max)| min <= low && high <= max))
+    else {
+        return Ok(None);
+    };
+    let options = CastOptions {
+        safe: false,
+        ..Default::default()
+    };
+    let array = cast_with_options(&data.as_ref(), &DataType::Int64, &options)
+        .context("failed casting integer channel into i64")?;
+    // no overflow, the converted range fits in target type
+    let array: Int64Array = array
+        .as_primitive::<Int64Type>()
+        .unary(|value| value * p2 + p1);
+    let array = cast_with_options(&array, &target, &options)
+        .with_context(|| format!("failed casting converted channel into {target}"))?;
+    Ok(Some(try_from(&array)?))
+}
+
+/// convert channel array into physical values as required by its CCBlock
+pub(crate) fn convert_channel(cn: &mut Cn4, sharable: &SharableBlocks) -> Result<(), Error> {
+    // Could be empty if only initialised
+    if let Some(conv) = sharable.cc.get(&cn.block.cn_cc_conversion) {
+        // conversions building a new array do not keep the invalid values
+        let validity = if cn.data.nullable() {
+            cn.data.validity()
+        } else {
+            None
+        };
+        match conv.cc_type {
+            1 => match &conv.cc_val {
+                CcVal::Real(cc_val) => linear_conversion(cn, cc_val)
+                    .with_context(|| format!("linear conversion failed for {}", cn.unique_name))?,
+                CcVal::Uint(_) => (),
+            },
+            2 => match &conv.cc_val {
+                CcVal::Real(cc_val) => rational_conversion(cn, cc_val).with_context(|| {
+                    format!("rational conversion failed for {}", cn.unique_name)
+                })?,
+                CcVal::Uint(_) => (),
+            },
+            3 => {
+                if !&conv.cc_ref.is_empty() {
+                    if let Ok(Some(conv)) = sharable.get_tx(conv.cc_ref[0]) {
+                        algebraic_conversion(cn, &conv).with_context(|| {
+                            format!("algebraic conversion failed for {}", cn.unique_name)
+                        })?
+                    }
+                }
+            }
+            4 => match &conv.cc_val {
+                CcVal::Real(cc_val) => value_to_value_with_interpolation(cn, cc_val.clone())
+                    .with_context(|| {
+                        format!(
+                            "value to value conversion with interpolation failed for {}",
+                            cn.unique_name
+                        )
+                    })?,
+                CcVal::Uint(_) => (),
+            },
+            5 => match &conv.cc_val {
+                CcVal::Real(cc_val) => value_to_value_without_interpolation(cn, cc_val.clone())
+                    .with_context(|| {
+                        format!(
+                            "value to value conversion without interpolation failed for {}",
+                            cn.unique_name
+                        )
+                    })?,
+                CcVal::Uint(_) => (),
+            },
+            6 => match &conv.cc_val {
+                CcVal::Real(cc_val) => value_range_to_value_table(cn, cc_val.clone())
+                    .with_context(|| {
+                        format!(
+                            "value range to value table conversion failed for {}",
+                            cn.unique_name
+                        )
+                    })?,
+                CcVal::Uint(_) => (),
+            },
+            7 => match &conv.cc_val {
+                CcVal::Real(cc_val) => value_to_text(cn, cc_val, &conv.cc_ref, sharable)
+                    .with_context(|| {
+                        format!("value to text conversion failed for {}", cn.unique_name)
+                    })?,
+                CcVal::Uint(_) => (),
+            },
+            8 => match &conv.cc_val {
+                CcVal::Real(cc_val) => value_range_to_text(cn, cc_val, &conv.cc_ref, sharable)
+                    .with_context(|| {
+                        format!(
+                            "value range to text conversion failed for {}",
+                            cn.unique_name
+                        )
+                    })?,
+                CcVal::Uint(_) => (),
+            },
+            9 => match &conv.cc_val {
+                CcVal::Real(cc_val) => text_to_value(cn, cc_val, &conv.cc_ref, sharable)
+                    .with_context(|| {
+                        format!("text to value conversion failed for {}", cn.unique_name)
+                    })?,
+                CcVal::Uint(_) => (),
+            },
+            10 => text_to_text(cn, &conv.cc_ref, sharable).with_context(|| {
+                format!("text to text conversion failed for {}", cn.unique_name)
+            })?,
+            11 => match &conv.cc_val {
+                CcVal::Real(_) => (),
+                CcVal::Uint(cc_val) => bitfield_text_table(cn, cc_val, &conv.cc_ref, sharable)
+                    .with_context(|| {
+                        format!(
+                            "bitfield text table conversion failed for {}",
+                            cn.unique_name
+                        )
+                    })?,
+            },
+            0 => (),
+            _ => bail!(
+                "conversion type not recognised for channel {} not possible, type {}",
+                cn.unique_name,
+                conv.cc_type,
+            ),
+        }
+        if let (Some(validity), Some(status)) = (&validity, &mut cn.status) {
+            // partial conversion, invalid samples are neither numeric nor status
+            for data in [&mut cn.data, status] {
+                let mut mask = BooleanBufferBuilder::new(validity.len());
+                match data.validity() {
+                    Some(nulls) => mask.append_buffer(&(validity.inner() & nulls.inner())),
+                    None => mask.append_buffer(validity.inner()),
+                }
+                data.set_validity(&mut mask)
+                    .with_context(|| format!("failed restoring validity of {}", cn.unique_name))?;
+            }
+        } else if let Some(validity) = validity {
+            if !cn.data.nullable() {
+                let mut mask = BooleanBufferBuilder::new(validity.len());
+                mask.append_buffer(validity.inner());
+                cn.data
+                    .set_validity(&mut mask)
+                    .with_context(|| format!("failed restoring validity of {}", cn.unique_name))?;
+            }
+        }
+    }
+    Ok(())
+}
+
 /// Generic function calculating linear expression
 #[inline]
 pub fn linear_calculation<T: ArrowPrimitiveType>(
@@ -184,7 +348,7 @@ where
         })
         .context("failed converting array to f64")?
         .into_builder()
-        .expect("Failed getting mutable F64");
+        .map_err(|_| anyhow!("Failed getting mutable F64"))?;
     array_f64.values_slice_mut().iter_mut().for_each(|x| {
         *x = *x * p2 + p1;
     });
@@ -196,6 +360,11 @@ fn linear_conversion(cn: &mut Cn4, cc_val: &[f64]) -> Result<(), Error> {
     let p1 = cc_val[0];
     let p2 = cc_val[1];
     if !(p1 == 0.0 && abs(p2 - 1.0) < 1e-12) {
+        if let Some(data) = integer_linear_conversion(&cn.data, p1, p2)? {
+            // integer factor and offset keep integer data
+            cn.data = data;
+            return Ok(());
+        }
         match &mut cn.data {
             ChannelData::UInt8(a) => {
                 cn.data = ChannelData::Float64(
@@ -392,10 +561,10 @@ where
         .clone();
     let array_f64 = array_f64
         .unary_mut(|x| (x * x * p1 + x * p2 + p3) / (x * x * p4 + x * p5 + p6))
-        .expect("error applying rational conversion");
-    Ok(array_f64
+        .map_err(|_| anyhow!("error applying rational conversion"))?;
+    array_f64
         .into_builder()
-        .expect("failed converting to builder"))
+        .map_err(|_| anyhow!("failed converting to builder"))
 }
 
 /// Apply rational conversion to get physical data
@@ -614,11 +783,7 @@ where
     Ok(PrimitiveBuilder::new_from_buffer(
         new_array.into(),
         array_f64.nulls().map(|null_buffer| {
-            null_buffer
-                .inner()
-                .sliced()
-                .into_mutable()
-                .expect("failed converting null_buffer into mutable")
+            MutableBuffer::from(null_buffer.inner().sliced().as_slice().to_vec())
         }),
     ))
 }
@@ -1150,10 +1315,10 @@ where
             {
                 Ok(idx) => val[idx].2,
                 Err(0) => *default_value,
-                Err(idx) if (idx >= val.len() && *a <= val[idx - 1].1) => val[idx - 1].2,
+                // value is above lower limit of previous range
                 Err(idx) => {
-                    if *a <= val[idx].1 {
-                        val[idx].2
+                    if *a <= val[idx - 1].1 {
+                        val[idx - 1].2
                     } else {
                         *default_value
                     }
@@ -1270,6 +1435,76 @@ enum DefaultTextOrScaleConversion {
     Nil,
 }
 
+/// builder of value to text conversions output. Partial conversions, having table entries
+/// converted by another conversion block, give numeric values and text statuses in separate
+/// arrays, each one being null where the other is valid
+struct TextOrScaleBuilder {
+    labels: StringDictionaryBuilder<UInt32Type>,
+    values: Option<Float64Builder>,
+}
+
+impl TextOrScaleBuilder {
+    fn new(capacity: usize, n_labels: usize, partial: bool) -> Self {
+        TextOrScaleBuilder {
+            labels: StringDictionaryBuilder::with_capacity(capacity, n_labels, 32 * n_labels),
+            values: partial.then(|| Float64Builder::with_capacity(capacity)),
+        }
+    }
+    fn append_text(&mut self, text: &str) {
+        self.labels.append_value(text);
+        if let Some(values) = &mut self.values {
+            values.append_null();
+        }
+    }
+    fn append_scale(&mut self, conv: &ConversionFunction, a: f64) {
+        match &mut self.values {
+            Some(values) => {
+                values.append_value(conv.eval(a));
+                self.labels.append_null();
+            }
+            None => self.labels.append_value(conv.eval_to_txt(a)),
+        }
+    }
+    /// appends conversion of value a by table entry or default if no entry matched
+    fn append(
+        &mut self,
+        tosc: Option<&TextOrScaleConversion>,
+        def: &DefaultTextOrScaleConversion,
+        a: f64,
+    ) {
+        match (tosc, def) {
+            (Some(TextOrScaleConversion::Txt(txt)), _) => self.append_text(txt),
+            (Some(TextOrScaleConversion::Scale(conv)), _) => self.append_scale(conv, a),
+            (None, DefaultTextOrScaleConversion::DefaultTxt(txt)) => self.append_text(txt),
+            (None, DefaultTextOrScaleConversion::DefaultScale(conv)) => self.append_scale(conv, a),
+            _ => self.append_scale(&ConversionFunction::Identity, a),
+        }
+    }
+    /// stores labels as channel data, or values as channel data and labels as status
+    /// for partial conversion
+    fn store(mut self, cn: &mut Cn4) {
+        match self.values {
+            Some(values) => {
+                cn.data = ChannelData::Float64(values);
+                cn.status = Some(ChannelData::Dictionary(self.labels.finish()));
+            }
+            None => {
+                cn.data = ChannelData::Dictionary(self.labels.finish());
+                cn.status = None;
+            }
+        }
+    }
+}
+
+/// true if one table entry or the default is converted by another conversion block
+fn is_partial<'a>(
+    mut table: impl Iterator<Item = &'a TextOrScaleConversion>,
+    def: &DefaultTextOrScaleConversion,
+) -> bool {
+    matches!(def, DefaultTextOrScaleConversion::DefaultScale(_))
+        || table.any(|tosc| matches!(tosc, TextOrScaleConversion::Scale(_)))
+}
+
 /// Generic function calculating integer value range to text
 #[inline]
 fn value_to_text_calculation_int<T: ArrowPrimitiveType>(
@@ -1278,7 +1513,7 @@ fn value_to_text_calculation_int<T: ArrowPrimitiveType>(
     cc_ref: &[i64],
     def: &DefaultTextOrScaleConversion,
     sharable: &SharableBlocks,
-) -> Result<LargeStringBuilder, Error>
+) -> Result<TextOrScaleBuilder, Error>
 where
 {
     // table applicable only to integers, no canonization
@@ -1302,53 +1537,37 @@ where
         .context("failed converting Array to Int64 Array")?
         .as_primitive::<Int64Type>()
         .clone();
-    let mut new_array = LargeStringBuilder::with_capacity(array_f64.len(), 32);
+    let mut new_array = TextOrScaleBuilder::new(
+        array_f64.len(),
+        cc_val.len(),
+        is_partial(table_int.values(), def),
+    );
     array_f64
         .iter()
         .zip(array_i64.iter())
         .for_each(|(a_f64, a_i64)| {
-            if let Some(tosc) = table_int.get(&a_i64.unwrap_or_default()) {
-                match tosc {
-                    TextOrScaleConversion::Txt(txt) => {
-                        new_array.append_value(txt.clone());
-                    }
-                    TextOrScaleConversion::Scale(conv) => {
-                        new_array.append_value(conv.eval_to_txt(a_f64.unwrap_or(0f64)));
-                    }
-                    _ => {
-                        new_array.append_value(a_f64.unwrap_or(0f64).to_string());
-                    }
-                }
-            } else {
-                match &def {
-                    DefaultTextOrScaleConversion::DefaultTxt(txt) => {
-                        new_array.append_value(txt.clone());
-                    }
-                    DefaultTextOrScaleConversion::DefaultScale(conv) => {
-                        new_array.append_value(conv.eval_to_txt(a_f64.unwrap_or(0f64)));
-                    }
-                    _ => {
-                        new_array.append_value(a_f64.unwrap_or(0f64).to_string());
-                    }
-                }
-            }
+            new_array.append(
+                table_int.get(&a_i64.unwrap_or_default()),
+                def,
+                a_f64.unwrap_or(0f64),
+            );
         });
-    if let Some(validity) = array.validity_slice_mut() {
-        let _ = new_array.validity_slice_mut().insert(validity);
-    }
     Ok(new_array)
 }
 
 /// Generic function calculating float value range to text
 #[inline]
-fn value_to_text_calculation_f32(
-    a: &PrimitiveBuilder<Float32Type>,
+fn value_to_text_calculation_float<T: ArrowPrimitiveType>(
+    a: &PrimitiveBuilder<T>,
     cc_val: &[f64],
     cc_ref: &[i64],
     canonization_value: f64,
     def: &DefaultTextOrScaleConversion,
     sharable: &SharableBlocks,
-) -> LargeStringBuilder {
+) -> TextOrScaleBuilder
+where
+    <T as ArrowPrimitiveType>::Native: AsPrimitive<f64>,
+{
     // table for floating point comparison
     let mut table_float: HashMap<i64, TextOrScaleConversion> = HashMap::with_capacity(cc_val.len());
     for (ind, val) in cc_val.iter().enumerate() {
@@ -1362,37 +1581,12 @@ fn value_to_text_calculation_f32(
             table_float.insert(ref_val, TextOrScaleConversion::Nil);
         }
     }
-    let mut new_array = LargeStringBuilder::with_capacity(a.len(), 32);
+    let mut new_array =
+        TextOrScaleBuilder::new(a.len(), cc_val.len(), is_partial(table_float.values(), def));
     a.values_slice().iter().for_each(|a| {
-        let ref_val = (a * canonization_value as f32)
-            .round()
-            .to_i64()
-            .unwrap_or_default();
-        if let Some(tosc) = table_float.get(&ref_val) {
-            match tosc {
-                TextOrScaleConversion::Txt(txt) => {
-                    new_array.append_value(txt.clone());
-                }
-                TextOrScaleConversion::Scale(conv) => {
-                    new_array.append_value(conv.eval_to_txt(a.to_f64().unwrap_or_default()));
-                }
-                _ => {
-                    new_array.append_value(a.to_string());
-                }
-            }
-        } else {
-            match &def {
-                DefaultTextOrScaleConversion::DefaultTxt(txt) => {
-                    new_array.append_value(txt.clone());
-                }
-                DefaultTextOrScaleConversion::DefaultScale(conv) => {
-                    new_array.append_value(conv.eval_to_txt(a.to_f64().unwrap_or_default()));
-                }
-                _ => {
-                    new_array.append_value(a.to_string());
-                }
-            }
-        }
+        let a: f64 = a.as_();
+        let ref_val = (a * canonization_value).round() as i64;
+        new_array.append(table_float.get(&ref_val), def, a);
     });
     new_array
 }
@@ -1413,140 +1607,38 @@ fn value_to_text(
     } else {
         def = DefaultTextOrScaleConversion::Nil;
     }
-    match &mut cn.data {
-        ChannelData::Int8(a) => {
-            cn.data = ChannelData::Utf8(value_to_text_calculation_int(
-                a,
-                cc_val,
-                cc_ref,
-                &def,
-                sharable,
-            ).context("failed value to text conversion of i8 channel")?);
-        }
-        ChannelData::UInt8(a) => {
-            cn.data = ChannelData::Utf8(value_to_text_calculation_int(
-                a,
-                cc_val,
-                cc_ref,
-                &def,
-                sharable,
-            ).context("failed value to text conversion of u8 channel")?);
-        }
-        ChannelData::Int16(a) => {
-            cn.data = ChannelData::Utf8(value_to_text_calculation_int(
-                a,
-                cc_val,
-                cc_ref,
-                &def,
-                sharable,
-            ).context("failed value to text conversion of i16 channel")?);
-        }
-        ChannelData::UInt16(a) => {
-            cn.data = ChannelData::Utf8(value_to_text_calculation_int(
-                a,
-                cc_val,
-                cc_ref,
-                &def,
-                sharable,
-            ).context("failed value to text conversion of u16 channel")?);
-        }
-        ChannelData::Int32(a) => {
-            cn.data = ChannelData::Utf8(value_to_text_calculation_int(
-                a,
-                cc_val,
-                cc_ref,
-                &def,
-                sharable,
-            ).context("failed value to text conversion of i32 channel")?);
-        }
-        ChannelData::UInt32(a) => {
-            cn.data = ChannelData::Utf8(value_to_text_calculation_int(
-                a,
-                cc_val,
-                cc_ref,
-                &def,
-                sharable,
-            ).context("failed value to text conversion of i8 channel")?);
-        }
+    let new_array = match &mut cn.data {
+        ChannelData::Int8(a) => value_to_text_calculation_int(a, cc_val, cc_ref, &def, sharable)
+            .context("failed value to text conversion of i8 channel")?,
+        ChannelData::UInt8(a) => value_to_text_calculation_int(a, cc_val, cc_ref, &def, sharable)
+            .context("failed value to text conversion of u8 channel")?,
+        ChannelData::Int16(a) => value_to_text_calculation_int(a, cc_val, cc_ref, &def, sharable)
+            .context("failed value to text conversion of i16 channel")?,
+        ChannelData::UInt16(a) => value_to_text_calculation_int(a, cc_val, cc_ref, &def, sharable)
+            .context("failed value to text conversion of u16 channel")?,
+        ChannelData::Int32(a) => value_to_text_calculation_int(a, cc_val, cc_ref, &def, sharable)
+            .context("failed value to text conversion of i32 channel")?,
+        ChannelData::UInt32(a) => value_to_text_calculation_int(a, cc_val, cc_ref, &def, sharable)
+            .context("failed value to text conversion of u32 channel")?,
         ChannelData::Float32(a) => {
-            cn.data = ChannelData::Utf8(value_to_text_calculation_f32(
-                a,
-                cc_val,
-                cc_ref,
-                1048576.0f64,
-                &def,
-                sharable,
-            ));
-        }
-        ChannelData::Int64(a) => {
-            cn.data = ChannelData::Utf8(value_to_text_calculation_int(
-                a,
-                cc_val,
-                cc_ref,
-                &def,
-                sharable,
-            ).context("failed value to text conversion of i64 channel")?);
-        }
-        ChannelData::UInt64(a) => {
-            cn.data = ChannelData::Utf8(value_to_text_calculation_int(
-                a,
-                cc_val,
-                cc_ref,
-                &def,
-                sharable,
-            ).context("failed value to text conversion of u64 channel")?);
+            value_to_text_calculation_float(a, cc_val, cc_ref, 1048576.0f64, &def, sharable)
         }
+        ChannelData::Int64(a) => value_to_text_calculation_int(a, cc_val, cc_ref, &def, sharable)
+            .context("failed value to text conversion of i64 channel")?,
+        ChannelData::UInt64(a) => value_to_text_calculation_int(a, cc_val, cc_ref, &def, sharable)
+            .context("failed value to text conversion of u64 channel")?,
         ChannelData::Float64(a) => {
-            // table for floating point comparison
-            let mut table_float: HashMap<i64, TextOrScaleConversion> =
-                HashMap::with_capacity(cc_val.len());
-            for (ind, val) in cc_val.iter().enumerate() {
-                let ref_val = (*val * 1024.0 * 1024.0).round() as i64; // Canonization
-                if let Ok(Some(txt)) = sharable.get_tx(cc_ref[ind]) {
-                    table_float.insert(ref_val, TextOrScaleConversion::Txt(txt));
-                } else if let Some(cc) = sharable.cc.get(&cc_ref[ind]) {
-                    let conv = conversion_function(cc, sharable);
-                    table_float.insert(ref_val, TextOrScaleConversion::Scale(Box::new(conv)));
-                } else {
-                    table_float.insert(ref_val, TextOrScaleConversion::Nil);
-                }
-            }
-            let mut new_array = LargeStringBuilder::with_capacity(a.len(), 32);
-            a.values_slice().iter().for_each(|a| {
-                let ref_val = (a * 1024.0 * 1024.0).round() as i64;
-                if let Some(tosc) = table_float.get(&ref_val) {
-                    match tosc {
-                        TextOrScaleConversion::Txt(txt) => {
-                            new_array.append_value(txt.clone());
-                        }
-                        TextOrScaleConversion::Scale(conv) => {
-                            new_array.append_value(conv.eval_to_txt(*a));
-                        }
-                        _ => {
-                            new_array.append_value(a.to_string());
-                        }
-                    }
-                } else {
-                    match &def {
-                        DefaultTextOrScaleConversion::DefaultTxt(txt) => {
-                            new_array.append_value(txt.clone());
-                        }
-                        DefaultTextOrScaleConversion::DefaultScale(conv) => {
-                            new_array.append_value(conv.eval_to_txt(*a));
-                        }
-                        _ => {
-                            new_array.append_value(a.to_string());
-                        }
-                    }
-                }
-            });
-            cn.data = ChannelData::Utf8(new_array);
+            value_to_text_calculation_float(a, cc_val, cc_ref, 1048576.0f64, &def, sharable)
         }
-        _ => warn!(
-            "value to text conversion of channel {} not possible, channel does not contain primitive",
-            cn.unique_name
-        ),
-    }
+        _ => {
+            warn!(
+                "value to text conversion of channel {} not possible, channel does not contain primitive",
+                cn.unique_name
+            );
+            return Ok(());
+        }
+    };
+    new_array.store(cn);
     Ok(())
 }
 
@@ -1557,6 +1649,10 @@ enum ConversionFunction {
     Linear(f64, f64),
     Rational(f64, f64, f64, f64, f64, f64),
     Algebraic(Instruction, Box<Slab>),
+    /// value to value table, with or without interpolation
+    ValueToValue(Vec<(f64, f64)>, bool),
+    /// value range to value table and default value
+    ValueRangeToValue(Vec<(f64, f64, f64)>, f64),
 }
 
 /// conversion function of single value (not arrays)
@@ -1591,6 +1687,18 @@ fn conversion_function(cc: &Cc4Block, sharable: &SharableBlocks) -> ConversionFu
                     ConversionFunction::Identity
                 }
             }
+            4 | 5 => ConversionFunction::ValueToValue(
+                cc_val.iter().tuples().map(|(x, y)| (*x, *y)).collect(),
+                cc.cc_type == 4,
+            ),
+            6 => ConversionFunction::ValueRangeToValue(
+                cc_val
+                    .iter()
+                    .tuples()
+                    .map(|(min, max, value)| (*min, *max, *value))
+                    .collect(),
+                cc_val.last().copied().unwrap_or_default(),
+            ),
             _ => ConversionFunction::Identity,
         },
         CcVal::Uint(_) => ConversionFunction::Identity,
@@ -1600,25 +1708,51 @@ fn conversion_function(cc: &Cc4Block, sharable: &SharableBlocks) -> ConversionFu
 /// conversion function implmentation for single value (not arrays)
 impl ConversionFunction {
     fn eval_to_txt(&self, a: f64) -> String {
+        self.eval(a).to_string()
+    }
+    fn eval(&self, a: f64) -> f64 {
         match self {
-            ConversionFunction::Identity => a.to_string(),
-            ConversionFunction::Linear(p1, p2) => (a * p2 + p1).to_string(),
+            ConversionFunction::Identity => a,
+            ConversionFunction::Linear(p1, p2) => a * p2 + p1,
             ConversionFunction::Rational(p1, p2, p3, p4, p5, p6) => {
                 let a_2 = f64::powi(a, 2);
-                ((a_2 * p1 + a * p2 + p3) / (a_2 * p4 + a * p5 + p6)).to_string()
+                (a_2 * p1 + a * p2 + p3) / (a_2 * p4 + a * p5 + p6)
+            }
+            ConversionFunction::ValueToValue(val, interpolation) => {
+                match val.binary_search_by(|(xi, _)| xi.partial_cmp(&a).unwrap_or(Ordering::Equal))
+                {
+                    Ok(idx) => val[idx].1,
+                    Err(0) => val.first().map_or(a, |(_, y)| *y),
+                    Err(idx) if idx >= val.len() => val[idx - 1].1,
+                    Err(idx) => {
+                        let (x0, y0) = val[idx - 1];
+                        let (x1, y1) = val[idx];
+                        if *interpolation {
+                            (y0 * (x1 - a) + y1 * (a - x0)) / (x1 - x0)
+                        } else if (a - x0) > (x1 - a) {
+                            y1
+                        } else {
+                            y0
+                        }
+                    }
+                }
             }
+            ConversionFunction::ValueRangeToValue(val, default_value) => val
+                .iter()
+                .find(|(min, max, _)| *min <= a && a <= *max)
+                .map_or(*default_value, |(_, _, value)| *value),
             ConversionFunction::Algebraic(compiled, slab) => {
                 let mut map: BTreeMap<String, f64> = BTreeMap::new();
                 map.insert("X".to_string(), a);
                 let result = compiled.eval(slab, &mut map);
                 match result {
-                    Ok(res) => res.to_string(),
+                    Ok(res) => res,
                     Err(e) => {
                         warn!(
                             "could not evaluate algebraic expression for {}, error {}",
                             a, e
                         );
-                        a.to_string()
+                        a
                     }
                 }
             }
@@ -1640,7 +1774,7 @@ fn value_range_to_text_calculation<T: ArrowPrimitiveType>(
     cc_val: &[f64],
     cc_ref: &[i64],
     sharable: &SharableBlocks,
-) -> LargeStringBuilder {
+) -> Result<TextOrScaleBuilder, Error> {
     let n_keys = cc_val.len() / 2;
     let mut keys: Vec<KeyRange> = Vec::with_capacity(n_keys);
     for (key_min, key_max) in cc_val.iter().tuples() {
@@ -1670,9 +1804,10 @@ fn value_range_to_text_calculation<T: ArrowPrimitiveType>(
     } else {
         def = DefaultTextOrScaleConversion::Nil;
     }
-    let mut new_array = LargeStringBuilder::with_capacity(array.len(), 32);
+    let mut new_array =
+        TextOrScaleBuilder::new(array.len(), n_keys + 1, is_partial(txt.iter(), &def));
     let array_f64: Float64Array = cast(&array.finish_cloned(), &DataType::Float64)
-        .expect("failed converting Array to f64 Array")
+        .context("failed converting Array to f64 Array")?
         .as_primitive::<Float64Type>()
         .clone();
     array_f64.values().iter().for_each(|a| {
@@ -1680,33 +1815,9 @@ fn value_range_to_text_calculation<T: ArrowPrimitiveType>(
             .iter()
             .enumerate()
             .find(|&x| (&x.1.min <= a) && (a <= &x.1.max));
-        if let Some(key) = matched_key {
-            match &txt[key.0] {
-                TextOrScaleConversion::Txt(txt) => {
-                    new_array.append_value(txt.clone());
-                }
-                TextOrScaleConversion::Scale(conv) => {
-                    new_array.append_value(conv.eval_to_txt(*a));
-                }
-                _ => {
-                    new_array.append_value(a.to_string());
-                }
-            }
-        } else {
-            match &def {
-                DefaultTextOrScaleConversion::DefaultTxt(txt) => {
-                    new_array.append_value(txt.clone());
-                }
-                DefaultTextOrScaleConversion::DefaultScale(conv) => {
-                    new_array.append_value(conv.eval_to_txt(*a));
-                }
-                _ => {
-                    new_array.append_value(a.to_string());
-                }
-            }
-        }
+        new_array.append(matched_key.map(|key| &txt[key.0]), &def, *a);
     });
-    new_array
+    Ok(new_array)
 }
 
 /// Apply value range to text or scale conversion to get physical data
@@ -1716,92 +1827,26 @@ fn value_range_to_text(
     cc_ref: &[i64],
     sharable: &SharableBlocks,
 ) -> Result<(), Error> {
-    match &mut cn.data {
-        ChannelData::Int8(a) => {
-            cn.data = ChannelData::Utf8(value_range_to_text_calculation(
-                a,
-                cc_val,
-                cc_ref,
-                sharable,
-            ));
-        }
-        ChannelData::UInt8(a) => {
-            cn.data = ChannelData::Utf8(value_range_to_text_calculation(
-                a,
-                cc_val,
-                cc_ref,
-                sharable,
-            ));
-        }
-        ChannelData::Int16(a) => {
-            cn.data = ChannelData::Utf8(value_range_to_text_calculation(
-                a,
-                cc_val,
-                cc_ref,
-                sharable,
-            ));
-        }
-        ChannelData::UInt16(a) => {
-            cn.data = ChannelData::Utf8(value_range_to_text_calculation(
-                a,
-                cc_val,
-                cc_ref,
-                sharable,
-            ));
-        }
-        ChannelData::Int32(a) => {
-            cn.data = ChannelData::Utf8(value_range_to_text_calculation(
-                a,
-                cc_val,
-                cc_ref,
-                sharable,
-            ));
-        }
-        ChannelData::UInt32(a) => {
-            cn.data = ChannelData::Utf8(value_range_to_text_calculation(
-                a,
-                cc_val,
-                cc_ref,
-                sharable,
-            ));
-        }
-        ChannelData::Float32(a) => {
-            cn.data = ChannelData::Utf8(value_range_to_text_calculation(
-                a,
-                cc_val,
-                cc_ref,
-                sharable,
-            ));
-        }
-        ChannelData::Int64(a) => {
-            cn.data = ChannelData::Utf8(value_range_to_text_calculation(
-                a,
-                cc_val,
-                cc_ref,
-                sharable,
-            ));
-        }
-        ChannelData::UInt64(a) => {
-            cn.data = ChannelData::Utf8(value_range_to_text_calculation(
-                a,
-                cc_val,
-                cc_ref,
-                sharable,
-            ));
-        }
-        ChannelData::Float64(a) => {
-            cn.data = ChannelData::Utf8(value_range_to_text_calculation(
-                a,
-                cc_val,
-                cc_ref,
-                sharable,
-            ));
+    let new_array = match &mut cn.data {
+        ChannelData::Int8(a) => value_range_to_text_calculation(a, cc_val, cc_ref, sharable)?,
+        ChannelData::UInt8(a) => value_range_to_text_calculation(a, cc_val, cc_ref, sharable)?,
+        ChannelData::Int16(a) => value_range_to_text_calculation(a, cc_val, cc_ref, sharable)?,
+        ChannelData::UInt16(a) => value_range_to_text_calculation(a, cc_val, cc_ref, sharable)?,
+        ChannelData::Int32(a) => value_range_to_text_calculation(a, cc_val, cc_ref, sharable)?,
+        ChannelData::UInt32(a) => value_range_to_text_calculation(a, cc_val, cc_ref, sharable)?,
+        ChannelData::Float32(a) => value_range_to_text_calculation(a, cc_val, cc_ref, sharable)?,
+        ChannelData::Int64(a) => value_range_to_text_calculation(a, cc_val, cc_ref, sharable)?,
+        ChannelData::UInt64(a) => value_range_to_text_calculation(a, cc_val, cc_ref, sharable)?,
+        ChannelData::Float64(a) => value_range_to_text_calculation(a, cc_val, cc_ref, sharable)?,
+        _ => {
+            warn!(
+                "value range to text conversion of channel {} not possible, channel does not contain primitive",
+                cn.unique_name
+            );
+            return Ok(());
         }
-        _ => warn!(
-            "value range to text conversion of channel {} not possible, channel does not contain primitive",
-            cn.unique_name
-        ),
-    }
+    };
+    new_array.store(cn);
     Ok(())
 }
 
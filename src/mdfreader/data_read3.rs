@@ -1,13 +1,12 @@
 //! this module implements low level data reading for mdf3 files.
-use crate::mdfinfo::mdfinfo3::Cn3;
+use crate::mdfinfo::mdfinfo3::{Cn3, VIRTUAL_MASTER};
+use crate::parallel::prelude::*;
 use anyhow::{bail, Context, Error, Result};
 use byteorder::{BigEndian, LittleEndian, ReadBytesExt};
 use encoding_rs::WINDOWS_1252;
 use half::f16;
-use rayon::prelude::*;
 use std::collections::HashMap;
 use std::collections::HashSet;
-use std::io::Cursor;
 
 use crate::data_holder::channel_data::ChannelData;
 
@@ -22,7 +21,10 @@ pub fn read_channels_from_bytes(
     // iterates for each channel in parallel with rayon crate
     channels
         .par_iter_mut()
-        .filter(|(_cn_record_position, cn)| channel_names_to_read_in_dg.contains(&cn.unique_name))
+        .filter(|(_cn_record_position, cn)| {
+            channel_names_to_read_in_dg.contains(&cn.unique_name)
+                && cn.block1.cn_type != VIRTUAL_MASTER
+        })
         .try_for_each(|(_cn_pos, cn): (&u32, &mut Cn3)| -> Result<(), Error> {
             let mut value: &[u8]; // value of channel at record
             let pos_byte_beg = cn.pos_byte_beg as usize;
@@ -198,21 +200,12 @@ pub fn read_channels_from_bytes(
                 }
                 ChannelData::Int64(a) => {
                     let data = a.values_slice_mut();
-                    if cn.endian {
-                        if n_bytes == 8 {
-                            for (i, record) in data_chunk.chunks(record_length).enumerate() {
-                                value = &record[pos_byte_beg..pos_byte_beg + n_bytes];
-                                data[i + previous_index] = i64::from_be_bytes(
-                                    value.try_into().context("Could not read be i64")?,
-                                );
-                            }
-                        } else if n_bytes == 6 {
-                            for (i, record) in data_chunk.chunks(record_length).enumerate() {
-                                value = &record[pos_byte_beg..pos_byte_beg + n_bytes];
-                                data[i + previous_index] = value
-                                    .read_i48::<BigEndian>()
-                                    .context("Could not read be i48")?;
-                            }
+                    if n_bytes == 8 && cn.endian {
+                        for (i, record) in data_chunk.chunks(record_length).enumerate() {
+                            value = &record[pos_byte_beg..pos_byte_beg + n_bytes];
+                            data[i + previous_index] = i64::from_be_bytes(
+                                value.try_into().context("Could not read be i64")?,
+                            );
                         }
                     } else if n_bytes == 8 {
                         for (i, record) in data_chunk.chunks(record_length).enumerate() {
@@ -221,47 +214,31 @@ pub fn read_channels_from_bytes(
                                 value.try_into().context("Could not read le i64")?,
                             );
                         }
-                    } else if n_bytes == 6 {
+                    } else if cn.endian {
+                        // 5 to 7 bytes, sign extended
+                        for (i, record) in data_chunk.chunks(record_length).enumerate() {
+                            value = &record[pos_byte_beg..pos_byte_beg + n_bytes];
+                            data[i + previous_index] = value
+                                .read_int::<BigEndian>(n_bytes)
+                                .context("Could not read be i64 from less bytes")?;
+                        }
+                    } else {
                         for (i, record) in data_chunk.chunks(record_length).enumerate() {
                             value = &record[pos_byte_beg..pos_byte_beg + n_bytes];
                             data[i + previous_index] = value
-                                .read_i48::<LittleEndian>()
-                                .context("Could not read le i48")?;
+                                .read_int::<LittleEndian>(n_bytes)
+                                .context("Could not read le i64 from less bytes")?;
                         }
                     }
                 }
                 ChannelData::UInt64(a) => {
                     let data = a.values_slice_mut();
-                    if cn.endian {
-                        if n_bytes == 8 {
-                            for (i, record) in data_chunk.chunks(record_length).enumerate() {
-                                value = &record[pos_byte_beg..pos_byte_beg + n_bytes];
-                                data[i + previous_index] = u64::from_be_bytes(
-                                    value.try_into().context("Could not read be u64")?,
-                                );
-                            }
-                        } else if n_bytes == 7 {
-                            let mut buf = [0u8; std::mem::size_of::<u64>()];
-                            for (i, record) in data_chunk.chunks(record_length).enumerate() {
-                                buf[0..7].copy_from_slice(&record[pos_byte_beg..pos_byte_beg + 7]);
-                                data[i + previous_index] = u64::from_be_bytes(buf);
-                            }
-                        } else if n_bytes == 6 {
-                            for (i, record) in data_chunk.chunks(record_length).enumerate() {
-                                value = &record[pos_byte_beg..pos_byte_beg + n_bytes];
-                                data[i + previous_index] = value
-                                    .read_u48::<BigEndian>()
-                                    .context("Could not read be u48")?;
-                            }
-                        } else {
-                            let mut buf = [0u8; 6];
-                            for (i, record) in data_chunk.chunks(record_length).enumerate() {
-                                buf[0..5]
-                                    .copy_from_slice(&record[pos_byte_beg..pos_byte_beg + n_bytes]);
-                                data[i + previous_index] = Cursor::new(buf)
-                                    .read_u48::<BigEndian>()
-                                    .context("Could not read be u48 from 5 bytes")?;
-                            }
+                    if n_bytes == 8 && cn.endian {
+                        for (i, record) in data_chunk.chunks(record_length).enumerate() {
+                            value = &record[pos_byte_beg..pos_byte_beg + n_bytes];
+                            data[i + previous_index] = u64::from_be_bytes(
+                                value.try_into().context("Could not read be u64")?,
+                            );
                         }
                     } else if n_bytes == 8 {
                         for (i, record) in data_chunk.chunks(record_length).enumerate() {
@@ -270,28 +247,20 @@ pub fn read_channels_from_bytes(
                                 value.try_into().context("Could not read le u64")?,
                             );
                         }
-                    } else if n_bytes == 7 {
-                        // little endian
-                        let mut buf = [0u8; std::mem::size_of::<u64>()];
-                        for (i, record) in data_chunk.chunks(record_length).enumerate() {
-                            buf[0..7].copy_from_slice(&record[pos_byte_beg..pos_byte_beg + 7]);
-                            data[i + previous_index] = u64::from_le_bytes(buf);
-                        }
-                    } else if n_bytes == 6 {
+                    } else if cn.endian {
+                        // 5 to 7 bytes
                         for (i, record) in data_chunk.chunks(record_length).enumerate() {
                             value = &record[pos_byte_beg..pos_byte_beg + n_bytes];
                             data[i + previous_index] = value
-                                .read_u48::<LittleEndian>()
-                                .context("Could not read le u48")?;
+                                .read_uint::<BigEndian>(n_bytes)
+                                .context("Could not read be u64 from less bytes")?;
                         }
                     } else {
-                        // n_bytes = 5
-                        let mut buf = [0u8; 6];
                         for (i, record) in data_chunk.chunks(record_length).enumerate() {
-                            buf[0..5].copy_from_slice(&record[pos_byte_beg..pos_byte_beg + 5]);
-                            data[i + previous_index] = Cursor::new(buf)
-                                .read_u48::<LittleEndian>()
-                                .context("Could not read le u48 from 5 bytes")?;
+                            value = &record[pos_byte_beg..pos_byte_beg + n_bytes];
+                            data[i + previous_index] = value
+                                .read_uint::<LittleEndian>(n_bytes)
+                                .context("Could not read le u64 from less bytes")?;
                         }
                     }
                 }
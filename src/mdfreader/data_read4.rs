@@ -1,7 +1,8 @@
 //! this module implements low level data reading for mdf4 files.
 use crate::data_holder::tensor_arrow::TensorArrow;
 use crate::mdfinfo::mdfinfo4::{Cn4, CnType};
-use anyhow::{Context, Error, Ok, Result};
+use crate::parallel::prelude::*;
+use anyhow::{anyhow, bail, Context, Error, Ok, Result};
 use arrow::array::{
     Float32Builder, Float64Builder, Int16Builder, Int32Builder, Int64Builder, Int8Builder,
     UInt16Builder, UInt32Builder, UInt64Builder, UInt8Builder,
@@ -9,27 +10,28 @@ use arrow::array::{
 use byteorder::{BigEndian, LittleEndian, ReadBytesExt};
 use encoding_rs::{UTF_16BE, UTF_16LE, WINDOWS_1252};
 use half::f16;
-use rayon::prelude::*;
+use log::warn;
 use std::io::Cursor;
-use std::str;
 use std::{
-    collections::HashSet,
+    collections::{HashMap, HashSet},
+    ops::Range,
     sync::{Arc, Mutex},
 };
 
 use crate::data_holder::channel_data::ChannelData;
+use crate::mdfreader::StringPolicy;
 
 /// converts raw data block containing only one channel into a ndarray
 pub fn read_one_channel_array(
     data_bytes: &Vec<u8>,
     cn: &mut Cn4,
     cycle_count: usize,
+    string_policy: StringPolicy,
 ) -> Result<(), Error> {
-    if (cn.block.cn_type == 0
+    if cn.block.cn_type == 0
         || cn.block.cn_type == 2
         || cn.block.cn_type == 4
-        || cn.block.cn_type == 5)
-        && !cn.data.is_empty()
+        || cn.block.cn_type == 5
     {
         // cn_type == 5 : Maximum length data channel, removing no valid bytes done by another size channel pointed by cn_data
         // cn_type == 0 : fixed length data channel
@@ -46,7 +48,8 @@ pub fn read_one_channel_array(
                 *a = Int8Builder::new_from_buffer(buf.into(), None);
             }
             ChannelData::UInt8(a) => {
-                *a = UInt8Builder::new_from_buffer(data_bytes.clone().into(), None);
+                let buf: Vec<u8> = data_bytes.iter().take(cycle_count).copied().collect();
+                *a = UInt8Builder::new_from_buffer(buf.into(), None);
             }
             ChannelData::Int16(a) => {
                 let mut buf = vec![0; cycle_count];
@@ -55,9 +58,7 @@ pub fn read_one_channel_array(
                         .read_i16_into::<BigEndian>(&mut buf)
                         .context("Could not read be i16 array")?;
                 } else {
-                    Cursor::new(data_bytes)
-                        .read_i16_into::<LittleEndian>(&mut buf)
-                        .context("Could not read le i16 array")?;
+                    copy_le_values(data_bytes, &mut buf).context("Could not read le i16 array")?;
                 }
                 *a = Int16Builder::new_from_buffer(buf.into(), None);
             }
@@ -68,9 +69,7 @@ pub fn read_one_channel_array(
                         .read_u16_into::<BigEndian>(&mut buf)
                         .context("Could not read be u16 array")?;
                 } else {
-                    Cursor::new(data_bytes)
-                        .read_u16_into::<LittleEndian>(&mut buf)
-                        .context("Could not read le 16 array")?;
+                    copy_le_values(data_bytes, &mut buf).context("Could not read le 16 array")?;
                 }
                 *a = UInt16Builder::new_from_buffer(buf.into(), None);
             }
@@ -82,8 +81,7 @@ pub fn read_one_channel_array(
                             .read_i32_into::<BigEndian>(&mut buf)
                             .context("Could not read be i32 array")?;
                     } else {
-                        Cursor::new(data_bytes)
-                            .read_i32_into::<LittleEndian>(&mut buf)
+                        copy_le_values(data_bytes, &mut buf)
                             .context("Could not read le i32 array")?;
                     }
                     *a = Int32Builder::new_from_buffer(buf.into(), None);
@@ -112,8 +110,7 @@ pub fn read_one_channel_array(
                             .read_u32_into::<BigEndian>(&mut buf)
                             .context("Could not read be u32 array")?;
                     } else {
-                        Cursor::new(data_bytes)
-                            .read_u32_into::<LittleEndian>(&mut buf)
+                        copy_le_values(data_bytes, &mut buf)
                             .context("Could not read le u32 array")?;
                     }
                     *a = UInt32Builder::new_from_buffer(buf.into(), None);
@@ -142,8 +139,7 @@ pub fn read_one_channel_array(
                             .read_f32_into::<BigEndian>(&mut buf)
                             .context("Could not read be f32 array")?;
                     } else {
-                        Cursor::new(data_bytes)
-                            .read_f32_into::<LittleEndian>(&mut buf)
+                        copy_le_values(data_bytes, &mut buf)
                             .context("Could not read le f32 array")?;
                     }
                     *a = Float32Builder::new_from_buffer(buf.into(), None);
@@ -176,24 +172,24 @@ pub fn read_one_channel_array(
                             .read_i64_into::<BigEndian>(&mut buf)
                             .context("Could not read be i64 array")?;
                     } else {
-                        Cursor::new(data_bytes)
-                            .read_i64_into::<LittleEndian>(&mut buf)
+                        copy_le_values(data_bytes, &mut buf)
                             .context("Could not read le i64 array")?;
                     }
                     *a = Int64Builder::new_from_buffer(buf.into(), None);
-                } else if n_bytes == 6 {
+                } else {
+                    // 5 to 7 bytes, sign extended
                     let data = a.values_slice_mut();
                     if cn.endian {
                         for (i, mut value) in data_bytes.chunks(n_bytes).enumerate() {
                             data[i] = value
-                                .read_i48::<BigEndian>()
-                                .context("Could not read be i48")?;
+                                .read_int::<BigEndian>(n_bytes)
+                                .context("Could not read be i64 from less bytes")?;
                         }
                     } else {
                         for (i, mut value) in data_bytes.chunks(n_bytes).enumerate() {
                             data[i] = value
-                                .read_i48::<LittleEndian>()
-                                .context("Could not read le i48")?;
+                                .read_int::<LittleEndian>(n_bytes)
+                                .context("Could not read le i64 from less bytes")?;
                         }
                     }
                 }
@@ -206,57 +202,24 @@ pub fn read_one_channel_array(
                             .read_u64_into::<BigEndian>(&mut buf)
                             .context("Could not read be u64 array")?;
                     } else {
-                        Cursor::new(data_bytes)
-                            .read_u64_into::<LittleEndian>(&mut buf)
+                        copy_le_values(data_bytes, &mut buf)
                             .context("Could not read le u64 array")?;
                     }
                     *a = UInt64Builder::new_from_buffer(buf.into(), None);
-                } else if n_bytes == 7 {
-                    let mut temp = [0u8; std::mem::size_of::<u64>()];
-                    let data = a.values_slice_mut();
-                    if cn.endian {
-                        for (i, value) in data_bytes.chunks(n_bytes).enumerate() {
-                            temp[0..7].copy_from_slice(&value[0..7]);
-                            data[i] = u64::from_be_bytes(temp);
-                        }
-                    } else {
-                        for (i, value) in data_bytes.chunks(n_bytes).enumerate() {
-                            temp[0..7].copy_from_slice(&value[0..7]);
-                            data[i] = u64::from_le_bytes(temp);
-                        }
-                    }
-                } else if n_bytes == 6 {
+                } else {
+                    // 5 to 7 bytes
                     let data = a.values_slice_mut();
                     if cn.endian {
                         for (i, mut value) in data_bytes.chunks(n_bytes).enumerate() {
                             data[i] = value
-                                .read_u48::<BigEndian>()
-                                .context("Could not read be u48")?;
+                                .read_uint::<BigEndian>(n_bytes)
+                                .context("Could not read be u64 from less bytes")?;
                         }
                     } else {
                         for (i, mut value) in data_bytes.chunks(n_bytes).enumerate() {
                             data[i] = value
-                                .read_u48::<LittleEndian>()
-                                .context("Could not read le u48")?;
-                        }
-                    }
-                } else {
-                    // n_bytes = 5
-                    let mut temp = [0u8; 6];
-                    let data = a.values_slice_mut();
-                    if cn.endian {
-                        for (i, value) in data_bytes.chunks(n_bytes).enumerate() {
-                            temp[0..5].copy_from_slice(&value[0..n_bytes]);
-                            data[i] = Cursor::new(temp)
-                                .read_u48::<BigEndian>()
-                                .context("Could not read be u48 from 5 bytes")?;
-                        }
-                    } else {
-                        for (i, value) in data_bytes.chunks(n_bytes).enumerate() {
-                            temp[0..5].copy_from_slice(&value[0..n_bytes]);
-                            data[i] = Cursor::new(temp)
-                                .read_u48::<LittleEndian>()
-                                .context("Could not read le u48 from 5 bytes")?;
+                                .read_uint::<LittleEndian>(n_bytes)
+                                .context("Could not read le u64 from less bytes")?;
                         }
                     }
                 }
@@ -268,9 +231,7 @@ pub fn read_one_channel_array(
                         .read_f64_into::<BigEndian>(&mut buf)
                         .context("Could not read be f64 array")?;
                 } else {
-                    Cursor::new(data_bytes)
-                        .read_f64_into::<LittleEndian>(&mut buf)
-                        .context("Could not read le f64 array")?;
+                    copy_le_values(data_bytes, &mut buf).context("Could not read le f64 array")?;
                 }
                 *a = Float64Builder::new_from_buffer(buf.into(), None);
             }
@@ -343,11 +304,7 @@ pub fn read_one_channel_array(
                 } else if cn.block.cn_data_type == 7 {
                     // 7: String UTF8
                     for value in data_bytes.chunks(n_bytes) {
-                        data.append_value(
-                            str::from_utf8(value)
-                                .context("Found invalid UTF-8")?
-                                .trim_end_matches('\0'),
-                        );
+                        data.append_value(string_policy.decode_utf8(value)?.trim_end_matches('\0'));
                     }
                 } else if cn.block.cn_data_type == 8 || cn.block.cn_data_type == 9 {
                     // 8 | 9 :String UTF16 to be converted into UTF8
@@ -531,27 +488,28 @@ pub fn read_one_channel_array(
             }
             ChannelData::ArrayDInt64(a) => {
                 let mut buf = vec![0; cycle_count * list_size];
-                if cn.endian {
-                    if n_bytes == 8 {
+                if n_bytes == 8 {
+                    if cn.endian {
                         Cursor::new(data_bytes)
                             .read_i64_into::<BigEndian>(&mut buf)
                             .context("Could not read be i64 array")?;
-                    } else if n_bytes == 6 {
-                        for (i, mut value) in data_bytes.chunks(n_bytes).enumerate() {
-                            buf[i] = value
-                                .read_i48::<BigEndian>()
-                                .context("Could not read be i48")?;
-                        }
+                    } else {
+                        Cursor::new(data_bytes)
+                            .read_i64_into::<LittleEndian>(&mut buf)
+                            .context("Could not read le i64 array")?;
                     }
-                } else if n_bytes == 8 {
-                    Cursor::new(data_bytes)
-                        .read_i64_into::<LittleEndian>(&mut buf)
-                        .context("Could not read le i64 array")?;
-                } else if n_bytes == 6 {
+                } else if cn.endian {
+                    // 5 to 7 bytes, sign extended
+                    for (i, mut value) in data_bytes.chunks(n_bytes).enumerate() {
+                        buf[i] = value
+                            .read_int::<BigEndian>(n_bytes)
+                            .context("Could not read be i64 array from less bytes")?;
+                    }
+                } else {
                     for (i, mut value) in data_bytes.chunks(n_bytes).enumerate() {
                         buf[i] = value
-                            .read_i48::<LittleEndian>()
-                            .context("Could not read le i48")?;
+                            .read_int::<LittleEndian>(n_bytes)
+                            .context("Could not read le i64 array from less bytes")?;
                     }
                 }
                 *a = TensorArrow::new_from_buffer(
@@ -572,49 +530,18 @@ pub fn read_one_channel_array(
                             .read_u64_into::<LittleEndian>(&mut buf)
                             .context("Could not read le u64 array")?;
                     }
-                } else if n_bytes == 7 {
-                    let mut temp = [0u8; std::mem::size_of::<u64>()];
-                    if cn.endian {
-                        for (i, value) in data_bytes.chunks(n_bytes).enumerate() {
-                            temp[0..7].copy_from_slice(&value[0..7]);
-                            buf[i] = u64::from_be_bytes(temp);
-                        }
-                    } else {
-                        for (i, value) in data_bytes.chunks(n_bytes).enumerate() {
-                            temp[0..7].copy_from_slice(&value[0..7]);
-                            buf[i] = u64::from_le_bytes(temp);
-                        }
-                    }
-                } else if n_bytes == 6 {
-                    if cn.endian {
-                        for (i, mut value) in data_bytes.chunks(n_bytes).enumerate() {
-                            buf[i] = value
-                                .read_u48::<BigEndian>()
-                                .context("Could not read be u48")?;
-                        }
-                    } else {
-                        for (i, mut value) in data_bytes.chunks(n_bytes).enumerate() {
-                            buf[i] = value
-                                .read_u48::<LittleEndian>()
-                                .context("Could not read le u48")?;
-                        }
+                } else if cn.endian {
+                    // 5 to 7 bytes
+                    for (i, mut value) in data_bytes.chunks(n_bytes).enumerate() {
+                        buf[i] = value
+                            .read_uint::<BigEndian>(n_bytes)
+                            .context("Could not read be u64 array from less bytes")?;
                     }
-                } else if n_bytes == 5 {
-                    let mut temp = [0u8; 6];
-                    if cn.endian {
-                        for (i, value) in data_bytes.chunks(n_bytes).enumerate() {
-                            temp[0..5].copy_from_slice(&value[0..n_bytes]);
-                            buf[i] = Cursor::new(temp)
-                                .read_u48::<BigEndian>()
-                                .context("Could not read be u48 from 5 bytes")?;
-                        }
-                    } else {
-                        for (i, value) in data_bytes.chunks(n_bytes).enumerate() {
-                            temp[0..5].copy_from_slice(&value[0..n_bytes]);
-                            buf[i] = Cursor::new(temp)
-                                .read_u48::<LittleEndian>()
-                                .context("Could not read le u48 from 5 bytes")?;
-                        }
+                } else {
+                    for (i, mut value) in data_bytes.chunks(n_bytes).enumerate() {
+                        buf[i] = value
+                            .read_uint::<LittleEndian>(n_bytes)
+                            .context("Could not read le u64 array from less bytes")?;
                     }
                 }
                 *a = TensorArrow::new_from_buffer(
@@ -640,6 +567,7 @@ pub fn read_one_channel_array(
                     cn.shape.1.clone(),
                 );
             }
+            ChannelData::Dictionary(_) | ChannelData::Boolean(_) => (),
         }
     }
     // Other channel types : virtual channels cn_type 3 & 6 are handled at initialisation
@@ -655,8 +583,18 @@ pub fn read_channels_from_bytes(
     previous_index: usize,
     channel_names_to_read_in_dg: &HashSet<String>,
     record_with_invalid_data: bool,
+    string_policy: StringPolicy,
 ) -> Result<Vec<i32>, Error> {
     let vlsd_channels: Arc<Mutex<Vec<i32>>> = Arc::new(Mutex::new(Vec::new()));
+    let mlsd_sizes = mlsd_size_channels(channels);
+    // few numeric channels of wide records are extracted together, record by record
+    let walked = walk_records(
+        data_chunk,
+        channels,
+        record_length,
+        previous_index,
+        channel_names_to_read_in_dg,
+    )?;
     // iterates for each channel in parallel with rayon crate
     channels
         .par_iter_mut()
@@ -667,14 +605,26 @@ pub fn read_channels_from_bytes(
                 || cn.block.cn_type == 4
                 || cn.block.cn_type == 5
             {
-                // cn_type == 5 : Maximum length data channel, removing no valid bytes done by another size channel pointed by cn_data
+                // cn_type == 5 : Maximum length data channel, number of valid bytes given by another size channel pointed by cn_data
                 // cn_type == 0 : fixed length data channel
                 // cn_type == 2 : master channel
                 // cn_type == 4 : synchronisation channel
                 let mut value: &[u8]; // value of channel at record
                 let pos_byte_beg = cn.pos_byte_beg as usize;
                 let n_bytes = cn.n_bytes as usize;
+                let size_channel = mlsd_sizes.get(rec_pos).copied();
+                let value_length = move |record: &[u8]| -> usize {
+                    size_channel.map_or(n_bytes, |size| size.read(record).min(n_bytes))
+                };
+                // strided numeric channels are gathered by unrolled blocks of records
+                #[cfg(feature = "simd")]
+                let gathered = size_channel.is_none()
+                    && !walked.contains(rec_pos)
+                    && gather_channel(data_chunk, record_length, cn, previous_index)?;
+                #[cfg(not(feature = "simd"))]
+                let gathered = false;
                 match &mut cn.data {
+                    _ if gathered || walked.contains(rec_pos) => {}
                     ChannelData::Int8(a) => {
                         let data = a.values_slice_mut();
                         for (i, record) in data_chunk.chunks(record_length).enumerate() {
@@ -701,6 +651,14 @@ pub fn read_channels_from_bytes(
                                     value.try_into().context("Could not read be i16")?,
                                 );
                             }
+                        } else if record_length == std::mem::size_of::<i16>() {
+                            // channel alone in record, values are contiguous
+                            let n_records = data_chunk.len() / record_length;
+                            copy_le_values(
+                                data_chunk,
+                                &mut data[previous_index..previous_index + n_records],
+                            )
+                            .context("Could not read le i16 array")?;
                         } else {
                             for (i, record) in data_chunk.chunks(record_length).enumerate() {
                                 value = &record
@@ -721,6 +679,14 @@ pub fn read_channels_from_bytes(
                                     value.try_into().context("Could not read be u16")?,
                                 );
                             }
+                        } else if record_length == std::mem::size_of::<u16>() {
+                            // channel alone in record, values are contiguous
+                            let n_records = data_chunk.len() / record_length;
+                            copy_le_values(
+                                data_chunk,
+                                &mut data[previous_index..previous_index + n_records],
+                            )
+                            .context("Could not read le u16 array")?;
                         } else {
                             for (i, record) in data_chunk.chunks(record_length).enumerate() {
                                 value = &record
@@ -757,6 +723,14 @@ pub fn read_channels_from_bytes(
                                     value.try_into().context("Could not read be i32")?,
                                 );
                             }
+                        } else if record_length == std::mem::size_of::<i32>() {
+                            // channel alone in record, values are contiguous
+                            let n_records = data_chunk.len() / record_length;
+                            copy_le_values(
+                                data_chunk,
+                                &mut data[previous_index..previous_index + n_records],
+                            )
+                            .context("Could not read le i32 array")?;
                         } else {
                             for (i, record) in data_chunk.chunks(record_length).enumerate() {
                                 value = &record
@@ -793,6 +767,14 @@ pub fn read_channels_from_bytes(
                                     value.try_into().context("Could not read be u32")?,
                                 );
                             }
+                        } else if record_length == std::mem::size_of::<u32>() {
+                            // channel alone in record, values are contiguous
+                            let n_records = data_chunk.len() / record_length;
+                            copy_le_values(
+                                data_chunk,
+                                &mut data[previous_index..previous_index + n_records],
+                            )
+                            .context("Could not read le u32 array")?;
                         } else {
                             for (i, record) in data_chunk.chunks(record_length).enumerate() {
                                 value = &record
@@ -833,6 +815,14 @@ pub fn read_channels_from_bytes(
                                 )
                                 .to_f32();
                             }
+                        } else if record_length == std::mem::size_of::<f32>() {
+                            // channel alone in record, values are contiguous
+                            let n_records = data_chunk.len() / record_length;
+                            copy_le_values(
+                                data_chunk,
+                                &mut data[previous_index..previous_index + n_records],
+                            )
+                            .context("Could not read le f32 array")?;
                         } else {
                             for (i, record) in data_chunk.chunks(record_length).enumerate() {
                                 value = &record
@@ -845,21 +835,21 @@ pub fn read_channels_from_bytes(
                     }
                     ChannelData::Int64(a) => {
                         let data = a.values_slice_mut();
-                        if cn.endian {
-                            if n_bytes == 8 {
-                                for (i, record) in data_chunk.chunks(record_length).enumerate() {
-                                    value = &record[pos_byte_beg..pos_byte_beg + n_bytes];
-                                    data[i + previous_index] = i64::from_be_bytes(
-                                        value.try_into().context("Could not read be i64")?,
-                                    );
-                                }
-                            } else if n_bytes == 6 {
-                                for (i, record) in data_chunk.chunks(record_length).enumerate() {
-                                    value = &record[pos_byte_beg..pos_byte_beg + n_bytes];
-                                    data[i + previous_index] = value
-                                        .read_i48::<BigEndian>()
-                                        .context("Could not read be i48")?;
-                                }
+                        if n_bytes == 8 && !cn.endian && record_length == std::mem::size_of::<i64>()
+                        {
+                            // channel alone in record, values are contiguous
+                            let n_records = data_chunk.len() / record_length;
+                            copy_le_values(
+                                data_chunk,
+                                &mut data[previous_index..previous_index + n_records],
+                            )
+                            .context("Could not read le i64 array")?;
+                        } else if n_bytes == 8 && cn.endian {
+                            for (i, record) in data_chunk.chunks(record_length).enumerate() {
+                                value = &record[pos_byte_beg..pos_byte_beg + n_bytes];
+                                data[i + previous_index] = i64::from_be_bytes(
+                                    value.try_into().context("Could not read be i64")?,
+                                );
                             }
                         } else if n_bytes == 8 {
                             for (i, record) in data_chunk.chunks(record_length).enumerate() {
@@ -868,49 +858,40 @@ pub fn read_channels_from_bytes(
                                     value.try_into().context("Could not read le i64")?,
                                 );
                             }
-                        } else if n_bytes == 6 {
+                        } else if cn.endian {
+                            // 5 to 7 bytes, sign extended
+                            for (i, record) in data_chunk.chunks(record_length).enumerate() {
+                                value = &record[pos_byte_beg..pos_byte_beg + n_bytes];
+                                data[i + previous_index] = value
+                                    .read_int::<BigEndian>(n_bytes)
+                                    .context("Could not read be i64 from less bytes")?;
+                            }
+                        } else {
                             for (i, record) in data_chunk.chunks(record_length).enumerate() {
                                 value = &record[pos_byte_beg..pos_byte_beg + n_bytes];
                                 data[i + previous_index] = value
-                                    .read_i48::<LittleEndian>()
-                                    .context("Could not read le i48")?;
+                                    .read_int::<LittleEndian>(n_bytes)
+                                    .context("Could not read le i64 from less bytes")?;
                             }
                         }
                     }
                     ChannelData::UInt64(a) => {
                         let data = a.values_slice_mut();
-                        if cn.endian {
-                            if n_bytes == 8 {
-                                for (i, record) in data_chunk.chunks(record_length).enumerate() {
-                                    value = &record[pos_byte_beg..pos_byte_beg + n_bytes];
-                                    data[i + previous_index] = u64::from_be_bytes(
-                                        value.try_into().context("Could not read be u64")?,
-                                    );
-                                }
-                            } else if n_bytes == 7 {
-                                let mut buf = [0u8; std::mem::size_of::<u64>()];
-                                for (i, record) in data_chunk.chunks(record_length).enumerate() {
-                                    buf[0..7]
-                                        .copy_from_slice(&record[pos_byte_beg..pos_byte_beg + 7]);
-                                    data[i + previous_index] = u64::from_be_bytes(buf);
-                                }
-                            } else if n_bytes == 6 {
-                                for (i, record) in data_chunk.chunks(record_length).enumerate() {
-                                    value = &record[pos_byte_beg..pos_byte_beg + n_bytes];
-                                    data[i + previous_index] = value
-                                        .read_u48::<BigEndian>()
-                                        .context("Could not read be u48")?;
-                                }
-                            } else {
-                                let mut buf = [0u8; 6];
-                                for (i, record) in data_chunk.chunks(record_length).enumerate() {
-                                    buf[0..5].copy_from_slice(
-                                        &record[pos_byte_beg..pos_byte_beg + n_bytes],
-                                    );
-                                    data[i + previous_index] = Cursor::new(buf)
-                                        .read_u48::<BigEndian>()
-                                        .context("Could not read be u48 from 5 bytes")?;
-                                }
+                        if n_bytes == 8 && !cn.endian && record_length == std::mem::size_of::<u64>()
+                        {
+                            // channel alone in record, values are contiguous
+                            let n_records = data_chunk.len() / record_length;
+                            copy_le_values(
+                                data_chunk,
+                                &mut data[previous_index..previous_index + n_records],
+                            )
+                            .context("Could not read le u64 array")?;
+                        } else if n_bytes == 8 && cn.endian {
+                            for (i, record) in data_chunk.chunks(record_length).enumerate() {
+                                value = &record[pos_byte_beg..pos_byte_beg + n_bytes];
+                                data[i + previous_index] = u64::from_be_bytes(
+                                    value.try_into().context("Could not read be u64")?,
+                                );
                             }
                         } else if n_bytes == 8 {
                             for (i, record) in data_chunk.chunks(record_length).enumerate() {
@@ -919,28 +900,20 @@ pub fn read_channels_from_bytes(
                                     value.try_into().context("Could not read le u64")?,
                                 );
                             }
-                        } else if n_bytes == 7 {
-                            // little endian
-                            let mut buf = [0u8; std::mem::size_of::<u64>()];
-                            for (i, record) in data_chunk.chunks(record_length).enumerate() {
-                                buf[0..7].copy_from_slice(&record[pos_byte_beg..pos_byte_beg + 7]);
-                                data[i + previous_index] = u64::from_le_bytes(buf);
-                            }
-                        } else if n_bytes == 6 {
+                        } else if cn.endian {
+                            // 5 to 7 bytes
                             for (i, record) in data_chunk.chunks(record_length).enumerate() {
                                 value = &record[pos_byte_beg..pos_byte_beg + n_bytes];
                                 data[i + previous_index] = value
-                                    .read_u48::<LittleEndian>()
-                                    .context("Could not read le u48")?;
+                                    .read_uint::<BigEndian>(n_bytes)
+                                    .context("Could not read be u64 from less bytes")?;
                             }
                         } else {
-                            // n_bytes = 5
-                            let mut buf = [0u8; 6];
                             for (i, record) in data_chunk.chunks(record_length).enumerate() {
-                                buf[0..5].copy_from_slice(&record[pos_byte_beg..pos_byte_beg + 5]);
-                                data[i + previous_index] = Cursor::new(buf)
-                                    .read_u48::<LittleEndian>()
-                                    .context("Could not read le u48 from 5 bytes")?;
+                                value = &record[pos_byte_beg..pos_byte_beg + n_bytes];
+                                data[i + previous_index] = value
+                                    .read_uint::<LittleEndian>(n_bytes)
+                                    .context("Could not read le u64 from less bytes")?;
                             }
                         }
                     }
@@ -954,6 +927,14 @@ pub fn read_channels_from_bytes(
                                     value.try_into().context("Could not read be f64")?,
                                 );
                             }
+                        } else if record_length == std::mem::size_of::<f64>() {
+                            // channel alone in record, values are contiguous
+                            let n_records = data_chunk.len() / record_length;
+                            copy_le_values(
+                                data_chunk,
+                                &mut data[previous_index..previous_index + n_records],
+                            )
+                            .context("Could not read le f64 array")?;
                         } else {
                             for (i, record) in data_chunk.chunks(record_length).enumerate() {
                                 value = &record
@@ -976,13 +957,13 @@ pub fn read_channels_from_bytes(
                                         [pos_byte_beg..pos_byte_beg + std::mem::size_of::<f16>()];
                                     im_val = &record[pos_byte_beg + std::mem::size_of::<f16>()
                                         ..pos_byte_beg + 2 * std::mem::size_of::<f16>()];
-                                    data[i * 2 + previous_index] = f16::from_be_bytes(
+                                    data[(i + previous_index) * 2] = f16::from_be_bytes(
                                         re_val
                                             .try_into()
                                             .context("Could not read be real f16 complex")?,
                                     )
                                     .to_f32();
-                                    data[i * 2 + 1 + previous_index] = f16::from_be_bytes(
+                                    data[(i + previous_index) * 2 + 1] = f16::from_be_bytes(
                                         im_val
                                             .try_into()
                                             .context("Could not read be img f16 complex")?,
@@ -995,13 +976,13 @@ pub fn read_channels_from_bytes(
                                         [pos_byte_beg..pos_byte_beg + std::mem::size_of::<f16>()];
                                     im_val = &record[pos_byte_beg + std::mem::size_of::<f16>()
                                         ..pos_byte_beg + 2 * std::mem::size_of::<f16>()];
-                                    data[i * 2 + previous_index] = f16::from_le_bytes(
+                                    data[(i + previous_index) * 2] = f16::from_le_bytes(
                                         re_val
                                             .try_into()
                                             .context("Could not read le real f16 complex")?,
                                     )
                                     .to_f32();
-                                    data[i * 2 + 1 + previous_index] = f16::from_le_bytes(
+                                    data[(i + previous_index) * 2 + 1] = f16::from_le_bytes(
                                         im_val
                                             .try_into()
                                             .context("Could not read le img f16 complex")?,
@@ -1019,12 +1000,12 @@ pub fn read_channels_from_bytes(
                                         [pos_byte_beg..pos_byte_beg + std::mem::size_of::<f32>()];
                                     im_val = &record[pos_byte_beg + std::mem::size_of::<f32>()
                                         ..pos_byte_beg + 2 * std::mem::size_of::<f32>()];
-                                    data[i * 2 + previous_index] = f32::from_be_bytes(
+                                    data[(i + previous_index) * 2] = f32::from_be_bytes(
                                         re_val
                                             .try_into()
                                             .context("Could not read be real f32 complex")?,
                                     );
-                                    data[i * 2 + 1 + previous_index] = f32::from_be_bytes(
+                                    data[(i + previous_index) * 2 + 1] = f32::from_be_bytes(
                                         im_val
                                             .try_into()
                                             .context("Could not read be img f32 complex")?,
@@ -1036,12 +1017,12 @@ pub fn read_channels_from_bytes(
                                         [pos_byte_beg..pos_byte_beg + std::mem::size_of::<f32>()];
                                     im_val = &record[pos_byte_beg + std::mem::size_of::<f32>()
                                         ..pos_byte_beg + 2 * std::mem::size_of::<f32>()];
-                                    data[i * 2 + previous_index] = f32::from_le_bytes(
+                                    data[(i + previous_index) * 2] = f32::from_le_bytes(
                                         re_val
                                             .try_into()
                                             .context("Could not read le real f32 complex")?,
                                     );
-                                    data[i * 2 + 1 + previous_index] = f32::from_le_bytes(
+                                    data[(i + previous_index) * 2 + 1] = f32::from_le_bytes(
                                         im_val
                                             .try_into()
                                             .context("Could not read le img f32 complex")?,
@@ -1061,12 +1042,12 @@ pub fn read_channels_from_bytes(
                                     [pos_byte_beg..pos_byte_beg + std::mem::size_of::<f64>()];
                                 im_val = &record[pos_byte_beg + std::mem::size_of::<f64>()
                                     ..pos_byte_beg + 2 * std::mem::size_of::<f64>()];
-                                data[i * 2 + previous_index] = f64::from_be_bytes(
+                                data[(i + previous_index) * 2] = f64::from_be_bytes(
                                     re_val
                                         .try_into()
                                         .context("Could not read be real f64 complex")?,
                                 );
-                                data[i * 2 + 1 + previous_index] = f64::from_be_bytes(
+                                data[(i + previous_index) * 2 + 1] = f64::from_be_bytes(
                                     im_val
                                         .try_into()
                                         .context("Could not read be img f64 complex")?,
@@ -1078,12 +1059,12 @@ pub fn read_channels_from_bytes(
                                     [pos_byte_beg..pos_byte_beg + std::mem::size_of::<f64>()];
                                 im_val = &record[pos_byte_beg + std::mem::size_of::<f64>()
                                     ..pos_byte_beg + 2 * std::mem::size_of::<f64>()];
-                                data[i * 2 + previous_index] = f64::from_le_bytes(
+                                data[(i + previous_index) * 2] = f64::from_le_bytes(
                                     re_val
                                         .try_into()
                                         .context("Could not read le real f64 complex")?,
                                 );
-                                data[i * 2 + 1 + previous_index] = f64::from_le_bytes(
+                                data[(i + previous_index) * 2 + 1] = f64::from_le_bytes(
                                     im_val
                                         .try_into()
                                         .context("Could not read le img f64 complex")?,
@@ -1096,7 +1077,7 @@ pub fn read_channels_from_bytes(
                             // SBC ISO-8859-1 to be converted into UTF8
                             let mut decoder = WINDOWS_1252.new_decoder();
                             for record in data_chunk.chunks(record_length) {
-                                value = &record[pos_byte_beg..pos_byte_beg + n_bytes];
+                                value = &record[pos_byte_beg..pos_byte_beg + value_length(record)];
                                 let mut dst = String::with_capacity(value.len());
                                 let (_result, _size, _replacement) =
                                     decoder.decode_to_string(value, &mut dst, false);
@@ -1105,18 +1086,17 @@ pub fn read_channels_from_bytes(
                         } else if cn.block.cn_data_type == 7 {
                             // 7: String UTF8
                             for record in data_chunk.chunks(record_length) {
-                                value = &record[pos_byte_beg..pos_byte_beg + n_bytes];
-                                let dst = str::from_utf8(value)
-                                    .context("Found invalid UTF-8")?
-                                    .trim_end_matches('\0');
-                                array.append_value(dst);
+                                value = &record[pos_byte_beg..pos_byte_beg + value_length(record)];
+                                let dst = string_policy.decode_utf8(value)?;
+                                array.append_value(dst.trim_end_matches('\0'));
                             }
                         } else if cn.block.cn_data_type == 8 || cn.block.cn_data_type == 9 {
                             // 8 | 9 :String UTF16 to be converted into UTF8
                             if cn.endian {
                                 let mut decoder = UTF_16BE.new_decoder();
                                 for record in data_chunk.chunks(record_length) {
-                                    value = &record[pos_byte_beg..pos_byte_beg + n_bytes];
+                                    value =
+                                        &record[pos_byte_beg..pos_byte_beg + value_length(record)];
                                     let mut dst = String::with_capacity(value.len());
                                     let (_result, _size, _replacement) =
                                         decoder.decode_to_string(value, &mut dst, false);
@@ -1125,7 +1105,8 @@ pub fn read_channels_from_bytes(
                             } else {
                                 let mut decoder = UTF_16LE.new_decoder();
                                 for record in data_chunk.chunks(record_length) {
-                                    value = &record[pos_byte_beg..pos_byte_beg + n_bytes];
+                                    value =
+                                        &record[pos_byte_beg..pos_byte_beg + value_length(record)];
                                     let mut dst = String::with_capacity(value.len());
                                     let (_result, _size, _replacement) =
                                         decoder.decode_to_string(value, &mut dst, false);
@@ -1136,7 +1117,9 @@ pub fn read_channels_from_bytes(
                     }
                     ChannelData::VariableSizeByteArray(array) => {
                         for record in data_chunk.chunks(record_length) {
-                            array.append_value(&record[pos_byte_beg..pos_byte_beg + n_bytes]);
+                            array.append_value(
+                                &record[pos_byte_beg..pos_byte_beg + value_length(record)],
+                            );
                         }
                     }
                     ChannelData::FixedSizeByteArray(a) => {
@@ -1399,156 +1382,39 @@ pub fn read_channels_from_bytes(
                     }
                     ChannelData::ArrayDInt64(a) => {
                         let data = a.values_slice_mut();
-                        if cn.endian {
-                            if n_bytes == 8 {
-                                for (i, record) in data_chunk.chunks(record_length).enumerate() {
-                                    for j in 0..cn.list_size {
-                                        value = &record[pos_byte_beg + j * n_bytes
-                                            ..pos_byte_beg + (j + 1) * n_bytes];
-                                        data[(i + previous_index) * cn.list_size + j] =
-                                            i64::from_be_bytes(
-                                                value
-                                                    .try_into()
-                                                    .context("Could not read be i64 array")?,
-                                            );
-                                    }
-                                }
-                            } else if n_bytes == 6 {
-                                for (i, record) in data_chunk.chunks(record_length).enumerate() {
-                                    for j in 0..cn.list_size {
-                                        value = &record[pos_byte_beg + j * n_bytes
-                                            ..pos_byte_beg + (j + 1) * n_bytes];
-                                        data[(i + previous_index) * cn.list_size + j] = value
-                                            .read_i48::<BigEndian>()
-                                            .context("Could not read be i48 array")?;
-                                    }
-                                }
-                            }
-                        } else if n_bytes == 8 {
-                            for (i, record) in data_chunk.chunks(record_length).enumerate() {
-                                for j in 0..cn.list_size {
-                                    value = &record[pos_byte_beg + j * n_bytes
-                                        ..pos_byte_beg + (j + 1) * n_bytes];
-                                    data[(i + previous_index) * cn.list_size + j] =
-                                        i64::from_le_bytes(
-                                            value
-                                                .try_into()
-                                                .context("Could not read le i64 array")?,
-                                        );
-                                }
-                            }
-                        } else if n_bytes == 6 {
-                            for (i, record) in data_chunk.chunks(record_length).enumerate() {
-                                for j in 0..cn.list_size {
-                                    value = &record[pos_byte_beg + j * n_bytes
-                                        ..pos_byte_beg + (j + 1) * n_bytes];
-                                    data[(i + previous_index) * cn.list_size + j] = value
-                                        .read_i48::<LittleEndian>()
-                                        .context("Could not read le i48 array")?;
-                                }
+                        for (i, record) in data_chunk.chunks(record_length).enumerate() {
+                            for j in 0..cn.list_size {
+                                let mut value = &record
+                                    [pos_byte_beg + j * n_bytes..pos_byte_beg + (j + 1) * n_bytes];
+                                // 5 to 8 bytes, sign extended
+                                data[(i + previous_index) * cn.list_size + j] = if cn.endian {
+                                    value
+                                        .read_int::<BigEndian>(n_bytes)
+                                        .context("Could not read be i64 array")?
+                                } else {
+                                    value
+                                        .read_int::<LittleEndian>(n_bytes)
+                                        .context("Could not read le i64 array")?
+                                };
                             }
                         }
                     }
                     ChannelData::ArrayDUInt64(a) => {
                         let data = a.values_slice_mut();
-                        if cn.endian {
-                            if n_bytes == 8 {
-                                for (i, record) in data_chunk.chunks(record_length).enumerate() {
-                                    for j in 0..cn.list_size {
-                                        value = &record[pos_byte_beg + j * n_bytes
-                                            ..pos_byte_beg + (j + 1) * n_bytes];
-                                        data[(i + previous_index) * cn.list_size + j] =
-                                            u64::from_le_bytes(
-                                                value
-                                                    .try_into()
-                                                    .context("Could not read be u64 array")?,
-                                            );
-                                    }
-                                }
-                            } else if n_bytes == 7 {
-                                let mut buf = [0u8; std::mem::size_of::<u64>()];
-                                for (i, record) in data_chunk.chunks(record_length).enumerate() {
-                                    for j in 0..cn.list_size {
-                                        buf[0..7].copy_from_slice(
-                                            &record[pos_byte_beg + j * n_bytes
-                                                ..pos_byte_beg + (j + 1) * n_bytes],
-                                        );
-                                        data[(i + previous_index) * cn.list_size + j] =
-                                            u64::from_le_bytes(buf);
-                                    }
-                                }
-                            } else if n_bytes == 6 {
-                                for (i, record) in data_chunk.chunks(record_length).enumerate() {
-                                    for j in 0..cn.list_size {
-                                        value = &record[pos_byte_beg + j * n_bytes
-                                            ..pos_byte_beg + (j + 1) * n_bytes];
-                                        data[(i + previous_index) * cn.list_size + j] = value
-                                            .read_u48::<BigEndian>()
-                                            .context("Could not read be u48 array")?;
-                                    }
-                                }
-                            } else if n_bytes == 5 {
-                                let mut buf = [0u8; 6];
-                                for (i, record) in data_chunk.chunks(record_length).enumerate() {
-                                    for j in 0..cn.list_size {
-                                        buf[0..5].copy_from_slice(
-                                            &record[pos_byte_beg + j * n_bytes
-                                                ..pos_byte_beg + (j + 1) * n_bytes],
-                                        );
-                                        data[(i + previous_index) * cn.list_size + j] =
-                                            Cursor::new(buf).read_u48::<BigEndian>().context(
-                                                "Could not read be u48 from 5 bytes in array",
-                                            )?;
-                                    }
-                                }
-                            }
-                        } else if n_bytes == 8 {
-                            for (i, record) in data_chunk.chunks(record_length).enumerate() {
-                                for j in 0..cn.list_size {
-                                    value = &record[pos_byte_beg + j * n_bytes
-                                        ..pos_byte_beg + (j + 1) * n_bytes];
-                                    data[(i + previous_index) * cn.list_size + j] =
-                                        u64::from_le_bytes(
-                                            value.try_into().context("Could not read le u64")?,
-                                        );
-                                }
-                            }
-                        } else if n_bytes == 7 {
-                            let mut buf = [0u8; std::mem::size_of::<u64>()];
-                            for (i, record) in data_chunk.chunks(record_length).enumerate() {
-                                for j in 0..cn.list_size {
-                                    buf[0..7].copy_from_slice(
-                                        &record[pos_byte_beg + j * n_bytes
-                                            ..pos_byte_beg + (j + 1) * n_bytes],
-                                    );
-                                    data[(i + previous_index) * cn.list_size + j] =
-                                        u64::from_le_bytes(buf);
-                                }
-                            }
-                        } else if n_bytes == 6 {
-                            for (i, record) in data_chunk.chunks(record_length).enumerate() {
-                                for j in 0..cn.list_size {
-                                    value = &record[pos_byte_beg + j * n_bytes
-                                        ..pos_byte_beg + (j + 1) * n_bytes];
-                                    data[(i + previous_index) * cn.list_size + j] = value
-                                        .read_u48::<LittleEndian>()
-                                        .context("Could not read le u48 array")?;
-                                }
-                            }
-                        } else {
-                            // n_bytes = 5
-                            let mut buf = [0u8; 6];
-                            for (i, record) in data_chunk.chunks(record_length).enumerate() {
-                                for j in 0..cn.list_size {
-                                    buf[0..5].copy_from_slice(
-                                        &record[pos_byte_beg + j * n_bytes
-                                            ..pos_byte_beg + (j + 1) * n_bytes],
-                                    );
-                                    data[(i + previous_index) * cn.list_size + j] =
-                                        Cursor::new(buf).read_u48::<LittleEndian>().context(
-                                            "Could not read le u48 from 5 bytes in array",
-                                        )?;
-                                }
+                        for (i, record) in data_chunk.chunks(record_length).enumerate() {
+                            for j in 0..cn.list_size {
+                                let mut value = &record
+                                    [pos_byte_beg + j * n_bytes..pos_byte_beg + (j + 1) * n_bytes];
+                                // 5 to 8 bytes
+                                data[(i + previous_index) * cn.list_size + j] = if cn.endian {
+                                    value
+                                        .read_uint::<BigEndian>(n_bytes)
+                                        .context("Could not read be u64 array")?
+                                } else {
+                                    value
+                                        .read_uint::<LittleEndian>(n_bytes)
+                                        .context("Could not read le u64 array")?
+                                };
                             }
                         }
                     }
@@ -1578,6 +1444,7 @@ pub fn read_channels_from_bytes(
                             }
                         }
                     }
+                    ChannelData::Dictionary(_) | ChannelData::Boolean(_) => (),
                 }
             } else if cn.block.cn_type == 1 {
                 // SD Block attached as data block is sorted
@@ -1585,7 +1452,7 @@ pub fn read_channels_from_bytes(
                     let c_vlsd_channel = Arc::clone(&vlsd_channels);
                     let mut vlsd_channel = c_vlsd_channel
                         .lock()
-                        .expect("Could not get lock from vlsd channel arc vec");
+                        .map_err(|_| anyhow!("Could not get lock from vlsd channel arc vec"))?;
                     vlsd_channel.push(*rec_pos);
                 }
             }
@@ -1614,6 +1481,476 @@ pub fn read_channels_from_bytes(
 
     let lock = vlsd_channels
         .lock()
-        .expect("Could not get lock from vlsd channel arc vec");
+        .map_err(|_| anyhow!("Could not get lock from vlsd channel arc vec"))?;
     Ok(lock.clone())
 }
+
+/// channels are extracted by a single walk over the records when the bytes of their values
+/// are at most 1/RECORD_WALK_COVERAGE of the record length
+const RECORD_WALK_COVERAGE: usize = 4;
+/// minimum number of records of the blocks walked in parallel
+const RECORD_WALK_BLOCK: usize = 1024;
+
+/// values of a channel extracted by record walk, from its position in record
+struct Lane<'a, T> {
+    pos_byte_beg: usize,
+    big_endian: bool,
+    values: &'a mut [T],
+}
+
+impl<'a, T: PlainNumeric> Lane<'a, T> {
+    /// lane of the range of values if the value fits in record
+    fn new(
+        values: &'a mut [T],
+        range: Range<usize>,
+        pos_byte_beg: usize,
+        big_endian: bool,
+        record_length: usize,
+    ) -> Option<Self> {
+        if pos_byte_beg + std::mem::size_of::<T>() > record_length {
+            return None;
+        }
+        Some(Lane {
+            pos_byte_beg,
+            big_endian,
+            values: values.get_mut(range)?,
+        })
+    }
+    /// stores the value of record at index
+    #[inline(always)]
+    fn read(&mut self, index: usize, record: &[u8]) {
+        let bytes = &record[self.pos_byte_beg..self.pos_byte_beg + std::mem::size_of::<T>()];
+        self.values[index] = if self.big_endian {
+            T::from_be_slice(bytes)
+        } else {
+            T::from_le_slice(bytes)
+        };
+    }
+    /// splits the values into lanes of block_records values
+    fn split(self, block_records: usize) -> impl Iterator<Item = Lane<'a, T>> {
+        let (pos_byte_beg, big_endian) = (self.pos_byte_beg, self.big_endian);
+        self.values
+            .chunks_mut(block_records)
+            .map(move |values| Lane {
+                pos_byte_beg,
+                big_endian,
+                values,
+            })
+    }
+}
+
+macro_rules! record_walk {
+    ($($field:ident: $t:ty),*) => {
+        /// channels extracted together by a single walk over the records, grouped by
+        /// value type for monomorphic inner loops
+        #[derive(Default)]
+        struct RecordWalk<'a> {
+            $($field: Vec<Lane<'a, $t>>,)*
+        }
+
+        impl<'a> RecordWalk<'a> {
+            /// number of channels and of bytes of their values in record
+            fn coverage(&self) -> (usize, usize) {
+                let (mut n_channels, mut n_bytes) = (0, 0);
+                $(
+                    n_channels += self.$field.len();
+                    n_bytes += self.$field.len() * std::mem::size_of::<$t>();
+                )*
+                (n_channels, n_bytes)
+            }
+            /// stores the channels values of the records
+            fn read(&mut self, records: &[u8], record_length: usize) {
+                for (index, record) in records.chunks_exact(record_length).enumerate() {
+                    $(
+                        for lane in self.$field.iter_mut() {
+                            lane.read(index, record);
+                        }
+                    )*
+                }
+            }
+            /// splits into walks of block_records records
+            fn split(self, block_records: usize) -> Vec<RecordWalk<'a>> {
+                let mut blocks: Vec<RecordWalk<'a>> = Vec::new();
+                $(
+                    for lane in self.$field {
+                        for (block, part) in lane.split(block_records).enumerate() {
+                            if blocks.len() <= block {
+                                blocks.push(RecordWalk::default());
+                            }
+                            blocks[block].$field.push(part);
+                        }
+                    }
+                )*
+                blocks
+            }
+        }
+    };
+}
+
+record_walk!(
+    int8: i8,
+    uint8: u8,
+    int16: i16,
+    uint16: u16,
+    int32: i32,
+    uint32: u32,
+    float32: f32,
+    int64: i64,
+    uint64: u64,
+    float64: f64
+);
+
+impl<'a> RecordWalk<'a> {
+    /// adds the range of values of a fixed length numeric channel with values of the byte
+    /// size of its data type, returns false if the channel needs per channel reading
+    fn push(&mut self, cn: &'a mut Cn4, range: Range<usize>, record_length: usize) -> bool {
+        if !matches!(cn.block.cn_type, 0 | 2 | 4) {
+            return false;
+        }
+        let (pos, be) = (cn.pos_byte_beg as usize, cn.endian);
+        let n_bytes = cn.n_bytes as usize;
+        let length = record_length;
+        match &mut cn.data {
+            ChannelData::Int8(a) if n_bytes == 1 => {
+                Lane::new(a.values_slice_mut(), range, pos, be, length)
+                    .map(|lane| self.int8.push(lane))
+            }
+            ChannelData::UInt8(a) if n_bytes == 1 => {
+                Lane::new(a.values_slice_mut(), range, pos, be, length)
+                    .map(|lane| self.uint8.push(lane))
+            }
+            ChannelData::Int16(a) if n_bytes == 2 => {
+                Lane::new(a.values_slice_mut(), range, pos, be, length)
+                    .map(|lane| self.int16.push(lane))
+            }
+            ChannelData::UInt16(a) if n_bytes == 2 => {
+                Lane::new(a.values_slice_mut(), range, pos, be, length)
+                    .map(|lane| self.uint16.push(lane))
+            }
+            ChannelData::Int32(a) if n_bytes == 4 => {
+                Lane::new(a.values_slice_mut(), range, pos, be, length)
+                    .map(|lane| self.int32.push(lane))
+            }
+            ChannelData::UInt32(a) if n_bytes == 4 => {
+                Lane::new(a.values_slice_mut(), range, pos, be, length)
+                    .map(|lane| self.uint32.push(lane))
+            }
+            ChannelData::Float32(a) if n_bytes == 4 => {
+                Lane::new(a.values_slice_mut(), range, pos, be, length)
+                    .map(|lane| self.float32.push(lane))
+            }
+            ChannelData::Int64(a) if n_bytes == 8 => {
+                Lane::new(a.values_slice_mut(), range, pos, be, length)
+                    .map(|lane| self.int64.push(lane))
+            }
+            ChannelData::UInt64(a) if n_bytes == 8 => {
+                Lane::new(a.values_slice_mut(), range, pos, be, length)
+                    .map(|lane| self.uint64.push(lane))
+            }
+            ChannelData::Float64(a) if n_bytes == 8 => {
+                Lane::new(a.values_slice_mut(), range, pos, be, length)
+                    .map(|lane| self.float64.push(lane))
+            }
+            _ => None,
+        }
+        .is_some()
+    }
+}
+
+/// extracts in a single pass over the records the requested fixed length numeric channels,
+/// when they are a few channels of a wide record. Returns the record positions of the
+/// channels extracted, empty if per channel reading is more efficient
+fn walk_records(
+    data_chunk: &[u8],
+    channels: &mut CnType,
+    record_length: usize,
+    previous_index: usize,
+    channel_names_to_read_in_dg: &HashSet<String>,
+) -> Result<HashSet<i32>> {
+    let n_records = data_chunk.len() / record_length;
+    let candidates = channels
+        .values()
+        .filter(|cn| channel_names_to_read_in_dg.contains(&cn.unique_name))
+        .count();
+    // channels not requested are skipped before any record is read
+    if candidates < 2 || candidates * RECORD_WALK_COVERAGE > record_length {
+        return Ok(HashSet::new());
+    }
+    let mut walk = RecordWalk::default();
+    let mut walked: HashSet<i32> = HashSet::with_capacity(candidates);
+    for (rec_pos, cn) in channels
+        .iter_mut()
+        .filter(|(_rec_pos, cn)| channel_names_to_read_in_dg.contains(&cn.unique_name))
+    {
+        if walk.push(
+            cn,
+            previous_index..previous_index + n_records,
+            record_length,
+        ) {
+            walked.insert(*rec_pos);
+        }
+    }
+    let (n_channels, n_bytes) = walk.coverage();
+    if n_channels < 2 || n_bytes * RECORD_WALK_COVERAGE > record_length {
+        return Ok(HashSet::new());
+    }
+    // blocks of records walked in parallel
+    let block_records = n_records
+        .div_ceil(crate::parallel::current_num_threads())
+        .max(RECORD_WALK_BLOCK);
+    walk.split(block_records)
+        .into_par_iter()
+        .zip(data_chunk.par_chunks(block_records * record_length))
+        .for_each(|(mut walk, records)| walk.read(records, record_length));
+    Ok(walked)
+}
+
+/// position and encoding in record of the size channel of a maximum length data channel
+#[derive(Debug, Clone, Copy)]
+struct SizeChannel {
+    pos_byte_beg: usize,
+    n_bytes: usize,
+    endian: bool,
+    bit_offset: u8,
+    bit_count: u32,
+}
+
+impl SizeChannel {
+    /// reads the number of valid bytes from record
+    fn read(&self, record: &[u8]) -> usize {
+        let n_bytes = self.n_bytes.min(std::mem::size_of::<u64>());
+        let mut buf = [0u8; 8];
+        let value = match record.get(self.pos_byte_beg..self.pos_byte_beg + n_bytes) {
+            Some(value) => value,
+            None => return 0,
+        };
+        let size = if self.endian {
+            buf[8 - n_bytes..].copy_from_slice(value);
+            u64::from_be_bytes(buf)
+        } else {
+            buf[..n_bytes].copy_from_slice(value);
+            u64::from_le_bytes(buf)
+        };
+        let size = size >> self.bit_offset;
+        let size = if self.bit_count > 0 && self.bit_count < 64 {
+            size & ((1u64 << self.bit_count) - 1)
+        } else {
+            size
+        };
+        size as usize
+    }
+}
+
+/// for each maximum length data channel (cn_type 5), finds its size channel pointed by cn_data
+fn mlsd_size_channels(channels: &CnType) -> HashMap<i32, SizeChannel> {
+    let mut sizes = HashMap::new();
+    for (rec_pos, cn) in channels.iter().filter(|(_, cn)| cn.block.cn_type == 5) {
+        if let Some(size_cn) = channels
+            .values()
+            .find(|size_cn| size_cn.block_position == cn.block.cn_data)
+        {
+            sizes.insert(
+                *rec_pos,
+                SizeChannel {
+                    pos_byte_beg: size_cn.pos_byte_beg as usize,
+                    n_bytes: size_cn.n_bytes as usize,
+                    endian: size_cn.endian,
+                    bit_offset: size_cn.block.cn_bit_offset,
+                    bit_count: size_cn.block.cn_bit_count,
+                },
+            );
+        } else {
+            warn!(
+                "size channel of maximum length data channel {} not found, reading all bytes",
+                cn.unique_name
+            );
+        }
+    }
+    sizes
+}
+
+/// plain numeric types for which any bit pattern is a valid value
+pub(crate) trait PlainNumeric: Copy {
+    /// reads the value from exactly size_of::<Self>() little endian bytes
+    fn from_le_slice(bytes: &[u8]) -> Self;
+    /// reads the value from exactly size_of::<Self>() big endian bytes
+    fn from_be_slice(bytes: &[u8]) -> Self;
+}
+
+macro_rules! impl_plain_numeric {
+    ($($t:ty),*) => {
+        $(impl PlainNumeric for $t {
+            fn from_le_slice(bytes: &[u8]) -> Self {
+                let mut buf = [0u8; std::mem::size_of::<$t>()];
+                buf.copy_from_slice(bytes);
+                <$t>::from_le_bytes(buf)
+            }
+            fn from_be_slice(bytes: &[u8]) -> Self {
+                let mut buf = [0u8; std::mem::size_of::<$t>()];
+                buf.copy_from_slice(bytes);
+                <$t>::from_be_bytes(buf)
+            }
+        })*
+    };
+}
+impl_plain_numeric!(i8, u8, i16, u16, i32, u32, i64, u64, f32, f64);
+
+/// fills data with contiguous little endian values, a single memcpy on little endian hosts
+pub(crate) fn copy_le_values<T: PlainNumeric>(bytes: &[u8], data: &mut [T]) -> Result<()> {
+    let n_bytes = std::mem::size_of_val(data);
+    if bytes.len() < n_bytes {
+        bail!(
+            "{} bytes available for {} values of {} bytes",
+            bytes.len(),
+            data.len(),
+            std::mem::size_of::<T>()
+        );
+    }
+    if cfg!(target_endian = "little") {
+        // SAFETY: PlainNumeric is only implemented for primitive numeric types without padding
+        // and u8 has no alignment requirement, the whole slice is viewed as bytes
+        let (_, target, _) = unsafe { data.align_to_mut::<u8>() };
+        target.copy_from_slice(&bytes[..n_bytes]);
+    } else {
+        for (value, chunk) in data
+            .iter_mut()
+            .zip(bytes.chunks_exact(std::mem::size_of::<T>()))
+        {
+            *value = T::from_le_slice(chunk);
+        }
+    }
+    Ok(())
+}
+
+/// number of records gathered per unrolled iteration
+#[cfg(feature = "simd")]
+const GATHER_LANES: usize = 8;
+
+/// extracts the value at pos_byte_beg of every record into data, unrolled by blocks of
+/// GATHER_LANES records with a single bounds check per block so that the loads can be vectorized
+#[cfg(feature = "simd")]
+pub(crate) fn gather_values<T: PlainNumeric>(
+    data_chunk: &[u8],
+    record_length: usize,
+    pos_byte_beg: usize,
+    big_endian: bool,
+    data: &mut [T],
+) -> Result<()> {
+    let size = std::mem::size_of::<T>();
+    let n_records = data_chunk.len() / record_length;
+    if pos_byte_beg + size > record_length || data.len() < n_records {
+        bail!(
+            "cannot gather {} bytes values at byte {} of {} records of {} bytes",
+            size,
+            pos_byte_beg,
+            n_records,
+            record_length
+        );
+    }
+    let read = if big_endian {
+        T::from_be_slice
+    } else {
+        T::from_le_slice
+    };
+    let block_length = record_length * GATHER_LANES;
+    let mut blocks = data_chunk.chunks_exact(block_length);
+    let mut values = data.chunks_exact_mut(GATHER_LANES);
+    for (block, values) in (&mut blocks).zip(&mut values) {
+        let block = &block[..(GATHER_LANES - 1) * record_length + pos_byte_beg + size];
+        let mut lanes = [read(&block[pos_byte_beg..pos_byte_beg + size]); GATHER_LANES];
+        for (lane, value) in lanes.iter_mut().enumerate().skip(1) {
+            let offset = lane * record_length + pos_byte_beg;
+            *value = read(&block[offset..offset + size]);
+        }
+        values.copy_from_slice(&lanes);
+    }
+    let start = (n_records / GATHER_LANES) * GATHER_LANES;
+    for (record, value) in blocks
+        .remainder()
+        .chunks_exact(record_length)
+        .zip(data[start..n_records].iter_mut())
+    {
+        *value = read(&record[pos_byte_beg..pos_byte_beg + size]);
+    }
+    Ok(())
+}
+
+/// gathers fixed length numeric channels of standard byte size, returns false if the
+/// channel data type or size needs the generic per record reading
+#[cfg(feature = "simd")]
+fn gather_channel(
+    data_chunk: &[u8],
+    record_length: usize,
+    cn: &mut Cn4,
+    previous_index: usize,
+) -> Result<bool> {
+    let pos_byte_beg = cn.pos_byte_beg as usize;
+    let n_bytes = cn.n_bytes as usize;
+    let big_endian = cn.endian;
+    if !big_endian && record_length == n_bytes {
+        // contiguous little endian values are already copied in one memcpy
+        return Ok(false);
+    }
+    let n_records = data_chunk.len() / record_length;
+    let range = previous_index..previous_index + n_records;
+    match &mut cn.data {
+        ChannelData::Int16(a) if n_bytes == 2 => gather_values(
+            data_chunk,
+            record_length,
+            pos_byte_beg,
+            big_endian,
+            &mut a.values_slice_mut()[range],
+        )?,
+        ChannelData::UInt16(a) if n_bytes == 2 => gather_values(
+            data_chunk,
+            record_length,
+            pos_byte_beg,
+            big_endian,
+            &mut a.values_slice_mut()[range],
+        )?,
+        ChannelData::Int32(a) if n_bytes == 4 => gather_values(
+            data_chunk,
+            record_length,
+            pos_byte_beg,
+            big_endian,
+            &mut a.values_slice_mut()[range],
+        )?,
+        ChannelData::UInt32(a) if n_bytes == 4 => gather_values(
+            data_chunk,
+            record_length,
+            pos_byte_beg,
+            big_endian,
+            &mut a.values_slice_mut()[range],
+        )?,
+        ChannelData::Float32(a) if n_bytes == 4 => gather_values(
+            data_chunk,
+            record_length,
+            pos_byte_beg,
+            big_endian,
+            &mut a.values_slice_mut()[range],
+        )?,
+        ChannelData::Int64(a) if n_bytes == 8 => gather_values(
+            data_chunk,
+            record_length,
+            pos_byte_beg,
+            big_endian,
+            &mut a.values_slice_mut()[range],
+        )?,
+        ChannelData::UInt64(a) if n_bytes == 8 => gather_values(
+            data_chunk,
+            record_length,
+            pos_byte_beg,
+            big_endian,
+            &mut a.values_slice_mut()[range],
+        )?,
+        ChannelData::Float64(a) if n_bytes == 8 => gather_values(
+            data_chunk,
+            record_length,
+            pos_byte_beg,
+            big_endian,
+            &mut a.values_slice_mut()[range],
+        )?,
+        _ => return Ok(false),
+    }
+    Ok(true)
+}
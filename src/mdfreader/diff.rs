@@ -0,0 +1,236 @@
+//! Comparison of two mdf files: channels present in one file only, metadata differences
+//! and per channel data differences, for instance to validate conversions and round trips
+use std::collections::HashSet;
+use std::fmt;
+
+use anyhow::{Context, Result};
+use arrow::array::{Array, ArrayRef, AsArray};
+use arrow::compute::cast;
+use arrow::compute::kernels::cmp::distinct;
+use arrow::datatypes::{DataType, Float64Type};
+
+use crate::mdfreader::Mdf;
+
+/// metadata property differing between the two files, at file level if channel is None
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetadataDiff {
+    pub channel: Option<String>,
+    /// property name: version, start time, unit, description, master or master type
+    pub property: String,
+    pub left: Option<String>,
+    pub right: Option<String>,
+}
+
+/// data differences of a channel loaded in both files
+#[derive(Debug, Clone, PartialEq)]
+pub struct DataDiff {
+    pub channel: String,
+    /// number of samples in the left file
+    pub left_len: usize,
+    /// number of samples in the right file
+    pub right_len: usize,
+    /// maximum absolute difference of the common samples, None for non numeric channels
+    /// or without valid common sample
+    pub max_abs_error: Option<f64>,
+    /// number of common samples differing beyond tolerance, validity included
+    pub n_different: usize,
+}
+
+/// result of the comparison of two files
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DiffReport {
+    pub left: String,
+    pub right: String,
+    /// channels only in the left file, sorted
+    pub only_left: Vec<String>,
+    /// channels only in the right file, sorted
+    pub only_right: Vec<String>,
+    pub metadata: Vec<MetadataDiff>,
+    /// channels whose data differ, sorted by name
+    pub data: Vec<DataDiff>,
+}
+
+impl DiffReport {
+    /// true if no difference was found
+    pub fn is_identical(&self) -> bool {
+        self.only_left.is_empty()
+            && self.only_right.is_empty()
+            && self.metadata.is_empty()
+            && self.data.is_empty()
+    }
+}
+
+impl fmt::Display for DiffReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "--- {}\n+++ {}", self.left, self.right)?;
+        for name in self.only_left.iter() {
+            writeln!(f, "- channel {name}")?;
+        }
+        for name in self.only_right.iter() {
+            writeln!(f, "+ channel {name}")?;
+        }
+        for diff in self.metadata.iter() {
+            let scope = diff
+                .channel
+                .as_ref()
+                .map_or_else(|| "file".to_string(), |name| format!("channel {name}"));
+            writeln!(
+                f,
+                "{scope} {}: {:?} != {:?}",
+                diff.property, diff.left, diff.right
+            )?;
+        }
+        for diff in self.data.iter() {
+            write!(f, "channel {} data:", diff.channel)?;
+            if diff.left_len != diff.right_len {
+                write!(f, " cycle counts {} != {}", diff.left_len, diff.right_len)?;
+            }
+            if diff.n_different > 0 {
+                write!(f, " {} samples differ", diff.n_different)?;
+            }
+            if let Some(error) = diff.max_abs_error {
+                write!(f, " max abs error {error}")?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// compares the channels, metadata and data loaded in memory of both files.
+/// Numeric values are equal if their absolute difference is within tolerance
+pub fn diff(left: &Mdf, right: &Mdf, tolerance: f64) -> Result<DiffReport> {
+    let left_names = left.get_channel_names_set();
+    let right_names = right.get_channel_names_set();
+    let sorted = |names: HashSet<&String>| {
+        let mut names: Vec<String> = names.into_iter().cloned().collect();
+        names.sort();
+        names
+    };
+    let mut report = DiffReport {
+        left: left.get_file_name(),
+        right: right.get_file_name(),
+        only_left: sorted(left_names.difference(&right_names).collect()),
+        only_right: sorted(right_names.difference(&left_names).collect()),
+        ..Default::default()
+    };
+
+    let mut push =
+        |channel: Option<&String>, property: &str, l: Option<String>, r: Option<String>| {
+            if l != r {
+                report.metadata.push(MetadataDiff {
+                    channel: channel.cloned(),
+                    property: property.to_string(),
+                    left: l,
+                    right: r,
+                });
+            }
+        };
+    push(
+        None,
+        "version",
+        Some(left.get_version().to_string()),
+        Some(right.get_version().to_string()),
+    );
+    push(
+        None,
+        "start time",
+        left.get_start_time().ok().map(|time| time.to_rfc3339()),
+        right.get_start_time().ok().map(|time| time.to_rfc3339()),
+    );
+    let common = sorted(left_names.intersection(&right_names).collect());
+    for name in common.iter() {
+        push(
+            Some(name),
+            "unit",
+            left.get_channel_unit(name)?,
+            right.get_channel_unit(name)?,
+        );
+        push(
+            Some(name),
+            "description",
+            left.get_channel_desc(name)?,
+            right.get_channel_desc(name)?,
+        );
+        push(
+            Some(name),
+            "master",
+            left.get_channel_master(name),
+            right.get_channel_master(name),
+        );
+        push(
+            Some(name),
+            "master type",
+            Some(left.get_channel_master_type(name).to_string()),
+            Some(right.get_channel_master_type(name).to_string()),
+        );
+    }
+
+    for name in common {
+        if let (Some(left_data), Some(right_data)) =
+            (left.get_channel_data(&name), right.get_channel_data(&name))
+        {
+            let (n_different, max_abs_error) =
+                diff_data(&left_data.as_ref(), &right_data.as_ref(), tolerance)
+                    .with_context(|| format!("failed comparing data of channel {name}"))?;
+            if n_different > 0 || left_data.len() != right_data.len() {
+                report.data.push(DataDiff {
+                    channel: name,
+                    left_len: left_data.len(),
+                    right_len: right_data.len(),
+                    max_abs_error,
+                    n_different,
+                });
+            }
+        }
+    }
+    Ok(report)
+}
+
+/// compares the common samples, returns the number of differing samples and the maximum
+/// absolute difference for numeric data. NaN values are equal
+fn diff_data(left: &ArrayRef, right: &ArrayRef, tolerance: f64) -> Result<(usize, Option<f64>)> {
+    let len = left.len().min(right.len());
+    let (left, right) = (left.slice(0, len), right.slice(0, len));
+    let numeric = |data_type: &DataType| data_type.is_numeric() || data_type == &DataType::Boolean;
+    if numeric(left.data_type()) && numeric(right.data_type()) {
+        let left = cast(&left, &DataType::Float64)?;
+        let right = cast(&right, &DataType::Float64)?;
+        let (left, right) = (
+            left.as_primitive::<Float64Type>(),
+            right.as_primitive::<Float64Type>(),
+        );
+        let mut n_different = 0;
+        let mut max_abs_error: Option<f64> = None;
+        for i in 0..len {
+            match (left.is_valid(i), right.is_valid(i)) {
+                (true, true) => {
+                    let (l, r) = (left.value(i), right.value(i));
+                    if l.is_nan() && r.is_nan() {
+                        continue;
+                    }
+                    let error = (l - r).abs();
+                    max_abs_error = Some(max_abs_error.map_or(error, |max| max.max(error)));
+                    if error.is_nan() || error > tolerance {
+                        n_different += 1;
+                    }
+                }
+                (false, false) => {}
+                _ => n_different += 1,
+            }
+        }
+        Ok((n_different, max_abs_error))
+    } else if left.data_type() == right.data_type() {
+        let n_different = match distinct(&left, &right) {
+            Ok(different) => different.true_count(),
+            // nested types like arrays compared sample by sample
+            Err(_) => (0..len)
+                .filter(|i| left.slice(*i, 1).to_data() != right.slice(*i, 1).to_data())
+                .count(),
+        };
+        Ok((n_different, None))
+    } else {
+        // not comparable, all samples differ
+        Ok((len, None))
+    }
+}
@@ -0,0 +1,192 @@
+//! on-disk index of the data blocks of a mdf4 file, saved next to the file.
+//! For each sorted channel group with a master channel, it lists the data blocks with
+//! their number of records and master values range so that time range readings can
+//! skip the blocks outside of the range without reading or decompressing them.
+use crate::mdfinfo::mdfinfo4::MdfInfo4;
+use crate::mdfreader::iterator4::ChannelGroupIterator;
+use anyhow::{Context, Result};
+use binrw::{binrw, BinReaderExt, BinWriterExt};
+use log::{info, warn};
+use md5::{Digest, Md5};
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Read};
+use std::time::UNIX_EPOCH;
+
+/// number of bytes of the ID and HD blocks at the beginning of a mdf4 file
+const HEADER_LENGTH: u64 = 64 + 104;
+
+/// Index of the data blocks of a mdf4 file
+#[derive(Debug, Default, Clone, PartialEq)]
+#[binrw]
+#[brw(little, magic = b"MDFRIDX2")]
+pub struct MdfIndex {
+    /// size in bytes of the indexed file
+    pub file_size: u64,
+    /// last modification time of the indexed file, in nanoseconds since epoch
+    pub modified: u64,
+    /// md5 hash of the ID and HD blocks of the indexed file
+    pub header_md5: [u8; 16],
+    /// number of indexed channel groups
+    n_groups: u64,
+    /// indexed channel groups
+    #[br(count = n_groups)]
+    pub groups: Vec<GroupIndex>,
+}
+
+/// Data blocks of a channel group
+#[derive(Debug, Default, Clone, PartialEq)]
+#[binrw]
+#[brw(little)]
+pub struct GroupIndex {
+    /// position of the data group block in file
+    pub dg_position: i64,
+    /// record id of the channel group
+    pub rec_id: u64,
+    /// number of data blocks
+    n_blocks: u64,
+    /// data blocks in reading order
+    #[br(count = n_blocks)]
+    pub blocks: Vec<BlockIndex>,
+}
+
+/// Number of records and master values range of a data block
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+#[binrw]
+#[brw(little)]
+pub struct BlockIndex {
+    /// position of the DT, DZ or DV block in file
+    pub position: i64,
+    /// number of records in the block
+    pub n_records: u64,
+    /// minimum master value of the block records
+    pub t_min: f64,
+    /// maximum master value of the block records
+    pub t_max: f64,
+}
+
+/// returns the index file name of a mdf file
+pub fn index_file_name(file_name: &str) -> String {
+    format!("{file_name}.mdfidx")
+}
+
+/// size, last modification time and header hash of a file, used to detect outdated index
+fn file_stamp(file_name: &str) -> Result<(u64, u64, [u8; 16])> {
+    let metadata = fs::metadata(file_name)
+        .with_context(|| format!("could not read metadata of file {file_name}"))?;
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map(|duration| duration.as_nanos() as u64)
+        .unwrap_or(0);
+    let mut header = Vec::with_capacity(HEADER_LENGTH as usize);
+    File::open(file_name)
+        .with_context(|| format!("could not open file {file_name}"))?
+        .take(HEADER_LENGTH)
+        .read_to_end(&mut header)
+        .with_context(|| format!("could not read header of file {file_name}"))?;
+    let mut header_md5 = [0u8; 16];
+    header_md5.copy_from_slice(&Md5::digest(&header));
+    Ok((metadata.len(), modified, header_md5))
+}
+
+impl MdfIndex {
+    /// reads the index saved next to the mdf file,
+    /// None if missing, unreadable or not matching the file anymore
+    pub fn load(file_name: &str) -> Option<MdfIndex> {
+        let index_name = index_file_name(file_name);
+        let file = File::open(&index_name).ok()?;
+        let mut rdr = BufReader::new(file);
+        let index: MdfIndex = match rdr.read_le() {
+            Ok(index) => index,
+            Err(e) => {
+                warn!("could not read index file {index_name}: {e}");
+                return None;
+            }
+        };
+        match file_stamp(file_name) {
+            Ok(stamp) if stamp == (index.file_size, index.modified, index.header_md5) => {
+                Some(index)
+            }
+            _ => {
+                info!("index file {index_name} is outdated and ignored");
+                None
+            }
+        }
+    }
+
+    /// writes the index next to the mdf file
+    pub fn save(&mut self, file_name: &str) -> Result<()> {
+        let index_name = index_file_name(file_name);
+        for group in self.groups.iter_mut() {
+            group.n_blocks = group.blocks.len() as u64;
+        }
+        self.n_groups = self.groups.len() as u64;
+        let file = File::create(&index_name)
+            .with_context(|| format!("could not create index file {index_name}"))?;
+        let mut writer = BufWriter::new(file);
+        writer
+            .write_le(self)
+            .with_context(|| format!("could not write index file {index_name}"))?;
+        Ok(())
+    }
+
+    /// returns the indexed blocks of a channel group, by block position
+    pub fn group_blocks(&self, dg_position: i64, rec_id: u64) -> HashMap<i64, BlockIndex> {
+        self.groups
+            .iter()
+            .find(|group| group.dg_position == dg_position && group.rec_id == rec_id)
+            .map(|group| {
+                group
+                    .blocks
+                    .iter()
+                    .map(|block| (block.position, *block))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+/// reads all the data blocks of the file to build its index and saves it next to the file
+pub fn build_index4(info: &MdfInfo4) -> Result<MdfIndex> {
+    let (file_size, modified, header_md5) = file_stamp(&info.file_name)?;
+    let mut index = MdfIndex {
+        file_size,
+        modified,
+        header_md5,
+        ..Default::default()
+    };
+    for (dg_position, dg) in info.dg.iter() {
+        for (rec_id, cg) in dg.cg.iter() {
+            let Some(channel_name) = cg
+                .cn
+                .values()
+                .find(|cn| cn.block.cn_type != 1)
+                .map(|cn| cn.unique_name.clone())
+            else {
+                continue;
+            };
+            let blocks = ChannelGroupIterator::new(info, &channel_name)?
+                .block_ranges()
+                .with_context(|| {
+                    format!("failed indexing data blocks of channel group of {channel_name}")
+                })?;
+            if let Some(blocks) = blocks {
+                index.groups.push(GroupIndex {
+                    dg_position: *dg_position,
+                    rec_id: *rec_id,
+                    n_blocks: blocks.len() as u64,
+                    blocks,
+                });
+            }
+        }
+    }
+    index.save(&info.file_name)?;
+    info!(
+        "Index of file {} saved in {}",
+        info.file_name,
+        index_file_name(&info.file_name)
+    );
+    Ok(index)
+}
@@ -0,0 +1,711 @@
+//! lazy record by record reading of a channel group, based in MdfInfo4's metadata
+use crate::data_holder::channel_data::{data_type_init, ChannelData};
+use crate::error::{block_id_string, MdfError};
+use crate::mdfinfo::mdfinfo4::{
+    parse_dz, Dg4, Dl4Block, Dt4Block, Hl4Block, MdfInfo4, SharableBlocks,
+};
+use crate::mdfreader::conversions4::convert_all_channels;
+use crate::mdfreader::data_read4::read_channels_from_bytes;
+use crate::mdfreader::index4::{BlockIndex, MdfIndex};
+use crate::mdfreader::mdfreader4::{
+    apply_bit_mask_offset, initialise_arrays, CHUNK_SIZE_READING_4,
+};
+use crate::mdfreader::{ReadOptions, StringPolicy};
+use anyhow::{bail, Context, Error, Result};
+use arrow::array::AsArray;
+use arrow::compute::cast;
+use arrow::datatypes::{DataType, Float64Type};
+use binrw::BinReaderExt;
+use std::collections::{HashMap, HashSet};
+use std::fs::{File, OpenOptions};
+use std::io::{BufReader, Read, Seek, SeekFrom};
+
+/// Iterator over the records of a channel group.
+/// Each item holds the physical values of a chunk of consecutive records for each channel
+/// of the group, so that files bigger than memory can be processed without loading
+/// whole channels with load_all_channels_data_in_memory.
+/// Sorted and unsorted DT, DZ, DL and HL data layouts are supported.
+/// VLSD channels (cn_type 1) are not decoded by this iterator.
+/// With a time range, only records with master values in range are returned.
+pub struct ChannelGroupIterator<'a, R: Read + Seek = File> {
+    /// file or any reader of the file content, data blocks are read only when iterating
+    reader: R,
+    /// positions of the data blocks (DT, DZ or DV) in file, in reading order
+    blocks: Vec<i64>,
+    /// index of next data block to read in blocks
+    block_index: usize,
+    /// position in file of the next bytes to read from current DT block
+    block_position: i64,
+    /// number of bytes remaining to be read from current DT block
+    block_remaining: usize,
+    /// copy of the data group metadata restricted to the iterated channel group
+    dg: Dg4,
+    /// record id of the iterated channel group
+    rec_id: u64,
+    /// record length of the iterated channel group including record id and invalid bytes
+    record_length: usize,
+    /// record length and VLSD flag for each record id of the data group, used for unsorted data
+    record_layouts: HashMap<u64, (usize, bool)>,
+    /// true if data group contains only one channel group
+    sorted: bool,
+    /// names of the channels decoded in each chunk
+    channel_names: HashSet<String>,
+    /// number of records per chunk
+    chunk_records: usize,
+    /// number of records not yet returned, bounded by cg_cycle_count
+    remaining_records: usize,
+    /// raw unsorted bytes not yet demultiplexed
+    data: Vec<u8>,
+    /// raw records of the iterated channel group not yet decoded
+    records: Vec<u8>,
+    /// index in channel group of the first record in records
+    record_index: u64,
+    /// master channel name of the channel group, if stored in records
+    master_name: Option<String>,
+    /// master values range of the returned records
+    time_range: Option<(f64, f64)>,
+    /// decoding of UTF-8 strings with invalid bytes
+    string_policy: StringPolicy,
+    /// indexed data blocks by position, from the index file if present
+    indexed_blocks: HashMap<i64, BlockIndex>,
+    /// conversion and text blocks
+    sharable: &'a SharableBlocks,
+}
+
+/// position of a data block relatively to the time range
+enum BlockTime {
+    Before,
+    Within,
+    After,
+}
+
+impl<'a> ChannelGroupIterator<'a> {
+    /// creates the iterator for the channel group containing the given channel
+    pub fn new(info: &'a MdfInfo4, channel_name: &str) -> Result<ChannelGroupIterator<'a>> {
+        let file = OpenOptions::new()
+            .read(true)
+            .open(&info.file_name)
+            .map_err(MdfError::Io)
+            .with_context(|| format!("Cannot find the file {}", info.file_name))?;
+        let mut iterator = ChannelGroupIterator::from_reader(info, channel_name, file)?;
+        // index file is only trusted next to the file it was built from
+        if let Some((_master, dg_pos, (_cg_pos, rec_id), _cn)) = info.get_channel_id(channel_name) {
+            iterator.indexed_blocks = MdfIndex::load(&info.file_name)
+                .map(|index| index.group_blocks(*dg_pos, *rec_id))
+                .unwrap_or_default();
+        }
+        Ok(iterator)
+    }
+}
+
+impl<'a, R: Read + Seek> ChannelGroupIterator<'a, R> {
+    /// creates the iterator for the channel group containing the given channel,
+    /// data blocks being read from the given reader of the file content. The index file,
+    /// describing a local file, is not used
+    pub fn from_reader(
+        info: &'a MdfInfo4,
+        channel_name: &str,
+        mut reader: R,
+    ) -> Result<ChannelGroupIterator<'a, R>> {
+        let Some((_master, dg_pos, (_cg_pos, rec_id), (_cn_pos, _rec_pos))) =
+            info.get_channel_id(channel_name)
+        else {
+            bail!("channel {} not found in file", channel_name)
+        };
+        let Some(file_dg) = info.dg.get(dg_pos) else {
+            bail!("data group of channel {} not found", channel_name)
+        };
+        let Some(file_cg) = file_dg.cg.get(rec_id) else {
+            bail!("channel group of channel {} not found", channel_name)
+        };
+        let record_layouts: HashMap<u64, (usize, bool)> = file_dg
+            .cg
+            .iter()
+            .map(|(id, cg)| {
+                (
+                    *id,
+                    (cg.record_length as usize, (cg.block.cg_flags & 0b1) != 0),
+                )
+            })
+            .collect();
+
+        // keeps only the iterated channel group with empty arrays of raw data type
+        let mut cg = file_cg.clone();
+        for cn in cg.cn.values_mut() {
+            cn.data = data_type_init(
+                cn.block.cn_type,
+                cn.block.cn_data_type,
+                cn.n_bytes,
+                cn.list_size,
+            )
+            .with_context(|| format!("failed initialising data of channel {}", cn.unique_name))?;
+        }
+        cg.invalid_bytes = None;
+        let channel_names: HashSet<String> = cg
+            .cn
+            .values()
+            .filter(|cn| cn.block.cn_type != 1)
+            .map(|cn| cn.unique_name.clone())
+            .collect();
+        let mut dg = Dg4 {
+            block: file_dg.block,
+            cg: HashMap::new(),
+        };
+        let master_name = cg
+            .master_channel_name
+            .clone()
+            .filter(|name| channel_names.contains(name));
+        let record_length = cg.record_length as usize;
+        let remaining_records = cg.block.cg_cycle_count as usize;
+        let cg_inval_bytes = cg.block.cg_inval_bytes;
+        dg.cg.insert(*rec_id, cg);
+
+        let blocks = if file_dg.block.dg_data != 0 {
+            let mut rdr = BufReader::new(&mut reader);
+            list_data_blocks(&mut rdr, file_dg.block.dg_data, cg_inval_bytes)
+                .context("failed listing data blocks")?
+        } else {
+            Vec::new()
+        };
+        Ok(ChannelGroupIterator {
+            reader,
+            blocks,
+            block_index: 0,
+            block_position: 0,
+            block_remaining: 0,
+            dg,
+            rec_id: *rec_id,
+            record_length,
+            record_layouts,
+            sorted: file_dg.cg.len() == 1,
+            channel_names,
+            chunk_records: (CHUNK_SIZE_READING_4 / record_length.max(1)).max(1),
+            remaining_records,
+            data: Vec::new(),
+            records: Vec::new(),
+            record_index: 0,
+            master_name,
+            time_range: None,
+            string_policy: StringPolicy::default(),
+            indexed_blocks: HashMap::new(),
+            sharable: &info.sharable,
+        })
+    }
+
+    /// restricts the decoded channels to the given ones, master channel is always decoded
+    pub fn with_channel_names(mut self, channel_names: &HashSet<String>) -> Self {
+        let master_name = self.master_name.clone();
+        self.channel_names
+            .retain(|name| channel_names.contains(name) || master_name.as_ref() == Some(name));
+        self
+    }
+
+    /// returns only the records with master values between t_start and t_stop.
+    /// Master channel is expected to be monotonic, sorted data blocks outside of the range are skipped.
+    pub fn with_time_range(mut self, t_start: f64, t_stop: f64) -> Self {
+        self.time_range = Some((t_start, t_stop));
+        self
+    }
+
+    /// decodes the UTF-8 strings with invalid bytes according to the given policy
+    #[allow(dead_code)]
+    pub fn with_string_policy(mut self, string_policy: StringPolicy) -> Self {
+        self.string_policy = string_policy;
+        self
+    }
+
+    /// reads and decodes in one go all the records, within time range if defined
+    pub(crate) fn read_all(mut self) -> Result<HashMap<String, ChannelData>> {
+        let mut records: Vec<u8> = Vec::new();
+        let mut n_records: usize = 0;
+        let mut first_index: Option<u64> = None;
+        while let Some((raw, n, index)) = self.next_raw()? {
+            first_index.get_or_insert(index);
+            records.extend(raw);
+            n_records += n;
+        }
+        self.decode_records(
+            &records,
+            n_records,
+            first_index.unwrap_or(0),
+            &self.channel_names,
+        )
+    }
+
+    /// reads the next piece of raw data from the data blocks, None when all blocks are consumed
+    fn read_next_bytes(&mut self) -> Result<Option<Vec<u8>>> {
+        while self.block_remaining == 0 {
+            let Some(position) = self.blocks.get(self.block_index).copied() else {
+                return Ok(None);
+            };
+            self.block_index += 1;
+            if let Some(block_time) = self.indexed_block_time(position) {
+                match block_time {
+                    (BlockTime::Before, n_records) => {
+                        self.record_index += n_records;
+                        self.remaining_records =
+                            self.remaining_records.saturating_sub(n_records as usize);
+                        continue;
+                    }
+                    (BlockTime::After, _) => {
+                        self.block_index = self.blocks.len();
+                        return Ok(None);
+                    }
+                    (BlockTime::Within, _) => {}
+                }
+            }
+            let mut rdr = BufReader::new(&mut self.reader);
+            rdr.seek(SeekFrom::Start(position as u64))
+                .context("Could not reach data block position")?;
+            let mut id = [0u8; 4];
+            rdr.read_exact(&mut id)
+                .context("could not read data block id")?;
+            if id == "##DZ".as_bytes() {
+                let (data, _block) = parse_dz(&mut rdr)?;
+                drop(rdr);
+                match self.data_time(&data)? {
+                    BlockTime::Before => {
+                        let n_records = data.len() / self.record_length;
+                        self.record_index += n_records as u64;
+                        self.remaining_records = self.remaining_records.saturating_sub(n_records);
+                        continue;
+                    }
+                    BlockTime::After => {
+                        self.block_index = self.blocks.len();
+                        return Ok(None);
+                    }
+                    BlockTime::Within => return Ok(Some(data)),
+                }
+            }
+            let block_header: Dt4Block = rdr
+                .read_le()
+                .context("could not read into Dt4Block structure")?;
+            self.block_position = position + 24;
+            self.block_remaining = (block_header.len - 24) as usize;
+            match self.block_time()? {
+                BlockTime::Before => {
+                    let n_records = self.block_remaining / self.record_length;
+                    self.record_index += n_records as u64;
+                    self.remaining_records = self.remaining_records.saturating_sub(n_records);
+                    self.block_remaining = 0;
+                }
+                BlockTime::After => {
+                    self.block_remaining = 0;
+                    self.block_index = self.blocks.len();
+                    return Ok(None);
+                }
+                BlockTime::Within => {}
+            }
+        }
+        let mut rdr = BufReader::new(&mut self.reader);
+        rdr.seek(SeekFrom::Start(self.block_position as u64))
+            .context("Could not reach data block position")?;
+        let chunk_size = self.block_remaining.min(CHUNK_SIZE_READING_4);
+        let mut data_chunk = vec![0u8; chunk_size];
+        rdr.read_exact(&mut data_chunk)
+            .context("Could not read data chunk")?;
+        self.block_position += chunk_size as i64;
+        self.block_remaining -= chunk_size;
+        Ok(Some(data_chunk))
+    }
+
+    /// compares the indexed master range of the data block at position with time range,
+    /// returning also its number of records. None if block is not indexed
+    fn indexed_block_time(&self, position: i64) -> Option<(BlockTime, u64)> {
+        let (t_start, t_stop) = self.time_range?;
+        let block = self.indexed_blocks.get(&position)?;
+        if !self.sorted || !self.records.is_empty() {
+            return None;
+        }
+        if block.t_max < t_start {
+            Some((BlockTime::Before, block.n_records))
+        } else if block.t_min > t_stop {
+            Some((BlockTime::After, block.n_records))
+        } else {
+            Some((BlockTime::Within, block.n_records))
+        }
+    }
+
+    /// reads all the data blocks to get their number of records and master values range.
+    /// None if the channel group cannot be indexed: unsorted data, no master channel
+    /// or records spread over several blocks
+    pub(crate) fn block_ranges(mut self) -> Result<Option<Vec<BlockIndex>>> {
+        if !self.sorted || self.master_name.is_none() || self.record_length == 0 {
+            return Ok(None);
+        }
+        self.time_range = None;
+        let mut ranges: Vec<BlockIndex> = Vec::new();
+        while self.remaining_records > 0 {
+            let Some(bytes) = self.read_next_bytes()? else {
+                break;
+            };
+            let position = self.blocks[self.block_index - 1];
+            if ranges.last().map(|block| block.position) != Some(position) {
+                if !self.records.is_empty() {
+                    return Ok(None);
+                }
+                ranges.push(BlockIndex {
+                    position,
+                    n_records: 0,
+                    t_min: f64::INFINITY,
+                    t_max: f64::NEG_INFINITY,
+                });
+            }
+            self.records.extend(bytes);
+            let n_records = (self.records.len() / self.record_length).min(self.remaining_records);
+            if n_records > 0 {
+                let master = self.master_values(
+                    &self.records[..n_records * self.record_length],
+                    n_records,
+                    self.record_index,
+                )?;
+                if let (Some(master), Some(block)) = (master, ranges.last_mut()) {
+                    for t in master {
+                        block.t_min = block.t_min.min(t);
+                        block.t_max = block.t_max.max(t);
+                    }
+                    block.n_records += n_records as u64;
+                }
+                self.records.drain(..n_records * self.record_length);
+                self.record_index += n_records as u64;
+                self.remaining_records -= n_records;
+            }
+        }
+        Ok(Some(ranges))
+    }
+
+    /// number of whole records of a sorted data block of block_length bytes whose master range
+    /// can be compared with time range, None if not possible or without time range
+    fn comparable_records(&self, block_length: usize) -> Option<usize> {
+        if self.time_range.is_none()
+            || self.master_name.is_none()
+            || !self.sorted
+            || !self.records.is_empty()
+            || self.record_length == 0
+            || block_length % self.record_length != 0
+        {
+            return None;
+        }
+        Some(block_length / self.record_length).filter(|n_records| *n_records > 0)
+    }
+
+    /// compares master values of the first and last of n_records records with time range
+    fn records_time(&self, first: &[u8], last: &[u8], n_records: usize) -> Result<BlockTime> {
+        let Some((t_start, t_stop)) = self.time_range else {
+            return Ok(BlockTime::Within);
+        };
+        let first = self.master_values(first, 1, self.record_index)?;
+        let last = self.master_values(last, 1, self.record_index + n_records as u64 - 1)?;
+        match (
+            first.and_then(|t| t.first().copied()),
+            last.and_then(|t| t.first().copied()),
+        ) {
+            (_, Some(t_last)) if t_last < t_start => Ok(BlockTime::Before),
+            (Some(t_first), _) if t_first > t_stop => Ok(BlockTime::After),
+            _ => Ok(BlockTime::Within),
+        }
+    }
+
+    /// compares master values of first and last records of the current DT block with time range.
+    /// Only possible for sorted data blocks containing whole records
+    fn block_time(&mut self) -> Result<BlockTime> {
+        let Some(n_records) = self.comparable_records(self.block_remaining) else {
+            return Ok(BlockTime::Within);
+        };
+        let record_length = self.record_length;
+        let block_position = self.block_position;
+        let mut rdr = BufReader::new(&mut self.reader);
+        let mut first = vec![0u8; record_length];
+        rdr.seek(SeekFrom::Start(block_position as u64))
+            .context("Could not reach first record of data block")?;
+        rdr.read_exact(&mut first)
+            .context("Could not read first record of data block")?;
+        let mut last = vec![0u8; record_length];
+        rdr.seek(SeekFrom::Start(
+            (block_position + ((n_records - 1) * record_length) as i64) as u64,
+        ))
+        .context("Could not reach last record of data block")?;
+        rdr.read_exact(&mut last)
+            .context("Could not read last record of data block")?;
+        drop(rdr);
+        self.records_time(&first, &last, n_records)
+    }
+
+    /// compares master values of first and last records of a decompressed DZ block with time
+    /// range. Only possible for sorted data blocks containing whole records
+    fn data_time(&self, data: &[u8]) -> Result<BlockTime> {
+        let Some(n_records) = self.comparable_records(data.len()) else {
+            return Ok(BlockTime::Within);
+        };
+        let record_length = self.record_length;
+        self.records_time(
+            &data[..record_length],
+            &data[(n_records - 1) * record_length..],
+            n_records,
+        )
+    }
+
+    /// moves the records of the iterated channel group from the unsorted data into records
+    fn demultiplex(&mut self) -> Result<()> {
+        let dg_rec_id_size = self.dg.block.dg_rec_id_size as usize;
+        let mut position: usize = 0;
+        while self.data.len() - position >= dg_rec_id_size {
+            let rec = &self.data[position..position + dg_rec_id_size];
+            let rec_id: u64 = match dg_rec_id_size {
+                1 => rec[0].into(),
+                2 => u16::from_le_bytes(rec.try_into().context("Could not convert record id u16")?)
+                    .into(),
+                4 => u32::from_le_bytes(rec.try_into().context("Could not convert record id u32")?)
+                    .into(),
+                8 => u64::from_le_bytes(rec.try_into().context("Could not convert record id u64")?),
+                _ => bail!("unsorted data group with record id size {}", dg_rec_id_size),
+            };
+            let Some((record_length, vlsd)) = self.record_layouts.get(&rec_id) else {
+                bail!("could not find the record id {}", rec_id)
+            };
+            let length = if *vlsd {
+                let offset = position + dg_rec_id_size;
+                if self.data.len() < offset + 4 {
+                    break; // not enough data remaining
+                }
+                let len = &self.data[offset..offset + 4];
+                dg_rec_id_size
+                    + 4
+                    + u32::from_le_bytes(len.try_into().context("Could not read length")?) as usize
+            } else {
+                *record_length
+            };
+            if self.data.len() - position < length {
+                break; // not enough data remaining
+            }
+            if rec_id == self.rec_id {
+                self.records
+                    .extend_from_slice(&self.data[position..position + length]);
+            }
+            position += length;
+        }
+        self.data.drain(..position);
+        Ok(())
+    }
+
+    /// takes the given number of records from the beginning of records, keeping only the ones
+    /// within time range. Returns the kept records, their number and the index of the first one
+    fn take_records(&mut self, n_records: usize) -> Result<Option<(Vec<u8>, usize, u64)>> {
+        let record_length = self.record_length;
+        let first_index = self.record_index;
+        let mut start = 0;
+        let mut end = n_records;
+        if let Some((t_start, t_stop)) = self.time_range {
+            if let Some(master) = self.master_values(
+                &self.records[..n_records * record_length],
+                n_records,
+                first_index,
+            )? {
+                start = master.partition_point(|t| *t < t_start);
+                end = master.partition_point(|t| *t <= t_stop).max(start);
+            }
+        }
+        let raw = self.records[start * record_length..end * record_length].to_vec();
+        self.records.drain(..n_records * record_length);
+        self.record_index += n_records as u64;
+        self.remaining_records -= n_records;
+        if end < n_records {
+            // following records are after the time range
+            self.remaining_records = 0;
+        }
+        if end > start {
+            Ok(Some((raw, end - start, first_index + start as u64)))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// decodes the master channel physical values of the given records
+    fn master_values(
+        &self,
+        records: &[u8],
+        n_records: usize,
+        first_index: u64,
+    ) -> Result<Option<Vec<f64>>> {
+        let Some(master_name) = &self.master_name else {
+            return Ok(None);
+        };
+        let channel_names = HashSet::from([master_name.clone()]);
+        let mut decoded = self.decode_records(records, n_records, first_index, &channel_names)?;
+        let Some(data) = decoded.remove(master_name) else {
+            return Ok(None);
+        };
+        let array = cast(&data.as_ref(), &DataType::Float64)
+            .context("failed casting master channel into f64")?;
+        Ok(Some(array.as_primitive::<Float64Type>().values().to_vec()))
+    }
+
+    /// decodes and converts the given records
+    fn decode_records(
+        &self,
+        records: &[u8],
+        n_records: usize,
+        first_index: u64,
+        channel_names: &HashSet<String>,
+    ) -> Result<HashMap<String, ChannelData>> {
+        let mut dg = self.dg.clone();
+        if let Some(cg) = dg.cg.get_mut(&self.rec_id) {
+            // channel data is not kept by clone, restores raw data types
+            if let Some(source_cg) = self.dg.cg.get(&self.rec_id) {
+                for (rec_pos, cn) in cg.cn.iter_mut() {
+                    if let Some(source_cn) = source_cg.cn.get(rec_pos) {
+                        cn.data = source_cn.data.clone();
+                    }
+                }
+            }
+            // arrays and validity masks are sized for the records
+            initialise_arrays(cg, &(n_records as u64), channel_names)
+                .context("failed initialising arrays")?;
+            // virtual channels values are the record index
+            for cn in cg.cn.values_mut().filter(|cn| {
+                (cn.block.cn_type == 3 || cn.block.cn_type == 6)
+                    && channel_names.contains(&cn.unique_name)
+            }) {
+                if let ChannelData::UInt64(a) = &mut cn.data {
+                    a.values_slice_mut()
+                        .iter_mut()
+                        .for_each(|v| *v += first_index);
+                }
+            }
+            read_channels_from_bytes(
+                records,
+                &mut cg.cn,
+                self.record_length,
+                0,
+                channel_names,
+                true,
+                self.string_policy,
+            )
+            .context("could not read channels from bytes")?;
+        }
+        apply_bit_mask_offset(&mut dg, channel_names).context("failed applying bit mask offset")?;
+        let mut chunk: HashMap<String, ChannelData> = HashMap::new();
+        for cg in dg.cg.values_mut() {
+            cg.process_all_channel_invalid_bits()
+                .context("failed processing all channel invalid bits")?;
+        }
+        convert_all_channels(
+            &mut dg,
+            self.sharable,
+            channel_names,
+            &ReadOptions::default(),
+        )
+        .context("failed converting all channels")?;
+        for cg in dg.cg.into_values() {
+            for cn in cg.cn.into_values() {
+                if channel_names.contains(&cn.unique_name) {
+                    chunk.insert(cn.unique_name, cn.data);
+                }
+            }
+        }
+        Ok(chunk)
+    }
+
+    /// returns the next raw records with their number and index of first record,
+    /// None when the channel group is exhausted
+    fn next_raw(&mut self) -> Result<Option<(Vec<u8>, usize, u64)>> {
+        loop {
+            if self.remaining_records == 0 {
+                return Ok(None);
+            }
+            let available =
+                (self.records.len() / self.record_length.max(1)).min(self.remaining_records);
+            // with time range, records are taken at the end of each block to check next block range
+            let block_end = self.time_range.is_some() && self.block_remaining == 0;
+            if available >= self.chunk_records || (block_end && available > 0) {
+                if let Some(raw) = self.take_records(available.min(self.chunk_records))? {
+                    return Ok(Some(raw));
+                }
+                continue;
+            }
+            match self.read_next_bytes()? {
+                Some(bytes) => {
+                    if self.sorted {
+                        self.records.extend(bytes);
+                    } else {
+                        self.data.extend(bytes);
+                        self.demultiplex()?;
+                    }
+                }
+                None => {
+                    if available > 0 {
+                        if let Some(raw) = self.take_records(available)? {
+                            return Ok(Some(raw));
+                        }
+                        continue;
+                    }
+                    return Ok(None);
+                }
+            }
+        }
+    }
+
+    /// returns the next chunk of records, None when the channel group is exhausted
+    fn next_chunk(&mut self) -> Result<Option<HashMap<String, ChannelData>>> {
+        match self.next_raw()? {
+            Some((records, n_records, first_index)) => self
+                .decode_records(&records, n_records, first_index, &self.channel_names)
+                .map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+impl<R: Read + Seek> Iterator for ChannelGroupIterator<'_, R> {
+    type Item = Result<HashMap<String, ChannelData>, Error>;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining_records == 0 {
+            return None;
+        }
+        let chunk = self.next_chunk().transpose();
+        if let Some(Err(_)) = chunk {
+            // no more reading after an error
+            self.remaining_records = 0;
+        }
+        chunk
+    }
+}
+
+/// Lists the data blocks pointed by dg_data, following HL and DL blocks
+fn list_data_blocks<R: Read + Seek>(
+    rdr: &mut BufReader<R>,
+    dg_data: i64,
+    cg_inval_bytes: u32,
+) -> Result<Vec<i64>> {
+    let mut blocks: Vec<i64> = Vec::new();
+    let mut position = dg_data;
+    rdr.seek(SeekFrom::Start(position as u64))
+        .context("Could not reach data block position")?;
+    let mut id = [0u8; 4];
+    rdr.read_exact(&mut id).context("could not read block id")?;
+    if id == "##HL".as_bytes() {
+        let block: Hl4Block = rdr.read_le().context("could not read HL block")?;
+        position = block.hl_dl_first;
+        rdr.seek(SeekFrom::Start(position as u64))
+            .context("Could not reach DL block from HL block")?;
+        rdr.read_exact(&mut id)
+            .context("could not read DL block id")?;
+    }
+    match &id {
+        b"##DT" | b"##DZ" => blocks.push(position),
+        b"##DV" if cg_inval_bytes == 0 => blocks.push(position),
+        b"##DL" => loop {
+            let block: Dl4Block = rdr.read_le().context("could not read DL block")?;
+            blocks.extend(block.dl_data);
+            if block.dl_dl_next <= 0 {
+                break;
+            }
+            rdr.seek(SeekFrom::Start(block.dl_dl_next as u64))
+                .context("Could not reach next DL block")?;
+            rdr.read_exact(&mut id)
+                .context("could not read DL block id")?;
+        },
+        _ => bail!(MdfError::UnsupportedBlock(block_id_string(&id))),
+    }
+    Ok(blocks)
+}
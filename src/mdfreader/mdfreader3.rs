@@ -1,30 +1,32 @@
 //! data read and load in memory based in MdfInfo3's metadata
-use rayon::prelude::*;
+use crate::parallel::prelude::*;
 
-use crate::mdfinfo::mdfinfo3::{Cg3, Cn3, Dg3};
+use crate::mdfinfo::mdfinfo3::{Cg3, Cn3, Dg3, SharableBlocks3};
 use crate::mdfinfo::MdfInfo;
-use anyhow::{Context, Error, Result};
+use anyhow::{bail, Context, Error, Result};
+use arrow::array::{Array, ArrayRef, AsArray, PrimitiveArray, PrimitiveBuilder};
+use arrow::datatypes::{
+    ArrowPrimitiveType, DataType, Float32Type, Float64Type, Int16Type, Int32Type, Int64Type,
+    Int8Type, UInt16Type, UInt32Type, UInt64Type, UInt8Type,
+};
+use log::warn;
 use std::collections::{HashMap, HashSet};
-use std::fs::File;
-use std::io::{BufReader, Read};
+use std::io::{BufReader, Read, Seek};
 
 use crate::mdfreader::data_read3::read_channels_from_bytes;
 
-use super::Mdf;
-use crate::data_holder::tensor_arrow::Order;
+use super::{Mdf, ReadOptions};
+use crate::data_holder::channel_data::ChannelData;
+use crate::data_holder::tensor_arrow::{Order, TensorArrow};
 use crate::mdfreader::conversions3::convert_all_channels;
 
-/// The following constant represents the size of data chunk to be read and processed.
-/// a big chunk will improve performance but consume more memory
-/// a small chunk will not consume too much memory but will cause many read calls, penalising performance
-pub const CHUNK_SIZE_READING_3: usize = 524288; // can be tuned according to architecture
-
 /// Reads the file data based on headers information contained in info parameter
 /// Hashset of channel names parameter allows to filter which channels to read
-pub fn mdfreader3<'a>(
-    rdr: &'a mut BufReader<&File>,
+pub fn mdfreader3<'a, R: Read + Seek>(
+    rdr: &'a mut BufReader<R>,
     mdf: &'a mut Mdf,
     channel_names: &HashSet<String>,
+    options: &ReadOptions,
 ) -> Result<(), Error> {
     match &mut mdf.mdf_info {
         MdfInfo::V3(info) => {
@@ -38,10 +40,13 @@ pub fn mdfreader3<'a>(
                     let cn = channel_group.channel_names.clone();
                     channel_names_present_in_dg.par_extend(cn);
                 }
-                let channel_names_to_read_in_dg: HashSet<String> = channel_names_present_in_dg
+                let mut channel_names_to_read_in_dg: HashSet<String> = channel_names_present_in_dg
                     .into_par_iter()
                     .filter(|v| channel_names.contains(v))
                     .collect();
+                // channels composing the requested ones are read too
+                let dependencies = dependencies_in_dg(dg, &channel_names_to_read_in_dg);
+                channel_names_to_read_in_dg.extend(dependencies);
                 if dg.block.dg_data != 0 && !channel_names_to_read_in_dg.is_empty() {
                     // header block
                     rdr.seek_relative(*data_position as i64 - position)
@@ -53,6 +58,7 @@ pub fn mdfreader3<'a>(
                                 rdr,
                                 channel_group,
                                 &channel_names_to_read_in_dg,
+                                options.chunk_size,
                             )?;
                             position = *data_position as i64
                                 + (channel_group.record_length as i64)
@@ -78,12 +84,17 @@ pub fn mdfreader3<'a>(
                             dg,
                             block_length,
                             &channel_names_to_read_in_dg,
+                            options.chunk_size,
                         )?;
                     }
 
+                    apply_bit_mask_offset(dg, &channel_names_to_read_in_dg);
+
                     // conversion of all channels to physical values
-                    convert_all_channels(dg, &info.sharable)
+                    convert_all_channels(dg, &info.sharable, &channel_names_to_read_in_dg, options)
                         .context("failed converting all channels")?;
+                    compose_dependencies(dg, &info.sharable, &channel_names_to_read_in_dg)
+                        .context("failed composing channels from their dependencies")?;
                 }
             }
         }
@@ -92,6 +103,161 @@ pub fn mdfreader3<'a>(
     Ok(())
 }
 
+/// keeps only the bits of the integer channels, their bit field not starting on a byte
+/// or not filling all its bytes
+fn apply_bit_mask_offset(dg: &mut Dg3, channel_names_to_read_in_dg: &HashSet<String>) {
+    for channel_group in dg.cg.values_mut() {
+        channel_group
+            .cn
+            .par_iter_mut()
+            .filter(|(_cn_position, cn)| channel_names_to_read_in_dg.contains(&cn.unique_name))
+            .for_each(|(_cn_position, cn)| {
+                // unsigned and signed integers of default, big and little endian byte order
+                if matches!(cn.block2.cn_data_type, 0 | 1 | 9 | 10 | 13 | 14) {
+                    cn.data.apply_bit_mask_offset(
+                        (cn.block2.cn_bit_offset % 8).into(),
+                        cn.block2.cn_bit_count.into(),
+                    );
+                    if cn.block2.cn_bit_count == 1
+                        && matches!(cn.block2.cn_data_type, 0 | 9 | 13)
+                        && cn.block1.cn_cc_conversion == 0
+                    {
+                        cn.data.bits_into_boolean();
+                    }
+                }
+            });
+    }
+}
+
+/// returns the names of the channels of the data group composing the given channels
+fn dependencies_in_dg(dg: &Dg3, channel_names: &HashSet<String>) -> Vec<String> {
+    dg.cg
+        .values()
+        .flat_map(|cg| cg.cn.values())
+        .filter(|cn| channel_names.contains(&cn.unique_name))
+        .filter_map(|cn| cn.dependency.as_ref())
+        .flat_map(|cd| cd.cd_dependencies.iter())
+        .filter(|(dg_pos, _cg_pos, _cn_pos)| *dg_pos == dg.block_position)
+        .filter_map(|(_dg_pos, cg_pos, cn_pos)| {
+            dg.cg
+                .values()
+                .find(|cg| cg.block_position == *cg_pos)?
+                .cn
+                .get(cn_pos)
+                .map(|cn| cn.unique_name.clone())
+        })
+        .collect()
+}
+
+/// replaces the data of the channels having a dependency block by the array of their
+/// dependent channels, like mdf4 CA arrays. Dependent channels must be in the same data
+/// group and share the same data type
+fn compose_dependencies(
+    dg: &mut Dg3,
+    sharable: &SharableBlocks3,
+    channel_names: &HashSet<String>,
+) -> Result<()> {
+    let mut composed: Vec<(u16, u32, ChannelData)> = Vec::new();
+    for (rec_id, cg) in dg.cg.iter() {
+        for (cn_pos, cn) in cg.cn.iter() {
+            let Some(cd) = &cn.dependency else {
+                continue;
+            };
+            if !channel_names.contains(&cn.unique_name) {
+                continue;
+            }
+            let mut columns: Vec<ArrayRef> = Vec::with_capacity(cd.cd_dependencies.len());
+            for (dg_pos, cg_pos, dependent_pos) in cd.cd_dependencies.iter() {
+                let dependent = dg
+                    .cg
+                    .values()
+                    .find(|cg| *dg_pos == dg.block_position && cg.block_position == *cg_pos)
+                    .and_then(|cg| cg.cn.get(dependent_pos));
+                match dependent {
+                    Some(dependent) => columns.push(dependent.physical_data(sharable)?.as_ref()),
+                    None => break,
+                }
+            }
+            if columns.len() != cd.cd_dependencies.len() {
+                warn!(
+                    "dependent channels of {} are not all in its data group, not composed",
+                    cn.unique_name
+                );
+                continue;
+            }
+            match array_from_columns(&columns, cd.shape()) {
+                Some(data) => composed.push((*rec_id, *cn_pos, data)),
+                None => warn!(
+                    "dependent channels of {} do not share the same numeric type and length, not composed",
+                    cn.unique_name
+                ),
+            }
+        }
+    }
+    for (rec_id, cn_pos, data) in composed {
+        if let Some(cn) = dg.cg.get_mut(&rec_id).and_then(|cg| cg.cn.get_mut(&cn_pos)) {
+            cn.data = data;
+            cn.raw_data = None;
+            cn.conversion_cache = None;
+        }
+    }
+    Ok(())
+}
+
+/// interleaves the columns into an array of the given shape per record, in row major order.
+/// None if columns are not of the same primitive type and length or not matching the shape
+fn array_from_columns(columns: &[ArrayRef], shape: Vec<usize>) -> Option<ChannelData> {
+    let first = columns.first()?;
+    if shape.iter().product::<usize>() != columns.len()
+        || columns
+            .iter()
+            .any(|column| column.data_type() != first.data_type() || column.len() != first.len())
+    {
+        return None;
+    }
+    let mut full_shape = vec![first.len()];
+    full_shape.extend(shape);
+    let data = match first.data_type() {
+        DataType::Int8 => ChannelData::ArrayDInt8(interleave::<Int8Type>(columns, full_shape)),
+        DataType::UInt8 => ChannelData::ArrayDUInt8(interleave::<UInt8Type>(columns, full_shape)),
+        DataType::Int16 => ChannelData::ArrayDInt16(interleave::<Int16Type>(columns, full_shape)),
+        DataType::UInt16 => {
+            ChannelData::ArrayDUInt16(interleave::<UInt16Type>(columns, full_shape))
+        }
+        DataType::Int32 => ChannelData::ArrayDInt32(interleave::<Int32Type>(columns, full_shape)),
+        DataType::UInt32 => {
+            ChannelData::ArrayDUInt32(interleave::<UInt32Type>(columns, full_shape))
+        }
+        DataType::Float32 => {
+            ChannelData::ArrayDFloat32(interleave::<Float32Type>(columns, full_shape))
+        }
+        DataType::Int64 => ChannelData::ArrayDInt64(interleave::<Int64Type>(columns, full_shape)),
+        DataType::UInt64 => {
+            ChannelData::ArrayDUInt64(interleave::<UInt64Type>(columns, full_shape))
+        }
+        DataType::Float64 => {
+            ChannelData::ArrayDFloat64(interleave::<Float64Type>(columns, full_shape))
+        }
+        _ => return None,
+    };
+    Some(data)
+}
+
+fn interleave<T: ArrowPrimitiveType>(columns: &[ArrayRef], shape: Vec<usize>) -> TensorArrow<T> {
+    let columns: Vec<&PrimitiveArray<T>> = columns
+        .iter()
+        .map(|column| column.as_primitive::<T>())
+        .collect();
+    let length = columns.first().map_or(0, |column| column.len());
+    let mut values = PrimitiveBuilder::<T>::with_capacity(length * columns.len());
+    for record in 0..length {
+        for column in columns.iter() {
+            values.append_value(column.value(record));
+        }
+    }
+    TensorArrow::new_from_primitive(values, None, shape, Order::RowMajor)
+}
+
 /// initialise ndarrays for the data group/block
 fn initialise_arrays(
     channel_group: &mut Cg3,
@@ -108,7 +274,7 @@ fn initialise_arrays(
                 cn.data = cn
                     .data
                     .zeros(
-                        0,
+                        cn.block1.cn_type as u8,
                         *cg_cycle_count as u64,
                         cn.n_bytes as u32,
                         (Vec::new(), Order::RowMajor),
@@ -127,10 +293,11 @@ fn initialise_arrays(
 }
 
 /// Returns chunk size and corresponding number of records from a channel group
-fn generate_chunks(channel_group: &Cg3) -> Vec<(usize, usize)> {
+fn generate_chunks(channel_group: &Cg3, chunk_size: usize) -> Vec<(usize, usize)> {
     let record_length = channel_group.record_length as usize;
     let cg_cycle_count = channel_group.block.cg_cycle_count as usize;
-    let n_chunks = (record_length * cg_cycle_count) / CHUNK_SIZE_READING_3 + 1; // number of chunks
+    let chunk_size = chunk_size.max(record_length).max(1); // at least one record per chunk
+    let n_chunks = (record_length * cg_cycle_count) / chunk_size + 1; // number of chunks
     let chunk_length = (record_length * cg_cycle_count) / n_chunks; // chunks length
     let n_record_chunk = chunk_length / record_length; // number of records in chunk
     let chunck = (n_record_chunk, record_length * n_record_chunk);
@@ -143,12 +310,13 @@ fn generate_chunks(channel_group: &Cg3) -> Vec<(usize, usize)> {
 }
 
 /// Reads all channels from given channel group having sorted data blocks
-fn read_all_channels_sorted(
-    rdr: &mut BufReader<&File>,
+fn read_all_channels_sorted<R: Read + Seek>(
+    rdr: &mut BufReader<R>,
     channel_group: &mut Cg3,
     channel_names_to_read_in_dg: &HashSet<String>,
+    chunk_size: usize,
 ) -> Result<(), Error> {
-    let chunks = generate_chunks(channel_group);
+    let chunks = generate_chunks(channel_group, chunk_size);
     // initialises the arrays
     initialise_arrays(
         channel_group,
@@ -176,13 +344,15 @@ fn read_all_channels_sorted(
 }
 
 /// Reads unsorted data block chunk by chunk
-fn read_all_channels_unsorted(
-    rdr: &mut BufReader<&File>,
+fn read_all_channels_unsorted<R: Read + Seek>(
+    rdr: &mut BufReader<R>,
     dg: &mut Dg3,
     block_length: i64,
     channel_names_to_read_in_dg: &HashSet<String>,
+    chunk_size: usize,
 ) -> Result<()> {
     let data_block_length = block_length as usize;
+    let chunk_size = chunk_size.max(1);
     let mut position: usize = 0;
     let mut record_counter: HashMap<u16, (usize, Vec<u8>)> = HashMap::new();
 
@@ -191,20 +361,16 @@ fn read_all_channels_unsorted(
         record_counter.insert(cg.block.cg_record_id, (0, Vec::new()));
     }
 
-    // reads the sorted data block into chunks
-    let mut data_chunk: Vec<u8>;
+    // reads the sorted data block into chunks, records split between two chunks
+    // being kept at the beginning of the next chunk
+    let mut data_chunk: Vec<u8> = Vec::new();
     while position < data_block_length {
-        if (data_block_length - position) > CHUNK_SIZE_READING_3 {
-            // not last chunk of data
-            data_chunk = vec![0u8; CHUNK_SIZE_READING_3];
-            position += CHUNK_SIZE_READING_3;
-        } else {
-            // last chunk of data
-            data_chunk = vec![0u8; data_block_length - position];
-            position += data_block_length - position;
-        }
-        rdr.read_exact(&mut data_chunk)
+        let length = (data_block_length - position).min(chunk_size);
+        let mut chunk = vec![0u8; length];
+        position += length;
+        rdr.read_exact(&mut chunk)
             .context("Could not read data chunk")?;
+        data_chunk.extend(chunk);
         read_all_channels_unsorted_from_bytes(
             &mut data_chunk,
             dg,
@@ -225,6 +391,7 @@ fn read_all_channels_unsorted_from_bytes(
 ) -> Result<(), Error> {
     let mut position: usize = 0;
     let data_length = data.len();
+    let trailing_record_id = dg.block.dg_n_record_ids == 2;
     // unsort data into sorted data blocks, except for VLSD CG.
     let mut remaining: usize = data_length - position;
     while remaining > 0 {
@@ -239,6 +406,11 @@ fn read_all_channels_unsorted_from_bytes(
             let record_length = cg.record_length as usize;
             if remaining >= record_length {
                 let record = &data[position..position + cg.record_length as usize];
+                if trailing_record_id && record.last().map(|id| u16::from(*id)) != Some(rec_id) {
+                    bail!(
+                        "record id {rec_id} at position {position} of data chunk is not repeated at record end"
+                    );
+                }
                 if let Some((_nrecord, data)) = record_counter.get_mut(&rec_id) {
                     data.extend(record);
                 }
@@ -246,6 +418,8 @@ fn read_all_channels_unsorted_from_bytes(
             } else {
                 break; // not enough data remaining
             }
+        } else {
+            bail!("unknown record id {rec_id} at position {position} of data chunk");
         }
         remaining = data_length - position;
     }
@@ -266,6 +440,7 @@ fn read_all_channels_unsorted_from_bytes(
                 channel_names_to_read_in_dg,
             )
             .context("failed reading channels from bytes")?;
+            *index += record_data.len() / (channel_group.record_length as usize).max(1);
             record_data.clear(); // clears data for new block, keeping capacity
         }
     }
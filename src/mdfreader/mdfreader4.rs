@@ -1,89 +1,71 @@
 //! data read and load in memory based in MdfInfo4's metadata
 use crate::data_holder::channel_data::ChannelData;
+use crate::data_holder::spill::{is_spillable, spill};
+use crate::data_holder::tensor_arrow::Order;
+use crate::error::{block_id_string, MdfError};
 use crate::mdfinfo::mdfinfo4::{
     parse_dz, parser_dl4_block, parser_ld4_block, Dl4Block, Dt4Block, Hl4Block, Ld4Block,
 };
-use crate::mdfinfo::mdfinfo4::{Blockheader4, Cg4, Cn4, Dg4};
+use crate::mdfinfo::mdfinfo4::{Cg4, Cn4, Dg4, MdfInfo4, SharableBlocks};
 use crate::mdfinfo::MdfInfo;
 use crate::mdfreader::conversions4::convert_all_channels;
 use crate::mdfreader::data_read4::read_channels_from_bytes;
 use crate::mdfreader::data_read4::read_one_channel_array;
+use crate::parallel::prelude::*;
 use anyhow::{bail, Context, Error, Result};
+use arrow::array::BooleanBufferBuilder;
 use binrw::BinReaderExt;
 use encoding_rs::{Decoder, UTF_16BE, UTF_16LE, WINDOWS_1252};
-use rayon::prelude::*;
-use std::fs::File;
-use std::io::Cursor;
-use std::str;
+use log::warn;
 use std::{
-    collections::{HashMap, HashSet},
-    io::{BufReader, Read},
+    collections::{BTreeMap, HashMap, HashSet},
+    fs::{File, OpenOptions},
+    io::{BufReader, Read, Seek},
+    path::Path,
 };
 
-use super::Mdf;
+use super::iterator4::ChannelGroupIterator;
+use super::{Mdf, ReadOptions, StringPolicy};
 
-/// The following constant represents the size of data chunk to be read and processed.
+/// The following constant represents the default size of data chunk to be read and processed.
 /// a big chunk will improve performance but consume more memory
 /// a small chunk will not consume too much memory but will cause many read calls, penalising performance
-pub const CHUNK_SIZE_READING_4: usize = 524288; // can be tuned according to architecture
+pub const CHUNK_SIZE_READING_4: usize = 524288; // can be tuned with ReadOptions
+
+/// Maximum estimated size of the records of the data groups read at the same time in parallel.
+/// Data groups are read by batches within this budget, a data group bigger than budget is read alone
+pub const MEMORY_BUDGET_READING_4: usize = 1 << 30; // can be tuned with ReadOptions
 
 /// Reads the file data based on headers information contained in info parameter
 /// Hashset of channel names parameter allows to filter which channels to read
-pub fn mdfreader4<'a>(
-    rdr: &'a mut BufReader<&File>,
+pub fn mdfreader4<'a, R: Read + Seek>(
+    rdr: &'a mut BufReader<R>,
     mdf: &'a mut Mdf,
     channel_names: &HashSet<String>,
+    options: &ReadOptions,
 ) -> Result<(), Error> {
     match &mut mdf.mdf_info {
         MdfInfo::V4(info) => {
             let mut position: i64 = 0;
-            let mut sorted: bool;
-            let mut channel_names_present_in_dg: HashSet<String>;
             let mut decoder: Dec = Dec {
                 windows_1252: WINDOWS_1252.new_decoder(),
                 utf_16_be: UTF_16BE.new_decoder(),
                 utf_16_le: UTF_16LE.new_decoder(),
+                string_policy: options.string_policy,
             };
             // read file data
             for (_dg_position, dg) in info.dg.iter_mut() {
-                // Let's find channel names
-                channel_names_present_in_dg = HashSet::new();
-                for channel_group in dg.cg.values() {
-                    let cn = channel_group.channel_names.clone();
-                    channel_names_present_in_dg.par_extend(cn);
-                }
-                let channel_names_to_read_in_dg: HashSet<_> = channel_names_present_in_dg
-                    .into_par_iter()
-                    .filter(|v| channel_names.contains(v))
-                    .collect();
+                let channel_names_to_read_in_dg = channel_names_to_read(dg, channel_names);
                 if dg.block.dg_data != 0 && !channel_names_to_read_in_dg.is_empty() {
-                    // header block
-                    rdr.seek_relative(dg.block.dg_data - position)
-                        .context("Could not position buffer")?; // change buffer position
-                    let mut id = [0u8; 4];
-                    rdr.read_exact(&mut id).context("could not read block id")?;
-                    sorted = dg.cg.len() == 1;
-                    position = read_data(
+                    position = read_dg(
                         rdr,
-                        id,
+                        position,
                         dg,
-                        dg.block.dg_data,
-                        sorted,
+                        &info.sharable,
                         &channel_names_to_read_in_dg,
                         &mut decoder,
-                    )
-                    .with_context(|| format!("failed reading data for dg {:?}", dg))?;
-                    apply_bit_mask_offset(dg, &channel_names_to_read_in_dg)
-                        .context("failed applying bit mask offset")?;
-                    // channel_group invalid bits calculation (only for DIBlocks)
-                    for channel_group in dg.cg.values_mut() {
-                        channel_group
-                            .process_all_channel_invalid_bits()
-                            .context("failed processing all channel invalid bits")?;
-                    }
-                    // conversion of all channels to physical values
-                    convert_all_channels(dg, &info.sharable)
-                        .context("failed converting all channels")?;
+                        options,
+                    )?;
                 }
             }
         }
@@ -92,16 +74,264 @@ pub fn mdfreader4<'a>(
     Ok(())
 }
 
+/// Reads the file data groups in parallel, each worker thread having its own file handle.
+/// Data groups are read by batches whose estimated records size is within options memory budget
+pub fn mdfreader4_parallel(
+    info: &mut MdfInfo4,
+    channel_names: &HashSet<String>,
+    options: &ReadOptions,
+) -> Result<(), Error> {
+    let MdfInfo4 {
+        file_name,
+        dg,
+        sharable,
+        ..
+    } = info;
+    let file_name = &*file_name;
+    let sharable = &*sharable;
+    // batches of data groups to read
+    let mut batches: Vec<Vec<(&mut Dg4, HashSet<String>)>> = Vec::new();
+    let mut batch_size: usize = 0;
+    for dg in dg.values_mut() {
+        let channel_names_to_read_in_dg = channel_names_to_read(dg, channel_names);
+        if dg.block.dg_data == 0 || channel_names_to_read_in_dg.is_empty() {
+            continue;
+        }
+        let dg_size: usize = dg
+            .cg
+            .values()
+            .map(|cg| cg.block.cg_cycle_count as usize * cg.record_length as usize)
+            .sum();
+        match batches.last_mut() {
+            Some(batch) if batch_size.saturating_add(dg_size) <= options.memory_budget => {
+                batch.push((dg, channel_names_to_read_in_dg));
+                batch_size += dg_size;
+            }
+            _ => {
+                batches.push(vec![(dg, channel_names_to_read_in_dg)]);
+                batch_size = dg_size;
+            }
+        }
+    }
+    for batch in batches {
+        batch
+            .into_par_iter()
+            .try_for_each(|(dg, channel_names_to_read_in_dg)| -> Result<()> {
+                let f: File = OpenOptions::new()
+                    .read(true)
+                    .write(false)
+                    .open(file_name)
+                    .map_err(MdfError::Io)
+                    .with_context(|| format!("Cannot find the file {file_name}"))?;
+                let mut rdr = BufReader::new(&f);
+                let mut decoder: Dec = Dec {
+                    windows_1252: WINDOWS_1252.new_decoder(),
+                    utf_16_be: UTF_16BE.new_decoder(),
+                    utf_16_le: UTF_16LE.new_decoder(),
+                    string_policy: options.string_policy,
+                };
+                read_dg(
+                    &mut rdr,
+                    0,
+                    dg,
+                    sharable,
+                    &channel_names_to_read_in_dg,
+                    &mut decoder,
+                    options,
+                )?;
+                Ok(())
+            })?;
+    }
+    Ok(())
+}
+
+/// returns the channel names of the data group to be read
+fn channel_names_to_read(dg: &Dg4, channel_names: &HashSet<String>) -> HashSet<String> {
+    let mut channel_names_present_in_dg = HashSet::new();
+    for channel_group in dg.cg.values() {
+        let cn = channel_group.channel_names.clone();
+        channel_names_present_in_dg.par_extend(cn);
+    }
+    channel_names_present_in_dg
+        .into_par_iter()
+        .filter(|v| channel_names.contains(v))
+        .collect()
+}
+
+/// Reads the data of a data group, applies bit masks, invalid bits and conversions.
+/// Returns the reader position
+fn read_dg<R: Read + Seek>(
+    rdr: &mut BufReader<R>,
+    position: i64,
+    dg: &mut Dg4,
+    sharable: &SharableBlocks,
+    channel_names_to_read_in_dg: &HashSet<String>,
+    decoder: &mut Dec,
+    options: &ReadOptions,
+) -> Result<i64> {
+    // header block
+    rdr.seek_relative(dg.block.dg_data - position)
+        .context("Could not position buffer")?; // change buffer position
+    let mut id = [0u8; 4];
+    rdr.read_exact(&mut id).context("could not read block id")?;
+    let sorted = dg.cg.len() == 1;
+    let position = read_data(
+        rdr,
+        id,
+        dg,
+        dg.block.dg_data,
+        sorted,
+        channel_names_to_read_in_dg,
+        decoder,
+        options.chunk_size,
+    )
+    .with_context(|| format!("failed reading data for dg {:?}", dg))?;
+    apply_bit_mask_offset(dg, channel_names_to_read_in_dg)
+        .context("failed applying bit mask offset")?;
+    // channel_group invalid bits calculation (only for DIBlocks)
+    for channel_group in dg.cg.values_mut() {
+        channel_group
+            .process_all_channel_invalid_bits()
+            .context("failed processing all channel invalid bits")?;
+    }
+    // conversion of all channels to physical values
+    convert_all_channels(dg, sharable, channel_names_to_read_in_dg, options)
+        .context("failed converting all channels")?;
+    if let Some(directory) = &options.spill_directory {
+        spill_dg(dg, directory, channel_names_to_read_in_dg)
+            .context("failed spilling data group data to disk")?;
+    }
+    Ok(position)
+}
+
+/// moves the converted data of the channels read into spill files of directory,
+/// one per channel group and data length. Channels with deferred conversion and
+/// arrays are kept in memory
+fn spill_dg(dg: &mut Dg4, directory: &Path, channel_names: &HashSet<String>) -> Result<()> {
+    for cg in dg.cg.values_mut() {
+        let mut by_length: BTreeMap<usize, Vec<&mut Cn4>> = BTreeMap::new();
+        for cn in cg.cn.values_mut().filter(|cn| {
+            channel_names.contains(&cn.unique_name)
+                && cn.conversion_cache.is_none()
+                && is_spillable(&cn.data)
+        }) {
+            by_length.entry(cn.data.len()).or_default().push(cn);
+        }
+        for channels in by_length.into_values() {
+            let data: Vec<&ChannelData> = channels.iter().map(|cn| &cn.data).collect();
+            let spilled = spill(directory, &data)?;
+            for (cn, spilled) in channels.into_iter().zip(spilled) {
+                cn.data =
+                    cn.data
+                        .zeros(cn.block.cn_data_type, 0, 0, (Vec::new(), Order::RowMajor))?;
+                cn.spilled = Some(spilled);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Reads only the records of the channels with master values between t_start and t_stop.
+/// Each channel group is read with ChannelGroupIterator, skipping the data blocks out of range
+pub fn mdfreader4_between(
+    info: &mut MdfInfo4,
+    channel_names: &HashSet<String>,
+    t_start: f64,
+    t_stop: f64,
+) -> Result<(), Error> {
+    read_groups_between(info, channel_names, |info, channel_name, names| {
+        ChannelGroupIterator::new(info, channel_name)?
+            .with_channel_names(names)
+            .with_time_range(t_start, t_stop)
+            .read_all()
+    })
+}
+
+/// Reads from reader only the records of the channels with master values between t_start
+/// and t_stop, skipping the data blocks out of range without index file
+pub fn mdfreader4_between_from_reader<R: Read + Seek>(
+    info: &mut MdfInfo4,
+    mut reader: R,
+    channel_names: &HashSet<String>,
+    t_start: f64,
+    t_stop: f64,
+) -> Result<(), Error> {
+    read_groups_between(info, channel_names, |info, channel_name, names| {
+        ChannelGroupIterator::from_reader(info, channel_name, &mut reader)?
+            .with_channel_names(names)
+            .with_time_range(t_start, t_stop)
+            .read_all()
+    })
+}
+
+/// reads each channel group of the channels with read_group, given a channel of the group
+/// and the requested channels of the group, and stores the data read
+fn read_groups_between<F>(
+    info: &mut MdfInfo4,
+    channel_names: &HashSet<String>,
+    mut read_group: F,
+) -> Result<(), Error>
+where
+    F: FnMut(&MdfInfo4, &str, &HashSet<String>) -> Result<HashMap<String, ChannelData>>,
+{
+    // groups requested channels by channel group
+    let mut groups: HashMap<(i64, u64), HashSet<String>> = HashMap::new();
+    for channel_name in channel_names {
+        if let Some((_master, dg_pos, (_cg_pos, rec_id), (_cn_pos, _rec_pos))) =
+            info.get_channel_id(channel_name)
+        {
+            groups
+                .entry((*dg_pos, *rec_id))
+                .or_default()
+                .insert(channel_name.clone());
+        }
+    }
+    for ((dg_pos, rec_id), names) in groups {
+        let Some(channel_name) = names.iter().next() else {
+            continue;
+        };
+        let mut data = read_group(info, channel_name, &names).with_context(|| {
+            format!(
+                "failed reading time range of channel group of {}",
+                channel_name
+            )
+        })?;
+        if let Some(cg) = info
+            .dg
+            .get_mut(&dg_pos)
+            .and_then(|dg| dg.cg.get_mut(&rec_id))
+        {
+            for cn in cg.cn.values_mut() {
+                if let Some(channel_data) = data.remove(&cn.unique_name) {
+                    cn.data = channel_data;
+                    cn.raw_data = None;
+                    cn.status = None;
+                    cn.conversion_cache = None;
+                    cn.spilled = None;
+                } else if names.contains(&cn.unique_name) {
+                    warn!(
+                        "channel {} could not be read within time range",
+                        cn.unique_name
+                    );
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
 /// Reads all kind of data layout : simple DT or DV, sorted or unsorted, Data List,
 /// compressed data blocks DZ or Sample DATA
-fn read_data(
-    rdr: &mut BufReader<&File>,
+#[allow(clippy::too_many_arguments)]
+fn read_data<R: Read + Seek>(
+    rdr: &mut BufReader<R>,
     id: [u8; 4],
     dg: &mut Dg4,
     mut position: i64,
     sorted: bool,
     channel_names_to_read_in_dg: &HashSet<String>,
     decoder: &mut Dec,
+    chunk_size: usize,
 ) -> Result<i64> {
     // block header is already read
     let mut vlsd_channels: Vec<i32> = Vec::new();
@@ -115,9 +345,14 @@ fn read_data(
             if sorted {
                 // sorted data group
                 for channel_group in dg.cg.values_mut() {
-                    vlsd_channels =
-                        read_all_channels_sorted(rdr, channel_group, channel_names_to_read_in_dg)
-                            .context("failed reading all channels sorted")?;
+                    vlsd_channels = read_all_channels_sorted(
+                        rdr,
+                        channel_group,
+                        channel_names_to_read_in_dg,
+                        chunk_size,
+                        decoder.string_policy,
+                    )
+                    .context("failed reading all channels sorted")?;
                     position += block_header.len as i64;
                 }
                 if !vlsd_channels.is_empty() {
@@ -147,6 +382,8 @@ fn read_data(
                     dg,
                     block_header.len as i64,
                     channel_names_to_read_in_dg,
+                    chunk_size,
+                    decoder.string_policy,
                 )
                 .context("failed reading all channels unsorted")?;
                 position += block_header.len as i64;
@@ -163,6 +400,7 @@ fn read_data(
                         &data,
                         channel_group,
                         channel_names_to_read_in_dg,
+                        decoder.string_policy,
                     )
                     .context("failed reading all channels sorted from bytes")?;
                 }
@@ -227,6 +465,7 @@ fn read_data(
                 sorted,
                 channel_names_to_read_in_dg,
                 decoder,
+                chunk_size,
             )
             .context("failed reading data from HL block")?;
         }
@@ -236,7 +475,30 @@ fn read_data(
             if sorted {
                 // sorted data group
                 for channel_group in dg.cg.values_mut() {
+                    // initialises the arrays
+                    initialise_arrays(
+                        channel_group,
+                        &channel_group.block.cg_cycle_count.clone(),
+                        channel_names_to_read_in_dg,
+                    )
+                    .context("failed initialising arrays for sorted dl4 block")?;
                     let (dl_blocks, pos) = parser_dl4(rdr, position)?;
+                    if is_column(channel_group) && channel_group.block.cg_inval_bytes == 0 {
+                        let data_pointers: Vec<i64> = dl_blocks
+                            .iter()
+                            .flat_map(|dl| dl.dl_data.iter().copied())
+                            .collect();
+                        position = read_column(
+                            rdr,
+                            pos,
+                            &data_pointers,
+                            channel_group,
+                            channel_names_to_read_in_dg,
+                            decoder.string_policy,
+                        )
+                        .context("failed reading column from DL4 blocks")?;
+                        continue;
+                    }
                     let (pos, vlsd) = parser_dl4_sorted(
                         rdr,
                         dl_blocks,
@@ -273,8 +535,15 @@ fn read_data(
                     .context("failed intialising arrays")?;
                 }
                 let (dl_blocks, pos) = parser_dl4(rdr, position)?;
-                let pos = parser_dl4_unsorted(rdr, dg, dl_blocks, pos, channel_names_to_read_in_dg)
-                    .context("failed parsing DL4 block unsorted")?;
+                let pos = parser_dl4_unsorted(
+                    rdr,
+                    dg,
+                    dl_blocks,
+                    pos,
+                    channel_names_to_read_in_dg,
+                    decoder.string_policy,
+                )
+                .context("failed parsing DL4 block unsorted")?;
                 position = pos;
             }
         }
@@ -282,8 +551,14 @@ fn read_data(
             // ##LD
             // list data, cannot be used for unsorted data
             for channel_group in dg.cg.values_mut() {
-                let pos = parser_ld4(rdr, position, channel_group, channel_names_to_read_in_dg)
-                    .context("failed parsing DL4 block")?;
+                let pos = parser_ld4(
+                    rdr,
+                    position,
+                    channel_group,
+                    channel_names_to_read_in_dg,
+                    decoder.string_policy,
+                )
+                .context("failed parsing DL4 block")?;
                 position = pos;
             }
         }
@@ -295,8 +570,34 @@ fn read_data(
                 .read_le()
                 .context("could not read into Dv4Block structure")?;
             for channel_group in dg.cg.values_mut() {
-                read_all_channels_sorted(rdr, channel_group, channel_names_to_read_in_dg)
+                if is_column(channel_group) {
+                    // single channel, the block is read at once without record chunking
+                    initialise_arrays(
+                        channel_group,
+                        &channel_group.block.cg_cycle_count.clone(),
+                        channel_names_to_read_in_dg,
+                    )
+                    .context("failed initialising arrays for DV block")?;
+                    let mut data = vec![0u8; (block_header.len - 24) as usize];
+                    rdr.read_exact(&mut data)
+                        .context("Could not read DV block data")?;
+                    read_column_from_bytes(
+                        data,
+                        channel_group,
+                        channel_names_to_read_in_dg,
+                        decoder.string_policy,
+                    )
+                    .context("failed reading column from DV block")?;
+                } else {
+                    read_all_channels_sorted(
+                        rdr,
+                        channel_group,
+                        channel_names_to_read_in_dg,
+                        chunk_size,
+                        decoder.string_policy,
+                    )
                     .context("failed reading all channels sorted")?;
+                }
             }
             position += block_header.len as i64;
         }
@@ -304,7 +605,7 @@ fn read_data(
             // ##DG
             bail!("Weird, a DG block type {:?}", id) // should never happen
         }
-        _ => bail!("Unknown data block type {:?}", id), // should never happen
+        _ => bail!(MdfError::UnsupportedBlock(block_id_string(&id))), // should never happen
     }
     Ok(position)
 }
@@ -312,7 +613,7 @@ fn read_data(
 /// Header List block reader
 /// This HL Block references Data List Blocks that are listing DZ Blocks
 /// It is existing to add complementary information about compression in DZ
-fn read_hl(rdr: &mut BufReader<&File>, mut position: i64) -> Result<(i64, [u8; 4])> {
+fn read_hl<R: Read + Seek>(rdr: &mut BufReader<R>, mut position: i64) -> Result<(i64, [u8; 4])> {
     // compressed data in datal list
     let block: Hl4Block = rdr.read_le().context("could not read HL block")?;
     position += block.hl_len as i64;
@@ -327,8 +628,8 @@ fn read_hl(rdr: &mut BufReader<&File>, mut position: i64) -> Result<(i64, [u8; 4
 }
 
 /// Reads Signal Data Block containing VLSD channel, pointed by cn_data
-fn read_sd(
-    rdr: &mut BufReader<&File>,
+fn read_sd<R: Read + Seek>(
+    rdr: &mut BufReader<R>,
     dg: &mut Dg4,
     vlsd_channels: &[i32],
     mut position: i64,
@@ -417,14 +718,14 @@ fn read_vlsd_from_bytes(
         ChannelData::Complex64(_) => {}
         ChannelData::Utf8(array) => {
             if cn.block.cn_data_type == 6 {
-                while remaining > 0 {
+                while remaining >= std::mem::size_of::<u32>() {
                     let len = &data[position..position + std::mem::size_of::<u32>()];
                     let length: usize =
                         u32::from_le_bytes(len.try_into().context("Could not read length")?)
                             as usize;
                     if (position + length + 4) <= data_length {
                         position += std::mem::size_of::<u32>();
-                        let record = &data[position..position + length - 1]; // do not take null terminated character
+                        let record = &data[position..position + length.saturating_sub(1)]; // do not take null terminated character
                         let mut dst = String::with_capacity(record.len());
                         let (_result, _size, _replacement) = decoder
                             .windows_1252
@@ -435,38 +736,30 @@ fn read_vlsd_from_bytes(
                         nrecord += 1;
                     } else {
                         remaining = data_length - position;
-                        // copies tail part at beginnning of vect
-                        data.copy_within(position.., 0);
-                        // clears the last part
-                        data.truncate(remaining);
                         break;
                     }
                 }
             } else if cn.block.cn_data_type == 7 {
-                while remaining > 0 {
+                while remaining >= std::mem::size_of::<u32>() {
                     let len = &data[position..position + std::mem::size_of::<u32>()];
                     let length: usize =
                         u32::from_le_bytes(len.try_into().context("Could not read length")?)
                             as usize;
                     if (position + length + 4) <= data_length {
                         position += std::mem::size_of::<u32>();
-                        let record = &data[position..position + length - 1]; // do not take null terminated character
-                        let dst = str::from_utf8(record).context("Found invalid UTF-8")?;
+                        let record = &data[position..position + length.saturating_sub(1)]; // do not take null terminated character
+                        let dst = decoder.string_policy.decode_utf8(record)?;
                         array.append_value(dst);
                         position += length;
                         remaining = data_length - position;
                         nrecord += 1;
                     } else {
                         remaining = data_length - position;
-                        // copies tail part at beginnning of vect
-                        data.copy_within(position.., 0);
-                        // clears the last part
-                        data.truncate(remaining);
                         break;
                     }
                 }
             } else if cn.block.cn_data_type == 8 {
-                while remaining > 0 {
+                while remaining >= std::mem::size_of::<u32>() {
                     let len = &data[position..position + std::mem::size_of::<u32>()];
                     let length: usize =
                         u32::from_le_bytes(len.try_into().context("Could not read length")?)
@@ -483,15 +776,11 @@ fn read_vlsd_from_bytes(
                         nrecord += 1;
                     } else {
                         remaining = data_length - position;
-                        // copies tail part at beginnning of vect
-                        data.copy_within(position.., 0);
-                        // clears the last part
-                        data.truncate(remaining);
                         break;
                     }
                 }
             } else if cn.block.cn_data_type == 9 {
-                while remaining > 0 {
+                while remaining >= std::mem::size_of::<u32>() {
                     let len = &data[position..position + std::mem::size_of::<u32>()];
                     let length: usize =
                         u32::from_le_bytes(len.try_into().context("Could not read length")?)
@@ -508,20 +797,21 @@ fn read_vlsd_from_bytes(
                         nrecord += 1;
                     } else {
                         remaining = data_length - position;
-                        // copies tail part at beginnning of vect
-                        data.copy_within(position.., 0);
-                        // clears the last part
-                        data.truncate(remaining);
                         break;
                     }
                 }
             };
             if remaining == 0 {
                 data.clear()
+            } else {
+                // copies tail part at beginnning of vect
+                data.copy_within(position.., 0);
+                // clears the last part
+                data.truncate(remaining);
             }
         }
         ChannelData::VariableSizeByteArray(array) => {
-            while remaining > 0 {
+            while remaining >= std::mem::size_of::<u32>() {
                 let len = &data[position..position + std::mem::size_of::<u32>()];
                 let length: usize =
                     u32::from_le_bytes(len.try_into().context("Could not read length")?) as usize;
@@ -534,15 +824,16 @@ fn read_vlsd_from_bytes(
                     nrecord += 1;
                 } else {
                     remaining = data_length - position;
-                    // copies tail part at beginnning of vect
-                    data.copy_within(position.., 0);
-                    // clears the last part
-                    data.truncate(remaining);
                     break;
                 }
             }
             if remaining == 0 {
                 data.clear()
+            } else {
+                // copies tail part at beginnning of vect
+                data.copy_within(position.., 0);
+                // clears the last part
+                data.truncate(remaining);
             }
         }
         ChannelData::FixedSizeByteArray(_) => {}
@@ -556,16 +847,18 @@ fn read_vlsd_from_bytes(
         ChannelData::ArrayDInt64(_) => {}
         ChannelData::ArrayDUInt64(_) => {}
         ChannelData::ArrayDFloat64(_) => {}
+        ChannelData::Dictionary(_) | ChannelData::Boolean(_) => {}
     }
     Ok(nrecord + previous_index)
 }
 
 /// Reads all DL Blocks and returns a vect of them
-fn parser_ld4(
-    rdr: &mut BufReader<&File>,
+fn parser_ld4<R: Read + Seek>(
+    rdr: &mut BufReader<R>,
     mut position: i64,
     channel_group: &mut Cg4,
     channel_names_to_read_in_dg: &HashSet<String>,
+    string_policy: StringPolicy,
 ) -> Result<i64> {
     let mut ld_blocks: Vec<Ld4Block> = Vec::new();
     let (block, pos) = parser_ld4_block(rdr, position, position)?;
@@ -585,67 +878,39 @@ fn parser_ld4(
         ld_blocks.push(block.clone());
         next_ld = block.ld_ld_next();
     }
-    if ld_blocks.len() == 1 && ld_blocks[0].ld_data().len() == 1 && channel_group.cn.len() == 1 {
-        // only one DV block, reading can be optimised
-        // Reads DV or DZ block id
-        let ld_data = ld_blocks[0].ld_data()[0];
-        rdr.seek_relative(ld_data - position)
-            .context("Could not reach DV or DZ block position from LD block")?;
-        let mut id = [0u8; 4];
-        rdr.read_exact(&mut id)
-            .context("could not read data block id from ld4 invalid")?;
+    if is_column(channel_group) {
+        // single channel, DV blocks are concatenated and read at once
         initialise_arrays(
             channel_group,
             &channel_group.block.cg_cycle_count.clone(),
             channel_names_to_read_in_dg,
         )
         .context("failed initialising arrays")?;
-        if id == "##DZ".as_bytes() {
-            let (dt, block_header) =
-                parse_dz(rdr).context("failed parsing dz block pointed by ld4 block")?;
-            for (_rec_pos, cn) in channel_group.cn.iter_mut() {
-                read_one_channel_array(&dt, cn, channel_group.block.cg_cycle_count as usize)
-                    .context("failed reading one channel array from DZ")?;
-            }
-            position = ld_data + block_header.len as i64;
-        } else {
-            let block_header: Dt4Block = rdr.read_le().context("Could not read DV block header")?;
-            let mut buf = vec![0u8; block_header.len as usize - 24];
-            rdr.read_exact(&mut buf)
-                .context("Could not read Dt4 block")?;
-            for (_rec_pos, cn) in channel_group.cn.iter_mut() {
-                read_one_channel_array(&buf, cn, channel_group.block.cg_cycle_count as usize)
-                    .context("failed reading one channel array")?;
-            }
-            position = ld_data + block_header.len as i64;
-        }
-        if channel_group.block.cg_inval_bytes > 0 {
-            // Reads invalid DI or DZ block
-            let ld_invalid_data_vec = ld_blocks[0].ld_invalid_data();
-            let ld_invalid_data = if !ld_invalid_data_vec.is_empty() {
-                ld_invalid_data_vec[0]
-            } else {
-                bail!("no invalid block (di or dz) pointer found in ld4 block")
-            };
-            rdr.seek_relative(ld_invalid_data - position)
-                .context("Could not reach DI or DZ block position")?;
-            let mut id = [0u8; 4];
-            rdr.read_exact(&mut id)
-                .context("could not read data block id from ld4 invalid")?;
-            if id == "##DZ".as_bytes() {
-                let (dt, block_header) = parse_dz(rdr)?;
-                channel_group.invalid_bytes = Some(dt);
-                position = ld_invalid_data + block_header.len as i64;
-            } else {
-                let block_header: Dt4Block = rdr
-                    .read_le()
-                    .context("Could not read into DZ or DI block header")?;
-                let mut buf = vec![0u8; (block_header.len - 24) as usize];
-                rdr.read_exact(&mut buf)
-                    .context("Could not read data block")?;
-                channel_group.invalid_bytes = Some(buf);
-                position = ld_invalid_data + block_header.len as i64;
+        let data_pointers: Vec<i64> = ld_blocks.iter().flat_map(|ld| ld.ld_data()).collect();
+        position = read_column(
+            rdr,
+            position,
+            &data_pointers,
+            channel_group,
+            channel_names_to_read_in_dg,
+            string_policy,
+        )
+        .context("failed reading column from LD4 blocks")?;
+        let invalid_pointers: Vec<i64> = ld_blocks
+            .iter()
+            .flat_map(|ld| ld.ld_invalid_data())
+            .collect();
+        if channel_group.block.cg_inval_bytes > 0 && !invalid_pointers.is_empty() {
+            // Reads invalid DI or DZ blocks, some implementations are pre allocating them
+            let length = channel_group.block.cg_inval_bytes as usize
+                * channel_group.block.cg_cycle_count as usize;
+            let mut invalid = Vec::with_capacity(length);
+            for data_pointer in invalid_pointers {
+                position = append_block_data(rdr, position, data_pointer, &mut invalid)
+                    .context("failed reading invalid block pointed by ld4 block")?;
             }
+            invalid.resize(length, 0);
+            channel_group.invalid_bytes = Some(invalid);
         }
     } else {
         // several DV, LD or channels per DG
@@ -655,18 +920,120 @@ fn parser_ld4(
             channel_group,
             ld_blocks,
             channel_names_to_read_in_dg,
+            string_policy,
         )?;
     }
     Ok(position)
 }
 
+/// true if the channel group has a single fixed length channel and no record id, its data
+/// blocks containing only the channel values one after the other (column storage)
+fn is_column(channel_group: &Cg4) -> bool {
+    let mut channels = channel_group.cn.values();
+    match (channels.next(), channels.next()) {
+        (Some(cn), None) => {
+            matches!(cn.block.cn_type, 0 | 2 | 4)
+                && cn.pos_byte_beg == 0
+                && channel_group.block.cg_data_bytes as usize == cn.n_bytes as usize * cn.list_size
+                && channel_group.record_length
+                    == channel_group.block.cg_data_bytes + channel_group.block.cg_inval_bytes
+        }
+        _ => false,
+    }
+}
+
+/// appends the content of the DT, DV, DI or DZ block at data_pointer to data,
+/// returns the reader position
+fn append_block_data<R: Read + Seek>(
+    rdr: &mut BufReader<R>,
+    position: i64,
+    data_pointer: i64,
+    data: &mut Vec<u8>,
+) -> Result<i64> {
+    rdr.seek_relative(data_pointer - position)
+        .context("Could not reach data block position")?;
+    let mut id = [0u8; 4];
+    rdr.read_exact(&mut id)
+        .context("could not read data block id")?;
+    if id == "##DZ".as_bytes() {
+        let (dt, block_header) = parse_dz(rdr)?;
+        if data.is_empty() {
+            *data = dt;
+        } else {
+            data.extend_from_slice(&dt);
+        }
+        Ok(data_pointer + block_header.len as i64)
+    } else {
+        let block_header: Dt4Block = rdr.read_le().context("Could not read data block header")?;
+        let start = data.len();
+        data.resize(start + (block_header.len - 24) as usize, 0);
+        rdr.read_exact(&mut data[start..])
+            .context("Could not read data block")?;
+        Ok(data_pointer + block_header.len as i64)
+    }
+}
+
+/// reads the channel of a column channel group from its data blocks concatenated,
+/// without record chunking. Arrays are initialised by caller. Returns the reader position
+fn read_column<R: Read + Seek>(
+    rdr: &mut BufReader<R>,
+    mut position: i64,
+    data_pointers: &[i64],
+    channel_group: &mut Cg4,
+    channel_names_to_read_in_dg: &HashSet<String>,
+    string_policy: StringPolicy,
+) -> Result<i64> {
+    let mut data = Vec::with_capacity(
+        channel_group.block.cg_data_bytes as usize * channel_group.block.cg_cycle_count as usize,
+    );
+    for data_pointer in data_pointers {
+        position = append_block_data(rdr, position, *data_pointer, &mut data)?;
+    }
+    read_column_from_bytes(
+        data,
+        channel_group,
+        channel_names_to_read_in_dg,
+        string_policy,
+    )?;
+    Ok(position)
+}
+
+/// copies the values of a column channel group into its channel array
+fn read_column_from_bytes(
+    mut data: Vec<u8>,
+    channel_group: &mut Cg4,
+    channel_names_to_read_in_dg: &HashSet<String>,
+    string_policy: StringPolicy,
+) -> Result<()> {
+    let cycle_count = channel_group.block.cg_cycle_count as usize;
+    let length = channel_group.block.cg_data_bytes as usize * cycle_count;
+    if data.len() < length {
+        bail!(MdfError::CorruptData(format!(
+            "{} bytes of column data instead of {length}",
+            data.len()
+        )));
+    }
+    // some implementations are pre allocating equal length blocks
+    data.truncate(length);
+    for cn in channel_group
+        .cn
+        .values_mut()
+        .filter(|cn| channel_names_to_read_in_dg.contains(&cn.unique_name))
+    {
+        read_one_channel_array(&data, cn, cycle_count, string_policy)
+            .with_context(|| format!("failed reading channel {} array", cn.unique_name))?;
+    }
+    Ok(())
+}
+
 /// reads DV and DI block containing several channels
-fn read_dv_di(
-    rdr: &mut BufReader<&File>,
+fn read_dv_di<R: Read + Seek>(
+    rdr: &mut BufReader<R>,
     mut position: i64,
     channel_group: &mut Cg4,
     ld_blocks: Vec<Ld4Block>,
     channel_names_to_read_in_dg: &HashSet<String>,
+    string_policy: StringPolicy,
 ) -> Result<i64, Error> {
     let cg_cycle_count = channel_group.block.cg_cycle_count as usize;
     let cg_inval_bytes = channel_group.block.cg_inval_bytes as usize;
@@ -712,7 +1079,8 @@ fn read_dv_di(
                 position = data_pointer + block_header.len as i64;
             }
             // Copies full sized records in block into channels arrays
-            let record_length = channel_group.record_length as usize;
+            // DV blocks do not contain invalid bytes, stored separately in DI blocks
+            let record_length = channel_group.record_length as usize - cg_inval_bytes;
             let n_record_chunk = block_length / record_length;
             if previous_index + n_record_chunk < cg_cycle_count {
                 read_channels_from_bytes(
@@ -722,17 +1090,19 @@ fn read_dv_di(
                     previous_index,
                     channel_names_to_read_in_dg,
                     false,
+                    string_policy,
                 )
                 .context("failed reading channels from dv di blocks")?;
             } else {
                 // Some implementation are pre allocating equal length blocks
                 read_channels_from_bytes(
-                    &data[..record_length * (cg_cycle_count - previous_index)],
+                    &data[..record_length * cg_cycle_count.saturating_sub(previous_index)],
                     &mut channel_group.cn,
                     record_length,
                     previous_index,
                     channel_names_to_read_in_dg,
                     false,
+                    string_policy,
                 )
                 .context("failed reading channels from from dv di blocks")?;
             }
@@ -773,10 +1143,11 @@ fn read_dv_di(
                 block_length = (block_header.len - 24) as usize;
                 position = data_pointer + block_header.len as i64;
             }
-            // Copies invalid data
+            // Copies invalid data, some implementations are pre allocating equal length blocks
             if let Some(invalid) = &mut channel_group.invalid_bytes {
+                let block_length = block_length.min(invalid.len() - previous_invalid_pos);
                 invalid[previous_invalid_pos..previous_invalid_pos + block_length]
-                    .copy_from_slice(&invalid_data);
+                    .copy_from_slice(&invalid_data[..block_length]);
                 previous_invalid_pos += block_length;
             }
             invalid_data.clear();
@@ -786,7 +1157,10 @@ fn read_dv_di(
 }
 
 /// Reads all DL Blocks and returns a vect of them
-fn parser_dl4(rdr: &mut BufReader<&File>, mut position: i64) -> Result<(Vec<Dl4Block>, i64)> {
+fn parser_dl4<R: Read + Seek>(
+    rdr: &mut BufReader<R>,
+    mut position: i64,
+) -> Result<(Vec<Dl4Block>, i64)> {
     let mut dl_blocks: Vec<Dl4Block> = Vec::new();
     let (block, pos) = parser_dl4_block(rdr, position, position)?;
     position = pos;
@@ -808,8 +1182,8 @@ fn parser_dl4(rdr: &mut BufReader<&File>, mut position: i64) -> Result<(Vec<Dl4B
 }
 
 /// Reads all sorted data blocks pointed by DL4 Blocks
-fn parser_dl4_sorted(
-    rdr: &mut BufReader<&File>,
+fn parser_dl4_sorted<R: Read + Seek>(
+    rdr: &mut BufReader<R>,
     dl_blocks: Vec<Dl4Block>,
     mut position: i64,
     channel_group: &mut Cg4,
@@ -817,13 +1191,7 @@ fn parser_dl4_sorted(
     rec_pos: &i32,
     channel_names_to_read_in_dg: &HashSet<String>,
 ) -> Result<(i64, Vec<i32>)> {
-    // initialises the arrays
-    initialise_arrays(
-        channel_group,
-        &channel_group.block.cg_cycle_count.clone(),
-        channel_names_to_read_in_dg,
-    )
-    .context("failed initialising arrays for sorted dl4 block")?;
+    // arrays are initialised by caller, SD blocks are appended to already read channel group
     // Read all data blocks
     let mut data: Vec<u8> = Vec::new();
     let mut previous_index: usize = 0;
@@ -870,17 +1238,19 @@ fn parser_dl4_sorted(
                         previous_index,
                         channel_names_to_read_in_dg,
                         true,
+                        decoder.string_policy,
                     )
                     .context("could not read channels from bytes")?;
                 } else {
                     // Some implementation are pre allocating equal length blocks
                     vlsd_channels = read_channels_from_bytes(
-                        &data[..record_length * (cg_cycle_count - previous_index)],
+                        &data[..record_length * cg_cycle_count.saturating_sub(previous_index)],
                         &mut channel_group.cn,
                         record_length,
                         previous_index,
                         channel_names_to_read_in_dg,
                         true,
+                        decoder.string_policy,
                     )
                     .context("could not read channels from bytes")?;
                 }
@@ -902,12 +1272,13 @@ fn parser_dl4_sorted(
 }
 
 /// Reads all unsorted data blocks pointed by DL4 Blocks
-fn parser_dl4_unsorted(
-    rdr: &mut BufReader<&File>,
+fn parser_dl4_unsorted<R: Read + Seek>(
+    rdr: &mut BufReader<R>,
     dg: &mut Dg4,
     dl_blocks: Vec<Dl4Block>,
     mut position: i64,
     channel_names_to_read_in_dg: &HashSet<String>,
+    string_policy: StringPolicy,
 ) -> Result<i64> {
     // Read all data blocks
     let mut data: Vec<u8> = Vec::new();
@@ -915,6 +1286,7 @@ fn parser_dl4_unsorted(
         windows_1252: WINDOWS_1252.new_decoder(),
         utf_16_be: UTF_16BE.new_decoder(),
         utf_16_le: UTF_16LE.new_decoder(),
+        string_policy,
     };
     // initialise record counter
     let mut record_counter: HashMap<u64, (usize, Vec<u8>)> = HashMap::new();
@@ -925,20 +1297,20 @@ fn parser_dl4_unsorted(
         for data_pointer in dl.dl_data {
             rdr.seek_relative(data_pointer - position)
                 .context("Could not reach DT or DZ position from DL")?;
-            let mut buf = [0u8; 24];
-            rdr.read_exact(&mut buf)
-                .context("could not read blockheader4 Id")?;
-            let mut block = Cursor::new(buf);
-            let header: Blockheader4 = block.read_le().context("could not parse blockheader4")?;
-            if header.hdr_id == "##DZ".as_bytes() {
-                let (dt, _block) = parse_dz(rdr)?;
+            let mut id = [0u8; 4];
+            rdr.read_exact(&mut id).context("could not read block id")?;
+            let block_length = if id == "##DZ".as_bytes() {
+                let (dt, block) = parse_dz(rdr)?;
                 data.extend(dt);
+                block.len
             } else {
-                let mut buf = vec![0u8; (header.hdr_len - 24) as usize];
+                let block: Dt4Block = rdr.read_le().context("could not read DT block")?;
+                let mut buf = vec![0u8; (block.len - 24) as usize];
                 rdr.read_exact(&mut buf)
                     .context("Could not read DT block data")?;
                 data.extend(buf);
-            }
+                block.len
+            };
             // saves records as much as possible
             read_all_channels_unsorted_from_bytes(
                 &mut data,
@@ -947,17 +1319,18 @@ fn parser_dl4_unsorted(
                 &mut decoder,
                 channel_names_to_read_in_dg,
             )?;
-            position = data_pointer + header.hdr_len as i64;
+            position = data_pointer + block_length as i64;
         }
     }
     Ok(position)
 }
 
 /// Returns chunk size and corresponding number of records from a channel group
-fn generate_chunks(channel_group: &Cg4) -> Vec<(usize, usize)> {
+fn generate_chunks(channel_group: &Cg4, chunk_size: usize) -> Vec<(usize, usize)> {
     let record_length = channel_group.record_length as usize;
     let cg_cycle_count = channel_group.block.cg_cycle_count as usize;
-    let n_chunks = (record_length * cg_cycle_count) / CHUNK_SIZE_READING_4 + 1; // number of chunks
+    let chunk_size = chunk_size.max(record_length).max(1); // at least one record per chunk
+    let n_chunks = (record_length * cg_cycle_count) / chunk_size + 1; // number of chunks
     let chunk_length = (record_length * cg_cycle_count) / n_chunks; // chunks length
     let n_record_chunk = chunk_length / record_length; // number of records in chunk
     let chunck = (n_record_chunk, record_length * n_record_chunk);
@@ -970,12 +1343,14 @@ fn generate_chunks(channel_group: &Cg4) -> Vec<(usize, usize)> {
 }
 
 /// Reads all channels from given channel group having sorted data blocks
-fn read_all_channels_sorted(
-    rdr: &mut BufReader<&File>,
+fn read_all_channels_sorted<R: Read + Seek>(
+    rdr: &mut BufReader<R>,
     channel_group: &mut Cg4,
     channel_names_to_read_in_dg: &HashSet<String>,
+    chunk_size: usize,
+    string_policy: StringPolicy,
 ) -> Result<Vec<i32>> {
-    let chunks = generate_chunks(channel_group);
+    let chunks = generate_chunks(channel_group, chunk_size);
     // initialises the arrays
     initialise_arrays(
         channel_group,
@@ -997,6 +1372,7 @@ fn read_all_channels_sorted(
             previous_index,
             channel_names_to_read_in_dg,
             true,
+            string_policy,
         )
         .context("could not read channels from bytes")?;
         previous_index += n_record_chunk;
@@ -1009,6 +1385,7 @@ fn read_all_channels_sorted_from_bytes(
     data: &[u8],
     channel_group: &mut Cg4,
     channel_names_to_read_in_dg: &HashSet<String>,
+    string_policy: StringPolicy,
 ) -> Result<Vec<i32>> {
     // initialises the arrays
     initialise_arrays(
@@ -1024,25 +1401,30 @@ fn read_all_channels_sorted_from_bytes(
         0,
         channel_names_to_read_in_dg,
         true,
+        string_policy,
     )
     .context("failed initilising arrays")?;
     Ok(vlsd_channels)
 }
 
 /// Reads unsorted data block chunk by chunk
-fn read_all_channels_unsorted(
-    rdr: &mut BufReader<&File>,
+fn read_all_channels_unsorted<R: Read + Seek>(
+    rdr: &mut BufReader<R>,
     dg: &mut Dg4,
     block_length: i64,
     channel_names_to_read_in_dg: &HashSet<String>,
+    chunk_size: usize,
+    string_policy: StringPolicy,
 ) -> Result<()> {
     let data_block_length = block_length as usize;
+    let chunk_size = chunk_size.max(1);
     let mut position: usize = 24;
     let mut record_counter: HashMap<u64, (usize, Vec<u8>)> = HashMap::new();
     let mut decoder: Dec = Dec {
         windows_1252: WINDOWS_1252.new_decoder(),
         utf_16_be: UTF_16BE.new_decoder(),
         utf_16_le: UTF_16LE.new_decoder(),
+        string_policy,
     };
     // initialise record counter that will contain sorted data blocks for each channel group
     for cg in dg.cg.values_mut() {
@@ -1053,10 +1435,10 @@ fn read_all_channels_unsorted(
     let mut data: Vec<u8> = Vec::new();
     let mut data_chunk: Vec<u8>;
     while position < data_block_length {
-        if (data_block_length - position) > CHUNK_SIZE_READING_4 {
+        if (data_block_length - position) > chunk_size {
             // not last chunk of data
-            data_chunk = vec![0u8; CHUNK_SIZE_READING_4];
-            position += CHUNK_SIZE_READING_4;
+            data_chunk = vec![0u8; chunk_size];
+            position += chunk_size;
         } else {
             // last chunk of data
             data_chunk = vec![0u8; data_block_length - position];
@@ -1135,11 +1517,11 @@ fn read_all_channels_unsorted_from_bytes(
                                                         .windows_1252
                                                         .decode_to_string(record, &mut dst, false);
                                                 } else if target_cn.block.cn_data_type == 7 {
-                                                    dst = str::from_utf8(record)
-                                                        .context(
-                                                            "Found invalid UTF-8 from VLSD record",
-                                                        )?
-                                                        .to_string();
+                                                    dst = decoder
+                                                        .string_policy
+                                                        .decode_utf8(record)
+                                                        .context("failed decoding VLSD record")?
+                                                        .into_owned();
                                                 } else if target_cn.block.cn_data_type == 8 {
                                                     let (_result, _size, _replacement) = decoder
                                                         .utf_16_le
@@ -1213,8 +1595,16 @@ fn read_all_channels_unsorted_from_bytes(
                 *index,
                 channel_names_to_read_in_dg,
                 true,
+                decoder.string_policy,
             )
             .context("failed reading channels from bytes after reading unsorted data")?;
+            // records of next blocks are copied after the ones already read
+            if let Some(n_records) = record_data
+                .len()
+                .checked_div(channel_group.record_length as usize)
+            {
+                *index += n_records;
+            }
             record_data.clear(); // clears data for new block, keeping capacity
         }
     }
@@ -1226,10 +1616,11 @@ struct Dec {
     windows_1252: Decoder,
     utf_16_be: Decoder,
     utf_16_le: Decoder,
+    string_policy: StringPolicy,
 }
 
 /// initialise ndarrays for the data group/block
-fn initialise_arrays(
+pub(crate) fn initialise_arrays(
     channel_group: &mut Cg4,
     cg_cycle_count: &u64,
     channel_names_to_read_in_dg: &HashSet<String>,
@@ -1252,6 +1643,14 @@ fn initialise_arrays(
                     .with_context(|| {
                         format!("Zeros initialisation of channel {} failed", cn.unique_name)
                     })?;
+                // validity mask initialised to all valid values
+                if let Some((validity, _invalid_byte_position, _invalid_byte_mask)) =
+                    &mut cn.invalid_mask
+                {
+                    let mut buffer = BooleanBufferBuilder::new(*cg_cycle_count as usize);
+                    buffer.append_n(*cg_cycle_count as usize, true);
+                    *validity = Some(buffer);
+                }
                 Ok(())
             },
         )
@@ -1265,7 +1664,7 @@ fn initialise_arrays(
 }
 
 /// applies bit mask if required in channel block
-fn apply_bit_mask_offset(
+pub(crate) fn apply_bit_mask_offset(
     dg: &mut Dg4,
     channel_names_to_read_in_dg: &HashSet<String>,
 ) -> Result<(), Error> {
@@ -1277,189 +1676,20 @@ fn apply_bit_mask_offset(
             .filter(|(_cn_record_position, cn)| {
                 channel_names_to_read_in_dg.contains(&cn.unique_name)
             })
-            .try_for_each(|(_rec_pos, cn): (&i32, &mut Cn4)| -> Result<(), Error> {
+            .for_each(|(_rec_pos, cn): (&i32, &mut Cn4)| {
                 if cn.block.cn_data_type <= 3 {
-                    let left_shift =
-                        cn.n_bytes * 8 - (cn.block.cn_bit_offset as u32) - cn.block.cn_bit_count;
-                    let right_shift = left_shift + (cn.block.cn_bit_offset as u32);
-                    if left_shift > 0 || right_shift > 0 {
-                        match &mut cn.data {
-                            ChannelData::Int8(array) => {
-                                let a = array.values_slice_mut();
-                                if left_shift > 0 {
-                                    a.iter_mut().for_each(|x| *x <<= left_shift)
-                                };
-                                if right_shift > 0 {
-                                    a.iter_mut().for_each(|x| *x >>= right_shift)
-                                };
-                            }
-                            ChannelData::UInt8(array) => {
-                                let a = array.values_slice_mut();
-                                if left_shift > 0 {
-                                    a.iter_mut().for_each(|x| *x <<= left_shift)
-                                };
-                                if right_shift > 0 {
-                                    a.iter_mut().for_each(|x| *x >>= right_shift)
-                                };
-                            }
-                            ChannelData::Int16(array) => {
-                                let a = array.values_slice_mut();
-                                if left_shift > 0 {
-                                    a.iter_mut().for_each(|x| *x <<= left_shift)
-                                };
-                                if right_shift > 0 {
-                                    a.iter_mut().for_each(|x| *x >>= right_shift)
-                                };
-                            }
-                            ChannelData::UInt16(array) => {
-                                let a = array.values_slice_mut();
-                                if left_shift > 0 {
-                                    a.iter_mut().for_each(|x| *x <<= left_shift)
-                                };
-                                if right_shift > 0 {
-                                    a.iter_mut().for_each(|x| *x >>= right_shift)
-                                };
-                            }
-                            ChannelData::Int32(array) => {
-                                let a = array.values_slice_mut();
-                                if left_shift > 0 {
-                                    a.iter_mut().for_each(|x| *x <<= left_shift)
-                                };
-                                if right_shift > 0 {
-                                    a.iter_mut().for_each(|x| *x >>= right_shift)
-                                };
-                            }
-                            ChannelData::UInt32(array) => {
-                                let a = array.values_slice_mut();
-                                if left_shift > 0 {
-                                    a.iter_mut().for_each(|x| *x <<= left_shift)
-                                };
-                                if right_shift > 0 {
-                                    a.iter_mut().for_each(|x| *x >>= right_shift)
-                                };
-                            }
-                            ChannelData::Float32(_) => (),
-                            ChannelData::Int64(array) => {
-                                let a = array.values_slice_mut();
-                                let left_shift =
-                                    64 - (cn.block.cn_bit_offset as u32) - cn.block.cn_bit_count;
-                                let right_shift = left_shift + (cn.block.cn_bit_offset as u32);
-                                if left_shift > 0 {
-                                    a.iter_mut().for_each(|x| *x <<= left_shift)
-                                };
-                                if right_shift > 0 {
-                                    a.iter_mut().for_each(|x| *x >>= right_shift)
-                                };
-                            }
-                            ChannelData::UInt64(array) => {
-                                let a = array.values_slice_mut();
-                                let left_shift =
-                                    64 - (cn.block.cn_bit_offset as u32) - cn.block.cn_bit_count;
-                                let right_shift = left_shift + (cn.block.cn_bit_offset as u32);
-                                if left_shift > 0 {
-                                    a.iter_mut().for_each(|x| *x <<= left_shift)
-                                };
-                                if right_shift > 0 {
-                                    a.iter_mut().for_each(|x| *x >>= right_shift)
-                                };
-                            }
-                            ChannelData::Float64(_) => (),
-                            ChannelData::Complex32(_) => (),
-                            ChannelData::Complex64(_) => (),
-                            ChannelData::Utf8(_) => (),
-                            ChannelData::VariableSizeByteArray(_) => (),
-                            ChannelData::FixedSizeByteArray(_) => (),
-                            ChannelData::ArrayDInt8(array) => {
-                                let a = array.values_slice_mut();
-                                if left_shift > 0 {
-                                    a.iter_mut().for_each(|x| *x <<= left_shift)
-                                };
-                                if right_shift > 0 {
-                                    a.iter_mut().for_each(|x| *x >>= right_shift)
-                                };
-                            }
-                            ChannelData::ArrayDUInt8(array) => {
-                                let a = array.values_slice_mut();
-                                if left_shift > 0 {
-                                    a.iter_mut().for_each(|x| *x <<= left_shift)
-                                };
-                                if right_shift > 0 {
-                                    a.iter_mut().for_each(|x| *x >>= right_shift)
-                                };
-                            }
-                            ChannelData::ArrayDInt16(array) => {
-                                let a = array.values_slice_mut();
-                                if left_shift > 0 {
-                                    a.iter_mut().for_each(|x| *x <<= left_shift)
-                                };
-                                if right_shift > 0 {
-                                    a.iter_mut().for_each(|x| *x >>= right_shift)
-                                };
-                            }
-                            ChannelData::ArrayDUInt16(array) => {
-                                let a = array.values_slice_mut();
-                                if left_shift > 0 {
-                                    a.iter_mut().for_each(|x| *x <<= left_shift)
-                                };
-                                if right_shift > 0 {
-                                    a.iter_mut().for_each(|x| *x >>= right_shift)
-                                };
-                            }
-                            ChannelData::ArrayDInt32(array) => {
-                                let a = array.values_slice_mut();
-                                if left_shift > 0 {
-                                    a.iter_mut().for_each(|x| *x <<= left_shift)
-                                };
-                                if right_shift > 0 {
-                                    a.iter_mut().for_each(|x| *x >>= right_shift)
-                                };
-                            }
-                            ChannelData::ArrayDUInt32(array) => {
-                                let a = array.values_slice_mut();
-                                if left_shift > 0 {
-                                    a.iter_mut().for_each(|x| *x <<= left_shift)
-                                };
-                                if right_shift > 0 {
-                                    a.iter_mut().for_each(|x| *x >>= right_shift)
-                                };
-                            }
-                            ChannelData::ArrayDFloat32(_) => (),
-                            ChannelData::ArrayDInt64(array) => {
-                                let a = array.values_slice_mut();
-                                let left_shift =
-                                    64 - (cn.block.cn_bit_offset as u32) - cn.block.cn_bit_count;
-                                let right_shift = left_shift + (cn.block.cn_bit_offset as u32);
-                                if left_shift > 0 {
-                                    a.iter_mut().for_each(|x| *x <<= left_shift)
-                                };
-                                if right_shift > 0 {
-                                    a.iter_mut().for_each(|x| *x >>= right_shift)
-                                };
-                            }
-                            ChannelData::ArrayDUInt64(array) => {
-                                let a = array.values_slice_mut();
-                                let left_shift =
-                                    64 - (cn.block.cn_bit_offset as u32) - cn.block.cn_bit_count;
-                                let right_shift = left_shift + (cn.block.cn_bit_offset as u32);
-                                if left_shift > 0 {
-                                    a.iter_mut().for_each(|x| *x <<= left_shift)
-                                };
-                                if right_shift > 0 {
-                                    a.iter_mut().for_each(|x| *x >>= right_shift)
-                                };
-                            }
-                            ChannelData::ArrayDFloat64(_) => (),
-                        }
+                    cn.data.apply_bit_mask_offset(
+                        cn.block.cn_bit_offset.into(),
+                        cn.block.cn_bit_count,
+                    );
+                    if cn.block.cn_bit_count == 1
+                        && cn.block.cn_data_type <= 1
+                        && cn.block.cn_cc_conversion == 0
+                    {
+                        cn.data.bits_into_boolean();
                     }
                 }
-                Ok(())
-            })
-            .with_context(|| {
-                format!(
-                    "bit mask application failed for channel group {:?}",
-                    channel_group
-                )
-            })?
+            });
     }
     Ok(())
 }
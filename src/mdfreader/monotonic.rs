@@ -0,0 +1,222 @@
+//! Enforcement of monotonic master channels in memory, broken by duplicate timestamps or clock
+//! resets, by sorting the records of their group or offsetting the master values
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use std::fmt;
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use arrow::array::{Array, ArrayRef, Float64Array, UInt32Array};
+use arrow::compute::{cast, take};
+use log::warn;
+
+use crate::data_holder::resample::to_f64;
+use crate::mdfreader::Mdf;
+
+/// fix applied to non monotonic master channels
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MonotonicFix {
+    /// records of the group are sorted by master value, invalid values last. Records with
+    /// duplicate master values are kept in their order
+    Sort,
+    /// master values from a clock reset or a duplicate are offset to continue from the
+    /// previous value by the median step, channels data being unchanged
+    Offset,
+}
+
+/// changes made to a non monotonic master channel
+#[derive(Debug, Clone, PartialEq)]
+pub struct MasterFix {
+    pub master: String,
+    pub method: MonotonicFix,
+    /// number of records moved by sorting
+    pub n_moved: usize,
+    /// number of records left with the same master value as the previous record
+    pub n_duplicates: usize,
+    /// index of the first sample offset and offset added from it, for each reset or duplicate
+    pub offsets: Vec<(usize, f64)>,
+}
+
+impl fmt::Display for MasterFix {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.method {
+            MonotonicFix::Sort => write!(
+                f,
+                "{}: {} records moved by sorting, {} duplicate master values",
+                self.master, self.n_moved, self.n_duplicates
+            ),
+            MonotonicFix::Offset => {
+                write!(f, "{}: {} offsets", self.master, self.offsets.len())?;
+                for (index, offset) in self.offsets.iter() {
+                    write!(f, ", {offset} from sample {index}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// master values order, invalid and NaN values last
+fn master_cmp(a: Option<f64>, b: Option<f64>) -> Ordering {
+    match (a.filter(|a| !a.is_nan()), b.filter(|b| !b.is_nan())) {
+        (Some(a), Some(b)) => a.total_cmp(&b),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+/// number of valid master values equal to the previous one
+fn count_duplicates(values: impl Iterator<Item = Option<f64>>) -> usize {
+    let valid: Vec<f64> = values.flatten().filter(|value| !value.is_nan()).collect();
+    valid.windows(2).filter(|pair| pair[0] == pair[1]).count()
+}
+
+/// stable sort indexes of the records by master value, None if already sorted
+fn sort_master(master: String, values: &Float64Array) -> Option<(UInt32Array, MasterFix)> {
+    let mut indexes: Vec<u32> = (0..values.len() as u32).collect();
+    indexes.sort_by(|a, b| {
+        master_cmp(
+            values
+                .is_valid(*a as usize)
+                .then(|| values.value(*a as usize)),
+            values
+                .is_valid(*b as usize)
+                .then(|| values.value(*b as usize)),
+        )
+    });
+    let n_moved = indexes
+        .iter()
+        .enumerate()
+        .filter(|(i, j)| *i != **j as usize)
+        .count();
+    if n_moved == 0 {
+        return None;
+    }
+    let fix = MasterFix {
+        master,
+        method: MonotonicFix::Sort,
+        n_moved,
+        n_duplicates: count_duplicates(indexes.iter().map(|i| {
+            let i = *i as usize;
+            values.is_valid(i).then(|| values.value(i))
+        })),
+        offsets: Vec::new(),
+    };
+    Some((UInt32Array::from(indexes), fix))
+}
+
+/// master values strictly increasing, offset from each step not going forward so that they
+/// continue by the median step. None if already strictly increasing or without forward step
+fn offset_master(master: String, values: &Float64Array) -> Option<(Float64Array, MasterFix)> {
+    let valid: Vec<(usize, f64)> = values
+        .iter()
+        .enumerate()
+        .filter_map(|(i, value)| {
+            value
+                .filter(|value| !value.is_nan())
+                .map(|value| (i, value))
+        })
+        .collect();
+    let mut steps: Vec<f64> = valid
+        .windows(2)
+        .map(|pair| pair[1].1 - pair[0].1)
+        .filter(|step| *step > 0.0)
+        .collect();
+    if steps.len() + 1 >= valid.len() {
+        return None;
+    }
+    steps.sort_by(f64::total_cmp);
+    let Some(period) = steps.get(steps.len() / 2).copied() else {
+        warn!("master channel {master} has no forward step to offset its values");
+        return None;
+    };
+    let mut fixed: Vec<Option<f64>> = values.iter().collect();
+    let mut offsets = Vec::new();
+    let mut offset = 0.0;
+    let mut previous: Option<f64> = None;
+    for (index, value) in valid {
+        if let Some(previous) = previous.filter(|previous| value + offset <= *previous) {
+            let new_offset = previous + period - value;
+            offsets.push((index, new_offset - offset));
+            offset = new_offset;
+        }
+        fixed[index] = Some(value + offset);
+        previous = Some(value + offset);
+    }
+    let fix = MasterFix {
+        master,
+        method: MonotonicFix::Offset,
+        n_moved: 0,
+        n_duplicates: 0,
+        offsets,
+    };
+    Some((Float64Array::from(fixed), fix))
+}
+
+/// makes monotonic the master channels in memory with method, along with their group channels
+/// when sorted. Only the masters and channels in channel_names are concerned if given
+pub fn enforce_monotonic(
+    mdf: &mut Mdf,
+    channel_names: Option<&HashSet<String>>,
+    method: MonotonicFix,
+) -> Result<Vec<MasterFix>> {
+    let selected = |name: &String| channel_names.is_none_or(|names| names.contains(name));
+    let groups = mdf.get_master_channel_names_set();
+    let mut masters: Vec<&String> = groups
+        .keys()
+        .flatten()
+        .filter(|master| selected(master))
+        .collect();
+    masters.sort();
+    let mut fixes = Vec::new();
+    for master in masters {
+        let Some(master_data) = mdf
+            .get_channel_data(master)
+            .filter(|data| !data.is_empty())
+            .map(|data| data.as_ref())
+        else {
+            continue;
+        };
+        let values = to_f64(&master_data)
+            .with_context(|| format!("failed reading master channel {master}"))?;
+        match method {
+            MonotonicFix::Sort => {
+                let Some((indexes, fix)) = sort_master(master.clone(), &values) else {
+                    continue;
+                };
+                let mut channels: Vec<&String> = groups
+                    .get(&Some(master.clone()))
+                    .map(|names| names.iter().filter(|name| selected(name)).collect())
+                    .unwrap_or_default();
+                if !channels.contains(&master) {
+                    channels.push(master);
+                }
+                for name in channels {
+                    let Some(data) = mdf
+                        .get_channel_data(name)
+                        .filter(|data| data.len() == master_data.len())
+                        .map(|data| data.as_ref())
+                    else {
+                        continue;
+                    };
+                    let sorted = take(&data, &indexes, None)
+                        .with_context(|| format!("failed sorting channel {name}"))?;
+                    mdf.set_channel_data(name, sorted)?;
+                }
+                fixes.push(fix);
+            }
+            MonotonicFix::Offset => {
+                let Some((fixed, fix)) = offset_master(master.clone(), &values) else {
+                    continue;
+                };
+                let fixed: ArrayRef = Arc::new(fixed);
+                let fixed = cast(&fixed, master_data.data_type())
+                    .with_context(|| format!("failed casting back master channel {master}"))?;
+                mdf.set_channel_data(master, fixed)?;
+                fixes.push(fix);
+            }
+        }
+    }
+    Ok(fixes)
+}
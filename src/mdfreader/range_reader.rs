@@ -0,0 +1,205 @@
+//! reading of mdf files stored remotely, object storage for instance, by byte ranges
+use std::collections::{HashMap, VecDeque};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use object_store::path::Path;
+use object_store::ObjectStore;
+use tokio::runtime::{Builder, Runtime};
+
+/// default size in bytes of the blocks fetched from the source
+#[allow(dead_code)]
+const BLOCK_SIZE: usize = 1 << 20;
+/// default number of blocks kept in cache
+#[allow(dead_code)]
+const CACHE_BLOCKS: usize = 16;
+
+/// file content readable by byte ranges, typically an object storage client
+/// issuing range requests
+#[allow(dead_code)]
+pub trait RangeSource {
+    /// returns the size in bytes of the file
+    fn size(&self) -> Result<u64>;
+    /// returns length bytes of the file starting at offset
+    fn read_range(&self, offset: u64, length: usize) -> Result<Vec<u8>>;
+}
+
+/// object of an object store (S3, GCS, Azure, HTTP or local), fetched by range requests
+/// run on its own runtime
+#[allow(dead_code)]
+pub struct ObjectStoreSource {
+    /// store containing the object
+    store: Arc<dyn ObjectStore>,
+    /// location of the object in store
+    location: Path,
+    /// runtime blocking on the store requests
+    runtime: Runtime,
+}
+
+#[allow(dead_code)]
+impl ObjectStoreSource {
+    /// creates the source of the object at location in store
+    pub fn new(store: Arc<dyn ObjectStore>, location: Path) -> Result<Self> {
+        let runtime = Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .context("failed creating runtime of object store requests")?;
+        Ok(ObjectStoreSource {
+            store,
+            location,
+            runtime,
+        })
+    }
+}
+
+impl RangeSource for ObjectStoreSource {
+    fn size(&self) -> Result<u64> {
+        let meta = self
+            .runtime
+            .block_on(self.store.head(&self.location))
+            .with_context(|| format!("failed getting metadata of object {}", self.location))?;
+        Ok(meta.size as u64)
+    }
+    fn read_range(&self, offset: u64, length: usize) -> Result<Vec<u8>> {
+        let start = offset as usize;
+        let bytes = self
+            .runtime
+            .block_on(self.store.get_range(&self.location, start..start + length))
+            .with_context(|| format!("failed reading range of object {}", self.location))?;
+        Ok(bytes.to_vec())
+    }
+}
+
+/// Read and Seek adapter of a range source, so that metadata parsing and partial loading
+/// of channels only fetch the parts of the file they need.
+/// Small reads are served from a cache of fixed size blocks, reads bigger than a block
+/// (data blocks for instance) are fetched with a single range request
+#[allow(dead_code)]
+pub struct RangeReader<S: RangeSource> {
+    /// remote file content
+    source: S,
+    /// file size in bytes
+    size: u64,
+    /// current reading position
+    position: u64,
+    /// size in bytes of the cached blocks
+    block_size: usize,
+    /// maximum number of cached blocks
+    cache_blocks: usize,
+    /// cached blocks by index
+    cache: HashMap<u64, Vec<u8>>,
+    /// cached blocks indexes, oldest first
+    cache_order: VecDeque<u64>,
+}
+
+#[allow(dead_code)]
+impl<S: RangeSource> RangeReader<S> {
+    /// creates the reader of the source content
+    pub fn new(source: S) -> Result<Self> {
+        let size = source
+            .size()
+            .context("failed getting size of range source")?;
+        Ok(RangeReader {
+            source,
+            size,
+            position: 0,
+            block_size: BLOCK_SIZE,
+            cache_blocks: CACHE_BLOCKS,
+            cache: HashMap::new(),
+            cache_order: VecDeque::new(),
+        })
+    }
+
+    /// sets the size in bytes of the blocks fetched and cached for small reads
+    pub fn with_block_size(mut self, block_size: usize) -> Self {
+        self.block_size = block_size.max(1);
+        self.clear_cache();
+        self
+    }
+
+    /// sets the maximum number of cached blocks
+    pub fn with_cache_blocks(mut self, cache_blocks: usize) -> Self {
+        self.cache_blocks = cache_blocks.max(1);
+        self.clear_cache();
+        self
+    }
+
+    /// returns the source of the reader
+    pub fn into_inner(self) -> S {
+        self.source
+    }
+
+    fn clear_cache(&mut self) {
+        self.cache.clear();
+        self.cache_order.clear();
+    }
+
+    /// returns the cached block, fetching it from source if needed
+    fn block(&mut self, index: u64) -> Result<&[u8]> {
+        if !self.cache.contains_key(&index) {
+            let offset = index * self.block_size as u64;
+            let length = (self.size - offset).min(self.block_size as u64) as usize;
+            let block = self
+                .source
+                .read_range(offset, length)
+                .with_context(|| format!("failed reading {length} bytes at {offset}"))?;
+            if self.cache_order.len() >= self.cache_blocks {
+                if let Some(oldest) = self.cache_order.pop_front() {
+                    self.cache.remove(&oldest);
+                }
+            }
+            self.cache_order.push_back(index);
+            self.cache.insert(index, block);
+        }
+        Ok(&self.cache[&index])
+    }
+}
+
+impl<S: RangeSource> Read for RangeReader<S> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.position >= self.size || buf.is_empty() {
+            return Ok(0);
+        }
+        let length = (self.size - self.position).min(buf.len() as u64) as usize;
+        if length >= self.block_size {
+            let bytes = self
+                .source
+                .read_range(self.position, length)
+                .map_err(io::Error::other)?;
+            let length = bytes.len().min(length);
+            buf[..length].copy_from_slice(&bytes[..length]);
+            self.position += length as u64;
+            return Ok(length);
+        }
+        let block_size = self.block_size as u64;
+        let start = (self.position % block_size) as usize;
+        let block = self
+            .block(self.position / block_size)
+            .map_err(io::Error::other)?;
+        let length = length.min(block.len().saturating_sub(start));
+        buf[..length].copy_from_slice(&block[start..start + length]);
+        self.position += length as u64;
+        Ok(length)
+    }
+}
+
+impl<S: RangeSource> Seek for RangeReader<S> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let position = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(offset) => self.size.checked_add_signed(offset),
+            SeekFrom::Current(offset) => self.position.checked_add_signed(offset),
+        };
+        match position {
+            Some(position) => {
+                self.position = position;
+                Ok(position)
+            }
+            None => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )),
+        }
+    }
+}
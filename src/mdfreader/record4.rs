@@ -0,0 +1,199 @@
+//! typed iteration of channel group records mapped onto user structs
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::io::{Read, Seek};
+use std::marker::PhantomData;
+
+use anyhow::{Context, Error, Result};
+use arrow::array::{Array, ArrayRef, AsArray};
+use arrow::compute::cast;
+use arrow::datatypes::{
+    DataType, Float32Type, Float64Type, Int16Type, Int32Type, Int64Type, Int8Type, UInt16Type,
+    UInt32Type, UInt64Type, UInt8Type,
+};
+
+use crate::mdfreader::iterator4::ChannelGroupIterator;
+
+/// mapping of a channel group record onto a user struct, to iterate typed records
+/// with Mdf::iter_typed_records
+pub trait FromRecord: Sized {
+    /// names of the channels read to build the struct
+    fn channel_names() -> Vec<String>;
+    /// builds the struct from the channel values of a record
+    fn from_record(record: &Record) -> Result<Self>;
+}
+
+/// channel value type readable from a record
+#[allow(dead_code)]
+pub trait RecordValue: Sized {
+    /// arrow data type the channel array is cast into before reading values
+    fn data_type() -> DataType;
+    /// value at index of an array of data_type, None if null
+    fn value(array: &dyn Array, index: usize) -> Option<Self>;
+}
+
+macro_rules! primitive_record_value {
+    ($native:ty, $arrow_type:ty, $data_type:expr) => {
+        impl RecordValue for $native {
+            fn data_type() -> DataType {
+                $data_type
+            }
+            fn value(array: &dyn Array, index: usize) -> Option<Self> {
+                let array = array.as_primitive::<$arrow_type>();
+                array.is_valid(index).then(|| array.value(index))
+            }
+        }
+    };
+}
+
+primitive_record_value!(i8, Int8Type, DataType::Int8);
+primitive_record_value!(u8, UInt8Type, DataType::UInt8);
+primitive_record_value!(i16, Int16Type, DataType::Int16);
+primitive_record_value!(u16, UInt16Type, DataType::UInt16);
+primitive_record_value!(i32, Int32Type, DataType::Int32);
+primitive_record_value!(u32, UInt32Type, DataType::UInt32);
+primitive_record_value!(i64, Int64Type, DataType::Int64);
+primitive_record_value!(u64, UInt64Type, DataType::UInt64);
+primitive_record_value!(f32, Float32Type, DataType::Float32);
+primitive_record_value!(f64, Float64Type, DataType::Float64);
+
+impl RecordValue for bool {
+    fn data_type() -> DataType {
+        DataType::Boolean
+    }
+    fn value(array: &dyn Array, index: usize) -> Option<Self> {
+        let array = array.as_boolean();
+        array.is_valid(index).then(|| array.value(index))
+    }
+}
+
+impl RecordValue for String {
+    fn data_type() -> DataType {
+        DataType::Utf8
+    }
+    fn value(array: &dyn Array, index: usize) -> Option<Self> {
+        let array = array.as_string::<i32>();
+        array
+            .is_valid(index)
+            .then(|| array.value(index).to_string())
+    }
+}
+
+/// invalid values are read as None instead of failing
+impl<V: RecordValue> RecordValue for Option<V> {
+    fn data_type() -> DataType {
+        V::data_type()
+    }
+    fn value(array: &dyn Array, index: usize) -> Option<Self> {
+        Some(V::value(array, index))
+    }
+}
+
+/// chunk of records decoded by the channel group iterator, channel arrays being cast
+/// once per chunk into the data types read
+#[allow(dead_code)]
+struct RecordChunk {
+    /// channel arrays as decoded
+    arrays: HashMap<String, ArrayRef>,
+    /// channel arrays cast into another data type
+    cast_arrays: RefCell<HashMap<(String, DataType), ArrayRef>>,
+    /// number of records in chunk
+    len: usize,
+}
+
+#[allow(dead_code)]
+impl RecordChunk {
+    /// returns the channel array with the given data type
+    fn array(&self, channel_name: &str, data_type: &DataType) -> Result<ArrayRef> {
+        let array = self
+            .arrays
+            .get(channel_name)
+            .with_context(|| format!("channel {channel_name} not in record"))?;
+        if array.data_type() == data_type {
+            return Ok(array.clone());
+        }
+        let key = (channel_name.to_string(), data_type.clone());
+        if let Some(array) = self.cast_arrays.borrow().get(&key) {
+            return Ok(array.clone());
+        }
+        let cast_array = cast(array, data_type)
+            .with_context(|| format!("channel {channel_name} can not be read as {data_type}"))?;
+        self.cast_arrays
+            .borrow_mut()
+            .insert(key, cast_array.clone());
+        Ok(cast_array)
+    }
+}
+
+/// channel values of one record
+#[allow(dead_code)]
+pub struct Record<'a> {
+    chunk: &'a RecordChunk,
+    index: usize,
+}
+
+#[allow(dead_code)]
+impl Record<'_> {
+    /// returns the value of the channel in the record, failing if it is invalid
+    pub fn get<V: RecordValue>(&self, channel_name: &str) -> Result<V> {
+        let array = self.chunk.array(channel_name, &V::data_type())?;
+        V::value(&array, self.index)
+            .with_context(|| format!("channel {channel_name} value is invalid in record"))
+    }
+}
+
+/// Iterator over the records of a channel group, each record being mapped onto T
+pub struct TypedRecordIterator<'a, T: FromRecord, R: Read + Seek> {
+    /// chunks of records iterator
+    records: ChannelGroupIterator<'a, R>,
+    /// current chunk of records
+    chunk: Option<RecordChunk>,
+    /// index of next record in chunk
+    index: usize,
+    record_type: PhantomData<T>,
+}
+
+impl<'a, T: FromRecord, R: Read + Seek> TypedRecordIterator<'a, T, R> {
+    /// creates the iterator decoding only the channels read by T
+    pub fn new(records: ChannelGroupIterator<'a, R>) -> Self {
+        let channel_names: HashSet<String> = T::channel_names().into_iter().collect();
+        TypedRecordIterator {
+            records: records.with_channel_names(&channel_names),
+            chunk: None,
+            index: 0,
+            record_type: PhantomData,
+        }
+    }
+}
+
+impl<T: FromRecord, R: Read + Seek> Iterator for TypedRecordIterator<'_, T, R> {
+    type Item = Result<T, Error>;
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(chunk) = &self.chunk {
+                if self.index < chunk.len {
+                    let record = Record {
+                        chunk,
+                        index: self.index,
+                    };
+                    self.index += 1;
+                    return Some(T::from_record(&record));
+                }
+            }
+            let chunk = match self.records.next()? {
+                Ok(chunk) => chunk,
+                Err(error) => return Some(Err(error)),
+            };
+            let arrays: HashMap<String, ArrayRef> = chunk
+                .into_iter()
+                .map(|(name, data)| (name, data.as_ref()))
+                .collect();
+            self.chunk = Some(RecordChunk {
+                len: arrays.values().map(|array| array.len()).max().unwrap_or(0),
+                arrays,
+                cast_arrays: RefCell::new(HashMap::new()),
+            });
+            self.index = 0;
+        }
+    }
+}
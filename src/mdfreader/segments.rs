@@ -0,0 +1,131 @@
+//! Detection of the recording gaps, time intervals without any sample of the time master
+//! channels like logger pauses, and split of the file into one Mdf per continuous section
+use std::collections::HashMap;
+
+use anyhow::{bail, Context, Result};
+use arrow::array::{Array, BooleanArray};
+use arrow::compute::filter;
+use log::info;
+
+use crate::data_holder::resample::to_f64;
+use crate::mdfinfo::mdfinfo4::{FhBlock, MdfInfo4};
+use crate::mdfinfo::MdfInfo;
+use crate::mdfreader::Mdf;
+use crate::mdfwriter::merge4::start_time_ns;
+
+/// names of the time master channels with data in memory
+fn time_masters(mdf: &Mdf) -> Vec<String> {
+    let mut masters: Vec<String> = mdf
+        .get_master_channel_names_set()
+        .into_keys()
+        .flatten()
+        .filter(|master| {
+            mdf.get_channel_master_type(master) == 1
+                && mdf
+                    .get_channel_data(master)
+                    .is_some_and(|data| !data.is_empty())
+        })
+        .collect();
+    masters.sort();
+    masters
+}
+
+/// time intervals longer than threshold without any sample of the time master channels in
+/// memory, sorted by start time
+pub fn detect_gaps(mdf: &Mdf, threshold: f64) -> Result<Vec<(f64, f64)>> {
+    if threshold.is_nan() || threshold <= 0.0 {
+        bail!("gap threshold must be strictly positive, got {threshold}");
+    }
+    let mut times: Vec<f64> = Vec::new();
+    for master in time_masters(mdf) {
+        if let Some(data) = mdf.get_channel_data(&master) {
+            let values = to_f64(&data.as_ref())
+                .with_context(|| format!("failed reading master channel {master}"))?;
+            times.extend(values.iter().flatten().filter(|t| !t.is_nan()));
+        }
+    }
+    times.sort_by(f64::total_cmp);
+    Ok(times
+        .windows(2)
+        .filter(|pair| pair[1] - pair[0] > threshold)
+        .map(|pair| (pair[0], pair[1]))
+        .collect())
+}
+
+/// splits the time based channels in memory into one Mdf per section between gaps longer
+/// than threshold. Channels keep their master values, groups without samples in a section
+/// are left out of its Mdf, channels of other master types are not kept
+pub fn split_on_gaps(mdf: &Mdf, threshold: f64) -> Result<Vec<Mdf>> {
+    let gaps = detect_gaps(mdf, threshold)?;
+    let mut bounds: Vec<(f64, f64)> = Vec::with_capacity(gaps.len() + 1);
+    let mut start = f64::NEG_INFINITY;
+    for (gap_start, gap_end) in gaps.iter() {
+        bounds.push((start, *gap_start));
+        start = *gap_end;
+    }
+    bounds.push((start, f64::INFINITY));
+    let groups = mdf.get_master_channel_names_set();
+    let masters = time_masters(mdf);
+    let mut segments = Vec::with_capacity(bounds.len());
+    for (start, end) in bounds {
+        let mut info = MdfInfo4::new(&mdf.get_file_name(), mdf.get_channel_names_set().len());
+        info.fh.push(FhBlock::default());
+        info.hd_block.hd_start_time_ns = start_time_ns(mdf);
+        let mut segment = Mdf {
+            mdf_info: MdfInfo::V4(Box::new(info)),
+            stats: HashMap::new(),
+            master_fixes: Vec::new(),
+        };
+        for master in masters.iter() {
+            let Some(master_data) = mdf.get_channel_data(master) else {
+                continue;
+            };
+            let master_data = master_data.as_ref();
+            let mask: BooleanArray = to_f64(&master_data)?
+                .iter()
+                .map(|t| Some(t.is_some_and(|t| start <= t && t <= end)))
+                .collect();
+            if mask.true_count() == 0 {
+                continue;
+            }
+            let mut channel_names: Vec<&String> = groups
+                .get(&Some(master.clone()))
+                .map(|names| names.iter().filter(|name| *name != master).collect())
+                .unwrap_or_default();
+            channel_names.sort();
+            segment.add_channel(
+                master.clone(),
+                filter(&master_data, &mask).context("failed filtering master channel")?,
+                Some(master.clone()),
+                Some(1),
+                true,
+                mdf.get_channel_unit(master)?,
+                mdf.get_channel_desc(master)?,
+            )?;
+            for name in channel_names {
+                let Some(data) = mdf
+                    .get_channel_data(name)
+                    .filter(|data| data.len() == master_data.len())
+                else {
+                    continue;
+                };
+                let data = filter(&data.as_ref(), &mask)
+                    .with_context(|| format!("failed filtering channel {name}"))?;
+                segment
+                    .add_channel(
+                        name.clone(),
+                        data,
+                        Some(master.clone()),
+                        None,
+                        false,
+                        mdf.get_channel_unit(name)?,
+                        mdf.get_channel_desc(name)?,
+                    )
+                    .with_context(|| format!("failed adding channel {name} to segment"))?;
+            }
+        }
+        segments.push(segment);
+    }
+    info!("Split channels into {} segments", segments.len());
+    Ok(segments)
+}
@@ -1,3 +1,12 @@
-//! This module provides writer of data in memory into mdf4.2 file
+//! This module provides writers of data in memory into mdf4.2 file and of mdf3 file copies,
+//! a streaming mdf4 writer of records as they arrive, as well as concatenation and merging
+//! of files before writing and finalization of unfinalized mdf4 files or saving in place of their edited metadata,
+//! and integrity hash of written files
+pub mod finalize4;
+pub mod integrity4;
 pub mod mdfwriter3;
 pub mod mdfwriter4;
+pub mod merge4;
+pub mod metadata4;
+#[allow(dead_code)]
+pub mod streaming4;
@@ -0,0 +1,544 @@
+//! Finalization of mdf4 files left unfinalized by a logger crash or power loss:
+//! the ASAM finalization steps required by the unfinalized flags are applied in place,
+//! a file history entry is added and the file identifier is set back to MDF.
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fs::OpenOptions;
+use std::io::{BufReader, Cursor, Read, Seek, SeekFrom, Write};
+
+use anyhow::{bail, Context, Result};
+use binrw::BinWriterExt;
+use log::{info, warn};
+
+use crate::error::{block_id_string, MdfError};
+use crate::mdfinfo::mdfinfo4::{parse_dz, BlockType, FhBlock, MetaData, MetaDataBlockType};
+
+/// file identifier of unfinalized files
+pub(crate) const UNFINALIZED_ID: &[u8; 8] = b"UnFinMF ";
+/// file identifier of finalized files
+pub(crate) const FINALIZED_ID: &[u8; 8] = b"MDF     ";
+/// update of cycle counters in CG/CA blocks required
+pub(crate) const UPDATE_CG_CYCLE_COUNT: u16 = 1;
+/// update of cycle counters in SR blocks required
+const UPDATE_SR_CYCLE_COUNT: u16 = 1 << 1;
+/// update of length for last DT block required
+pub(crate) const UPDATE_LAST_DT_LENGTH: u16 = 1 << 2;
+/// update of length for last RD block required
+const UPDATE_LAST_RD_LENGTH: u16 = 1 << 3;
+/// update of last DL block in each chained list of DL blocks required
+const UPDATE_LAST_DL: u16 = 1 << 4;
+/// update of cg_data_bytes and cg_inval_bytes in VLSD CG block required
+pub(crate) const UPDATE_VLSD_BYTES: u16 = 1 << 5;
+/// update of offset values for VLSD channel required
+const UPDATE_VLSD_OFFSETS: u16 = 1 << 6;
+
+/// mdf4 block header and links
+struct Block {
+    position: u64,
+    id: [u8; 4],
+    len: u64,
+    links: Vec<u64>,
+}
+
+impl Block {
+    fn data_position(&self) -> u64 {
+        self.position + 24 + 8 * self.links.len() as u64
+    }
+    fn link(&self, index: usize) -> u64 {
+        self.links.get(index).copied().unwrap_or(0)
+    }
+}
+
+/// channel group members needed to count records
+struct ChannelGroup {
+    position: u64,
+    data_position: u64,
+    record_id: u64,
+    vlsd: bool,
+    data_bytes: u64,
+    inval_bytes: u64,
+}
+
+struct Finalizer<R> {
+    rdr: R,
+    size: u64,
+    /// positions of all the blocks linked from the header
+    positions: BTreeSet<u64>,
+    /// bytes to be written at file positions
+    writes: Vec<(u64, Vec<u8>)>,
+}
+
+impl<R: Read + Seek> Finalizer<R> {
+    fn read_at(&mut self, position: u64, buf: &mut [u8]) -> Result<()> {
+        self.rdr
+            .seek(SeekFrom::Start(position))
+            .context("Could not reach block position")?;
+        self.rdr
+            .read_exact(buf)
+            .with_context(|| format!("Could not read {} bytes at {position}", buf.len()))
+    }
+    fn u64_at(&mut self, position: u64) -> Result<u64> {
+        let mut buf = [0u8; 8];
+        self.read_at(position, &mut buf)?;
+        Ok(u64::from_le_bytes(buf))
+    }
+    /// reads block header and links, None if position is not a block within the file
+    fn block(&mut self, position: u64) -> Result<Option<Block>> {
+        if position == 0 || position.saturating_add(24) > self.size {
+            return Ok(None);
+        }
+        let mut header = [0u8; 24];
+        self.read_at(position, &mut header)?;
+        let len = u64::from_le_bytes(header[8..16].try_into().unwrap_or_default());
+        let n_links = u64::from_le_bytes(header[16..24].try_into().unwrap_or_default());
+        if &header[..2] != b"##"
+            || position.saturating_add(24 + n_links.saturating_mul(8)) > self.size
+        {
+            return Ok(None);
+        }
+        let mut links = vec![0u8; n_links as usize * 8];
+        self.read_at(position + 24, &mut links)?;
+        let mut id = [0u8; 4];
+        id.copy_from_slice(&header[..4]);
+        Ok(Some(Block {
+            position,
+            id,
+            len,
+            links: links
+                .chunks(8)
+                .map(|l| u64::from_le_bytes(l.try_into().unwrap_or_default()))
+                .collect(),
+        }))
+    }
+    /// reads a block expected to be of the given type
+    fn expect_block(&mut self, position: u64, id: &[u8; 4]) -> Result<Block> {
+        match self.block(position)? {
+            Some(block) if &block.id == id => Ok(block),
+            _ => bail!(MdfError::Parse(format!(
+                "no {} block found at position {position}",
+                block_id_string(id)
+            ))),
+        }
+    }
+    /// collects positions of all the blocks linked from the header
+    fn walk(&mut self) -> Result<()> {
+        let mut stack = vec![64u64];
+        while let Some(position) = stack.pop() {
+            if !self.positions.insert(position) {
+                continue;
+            }
+            match self.block(position)? {
+                Some(block) => stack.extend(block.links.iter().filter(|l| **l != 0)),
+                None => {
+                    self.positions.remove(&position);
+                }
+            }
+        }
+        Ok(())
+    }
+    /// data block valid for reading, not exceeding file for compressed blocks
+    fn is_data_block(&mut self, position: u64) -> Result<bool> {
+        Ok(match self.block(position)? {
+            Some(block) => match &block.id {
+                b"##DT" | b"##SD" | b"##RD" | b"##DV" | b"##DI" => true,
+                b"##DZ" => block.position.saturating_add(block.len) <= self.size,
+                _ => false,
+            },
+            None => false,
+        })
+    }
+    /// rewrites in place the data list keeping its first valid data blocks
+    fn truncate_dl(&mut self, dl: &Block, n_valid: usize) -> Result<()> {
+        let n_data = dl.links.len().saturating_sub(1);
+        let mut members = vec![0u8; dl.len.saturating_sub(24 + 8 * dl.links.len() as u64) as usize];
+        self.read_at(dl.data_position(), &mut members)?;
+        let flags = members.first().copied().unwrap_or(0);
+        let mut new_members = vec![flags, 0, 0, 0];
+        new_members.extend_from_slice(&(n_valid as u32).to_le_bytes());
+        let mut offset = 8;
+        if flags & 1 == 1 {
+            new_members.extend_from_slice(members.get(8..16).unwrap_or(&[0u8; 8]));
+            offset += 8;
+        } else {
+            new_members.extend_from_slice(members.get(8..8 + 8 * n_valid).unwrap_or_default());
+            offset += 8 * n_data;
+        }
+        // time, angle and distance values
+        for bit in 1..4 {
+            if flags & (1 << bit) != 0 {
+                new_members.extend_from_slice(
+                    members
+                        .get(offset..offset + 8 * n_valid)
+                        .unwrap_or_default(),
+                );
+                offset += 8 * n_data;
+            }
+        }
+        let n_links = n_valid as u64 + 1;
+        let len = 24 + 8 * n_links + new_members.len() as u64;
+        let mut bytes = Vec::with_capacity(len as usize);
+        bytes.extend_from_slice(b"##DL\0\0\0\0");
+        bytes.extend_from_slice(&len.to_le_bytes());
+        bytes.extend_from_slice(&n_links.to_le_bytes());
+        for link in dl.links.iter().take(n_valid + 1) {
+            bytes.extend_from_slice(&link.to_le_bytes());
+        }
+        bytes.extend(new_members);
+        self.writes.push((dl.position, bytes));
+        info!(
+            "Data list at {} truncated to {n_valid} data blocks out of {n_data}",
+            dl.position
+        );
+        Ok(())
+    }
+    /// returns the data blocks of a data group in order, fixing the data lists if required
+    fn data_blocks(&mut self, link: u64, flags: u16) -> Result<Vec<Block>> {
+        let Some(mut block) = self.block(link)? else {
+            return Ok(Vec::new());
+        };
+        if &block.id == b"##HL" {
+            block = self.expect_block(block.link(0), b"##DL")?;
+        }
+        let mut blocks = Vec::new();
+        let mut visited = HashSet::new();
+        if &block.id == b"##LD" {
+            // column storage, data values blocks are followed by the invalidation blocks
+            let mut ld = Some(block);
+            while let Some(list) = ld.filter(|b| &b.id == b"##LD") {
+                if !visited.insert(list.position) {
+                    break;
+                }
+                let mut count = [0u8; 4];
+                self.read_at(list.data_position() + 4, &mut count)?;
+                for data in list
+                    .links
+                    .iter()
+                    .skip(1)
+                    .take(u32::from_le_bytes(count) as usize)
+                {
+                    blocks.push(self.expect_block(*data, b"##DV")?);
+                }
+                ld = self.block(list.link(0))?;
+            }
+            return Ok(blocks);
+        }
+        if &block.id != b"##DL" {
+            return Ok(vec![block]);
+        }
+        let mut dl = Some(block);
+        while let Some(list) = dl.filter(|b| &b.id == b"##DL") {
+            if !visited.insert(list.position) {
+                break;
+            }
+            let mut n_valid = 0;
+            for data in list.links.iter().skip(1) {
+                if !self.is_data_block(*data)? {
+                    break;
+                }
+                n_valid += 1;
+            }
+            if n_valid + 1 < list.links.len() {
+                if flags & UPDATE_LAST_DL == 0 {
+                    bail!(MdfError::CorruptData(format!(
+                        "data list at {} is referencing invalid data blocks",
+                        list.position
+                    )));
+                }
+                self.truncate_dl(&list, n_valid)?;
+            }
+            for data in list.links.iter().skip(1).take(n_valid) {
+                if let Some(block) = self.block(*data)? {
+                    blocks.push(block);
+                }
+            }
+            if n_valid + 1 < list.links.len() {
+                break;
+            }
+            dl = self.block(list.link(0))?;
+        }
+        Ok(blocks)
+    }
+    /// length of the last DT or DV block of the file extended until file end
+    fn fix_last_dt(&mut self, blocks: &mut [Block]) {
+        if let Some(last) = blocks.last_mut() {
+            if matches!(&last.id, b"##DT" | b"##DV")
+                && self.positions.range(last.position + 1..).next().is_none()
+            {
+                last.len = self.size - last.position;
+            }
+        }
+    }
+    /// reads the decompressed bytes of the data blocks
+    fn read_data(&mut self, blocks: &[Block]) -> Result<Vec<u8>> {
+        let mut data = Vec::new();
+        for block in blocks {
+            match &block.id {
+                b"##DT" => {
+                    let mut buf = vec![0u8; (block.len - 24) as usize];
+                    self.read_at(block.data_position(), &mut buf)?;
+                    data.extend(buf);
+                }
+                b"##DZ" => {
+                    self.rdr
+                        .seek(SeekFrom::Start(block.position + 4))
+                        .context("Could not reach DZ block")?;
+                    let mut rdr = BufReader::new(&mut self.rdr);
+                    let (buf, _) = parse_dz(&mut rdr)?;
+                    data.extend(buf);
+                }
+                id => bail!(MdfError::UnsupportedBlock(format!(
+                    "{} data block in unsorted data group",
+                    block_id_string(id)
+                ))),
+            }
+        }
+        Ok(data)
+    }
+    /// length of data contained in the blocks and true if data values are stored in columns
+    fn data_length(&mut self, blocks: &[Block]) -> Result<(u64, bool)> {
+        let mut length = 0;
+        let mut column = false;
+        for block in blocks {
+            length += match &block.id {
+                b"##DT" => block.len - 24,
+                b"##DV" => {
+                    column = true;
+                    block.len - 24
+                }
+                b"##DZ" => {
+                    let mut original_id = [0u8; 2];
+                    self.read_at(block.data_position(), &mut original_id)?;
+                    column = &original_id == b"DV";
+                    self.u64_at(block.data_position() + 8)?
+                }
+                id => bail!(MdfError::UnsupportedBlock(format!(
+                    "{} data block for finalization",
+                    block_id_string(id)
+                ))),
+            };
+        }
+        Ok((length, column))
+    }
+    /// sets the length of the last DT or DV block, removing the trailing incomplete record bytes
+    fn write_last_dt(&mut self, blocks: &[Block], excess: u64) {
+        if let Some(last) = blocks.last() {
+            if matches!(&last.id, b"##DT" | b"##DV") {
+                let len = last.len - excess.min(last.len - 24);
+                self.writes
+                    .push((last.position + 8, len.to_le_bytes().to_vec()));
+            }
+        }
+    }
+    /// recomputes the cycle counts of the channel groups of a data group from its data
+    fn finalize_dg(&mut self, dg: &Block, flags: u16) -> Result<()> {
+        let mut record_id_size = [0u8; 1];
+        self.read_at(dg.data_position(), &mut record_id_size)?;
+        let record_id_size = record_id_size[0] as u64;
+        let mut cgs = Vec::new();
+        let mut cg_position = dg.link(1);
+        let mut visited = HashSet::new();
+        while cg_position != 0 && visited.insert(cg_position) {
+            let cg = self.expect_block(cg_position, b"##CG")?;
+            let mut members = [0u8; 32];
+            self.read_at(cg.data_position(), &mut members)?;
+            let vlsd = members[16] & 1 == 1;
+            let data_bytes = u32::from_le_bytes(members[24..28].try_into().unwrap_or_default());
+            let inval_bytes = u32::from_le_bytes(members[28..32].try_into().unwrap_or_default());
+            cgs.push(ChannelGroup {
+                position: cg.position,
+                data_position: cg.data_position(),
+                record_id: u64::from_le_bytes(members[..8].try_into().unwrap_or_default()),
+                vlsd,
+                data_bytes: data_bytes as u64,
+                inval_bytes: inval_bytes as u64,
+            });
+            cg_position = cg.link(0);
+        }
+        let mut blocks = self.data_blocks(dg.link(2), flags)?;
+        if flags & UPDATE_LAST_DT_LENGTH != 0 {
+            self.fix_last_dt(&mut blocks);
+        }
+        if flags & (UPDATE_CG_CYCLE_COUNT | UPDATE_VLSD_BYTES | UPDATE_LAST_DT_LENGTH) == 0 {
+            return Ok(());
+        }
+        // cycle count and VLSD bytes by channel group position
+        let mut counts: HashMap<u64, (u64, u64)> = HashMap::new();
+        if let [cg] = cgs.as_slice() {
+            if !cg.vlsd {
+                let (length, column) = self.data_length(&blocks)?;
+                // column storage has no record id and invalidation bytes in values blocks
+                let record_size = if column {
+                    cg.data_bytes
+                } else {
+                    record_id_size + cg.data_bytes + cg.inval_bytes
+                };
+                let cycle_count = length.checked_div(record_size).unwrap_or(0);
+                counts.insert(cg.position, (cycle_count, 0));
+                if record_size > 0 {
+                    self.write_last_dt(&blocks, length - cycle_count * record_size);
+                }
+            }
+        }
+        if counts.is_empty() && !cgs.is_empty() {
+            // unsorted data group, records are identified by their record id
+            if record_id_size == 0 {
+                bail!(MdfError::Parse(
+                    "unsorted data group without record id".to_string()
+                ));
+            }
+            let data = self.read_data(&blocks)?;
+            let by_id: HashMap<u64, &ChannelGroup> =
+                cgs.iter().map(|cg| (cg.record_id, cg)).collect();
+            let id_size = record_id_size as usize;
+            let mut position = 0usize;
+            while position + id_size <= data.len() {
+                let mut id = [0u8; 8];
+                id[..id_size].copy_from_slice(&data[position..position + id_size]);
+                let Some(cg) = by_id.get(&u64::from_le_bytes(id)) else {
+                    break;
+                };
+                let record_length = if cg.vlsd {
+                    let Some(length) = data.get(position + id_size..position + id_size + 4) else {
+                        break;
+                    };
+                    4 + u32::from_le_bytes(length.try_into().unwrap_or_default()) as usize
+                } else {
+                    (cg.data_bytes + cg.inval_bytes) as usize
+                };
+                if position + id_size + record_length > data.len() {
+                    break;
+                }
+                let count = counts.entry(cg.position).or_default();
+                count.0 += 1;
+                // VLSD size does not include the values length
+                count.1 += record_length as u64 - 4 * cg.vlsd as u64;
+                position += id_size + record_length;
+            }
+            self.write_last_dt(&blocks, (data.len() - position) as u64);
+        }
+        for cg in cgs.iter() {
+            let (cycle_count, vlsd_bytes) = counts.get(&cg.position).copied().unwrap_or_default();
+            self.writes
+                .push((cg.data_position + 8, cycle_count.to_le_bytes().to_vec()));
+            if cg.vlsd && flags & UPDATE_VLSD_BYTES != 0 {
+                self.writes
+                    .push((cg.data_position + 24, vlsd_bytes.to_le_bytes().to_vec()));
+            }
+        }
+        Ok(())
+    }
+    /// appends a file history block at file end and links it after the last one
+    fn add_file_history(&mut self) -> Result<()> {
+        let hd = self.expect_block(64, b"##HD")?;
+        let mut link_position = hd.position + 24 + 8; // hd_fh_first
+        let mut fh_position = hd.link(1);
+        let mut visited = HashSet::new();
+        while fh_position != 0 && visited.insert(fh_position) {
+            let fh = self.expect_block(fh_position, b"##FH")?;
+            link_position = fh.position + 24; // fh_fh_next
+            fh_position = fh.link(0);
+        }
+        let position = self.size + (8 - self.size % 8) % 8;
+        let mut fh = FhBlock::default();
+        fh.fh_md_comment = position as i64 + 56;
+        let mut comments = MetaData::new(MetaDataBlockType::MdBlock, BlockType::FH);
+        comments.create_fh("finalized");
+        let mut buffer = Cursor::new(vec![0u8; (position - self.size) as usize]);
+        buffer.seek(SeekFrom::End(0))?;
+        buffer.write_le(&fh).context("Could not write FHBlock")?;
+        comments.write(&mut buffer)?;
+        self.writes.push((self.size, buffer.into_inner()));
+        self.writes
+            .push((link_position, position.to_le_bytes().to_vec()));
+        Ok(())
+    }
+}
+
+/// finalizes in place an unfinalized mdf4 file: recomputes the cycle counts from data length,
+/// fixes the last DT block length and data lists, updates the file history and identifier.
+/// Returns false if the file was already finalized
+pub fn finalize4(file_name: &str) -> Result<bool> {
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(file_name)
+        .map_err(MdfError::Io)
+        .with_context(|| format!("Cannot open the file {file_name} for writing"))?;
+    let mut rdr = BufReader::new(&file);
+    let size = rdr
+        .seek(SeekFrom::End(0))
+        .context("Could not reach end of file")?;
+    let mut finalizer = Finalizer {
+        rdr,
+        size,
+        positions: BTreeSet::new(),
+        writes: Vec::new(),
+    };
+    if size < 64 {
+        bail!(MdfError::Parse(format!(
+            "{file_name} is too small to be a mdf file"
+        )));
+    }
+    let mut id = [0u8; 64];
+    finalizer.read_at(0, &mut id)?;
+    let flags = u16::from_le_bytes([id[60], id[61]]);
+    let custom_flags = u16::from_le_bytes([id[62], id[63]]);
+    let version = u16::from_le_bytes([id[28], id[29]]);
+    if &id[..8] != UNFINALIZED_ID {
+        if &id[..3] == b"MDF" && flags == 0 && custom_flags == 0 {
+            info!("File {file_name} is already finalized");
+            return Ok(false);
+        } else if &id[..3] != b"MDF" {
+            bail!(MdfError::Parse(format!(
+                "{file_name} is not a mdf file, file identifier is {:?}",
+                block_id_string(&id[..8])
+            )));
+        }
+    }
+    if version < 400 {
+        bail!("finalization is only available for mdf4 files");
+    }
+    for (flag, step) in [
+        (UPDATE_SR_CYCLE_COUNT, "sample reduction cycle counts"),
+        (UPDATE_LAST_RD_LENGTH, "last RD block length"),
+        (UPDATE_VLSD_OFFSETS, "VLSD channels offsets"),
+    ] {
+        if flags & flag != 0 {
+            bail!(MdfError::UnsupportedBlock(format!(
+                "finalization step update of {step} required by file {file_name}"
+            )));
+        }
+    }
+    if custom_flags != 0 {
+        warn!("custom unfinalized flags {custom_flags:#x} of file {file_name} are ignored");
+    }
+
+    finalizer.walk()?;
+    let hd = finalizer.expect_block(64, b"##HD")?;
+    let mut dg_position = hd.link(0);
+    let mut visited = HashSet::new();
+    while dg_position != 0 && visited.insert(dg_position) {
+        let dg = finalizer.expect_block(dg_position, b"##DG")?;
+        finalizer
+            .finalize_dg(&dg, flags)
+            .with_context(|| format!("failed finalizing data group at {dg_position}"))?;
+        dg_position = dg.link(0);
+    }
+    finalizer.add_file_history()?;
+    finalizer.writes.push((0, FINALIZED_ID.to_vec()));
+    finalizer.writes.push((60, vec![0u8; 4]));
+
+    let writes = finalizer.writes;
+    drop(finalizer.rdr);
+    let mut writer = &file;
+    for (position, bytes) in writes {
+        writer
+            .seek(SeekFrom::Start(position))
+            .context("Could not reach position to write")?;
+        writer
+            .write_all(&bytes)
+            .with_context(|| format!("Could not write finalized block at {position}"))?;
+    }
+    writer.flush().context("Could not flush finalized file")?;
+    info!("Finalized file {file_name} having unfinalized flags {flags:#x}");
+    Ok(true)
+}
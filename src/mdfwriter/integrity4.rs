@@ -0,0 +1,146 @@
+//! Integrity of mdf4 files: MD5 hash of the whole file stored in the header comment for
+//! traceability. The header comment with the hash property is appended at the end of the file,
+//! the hash being computed with its hexadecimal text replaced by zeros.
+use std::fs::OpenOptions;
+use std::io::{BufReader, Read, Seek, SeekFrom, Write};
+
+use anyhow::{bail, Context, Result};
+use md5::{Digest, Md5};
+
+use crate::error::MdfError;
+use crate::mdfinfo::mdfinfo4::{BlockType, MdfInfo4, MetaData, MetaDataBlockType};
+
+/// header comment property holding the file hash
+pub const FILE_HASH_PROPERTY: &str = "file_md5";
+/// position of the link to the header comment, in HD block at 64
+const HD_MD_COMMENT: u64 = 64 + 24 + 5 * 8;
+/// hash text while hashing
+const HASH_PLACEHOLDER: [u8; 32] = [b'0'; 32];
+
+/// MD5 hash in hexadecimal of the file, the 32 bytes at hash_position replaced by zeros
+fn file_md5<R: Read>(rdr: &mut R, hash_position: u64) -> Result<String> {
+    let mut hasher = Md5::new();
+    let mut buffer = vec![0u8; 1 << 16];
+    let mut position: u64 = 0;
+    loop {
+        let n = rdr
+            .read(&mut buffer)
+            .context("Could not read file to hash")?;
+        if n == 0 {
+            break;
+        }
+        let chunk = &mut buffer[..n];
+        // placeholder overlapping the chunk
+        let start = hash_position.max(position);
+        let end = (hash_position + 32).min(position + n as u64);
+        if start < end {
+            chunk[(start - position) as usize..(end - position) as usize].fill(b'0');
+        }
+        hasher.update(chunk);
+        position += n as u64;
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// file position of the hash text in the header comment block at md_position
+fn hash_position<R: Read + Seek>(rdr: &mut R, md_position: u64, hash: &[u8]) -> Result<u64> {
+    let mut header = [0u8; 24];
+    rdr.seek(SeekFrom::Start(md_position))
+        .context("Could not reach header comment")?;
+    rdr.read_exact(&mut header)
+        .context("Could not read header comment block header")?;
+    let len = u64::from_le_bytes(header[8..16].try_into()?);
+    let mut data = vec![0u8; len.saturating_sub(24) as usize];
+    rdr.read_exact(&mut data)
+        .context("Could not read header comment")?;
+    let offset = data
+        .windows(hash.len())
+        .position(|window| window == hash)
+        .context("hash not found in header comment")?;
+    Ok(md_position + 24 + offset as u64)
+}
+
+/// stores in place the MD5 hash of the whole file in its header comment, returns the hash
+pub fn store_file_hash4(info: &mut MdfInfo4) -> Result<String> {
+    let mut comments = info
+        .header_comments()
+        .context("failed reading header comments")?
+        .unwrap_or_default();
+    comments.insert(
+        FILE_HASH_PROPERTY.to_string(),
+        String::from_utf8_lossy(&HASH_PLACEHOLDER).to_string(),
+    );
+    let mut md = MetaData::new(MetaDataBlockType::MdBlock, BlockType::HD);
+    md.create_hd(&comments);
+
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(&info.file_name)
+        .map_err(MdfError::Io)
+        .with_context(|| format!("Cannot open the file {} for writing", info.file_name))?;
+    let end = file
+        .seek(SeekFrom::End(0))
+        .context("Could not reach end of file")?;
+    let md_position = end.next_multiple_of(8);
+    let mut bytes = vec![0u8; (md_position - end) as usize];
+    let mut buffer = std::io::Cursor::new(Vec::new());
+    md.write(&mut buffer)?;
+    bytes.extend(buffer.into_inner());
+    file.write_all(&bytes)
+        .context("Could not append header comment")?;
+    file.seek(SeekFrom::Start(HD_MD_COMMENT))
+        .context("Could not reach header comment link")?;
+    file.write_all(&md_position.to_le_bytes())
+        .context("Could not write header comment link")?;
+    file.flush().context("Could not flush file")?;
+
+    let mut rdr = BufReader::new(&file);
+    let position = hash_position(&mut rdr, md_position, &HASH_PLACEHOLDER)?;
+    rdr.seek(SeekFrom::Start(0))
+        .context("Could not reach start of file")?;
+    let hash = file_md5(&mut rdr, position)?;
+    drop(rdr);
+    file.seek(SeekFrom::Start(position))
+        .context("Could not reach hash position")?;
+    file.write_all(hash.as_bytes())
+        .context("Could not write hash")?;
+    file.flush().context("Could not flush file")?;
+
+    comments.insert(FILE_HASH_PROPERTY.to_string(), hash.clone());
+    md.create_hd(&comments);
+    info.hd_block.hd_md_comment = md_position as i64;
+    info.sharable.md_tx.insert(md_position as i64, md);
+    info.sharable.parse_hd_comments(md_position as i64);
+    Ok(hash)
+}
+
+/// verifies the MD5 hash stored in the header comment, None if the file has no hash
+pub fn verify_file_hash4(info: &MdfInfo4) -> Result<Option<bool>> {
+    let Some(hash) = info
+        .header_comments()
+        .context("failed reading header comments")?
+        .and_then(|mut comments| comments.remove(FILE_HASH_PROPERTY))
+    else {
+        return Ok(None);
+    };
+    if hash.len() != HASH_PLACEHOLDER.len() {
+        bail!(MdfError::CorruptData(format!(
+            "file hash {hash} is not a MD5 hash"
+        )));
+    }
+    let file = OpenOptions::new()
+        .read(true)
+        .open(&info.file_name)
+        .map_err(MdfError::Io)
+        .with_context(|| format!("Cannot open the file {}", info.file_name))?;
+    let mut rdr = BufReader::new(file);
+    let position = hash_position(
+        &mut rdr,
+        info.hd_block.hd_md_comment as u64,
+        hash.as_bytes(),
+    )?;
+    rdr.seek(SeekFrom::Start(0))
+        .context("Could not reach start of file")?;
+    Ok(Some(file_md5(&mut rdr, position)? == hash))
+}
@@ -1,21 +1,47 @@
-//! Converter of mdf version 3.x into mdf version 4.2
+//! Converter of mdf version 3.x into mdf version 4.2 and writer of mdf version 3.x
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{copy, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+
 use crate::data_holder::tensor_arrow::Order;
 use crate::mdfinfo::mdfinfo3::{convert_data_type_3to4, Cg3, Cn3, Dg3};
-use crate::mdfreader::{DataSignature, MasterSignature};
+use crate::mdfreader::{DataSignature, HeaderOptions, MasterSignature};
 
 use crate::mdfinfo::{
     mdfinfo3::MdfInfo3,
-    mdfinfo4::{FhBlock, MdfInfo4},
+    mdfinfo4::{
+        position_generator, At4Block, BlockType, FhBlock, MdfInfo4, MetaData, MetaDataBlockType,
+    },
+    MdfInfo,
 };
-use anyhow::{Context, Error, Result};
+use crate::mdfreader::Mdf;
+use anyhow::{bail, Context, Error, Result};
+
+/// file name of the attachment keeping the mdf3 program block
+pub const PROGRAM_BLOCK_FILE_NAME: &str = "mdf3_program_block.bin";
 
 /// Converts mdfinfo3 into mdfinfo4
 pub fn convert3to4(mdf3: &MdfInfo3, file_name: &str) -> Result<MdfInfo4, Error> {
     let n_channels = mdf3.get_channel_names_set().len();
     let mut mdf4 = MdfInfo4::new(file_name, n_channels);
     // FH
-    let fh = FhBlock::default();
+    let mut fh = FhBlock::default();
+    fh.fh_md_comment = position_generator();
+    let mut fh_comments = MetaData::new(MetaDataBlockType::MdBlock, BlockType::FH);
+    fh_comments.create_fh("converted from mdf version 3");
+    mdf4.sharable.md_tx.insert(fh.fh_md_comment, fh_comments);
     mdf4.fh.push(fh);
+    // HD, program identifier, properties and start time kept
+    mdf4.id_block.id_prog = mdf3.id_block.id_prog;
+    let non_empty = |text: &String| (!text.is_empty()).then(|| text.clone());
+    mdf4.set_header(&HeaderOptions {
+        program: None,
+        author: non_empty(&mdf3.hd_block.hd_author),
+        project: non_empty(&mdf3.hd_block.hd_project),
+        subject: non_empty(&mdf3.hd_block.hd_subject),
+        comment: non_empty(&mdf3.hd_comment),
+        start_time: mdf3.hd_block.start_time().ok(),
+    })?;
 
     mdf3.dg.iter().try_for_each(
         |(_dg_block_position, dg): (&u32, &Dg3)| -> Result<(), Error> {
@@ -48,13 +74,14 @@ pub fn convert3to4(mdf3: &MdfInfo3, file_name: &str) -> Result<MdfInfo4, Error>
                                 };
                                 mdf4.add_channel(
                                     master_channel_name.clone(),
-                                    cn.data.clone(),
+                                    cn.physical_data(&mdf3.sharable)?.clone(),
                                     data_signature,
                                     master_signature,
                                     unit,
                                     desc,
                                 )
                                 .context("Failed adding channel")?;
+                                convert_source(mdf3, &mut mdf4, cn, master_channel_name);
                             }
                         }
                     }
@@ -85,12 +112,13 @@ pub fn convert3to4(mdf3: &MdfInfo3, file_name: &str) -> Result<MdfInfo4, Error>
                             };
                             mdf4.add_channel(
                                 cn.unique_name.clone(),
-                                cn.data.clone(),
+                                cn.physical_data(&mdf3.sharable)?.clone(),
                                 data_signature,
                                 master_signature,
                                 unit,
                                 desc,
                             )?;
+                            convert_source(mdf3, &mut mdf4, cn, &cn.unique_name);
                             Ok(())
                         })
                         .context("Failed adding channels")?;
@@ -99,5 +127,264 @@ pub fn convert3to4(mdf3: &MdfInfo3, file_name: &str) -> Result<MdfInfo4, Error>
             Ok(())
         },
     )?;
+    // program block kept as embedded attachment
+    if let Some(program) = &mdf3.program_block {
+        let mut at_block = At4Block::new_embedded(program);
+        at_block.at_tx_filename = position_generator();
+        mdf4.sharable
+            .create_tx(at_block.at_tx_filename, PROGRAM_BLOCK_FILE_NAME.to_string());
+        at_block.at_tx_mimetype = position_generator();
+        mdf4.sharable.create_tx(
+            at_block.at_tx_mimetype,
+            "application/octet-stream".to_string(),
+        );
+        mdf4.at
+            .insert(position_generator(), (at_block, Some(program.clone())));
+    }
     Ok(mdf4)
 }
+
+/// converts the CE block of the mdf3 channel into the SI block of the mdf4 channel
+fn convert_source(mdf3: &MdfInfo3, mdf4: &mut MdfInfo4, cn: &Cn3, channel_name: &str) {
+    let Some(ce) = mdf3.sharable.ce.get(&cn.block1.cn_ce_source) else {
+        return;
+    };
+    let si = ce.to_si4(&mut mdf4.sharable);
+    if let Some((_master, dg_pos, (_cg_pos, rec_id), (_cn_pos, rec_pos))) =
+        mdf4.channel_names_set.get(channel_name)
+    {
+        if let Some(cn4) = mdf4
+            .dg
+            .get_mut(dg_pos)
+            .and_then(|dg| dg.cg.get_mut(rec_id))
+            .and_then(|cg| cg.cn.get_mut(rec_pos))
+        {
+            let position = position_generator();
+            mdf4.sharable.si.insert(position, si);
+            cn4.block.cn_si_source = position;
+        }
+    }
+}
+
+/// writes mdf3 file, copying the blocks of the source file with relocated links.
+/// Conversion (CC), extension (CE), text blocks and raw data records are kept bit-exact,
+/// data modified in memory is not written. Sample reduction blocks are not copied.
+pub fn mdfwriter3(mdf: &Mdf, file_name: &str) -> Result<Mdf> {
+    let info = match &mdf.mdf_info {
+        MdfInfo::V3(mdfinfo3) => mdfinfo3,
+        MdfInfo::V4(_) => bail!("only mdf version 3.x files can be written as version 3.x"),
+    };
+    if let (Ok(source), Ok(target)) = (
+        std::fs::canonicalize(&info.file_name),
+        std::fs::canonicalize(file_name),
+    ) {
+        if source == target {
+            bail!("cannot overwrite source file {}", info.file_name);
+        }
+    }
+    let source = File::open(&info.file_name)
+        .with_context(|| format!("Cannot open source file {}", info.file_name))?;
+    let mut copier = BlockCopier3 {
+        rdr: BufReader::new(source),
+        buffer: Vec::new(),
+        relocated: HashMap::new(),
+        pending: Vec::new(),
+        data: Vec::new(),
+    };
+    // ID block is kept as is, HD block always follows it
+    let mut id_block = [0u8; 64];
+    copier
+        .rdr
+        .read_exact(&mut id_block)
+        .context("Could not read IdBlock")?;
+    copier.buffer.extend_from_slice(&id_block);
+    copier.copy(64)?;
+    copier.resolve()?;
+
+    // data blocks are placed after all metadata blocks
+    let mut data_position = copier.buffer.len() as u32;
+    let mut data_blocks: Vec<(u32, u64)> = Vec::with_capacity(copier.data.len());
+    for (link_position, source_position) in std::mem::take(&mut copier.data) {
+        let length: u64 = info
+            .dg
+            .get(&source_position)
+            .map(|dg| {
+                dg.cg
+                    .values()
+                    .map(|cg| cg.block.cg_cycle_count as u64 * cg.record_length as u64)
+                    .sum()
+            })
+            .unwrap_or(0);
+        if length == 0 {
+            copier.patch(link_position, 0);
+            continue;
+        }
+        copier.patch(link_position, data_position);
+        data_blocks.push((source_position, length));
+        data_position = data_position
+            .checked_add(length as u32)
+            .context("data exceeds mdf version 3.x maximum file size")?;
+    }
+
+    let f: File = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(file_name)
+        .context("Cannot create the file")?;
+    let mut writer = BufWriter::new(f);
+    writer
+        .write_all(&copier.buffer)
+        .context("Could not write metadata blocks")?;
+    for (source_position, length) in data_blocks {
+        copier
+            .rdr
+            .seek(SeekFrom::Start(source_position as u64))
+            .context("Could not reach data block position")?;
+        let copied = copy(&mut (&mut copier.rdr).take(length), &mut writer)
+            .context("Could not copy data block")?;
+        if copied != length {
+            bail!("data block at position {} is truncated", source_position);
+        }
+    }
+    writer.flush().context("Could not flush file")?;
+    Mdf::new(file_name)
+}
+
+/// copies blocks from the source file, relocating their links
+struct BlockCopier3 {
+    rdr: BufReader<File>,
+    /// new file content
+    buffer: Vec<u8>,
+    /// source block position to new block position
+    relocated: HashMap<u32, u32>,
+    /// links to be resolved: position of link in buffer and source block position
+    pending: Vec<(usize, u32)>,
+    /// data links: position of link in buffer and source data position
+    data: Vec<(usize, u32)>,
+}
+
+impl BlockCopier3 {
+    /// copies block at source position if not already done, returns its new position
+    fn copy(&mut self, source_position: u32) -> Result<u32> {
+        if source_position == 0 {
+            return Ok(0);
+        }
+        if let Some(position) = self.relocated.get(&source_position) {
+            return Ok(*position);
+        }
+        self.rdr
+            .seek(SeekFrom::Start(source_position as u64))
+            .context("Could not reach block position")?;
+        let mut header = [0u8; 4];
+        self.rdr
+            .read_exact(&mut header)
+            .context("Could not read block header")?;
+        let block_length = u16::from_le_bytes([header[2], header[3]]) as usize;
+        if block_length < 4 {
+            bail!(
+                "block {} at position {} has invalid length {}",
+                String::from_utf8_lossy(&header[0..2]),
+                source_position,
+                block_length
+            );
+        }
+        let mut block = vec![0u8; block_length];
+        block[0..4].copy_from_slice(&header);
+        self.rdr
+            .read_exact(&mut block[4..])
+            .context("Could not read block")?;
+
+        let position = self.buffer.len();
+        let new_position = u32::try_from(position)
+            .context("metadata exceeds mdf version 3.x maximum file size")?;
+        self.relocated.insert(source_position, new_position);
+        let (links, data_link, null_links) = block_links3(&block);
+        self.buffer.extend_from_slice(&block);
+        for offset in links {
+            self.pending
+                .push((position + offset, read_link(&block, offset)));
+        }
+        if let Some(offset) = data_link {
+            self.data
+                .push((position + offset, read_link(&block, offset)));
+        }
+        for offset in null_links {
+            self.patch(position + offset, 0);
+        }
+        Ok(new_position)
+    }
+    /// copies all blocks linked from the already copied blocks
+    fn resolve(&mut self) -> Result<()> {
+        while let Some((link_position, source_position)) = self.pending.pop() {
+            let new_position = self
+                .copy(source_position)
+                .with_context(|| format!("failed copying block at position {}", source_position))?;
+            self.patch(link_position, new_position);
+        }
+        Ok(())
+    }
+    /// writes link value at position in buffer
+    fn patch(&mut self, link_position: usize, value: u32) {
+        self.buffer[link_position..link_position + 4].copy_from_slice(&value.to_le_bytes());
+    }
+}
+
+/// reads link at offset in block
+fn read_link(block: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([
+        block[offset],
+        block[offset + 1],
+        block[offset + 2],
+        block[offset + 3],
+    ])
+}
+
+/// returns block link offsets to be relocated, the offset of data link and links to be nulled
+fn block_links3(block: &[u8]) -> (Vec<usize>, Option<usize>, Vec<usize>) {
+    let length = block.len();
+    // keeps only links fully included in block
+    let within = |offsets: Vec<usize>| -> Vec<usize> {
+        offsets
+            .into_iter()
+            .filter(|offset| offset + 4 <= length)
+            .collect()
+    };
+    match &block[0..2] {
+        b"HD" => (within(vec![4, 8, 12]), None, Vec::new()),
+        b"DG" => (
+            within(vec![4, 8, 12]),
+            Some(16).filter(|offset| offset + 4 <= length),
+            Vec::new(),
+        ),
+        // sample reduction blocks are not copied
+        b"CG" => (within(vec![4, 8, 12]), None, within(vec![26])),
+        b"CN" => (within(vec![4, 8, 12, 16, 20, 218, 222]), None, Vec::new()),
+        b"CC" if length >= 46 => {
+            let cc_type = u16::from_le_bytes([block[42], block[43]]);
+            let cc_size = u16::from_le_bytes([block[44], block[45]]) as usize;
+            if cc_type == 12 {
+                // text range table, each range has a TX block
+                (
+                    within((0..cc_size).map(|i| 46 + i * 20 + 16).collect()),
+                    None,
+                    Vec::new(),
+                )
+            } else {
+                (Vec::new(), None, Vec::new())
+            }
+        }
+        b"CD" if length >= 8 => {
+            // dependencies are triples of DG, CG and CN links
+            let n_dependencies = u16::from_le_bytes([block[6], block[7]]) as usize;
+            (
+                within((0..n_dependencies * 3).map(|i| 8 + i * 4).collect()),
+                None,
+                Vec::new(),
+            )
+        }
+        b"TR" => (within(vec![4]), None, Vec::new()),
+        // TX, PR, CE and unknown blocks have no links
+        _ => (Vec::new(), None, Vec::new()),
+    }
+}
@@ -1,38 +1,144 @@
-//! Writer of data in memory into mdf4.2 file
+//! Writer of data in memory into mdf4.2 file.
+//! Data is column oriented: each channel is written in its own data group with a DV block
+//! of its values, or a LD list of DZ compressed or DV values and DI invalidation blocks.
+//! Strings and byte arrays are variable length channels with DT records of offsets
+//! to their values in a SD block, both possibly DZ compressed.
+//! Events and attachments are kept with their linked text and metadata blocks
+#[cfg(not(feature = "single-thread"))]
+use std::thread::{self, JoinHandle};
 use std::{
     collections::{HashMap, HashSet},
     fs::OpenOptions,
     io::{BufWriter, Cursor, Seek, SeekFrom, Write},
     ops::Deref,
     sync::Arc,
-    thread,
 };
 
+use crate::parallel::prelude::*;
 use crate::{
-    data_holder::channel_data::{data_type_init, ChannelData},
+    data_holder::{
+        channel_data::{data_type_init, try_from, ChannelData},
+        tensor_arrow::Order,
+    },
     mdfinfo::{
         mdfinfo4::{
-            default_short_header, BlockType, Blockheader4, Ca4Block, Ca4BlockMembers, Cg4,
-            Cg4Block, Cn4, Cn4Block, Compo, Composition, Dg4, Dg4Block, Dz4Block, FhBlock,
-            Ld4Block, MdfInfo4, MetaData, MetaDataBlockType,
+            default_short_header, At4Block, BlockType, Blockheader4, Ca4Block, Ca4BlockMembers,
+            Cg4, Cg4Block, Cn4, Cn4Block, Compo, Composition, Dg4, Dg4Block, Dz4Block, Ev4Block,
+            EventScope, FhBlock, FileHistory, Ld4Block, MdfInfo4, MetaData, MetaDataBlockType,
+            Sr4Block, DZ_DEFLATE, DZ_TRANSPOSED_DEFLATE, DZ_TRANSPOSED_ZSTD, DZ_ZSTD,
         },
         MdfInfo,
     },
     mdfreader::Mdf,
 };
-use anyhow::{bail, Context, Error, Result};
+use anyhow::{anyhow, bail, Context, Error, Result};
+use arrow::array::{Array, AsArray, Float64Builder, StringArray};
 use arrow::buffer::NullBuffer;
+use arrow::compute::cast;
+use arrow::datatypes::{DataType, Float64Type};
 use binrw::BinWriterExt;
-use crossbeam_channel::bounded;
+#[cfg(not(feature = "single-thread"))]
+use crossbeam_channel::{bounded, Sender};
 use parking_lot::Mutex;
-use rayon::iter::{IntoParallelRefMutIterator, ParallelIterator};
 use std::fs::File;
 use yazi::{CompressionLevel, Encoder, Format};
 
 use super::mdfwriter3::convert3to4;
 
-/// writes mdf4.2 file
-pub fn mdfwriter4(mdf: &Mdf, file_name: &str, compression: bool) -> Result<Mdf> {
+/// deflate level used when compression is only enabled, best size
+pub const DEFAULT_DEFLATE_LEVEL: u8 = 9;
+/// zstd level used when zstd compression is only enabled, good ratio at reasonable speed
+pub const DEFAULT_ZSTD_LEVEL: i32 = 9;
+
+/// compression of the data blocks written in DZ blocks, deflate level from 0 (none) to 10
+/// (best size), zstd level from 1 to 22 (best size).
+/// A block is written uncompressed when compression does not reduce its size
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Compression {
+    /// DV and DI blocks
+    #[default]
+    None,
+    /// deflate of the data bytes
+    Deflate { level: u8 },
+    /// deflate of the data bytes transposed by value size, grouping the bytes of same
+    /// significance for better ratios on numeric data
+    TransposeDeflate { level: u8 },
+    /// zstandard of the data bytes, faster to decompress than deflate
+    Zstd { level: i32 },
+    /// zstandard of the data bytes transposed by value size
+    TransposeZstd { level: i32 },
+}
+
+impl From<bool> for Compression {
+    fn from(compression: bool) -> Self {
+        if compression {
+            Compression::Deflate {
+                level: DEFAULT_DEFLATE_LEVEL,
+            }
+        } else {
+            Compression::None
+        }
+    }
+}
+
+impl Compression {
+    /// compresses the bytes, None if no compression
+    fn compress(&self, bytes: &[u8]) -> Result<Option<Vec<u8>>> {
+        match self {
+            Compression::None => Ok(None),
+            Compression::Deflate { level } | Compression::TransposeDeflate { level } => {
+                let mut compressed = Vec::new();
+                let mut encoder = Encoder::boxed();
+                encoder.set_format(Format::Zlib);
+                encoder.set_level(CompressionLevel::Specific(*level));
+                let mut stream = encoder.stream_into_vec(&mut compressed);
+                stream
+                    .write(bytes)
+                    .map_err(|e| anyhow!("Could not compress data: {:?}", e))?;
+                stream
+                    .finish()
+                    .map_err(|e| anyhow!("failed finishing to compress data: {:?}", e))?;
+                Ok(Some(compressed))
+            }
+            Compression::Zstd { level } | Compression::TransposeZstd { level } => Ok(Some(
+                zstd::bulk::compress(bytes, *level).context("Could not compress data")?,
+            )),
+        }
+    }
+    /// true if values are transposed before compression
+    fn transposes(&self) -> bool {
+        matches!(
+            self,
+            Compression::TransposeDeflate { .. } | Compression::TransposeZstd { .. }
+        )
+    }
+    /// DZ block zip type
+    fn zip_type(&self, transposed: bool) -> u8 {
+        match (self, transposed) {
+            (Compression::Zstd { .. } | Compression::TransposeZstd { .. }, false) => DZ_ZSTD,
+            (Compression::Zstd { .. } | Compression::TransposeZstd { .. }, true) => {
+                DZ_TRANSPOSED_ZSTD
+            }
+            (_, false) => DZ_DEFLATE,
+            (_, true) => DZ_TRANSPOSED_DEFLATE,
+        }
+    }
+}
+
+/// writes mdf4.2 file.
+/// For each reduction factor, a sample reduction block is generated per channel group
+/// with mean, min and max values of each interval of reduction factor samples.
+/// The data groups of uncompressed_channels (already compressed byte arrays for instance)
+/// are written without compression.
+/// The file history is kept and appended with an entry of comment for this writing
+pub fn mdfwriter4(
+    mdf: &Mdf,
+    file_name: &str,
+    compression: Compression,
+    reduction_factors: &[usize],
+    uncompressed_channels: &HashSet<String>,
+    comment: &str,
+) -> Result<Mdf> {
     let info: MdfInfo4 = match &mdf.mdf_info {
         MdfInfo::V3(mdfinfo3) => convert3to4(mdfinfo3, file_name)
             .context("failed converting mdf version 3 into version 4")?,
@@ -40,106 +146,245 @@ pub fn mdfwriter4(mdf: &Mdf, file_name: &str, compression: bool) -> Result<Mdf>
     };
     let n_channels = mdf.mdf_info.get_channel_names_set().len();
     let mut new_info = MdfInfo4::new(file_name, n_channels);
+    // program identifier and header data members of the source, links set while writing
+    new_info.id_block.id_prog = info.id_block.id_prog;
+    new_info.hd_block = info.hd_block.without_links();
     let mut pointer: i64 = 168; // after HD block
-                                // FH block
-    new_info.fh = Vec::new();
-    let mut fh = FhBlock::default();
+                                // FH blocks
+    let mut history = info
+        .get_file_history()
+        .context("failed reading file history")?;
+    history.push(FileHistory::new(comment));
     new_info.hd_block.hd_fh_first = pointer;
-    pointer += 56;
-    // Writes FH comments
-    fh.fh_md_comment = pointer;
-    let mut fh_comments = MetaData::new(MetaDataBlockType::MdBlock, BlockType::FH);
-    fh_comments.create_fh();
-    pointer += fh_comments.block.hdr_len as i64;
+    let mut fh_blocks: Vec<(FhBlock, MetaData)> = Vec::with_capacity(history.len());
+    for entry in history.iter() {
+        if let Some((fh, _)) = fh_blocks.last_mut() {
+            fh.fh_fh_next = pointer;
+        }
+        let mut fh = FhBlock::from_history(entry);
+        pointer += 56;
+        // FH comments
+        fh.fh_md_comment = pointer;
+        let mut fh_comments = MetaData::new(MetaDataBlockType::MdBlock, BlockType::FH);
+        fh_comments.create_fh_history(entry);
+        pointer += fh_comments.block.hdr_len as i64;
+        fh_blocks.push((fh, fh_comments));
+    }
+    // HD comments
+    let hd_comments = match info
+        .header_comments()
+        .context("failed reading header comments")?
+    {
+        Some(comments) => {
+            let mut hd_comments = MetaData::new(MetaDataBlockType::MdBlock, BlockType::HD);
+            hd_comments.create_hd(&comments);
+            new_info.hd_block.hd_md_comment = pointer;
+            pointer += hd_comments.block.hdr_len as i64;
+            Some(hd_comments)
+        }
+        None => None,
+    };
     let mut last_dg_pointer: i64 = pointer;
     new_info.hd_block.hd_dg_first = pointer;
 
+    // channel groups with their master channel in another group, added with add_channel
+    // for instance, are written after the groups of their master to refer to it
+    let remote_master = |cg: &Cg4| {
+        cg.master_channel_name
+            .as_ref()
+            .is_some_and(|name| !cg.channel_names.contains(name))
+    };
+    let mut groups: Vec<&Cg4> = info.dg.values().flat_map(|dg| dg.cg.values()).collect();
+    groups.sort_by_key(|cg| remote_master(cg));
+    let mut master_groups: HashMap<String, i64> = HashMap::new();
+
     // builds meta data blocks for the new file
-    for (_dg_position, dg) in info.dg.iter() {
-        for (_record_id, cg) in dg.cg.iter() {
-            let mut cg_cg_master: i64 = 0;
-
-            // find master channel and start to write blocks for it
-            if let Some(master_channel_name) = &cg.master_channel_name {
-                if let Some((
-                    _master_name,
-                    _dg_master_position,
-                    (_cg_master_block_position, _record_id),
-                    (_cn_master_block_position, cn_master_record_position),
-                )) = info.get_channel_id(master_channel_name)
-                {
-                    if let Some(cn_master) = cg.cn.get(cn_master_record_position) {
-                        if let Some(data) = mdf.get_channel_data(&cn_master.unique_name) {
-                            // Writing master channel
-                            cg_cg_master = pointer + 64; // after DGBlock
-                            last_dg_pointer = pointer;
-                            pointer = create_blocks(
-                                &mut new_info,
-                                &info,
-                                pointer,
-                                cg,
-                                cn_master,
-                                data,
-                                &cg_cg_master,
-                                true,
-                            )?;
-                        }
-                    }
-                }
-            }
+    for cg in groups {
+        let mut cg_cg_master: i64 = 0;
 
-            // create the other non master channel blocks
-            for (_cn_record_position, cn) in cg.cn.iter() {
-                // not master channel
-                if cn.block.cn_type != 2 && cn.block.cn_type != 3 {
-                    if let Some(data) = mdf.get_channel_data(&cn.unique_name) {
+        // find master channel and start to write blocks for it
+        if let Some(master_channel_name) = &cg.master_channel_name {
+            if remote_master(cg) {
+                cg_cg_master = master_groups
+                    .get(master_channel_name)
+                    .copied()
+                    .unwrap_or_default();
+            } else if let Some((
+                _master_name,
+                _dg_master_position,
+                (_cg_master_block_position, _record_id),
+                (_cn_master_block_position, cn_master_record_position),
+            )) = info.get_channel_id(master_channel_name)
+            {
+                if let Some(cn_master) = cg.cn.get(cn_master_record_position) {
+                    if let Some(data) = mdf
+                        .mdf_info
+                        .get_channel_data_uncached(&cn_master.unique_name)
+                    {
+                        // Writing master channel
+                        cg_cg_master = pointer + 64; // after DGBlock
+                        master_groups.insert(master_channel_name.clone(), cg_cg_master);
                         last_dg_pointer = pointer;
                         pointer = create_blocks(
                             &mut new_info,
                             &info,
                             pointer,
                             cg,
-                            cn,
-                            data,
+                            cn_master,
+                            &data,
                             &cg_cg_master,
-                            false,
+                            true,
                         )?;
                     }
                 }
             }
         }
+
+        // create the other non master channel blocks
+        for (_cn_record_position, cn) in cg.cn.iter() {
+            // not master channel
+            if cn.block.cn_type != 2 && cn.block.cn_type != 3 {
+                if let Some(data) = mdf.mdf_info.get_channel_data_uncached(&cn.unique_name) {
+                    last_dg_pointer = pointer;
+                    pointer = create_blocks(
+                        &mut new_info,
+                        &info,
+                        pointer,
+                        cg,
+                        cn,
+                        &data,
+                        &cg_cg_master,
+                        false,
+                    )?;
+                }
+            }
+        }
     }
     // last DG must point to null DGBlock
     if let Some(last_dg) = new_info.dg.get_mut(&last_dg_pointer) {
         last_dg.block.dg_dg_next = 0;
     }
 
-    // thread writing the channels data first as block size can be unknown due to compression
-    let (tx, rx) = bounded::<Vec<u8>>(n_channels);
+    // SR and RD blocks of sample reductions
+    let mut sr_blocks: Vec<(Sr4Block, Blockheader4, Vec<u8>)> = Vec::new();
+    for dg in new_info.dg.values_mut() {
+        for cg in dg.cg.values_mut() {
+            let mut previous_sr: Option<usize> = None;
+            for cn in cg.cn.values() {
+                let Some(data) = mdf.mdf_info.get_channel_data_uncached(&cn.unique_name) else {
+                    continue;
+                };
+                for reduction_factor in reduction_factors.iter().filter(|f| **f > 1) {
+                    let Some((n_intervals, rd_data)) =
+                        create_rd(&data, *reduction_factor, cg.block.cg_inval_bytes > 0)
+                            .with_context(|| {
+                                format!("failed creating sample reduction of {}", cn.unique_name)
+                            })?
+                    else {
+                        continue;
+                    };
+                    let sr_position = pointer;
+                    pointer += default_short_header(BlockType::SR).hdr_len as i64;
+                    let mut rd_block = Blockheader4::default();
+                    rd_block.hdr_id = [35, 35, 82, 68]; // ##RD
+                    rd_block.hdr_links = 0;
+                    rd_block.hdr_len += rd_data.len() as u64;
+                    let mut sr_block = Sr4Block::default();
+                    sr_block.sr_links = 2;
+                    sr_block.sr_data = pointer;
+                    sr_block.sr_cycle_count = n_intervals;
+                    sr_block.sr_interval = *reduction_factor as f64;
+                    sr_block.sr_sync_type = 4; // index
+                    sr_block.sr_flags = u8::from(cg.block.cg_inval_bytes > 0);
+                    pointer += rd_block.hdr_len as i64 + (8 - rd_data.len() as i64 % 8) % 8;
+                    match previous_sr {
+                        Some(index) => sr_blocks[index].0.sr_sr_next = sr_position,
+                        None => cg.block.cg_sr_first = sr_position,
+                    }
+                    previous_sr = Some(sr_blocks.len());
+                    sr_blocks.push((sr_block, rd_block, rd_data));
+                }
+            }
+        }
+    }
+
+    // EV blocks, scope referencing the written channels and channel groups
+    let mut ev_blocks: Vec<(i64, Ev4Block)> = Vec::new();
+    for event in info.get_events().context("failed reading events")? {
+        let mut scope: Vec<i64> = Vec::with_capacity(event.scope.len());
+        for reference in event.scope.iter() {
+            match reference {
+                EventScope::Channel(name) => {
+                    if let Some((_m, _dg, _cg, (cn_pos, _rec_pos))) = new_info.get_channel_id(name)
+                    {
+                        scope.push(*cn_pos);
+                    }
+                }
+                EventScope::ChannelGroup(name) => {
+                    if let Some((_m, _dg, (cg_pos, _rec_id), _cn)) = new_info.get_channel_id(name) {
+                        scope.push(*cg_pos);
+                    }
+                }
+            }
+        }
+        let ev_position = pointer;
+        let mut ev_block = Ev4Block::new(&event, 0, 0, scope);
+        pointer += ev_block.len() as i64;
+        if let Some(name) = &event.name {
+            ev_block.ev_tx_name = pointer;
+            new_info.sharable.create_tx(pointer, name.clone());
+            if let Some(tx) = new_info.sharable.md_tx.get(&pointer) {
+                pointer += tx.block.hdr_len as i64;
+            }
+        }
+        if let Some(comment) = &event.comment {
+            ev_block.ev_md_comment = pointer;
+            new_info.sharable.create_tx(pointer, comment.clone());
+            if let Some(tx) = new_info.sharable.md_tx.get(&pointer) {
+                pointer += tx.block.hdr_len as i64;
+            }
+        }
+        if let Some((_previous_position, previous)) = ev_blocks.last_mut() {
+            previous.set_next(ev_position);
+        } else {
+            new_info.hd_block.hd_ev_first = ev_position;
+        }
+        ev_blocks.push((ev_position, ev_block));
+    }
+
+    // AT blocks with their embedded data, file name, mime type and comment
+    let mut at_blocks: Vec<(i64, At4Block, Option<Vec<u8>>)> = Vec::with_capacity(info.at.len());
+    let mut attachments: Vec<_> = info.at.iter().collect();
+    attachments.sort_by_key(|(position, _)| **position);
+    for (_position, (block, data)) in attachments {
+        let at_position = pointer;
+        let mut at_block = *block;
+        at_block.set_next(0);
+        pointer += at_block.len() as i64 + (8 - at_block.len() as i64 % 8) % 8;
+        for link in [
+            &mut at_block.at_tx_filename,
+            &mut at_block.at_tx_mimetype,
+            &mut at_block.at_md_comment,
+        ] {
+            if let Some(md) = info.sharable.md_tx.get(link) {
+                *link = pointer;
+                new_info.sharable.md_tx.insert(pointer, md.clone());
+                pointer += md.block.hdr_len as i64;
+            } else {
+                *link = 0;
+            }
+        }
+        if let Some((_previous_position, previous, _data)) = at_blocks.last_mut() {
+            previous.set_next(at_position);
+        } else {
+            new_info.hd_block.hd_at_first = at_position;
+        }
+        at_blocks.push((at_position, at_block, data.clone()));
+    }
+
+    // channels data written first as block size can be unknown due to compression
     let fname = Arc::new(Mutex::new(file_name.to_string()));
-    let sfname = Arc::clone(&fname);
-    thread::spawn(move || -> Result<(), Error> {
-        let file_name = Arc::clone(&sfname);
-        let file = file_name.lock();
-        let f: File = OpenOptions::new()
-            .read(true)
-            .write(true)
-            .create(true)
-            .truncate(false)
-            .open(&*file)
-            .context("Cannot create the file")?;
-        let mut writer = BufWriter::new(&f);
-
-        writer
-            .seek(SeekFrom::Start(pointer as u64))
-            .context("Could not reach position to write data blocks")?;
-        for buffer in rx {
-            writer
-                .write_all(&buffer)
-                .context("Could not write data blocks buffer")?;
-        }
-        Ok(())
-    });
+    let data_writer = DataBlocksWriter::new(file_name, pointer, n_channels)?;
 
     let data_pointer = Arc::new(Mutex::new(pointer));
     new_info
@@ -147,23 +392,46 @@ pub fn mdfwriter4(mdf: &Mdf, file_name: &str, compression: bool) -> Result<Mdf>
         .par_iter_mut()
         .try_for_each(|(_dg_block_position, dg)| -> Result<(), Error> {
             for (_rec_id, cg) in dg.cg.iter_mut() {
-                for (_rec_pos, cn) in cg.cn.iter() {
-                    let dt = mdf.get_channel_data(&cn.unique_name);
+                for (_rec_pos, cn) in cg.cn.iter_mut() {
+                    let dt = mdf.mdf_info.get_channel_data_uncached(&cn.unique_name);
                     if let Some(data) = dt {
                         let m = data.validity();
-                        if !data.is_empty() && data.bit_count() > 0 {
+                        if !data.is_empty() && (data.bit_count() > 0 || cn.block.cn_type == 1) {
                             // empty strings are not written
+                            let compression = if uncompressed_channels.contains(&cn.unique_name) {
+                                Compression::None
+                            } else {
+                                compression
+                            };
+                            if cn.block.cn_type == 1 {
+                                // variable length channel, offsets records in DT block
+                                // followed by the values in SD block
+                                let (records, signal_data) = create_vlsd(&data, m.as_ref())
+                                    .context("failed creating vlsd records")?;
+                                let dt_block = create_dt_sd([68, 84], &records, compression)
+                                    .context("failed creating dt or dz block")?;
+                                let sd_block = create_dt_sd([83, 68], &signal_data, compression)
+                                    .context("failed creating sd or dz block")?;
+                                let data_pointer = Arc::clone(&data_pointer);
+                                let mut locked_data_pointer = data_pointer.lock();
+                                dg.block.dg_data = *locked_data_pointer;
+                                cn.block.cn_data = dg.block.dg_data + dt_block.len() as i64;
+                                *locked_data_pointer += (dt_block.len() + sd_block.len()) as i64;
+                                data_writer.write([dt_block, sd_block].concat())?;
+                                drop(locked_data_pointer);
+                                continue;
+                            }
                             let mut offset: i64 = 0;
                             let mut ld_block: Option<Ld4Block> = None;
-                            if compression || m.is_some() {
+                            if compression != Compression::None || m.is_some() {
                                 ld_block = create_ld(&m, &mut offset);
                             }
 
-                            let data_block = if compression {
-                                create_dz_dv(data, &mut offset)
+                            let data_block = if compression != Compression::None {
+                                create_dz_dv(&data, &mut offset, compression)
                                     .context("failed creating dz or dv block")?
                             } else {
-                                create_dv(data, &mut offset).context("failed creating dv block")?
+                                create_dv(&data, &mut offset).context("failed creating dv block")?
                             };
 
                             // invalid mask existing
@@ -173,8 +441,8 @@ pub fn mdfwriter4(mdf: &Mdf, file_name: &str, compression: bool) -> Result<Mdf>
                                 if let Some(ref mut ld) = ld_block {
                                     ld.ld_links.push(offset);
                                 }
-                                if compression {
-                                    invalid_block = create_dz_di(&mask, &mut offset)
+                                if compression != Compression::None {
+                                    invalid_block = create_dz_di(&mask, &mut offset, compression)
                                         .context("failed creating dz or di block")?;
                                 } else {
                                     invalid_block = create_di(&mask, &mut offset)
@@ -193,7 +461,7 @@ pub fn mdfwriter4(mdf: &Mdf, file_name: &str, compression: bool) -> Result<Mdf>
                                 invalid_block,
                                 offset as usize,
                             )?;
-                            tx.send(buffer).context("Channel disconnected")?;
+                            data_writer.write(buffer)?;
                             drop(locked_data_pointer);
                         }
                     }
@@ -201,7 +469,8 @@ pub fn mdfwriter4(mdf: &Mdf, file_name: &str, compression: bool) -> Result<Mdf>
             }
             Ok(())
         })?;
-    drop(tx);
+    // data blocks fully written before returning, also when channels are sent sequentially
+    data_writer.finish().context("failed writing data blocks")?;
 
     let file_name = Arc::clone(&fname);
     let file = file_name.lock();
@@ -222,9 +491,26 @@ pub fn mdfwriter4(mdf: &Mdf, file_name: &str, compression: bool) -> Result<Mdf>
     buffer
         .write_le(&new_info.hd_block)
         .context("Could not write HDBlock")?;
-    // Writes FHBlock
-    buffer.write_le(&fh).context("Could not write FHBlock")?;
-    fh_comments.write(&mut buffer)?; // FH comments
+    // Writes FHBlocks
+    for (fh, fh_comments) in fh_blocks {
+        buffer.write_le(&fh).context("Could not write FHBlock")?;
+        fh_comments.write(&mut buffer)?; // FH comments
+        new_info
+            .sharable
+            .md_tx
+            .insert(fh.fh_md_comment, fh_comments);
+        new_info.fh.push(fh);
+    }
+    if let Some(hd_comments) = hd_comments {
+        hd_comments.write(&mut buffer)?; // HD comments
+        new_info
+            .sharable
+            .md_tx
+            .insert(new_info.hd_block.hd_md_comment, hd_comments);
+        new_info
+            .sharable
+            .parse_hd_comments(new_info.hd_block.hd_md_comment);
+    }
 
     // Writes DG+CG+CN blocks
     for (_position, dg) in new_info.dg.iter() {
@@ -263,6 +549,18 @@ pub fn mdfwriter4(mdf: &Mdf, file_name: &str, compression: bool) -> Result<Mdf>
                         .write(&mut buffer)
                         .context("Failed writing tx comment")?;
                 }
+                // SI Block channel source with its name, path and comment
+                if let Some(si) = new_info.sharable.si.get(&cn.block.cn_si_source) {
+                    buffer
+                        .write_le(&default_short_header(BlockType::SI))
+                        .context("Could not write SIBlock header")?;
+                    buffer.write_le(si).context("Could not write SIBlock")?;
+                    for position in [si.si_tx_name, si.si_tx_path, si.si_md_comment] {
+                        if let Some(tx) = new_info.sharable.md_tx.get(&position) {
+                            tx.write(&mut buffer).context("Failed writing source tx")?;
+                        }
+                    }
+                }
                 // channel array
                 if let Some(compo) = &cn.composition {
                     match &compo.block {
@@ -278,11 +576,8 @@ pub fn mdfwriter4(mdf: &Mdf, file_name: &str, compression: bool) -> Result<Mdf>
                             buffer
                                 .write_le(&ca_composition)
                                 .context("Could not write CABlock ca_composition")?;
-                            let mut ca_block = Ca4BlockMembers::default();
-                            ca_block.ca_ndim = c.ca_ndim;
-                            ca_block.ca_dim_size.clone_from(&c.ca_dim_size);
                             buffer
-                                .write_le(&ca_composition)
+                                .write_le(&Ca4BlockMembers::from(c.as_ref()))
                                 .context("Could not write CABlock members")?;
                         }
                         Compo::CN(_) => {}
@@ -291,15 +586,156 @@ pub fn mdfwriter4(mdf: &Mdf, file_name: &str, compression: bool) -> Result<Mdf>
             }
         }
     }
+    // Writes SR and RD blocks
+    for (sr_block, rd_block, rd_data) in sr_blocks.iter() {
+        buffer
+            .write_le(&default_short_header(BlockType::SR))
+            .context("Could not write SRBlock header")?;
+        buffer
+            .write_le(sr_block)
+            .context("Could not write SRBlock")?;
+        buffer
+            .write_le(rd_block)
+            .context("Could not write RDBlock header")?;
+        buffer
+            .write_all(rd_data)
+            .context("Could not write RDBlock data")?;
+        buffer
+            .write_all(&vec![0u8; (8 - rd_data.len() % 8) % 8])
+            .context("Could not align RDBlock")?;
+    }
+    // Writes EV blocks with their name and comment
+    for (_position, ev_block) in ev_blocks.iter() {
+        let mut header = default_short_header(BlockType::EV);
+        header.hdr_len = ev_block.len();
+        buffer
+            .write_le(&header)
+            .context("Could not write EVBlock header")?;
+        buffer
+            .write_le(ev_block)
+            .context("Could not write EVBlock")?;
+        for position in [ev_block.ev_tx_name, ev_block.ev_md_comment] {
+            if let Some(tx) = new_info.sharable.md_tx.get(&position) {
+                tx.write(&mut buffer).context("Failed writing event tx")?;
+            }
+        }
+    }
+    new_info.ev = ev_blocks.into_iter().collect();
+    // Writes AT blocks with their embedded data and linked blocks
+    for (_position, at_block, data) in at_blocks.iter() {
+        buffer
+            .write_le(at_block)
+            .context("Could not write ATBlock")?;
+        let embedded = data.as_deref().unwrap_or_default();
+        buffer
+            .write_all(embedded)
+            .context("Could not write ATBlock embedded data")?;
+        buffer
+            .write_all(&vec![0u8; (8 - at_block.len() as usize % 8) % 8])
+            .context("Could not align ATBlock")?;
+        for position in [
+            at_block.at_tx_filename,
+            at_block.at_tx_mimetype,
+            at_block.at_md_comment,
+        ] {
+            if let Some(md) = new_info.sharable.md_tx.get(&position) {
+                md.write(&mut buffer)
+                    .context("Failed writing attachment metadata")?;
+            }
+        }
+    }
+    new_info.at = at_blocks
+        .into_iter()
+        .map(|(position, block, data)| (position, (block, data)))
+        .collect();
     writer
         .write_all(&buffer.into_inner())
         .context("Could not write DG+CG+CN blocks")?;
     writer.flush().context("Could not flush file")?;
     Ok(Mdf {
         mdf_info: MdfInfo::V4(Box::new(new_info)),
+        stats: HashMap::new(),
+        master_fixes: Vec::new(),
     })
 }
 
+/// opens the file to write the data blocks from position
+fn data_blocks_file(file_name: &str, position: i64) -> Result<BufWriter<File>> {
+    let f: File = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(file_name)
+        .context("Cannot create the file")?;
+    let mut writer = BufWriter::new(f);
+    writer
+        .seek(SeekFrom::Start(position as u64))
+        .context("Could not reach position to write data blocks")?;
+    Ok(writer)
+}
+
+/// Writer of the data blocks buffers in sequence, in a dedicated thread
+#[cfg(not(feature = "single-thread"))]
+struct DataBlocksWriter {
+    tx: Sender<Vec<u8>>,
+    handle: JoinHandle<Result<()>>,
+}
+
+#[cfg(not(feature = "single-thread"))]
+impl DataBlocksWriter {
+    fn new(file_name: &str, position: i64, n_buffers: usize) -> Result<Self> {
+        let (tx, rx) = bounded::<Vec<u8>>(n_buffers);
+        let mut writer = data_blocks_file(file_name, position)?;
+        let handle = thread::spawn(move || -> Result<()> {
+            for buffer in rx {
+                writer
+                    .write_all(&buffer)
+                    .context("Could not write data blocks buffer")?;
+            }
+            writer.flush().context("Could not flush data blocks")?;
+            Ok(())
+        });
+        Ok(Self { tx, handle })
+    }
+    fn write(&self, buffer: Vec<u8>) -> Result<()> {
+        self.tx.send(buffer).context("Channel disconnected")
+    }
+    fn finish(self) -> Result<()> {
+        drop(self.tx);
+        self.handle
+            .join()
+            .map_err(|_| anyhow!("data blocks writing thread panicked"))?
+    }
+}
+
+/// Writer of the data blocks buffers in sequence, inline in the calling thread
+#[cfg(feature = "single-thread")]
+struct DataBlocksWriter {
+    writer: Mutex<BufWriter<File>>,
+}
+
+#[cfg(feature = "single-thread")]
+impl DataBlocksWriter {
+    fn new(file_name: &str, position: i64, _n_buffers: usize) -> Result<Self> {
+        Ok(Self {
+            writer: Mutex::new(data_blocks_file(file_name, position)?),
+        })
+    }
+    fn write(&self, buffer: Vec<u8>) -> Result<()> {
+        self.writer
+            .lock()
+            .write_all(&buffer)
+            .context("Could not write data blocks buffer")
+    }
+    fn finish(self) -> Result<()> {
+        self.writer
+            .into_inner()
+            .flush()
+            .context("Could not flush data blocks")
+    }
+}
+
 /// Writes the data blocks
 fn write_data_blocks(
     position: i64,
@@ -388,6 +824,89 @@ fn create_ld(m: &Option<NullBuffer>, offset: &mut i64) -> Option<Ld4Block> {
     Some(ld_block)
 }
 
+/// Creates the records of a variable length channel, u64 offsets of its values in signal data
+/// followed by the invalid byte if any, and its signal data of u32 lengths and values,
+/// strings being null terminated
+fn create_vlsd(data: &ChannelData, mask: Option<&NullBuffer>) -> Result<(Vec<u8>, Vec<u8>)> {
+    let mut records: Vec<u8> = Vec::with_capacity(data.len() * (8 + usize::from(mask.is_some())));
+    let mut signal_data: Vec<u8> = Vec::new();
+    let mut push = |index: usize, value: &[u8], terminated: bool| {
+        records.extend_from_slice(&(signal_data.len() as u64).to_le_bytes());
+        if let Some(mask) = mask {
+            records.push(u8::from(mask.is_null(index)));
+        }
+        let length = value.len() + usize::from(terminated);
+        signal_data.extend_from_slice(&(length as u32).to_le_bytes());
+        signal_data.extend_from_slice(value);
+        if terminated {
+            signal_data.push(0);
+        }
+    };
+    match data {
+        ChannelData::Utf8(a) => {
+            for (index, value) in a.finish_cloned().iter().enumerate() {
+                push(index, value.unwrap_or_default().as_bytes(), true);
+            }
+        }
+        ChannelData::VariableSizeByteArray(a) => {
+            for (index, value) in a.finish_cloned().iter().enumerate() {
+                push(index, value.unwrap_or_default(), false);
+            }
+        }
+        ChannelData::Dictionary(a) => {
+            let labels = a
+                .downcast_dict::<StringArray>()
+                .context("dictionary values are not strings")?;
+            for (index, value) in labels.into_iter().enumerate() {
+                push(index, value.unwrap_or_default().as_bytes(), true);
+            }
+        }
+        _ => bail!("channel data is not of variable length"),
+    }
+    Ok((records, signal_data))
+}
+
+/// Creates a DT or SD block of the bytes, or its DZ block if compression reduces its size,
+/// 8 bytes aligned
+fn create_dt_sd(block_type: [u8; 2], bytes: &[u8], compression: Compression) -> Result<Vec<u8>> {
+    let compressed = compression
+        .compress(bytes)?
+        .filter(|compressed| compressed.len() < bytes.len());
+    let mut buffer = Cursor::new(Vec::new());
+    if let Some(compressed) = compressed {
+        let mut dz_block = Dz4Block::default();
+        dz_block.dz_org_block_type = block_type;
+        dz_block.dz_zip_type = compression.zip_type(false);
+        dz_block.dz_org_data_length = bytes.len() as u64;
+        dz_block.dz_data_length = compressed.len() as u64;
+        dz_block.len = dz_block.dz_data_length + 48;
+        buffer
+            .write_all(b"##DZ")
+            .context("Could not write DZBlock id")?;
+        buffer
+            .write_le(&dz_block)
+            .context("Could not write DZBlock")?;
+        buffer
+            .write_all(&compressed)
+            .context("Could not write DZBlock data")?;
+    } else {
+        let mut header = Blockheader4::default();
+        header.hdr_id = [35, 35, block_type[0], block_type[1]];
+        header.hdr_len += bytes.len() as u64;
+        buffer
+            .write_le(&header)
+            .context("Could not write data block header")?;
+        buffer
+            .write_all(bytes)
+            .context("Could not write data block")?;
+    }
+    let length = buffer.get_ref().len();
+    buffer
+        .write_all(&vec![0u8; (8 - length % 8) % 8])
+        .context("Could not align data block")?;
+    Ok(buffer.into_inner())
+}
+
 /// Create a DV Block
 fn create_dv(data: &ChannelData, offset: &mut i64) -> Result<(DataBlock, usize, Vec<u8>), Error> {
     let mut dv_block = Blockheader4::default();
@@ -404,6 +923,73 @@ fn create_dv(data: &ChannelData, offset: &mut i64) -> Result<(DataBlock, usize,
     Ok((DataBlock::DvDi(dv_block), byte_aligned, data_bytes))
 }
 
+/// Creates the sample reduction records of a channel, for each interval of reduction_factor samples
+/// the mean, min and max values followed by the invalid byte if any.
+/// Returns the number of intervals and the records, None if data is not numeric
+fn create_rd(
+    data: &ChannelData,
+    reduction_factor: usize,
+    inval_byte: bool,
+) -> Result<Option<(u64, Vec<u8>)>> {
+    let array = data.as_ref();
+    let data_type = array.data_type().clone();
+    if !data_type.is_numeric() || data.ndim() > 1 || data.is_empty() {
+        return Ok(None);
+    }
+    let values = cast(&array, &DataType::Float64).context("failed casting data into f64")?;
+    let values = values.as_primitive::<Float64Type>();
+    let n_intervals = values.len().div_ceil(reduction_factor);
+    let mut means = Float64Builder::with_capacity(n_intervals);
+    let mut mins = Float64Builder::with_capacity(n_intervals);
+    let mut maxs = Float64Builder::with_capacity(n_intervals);
+    for start in (0..values.len()).step_by(reduction_factor) {
+        let interval = values.slice(start, reduction_factor.min(values.len() - start));
+        let (mut sum, mut count) = (0f64, 0usize);
+        let (mut min, mut max) = (f64::INFINITY, f64::NEG_INFINITY);
+        for value in interval.iter().flatten() {
+            sum += value;
+            count += 1;
+            min = min.min(value);
+            max = max.max(value);
+        }
+        if count > 0 {
+            let mean = sum / count as f64;
+            // integer means are rounded
+            means.append_value(if data_type.is_floating() {
+                mean
+            } else {
+                mean.round()
+            });
+            mins.append_value(min);
+            maxs.append_value(max);
+        } else {
+            means.append_null();
+            mins.append_null();
+            maxs.append_null();
+        }
+    }
+    let mut columns: Vec<(Vec<u8>, Option<NullBuffer>)> = Vec::with_capacity(3);
+    for mut builder in [means, mins, maxs] {
+        let reduced = cast(&builder.finish(), &data_type)
+            .context("failed casting reduced data into channel data type")?;
+        let reduced = try_from(&reduced).context("failed converting reduced data")?;
+        columns.push((reduced.to_bytes()?, reduced.validity()));
+    }
+    let byte_count = data.byte_count() as usize;
+    let mut records: Vec<u8> =
+        Vec::with_capacity(n_intervals * 3 * (byte_count + usize::from(inval_byte)));
+    for interval in 0..n_intervals {
+        for (bytes, validity) in columns.iter() {
+            records.extend_from_slice(&bytes[interval * byte_count..(interval + 1) * byte_count]);
+            if inval_byte {
+                let valid = validity.as_ref().is_none_or(|v| v.is_valid(interval));
+                records.push(u8::from(!valid));
+            }
+        }
+    }
+    Ok(Some((n_intervals as u64, records)))
+}
+
 /// Enumeration of data block types
 #[derive(Debug, Clone)]
 enum DataBlock {
@@ -415,22 +1001,29 @@ enum DataBlock {
 fn create_dz_dv(
     data: &ChannelData,
     offset: &mut i64,
+    compression: Compression,
 ) -> Result<(DataBlock, usize, Vec<u8>), Error> {
     let mut dz_block = Dz4Block::default();
-    let mut encoder = Encoder::boxed();
-    encoder.set_format(Format::Zlib);
-    encoder.set_level(CompressionLevel::BestSize);
-    let mut data_bytes = Vec::new();
-    let mut stream = encoder.stream_into_vec(&mut data_bytes);
-    let bytes = data
+    let mut bytes = data
         .to_bytes()
         .context("failed converting array data into bytes for dz or dv block")?;
-    stream.write(&bytes).expect("Could not compress data");
-    dz_block.dz_data_length = stream.finish().expect("failed finishing to compress data");
+    let columns = data.byte_count() as usize;
+    let transposed = compression.transposes() && columns > 1 && bytes.len() >= 2 * columns;
+    if transposed {
+        // rows of values become columns of same significance bytes, tail kept as is
+        let rows = bytes.len() / columns;
+        let mut transposed = vec![0u8; rows * columns];
+        transpose::transpose(&bytes[..rows * columns], &mut transposed, columns, rows);
+        transposed.extend_from_slice(&bytes[rows * columns..]);
+        bytes = transposed;
+        dz_block.dz_zip_parameter = columns as u32;
+    }
+    dz_block.dz_zip_type = compression.zip_type(transposed);
+    let mut data_bytes = compression.compress(&bytes)?.unwrap_or_default();
+    dz_block.dz_data_length = data_bytes.len() as u64;
     let dv_dz_block: DataBlock;
     let byte_aligned: usize;
-    let length = data.len();
-    dz_block.dz_org_data_length = (length * data.byte_count() as usize) as u64;
+    dz_block.dz_org_data_length = bytes.len() as u64;
     if dz_block.dz_org_data_length < dz_block.dz_data_length {
         (dv_dz_block, byte_aligned, data_bytes) = create_dv(data, offset)?;
     } else {
@@ -449,8 +1042,9 @@ fn create_di(mask: &NullBuffer, offset: &mut i64) -> Result<Option<(DataBlock, V
     let mask_length = mask.len();
     dv_invalid_block.hdr_len += mask_length as u64;
     let byte_aligned = 8 - mask_length % 8;
+    // arrow considers bit set as valid while mdf spec considers bit set as invalid
     let invalid_data: Vec<u8> = [
-        mask.iter().map(|v| v as u8).collect::<Vec<u8>>(),
+        mask.iter().map(|v| !v as u8).collect::<Vec<u8>>(),
         vec![0; byte_aligned],
     ]
     .concat();
@@ -462,20 +1056,16 @@ fn create_di(mask: &NullBuffer, offset: &mut i64) -> Result<Option<(DataBlock, V
 fn create_dz_di(
     mask: &NullBuffer,
     offset: &mut i64,
+    compression: Compression,
 ) -> Result<Option<(DataBlock, Vec<u8>)>, Error> {
     let mut dz_invalid_block = Dz4Block::default();
     dz_invalid_block.dz_org_data_length = mask.len() as u64;
-    let mut encoder = Encoder::boxed();
-    encoder.set_format(Format::Zlib);
-    encoder.set_level(CompressionLevel::BestSize);
-    let mut data_bytes = Vec::new();
-    let mut stream = encoder.stream_into_vec(&mut data_bytes);
-    stream
-        .write(mask.iter().map(|v| v as u8).collect::<Vec<u8>>().as_slice())
-        .expect("Could not compress invalid data");
-    dz_invalid_block.dz_data_length = stream
-        .finish()
-        .expect("failed finishing to compress invalid data");
+    dz_invalid_block.dz_zip_type = compression.zip_type(false);
+    let mut data_bytes = compression
+        .compress(&mask.iter().map(|v| !v as u8).collect::<Vec<u8>>())
+        .context("Could not compress invalid data")?
+        .unwrap_or_default();
+    dz_invalid_block.dz_data_length = data_bytes.len() as u64;
     if dz_invalid_block.dz_org_data_length < dz_invalid_block.dz_data_length {
         Ok(create_di(mask, offset)?)
     } else {
@@ -500,9 +1090,15 @@ fn create_blocks(
     cg_cg_master: &i64,
     master_flag: bool,
 ) -> Result<i64> {
-    let bit_count = data.bit_count();
+    // strings and byte arrays are written as variable length signal data channels,
+    // their records holding the u64 offsets of the values in SD block
+    let vlsd = matches!(
+        data,
+        ChannelData::Utf8(_) | ChannelData::VariableSizeByteArray(_) | ChannelData::Dictionary(_)
+    );
+    let bit_count = if vlsd { 64 } else { data.bit_count() };
     if !data.is_empty() && bit_count > 0 {
-        let byte_count = data.byte_count();
+        let byte_count = if vlsd { 8 } else { data.byte_count() };
         // no empty strings
         let mut dg_block = Dg4Block::default();
         let mut cg_block_header = default_short_header(BlockType::CG);
@@ -543,11 +1139,19 @@ fn create_blocks(
             }
         }
 
+        if vlsd {
+            cn_block.cn_type = 1;
+        }
+
         let machine_endian: bool = cfg!(target_endian = "big");
 
         cn_block.cn_data_type = data.data_type(machine_endian);
 
         cn_block.cn_bit_count = bit_count;
+        if data.nullable() {
+            // invalidation bit valid, bit position 0 of the group invalid byte
+            cn_block.cn_flags |= 0b10;
+        }
 
         pointer += cn_block_header.hdr_len as i64;
 
@@ -590,26 +1194,59 @@ fn create_blocks(
             }
         }
 
-        // Channel array
-        let data_ndim = data.ndim();
+        // channel source information
+        if let Some(si) = info.sharable.si.get(&cn.block.cn_si_source) {
+            let mut new_si = *si;
+            cn_block.cn_si_source = pointer;
+            pointer += default_short_header(BlockType::SI).hdr_len as i64;
+            for link in [
+                &mut new_si.si_tx_name,
+                &mut new_si.si_tx_path,
+                &mut new_si.si_md_comment,
+            ] {
+                match info
+                    .sharable
+                    .md_tx
+                    .get(link)
+                    .and_then(|tx| tx.get_tx_bytes())
+                {
+                    Some(tx_str) => {
+                        let mut tx_block = MetaData::new(MetaDataBlockType::TX, BlockType::SI);
+                        tx_block.set_data_buffer(tx_str);
+                        *link = pointer;
+                        pointer += tx_block.block.hdr_len as i64;
+                        new_info.sharable.md_tx.insert(*link, tx_block);
+                    }
+                    None => *link = 0,
+                }
+            }
+            new_info.sharable.si.insert(cn_block.cn_si_source, new_si);
+        }
+
+        // Channel array, first dimension of data shape is the number of records
+        let shape = data.shape();
+        let mut list_size = cn.list_size;
         let mut composition: Option<Composition> = None;
-        if data_ndim > 1 {
-            let data_dim_size = cn
-                .data
-                .shape()
+        if shape.0.len() > 1 {
+            let data_dim_size = shape
                 .0
                 .iter()
                 .skip(1)
                 .map(|x| *x as u64)
                 .collect::<Vec<_>>();
-            // data_dim_size.remove(0);
+            list_size = shape.0.iter().skip(1).product();
             let mut ca_block = Ca4Block::default();
-            cg_block.cg_data_bytes = cn.list_size as u32 * byte_count;
+            cg_block.cg_data_bytes = list_size as u32 * byte_count;
 
             cn_block.cn_composition = pointer;
-            ca_block.ca_ndim = data_ndim as u16;
-            ca_block.ca_dim_size.clone_from(&data_dim_size);
-            ca_block.ca_len = 48 + 8 * data_ndim as u64;
+            ca_block.ca_ndim = data_dim_size.len() as u16;
+            // elements stored next to each other in record
+            ca_block.ca_byte_offset_base = byte_count as i32;
+            if shape.1 == Order::ColumnMajor {
+                ca_block.ca_flags |= 0b100_0000;
+            }
+            ca_block.ca_len = 48 + 8 * data_dim_size.len() as u64;
+            ca_block.ca_dim_size = data_dim_size;
             pointer += ca_block.ca_len as i64;
             composition = Some(Composition {
                 block: Compo::CA(Box::new(ca_block)),
@@ -626,7 +1263,7 @@ fn create_blocks(
                 cn_block.cn_type,
                 cn_block.cn_data_type,
                 cg_block.cg_data_bytes,
-                cn.list_size,
+                list_size,
             )
             .with_context(|| format!("failed initilising array for channel {}", cn.unique_name))?,
             block: cn_block,
@@ -635,9 +1272,18 @@ fn create_blocks(
             pos_byte_beg: 0,
             n_bytes: cg_block.cg_data_bytes,
             composition,
-            list_size: cn.list_size,
-            shape: cn.shape.clone(),
-            invalid_mask: None,
+            list_size,
+            shape,
+            // invalidation bit at position 0 of the invalid byte following data bytes
+            invalid_mask: if data.nullable() {
+                Some((None, cg_block.cg_data_bytes as usize, 1))
+            } else {
+                None
+            },
+            raw_data: None,
+            status: None,
+            conversion_cache: None,
+            spilled: None,
         };
         let mut new_cg = Cg4 {
             header: cg_block_header,
@@ -646,7 +1292,7 @@ fn create_blocks(
             cn: HashMap::new(),
             block_position: cg_position,
             channel_names: HashSet::new(),
-            record_length: cg_block.cg_data_bytes,
+            record_length: cg_block.cg_data_bytes + cg_block.cg_inval_bytes,
             vlsd_cg: None,
             invalid_bytes: None,
         };
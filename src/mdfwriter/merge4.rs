@@ -0,0 +1,298 @@
+//! Combination of several mdf files into a single mdf4 in memory, ready to be written:
+//! concatenation of files recorded sequentially and merging of channel groups of different files.
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use anyhow::{bail, Context, Result};
+use arrow::array::{Array, ArrayRef, AsArray};
+use arrow::compute::{cast, concat};
+use arrow::datatypes::{DataType, Float64Type};
+use log::info;
+
+use crate::mdfinfo::MdfInfo;
+use crate::mdfreader::Mdf;
+use crate::mdfwriter::mdfwriter3::convert3to4;
+
+/// opens a file and loads all its channels data in memory
+fn open_in_memory(file_name: &str) -> Result<Mdf> {
+    let mut mdf = Mdf::new(file_name)?;
+    mdf.load_all_channels_data_in_memory()
+        .with_context(|| format!("failed loading data of file {file_name}"))?;
+    Ok(mdf)
+}
+
+/// recording start time in nanoseconds since epoch
+pub(crate) fn start_time_ns(mdf: &Mdf) -> u64 {
+    match &mdf.mdf_info {
+        MdfInfo::V3(mdfinfo3) => mdfinfo3.hd_block.hd_start_time_ns.unwrap_or(0),
+        MdfInfo::V4(mdfinfo4) => mdfinfo4.hd_block.hd_start_time_ns,
+    }
+}
+
+/// converts mdf3 into mdf4, keeping data and recording start time
+fn into_mdf4(mdf: Mdf) -> Result<Mdf> {
+    let start_time = mdf.get_start_time()?;
+    match mdf.mdf_info {
+        MdfInfo::V3(mdfinfo3) => {
+            let mut file_name = PathBuf::from(mdfinfo3.file_name.as_str());
+            file_name.set_extension("mf4");
+            let mut mdfinfo4 = convert3to4(&mdfinfo3, &file_name.to_string_lossy())
+                .context("failed converting mdf3 into mdf4")?;
+            mdfinfo4.hd_block.hd_start_time_ns = start_time
+                .timestamp_nanos_opt()
+                .context("start time out of range")?
+                as u64;
+            mdfinfo4.hd_block.hd_tz_offset_min =
+                (start_time.offset().local_minus_utc() / 60) as i16;
+            mdfinfo4.hd_block.hd_time_flags = 0b10; // time offsets valid
+            Ok(Mdf {
+                mdf_info: MdfInfo::V4(Box::new(mdfinfo4)),
+                stats: HashMap::new(),
+                master_fixes: Vec::new(),
+            })
+        }
+        MdfInfo::V4(_) => Ok(mdf),
+    }
+}
+
+/// alignment of the time master channels of the files combined by concatenation or merge
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyncOptions {
+    /// time master channels are offset by the time offsets of the files to the first one
+    pub apply_offsets: bool,
+    /// channel present in each file recording a time common to all loggers in seconds,
+    /// like GPS time, used instead of the recording start times to compute the offsets
+    pub sync_channel: Option<String>,
+}
+
+impl Default for SyncOptions {
+    fn default() -> Self {
+        SyncOptions {
+            apply_offsets: true,
+            sync_channel: None,
+        }
+    }
+}
+
+/// returns the first valid value of the channel minus the value of its master at this sample
+fn sync_point(mdf: &Mdf, channel_name: &str) -> Result<f64> {
+    let master = mdf
+        .get_channel_master(channel_name)
+        .with_context(|| format!("sync channel {channel_name} has no master channel"))?;
+    let to_f64 = |name: &str| -> Result<ArrayRef> {
+        let data = mdf
+            .get_channel_data(name)
+            .with_context(|| format!("channel {name} data not in memory"))?;
+        cast(&data.as_ref(), &DataType::Float64)
+            .with_context(|| format!("failed casting channel {name} into f64"))
+    };
+    let values = to_f64(channel_name)?;
+    let times = to_f64(&master)?;
+    values
+        .as_primitive::<Float64Type>()
+        .iter()
+        .zip(times.as_primitive::<Float64Type>().iter())
+        .find_map(|(value, time)| Some(value? - time?))
+        .with_context(|| format!("sync channel {channel_name} has no valid sample"))
+}
+
+/// time offset in seconds of mdf relative to reference, difference of recording start times
+/// or of the common time recorded by sync_channel
+fn time_offset(reference: &Mdf, mdf: &Mdf, sync_channel: Option<&str>) -> Result<f64> {
+    if let Some(channel_name) = sync_channel {
+        return Ok(sync_point(mdf, channel_name)? - sync_point(reference, channel_name)?);
+    }
+    let start_ns = |mdf: &Mdf| -> Result<i64> {
+        mdf.get_start_time()?
+            .timestamp_nanos_opt()
+            .context("start time out of range")
+    };
+    Ok((start_ns(mdf)? - start_ns(reference)?) as f64 / 1e9)
+}
+
+/// returns the time offsets in seconds of the files relative to the first one, from their
+/// absolute recording start times or from the common time recorded by sync_channel
+pub fn sync_files(files: &[Mdf], sync_channel: Option<&str>) -> Result<Vec<f64>> {
+    let Some(reference) = files.first() else {
+        return Ok(Vec::new());
+    };
+    files
+        .iter()
+        .map(|mdf| time_offset(reference, mdf, sync_channel))
+        .collect()
+}
+
+/// time offset applied to the time master channels of mdf
+fn applied_offset(reference: &Mdf, mdf: &Mdf, options: &SyncOptions) -> Result<f64> {
+    if !options.apply_offsets {
+        return Ok(0.0);
+    }
+    time_offset(reference, mdf, options.sync_channel.as_deref())
+}
+
+/// returns the time master channels names
+fn time_masters(mdf: &Mdf) -> HashSet<String> {
+    mdf.get_master_channel_names_set()
+        .into_keys()
+        .flatten()
+        .filter(|master| mdf.get_channel_master_type(master) == 1)
+        .collect()
+}
+
+/// returns the channel data in f64 shifted by offset
+fn shift_master(data: &ArrayRef, offset: f64) -> Result<ArrayRef> {
+    let data = cast(data, &DataType::Float64).context("failed casting master channel into f64")?;
+    Ok(Arc::new(
+        data.as_primitive::<Float64Type>()
+            .unary::<_, Float64Type>(|t| t + offset),
+    ))
+}
+
+/// appends files recorded sequentially having the same channels.
+/// Time master channels are offset by the time offset with the first file
+pub fn concatenate4(files: &[&str], options: &SyncOptions) -> Result<Mdf> {
+    let Some((first, others)) = files.split_first() else {
+        bail!("no file to concatenate");
+    };
+    let mut mdf = into_mdf4(open_in_memory(first)?)?;
+    let channel_names = mdf.get_channel_names_set();
+    let masters = time_masters(&mdf);
+    let mut arrays: HashMap<String, Vec<ArrayRef>> = HashMap::new();
+    for name in channel_names.iter() {
+        if let Some(data) = mdf.get_channel_data(name) {
+            let data = data.as_ref();
+            let data = if masters.contains(name) {
+                shift_master(&data, 0.0)?
+            } else {
+                data
+            };
+            arrays.insert(name.clone(), vec![data]);
+        }
+    }
+    for file in others {
+        let other = open_in_memory(file)?;
+        if other.get_channel_names_set() != channel_names {
+            bail!("channels of file {file} are not matching the ones of file {first}");
+        }
+        let offset = applied_offset(&mdf, &other, options)?;
+        for (name, list) in arrays.iter_mut() {
+            let Some(data) = other.get_channel_data(name) else {
+                continue;
+            };
+            let data = if masters.contains(name) {
+                shift_master(&data.as_ref(), offset)?
+            } else {
+                cast(&data.as_ref(), list[0].data_type()).with_context(|| {
+                    format!("channel {name} of file {file} has incompatible data type")
+                })?
+            };
+            list.push(data);
+        }
+    }
+    for (name, list) in arrays {
+        let list: Vec<&dyn Array> = list.iter().map(|a| a.as_ref()).collect();
+        let data = concat(&list).with_context(|| format!("failed concatenating channel {name}"))?;
+        mdf.set_channel_data(&name, data)?;
+    }
+    // number of records of the channel groups is growing
+    if let MdfInfo::V4(mdfinfo4) = &mut mdf.mdf_info {
+        for dg in mdfinfo4.dg.values_mut() {
+            for cg in dg.cg.values_mut() {
+                cg.block.cg_cycle_count =
+                    cg.cn.values().map(|cn| cn.data.len()).max().unwrap_or(0) as u64;
+            }
+        }
+    }
+    info!("Concatenated {} files", files.len());
+    Ok(mdf)
+}
+
+/// returns name if not existing in names, otherwise name with file index suffix
+fn unique_name(names: &HashSet<String>, name: &str, file_index: usize) -> String {
+    if !names.contains(name) {
+        return name.to_string();
+    }
+    let mut new_name = format!("{name}_{file_index}");
+    let mut counter = 1;
+    while names.contains(&new_name) {
+        new_name = format!("{name}_{file_index}_{counter}");
+        counter += 1;
+    }
+    new_name
+}
+
+/// returns the channel names by master channel name
+fn master_groups(mdf: &Mdf) -> HashMap<Option<String>, Vec<String>> {
+    let mut groups: HashMap<Option<String>, Vec<String>> = HashMap::new();
+    for name in mdf.get_channel_names_set() {
+        groups
+            .entry(mdf.get_channel_master(&name))
+            .or_default()
+            .push(name);
+    }
+    groups
+}
+
+/// unions the channel groups of several files into one.
+/// Time master channels are offset by the time offset with the first file
+/// and duplicated channel names are suffixed by the file index
+pub fn merge4(files: &[&str], options: &SyncOptions) -> Result<Mdf> {
+    let Some((first, others)) = files.split_first() else {
+        bail!("no file to merge");
+    };
+    let mut mdf = into_mdf4(open_in_memory(first)?)?;
+    for (index, file) in others.iter().enumerate() {
+        let other = open_in_memory(file)?;
+        let offset = applied_offset(&mdf, &other, options)?;
+        let masters = time_masters(&other);
+        let mut names = mdf.get_channel_names_set();
+        for (master, channel_names) in master_groups(&other) {
+            let new_master = match &master {
+                Some(master) => {
+                    let Some(data) = other.get_channel_data(master) else {
+                        continue;
+                    };
+                    let data = if masters.contains(master) {
+                        shift_master(&data.as_ref(), offset)?
+                    } else {
+                        data.as_ref()
+                    };
+                    let new_master = unique_name(&names, master, index + 1);
+                    names.insert(new_master.clone());
+                    mdf.add_channel(
+                        new_master.clone(),
+                        data,
+                        Some(new_master.clone()),
+                        Some(other.get_channel_master_type(master)),
+                        true,
+                        other.get_channel_unit(master)?,
+                        other.get_channel_desc(master)?,
+                    )
+                    .with_context(|| format!("failed adding master {master} of file {file}"))?;
+                    Some(new_master)
+                }
+                None => None,
+            };
+            for name in channel_names.iter().filter(|n| Some(*n) != master.as_ref()) {
+                let Some(data) = other.get_channel_data(name) else {
+                    continue;
+                };
+                let new_name = unique_name(&names, name, index + 1);
+                names.insert(new_name.clone());
+                mdf.add_channel(
+                    new_name,
+                    data.as_ref(),
+                    new_master.clone(),
+                    None,
+                    false,
+                    other.get_channel_unit(name)?,
+                    other.get_channel_desc(name)?,
+                )
+                .with_context(|| format!("failed adding channel {name} of file {file}"))?;
+            }
+        }
+    }
+    info!("Merged {} files", files.len());
+    Ok(mdf)
+}
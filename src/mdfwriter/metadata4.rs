@@ -0,0 +1,217 @@
+//! Saving in place of the channels metadata edited in memory: the text and source blocks
+//! created in memory are appended at the end of the mdf4 file and the modified channel blocks
+//! are patched, data blocks are left untouched.
+use std::collections::{HashMap, HashSet};
+use std::fs::OpenOptions;
+use std::io::{BufReader, Cursor, Read, Seek, SeekFrom, Write};
+
+use anyhow::{bail, Context, Result};
+use binrw::BinWriterExt;
+use log::info;
+
+use crate::error::MdfError;
+use crate::mdfinfo::mdfinfo4::{
+    default_short_header, BlockType, FhBlock, MdfInfo4, MetaData, MetaDataBlockType,
+};
+
+/// size of the CN block header before its links count
+const CN_HEADER_LEN: u64 = 16;
+/// position of the link to first file history block, in HD block at 64
+const HD_FH_FIRST: u64 = 64 + 24 + 8;
+
+/// blocks to be written at file positions, new ones appended at end of file
+struct Patcher {
+    /// end of file, position of next appended block
+    end: u64,
+    /// bytes to be written at file positions
+    writes: Vec<(u64, Vec<u8>)>,
+    /// file positions of the blocks created in memory
+    moved: HashMap<i64, i64>,
+}
+
+impl Patcher {
+    /// reserves space at end of file for a block and returns its position
+    fn reserve(&mut self, len: u64) -> u64 {
+        let position = self.end;
+        self.end += len;
+        position
+    }
+    /// appends the text block created in memory, negative link, and returns its file position
+    fn append_tx(&mut self, info: &mut MdfInfo4, link: i64) -> Result<i64> {
+        if link >= 0 {
+            return Ok(link);
+        }
+        if let Some(position) = self.moved.get(&link) {
+            return Ok(*position);
+        }
+        let Some(tx) = info.sharable.md_tx.remove(&link) else {
+            return Ok(0);
+        };
+        let position = self.reserve(tx.block.hdr_len);
+        let mut buffer = Cursor::new(Vec::new());
+        tx.write(&mut buffer)?;
+        self.writes.push((position, buffer.into_inner()));
+        info.sharable.md_tx.insert(position as i64, tx);
+        self.moved.insert(link, position as i64);
+        Ok(position as i64)
+    }
+    /// appends the source information block created in memory and its texts,
+    /// returns its file position
+    fn append_si(&mut self, info: &mut MdfInfo4, link: i64) -> Result<i64> {
+        if link >= 0 {
+            return Ok(link);
+        }
+        if let Some(position) = self.moved.get(&link) {
+            return Ok(*position);
+        }
+        let Some(mut si) = info.sharable.si.remove(&link) else {
+            return Ok(0);
+        };
+        let header = default_short_header(BlockType::SI);
+        let position = self.reserve(header.hdr_len);
+        si.si_tx_name = self.append_tx(info, si.si_tx_name)?;
+        si.si_tx_path = self.append_tx(info, si.si_tx_path)?;
+        si.si_md_comment = self.append_tx(info, si.si_md_comment)?;
+        let mut buffer = Cursor::new(Vec::new());
+        buffer
+            .write_le(&header)
+            .context("Could not write SIBlock header")?;
+        buffer.write_le(&si).context("Could not write SIBlock")?;
+        self.writes.push((position, buffer.into_inner()));
+        info.sharable.si.insert(position as i64, si);
+        self.moved.insert(link, position as i64);
+        Ok(position as i64)
+    }
+}
+
+/// reads the little endian u64 at position
+fn u64_at<R: Read + Seek>(rdr: &mut R, position: u64) -> Result<u64> {
+    let mut buf = [0u8; 8];
+    rdr.seek(SeekFrom::Start(position))
+        .context("Could not reach link position")?;
+    rdr.read_exact(&mut buf)
+        .with_context(|| format!("Could not read link at {position}"))?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+/// saves in place into the mdf4 file the channels units, comments, sources and master types
+/// modified in memory, adding a file history entry. Removed or renamed channels are not saved.
+/// Returns the number of channel blocks patched
+pub fn save_metadata4(info: &mut MdfInfo4) -> Result<usize> {
+    let file_name = info.file_name.clone();
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(&file_name)
+        .map_err(MdfError::Io)
+        .with_context(|| format!("Cannot open the file {file_name} for writing"))?;
+    let mut rdr = BufReader::new(&file);
+    let size = rdr
+        .seek(SeekFrom::End(0))
+        .context("Could not reach end of file")?;
+    // appended blocks are 8 bytes aligned
+    let mut patcher = Patcher {
+        end: size + (8 - size % 8) % 8,
+        writes: vec![(size, vec![0u8; ((8 - size % 8) % 8) as usize])],
+        moved: HashMap::new(),
+    };
+
+    // channels data group position, record id and record position
+    let mut channels: Vec<(i64, u64, i32)> = Vec::new();
+    for (dg_position, dg) in info.dg.iter() {
+        for (rec_id, cg) in dg.cg.iter() {
+            for (rec_pos, cn) in cg.cn.iter() {
+                if cn.block_position <= 0 {
+                    bail!(MdfError::Parse(format!(
+                        "channel {} was added in memory, file {file_name} must be written",
+                        cn.unique_name
+                    )));
+                }
+                channels.push((*dg_position, *rec_id, *rec_pos));
+            }
+        }
+    }
+
+    let mut patched: usize = 0;
+    for (dg_position, rec_id, rec_pos) in channels {
+        let Some(mut block) = info
+            .dg
+            .get(&dg_position)
+            .and_then(|dg| dg.cg.get(&rec_id))
+            .and_then(|cg| cg.cn.get(&rec_pos))
+            .map(|cn| cn.block.clone())
+        else {
+            continue;
+        };
+        block.cn_tx_name = patcher.append_tx(info, block.cn_tx_name)?;
+        block.cn_md_unit = patcher.append_tx(info, block.cn_md_unit)?;
+        block.cn_md_comment = patcher.append_tx(info, block.cn_md_comment)?;
+        block.cn_si_source = patcher.append_si(info, block.cn_si_source)?;
+        let Some(cn) = info
+            .dg
+            .get_mut(&dg_position)
+            .and_then(|dg| dg.cg.get_mut(&rec_id))
+            .and_then(|cg| cg.cn.get_mut(&rec_pos))
+        else {
+            continue;
+        };
+        cn.block = block;
+        // channel blocks are only rewritten if different from file
+        let mut buffer = Cursor::new(Vec::new());
+        buffer
+            .write_le(&cn.block)
+            .context("Could not write CNBlock")?;
+        let buffer = buffer.into_inner();
+        let position = cn.block_position as u64 + CN_HEADER_LEN;
+        let mut original = vec![0u8; buffer.len()];
+        rdr.seek(SeekFrom::Start(position))
+            .context("Could not reach CN block position")?;
+        rdr.read_exact(&mut original)
+            .with_context(|| format!("Could not read CN block at {position}"))?;
+        if original != buffer {
+            patcher.writes.push((position, buffer));
+            patched += 1;
+        }
+    }
+    if patched == 0 {
+        info!("No metadata modified to be saved in file {file_name}");
+        return Ok(0);
+    }
+
+    // file history entry appended to the list
+    let mut link_position = HD_FH_FIRST;
+    let mut fh_position = u64_at(&mut rdr, link_position)?;
+    let mut visited = HashSet::new();
+    while fh_position != 0 && visited.insert(fh_position) {
+        link_position = fh_position + 24; // fh_fh_next
+        fh_position = u64_at(&mut rdr, link_position)?;
+    }
+    let mut fh = FhBlock::default();
+    let position = patcher.reserve(56);
+    fh.fh_md_comment = position as i64 + 56;
+    let mut comments = MetaData::new(MetaDataBlockType::MdBlock, BlockType::FH);
+    comments.create_fh("metadata edited");
+    patcher.reserve(comments.block.hdr_len);
+    let mut buffer = Cursor::new(Vec::new());
+    buffer.write_le(&fh).context("Could not write FHBlock")?;
+    comments.write(&mut buffer)?;
+    patcher.writes.push((position, buffer.into_inner()));
+    patcher
+        .writes
+        .push((link_position, position.to_le_bytes().to_vec()));
+    info.fh.push(fh);
+
+    drop(rdr);
+    let mut writer = &file;
+    for (position, bytes) in patcher.writes {
+        writer
+            .seek(SeekFrom::Start(position))
+            .context("Could not reach position to write")?;
+        writer
+            .write_all(&bytes)
+            .with_context(|| format!("Could not write metadata block at {position}"))?;
+    }
+    writer.flush().context("Could not flush file")?;
+    info!("Saved metadata of {patched} channels in file {file_name}");
+    Ok(patched)
+}
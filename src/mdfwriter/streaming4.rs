@@ -0,0 +1,705 @@
+//! Streaming writer of mdf4 files: channel groups are declared while the file is open
+//! and their records appended as they arrive, buffered and written in DT blocks.
+//! Channel groups are written in sorted data groups, or in unsorted data groups with
+//! record ids, variable length values being written in VLSD channel groups.
+//! The file is marked unfinalized while recording, each DT block being linked by a data list
+//! as soon as written, so that a file left by a crash can be repaired with
+//! Mdf::repair_unfinalized. Cycle counts are set and the file marked finalized when finalizing.
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, Cursor, Seek, SeekFrom, Write};
+
+use anyhow::{bail, Context, Result};
+use arrow::datatypes::DataType;
+use binrw::BinWriterExt;
+use log::warn;
+
+use crate::error::MdfError;
+use crate::mdfinfo::mdfinfo4::{
+    default_short_header, BlockType, Cg4Block, Cn4Block, Dg4Block, Dl4Block, Dt4Block, FhBlock,
+    Hd4, MetaData, MetaDataBlockType,
+};
+use crate::mdfinfo::IdBlock;
+use crate::mdfwriter::finalize4::{
+    FINALIZED_ID, UNFINALIZED_ID, UPDATE_CG_CYCLE_COUNT, UPDATE_LAST_DT_LENGTH, UPDATE_VLSD_BYTES,
+};
+
+/// maximum size of the data written in each DT block
+pub const DT_BLOCK_SIZE: usize = 1 << 22;
+/// position of the link to first data group block, in HD block at 64
+const HD_DG_FIRST: u64 = 64 + 24;
+/// offset of dg_dg_next in DG block
+const DG_DG_NEXT: u64 = 24;
+/// offset of dg_data in DG block
+const DG_DATA: u64 = 24 + 16;
+/// offset of dl_dl_next in DL block
+const DL_DL_NEXT: u64 = 24;
+/// offset of id_unfin_flags in ID block
+const ID_UNFIN_FLAGS: u64 = 60;
+/// finalization steps required by a file left unfinalized while recording
+const UNFIN_FLAGS: u16 = UPDATE_CG_CYCLE_COUNT | UPDATE_LAST_DT_LENGTH | UPDATE_VLSD_BYTES;
+/// offset of cg_cycle_count in CG block without master link
+const CG_CYCLE_COUNT: u64 = 24 + 6 * 8 + 8;
+/// offset of cg_data_bytes in CG block without master link
+const CG_DATA_BYTES: u64 = CG_CYCLE_COUNT + 16;
+
+/// value of a record, converted into the data type of its channel
+#[derive(Debug, Clone, PartialEq)]
+pub enum RecordValue {
+    UInt(u64),
+    Int(i64),
+    Float(f64),
+    /// text of a variable length string channel
+    Str(String),
+    /// bytes of a variable length byte array channel
+    Bytes(Vec<u8>),
+}
+
+impl RecordValue {
+    fn as_u64(&self) -> Option<u64> {
+        match *self {
+            RecordValue::UInt(v) => Some(v),
+            RecordValue::Int(v) => Some(v as u64),
+            RecordValue::Float(v) => Some(v as u64),
+            _ => None,
+        }
+    }
+    fn as_i64(&self) -> Option<i64> {
+        match *self {
+            RecordValue::UInt(v) => Some(v as i64),
+            RecordValue::Int(v) => Some(v),
+            RecordValue::Float(v) => Some(v as i64),
+            _ => None,
+        }
+    }
+    fn as_f64(&self) -> Option<f64> {
+        match *self {
+            RecordValue::UInt(v) => Some(v as f64),
+            RecordValue::Int(v) => Some(v as f64),
+            RecordValue::Float(v) => Some(v),
+            _ => None,
+        }
+    }
+    fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            RecordValue::Str(v) => Some(v.as_bytes()),
+            RecordValue::Bytes(v) => Some(v),
+            _ => None,
+        }
+    }
+}
+
+macro_rules! record_value_from {
+    ($variant:ident, $target:ty, $($t:ty),+) => {
+        $(impl From<$t> for RecordValue {
+            fn from(value: $t) -> Self {
+                RecordValue::$variant(value as $target)
+            }
+        })+
+    };
+}
+record_value_from!(UInt, u64, u8, u16, u32, u64);
+record_value_from!(Int, i64, i8, i16, i32, i64);
+record_value_from!(Float, f64, f32, f64);
+
+impl From<&str> for RecordValue {
+    fn from(value: &str) -> Self {
+        RecordValue::Str(value.to_string())
+    }
+}
+
+impl From<String> for RecordValue {
+    fn from(value: String) -> Self {
+        RecordValue::Str(value)
+    }
+}
+
+impl From<Vec<u8>> for RecordValue {
+    fn from(value: Vec<u8>) -> Self {
+        RecordValue::Bytes(value)
+    }
+}
+
+/// channel declared in a streamed channel group
+#[derive(Debug, Clone, PartialEq)]
+pub struct StreamChannel {
+    /// channel name
+    pub name: String,
+    /// physical unit, optional
+    pub unit: Option<String>,
+    /// integer, float, string or binary data type of the values
+    pub data_type: DataType,
+    /// true for the time master channel of the group
+    pub master: bool,
+}
+
+impl StreamChannel {
+    /// data channel without unit
+    pub fn new(name: &str, data_type: DataType) -> Self {
+        StreamChannel {
+            name: name.to_string(),
+            unit: None,
+            data_type,
+            master: false,
+        }
+    }
+    /// time master channel in seconds
+    pub fn master(name: &str, data_type: DataType) -> Self {
+        StreamChannel {
+            name: name.to_string(),
+            unit: Some("s".to_string()),
+            data_type,
+            master: true,
+        }
+    }
+    /// sets the physical unit
+    pub fn with_unit(mut self, unit: &str) -> Self {
+        self.unit = Some(unit.to_string());
+        self
+    }
+    /// true for strings and byte arrays, written in VLSD channel groups
+    fn is_vlsd(&self) -> bool {
+        matches!(
+            self.data_type,
+            DataType::Utf8 | DataType::LargeUtf8 | DataType::Binary | DataType::LargeBinary
+        )
+    }
+}
+
+/// returns the mdf4 data type and the byte count in record of a supported arrow data type,
+/// variable length values being referenced by their u64 offset
+fn channel_data_type(data_type: &DataType) -> Result<(u8, u32)> {
+    match data_type {
+        DataType::UInt8 => Ok((0, 1)),
+        DataType::UInt16 => Ok((0, 2)),
+        DataType::UInt32 => Ok((0, 4)),
+        DataType::UInt64 => Ok((0, 8)),
+        DataType::Int8 => Ok((2, 1)),
+        DataType::Int16 => Ok((2, 2)),
+        DataType::Int32 => Ok((2, 4)),
+        DataType::Int64 => Ok((2, 8)),
+        DataType::Float32 => Ok((4, 4)),
+        DataType::Float64 => Ok((4, 8)),
+        DataType::Utf8 | DataType::LargeUtf8 => Ok((7, 8)),
+        DataType::Binary | DataType::LargeBinary => Ok((10, 8)),
+        _ => bail!(MdfError::Parse(format!(
+            "data type {data_type} not supported by streaming writer"
+        ))),
+    }
+}
+
+/// appends the little endian bytes of the numeric value converted into data type
+fn encode(value: &RecordValue, data_type: &DataType, record: &mut Vec<u8>) -> Result<()> {
+    let invalid = || MdfError::Parse(format!("value {value:?} invalid for type {data_type}"));
+    match data_type {
+        DataType::UInt8 => record.extend((value.as_u64().ok_or_else(invalid)? as u8).to_le_bytes()),
+        DataType::UInt16 => {
+            record.extend((value.as_u64().ok_or_else(invalid)? as u16).to_le_bytes())
+        }
+        DataType::UInt32 => {
+            record.extend((value.as_u64().ok_or_else(invalid)? as u32).to_le_bytes())
+        }
+        DataType::UInt64 => record.extend(value.as_u64().ok_or_else(invalid)?.to_le_bytes()),
+        DataType::Int8 => record.extend((value.as_i64().ok_or_else(invalid)? as i8).to_le_bytes()),
+        DataType::Int16 => {
+            record.extend((value.as_i64().ok_or_else(invalid)? as i16).to_le_bytes())
+        }
+        DataType::Int32 => {
+            record.extend((value.as_i64().ok_or_else(invalid)? as i32).to_le_bytes())
+        }
+        DataType::Int64 => record.extend(value.as_i64().ok_or_else(invalid)?.to_le_bytes()),
+        DataType::Float32 => {
+            record.extend((value.as_f64().ok_or_else(invalid)? as f32).to_le_bytes())
+        }
+        _ => record.extend(value.as_f64().ok_or_else(invalid)?.to_le_bytes()),
+    }
+    Ok(())
+}
+
+/// appends the record id on its size in bytes
+fn encode_record_id(record_id: u64, size: u8, record: &mut Vec<u8>) {
+    match size {
+        1 => record.push(record_id as u8),
+        2 => record.extend((record_id as u16).to_le_bytes()),
+        4 => record.extend((record_id as u32).to_le_bytes()),
+        8 => record.extend(record_id.to_le_bytes()),
+        _ => {}
+    }
+}
+
+/// data group streamed in DT blocks
+struct StreamDataGroup {
+    dg_position: u64,
+    /// number of bytes of the record ids, 0 for sorted data group
+    rec_id_size: u8,
+    /// records not yet written
+    buffer: Vec<u8>,
+    /// position of the last DL block, each one listing a DT block
+    last_list: Option<u64>,
+    /// number of bytes written in DT blocks
+    written: u64,
+}
+
+/// VLSD channel group holding the values of a variable length channel
+struct VlsdGroup {
+    record_id: u64,
+    cg_position: u64,
+    cycle_count: u64,
+    /// total size of the values with their length
+    data_bytes: u64,
+}
+
+/// channel group streamed in a data group
+struct StreamGroup {
+    /// index of the data group
+    data_group: usize,
+    record_id: u64,
+    cg_position: u64,
+    data_types: Vec<DataType>,
+    /// VLSD channel groups of the variable length channels, by channel index
+    vlsd: Vec<Option<VlsdGroup>>,
+    cycle_count: u64,
+}
+
+/// streaming mdf4 writer, see module documentation.
+/// Buffered records are written and the file finalized on finalize or when dropped
+pub struct MdfWriter {
+    file_name: String,
+    writer: BufWriter<File>,
+    /// end of file, position of next block
+    end: u64,
+    data_groups: Vec<StreamDataGroup>,
+    groups: Vec<StreamGroup>,
+    /// position of the link to be set with the next data group
+    dg_link: u64,
+    finalized: bool,
+}
+
+impl MdfWriter {
+    /// creates the mdf4 file marked unfinalized with its identification, header and
+    /// file history blocks
+    pub fn create(file_name: &str) -> Result<Self> {
+        let f: File = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(file_name)
+            .map_err(MdfError::Io)
+            .with_context(|| format!("Cannot create the file {file_name}"))?;
+        let mut buffer = Cursor::new(Vec::new());
+        buffer
+            .write_le(&IdBlock::default())
+            .context("Could not write IdBlock")?;
+        let mut hd = Hd4::default();
+        hd.hd_fh_first = 168; // after HD block
+        buffer.write_le(&hd).context("Could not write HDBlock")?;
+        let mut fh = FhBlock::default();
+        fh.fh_md_comment = hd.hd_fh_first + 56;
+        buffer.write_le(&fh).context("Could not write FHBlock")?;
+        let mut fh_comments = MetaData::new(MetaDataBlockType::MdBlock, BlockType::FH);
+        fh_comments.create_fh("created by streaming");
+        fh_comments.write(&mut buffer)?;
+        let mut buffer = buffer.into_inner();
+        buffer[..8].copy_from_slice(UNFINALIZED_ID);
+        let flags = ID_UNFIN_FLAGS as usize;
+        buffer[flags..flags + 2].copy_from_slice(&UNFIN_FLAGS.to_le_bytes());
+        let mut writer = BufWriter::new(f);
+        writer
+            .write_all(&buffer)
+            .context("Could not write file header blocks")?;
+        Ok(MdfWriter {
+            file_name: file_name.to_string(),
+            writer,
+            end: buffer.len() as u64,
+            data_groups: Vec::new(),
+            groups: Vec::new(),
+            dg_link: HD_DG_FIRST,
+            finalized: false,
+        })
+    }
+
+    /// writes bytes at a position before end of file
+    fn patch(&mut self, position: u64, bytes: &[u8]) -> Result<()> {
+        self.writer
+            .seek(SeekFrom::Start(position))
+            .context("Could not reach position to patch")?;
+        self.writer
+            .write_all(bytes)
+            .with_context(|| format!("Could not patch file at {position}"))?;
+        self.writer
+            .seek(SeekFrom::Start(self.end))
+            .context("Could not reach end of file")?;
+        Ok(())
+    }
+
+    /// appends blocks at end of file
+    fn append(&mut self, bytes: &[u8]) -> Result<u64> {
+        let position = self.end;
+        self.writer
+            .write_all(bytes)
+            .context("Could not append blocks")?;
+        self.end += bytes.len() as u64;
+        Ok(position)
+    }
+
+    /// declares a channel group in its own data group, records values being given in
+    /// channels order. Returns the index of the group used to write its records.
+    /// A group with variable length channels is written in an unsorted data group
+    pub fn add_channel_group(&mut self, channels: &[StreamChannel]) -> Result<usize> {
+        let unsorted = channels.iter().any(|cn| cn.is_vlsd());
+        let groups = self.add_data_group(&[channels], unsorted)?;
+        Ok(groups[0])
+    }
+
+    /// declares several channel groups sharing an unsorted data group, their records being
+    /// identified by record ids and written in arrival order, as logged from asynchronous
+    /// sources. Returns the indexes of the groups used to write their records
+    pub fn add_unsorted_data_group(
+        &mut self,
+        channel_groups: &[&[StreamChannel]],
+    ) -> Result<Vec<usize>> {
+        self.add_data_group(channel_groups, true)
+    }
+
+    /// writes the data group, its channel groups with their channels and VLSD channel groups
+    fn add_data_group(
+        &mut self,
+        channel_groups: &[&[StreamChannel]],
+        unsorted: bool,
+    ) -> Result<Vec<usize>> {
+        if self.finalized {
+            bail!("file {} is already finalized", self.file_name);
+        }
+        if channel_groups.is_empty() {
+            bail!("data group without channel group");
+        }
+        for channels in channel_groups {
+            if channels.is_empty() {
+                bail!("channel group without channel");
+            }
+            if channels.iter().filter(|cn| cn.master).count() > 1 {
+                bail!("channel group with more than one master channel");
+            }
+        }
+        // record ids start at 1, one per channel group and variable length channel
+        let n_records = channel_groups
+            .iter()
+            .map(|channels| 1 + channels.iter().filter(|cn| cn.is_vlsd()).count())
+            .sum::<usize>();
+        let rec_id_size: u8 = match n_records {
+            _ if !unsorted => 0,
+            0..=254 => 1,
+            255..=65534 => 2,
+            _ => 4,
+        };
+        let dg_position = self.end;
+        let data_group = self.data_groups.len();
+        let mut pointer = dg_position as i64;
+        let mut dg_block = Dg4Block::default();
+        dg_block.dg_rec_id_size = rec_id_size;
+        pointer += dg_block.dg_len as i64;
+        dg_block.dg_cg_first = pointer;
+
+        let mut record_id: u64 = unsorted as u64;
+        let mut blocks = Cursor::new(Vec::new());
+        let mut groups: Vec<StreamGroup> = Vec::with_capacity(channel_groups.len());
+        for (cg_index, channels) in channel_groups.iter().enumerate() {
+            let cg_position = pointer as u64;
+            let cg_header = default_short_header(BlockType::CG);
+            let mut cg_block = Cg4Block::default();
+            cg_block.cg_record_id = record_id;
+            pointer += cg_header.hdr_len as i64;
+            cg_block.cg_cn_first = pointer;
+
+            // channels and their texts
+            let mut cn_blocks: Vec<(Cn4Block, MetaData, Option<MetaData>)> = Vec::new();
+            let mut byte_offset: u32 = 0;
+            for (index, channel) in channels.iter().enumerate() {
+                let (cn_data_type, byte_count) = channel_data_type(&channel.data_type)
+                    .with_context(|| format!("invalid channel {}", channel.name))?;
+                let mut cn_block = Cn4Block::default();
+                pointer += default_short_header(BlockType::CN).hdr_len as i64;
+                if channel.is_vlsd() {
+                    cn_block.cn_type = 1;
+                } else if channel.master {
+                    cn_block.cn_type = 2;
+                    cn_block.cn_sync_type = 1; // time
+                }
+                cn_block.cn_data_type = cn_data_type;
+                cn_block.cn_byte_offset = byte_offset;
+                cn_block.cn_bit_count = byte_count * 8;
+                byte_offset += byte_count;
+                let mut tx_name = MetaData::new(MetaDataBlockType::TX, BlockType::CN);
+                tx_name.set_data_buffer(channel.name.as_bytes());
+                cn_block.cn_tx_name = pointer;
+                pointer += tx_name.block.hdr_len as i64;
+                let mut tx_unit: Option<MetaData> = None;
+                if let Some(unit) = &channel.unit {
+                    let mut tx = MetaData::new(MetaDataBlockType::TX, BlockType::CN);
+                    tx.set_data_buffer(unit.as_bytes());
+                    cn_block.cn_md_unit = pointer;
+                    pointer += tx.block.hdr_len as i64;
+                    tx_unit = Some(tx);
+                }
+                if index + 1 < channels.len() {
+                    cn_block.cn_cn_next = pointer;
+                }
+                cn_blocks.push((cn_block, tx_name, tx_unit));
+            }
+            cg_block.cg_data_bytes = byte_offset;
+
+            // VLSD channel groups following the channels, linked from their channel
+            let mut vlsd: Vec<Option<VlsdGroup>> = Vec::with_capacity(channels.len());
+            let mut vlsd_blocks: Vec<Cg4Block> = Vec::new();
+            let vlsd_first = pointer;
+            for (channel, (cn_block, _, _)) in channels.iter().zip(cn_blocks.iter_mut()) {
+                if !channel.is_vlsd() {
+                    vlsd.push(None);
+                    continue;
+                }
+                record_id += 1;
+                cn_block.cn_data = pointer;
+                let mut vlsd_block = Cg4Block::default();
+                vlsd_block.cg_record_id = record_id;
+                vlsd_block.cg_flags = 0b1; // VLSD channel group
+                vlsd.push(Some(VlsdGroup {
+                    record_id,
+                    cg_position: pointer as u64,
+                    cycle_count: 0,
+                    data_bytes: 0,
+                }));
+                pointer += cg_header.hdr_len as i64;
+                vlsd_block.cg_cg_next = pointer;
+                vlsd_blocks.push(vlsd_block);
+            }
+            let last_group = cg_index + 1 == channel_groups.len();
+            if !vlsd_blocks.is_empty() {
+                cg_block.cg_cg_next = vlsd_first;
+            } else if !last_group {
+                cg_block.cg_cg_next = pointer;
+            }
+            if last_group {
+                if let Some(vlsd_block) = vlsd_blocks.last_mut() {
+                    vlsd_block.cg_cg_next = 0;
+                }
+            }
+
+            blocks
+                .write_le(&cg_header)
+                .context("Could not write CGBlock header")?;
+            blocks
+                .write_le(&cg_block)
+                .context("Could not write CGBlock")?;
+            for (cn_block, tx_name, tx_unit) in cn_blocks {
+                blocks
+                    .write_le(&default_short_header(BlockType::CN))
+                    .context("Could not write CNBlock header")?;
+                blocks
+                    .write_le(&cn_block)
+                    .context("Could not write CNBlock")?;
+                tx_name.write(&mut blocks)?;
+                if let Some(tx) = tx_unit {
+                    tx.write(&mut blocks)?;
+                }
+            }
+            for vlsd_block in vlsd_blocks {
+                blocks
+                    .write_le(&cg_header)
+                    .context("Could not write VLSD CGBlock header")?;
+                blocks
+                    .write_le(&vlsd_block)
+                    .context("Could not write VLSD CGBlock")?;
+            }
+            groups.push(StreamGroup {
+                data_group,
+                record_id: cg_block.cg_record_id,
+                cg_position,
+                data_types: channels.iter().map(|cn| cn.data_type.clone()).collect(),
+                vlsd,
+                cycle_count: 0,
+            });
+            record_id += unsorted as u64;
+        }
+
+        let mut buffer = Cursor::new(Vec::new());
+        buffer
+            .write_le(&dg_block)
+            .context("Could not write DGBlock")?;
+        buffer
+            .write_all(&blocks.into_inner())
+            .context("Could not write CG and CN blocks")?;
+        self.append(&buffer.into_inner())?;
+        // links the new data group to the previous one or to the header
+        self.patch(self.dg_link, &dg_position.to_le_bytes())?;
+        self.dg_link = dg_position + DG_DG_NEXT;
+        self.data_groups.push(StreamDataGroup {
+            dg_position,
+            rec_id_size,
+            buffer: Vec::new(),
+            last_list: None,
+            written: 0,
+        });
+        let first = self.groups.len();
+        self.groups.extend(groups);
+        Ok((first..self.groups.len()).collect())
+    }
+
+    /// appends records to the channel group, each record having one value per channel.
+    /// Returns the number of records written
+    pub fn write_records<I, R>(&mut self, group: usize, records: I) -> Result<u64>
+    where
+        I: IntoIterator<Item = R>,
+        R: AsRef<[RecordValue]>,
+    {
+        if self.finalized {
+            bail!("file {} is already finalized", self.file_name);
+        }
+        let Some(stream) = self.groups.get_mut(group) else {
+            bail!("unknown channel group {group}");
+        };
+        let data_group = &mut self.data_groups[stream.data_group];
+        let rec_id_size = data_group.rec_id_size;
+        let mut count: u64 = 0;
+        let mut record_bytes: Vec<u8> = Vec::new();
+        for record in records {
+            let record = record.as_ref();
+            if record.len() != stream.data_types.len() {
+                bail!(MdfError::Parse(format!(
+                    "record of {} values for channel group {group} of {} channels",
+                    record.len(),
+                    stream.data_types.len()
+                )));
+            }
+            record_bytes.clear();
+            encode_record_id(stream.record_id, rec_id_size, &mut record_bytes);
+            for ((value, data_type), vlsd) in record
+                .iter()
+                .zip(stream.data_types.iter())
+                .zip(stream.vlsd.iter_mut())
+            {
+                match vlsd {
+                    Some(vlsd) => {
+                        // value in its VLSD record, before the record referencing its offset
+                        let bytes = value.as_bytes().ok_or_else(|| {
+                            MdfError::Parse(format!("value {value:?} invalid for type {data_type}"))
+                        })?;
+                        encode_record_id(vlsd.record_id, rec_id_size, &mut data_group.buffer);
+                        data_group.buffer.extend((bytes.len() as u32).to_le_bytes());
+                        data_group.buffer.extend(bytes);
+                        record_bytes.extend(vlsd.data_bytes.to_le_bytes());
+                        vlsd.data_bytes += 4 + bytes.len() as u64;
+                        vlsd.cycle_count += 1;
+                    }
+                    None => encode(value, data_type, &mut record_bytes)?,
+                }
+            }
+            data_group.buffer.extend(&record_bytes);
+            count += 1;
+        }
+        stream.cycle_count += count;
+        let data_group = stream.data_group;
+        if self.data_groups[data_group].buffer.len() >= DT_BLOCK_SIZE {
+            self.flush_data_group(data_group)?;
+        }
+        Ok(count)
+    }
+
+    /// writes the buffered records of all channel groups in DT blocks and flushes the file,
+    /// so that they can be recovered if recording is interrupted
+    pub fn flush(&mut self) -> Result<()> {
+        if self.finalized {
+            bail!("file {} is already finalized", self.file_name);
+        }
+        for data_group in 0..self.data_groups.len() {
+            self.flush_data_group(data_group)?;
+        }
+        self.writer.flush().context("Could not flush file")
+    }
+
+    /// writes the buffered records of a data group in a DT block followed by a DL block
+    /// listing it, linked from the data group or from the previous DL block
+    fn flush_data_group(&mut self, data_group: usize) -> Result<()> {
+        let data = std::mem::take(&mut self.data_groups[data_group].buffer);
+        if data.is_empty() {
+            return Ok(());
+        }
+        let mut dt_block = Dt4Block::default();
+        dt_block.len = data.len() as u64 + 24;
+        let mut buffer = Cursor::new(Vec::new());
+        buffer
+            .write_all(b"##DT")
+            .context("Could not write DTBlock id")?;
+        buffer
+            .write_le(&dt_block)
+            .context("Could not write DTBlock")?;
+        buffer
+            .write_all(&data)
+            .context("Could not write DTBlock data")?;
+        buffer
+            .write_all(&vec![0u8; (8 - data.len() % 8) % 8])
+            .context("Could not align DTBlock")?;
+        let position = self.append(&buffer.into_inner())?;
+        let stream = &self.data_groups[data_group];
+        let dl_block = Dl4Block::new(vec![position as i64], vec![stream.written]);
+        let link = match stream.last_list {
+            Some(last_list) => last_list + DL_DL_NEXT,
+            None => stream.dg_position + DG_DATA,
+        };
+        let mut buffer = Cursor::new(Vec::new());
+        buffer
+            .write_all(b"##DL")
+            .context("Could not write DLBlock id")?;
+        buffer
+            .write_le(&dl_block)
+            .context("Could not write DLBlock")?;
+        let dl_position = self.append(&buffer.into_inner())?;
+        self.patch(link, &dl_position.to_le_bytes())?;
+        let stream = &mut self.data_groups[data_group];
+        stream.last_list = Some(dl_position);
+        stream.written += data.len() as u64;
+        Ok(())
+    }
+
+    /// writes the remaining records, sets the cycle counts and marks the file finalized
+    fn complete(&mut self) -> Result<()> {
+        if self.finalized {
+            return Ok(());
+        }
+        self.finalized = true;
+        for data_group in 0..self.data_groups.len() {
+            self.flush_data_group(data_group)?;
+        }
+        let mut counts: Vec<(u64, u64, Option<u64>)> = Vec::new();
+        for stream in self.groups.iter() {
+            counts.push((stream.cg_position, stream.cycle_count, None));
+            for vlsd in stream.vlsd.iter().flatten() {
+                counts.push((vlsd.cg_position, vlsd.cycle_count, Some(vlsd.data_bytes)));
+            }
+        }
+        for (cg_position, cycle_count, data_bytes) in counts {
+            self.patch(cg_position + CG_CYCLE_COUNT, &cycle_count.to_le_bytes())?;
+            if let Some(data_bytes) = data_bytes {
+                // low part in cg_data_bytes and high part in cg_inval_bytes
+                self.patch(cg_position + CG_DATA_BYTES, &data_bytes.to_le_bytes())?;
+            }
+        }
+        self.writer.flush().context("Could not flush file")?;
+        self.patch(0, FINALIZED_ID)?;
+        self.patch(ID_UNFIN_FLAGS, &0u16.to_le_bytes())?;
+        self.writer.flush().context("Could not flush file")?;
+        Ok(())
+    }
+
+    /// writes the remaining records and completes the file
+    pub fn finalize(mut self) -> Result<()> {
+        self.complete()
+            .with_context(|| format!("failed finalizing file {}", self.file_name))
+    }
+}
+
+impl Drop for MdfWriter {
+    fn drop(&mut self) {
+        if let Err(error) = self.complete() {
+            warn!("failed finalizing file {}: {error:#}", self.file_name);
+        }
+    }
+}
@@ -0,0 +1,123 @@
+//! Parallel iterators and thread pool of rayon. With the single-thread feature or without
+//! the rayon feature, the same iterators run sequentially in the calling thread, for
+//! applications managing their own threads or tiny systems.
+pub use pool::*;
+
+#[cfg(all(feature = "rayon", not(feature = "single-thread")))]
+mod pool {
+    use anyhow::{Context, Result};
+
+    pub mod prelude {
+        pub use rayon::prelude::*;
+    }
+
+    /// number of threads of the current thread pool
+    pub fn current_num_threads() -> usize {
+        rayon::current_num_threads()
+    }
+
+    /// runs op in a thread pool of n_threads, in the global thread pool if None
+    pub fn install<R: Send>(
+        n_threads: Option<usize>,
+        op: impl FnOnce() -> Result<R> + Send,
+    ) -> Result<R> {
+        match n_threads {
+            Some(n_threads) => rayon::ThreadPoolBuilder::new()
+                .num_threads(n_threads)
+                .build()
+                .context("failed building reading thread pool")?
+                .install(op),
+            None => op(),
+        }
+    }
+}
+
+#[cfg(any(feature = "single-thread", not(feature = "rayon")))]
+mod pool {
+    use anyhow::Result;
+
+    /// sequential counterparts of the rayon traits used by the crate
+    pub mod prelude {
+        /// into_par_iter as into_iter
+        pub trait IntoParallelIterator: IntoIterator + Sized {
+            fn into_par_iter(self) -> Self::IntoIter {
+                self.into_iter()
+            }
+        }
+        impl<I: IntoIterator> IntoParallelIterator for I {}
+
+        /// par_iter as iter
+        pub trait IntoParallelRefIterator<'a> {
+            type Iter: Iterator;
+            fn par_iter(&'a self) -> Self::Iter;
+        }
+        impl<'a, T: 'a + ?Sized> IntoParallelRefIterator<'a> for T
+        where
+            &'a T: IntoIterator,
+        {
+            type Iter = <&'a T as IntoIterator>::IntoIter;
+            fn par_iter(&'a self) -> Self::Iter {
+                self.into_iter()
+            }
+        }
+
+        /// par_iter_mut as iter_mut
+        pub trait IntoParallelRefMutIterator<'a> {
+            type Iter: Iterator;
+            fn par_iter_mut(&'a mut self) -> Self::Iter;
+        }
+        impl<'a, T: 'a + ?Sized> IntoParallelRefMutIterator<'a> for T
+        where
+            &'a mut T: IntoIterator,
+        {
+            type Iter = <&'a mut T as IntoIterator>::IntoIter;
+            fn par_iter_mut(&'a mut self) -> Self::Iter {
+                self.into_iter()
+            }
+        }
+
+        /// par_chunks as chunks
+        pub trait ParallelSlice<T> {
+            fn par_chunks(&self, chunk_size: usize) -> std::slice::Chunks<'_, T>;
+        }
+        impl<T> ParallelSlice<T> for [T] {
+            fn par_chunks(&self, chunk_size: usize) -> std::slice::Chunks<'_, T> {
+                self.chunks(chunk_size)
+            }
+        }
+
+        /// par_extend as extend
+        pub trait ParallelExtend<T> {
+            fn par_extend<I: IntoIterator<Item = T>>(&mut self, iter: I);
+        }
+        impl<T, C: Extend<T>> ParallelExtend<T> for C {
+            fn par_extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+                self.extend(iter)
+            }
+        }
+
+        /// rayon iterator methods differing from the standard ones
+        pub trait ParallelIterator: Iterator + Sized {
+            fn reduce_with<F>(self, op: F) -> Option<Self::Item>
+            where
+                F: Fn(Self::Item, Self::Item) -> Self::Item,
+            {
+                self.reduce(op)
+            }
+        }
+        impl<I: Iterator> ParallelIterator for I {}
+    }
+
+    /// only the calling thread
+    pub fn current_num_threads() -> usize {
+        1
+    }
+
+    /// runs op in the calling thread whatever the number of threads
+    pub fn install<R: Send>(
+        _n_threads: Option<usize>,
+        op: impl FnOnce() -> Result<R> + Send,
+    ) -> Result<R> {
+        op()
+    }
+}
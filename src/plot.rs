@@ -0,0 +1,325 @@
+//! Quick plotting of channels against their master for sanity checks:
+//! characters drawing in terminal or png image file, channels data must be loaded in memory.
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+use anyhow::{anyhow, bail, Context, Result};
+use arrow::array::AsArray;
+use arrow::compute::cast;
+use arrow::datatypes::{DataType, Float64Type};
+use yazi::{CompressionLevel, Encoder, Format};
+
+use crate::mdfreader::Mdf;
+
+/// symbols used to draw the channels in terminal
+const SYMBOLS: [char; 8] = ['*', '+', 'o', 'x', '#', '@', '%', '&'];
+/// colors used to draw the channels in png image
+const COLORS: [[u8; 3]; 8] = [
+    [31, 119, 180],
+    [255, 127, 14],
+    [44, 160, 44],
+    [214, 39, 40],
+    [148, 103, 189],
+    [140, 86, 75],
+    [227, 119, 194],
+    [127, 127, 127],
+];
+/// margin in pixels around the png plot area
+const MARGIN: usize = 20;
+
+/// valid samples of a channel against its master, or index if no master
+pub struct Series {
+    pub name: String,
+    pub unit: Option<String>,
+    pub master: Option<String>,
+    pub points: Vec<(f64, f64)>,
+}
+
+/// returns the channel data in f64, None if not in memory or not numeric
+fn to_f64(mdf: &Mdf, name: &str) -> Option<Vec<Option<f64>>> {
+    let data = mdf.get_channel_data(name).filter(|d| !d.is_empty())?;
+    let data = cast(&data.as_ref(), &DataType::Float64).ok()?;
+    Some(data.as_primitive::<Float64Type>().iter().collect())
+}
+
+/// builds the series of the channels, invalid or not finite samples are skipped
+pub fn series(mdf: &Mdf, channel_names: &[String]) -> Result<Vec<Series>> {
+    let mut series = Vec::with_capacity(channel_names.len());
+    for name in channel_names {
+        let Some(values) = to_f64(mdf, name) else {
+            bail!("channel {name} has no numeric data in memory to plot");
+        };
+        let master = mdf.get_channel_master(name).filter(|m| m != name);
+        let master_values = master.as_deref().and_then(|m| to_f64(mdf, m));
+        let points = values
+            .iter()
+            .enumerate()
+            .filter_map(|(index, y)| {
+                let x = match &master_values {
+                    Some(master_values) => master_values.get(index).copied().flatten()?,
+                    None => index as f64,
+                };
+                let y = (*y)?;
+                (x.is_finite() && y.is_finite()).then_some((x, y))
+            })
+            .collect();
+        series.push(Series {
+            name: name.clone(),
+            unit: mdf.get_channel_unit(name)?,
+            master: master.filter(|_| master_values.is_some()),
+            points,
+        });
+    }
+    Ok(series)
+}
+
+/// x and y ranges covering all series, widened when empty
+fn bounds(series: &[Series]) -> Result<((f64, f64), (f64, f64))> {
+    let mut x = (f64::INFINITY, f64::NEG_INFINITY);
+    let mut y = (f64::INFINITY, f64::NEG_INFINITY);
+    for (px, py) in series.iter().flat_map(|s| s.points.iter()) {
+        x = (x.0.min(*px), x.1.max(*px));
+        y = (y.0.min(*py), y.1.max(*py));
+    }
+    if x.0 > x.1 {
+        bail!("no valid sample to plot");
+    }
+    let widen = |(min, max): (f64, f64)| {
+        if min == max {
+            (min - 0.5, max + 0.5)
+        } else {
+            (min, max)
+        }
+    };
+    Ok((widen(x), widen(y)))
+}
+
+/// scales value in range into [0, size - 1]
+fn scale(value: f64, (min, max): (f64, f64), size: usize) -> usize {
+    (((value - min) / (max - min)) * (size - 1) as f64).round() as usize
+}
+
+/// one line per series with its symbol or color index, unit and value range
+fn legend(series: &[Series], marker: impl Fn(usize) -> String) -> String {
+    let mut legend = String::new();
+    for (index, s) in series.iter().enumerate() {
+        let (min, max) = s
+            .points
+            .iter()
+            .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), (_, y)| {
+                (min.min(*y), max.max(*y))
+            });
+        legend.push_str(&format!(
+            "{} {} [{}] vs {}: {} samples, min {min}, max {max}\n",
+            marker(index),
+            s.name,
+            s.unit.as_deref().unwrap_or_default(),
+            s.master.as_deref().unwrap_or("index"),
+            s.points.len(),
+        ));
+    }
+    legend
+}
+
+/// draws the channels with characters, returns the plot and its legend
+pub fn plot_terminal(
+    mdf: &Mdf,
+    channel_names: &[String],
+    width: usize,
+    height: usize,
+) -> Result<String> {
+    if width < 2 || height < 2 {
+        bail!("plot size {width}x{height} is too small");
+    }
+    let series = series(mdf, channel_names)?;
+    let (x_range, y_range) = bounds(&series)?;
+    let mut grid = vec![vec![' '; width]; height];
+    for (index, s) in series.iter().enumerate() {
+        for (x, y) in s.points.iter() {
+            let column = scale(*x, x_range, width);
+            let row = height - 1 - scale(*y, y_range, height);
+            grid[row][column] = SYMBOLS[index % SYMBOLS.len()];
+        }
+    }
+    let label_width = format!("{:.4e}", y_range.1)
+        .len()
+        .max(format!("{:.4e}", y_range.0).len());
+    let mut plot = String::new();
+    for (row, line) in grid.iter().enumerate() {
+        let label = match row {
+            0 => format!("{:.4e}", y_range.1),
+            r if r == height - 1 => format!("{:.4e}", y_range.0),
+            _ => String::new(),
+        };
+        plot.push_str(&format!(
+            "{label:>label_width$} |{}\n",
+            line.iter().collect::<String>()
+        ));
+    }
+    plot.push_str(&format!("{:>label_width$} +{}\n", "", "-".repeat(width)));
+    let x_min = format!("{:.4e}", x_range.0);
+    let x_max = format!("{:.4e}", x_range.1);
+    plot.push_str(&format!(
+        "{:>label_width$}  {x_min}{x_max:>padding$}\n",
+        "",
+        padding = width.saturating_sub(x_min.len())
+    ));
+    plot.push_str(&legend(&series, |index| {
+        SYMBOLS[index % SYMBOLS.len()].to_string()
+    }));
+    Ok(plot)
+}
+
+/// rgb image drawn line by line
+struct Canvas {
+    width: usize,
+    height: usize,
+    pixels: Vec<u8>,
+}
+
+impl Canvas {
+    fn new(width: usize, height: usize) -> Self {
+        Canvas {
+            width,
+            height,
+            pixels: vec![255; width * height * 3],
+        }
+    }
+    fn set(&mut self, x: i64, y: i64, color: [u8; 3]) {
+        if x >= 0 && y >= 0 && (x as usize) < self.width && (y as usize) < self.height {
+            let offset = (y as usize * self.width + x as usize) * 3;
+            self.pixels[offset..offset + 3].copy_from_slice(&color);
+        }
+    }
+    /// Bresenham line between two points
+    fn line(&mut self, (mut x0, mut y0): (i64, i64), (x1, y1): (i64, i64), color: [u8; 3]) {
+        let dx = (x1 - x0).abs();
+        let dy = -(y1 - y0).abs();
+        let sx = if x0 < x1 { 1 } else { -1 };
+        let sy = if y0 < y1 { 1 } else { -1 };
+        let mut error = dx + dy;
+        loop {
+            self.set(x0, y0, color);
+            if x0 == x1 && y0 == y1 {
+                break;
+            }
+            let e2 = 2 * error;
+            if e2 >= dy {
+                error += dy;
+                x0 += sx;
+            }
+            if e2 <= dx {
+                error += dx;
+                y0 += sy;
+            }
+        }
+    }
+    /// png encoding with 8 bits rgb pixels, no filtering
+    fn to_png(&self) -> Result<Vec<u8>> {
+        let mut raw = Vec::with_capacity((self.width * 3 + 1) * self.height);
+        for row in self.pixels.chunks(self.width * 3) {
+            raw.push(0);
+            raw.extend_from_slice(row);
+        }
+        let mut encoder = Encoder::boxed();
+        encoder.set_format(Format::Zlib);
+        encoder.set_level(CompressionLevel::Default);
+        let mut compressed = Vec::new();
+        let mut stream = encoder.stream_into_vec(&mut compressed);
+        stream
+            .write_all(&raw)
+            .map_err(|e| anyhow!("failed compressing png image data: {:?}", e))?;
+        stream
+            .finish()
+            .map_err(|e| anyhow!("failed finishing png image compression: {:?}", e))?;
+        let mut header = Vec::with_capacity(13);
+        header.extend_from_slice(&(self.width as u32).to_be_bytes());
+        header.extend_from_slice(&(self.height as u32).to_be_bytes());
+        header.extend_from_slice(&[8, 2, 0, 0, 0]);
+        let mut png = vec![0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+        png_chunk(&mut png, b"IHDR", &header);
+        png_chunk(&mut png, b"IDAT", &compressed);
+        png_chunk(&mut png, b"IEND", &[]);
+        Ok(png)
+    }
+}
+
+/// crc32 of png chunks
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for byte in bytes {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+/// appends a png chunk with its length and crc
+fn png_chunk(png: &mut Vec<u8>, chunk_type: &[u8; 4], data: &[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = png.len();
+    png.extend_from_slice(chunk_type);
+    png.extend_from_slice(data);
+    let crc = crc32(&png[start..]);
+    png.extend_from_slice(&crc.to_be_bytes());
+}
+
+/// draws the channels as colored lines into a png image file, returns the legend
+pub fn plot_png(
+    mdf: &Mdf,
+    channel_names: &[String],
+    file_name: &str,
+    width: usize,
+    height: usize,
+) -> Result<String> {
+    if width <= 2 * MARGIN + 1 || height <= 2 * MARGIN + 1 {
+        bail!("plot size {width}x{height} is too small");
+    }
+    let series = series(mdf, channel_names)?;
+    let (x_range, y_range) = bounds(&series)?;
+    let mut canvas = Canvas::new(width, height);
+    let (left, top) = (MARGIN as i64, MARGIN as i64);
+    let (right, bottom) = ((width - MARGIN) as i64, (height - MARGIN) as i64);
+    let frame = [160, 160, 160];
+    canvas.line((left, top), (right, top), frame);
+    canvas.line((right, top), (right, bottom), frame);
+    canvas.line((right, bottom), (left, bottom), frame);
+    canvas.line((left, bottom), (left, top), frame);
+    let plot_width = width - 2 * MARGIN + 1;
+    let plot_height = height - 2 * MARGIN + 1;
+    for (index, s) in series.iter().enumerate() {
+        let color = COLORS[index % COLORS.len()];
+        let pixels: Vec<(i64, i64)> = s
+            .points
+            .iter()
+            .map(|(x, y)| {
+                (
+                    left + scale(*x, x_range, plot_width) as i64,
+                    bottom - scale(*y, y_range, plot_height) as i64,
+                )
+            })
+            .collect();
+        match pixels.as_slice() {
+            [single] => canvas.set(single.0, single.1, color),
+            _ => pixels
+                .windows(2)
+                .for_each(|pair| canvas.line(pair[0], pair[1], color)),
+        }
+    }
+    let file =
+        File::create(file_name).with_context(|| format!("failed creating png file {file_name}"))?;
+    let mut writer = BufWriter::new(file);
+    writer
+        .write_all(&canvas.to_png()?)
+        .with_context(|| format!("failed writing png file {file_name}"))?;
+    Ok(legend(&series, |index| {
+        let [r, g, b] = COLORS[index % COLORS.len()];
+        format!("#{r:02x}{g:02x}{b:02x}")
+    }))
+}
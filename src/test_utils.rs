@@ -0,0 +1,917 @@
+//! Synthetic mdf3 and mdf4 files built programmatically with chosen channel data types,
+//! data block layouts and sizes, so that reading can be tested without external files.
+pub mod golden;
+
+use std::fs;
+use std::sync::Arc;
+
+use anyhow::{bail, Context, Result};
+use arrow::array::{
+    ArrayRef, AsArray, FixedSizeBinaryArray, Float32Array, Float64Array, Int16Array, Int32Array,
+    Int64Array, Int8Array, LargeBinaryArray, LargeStringArray, UInt16Array, UInt32Array,
+    UInt64Array, UInt8Array,
+};
+use arrow::compute::cast;
+use arrow::datatypes::{DataType, Float64Type};
+use yazi::{compress, CompressionLevel, Format};
+
+/// layout of the data blocks of each data group
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DataLayout {
+    /// one DT block, the only layout of mdf3
+    Dt,
+    /// DL block listing DT blocks of at most records_per_block records
+    Dl { records_per_block: usize },
+    /// one deflate compressed DZ block
+    Dz,
+    /// HL block pointing to a DL block listing DZ blocks of at most records_per_block records
+    Hl { records_per_block: usize },
+}
+
+/// Builder of synthetic mdf files. Each channel group has a Float64 time master channel
+/// named time_<group> followed by channels named g<group>_c<index> of the chosen data types,
+/// all having deterministic values returned by expected_data. LargeUtf8 and LargeBinary
+/// channels are mdf4 variable length signal data channels of values of varying lengths
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyntheticMdf {
+    /// 330 or 420
+    version: u16,
+    /// data types of the channels of each channel group, master excluded
+    groups: Vec<Vec<DataType>>,
+    /// number of records of each channel group
+    records: usize,
+    /// data blocks layout
+    layout: DataLayout,
+    /// one data group per channel group, else one data group with records of all groups
+    /// identified by a 1 byte record id
+    sorted: bool,
+    /// offset and factor of the linear conversion of the channels, masters excluded
+    conversion: Option<(f64, f64)>,
+    /// group and dimensions of a channel composed of all the group channels by a CD block
+    dependency: Option<(usize, Vec<u16>)>,
+    /// record id repeated at the end of the unsorted records, mdf3 only
+    trailing_record_id: bool,
+    /// group and file name of a video, the first channel of the group being its frame index
+    /// synchronisation channel, mdf4 only
+    video_sync: Option<(usize, String)>,
+    /// maximum size in bytes of the signal data blocks of variable length channels, listed
+    /// by a DL block, so that values and their length can be split between two blocks
+    signal_data_block_size: Option<usize>,
+    /// variable length channels written in the records as maximum length data channels,
+    /// their number of valid bytes being given by a size channel appended to their group
+    maximum_length: bool,
+}
+
+impl SyntheticMdf {
+    /// mdf 4.20 file builder
+    pub fn mdf4() -> Self {
+        SyntheticMdf {
+            version: 420,
+            groups: Vec::new(),
+            records: 100,
+            layout: DataLayout::Dt,
+            sorted: true,
+            conversion: None,
+            dependency: None,
+            trailing_record_id: false,
+            video_sync: None,
+            signal_data_block_size: None,
+            maximum_length: false,
+        }
+    }
+    /// mdf 3.30 file builder
+    pub fn mdf3() -> Self {
+        SyntheticMdf {
+            version: 330,
+            ..Self::mdf4()
+        }
+    }
+    /// adds a channel group with channels of the given data types, integers, floats, or for
+    /// mdf4 LargeUtf8 and LargeBinary
+    pub fn with_group(mut self, data_types: &[DataType]) -> Self {
+        self.groups.push(data_types.to_vec());
+        self
+    }
+    /// sets the number of records of each channel group
+    pub fn with_records(mut self, records: usize) -> Self {
+        self.records = records;
+        self
+    }
+    /// sets the data blocks layout
+    pub fn with_layout(mut self, layout: DataLayout) -> Self {
+        self.layout = layout;
+        self
+    }
+    /// writes the records of all channel groups interleaved in a single data group
+    pub fn unsorted(mut self) -> Self {
+        self.sorted = false;
+        self
+    }
+    /// adds to the channels, masters excluded, a linear conversion offset + factor * raw,
+    /// mdf4 only. The factor should not be an integer so that all data converts into Float64
+    pub fn with_linear_conversion(mut self, offset: f64, factor: f64) -> Self {
+        self.conversion = Some((offset, factor));
+        self
+    }
+    /// adds to the group a channel named g<group>_array depending on all the other channels
+    /// of the group, master excluded, with the given dimensions. Mdf3 only, the channels
+    /// should share the same data type
+    pub fn with_dependency(mut self, group: usize, dims: &[u16]) -> Self {
+        self.dependency = Some((group, dims.to_vec()));
+        self
+    }
+    /// repeats the record id at the end of each record of unsorted data, mdf3 only
+    pub fn with_trailing_record_id(mut self) -> Self {
+        self.trailing_record_id = true;
+        self
+    }
+    /// makes the first channel of the group, after master, a synchronisation channel of
+    /// frame indexes referencing an external video file attachment, mdf4 only
+    pub fn with_video_sync(mut self, group: usize, file_name: &str) -> Self {
+        self.video_sync = Some((group, file_name.to_string()));
+        self
+    }
+    /// splits the signal data of the variable length channels into blocks of at most size
+    /// bytes listed by a DL block, DZ compressed with Dz and Hl layouts. Mdf4 only
+    pub fn with_signal_data_block_size(mut self, size: usize) -> Self {
+        self.signal_data_block_size = Some(size);
+        self
+    }
+    /// writes the variable length channels in the records as maximum length data channels
+    /// of the longest value, each with a UInt32 size channel named g<group>_c<index>_size
+    /// appended to its group. Mdf4 only
+    pub fn with_maximum_length(mut self) -> Self {
+        self.maximum_length = true;
+        self
+    }
+    /// name of the master channel of the group
+    pub fn master_name(group: usize) -> String {
+        format!("time_{group}")
+    }
+    /// name of the channel of the group
+    pub fn channel_name(group: usize, index: usize) -> String {
+        format!("g{group}_c{index}")
+    }
+    /// name of the size channel of a maximum length data channel of the group
+    pub fn size_name(group: usize, index: usize) -> String {
+        format!("g{group}_c{index}_size")
+    }
+    /// name of the channel composed by dependency of the group
+    pub fn dependency_name(group: usize) -> String {
+        format!("g{group}_array")
+    }
+    /// names of all the channels, masters and size channels included, composed channel
+    /// excluded
+    pub fn channel_names(&self) -> Vec<String> {
+        self.groups
+            .iter()
+            .enumerate()
+            .flat_map(|(group, data_types)| {
+                std::iter::once(Self::master_name(group))
+                    .chain((0..data_types.len()).map(move |index| Self::channel_name(group, index)))
+                    .chain(
+                        self.maximum_length_indexes(group)
+                            .into_iter()
+                            .map(move |index| Self::size_name(group, index)),
+                    )
+            })
+            .collect()
+    }
+    /// values of the channel written in file, None if channel does not exist
+    pub fn expected_data(&self, channel_name: &str) -> Option<ArrayRef> {
+        self.groups
+            .iter()
+            .enumerate()
+            .find_map(|(group, data_types)| {
+                if channel_name == Self::master_name(group) {
+                    return Some(Ok::<_, anyhow::Error>(master_values(self.records)));
+                }
+                if let Some(index) = self
+                    .maximum_length_indexes(group)
+                    .into_iter()
+                    .find(|index| channel_name == Self::size_name(group, *index))
+                {
+                    return Some(
+                        channel_values(&data_types[index], seed(group, index), self.records)
+                            .and_then(|values| maximum_length_values(&values))
+                            .map(|(_values, sizes)| sizes),
+                    );
+                }
+                (0..data_types.len())
+                    .find(|index| channel_name == Self::channel_name(group, *index))
+                    .map(|index| {
+                        let values =
+                            channel_values(&data_types[index], seed(group, index), self.records)?;
+                        match self.conversion {
+                            Some((offset, factor)) => Ok(Arc::new(
+                                cast(&values, &DataType::Float64)?
+                                    .as_primitive::<Float64Type>()
+                                    .unary::<_, Float64Type>(|value| value * factor + offset),
+                            ) as ArrayRef),
+                            None => Ok(values),
+                        }
+                    })
+            })?
+            .ok()
+    }
+    /// writes the file
+    pub fn write(&self, file_name: &str) -> Result<()> {
+        fs::write(file_name, self.to_bytes()?)
+            .with_context(|| format!("failed writing synthetic file {file_name}"))
+    }
+    /// returns the file content
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        if self.groups.is_empty() {
+            bail!("synthetic file needs at least one channel group");
+        }
+        if self.groups.len() > 255 {
+            bail!("synthetic file has more than 255 channel groups");
+        }
+        let groups = self
+            .groups
+            .iter()
+            .enumerate()
+            .map(|(group, data_types)| {
+                let mut columns = vec![master_values(self.records)];
+                let mut sizes = Vec::new();
+                for (index, data_type) in data_types.iter().enumerate() {
+                    let values = channel_values(data_type, seed(group, index), self.records)?;
+                    if self.maximum_length && is_variable_length(data_type) {
+                        let (values, size) = maximum_length_values(&values)?;
+                        columns.push(values);
+                        sizes.push(size);
+                    } else {
+                        columns.push(values);
+                    }
+                }
+                columns.extend(sizes);
+                Ok(columns)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        if self.version < 400 {
+            if self.layout != DataLayout::Dt {
+                bail!("mdf3 data can only be written in a single data block");
+            }
+            if self.conversion.is_some() {
+                bail!("mdf3 synthetic channels can not have conversion");
+            }
+            if self.video_sync.is_some() {
+                bail!("mdf3 synthetic channels can not have video synchronisation");
+            }
+            if self.has_variable_length() {
+                bail!("mdf3 synthetic channels can not have variable length");
+            }
+            if let Some((group, _)) = &self.dependency {
+                if self
+                    .groups
+                    .get(*group)
+                    .is_none_or(|data_types| data_types.is_empty())
+                {
+                    bail!("dependency group {group} has no channel");
+                }
+            }
+            Ok(self.mdf3_bytes(&groups))
+        } else if self.dependency.is_some() {
+            bail!("mdf4 synthetic channels can not have dependency");
+        } else if self.trailing_record_id {
+            bail!("mdf4 records can not have a trailing record id");
+        } else if self.has_variable_length() && self.conversion.is_some() {
+            bail!("mdf4 variable length channels can not have conversion");
+        } else if self.has_variable_length() && !self.sorted && !self.maximum_length {
+            bail!("mdf4 variable length signal data channels need sorted data");
+        } else if self.video_sync.as_ref().is_some_and(|(group, _)| {
+            self.groups
+                .get(*group)
+                .is_none_or(|data_types| data_types.is_empty())
+        }) {
+            bail!("video synchronisation group has no channel");
+        } else {
+            Ok(self.mdf4_bytes(&groups))
+        }
+    }
+
+    /// true if a channel has variable length values
+    fn has_variable_length(&self) -> bool {
+        self.groups.iter().flatten().any(is_variable_length)
+    }
+
+    /// indexes of the channels of the group written as maximum length data channels
+    fn maximum_length_indexes(&self, group: usize) -> Vec<usize> {
+        match self.groups.get(group) {
+            Some(data_types) if self.maximum_length => (0..data_types.len())
+                .filter(|index| is_variable_length(&data_types[*index]))
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// records of each data group, each record being prefixed by its record id if unsorted,
+    /// and followed by it if trailing record id is set
+    fn data_groups(&self, groups: &[Vec<ArrayRef>]) -> Vec<Vec<Vec<u8>>> {
+        let records = |columns: &[ArrayRef]| -> Vec<Vec<u8>> {
+            (0..self.records)
+                .map(|record| {
+                    columns
+                        .iter()
+                        .flat_map(|column| sample_bytes(column, record))
+                        .collect()
+                })
+                .collect()
+        };
+        if self.sorted {
+            groups.iter().map(|columns| records(columns)).collect()
+        } else {
+            let records: Vec<Vec<Vec<u8>>> =
+                groups.iter().map(|columns| records(columns)).collect();
+            let mut interleaved = Vec::with_capacity(self.records * groups.len());
+            for record in 0..self.records {
+                for (group, group_records) in records.iter().enumerate() {
+                    let mut bytes = vec![group as u8 + 1];
+                    bytes.extend_from_slice(&group_records[record]);
+                    if self.trailing_record_id {
+                        bytes.push(group as u8 + 1);
+                    }
+                    interleaved.push(bytes);
+                }
+            }
+            vec![interleaved]
+        }
+    }
+
+    /// mdf4 file: ID, HD, FH blocks then for each data group DG, CG, TX and CN blocks
+    /// followed by its data blocks
+    fn mdf4_bytes(&self, groups: &[Vec<ArrayRef>]) -> Vec<u8> {
+        let mut buffer = Vec::new();
+        buffer.extend_from_slice(b"MDF     4.20    mdfr    ");
+        buffer.extend_from_slice(&[0u8; 4]);
+        buffer.extend_from_slice(&420u16.to_le_bytes());
+        buffer.extend_from_slice(&[0u8; 34]);
+        let mut hd_data = Vec::with_capacity(32);
+        hd_data.extend_from_slice(&1_700_000_000_000_000_000u64.to_le_bytes());
+        hd_data.extend_from_slice(&[0u8; 8]); // time zone, dst, flags, reserved
+        hd_data.extend_from_slice(&[0u8; 16]); // start angle and distance
+        let hd = block4(&mut buffer, b"##HD", &[0; 6], &hd_data);
+        let fh = block4(&mut buffer, b"##FH", &[0; 2], &[0u8; 16]);
+        set_link4(&mut buffer, hd, 1, fh);
+        let video = self.video_sync.as_ref().map(|(group, file_name)| {
+            let tx_file = block4(&mut buffer, b"##TX", &[], &text4(file_name));
+            let tx_mime = block4(&mut buffer, b"##TX", &[], &text4("video/mp4"));
+            // external file: flags, creator, reserved, md5, original and embedded sizes
+            let at = block4(&mut buffer, b"##AT", &[0, tx_file, tx_mime, 0], &[0u8; 40]);
+            set_link4(&mut buffer, hd, 3, at);
+            (*group, at)
+        });
+        let cc = self.conversion.map(|(offset, factor)| {
+            let mut cc_data = vec![1u8, 0, 0, 0, 0, 0, 2, 0]; // linear, 2 values
+            cc_data.extend_from_slice(&[0u8; 16]); // physical range
+            cc_data.extend_from_slice(&offset.to_le_bytes());
+            cc_data.extend_from_slice(&factor.to_le_bytes());
+            block4(&mut buffer, b"##CC", &[0; 4], &cc_data)
+        });
+
+        let data_groups = self.data_groups(groups);
+        let channel_groups: Vec<Vec<usize>> = if self.sorted {
+            (0..groups.len()).map(|group| vec![group]).collect()
+        } else {
+            vec![(0..groups.len()).collect()]
+        };
+        let mut previous_dg: Option<u64> = None;
+        for (records, group_indexes) in data_groups.iter().zip(channel_groups) {
+            let record_id_size = u8::from(!self.sorted);
+            let mut dg_data = vec![record_id_size];
+            dg_data.extend_from_slice(&[0u8; 7]);
+            let dg = block4(&mut buffer, b"##DG", &[0; 4], &dg_data);
+            match previous_dg {
+                Some(previous) => set_link4(&mut buffer, previous, 0, dg),
+                None => set_link4(&mut buffer, hd, 0, dg),
+            }
+            previous_dg = Some(dg);
+            let mut previous_cg: Option<u64> = None;
+            for group in group_indexes {
+                let columns = &groups[group];
+                let record_length: usize = columns.iter().map(sample_size).sum();
+                let mut cg_data = Vec::with_capacity(32);
+                cg_data.extend_from_slice(&(group as u64 + u64::from(!self.sorted)).to_le_bytes());
+                cg_data.extend_from_slice(&(self.records as u64).to_le_bytes());
+                cg_data.extend_from_slice(&[0u8; 8]); // flags, path separator, reserved
+                cg_data.extend_from_slice(&(record_length as u32).to_le_bytes());
+                cg_data.extend_from_slice(&0u32.to_le_bytes());
+                let cg = block4(&mut buffer, b"##CG", &[0; 6], &cg_data);
+                match previous_cg {
+                    Some(previous) => set_link4(&mut buffer, previous, 0, cg),
+                    None => set_link4(&mut buffer, dg, 1, cg),
+                }
+                previous_cg = Some(cg);
+                let mut previous_cn: Option<u64> = None;
+                let mut byte_offset = 0usize;
+                let data_types = &self.groups[group];
+                let maximum_length_indexes = self.maximum_length_indexes(group);
+                let mut maximum_length_cns = Vec::new();
+                let mut size_cns = Vec::new();
+                for (index, column) in columns.iter().enumerate() {
+                    let name = if index == 0 {
+                        Self::master_name(group)
+                    } else if index <= data_types.len() {
+                        Self::channel_name(group, index - 1)
+                    } else {
+                        Self::size_name(group, maximum_length_indexes[index - 1 - data_types.len()])
+                    };
+                    // original data type of the maximum length data channels
+                    let data_type = if (1..=data_types.len()).contains(&index) {
+                        &data_types[index - 1]
+                    } else {
+                        column.data_type()
+                    };
+                    let tx = block4(&mut buffer, b"##TX", &[], &text4(&name));
+                    let video_at = video
+                        .filter(|(video_group, _)| *video_group == group && index == 1)
+                        .map(|(_, at)| at);
+                    let signal_data = signal_data(column)
+                        .map(|values| self.signal_data_blocks4(&mut buffer, &values));
+                    let maximum_length = matches!(column.data_type(), DataType::FixedSizeBinary(_));
+                    let (cn_type, sync_type) = match (index, video_at, signal_data) {
+                        (0, _, _) => (2u8, 1u8),
+                        (_, Some(_), _) => (4, 4),
+                        (_, _, Some(_)) => (1, 0),
+                        _ if maximum_length => (5, 0),
+                        _ => (0, 0),
+                    };
+                    let data_type = match data_type {
+                        DataType::Float32 | DataType::Float64 => 4u8,
+                        DataType::Int8 | DataType::Int16 | DataType::Int32 | DataType::Int64 => 2,
+                        DataType::LargeUtf8 => 7,
+                        DataType::LargeBinary => 10,
+                        _ => 0,
+                    };
+                    let size = sample_size(column);
+                    let mut cn_data = vec![cn_type, sync_type, data_type, 0];
+                    cn_data.extend_from_slice(&(byte_offset as u32).to_le_bytes());
+                    cn_data.extend_from_slice(&(8 * size as u32).to_le_bytes());
+                    cn_data.extend_from_slice(&[0u8; 12]); // flags, invalid bit, precision...
+                    cn_data.extend_from_slice(&[0u8; 48]); // ranges and limits
+                    let cc = if index == 0 {
+                        0
+                    } else {
+                        cc.unwrap_or_default()
+                    };
+                    let cn = block4(
+                        &mut buffer,
+                        b"##CN",
+                        &[
+                            0,
+                            0,
+                            tx,
+                            0,
+                            cc,
+                            video_at.or(signal_data).unwrap_or_default(),
+                            0,
+                            0,
+                        ],
+                        &cn_data,
+                    );
+                    match previous_cn {
+                        Some(previous) => set_link4(&mut buffer, previous, 0, cn),
+                        None => set_link4(&mut buffer, cg, 1, cn),
+                    }
+                    previous_cn = Some(cn);
+                    byte_offset += size;
+                    if maximum_length {
+                        maximum_length_cns.push(cn);
+                    } else if index > data_types.len() {
+                        size_cns.push(cn);
+                    }
+                }
+                // data links of the maximum length data channels to their size channel
+                for (cn, size_cn) in maximum_length_cns.into_iter().zip(size_cns) {
+                    set_link4(&mut buffer, cn, 5, size_cn);
+                }
+            }
+            let data = self.data_blocks4(&mut buffer, records);
+            set_link4(&mut buffer, dg, 2, data);
+        }
+        buffer
+    }
+
+    /// writes the data blocks of a data group according to layout, returning the
+    /// position of the block referenced by the data group
+    fn data_blocks4(&self, buffer: &mut Vec<u8>, records: &[Vec<u8>]) -> u64 {
+        match self.layout {
+            DataLayout::Dt => block4(buffer, b"##DT", &[], &records.concat()),
+            DataLayout::Dz => dz_block(buffer, b"DT", &records.concat()),
+            DataLayout::Dl { records_per_block } | DataLayout::Hl { records_per_block } => {
+                let chunks = records.chunks(records_per_block.max(1));
+                let compressed = matches!(self.layout, DataLayout::Hl { .. });
+                list_blocks4(
+                    buffer,
+                    b"DT",
+                    chunks.map(|c| c.concat()).collect(),
+                    compressed,
+                )
+            }
+        }
+    }
+
+    /// writes the signal data blocks of the values of a variable length channel according
+    /// to layout and signal data block size, returning the position of the block referenced
+    /// by the channel
+    fn signal_data_blocks4(&self, buffer: &mut Vec<u8>, values: &[Vec<u8>]) -> u64 {
+        let compressed = matches!(self.layout, DataLayout::Dz | DataLayout::Hl { .. });
+        if let Some(size) = self.signal_data_block_size {
+            let bytes = values.concat();
+            let chunks = bytes.chunks(size.max(1)).map(|c| c.to_vec());
+            return list_blocks4(buffer, b"SD", chunks.collect(), compressed);
+        }
+        match self.layout {
+            DataLayout::Dt => block4(buffer, b"##SD", &[], &values.concat()),
+            DataLayout::Dz => dz_block(buffer, b"SD", &values.concat()),
+            DataLayout::Dl { records_per_block } | DataLayout::Hl { records_per_block } => {
+                let chunks = values.chunks(records_per_block.max(1));
+                list_blocks4(
+                    buffer,
+                    b"SD",
+                    chunks.map(|c| c.concat()).collect(),
+                    compressed,
+                )
+            }
+        }
+    }
+
+    /// mdf3 file: ID and HD blocks then for each data group DG, CG and CN blocks
+    /// followed by its data block
+    fn mdf3_bytes(&self, groups: &[Vec<ArrayRef>]) -> Vec<u8> {
+        let mut buffer = Vec::new();
+        buffer.extend_from_slice(b"MDF     3.30    mdfr    ");
+        buffer.extend_from_slice(&0u16.to_le_bytes()); // little endian
+        buffer.extend_from_slice(&0u16.to_le_bytes()); // IEEE 754 floats
+        buffer.extend_from_slice(&330u16.to_le_bytes());
+        buffer.extend_from_slice(&0u16.to_le_bytes()); // code page
+        buffer.extend_from_slice(&[0u8; 32]);
+        let data_groups = self.data_groups(groups);
+        let hd = buffer.len();
+        buffer.extend_from_slice(b"HD");
+        buffer.extend_from_slice(&208u16.to_le_bytes());
+        buffer.extend_from_slice(&[0u8; 12]); // dg, comment and program links
+        buffer.extend_from_slice(&(data_groups.len() as u16).to_le_bytes());
+        buffer.extend_from_slice(b"14:11:202322:13:20");
+        buffer.extend_from_slice(&[0u8; 128]); // author, organization, project, subject
+        buffer.extend_from_slice(&1_700_000_000_000_000_000u64.to_le_bytes());
+        buffer.extend_from_slice(&[0u8; 36]); // utc offset, time quality and timer
+        let channel_groups: Vec<Vec<usize>> = if self.sorted {
+            (0..groups.len()).map(|group| vec![group]).collect()
+        } else {
+            vec![(0..groups.len()).collect()]
+        };
+        let mut previous_dg: Option<usize> = None;
+        for (records, group_indexes) in data_groups.iter().zip(channel_groups) {
+            let dg = buffer.len();
+            buffer.extend_from_slice(b"DG");
+            buffer.extend_from_slice(&28u16.to_le_bytes());
+            buffer.extend_from_slice(&[0u8; 16]); // next, cg, trigger and data links
+            buffer.extend_from_slice(&(group_indexes.len() as u16).to_le_bytes());
+            let n_record_ids = match (self.sorted, self.trailing_record_id) {
+                (true, _) => 0u16,
+                (false, false) => 1,
+                (false, true) => 2,
+            };
+            buffer.extend_from_slice(&n_record_ids.to_le_bytes());
+            buffer.extend_from_slice(&[0u8; 4]);
+            match previous_dg {
+                Some(previous) => set_link3(&mut buffer, previous, 0, dg),
+                None => set_link3(&mut buffer, hd, 0, dg),
+            }
+            previous_dg = Some(dg);
+            let mut previous_cg: Option<usize> = None;
+            for group in group_indexes {
+                let columns = &groups[group];
+                let record_length: usize = columns.iter().map(sample_size).sum();
+                let cg = buffer.len();
+                buffer.extend_from_slice(b"CG");
+                buffer.extend_from_slice(&30u16.to_le_bytes());
+                buffer.extend_from_slice(&[0u8; 12]); // next, cn and comment links
+                buffer.extend_from_slice(&(group as u16 + u16::from(!self.sorted)).to_le_bytes());
+                let dependency = self
+                    .dependency
+                    .as_ref()
+                    .filter(|(dependency_group, _)| *dependency_group == group);
+                let n_channels = columns.len() + usize::from(dependency.is_some());
+                buffer.extend_from_slice(&(n_channels as u16).to_le_bytes());
+                buffer.extend_from_slice(&(record_length as u16).to_le_bytes());
+                buffer.extend_from_slice(&(self.records as u32).to_le_bytes());
+                buffer.extend_from_slice(&0u32.to_le_bytes());
+                match previous_cg {
+                    Some(previous) => set_link3(&mut buffer, previous, 0, cg),
+                    None => set_link3(&mut buffer, dg, 1, cg),
+                }
+                previous_cg = Some(cg);
+                let mut channels: Vec<usize> = Vec::with_capacity(n_channels);
+                let mut bit_offset = 0usize;
+                for (index, column) in columns.iter().enumerate() {
+                    let name = if index == 0 {
+                        Self::master_name(group)
+                    } else {
+                        Self::channel_name(group, index - 1)
+                    };
+                    channels.push(cn3(&mut buffer, &name, index == 0, bit_offset, column));
+                    bit_offset += 8 * sample_size(column);
+                }
+                if let Some((_, dims)) = dependency {
+                    // overlaps the first dependent channel, its data being replaced when read
+                    let cn = cn3(
+                        &mut buffer,
+                        &Self::dependency_name(group),
+                        false,
+                        8 * sample_size(&columns[0]),
+                        &columns[1],
+                    );
+                    let cd = buffer.len();
+                    let dependencies = &channels[1..];
+                    let dependency_type = if dims.len() > 1 {
+                        256 + dims.len() as u16
+                    } else {
+                        1
+                    };
+                    buffer.extend_from_slice(b"CD");
+                    buffer.extend_from_slice(
+                        &(8 + 12 * dependencies.len() as u16 + 2 * dims.len() as u16).to_le_bytes(),
+                    );
+                    buffer.extend_from_slice(&dependency_type.to_le_bytes());
+                    buffer.extend_from_slice(&(dependencies.len() as u16).to_le_bytes());
+                    for dependency in dependencies {
+                        for link in [dg, cg, *dependency] {
+                            buffer.extend_from_slice(&(link as u32).to_le_bytes());
+                        }
+                    }
+                    if dims.len() > 1 {
+                        for dim in dims {
+                            buffer.extend_from_slice(&dim.to_le_bytes());
+                        }
+                    }
+                    set_link3(&mut buffer, cn, 3, cd);
+                    channels.push(cn);
+                }
+                set_link3(&mut buffer, cg, 1, channels[0]);
+                for pair in channels.windows(2) {
+                    set_link3(&mut buffer, pair[0], 0, pair[1]);
+                }
+            }
+            let data = buffer.len();
+            buffer.extend_from_slice(&records.concat());
+            set_link3(&mut buffer, dg, 3, data);
+        }
+        buffer
+    }
+}
+
+/// writes a mdf3 CN block of the column data type, returns its position
+fn cn3(
+    buffer: &mut Vec<u8>,
+    name: &str,
+    master: bool,
+    bit_offset: usize,
+    column: &ArrayRef,
+) -> usize {
+    let data_type: u16 = match column.data_type() {
+        DataType::Float32 => 2,
+        DataType::Float64 => 3,
+        DataType::Int8 | DataType::Int16 | DataType::Int32 | DataType::Int64 => 1,
+        _ => 0,
+    };
+    let cn = buffer.len();
+    buffer.extend_from_slice(b"CN");
+    buffer.extend_from_slice(&228u16.to_le_bytes());
+    buffer.extend_from_slice(&[0u8; 20]); // next, cc, ce, cd and comment links
+    buffer.extend_from_slice(&u16::from(master).to_le_bytes());
+    let mut short_name = [0u8; 32];
+    short_name[..name.len().min(31)].copy_from_slice(&name.as_bytes()[..name.len().min(31)]);
+    buffer.extend_from_slice(&short_name);
+    buffer.extend_from_slice(&[0u8; 128]); // description
+    buffer.extend_from_slice(&(bit_offset as u16).to_le_bytes());
+    buffer.extend_from_slice(&(8 * sample_size(column) as u16).to_le_bytes());
+    buffer.extend_from_slice(&data_type.to_le_bytes());
+    buffer.extend_from_slice(&[0u8; 34]); // range, sampling rate and names
+    buffer.extend_from_slice(&0u16.to_le_bytes()); // additional byte offset
+    cn
+}
+
+/// seed of the channel values
+fn seed(group: usize, index: usize) -> i64 {
+    (group * 31 + index * 7 + 1) as i64
+}
+
+/// master time values, 10ms raster
+fn master_values(records: usize) -> ArrayRef {
+    Arc::new(Float64Array::from_iter_values(
+        (0..records).map(|record| record as f64 * 0.01),
+    ))
+}
+
+/// deterministic channel values, integers wrapping around their type range
+fn channel_values(data_type: &DataType, seed: i64, records: usize) -> Result<ArrayRef> {
+    let integers = (0..records as i64).map(|record| record.wrapping_mul(seed).wrapping_sub(50));
+    let floats = (0..records).map(|record| record as f64 * 0.25 * seed as f64 - 10.0);
+    let array: ArrayRef = match data_type {
+        DataType::UInt8 => Arc::new(UInt8Array::from_iter_values(integers.map(|v| v as u8))),
+        DataType::Int8 => Arc::new(Int8Array::from_iter_values(integers.map(|v| v as i8))),
+        DataType::UInt16 => Arc::new(UInt16Array::from_iter_values(integers.map(|v| v as u16))),
+        DataType::Int16 => Arc::new(Int16Array::from_iter_values(integers.map(|v| v as i16))),
+        DataType::UInt32 => Arc::new(UInt32Array::from_iter_values(integers.map(|v| v as u32))),
+        DataType::Int32 => Arc::new(Int32Array::from_iter_values(integers.map(|v| v as i32))),
+        DataType::UInt64 => Arc::new(UInt64Array::from_iter_values(integers.map(|v| v as u64))),
+        DataType::Int64 => Arc::new(Int64Array::from_iter_values(integers)),
+        DataType::Float32 => Arc::new(Float32Array::from_iter_values(floats.map(|v| v as f32))),
+        DataType::Float64 => Arc::new(Float64Array::from_iter_values(floats)),
+        // lengths varying from empty, with multi bytes characters
+        DataType::LargeUtf8 => Arc::new(LargeStringArray::from_iter_values(
+            (0..records).map(|record| "aé".repeat(record * seed.unsigned_abs() as usize % 7)),
+        )),
+        DataType::LargeBinary => Arc::new(LargeBinaryArray::from_iter_values((0..records).map(
+            |record| {
+                (0..record * seed.unsigned_abs() as usize % 13)
+                    .map(|byte| (byte + record) as u8)
+                    .collect::<Vec<u8>>()
+            },
+        ))),
+        _ => bail!("synthetic channels of type {data_type} are not supported"),
+    };
+    Ok(array)
+}
+
+/// true if the data type is written as variable length signal data
+fn is_variable_length(data_type: &DataType) -> bool {
+    matches!(data_type, DataType::LargeUtf8 | DataType::LargeBinary)
+}
+
+/// size in bytes of a sample, the signal data offset for variable length channels
+fn sample_size(column: &ArrayRef) -> usize {
+    match column.data_type() {
+        DataType::LargeUtf8 | DataType::LargeBinary => 8,
+        DataType::FixedSizeBinary(size) => *size as usize,
+        data_type => data_type.primitive_width().unwrap_or_default(),
+    }
+}
+
+/// values of a variable length column padded with zeros to the longest value, and their
+/// number of bytes
+fn maximum_length_values(column: &ArrayRef) -> Result<(ArrayRef, ArrayRef)> {
+    let values: Vec<&[u8]> = match column.data_type() {
+        DataType::LargeUtf8 => column
+            .as_string::<i64>()
+            .iter()
+            .map(|text| text.unwrap_or_default().as_bytes())
+            .collect(),
+        DataType::LargeBinary => column
+            .as_binary::<i64>()
+            .iter()
+            .map(|bytes| bytes.unwrap_or_default())
+            .collect(),
+        data_type => bail!("{data_type} is not a variable length data type"),
+    };
+    let size = values
+        .iter()
+        .map(|value| value.len())
+        .max()
+        .unwrap_or_default();
+    let padded = FixedSizeBinaryArray::try_from_iter(values.iter().map(|value| {
+        let mut padded = value.to_vec();
+        padded.resize(size.max(1), 0);
+        padded
+    }))?;
+    let sizes = UInt32Array::from_iter_values(values.iter().map(|value| value.len() as u32));
+    Ok((Arc::new(padded), Arc::new(sizes)))
+}
+
+/// signal data of each value of a variable length column, its length followed by its bytes,
+/// null terminated for strings. None for other columns
+fn signal_data(column: &ArrayRef) -> Option<Vec<Vec<u8>>> {
+    let value = |bytes: &[u8], terminator: &[u8]| -> Vec<u8> {
+        let length = (bytes.len() + terminator.len()) as u32;
+        [&length.to_le_bytes()[..], bytes, terminator].concat()
+    };
+    match column.data_type() {
+        DataType::LargeUtf8 => Some(
+            column
+                .as_string::<i64>()
+                .iter()
+                .map(|text| value(text.unwrap_or_default().as_bytes(), &[0]))
+                .collect(),
+        ),
+        DataType::LargeBinary => Some(
+            column
+                .as_binary::<i64>()
+                .iter()
+                .map(|bytes| value(bytes.unwrap_or_default(), &[]))
+                .collect(),
+        ),
+        _ => None,
+    }
+}
+
+/// little endian bytes of a sample, the offset of the value in signal data for variable
+/// length channels
+fn sample_bytes(column: &ArrayRef, record: usize) -> Vec<u8> {
+    // each previous value preceded by its u32 length, strings followed by null terminator
+    let offset = match column.data_type() {
+        DataType::LargeUtf8 => {
+            Some(column.as_string::<i64>().value_offsets()[record] as usize + 5 * record)
+        }
+        DataType::LargeBinary => {
+            Some(column.as_binary::<i64>().value_offsets()[record] as usize + 4 * record)
+        }
+        _ => None,
+    };
+    if let Some(offset) = offset {
+        return (offset as u64).to_le_bytes().to_vec();
+    }
+    let size = sample_size(column);
+    let data = column.to_data();
+    let mut bytes = data.buffers()[0].as_slice()[record * size..(record + 1) * size].to_vec();
+    if cfg!(target_endian = "big") && column.data_type().is_primitive() {
+        bytes.reverse();
+    }
+    bytes
+}
+
+/// appends the data blocks of block type, DZ compressed if requested, listed by a DL block
+/// itself pointed by a HL block if compressed, returning the position of the first list block
+fn list_blocks4(
+    buffer: &mut Vec<u8>,
+    block_type: &[u8; 2],
+    chunks: Vec<Vec<u8>>,
+    compressed: bool,
+) -> u64 {
+    let mut blocks = Vec::new();
+    let mut offsets = Vec::new();
+    let mut offset = 0u64;
+    for bytes in chunks {
+        offsets.push(offset);
+        offset += bytes.len() as u64;
+        blocks.push(if compressed {
+            dz_block(buffer, block_type, &bytes)
+        } else {
+            let id = [b'#', b'#', block_type[0], block_type[1]];
+            block4(buffer, &id, &[], &bytes)
+        });
+    }
+    let mut links = vec![0];
+    links.extend_from_slice(&blocks);
+    let mut dl_data = vec![0u8; 4];
+    dl_data.extend_from_slice(&(blocks.len() as u32).to_le_bytes());
+    offsets
+        .iter()
+        .for_each(|offset| dl_data.extend_from_slice(&offset.to_le_bytes()));
+    let dl = block4(buffer, b"##DL", &links, &dl_data);
+    if compressed {
+        block4(buffer, b"##HL", &[dl], &[0u8; 8])
+    } else {
+        dl
+    }
+}
+
+/// appends a mdf4 block with its header, links and data padded to 8 bytes,
+/// returning its position
+fn block4(buffer: &mut Vec<u8>, id: &[u8; 4], links: &[u64], data: &[u8]) -> u64 {
+    let position = buffer.len() as u64;
+    let length = 24 + 8 * links.len() + data.len();
+    buffer.extend_from_slice(id);
+    buffer.extend_from_slice(&[0u8; 4]);
+    buffer.extend_from_slice(&(length as u64).to_le_bytes());
+    buffer.extend_from_slice(&(links.len() as u64).to_le_bytes());
+    links
+        .iter()
+        .for_each(|link| buffer.extend_from_slice(&link.to_le_bytes()));
+    buffer.extend_from_slice(data);
+    buffer.resize(buffer.len().next_multiple_of(8), 0);
+    position
+}
+
+/// sets the link of index of the mdf4 block at position
+fn set_link4(buffer: &mut [u8], block: u64, index: usize, target: u64) {
+    let at = block as usize + 24 + 8 * index;
+    buffer[at..at + 8].copy_from_slice(&target.to_le_bytes());
+}
+
+/// sets the link of index of the mdf3 block at position
+fn set_link3(buffer: &mut [u8], block: usize, index: usize, target: usize) {
+    let at = block + 4 + 4 * index;
+    buffer[at..at + 4].copy_from_slice(&(target as u32).to_le_bytes());
+}
+
+/// null terminated utf8 text padded to 8 bytes
+fn text4(text: &str) -> Vec<u8> {
+    let mut bytes = text.as_bytes().to_vec();
+    bytes.resize((bytes.len() + 1).next_multiple_of(8), 0);
+    bytes
+}
+
+/// appends a DZ block of deflate compressed data of block type, returning its position
+fn dz_block(buffer: &mut Vec<u8>, block_type: &[u8; 2], data: &[u8]) -> u64 {
+    let compressed =
+        compress(data, Format::Zlib, CompressionLevel::Default).expect("deflate never fails");
+    let mut dz_data = Vec::with_capacity(24 + compressed.len());
+    dz_data.extend_from_slice(block_type);
+    dz_data.extend_from_slice(&[0u8; 6]); // deflate, reserved and parameter
+    dz_data.extend_from_slice(&(data.len() as u64).to_le_bytes());
+    dz_data.extend_from_slice(&(compressed.len() as u64).to_le_bytes());
+    dz_data.extend_from_slice(&compressed);
+    block4(buffer, b"##DZ", &[], &dz_data)
+}
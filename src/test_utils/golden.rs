@@ -0,0 +1,150 @@
+//! Golden files harness. The ASAM example files and the other reference files are not part of
+//! the repository: they are read from the directory given by the MDFR_TEST_DATA environment
+//! variable, or downloaded once from the zip archive url given by MDFR_TEST_DATA_URL.
+//! The directory is expected to contain the ASAM examples in
+//! MDF4/ASAM_COMMON_MDF_V4-1-0/Base_Standard/Examples and mdf3 files in mdf3.
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::OnceLock;
+
+use anyhow::{bail, Context, Result};
+use log::warn;
+
+use crate::mdfreader::Mdf;
+
+/// environment variable giving the directory of the test data
+pub const TEST_DATA_ENV: &str = "MDFR_TEST_DATA";
+/// environment variable giving the url of a zip archive of the test data
+pub const TEST_DATA_URL_ENV: &str = "MDFR_TEST_DATA_URL";
+/// ASAM mdf4 examples folder, relative to the test data directory
+pub const ASAM_EXAMPLES: &str = "MDF4/ASAM_COMMON_MDF_V4-1-0/Base_Standard/Examples";
+/// mdf3 files folder, relative to the test data directory
+pub const MDF3_EXAMPLES: &str = "mdf3";
+
+/// returns the test data directory, downloading it at first call if only the url is given.
+/// None if test data is not available, golden tests are then skipped
+pub fn test_data_dir() -> Option<&'static Path> {
+    static DIR: OnceLock<Option<PathBuf>> = OnceLock::new();
+    DIR.get_or_init(|| {
+        locate_test_data().unwrap_or_else(|error| {
+            warn!("test data not available: {error:#}");
+            None
+        })
+    })
+    .as_deref()
+}
+
+fn locate_test_data() -> Result<Option<PathBuf>> {
+    if let Some(dir) = env::var_os(TEST_DATA_ENV) {
+        let dir = PathBuf::from(dir);
+        if !dir.is_dir() {
+            bail!("{TEST_DATA_ENV} {} is not a directory", dir.display());
+        }
+        return Ok(Some(dir));
+    }
+    match env::var(TEST_DATA_URL_ENV) {
+        Ok(url) => download_test_data(&url).map(Some),
+        Err(_) => Ok(None),
+    }
+}
+
+/// downloads the zip archive with curl and extracts it with unzip into the temporary
+/// directory, reused by the next runs once complete
+pub fn download_test_data(url: &str) -> Result<PathBuf> {
+    let dir = env::temp_dir().join("mdfr_test_data");
+    let complete = dir.join(".complete");
+    if complete.exists() {
+        return Ok(dir);
+    }
+    fs::create_dir_all(&dir).with_context(|| format!("failed creating {}", dir.display()))?;
+    let archive = dir.join("test_data.zip");
+    run(Command::new("curl")
+        .args([
+            "--fail",
+            "--location",
+            "--silent",
+            "--show-error",
+            "--output",
+        ])
+        .arg(&archive)
+        .arg(url))
+    .with_context(|| format!("failed downloading {url}"))?;
+    run(Command::new("unzip")
+        .args(["-q", "-o"])
+        .arg(&archive)
+        .arg("-d")
+        .arg(&dir))
+    .with_context(|| format!("failed extracting {}", archive.display()))?;
+    fs::remove_file(&archive)?;
+    fs::write(&complete, url)?;
+    Ok(dir)
+}
+
+fn run(command: &mut Command) -> Result<()> {
+    let status = command
+        .status()
+        .with_context(|| format!("failed running {:?}", command.get_program()))?;
+    if !status.success() {
+        bail!("{:?} exited with {status}", command.get_program());
+    }
+    Ok(())
+}
+
+/// returns recursively the mdf files of the folder, sorted, identified by their extension
+pub fn mdf_files(folder: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let mut folders = vec![folder.to_path_buf()];
+    while let Some(folder) = folders.pop() {
+        for entry in
+            fs::read_dir(&folder).with_context(|| format!("failed reading {}", folder.display()))?
+        {
+            let path = entry?.path();
+            if path.is_dir() {
+                folders.push(path);
+            } else if path
+                .extension()
+                .and_then(|extension| extension.to_str())
+                .is_some_and(|extension| {
+                    ["mf4", "mdf", "dat"].contains(&extension.to_ascii_lowercase().as_str())
+                })
+            {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// reads the file, writes it as mdf4 in the temporary directory and reads it back.
+/// Returns the names of the channels whose data differ or are missing after writing
+pub fn round_trip(file: &Path) -> Result<Vec<String>> {
+    let file_name = file
+        .to_str()
+        .with_context(|| format!("{} is not a valid utf8 path", file.display()))?;
+    let mut original = Mdf::new(file_name)?;
+    original.load_all_channels_data_in_memory()?;
+    let stem = file
+        .file_stem()
+        .map(|stem| stem.to_string_lossy())
+        .unwrap_or_default();
+    let output = env::temp_dir().join(format!("mdfr_round_trip_{stem}.mf4"));
+    let output_name = output.to_string_lossy();
+    let mut written = original
+        .write(&output_name, false)
+        .with_context(|| format!("failed writing {file_name} into {output_name}"))?;
+    written.load_all_channels_data_in_memory()?;
+    let mut mismatches: Vec<String> = original
+        .get_channel_names_set()
+        .into_iter()
+        .filter(|name| {
+            original.get_channel_data(name).is_some()
+                && original.get_channel_data(name) != written.get_channel_data(name)
+        })
+        .collect();
+    mismatches.sort();
+    fs::remove_file(&output)?;
+    Ok(mismatches)
+}
@@ -1,28 +1,102 @@
 #[cfg(test)]
 mod tests {
-    use anyhow::Result;
+    use anyhow::{Context, Result};
     use arrow::array::{
-        AsArray, FixedSizeBinaryBuilder, Float64Array, Float64Builder, Int16Builder, Int32Builder,
-        Int64Builder, LargeStringBuilder, PrimitiveBuilder, UInt64Builder,
+        Array, AsArray, BooleanArray, DictionaryArray, FixedSizeBinaryBuilder, Float64Array,
+        Float64Builder, Int16Builder, Int32Builder, Int64Builder, LargeStringBuilder,
+        PrimitiveBuilder, StringArray, UInt64Builder,
     };
 
-    use arrow::datatypes::Float32Type;
+    use arrow::compute::cast;
+    use arrow::datatypes::{
+        DataType, Float32Type, Float64Type, Int16Type, Int64Type, UInt32Type, UInt64Type, UInt8Type,
+    };
 
+    use crate::bus::dbc::Dbc;
+    use crate::bus::decode_can_frames;
     use crate::data_holder::channel_data::ChannelData;
-    use crate::mdfreader::Mdf;
+    use crate::data_holder::decimate::DecimationMethod;
+    use crate::error::MdfError;
+    use crate::mdfinfo::mdfinfo4::{
+        parse_dz, position_generator, Event, EventScope, EventType, Si4Block, Sr4Block,
+    };
+    use crate::mdfinfo::validate::{validate_file, validate_reader, ProblemKind};
+    use crate::mdfinfo::MdfInfo;
+    use crate::mdfreader::index4::{index_file_name, MdfIndex};
+    use crate::mdfreader::{AnonymizeOptions, Mdf, ReadOptions};
+    use crate::mdfwriter::mdfwriter3::{convert3to4, PROGRAM_BLOCK_FILE_NAME};
+    use crate::mdfwriter::mdfwriter4::Compression;
+    use crate::plot::{plot_png, plot_terminal};
+    use crate::test_utils::golden::{
+        mdf_files, round_trip, test_data_dir, ASAM_EXAMPLES, MDF3_EXAMPLES,
+    };
+    use binrw::BinReaderExt;
     use glob::glob;
+    use std::collections::{HashMap, HashSet};
     use std::fs;
     use std::io;
+    use std::io::Seek;
     use std::path::Path;
-    use std::sync::Arc;
+    use std::sync::{Arc, LazyLock};
     use test_log::test;
+    use yazi::{compress, CompressionLevel, Format};
+
+    /// golden files folders, empty when test data is not available
+    static BASE_PATH_MDF4: LazyLock<String> = LazyLock::new(|| golden_folder(ASAM_EXAMPLES));
+    static BASE_PATH_MDF3: LazyLock<String> = LazyLock::new(|| golden_folder(MDF3_EXAMPLES));
+    /// folder of the files written by tests
+    static BASE_TEST_PATH: LazyLock<String> = LazyLock::new(|| {
+        let path = std::env::temp_dir().join("mdfr_test_files");
+        fs::create_dir_all(&path).expect("failed creating tests output folder");
+        path.to_string_lossy().into_owned()
+    });
+    static WRITING_MDF_FILE: LazyLock<String> =
+        LazyLock::new(|| format!("{}/test.mf4", *BASE_TEST_PATH));
+    static WRITING_PARQUET_FILE: LazyLock<String> =
+        LazyLock::new(|| format!("{}/test_parquet", *BASE_TEST_PATH));
+    static WRITING_HDF5_FILE: LazyLock<String> =
+        LazyLock::new(|| format!("{}/test_hdf5.hdf5", *BASE_TEST_PATH));
+
+    fn golden_folder(folder: &str) -> String {
+        test_data_dir()
+            .map(|dir| format!("{}/", dir.join(folder).display()))
+            .unwrap_or_default()
+    }
+
+    /// file of a test in the temporary folder, unique to the process, removed when dropped
+    struct TempFile(String);
+
+    impl TempFile {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!("mdfr_{}_{name}", std::process::id()));
+            TempFile(path.to_string_lossy().into_owned())
+        }
+        /// writes the synthetic file
+        fn synthetic(name: &str, file: &crate::test_utils::SyntheticMdf) -> Result<Self> {
+            let temp = TempFile::new(name);
+            file.write(temp.path())?;
+            Ok(temp)
+        }
+        fn path(&self) -> &str {
+            &self.0
+        }
+    }
 
-    static BASE_PATH_MDF4: &str = "/home/ratal/workspace/mdfreader/mdfreader/tests/MDF4/ASAM_COMMON_MDF_V4-1-0/Base_Standard/Examples/";
-    static BASE_PATH_MDF3: &str = "/home/ratal/workspace/mdfreader/mdfreader/tests/mdf3/";
-    static BASE_TEST_PATH: &str = "/home/ratal/workspace/mdfr/test_files";
-    static WRITING_MDF_FILE: &str = "/home/ratal/workspace/mdfr/test_files/test.mf4";
-    static WRITING_PARQUET_FILE: &str = "/home/ratal/workspace/mdfr/test_files/test_parquet";
-    static WRITING_HDF5_FILE: &str = "/home/ratal/workspace/mdfr/test_files/test_hdf5.hdf5";
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.0);
+        }
+    }
+
+    /// skips the test when golden files are not available
+    macro_rules! require_test_data {
+        () => {
+            if test_data_dir().is_none() {
+                println!("skipped, golden files not available, see test_utils::golden");
+                return Ok(());
+            }
+        };
+    }
 
     #[test]
     fn info_test() -> Result<()> {
@@ -93,6 +167,7 @@ mod tests {
 
     #[test]
     fn parse_all_folders4() -> io::Result<()> {
+        require_test_data!();
         let list_of_paths = [
             "Simple".to_string(),
             "ChannelInfo".to_string(),
@@ -130,15 +205,15 @@ mod tests {
         ];
         for path in list_of_paths.iter() {
             println!("reading folder : {}", path);
-            parse_info_folder(&format!("{}{}", BASE_PATH_MDF4, &path)).unwrap();
+            parse_info_folder(&format!("{}{}", *BASE_PATH_MDF4, &path)).unwrap();
         }
         Ok(())
     }
 
     #[test]
     fn parse_all_folders3() -> io::Result<()> {
-        let base_path = String::from("/home/ratal/workspace/mdfreader/mdfreader/tests/mdf3/");
-        parse_info_folder(&base_path).unwrap();
+        require_test_data!();
+        parse_info_folder(&BASE_PATH_MDF3).unwrap();
         Ok(())
     }
 
@@ -152,1076 +227,6140 @@ mod tests {
     }
 
     #[test]
-    fn data_types() -> Result<()> {
-        let list_of_paths = [
-            "DataTypes/ByteArray/".to_string(),
-            "DataTypes/CANopenTypes/".to_string(),
-            "DataTypes/IntegerTypes/".to_string(),
-            "DataTypes/RealTypes/".to_string(),
-            "DataTypes/StringTypes/".to_string(),
+    fn reading_from_bytes() -> Result<()> {
+        for file in ["test_files/test_basic.mf4", "test_files/test_mdf3.mdf"] {
+            let mut mdf = Mdf::new(file)?;
+            mdf.load_all_channels_data_in_memory()?;
+            let bytes = fs::read(file)?;
+            let mut from_bytes = Mdf::from_bytes(&bytes, file)?;
+            let channel_names = from_bytes.get_channel_names_set();
+            assert_eq!(channel_names, mdf.get_channel_names_set());
+            from_bytes.load_channels_data_from_bytes(&bytes, channel_names.clone())?;
+            for name in channel_names {
+                assert_eq!(
+                    from_bytes.get_channel_data(&name).map(|d| d.as_ref()),
+                    mdf.get_channel_data(&name).map(|d| d.as_ref())
+                );
+            }
+        }
+        assert!(Mdf::from_bytes(&[0x55u8; 128], "not a mdf").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn synthetic_files() -> Result<()> {
+        use crate::test_utils::{DataLayout, SyntheticMdf};
+        let layouts = [
+            DataLayout::Dt,
+            DataLayout::Dl {
+                records_per_block: 7,
+            },
+            DataLayout::Dz,
+            DataLayout::Hl {
+                records_per_block: 13,
+            },
         ];
-        let writing_mdf_file = format!("{}{}", WRITING_MDF_FILE, "_test".to_owned()).to_owned();
+        let mut files: Vec<SyntheticMdf> = layouts
+            .into_iter()
+            .map(|layout| SyntheticMdf::mdf4().with_layout(layout))
+            .collect();
+        files.push(SyntheticMdf::mdf3());
+        files.push(SyntheticMdf::mdf4().with_linear_conversion(-3.5, 0.125));
+        for file in files {
+            for sorted in [true, false] {
+                let mut file = file
+                    .clone()
+                    .with_group(&[DataType::UInt8, DataType::Int16, DataType::Float32])
+                    .with_group(&[
+                        DataType::UInt64,
+                        DataType::Int32,
+                        DataType::Float64,
+                        DataType::Int8,
+                        DataType::UInt16,
+                    ])
+                    .with_records(250);
+                if !sorted {
+                    file = file.unsorted();
+                }
+                let bytes = file.to_bytes()?;
+                let mut mdf = Mdf::from_bytes(&bytes, "synthetic.mf4")?;
+                mdf.load_channels_data_from_bytes(&bytes, mdf.get_channel_names_set())?;
+                for name in file.channel_names() {
+                    let data = mdf
+                        .get_channel_data(&name)
+                        .with_context(|| format!("{name} not found in {file:?}"))?;
+                    let expected = file.expected_data(&name).context("no expected data")?;
+                    assert_eq!(&data.as_ref(), &expected, "{name} of {file:?}");
+                }
+                assert_eq!(
+                    mdf.get_channel_master(&SyntheticMdf::channel_name(1, 3)),
+                    Some(SyntheticMdf::master_name(1))
+                );
+            }
+        }
+        assert!(SyntheticMdf::mdf3()
+            .with_group(&[DataType::UInt8])
+            .with_layout(DataLayout::Dz)
+            .to_bytes()
+            .is_err());
+        // mdf3 unsorted records split between read chunks, with record id repeated at end or not
+        let file = SyntheticMdf::mdf3()
+            .with_group(&[DataType::UInt16, DataType::Float32])
+            .with_group(&[DataType::Int64])
+            .with_records(3000)
+            .unsorted();
+        let file_name = std::env::temp_dir().join("test_synthetic_unsorted.dat");
+        let file_name = file_name.to_str().context("invalid temp path")?;
+        for file in [file.clone(), file.with_trailing_record_id()] {
+            file.write(file_name)?;
+            let mut mdf = Mdf::new(file_name)?;
+            let options = ReadOptions {
+                chunk_size: 1000,
+                ..Default::default()
+            };
+            mdf.load_channels_data_in_memory_with_options(mdf.get_channel_names_set(), &options)?;
+            for name in file.channel_names() {
+                let data = mdf.get_channel_data(&name).context("channel not found")?;
+                let expected = file.expected_data(&name).context("no expected data")?;
+                assert_eq!(&data.as_ref(), &expected, "{name} of {file:?}");
+            }
+        }
+        fs::remove_file(file_name)?;
+        Ok(())
+    }
 
-        // Integer testing
-        let file_name = format!(
-            "{}{}{}",
-            BASE_PATH_MDF4, list_of_paths[2], "Vector_IntegerTypes.MF4"
-        );
-        let mut mdf = Mdf::new(&file_name)?;
-        mdf.load_all_channels_data_in_memory()?;
-        let mut vect: Vec<i64> = vec![100; 201];
-        let mut counter: i64 = 0;
-        vect.iter_mut().for_each(|v| {
-            *v -= counter;
-            counter += 1
-        });
-        if let Some(data) = mdf.get_channel_data(&"Counter_INT64_BE".to_string()) {
-            assert_eq!(
-                ChannelData::Int64(Int64Builder::new_from_buffer(vect.clone().into(), None)),
-                data.clone()
-            );
+    #[test]
+    fn mdf3_unsorted_chunk_split() -> Result<()> {
+        use crate::test_utils::SyntheticMdf;
+        let file = SyntheticMdf::mdf3()
+            .with_group(&[DataType::UInt16, DataType::Float32])
+            .with_group(&[DataType::Int64])
+            .with_records(500)
+            .unsorted();
+        let file_name = std::env::temp_dir().join("mdfr_mdf3_unsorted_chunk_split.dat");
+        let file_name = file_name.to_str().context("invalid temp path")?;
+        for file in [file.clone(), file.with_trailing_record_id()] {
+            file.write(file_name)?;
+            // chunks smaller than a record, splitting record ids and values at every offset
+            for chunk_size in [1, 7, 16, 4093] {
+                let mut mdf = Mdf::new(file_name)?;
+                let options = ReadOptions {
+                    chunk_size,
+                    ..Default::default()
+                };
+                mdf.load_channels_data_in_memory_with_options(
+                    mdf.get_channel_names_set(),
+                    &options,
+                )?;
+                for name in file.channel_names() {
+                    let data = mdf.get_channel_data(&name).context("channel not found")?;
+                    let expected = file.expected_data(&name).context("no expected data")?;
+                    assert_eq!(
+                        &data.as_ref(),
+                        &expected,
+                        "{name} with chunk size {chunk_size} of {file:?}"
+                    );
+                }
+            }
         }
-        let mut mdf2 = mdf.write(&writing_mdf_file, false)?;
-        mdf2.load_all_channels_data_in_memory()?;
-        if let Some(data) = mdf2.get_channel_data(&"Counter_INT64_LE".to_string()) {
-            assert_eq!(
-                ChannelData::Int64(Int64Builder::new_from_buffer(vect.into(), None)),
-                data.clone()
-            );
+        fs::remove_file(file_name)?;
+        Ok(())
+    }
+
+    #[test]
+    fn vlsd_signal_data_blocks() -> Result<()> {
+        use crate::test_utils::{DataLayout, SyntheticMdf};
+        let file = SyntheticMdf::mdf4()
+            .with_group(&[DataType::LargeUtf8, DataType::UInt16, DataType::LargeBinary])
+            .with_records(150);
+        // SD block, SD in DZ, SD blocks in DL and DZ blocks in DL, then values and their
+        // length split between blocks of 3 and 10 bytes
+        let layouts = [
+            DataLayout::Dt,
+            DataLayout::Dz,
+            DataLayout::Dl {
+                records_per_block: 16,
+            },
+            DataLayout::Hl {
+                records_per_block: 16,
+            },
+        ];
+        for layout in layouts {
+            for block_size in [None, Some(3), Some(10)] {
+                let mut file = file.clone().with_layout(layout);
+                if let Some(block_size) = block_size {
+                    file = file.with_signal_data_block_size(block_size);
+                }
+                let bytes = file.to_bytes()?;
+                let mut mdf = Mdf::from_bytes(&bytes, "synthetic_vlsd.mf4")?;
+                mdf.load_channels_data_from_bytes(&bytes, mdf.get_channel_names_set())?;
+                for name in file.channel_names() {
+                    let data = mdf
+                        .get_channel_data(&name)
+                        .with_context(|| format!("{name} not found in {file:?}"))?;
+                    let expected = file.expected_data(&name).context("no expected data")?;
+                    assert_eq!(&data.as_ref(), &expected, "{name} of {file:?}");
+                }
+            }
         }
-        let mut vect: Vec<i32> = vec![100; 201];
-        let mut counter: i32 = 0;
-        vect.iter_mut().for_each(|v| {
-            *v -= counter;
-            counter += 1
-        });
-        // error in file, cn data type is 1 but should be 3
-        // if let Some(data) = mdf.get_channel_data(&"Counter_INT32_BE".to_string()) {
-        //     assert_eq!(
-        //         PrimitiveArray::new(DataType::Int32, Buffer::from(vect.clone()), None).boxed(),
-        //         data
-        //     );
-        // }
-        if let Some(data) = mdf2.get_channel_data(&"Counter_INT32_LE".to_string()) {
+        Ok(())
+    }
+
+    #[test]
+    fn maximum_length_data_channels() -> Result<()> {
+        use crate::test_utils::SyntheticMdf;
+        // values of varying lengths padded to the longest one, truncated by their size channel
+        let file = SyntheticMdf::mdf4()
+            .with_group(&[DataType::UInt8, DataType::LargeUtf8, DataType::LargeBinary])
+            .with_group(&[DataType::LargeUtf8])
+            .with_maximum_length()
+            .with_records(60);
+        for file in [file.clone(), file.unsorted()] {
+            let bytes = file.to_bytes()?;
+            let mut mdf = Mdf::from_bytes(&bytes, "synthetic_mlsd.mf4")?;
+            mdf.load_channels_data_from_bytes(&bytes, mdf.get_channel_names_set())?;
+            for name in file.channel_names() {
+                let data = mdf
+                    .get_channel_data(&name)
+                    .with_context(|| format!("{name} not found in {file:?}"))?;
+                let expected = file.expected_data(&name).context("no expected data")?;
+                assert_eq!(&data.as_ref(), &expected, "{name} of {file:?}");
+            }
+            let texts: Vec<Option<&str>> = mdf
+                .get_channel_str_iter(&SyntheticMdf::channel_name(0, 1))
+                .context("no text channel")?
+                .collect();
+            assert_eq!(texts[0], Some(""));
+            assert_eq!(texts[1], Some("aé"));
+            assert_eq!(texts[6], Some("aéaéaéaéaéaé"));
             assert_eq!(
-                ChannelData::Int32(Int32Builder::new_from_buffer(vect.into(), None)),
-                data.clone()
+                mdf.get_channel_data(&SyntheticMdf::channel_name(0, 2))
+                    .map(|data| data.as_ref().as_binary::<i64>().value(3).to_vec()),
+                Some(vec![3, 4, 5, 6, 7, 8])
             );
         }
-        let mut vect: Vec<i16> = vec![100; 201];
-        let mut counter: i16 = 0;
-        vect.iter_mut().for_each(|v| {
-            *v -= counter;
-            counter += 1
-        });
-        if let Some(data) = mdf.get_channel_data(&"Counter_INT16_BE".to_string()) {
-            assert_eq!(
-                ChannelData::Int16(Int16Builder::new_from_buffer(vect.clone().into(), None)),
-                data.clone()
-            );
+        Ok(())
+    }
+
+    #[test]
+    fn contiguous_values_fast_path() -> Result<()> {
+        use crate::mdfreader::data_read4::copy_le_values;
+        use crate::test_utils::{DataLayout, SyntheticMdf};
+        // masters alone in their records are copied in one memcpy, the same masters next to
+        // channels of odd sizes are read record by record
+        let master = SyntheticMdf::master_name(0);
+        for layout in [
+            DataLayout::Dt,
+            DataLayout::Dl {
+                records_per_block: 7,
+            },
+            DataLayout::Dz,
+            DataLayout::Hl {
+                records_per_block: 7,
+            },
+        ] {
+            let mut arrays = Vec::new();
+            for data_types in [&[][..], &[DataType::UInt8, DataType::Int16][..]] {
+                let file = SyntheticMdf::mdf4()
+                    .with_group(data_types)
+                    .with_records(101)
+                    .with_layout(layout);
+                let bytes = file.to_bytes()?;
+                let mut mdf = Mdf::from_bytes(&bytes, "contiguous.mf4")?;
+                mdf.load_channels_data_from_bytes(&bytes, mdf.get_channel_names_set())?;
+                let data = mdf.get_channel_data(&master).context("master not found")?;
+                let expected = file.expected_data(&master).context("no expected data")?;
+                assert_eq!(&data.as_ref(), &expected, "{master} of {file:?}");
+                arrays.push(data.as_ref());
+            }
+            assert_eq!(&arrays[0], &arrays[1], "{layout:?}");
         }
-        if let Some(data) = mdf2.get_channel_data(&"Counter_INT16_LE".to_string()) {
-            assert_eq!(
-                ChannelData::Int16(Int16Builder::new_from_buffer(vect.into(), None)),
-                data.clone()
-            );
+        // sources at any alignment, odd number of values followed by trailing bytes
+        macro_rules! assert_copied {
+            ($($t:ty),*) => {$(
+                let size = std::mem::size_of::<$t>();
+                for shift in 0..size {
+                    let mut bytes = vec![0xA5u8; shift];
+                    bytes.extend((0..27i64).flat_map(|value| ((value * 37 - 500) as $t).to_le_bytes()));
+                    bytes.extend([1, 2, 3]);
+                    let source = &bytes[shift..];
+                    let mut fast = vec![0 as $t; 27];
+                    copy_le_values(source, &mut fast)?;
+                    let scalar = source
+                        .chunks_exact(size)
+                        .take(27)
+                        .map(|chunk| Ok(<$t>::from_le_bytes(chunk.try_into()?)))
+                        .collect::<Result<Vec<$t>>>()?;
+                    assert_eq!(fast, scalar, "{} shifted by {shift}", stringify!($t));
+                    assert!(copy_le_values(&source[..size * 27 - 1], &mut fast).is_err());
+                }
+            )*};
         }
-        // Real types
-        let file_name = format!(
-            "{}{}{}",
-            BASE_PATH_MDF4, list_of_paths[3], "Vector_RealTypes.MF4"
-        );
-        let mut mdf = Mdf::new(&file_name)?;
-        mdf.load_all_channels_data_in_memory()?;
-        let file_name = format!(
-            "{}{}{}",
-            BASE_PATH_MDF4, list_of_paths[3], "halffloat_sinus.mf4"
+        assert_copied!(i16, u16, i32, u32, f32, i64, u64, f64);
+        Ok(())
+    }
+
+    #[test]
+    fn strided_values_gather() -> Result<()> {
+        use crate::test_utils::{DataLayout, SyntheticMdf};
+        // records of 47 bytes, channels at unaligned byte positions, UInt16, UInt32 and UInt64
+        // channels patched into bit fields of the same byte size at (bit offset, bit count)
+        let data_types = [
+            DataType::UInt8,
+            DataType::UInt16,
+            DataType::Int32,
+            DataType::UInt32,
+            DataType::Float32,
+            DataType::Int64,
+            DataType::UInt64,
+            DataType::Float64,
+        ];
+        let fields = [(1usize, (3u8, 13u32)), (3, (5, 27)), (6, (1, 63))];
+        for layout in [
+            DataLayout::Dt,
+            DataLayout::Dl {
+                records_per_block: 5,
+            },
+        ] {
+            let file = SyntheticMdf::mdf4()
+                .with_group(&data_types)
+                .with_records(37)
+                .with_layout(layout);
+            let mut bytes = file.to_bytes()?;
+            for (index, (bit_offset, bit_count)) in fields {
+                let cn = bytes
+                    .windows(4)
+                    .enumerate()
+                    .filter(|(_, id)| id == b"##CN")
+                    .nth(index + 1)
+                    .context("CN block not found")?
+                    .0;
+                bytes[cn + 91] = bit_offset;
+                bytes[cn + 96..cn + 100].copy_from_slice(&bit_count.to_le_bytes());
+            }
+            let mut mdf = Mdf::from_bytes(&bytes, "strided.mf4")?;
+            mdf.load_channels_data_from_bytes(&bytes, mdf.get_channel_names_set())?;
+            for name in file.channel_names() {
+                let data = mdf
+                    .get_channel_data(&name)
+                    .with_context(|| format!("channel {name} not found"))?
+                    .as_ref();
+                let expected = file.expected_data(&name).context("no expected data")?;
+                match fields
+                    .iter()
+                    .find(|(index, _)| name == SyntheticMdf::channel_name(0, *index))
+                {
+                    Some((_, (bit_offset, bit_count))) => {
+                        let expected: Vec<u64> = cast(&expected, &DataType::UInt64)?
+                            .as_primitive::<UInt64Type>()
+                            .values()
+                            .iter()
+                            .map(|value| (value >> bit_offset) & (u64::MAX >> (64 - bit_count)))
+                            .collect();
+                        assert_eq!(
+                            cast(&data, &DataType::UInt64)?
+                                .as_primitive::<UInt64Type>()
+                                .values()
+                                .to_vec(),
+                            expected,
+                            "{name} of {file:?}"
+                        );
+                    }
+                    None => assert_eq!(&data, &expected, "{name} of {file:?}"),
+                }
+            }
+        }
+        // same values gathered by unrolled blocks of records and read record by record, for
+        // odd record lengths, all byte positions and numbers of records around the block size
+        #[cfg(feature = "simd")]
+        {
+            use crate::mdfreader::data_read4::gather_values;
+            macro_rules! assert_gathered {
+                ($($t:ty),*) => {$(
+                    let size = std::mem::size_of::<$t>();
+                    for record_length in [size, size + 1, 2 * size + 3, 23, 47] {
+                        for pos_byte_beg in 0..=record_length - size {
+                            for big_endian in [false, true] {
+                                for n_records in [0usize, 1, 7, 8, 9, 37] {
+                                    let mut chunk: Vec<u8> = (0..record_length * n_records)
+                                        .map(|byte| (byte * 37 + 11) as u8)
+                                        .collect();
+                                    for (record, bytes) in
+                                        chunk.chunks_exact_mut(record_length).enumerate()
+                                    {
+                                        let value = (record as i64 * 37 - 500) as $t;
+                                        bytes[pos_byte_beg..pos_byte_beg + size].copy_from_slice(
+                                            &if big_endian {
+                                                value.to_be_bytes()
+                                            } else {
+                                                value.to_le_bytes()
+                                            },
+                                        );
+                                    }
+                                    let mut gathered = vec![0 as $t; n_records];
+                                    gather_values(
+                                        &chunk,
+                                        record_length,
+                                        pos_byte_beg,
+                                        big_endian,
+                                        &mut gathered,
+                                    )?;
+                                    let scalar = chunk
+                                        .chunks(record_length)
+                                        .map(|record| {
+                                            let value =
+                                                record[pos_byte_beg..pos_byte_beg + size].try_into()?;
+                                            Ok(if big_endian {
+                                                <$t>::from_be_bytes(value)
+                                            } else {
+                                                <$t>::from_le_bytes(value)
+                                            })
+                                        })
+                                        .collect::<Result<Vec<$t>>>()?;
+                                    assert_eq!(
+                                        gathered,
+                                        scalar,
+                                        "{} at byte {pos_byte_beg} of {n_records} records of \
+                                         {record_length} bytes, big endian {big_endian}",
+                                        stringify!($t)
+                                    );
+                                }
+                            }
+                        }
+                    }
+                )*};
+            }
+            assert_gathered!(i16, u16, i32, u32, f32, i64, u64, f64);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn mdf3_display_name() -> Result<()> {
+        use crate::test_utils::SyntheticMdf;
+        let file = SyntheticMdf::mdf3()
+            .with_group(&[DataType::UInt16, DataType::Float64])
+            .with_records(10);
+        let mut bytes = file.to_bytes()?;
+        let name = SyntheticMdf::channel_name(0, 0);
+        let short_name = format!("{name}\0");
+        let cn = bytes
+            .windows(short_name.len())
+            .position(|window| window == short_name.as_bytes())
+            .context("CN block not found")?
+            - 26;
+        // TX block appended at the end of file, linked as display name
+        let tx_position = bytes.len() as u32;
+        let display_name = b"Engine speed\0";
+        bytes.extend_from_slice(b"TX");
+        bytes.extend_from_slice(&(4 + display_name.len() as u16).to_le_bytes());
+        bytes.extend_from_slice(display_name);
+        bytes[cn + 222..cn + 226].copy_from_slice(&tx_position.to_le_bytes());
+        let mdf = Mdf::from_bytes(&bytes, "display_name.dat")?;
+        assert_eq!(
+            mdf.get_channel_display_name(&name)?,
+            Some("Engine speed".to_string())
         );
-        let mut mdf = Mdf::new(&file_name)?;
-        mdf.load_all_channels_data_in_memory()?;
-        let file_name = format!(
-            "{}{}{}",
-            BASE_PATH_MDF4, list_of_paths[3], "dSPACE_RealTypes.mf4"
+        assert_eq!(
+            mdf.get_channel_display_name(&SyntheticMdf::channel_name(0, 1))?,
+            None
         );
-        let mut mdf = Mdf::new(&file_name)?;
-        mdf.load_all_channels_data_in_memory()?;
+        assert_eq!(mdf.get_channel_display_name("unknown")?, None);
+        Ok(())
+    }
 
-        // StringTypes testing
-        // UTF8
-        let mut expected_string_result = LargeStringBuilder::with_capacity(10, 6);
-        expected_string_result.append_value("zero");
-        expected_string_result.append_value("one");
-        expected_string_result.append_value("two");
-        expected_string_result.append_value("three");
-        expected_string_result.append_value("four");
-        expected_string_result.append_value("five");
-        expected_string_result.append_value("six");
-        expected_string_result.append_value("seven");
-        expected_string_result.append_value("eight");
-        expected_string_result.append_value("nine");
-        let expected_string_result = ChannelData::Utf8(expected_string_result);
-        let file_name = format!(
-            "{}{}{}",
-            BASE_PATH_MDF4, list_of_paths[4], "Vector_FixedLengthStringUTF8.mf4"
+    #[test]
+    fn duplicate_policy() -> Result<()> {
+        use crate::mdfinfo::{DuplicatePolicy, ParseOptions};
+        use crate::test_utils::SyntheticMdf;
+        for file in [SyntheticMdf::mdf4(), SyntheticMdf::mdf3()] {
+            let file = file
+                .with_group(&[DataType::UInt16, DataType::Float64])
+                .with_group(&[DataType::UInt16, DataType::Float64])
+                .with_records(10);
+            let mut bytes = file.to_bytes()?;
+            // second group master and first channel named as the first group ones
+            for (name, duplicate) in [
+                (SyntheticMdf::master_name(1), SyntheticMdf::master_name(0)),
+                (
+                    SyntheticMdf::channel_name(1, 0),
+                    SyntheticMdf::channel_name(0, 0),
+                ),
+            ] {
+                let name = format!("{name}\0");
+                let position = bytes
+                    .windows(name.len())
+                    .position(|window| window == name.as_bytes())
+                    .context("channel name not found")?;
+                bytes[position..position + duplicate.len()].copy_from_slice(duplicate.as_bytes());
+            }
+            let parse = |policy: DuplicatePolicy| {
+                let options = ParseOptions {
+                    duplicate_policy: policy,
+                    ..Default::default()
+                };
+                Mdf::from_reader_with_options(io::Cursor::new(&bytes), "duplicates.mf4", &options)
+            };
+            assert!(parse(DuplicatePolicy::Error).is_err());
+            for (policy, reason) in [
+                (DuplicatePolicy::SuffixSource, "suffixed by block position"),
+                (DuplicatePolicy::SuffixGroup, "suffixed by channel group"),
+            ] {
+                let mdf = parse(policy)?;
+                assert_eq!(mdf.get_channel_names_set().len(), 6);
+                let duplicates = mdf.get_duplicate_channels();
+                assert_eq!(duplicates.len(), 2, "{policy:?}");
+                for duplicate in duplicates {
+                    let unique_name = duplicate.unique_name.as_ref().context("no unique name")?;
+                    assert!(unique_name.starts_with(&format!("{} ", duplicate.name)));
+                    assert!(mdf.get_channel_names_set().contains(unique_name));
+                    assert_eq!(duplicate.reason, reason);
+                }
+            }
+            // masters are kept renamed, other duplicates dropped
+            let mut mdf = parse(DuplicatePolicy::KeepFirst)?;
+            assert_eq!(mdf.get_channel_names_set().len(), 5);
+            let duplicates = mdf.get_duplicate_channels().to_vec();
+            assert_eq!(duplicates.len(), 2);
+            for duplicate in duplicates.iter() {
+                assert_eq!(
+                    duplicate.unique_name.is_none(),
+                    duplicate.name == SyntheticMdf::channel_name(0, 0)
+                );
+            }
+            mdf.load_channels_data_from_bytes(&bytes, mdf.get_channel_names_set())?;
+            let names = mdf.get_channel_names_set();
+            for name in names.iter() {
+                assert_eq!(mdf.get_channel_data(name).map(|data| data.len()), Some(10));
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn channel_tree() -> Result<()> {
+        use crate::test_utils::SyntheticMdf;
+        let file = SyntheticMdf::mdf4()
+            .with_group(&[DataType::UInt16, DataType::Float64])
+            .with_group(&[DataType::UInt16, DataType::Float64])
+            .with_records(10);
+        let mut bytes = file.to_bytes()?;
+        let mdf = Mdf::from_bytes(&bytes, "tree.mf4")?;
+        let MdfInfo::V4(info) = &mdf.mdf_info else {
+            panic!("synthetic file is not mdf4");
+        };
+        // DG, CG and CN positions triplet of each channel
+        let element = |name: String| -> Result<[i64; 3]> {
+            let (_master, dg, (cg, _rec_id), (cn, _rec_pos)) =
+                info.get_channel_id(&name).context("channel not found")?;
+            Ok([*dg, *cg, *cn])
+        };
+        let root_elements = element(SyntheticMdf::channel_name(0, 0))?;
+        let mut child_elements = element(SyntheticMdf::channel_name(1, 0))?.to_vec();
+        child_elements.extend(element(SyntheticMdf::channel_name(1, 1))?);
+        // appends a block at the end of the file, returning its position
+        let mut block = |id: &[u8; 4], links: &[i64], data: &[u8]| -> i64 {
+            let position = bytes.len() as i64;
+            bytes.extend_from_slice(id);
+            bytes.extend_from_slice(&[0u8; 4]);
+            bytes.extend_from_slice(&((24 + 8 * links.len() + data.len()) as u64).to_le_bytes());
+            bytes.extend_from_slice(&(links.len() as u64).to_le_bytes());
+            links
+                .iter()
+                .for_each(|link| bytes.extend_from_slice(&link.to_le_bytes()));
+            bytes.extend_from_slice(data);
+            bytes.resize(bytes.len().next_multiple_of(8), 0);
+            position
+        };
+        let ch_data =
+            |count: u32, ch_type: u8| [&count.to_le_bytes()[..], &[ch_type, 0, 0, 0]].concat();
+        let mut names = [0i64; 3];
+        for (position, name) in names.iter_mut().zip(["engine", "sensors", "spare"]) {
+            *position = block(b"##TX", &[], format!("{name}\0").as_bytes());
+        }
+        let spare = block(b"##CH", &[0, 0, names[2], 0], &ch_data(0, 0));
+        let sensors = [0, 0, names[1], 0]
+            .into_iter()
+            .chain(child_elements)
+            .collect::<Vec<i64>>();
+        let sensors = block(b"##CH", &sensors, &ch_data(2, 2));
+        let engine = [spare, sensors, names[0], 0]
+            .into_iter()
+            .chain(root_elements)
+            .collect::<Vec<i64>>();
+        let engine = block(b"##CH", &engine, &ch_data(1, 0));
+        // header hd_ch_first link
+        bytes[104..112].copy_from_slice(&engine.to_le_bytes());
+
+        let mdf = Mdf::from_bytes(&bytes, "tree.mf4")?;
+        let tree = mdf.get_channel_tree()?;
+        assert_eq!(tree.len(), 2);
+        assert_eq!(tree[0].name.as_deref(), Some("engine"));
+        assert_eq!(tree[0].channels, vec![SyntheticMdf::channel_name(0, 0)]);
+        assert_eq!(tree[0].children.len(), 1);
+        assert_eq!(tree[0].children[0].hierarchy_type, 2);
+        assert_eq!(tree[1].name.as_deref(), Some("spare"));
+        assert!(tree[1].channels.is_empty() && tree[1].children.is_empty());
+        let sensors: HashSet<String> = (0..2).map(|i| SyntheticMdf::channel_name(1, i)).collect();
+        assert_eq!(
+            mdf.get_hierarchy_channels(&["engine", "sensors"])?,
+            Some(sensors.clone())
         );
-        let mut mdf = Mdf::new(&file_name)?;
-        mdf.load_all_channels_data_in_memory()?;
-        if let Some(data) = mdf.get_channel_data(&"Time channel".to_string()) {
+        let mut engine = sensors;
+        engine.insert(SyntheticMdf::channel_name(0, 0));
+        assert_eq!(mdf.get_hierarchy_channels(&["engine"])?, Some(engine));
+        assert_eq!(mdf.get_hierarchy_channels(&["sensors"])?, None);
+        assert!(Mdf::from_bytes(
+            &SyntheticMdf::mdf3()
+                .with_group(&[DataType::UInt8])
+                .to_bytes()?,
+            "tree.dat"
+        )?
+        .get_channel_tree()?
+        .is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn mdf3_channel_dependency() -> Result<()> {
+        use crate::data_holder::tensor_arrow::Order;
+        use crate::test_utils::SyntheticMdf;
+        for sorted in [true, false] {
+            let mut file = SyntheticMdf::mdf3()
+                .with_group(&[DataType::UInt8])
+                .with_group(&vec![DataType::Int16; 6])
+                .with_dependency(1, &[2, 3])
+                .with_records(50);
+            if !sorted {
+                file = file.unsorted();
+            }
+            let bytes = file.to_bytes()?;
+            let mut mdf = Mdf::from_bytes(&bytes, "synthetic.dat")?;
+            let name = SyntheticMdf::dependency_name(1);
+            let dependencies: Vec<String> =
+                (0..6).map(|i| SyntheticMdf::channel_name(1, i)).collect();
+            assert_eq!(mdf.get_channel_dependencies(&name), dependencies);
+            assert!(mdf
+                .get_channel_dependencies(&SyntheticMdf::channel_name(1, 0))
+                .is_empty());
+            mdf.load_channels_data_from_bytes(&bytes, HashSet::from([name.clone()]))?;
+            let data = mdf
+                .get_channel_data(&name)
+                .context("composed channel not found")?;
+            assert_eq!(data.shape(), (vec![50, 2, 3], Order::RowMajor));
+            let columns = dependencies
+                .iter()
+                .map(|dependency| file.expected_data(dependency).context("no expected data"))
+                .collect::<Result<Vec<_>>>()?;
+            let expected: Vec<i16> = (0..50)
+                .flat_map(|record| {
+                    columns
+                        .iter()
+                        .map(move |column| column.as_primitive::<Int16Type>().value(record))
+                })
+                .collect();
+            let ChannelData::ArrayDInt16(tensor) = data else {
+                panic!("composed channel is not an Int16 array");
+            };
+            assert_eq!(tensor.values_slice(), expected.as_slice());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn bit_fields_crossing_bytes() -> Result<()> {
+        use crate::test_utils::SyntheticMdf;
+        // (bit offset, bit count) of the fields patched into the UInt32 and UInt64 channels
+        let fields = [(5u32, 12u32), (3, 50)];
+        // mdf4 and mdf3 data types of unsigned and signed, little and big endian integers
+        let data_types = [
+            ((0u8, 0u16), false, false),
+            ((1, 9), false, true),
+            ((2, 1), true, false),
+            ((3, 10), true, true),
+        ];
+        for file in [SyntheticMdf::mdf4(), SyntheticMdf::mdf3()] {
+            let file = file
+                .with_group(&[DataType::UInt32, DataType::UInt64])
+                .with_records(64);
+            for ((data_type4, data_type3), signed, big_endian) in data_types {
+                let mut bytes = file.to_bytes()?;
+                for (index, (bit_offset, bit_count)) in fields.iter().enumerate() {
+                    if bytes.starts_with(b"MDF     4") {
+                        // CN blocks of the master and of the channels, in order
+                        let cn = bytes
+                            .windows(4)
+                            .enumerate()
+                            .filter(|(_, id)| id == b"##CN")
+                            .nth(index + 1)
+                            .context("CN block not found")?
+                            .0;
+                        bytes[cn + 90] = data_type4;
+                        bytes[cn + 91] = *bit_offset as u8;
+                        bytes[cn + 96..cn + 100].copy_from_slice(&bit_count.to_le_bytes());
+                    } else {
+                        let short_name = format!("{}\0", SyntheticMdf::channel_name(0, index));
+                        let cn = bytes
+                            .windows(short_name.len())
+                            .position(|name| name == short_name.as_bytes())
+                            .context("CN block not found")?
+                            - 26;
+                        let cn_bit_offset = u16::from_le_bytes([bytes[cn + 186], bytes[cn + 187]]);
+                        bytes[cn + 186..cn + 188]
+                            .copy_from_slice(&(cn_bit_offset + *bit_offset as u16).to_le_bytes());
+                        bytes[cn + 188..cn + 190]
+                            .copy_from_slice(&(*bit_count as u16).to_le_bytes());
+                        bytes[cn + 190..cn + 192].copy_from_slice(&data_type3.to_le_bytes());
+                    }
+                }
+                let mut mdf = Mdf::from_bytes(&bytes, "bit_fields.mf4")?;
+                mdf.load_channels_data_from_bytes(&bytes, mdf.get_channel_names_set())?;
+                for (index, (bit_offset, bit_count)) in fields.iter().enumerate() {
+                    let name = SyntheticMdf::channel_name(0, index);
+                    let written = cast(
+                        &file.expected_data(&name).context("no expected data")?,
+                        &DataType::UInt64,
+                    )?;
+                    let size = 4 * (index + 1);
+                    let n_bytes = (bit_offset + bit_count).div_ceil(8) as usize;
+                    let expected: Vec<i64> = written
+                        .as_primitive::<UInt64Type>()
+                        .values()
+                        .iter()
+                        .map(|value| {
+                            let field_bytes = &value.to_le_bytes()[..size][..n_bytes];
+                            let raw = if big_endian {
+                                field_bytes.iter().fold(0u64, |raw, b| raw << 8 | *b as u64)
+                            } else {
+                                field_bytes
+                                    .iter()
+                                    .rev()
+                                    .fold(0u64, |raw, b| raw << 8 | *b as u64)
+                            };
+                            let field = raw << (64 - bit_offset - bit_count) >> (64 - bit_count);
+                            if signed {
+                                ((field << (64 - bit_count)) as i64) >> (64 - bit_count)
+                            } else {
+                                field as i64
+                            }
+                        })
+                        .collect();
+                    let data = mdf.get_channel_data(&name).context("channel not found")?;
+                    let data = cast(&data.as_ref(), &DataType::Int64)?;
+                    assert_eq!(
+                        data.as_primitive::<Int64Type>().values().to_vec(),
+                        expected,
+                        "{name} of {file:?}, signed {signed}, big endian {big_endian}"
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn single_bit_channels_as_boolean() -> Result<()> {
+        use crate::test_utils::SyntheticMdf;
+        let bit_offset = 3u8;
+        for file in [SyntheticMdf::mdf4(), SyntheticMdf::mdf3()] {
+            let file = file.with_group(&[DataType::UInt8]).with_records(40);
+            let name = SyntheticMdf::channel_name(0, 0);
+            let mut bytes = file.to_bytes()?;
+            if bytes.starts_with(b"MDF     4") {
+                let cn = bytes
+                    .windows(4)
+                    .enumerate()
+                    .filter(|(_, id)| id == b"##CN")
+                    .nth(1)
+                    .context("CN block not found")?
+                    .0;
+                bytes[cn + 91] = bit_offset;
+                bytes[cn + 96..cn + 100].copy_from_slice(&1u32.to_le_bytes());
+            } else {
+                let short_name = format!("{name}\0");
+                let cn = bytes
+                    .windows(short_name.len())
+                    .position(|window| window == short_name.as_bytes())
+                    .context("CN block not found")?
+                    - 26;
+                let cn_bit_offset = u16::from_le_bytes([bytes[cn + 186], bytes[cn + 187]]);
+                bytes[cn + 186..cn + 188]
+                    .copy_from_slice(&(cn_bit_offset + bit_offset as u16).to_le_bytes());
+                bytes[cn + 188..cn + 190].copy_from_slice(&1u16.to_le_bytes());
+            }
+            let written = cast(
+                &file.expected_data(&name).context("no expected data")?,
+                &DataType::UInt8,
+            )?;
+            let expected: BooleanArray = written
+                .as_primitive::<UInt8Type>()
+                .values()
+                .iter()
+                .map(|value| Some(value >> bit_offset & 1 == 1))
+                .collect();
+            let mut mdf = Mdf::from_bytes(&bytes, "single_bit.mf4")?;
+            mdf.load_channels_data_from_bytes(&bytes, mdf.get_channel_names_set())?;
+            let data = mdf.get_channel_data(&name).context("channel not found")?;
+            assert_eq!(data, &ChannelData::Boolean(expected.clone()), "{file:?}");
+            assert_eq!(data.as_ref().data_type(), &DataType::Boolean);
+            // reloading reads the bytes again
+            mdf.clear_channel_data_from_memory(HashSet::from([name.clone()]))?;
+            mdf.load_channels_data_from_bytes(&bytes, HashSet::from([name.clone()]))?;
             assert_eq!(
-                ChannelData::Float64(Float64Builder::new_from_buffer(
-                    vec![0., 1., 2., 3., 4., 5., 6., 7., 8., 9.].into(),
-                    None
-                )),
-                data.clone()
+                mdf.get_channel_data(&name),
+                Some(&ChannelData::Boolean(expected.clone()))
             );
+            // written back as single bits
+            let output = std::env::temp_dir().join("mdfr_single_bit.mf4");
+            let mut round_trip = mdf.write(&output.to_string_lossy(), false)?;
+            round_trip.load_all_channels_data_in_memory()?;
+            assert_eq!(
+                round_trip.get_channel_data(&name),
+                Some(&ChannelData::Boolean(expected))
+            );
+            std::fs::remove_file(&output)?;
         }
-        if let Some(data) = mdf.get_channel_data(&"Data channel".to_string()) {
-            assert_eq!(expected_string_result, data.clone());
-        }
-        let mut mdf2 = mdf.write(&writing_mdf_file, false)?;
-        mdf2.load_all_channels_data_in_memory()?;
-        if let Some(data) = mdf2.get_channel_data(&"Data channel".to_string()) {
-            assert_eq!(expected_string_result, data.clone());
+        Ok(())
+    }
+
+    #[test]
+    fn golden_round_trip() -> Result<()> {
+        require_test_data!();
+        let mut failures = Vec::new();
+        for folder in [&*BASE_PATH_MDF4, &*BASE_PATH_MDF3] {
+            for file in mdf_files(Path::new(folder))? {
+                match round_trip(&file) {
+                    Ok(mismatches) if mismatches.is_empty() => {}
+                    Ok(mismatches) => failures.push(format!(
+                        "{}: channels differ {:?}",
+                        file.display(),
+                        mismatches
+                    )),
+                    Err(error) => failures.push(format!("{}: {error:#}", file.display())),
+                }
+            }
         }
-        //UTF16
-        let file_name = format!(
-            "{}{}{}",
-            BASE_PATH_MDF4, list_of_paths[4], "Vector_FixedLengthStringUTF16_BE.mf4"
-        );
-        let mut mdf = Mdf::new(&file_name)?;
+        assert!(failures.is_empty(), "{}", failures.join("\n"));
+        Ok(())
+    }
+
+    #[test]
+    fn parallel_reading() -> Result<()> {
+        // written file has one data group per channel
+        let mut mdf = Mdf::new("test_files/test_basic.mf4")?;
         mdf.load_all_channels_data_in_memory()?;
-        if let Some(data) = mdf.get_channel_data(&"Data channel".to_string()) {
-            assert_eq!(expected_string_result, data.clone());
+        let writing_file = std::env::temp_dir().join("mdfr_parallel_reading.mf4");
+        let writing_file = writing_file.to_str().context("invalid temp path")?;
+        let _ = fs::remove_file(writing_file);
+        mdf.write(writing_file, false)?;
+        let mut sequential = Mdf::new(writing_file)?;
+        let channel_names = sequential.get_channel_names_set();
+        sequential
+            .load_channels_data_from_reader(fs::File::open(writing_file)?, channel_names.clone())?;
+        // budget of one byte reads data groups one after the other
+        let tuned = ReadOptions {
+            chunk_size: 1000,
+            max_parallelism: Some(2),
+            memory_budget: 1,
+            lazy_strings: true,
+            ..Default::default()
+        };
+        for options in [
+            ReadOptions {
+                memory_budget: 1,
+                ..Default::default()
+            },
+            ReadOptions {
+                memory_budget: usize::MAX,
+                ..Default::default()
+            },
+            tuned,
+        ] {
+            let mut parallel = Mdf::new(writing_file)?;
+            parallel.load_channels_data_in_memory_with_options(channel_names.clone(), &options)?;
+            for name in channel_names.iter() {
+                assert_eq!(
+                    parallel.get_channel_data(name).map(|d| d.as_ref()),
+                    sequential.get_channel_data(name).map(|d| d.as_ref())
+                );
+            }
         }
-        let mut mdf2 = mdf.write(&writing_mdf_file, false)?;
-        mdf2.load_all_channels_data_in_memory()?;
-        if let Some(data) = mdf2.get_channel_data(&"Data channel".to_string()) {
-            assert_eq!(expected_string_result, data.clone());
+        fs::remove_file(writing_file)?;
+        Ok(())
+    }
+
+    #[test]
+    fn corrupt_file() -> Result<()> {
+        let file_name = std::env::temp_dir().join("mdfr_not_an_mdf_file.mf4");
+        fs::write(&file_name, [0x55u8; 128])?;
+        let result = Mdf::new(file_name.to_str().unwrap_or_default());
+        fs::remove_file(&file_name)?;
+        match result {
+            Err(e) => assert!(matches!(
+                e.downcast_ref::<MdfError>(),
+                Some(MdfError::Parse(_))
+            )),
+            Ok(_) => panic!("a file without mdf identifier should not be parsed"),
         }
-        let file_name = format!(
-            "{}{}{}",
-            BASE_PATH_MDF4, list_of_paths[4], "Vector_FixedLengthStringUTF16_LE.mf4"
-        );
-        let mut mdf = Mdf::new(&file_name)?;
-        mdf.load_all_channels_data_in_memory()?;
-        if let Some(data) = mdf.get_channel_data(&"Data channel".to_string()) {
-            assert_eq!(expected_string_result, data.clone());
-        }
-        //SBC
-        let file_name = format!(
-            "{}{}{}",
-            BASE_PATH_MDF4, list_of_paths[4], "Vector_FixedLengthStringSBC.mf4"
-        );
-        let mut mdf = Mdf::new(&file_name)?;
-        mdf.load_all_channels_data_in_memory()?;
-        if let Some(data) = mdf.get_channel_data(&"Data channel".to_string()) {
-            assert_eq!(expected_string_result, data.clone());
-        }
-        let mut mdf2 = mdf.write(&writing_mdf_file, false)?;
-        mdf2.load_all_channels_data_in_memory()?;
-        if let Some(data) = mdf2.get_channel_data(&"Data channel".to_string()) {
-            assert_eq!(expected_string_result, data.clone());
+        let result = Mdf::new("test_files/not_existing_file.mf4");
+        match result {
+            Err(e) => assert!(matches!(
+                e.downcast_ref::<MdfError>(),
+                Some(MdfError::Io(_))
+            )),
+            Ok(_) => panic!("a not existing file should not be opened"),
         }
+        Ok(())
+    }
 
-        // byteArray testing
-        let file_name = format!(
-            "{}{}{}",
-            BASE_PATH_MDF4, list_of_paths[0], "Vector_ByteArrayFixedLength.mf4"
-        );
-        let mut mdf = Mdf::new(&file_name)?;
-        let mut byte_array = FixedSizeBinaryBuilder::with_capacity(10, 5);
-        byte_array.append_value(vec![255, 255, 255, 255, 255])?;
-        byte_array.append_value(vec![18, 35, 52, 69, 86])?;
-        byte_array.append_value(vec![0, 1, 2, 3, 4])?;
-        byte_array.append_value(vec![4, 3, 2, 1, 0])?;
-        byte_array.append_value(vec![255, 254, 253, 252, 251])?;
-        byte_array.append_value(vec![250, 249, 248, 247, 246])?;
-        byte_array.append_value(vec![245, 244, 243, 242, 241])?;
-        byte_array.append_value(vec![240, 239, 238, 237, 236])?;
-        byte_array.append_value(vec![235, 234, 233, 232, 231])?;
-        byte_array.append_value(vec![255, 255, 255, 255, 255])?;
+    #[test]
+    fn array_channel_list_size() -> Result<()> {
+        use crate::data_holder::tensor_arrow::{Order, TensorArrow};
+        use crate::mdfinfo::mdfinfo4::MdfInfo4;
+        use crate::mdfreader::{DataSignature, MasterSignature};
+        // 10 records of 2x3 arrays, the first dimension of the shape being the records
+        let data = ChannelData::ArrayDFloat64(TensorArrow::new_from_buffer(
+            (0..60).map(|i| i as f64).collect::<Vec<f64>>().into(),
+            vec![10, 2, 3],
+            Order::RowMajor,
+        ));
+        let data_signature = DataSignature {
+            len: 10,
+            data_type: data.data_type(false),
+            bit_count: data.bit_count(),
+            byte_count: data.byte_count(),
+            ndim: data.ndim(),
+            shape: data.shape(),
+        };
+        let master_signature = MasterSignature {
+            master_channel: None,
+            master_type: None,
+            master_flag: false,
+        };
+        let mut info = MdfInfo4::new("array_channel.mf4", 1);
+        info.add_channel(
+            "Map".to_string(),
+            data,
+            data_signature,
+            master_signature,
+            None,
+            None,
+        )?;
+        let Some((_master, dg_pos, (_cg_pos, rec_id), (_cn_pos, rec_pos))) =
+            info.channel_names_set.get("Map")
+        else {
+            panic!("channel not found");
+        };
+        let Some(cg) = info.dg.get(dg_pos).and_then(|dg| dg.cg.get(rec_id)) else {
+            panic!("channel group not found");
+        };
+        let Some(cn) = cg.cn.get(rec_pos) else {
+            panic!("channel not found in its group");
+        };
+        assert_eq!(cn.list_size, 6);
+        assert_eq!(cg.block.cg_data_bytes, 6 * 8);
+        Ok(())
+    }
+
+    #[test]
+    fn list_data_invalid() -> Result<()> {
+        // channel with invalid values is written in LD block with DV and DI blocks
+        let file = "test_files/test_basic.mf4";
+        let writing_file = std::env::temp_dir().join("mdfr_list_data_invalid.mf4");
+        let mut mdf = Mdf::new(file)?;
         mdf.load_all_channels_data_in_memory()?;
-        if let Some(data) = mdf.get_channel_data(&"Time channel".to_string()) {
-            assert_eq!(
-                ChannelData::Float64(Float64Builder::new_from_buffer(
-                    vec![0., 1., 2., 3., 4., 5., 6., 7., 8., 9.].into(),
-                    None
-                )),
-                data.clone()
-            );
-        }
-        if let Some(data) = mdf.get_channel_data(&"Data channel".to_string()) {
-            assert_eq!(&ChannelData::FixedSizeByteArray(byte_array), data);
-        }
-        let mut mdf2 = mdf.write(&writing_mdf_file, false)?;
-        mdf2.load_all_channels_data_in_memory()?;
-        if let Some(data) = mdf2.get_channel_data(&"Data channel".to_string()) {
-            let mut byte_array = FixedSizeBinaryBuilder::with_capacity(10, 5);
-            byte_array.append_value(vec![255, 255, 255, 255, 255])?;
-            byte_array.append_value(vec![18, 35, 52, 69, 86])?;
-            byte_array.append_value(vec![0, 1, 2, 3, 4])?;
-            byte_array.append_value(vec![4, 3, 2, 1, 0])?;
-            byte_array.append_value(vec![255, 254, 253, 252, 251])?;
-            byte_array.append_value(vec![250, 249, 248, 247, 246])?;
-            byte_array.append_value(vec![245, 244, 243, 242, 241])?;
-            byte_array.append_value(vec![240, 239, 238, 237, 236])?;
-            byte_array.append_value(vec![235, 234, 233, 232, 231])?;
-            byte_array.append_value(vec![255, 255, 255, 255, 255])?;
-            assert_eq!(&ChannelData::FixedSizeByteArray(byte_array), data);
+        let channel_name = r"Invalid_channel".to_string();
+        let values: Vec<Option<f64>> = (0..100)
+            .map(|i| if i % 3 == 0 { None } else { Some(i as f64) })
+            .collect();
+        mdf.add_channel(
+            channel_name.clone(),
+            Arc::new(Float64Array::from(values)),
+            None,
+            Some(0),
+            false,
+            None,
+            None,
+        )?;
+        for compression in [false, true] {
+            let mut mdf4 = mdf.write(writing_file.to_str().unwrap_or_default(), compression)?;
+            mdf4.load_all_channels_data_in_memory()?;
+            if let Some(data) = mdf4.get_channel_data(&channel_name) {
+                assert_eq!(data.len(), 100);
+                let array = data.finish_cloned();
+                let values = array.as_primitive::<Float64Type>().values();
+                assert_eq!(values[1], 1.0f64);
+                assert_eq!(values[98], 98.0f64);
+                assert_eq!(array.null_count(), 34);
+                assert!(array.is_null(99));
+                let validity = mdf4
+                    .get_channel_validity(&channel_name)
+                    .context("validity missing")?;
+                assert!(!validity.is_valid(0));
+                assert!(validity.is_valid(1));
+            } else {
+                panic!("channel not found");
+            }
         }
+        fs::remove_file(&writing_file)?;
         Ok(())
     }
 
     #[test]
-    fn channel_types() -> Result<()> {
-        let list_of_paths = [
-            "ChannelTypes/MasterChannels/".to_string(),
-            "ChannelTypes/MLSD/".to_string(),
-            "ChannelTypes/VLSD/".to_string(),
-            "ChannelTypes/VirtualData/".to_string(),
-            "ChannelTypes/Synchronization/".to_string(),
-        ];
-
-        // MasterTypes testing
-        let mut vect: Vec<f64> = vec![0.; 101];
-        let mut counter: f64 = 0.;
-        vect.iter_mut().for_each(|v| {
-            *v = counter.clone() * 0.03;
-            counter += 1.
-        });
-        let expected_master =
-            ChannelData::Float64(Float64Builder::new_from_buffer(vect.into(), None));
-        let file_name = format!(
-            "{}{}{}",
-            BASE_PATH_MDF4, list_of_paths[0], "Vector_VirtualTimeMasterChannel.mf4"
-        );
-        let mut mdf = Mdf::new(&file_name)?;
+    fn transposed_compression() -> Result<()> {
+        let mut mdf = Mdf::new("test_files/test_basic.mf4")?;
         mdf.load_all_channels_data_in_memory()?;
-        if let Some(data) = mdf.get_channel_data(&"Time channel".to_string()) {
-            assert_eq!(expected_master, data.clone());
+        let channel_names = mdf.get_channel_names_set();
+        let writing_file = std::env::temp_dir().join("mdfr_transposed_compression.mf4");
+        let writing_file = writing_file.to_str().context("invalid temp path")?;
+        let mut sizes = Vec::new();
+        for compression in [
+            Compression::None,
+            Compression::Deflate { level: 6 },
+            Compression::TransposeDeflate { level: 9 },
+            Compression::Zstd { level: 3 },
+            Compression::TransposeZstd { level: 9 },
+        ] {
+            let _ = fs::remove_file(writing_file);
+            let mut written = mdf.write(writing_file, compression)?;
+            sizes.push(fs::metadata(writing_file)?.len());
+            written.load_all_channels_data_in_memory()?;
+            for name in channel_names.iter() {
+                assert_eq!(
+                    written.get_channel_data(name).map(|d| d.as_ref()),
+                    mdf.get_channel_data(name).map(|d| d.as_ref())
+                );
+            }
         }
-        let file_name = format!(
-            "{}{}{}",
-            BASE_PATH_MDF4, list_of_paths[0], "Vector_DifferentMasterChannels.mf4"
-        );
-        let mut mdf = Mdf::new(&file_name)?;
+        assert!(sizes[2] < sizes[0]);
+        // all data groups opted out of compression
+        let _ = fs::remove_file(writing_file);
+        mdf.write_with_uncompressed_channels(
+            writing_file,
+            Compression::TransposeDeflate { level: 9 },
+            &channel_names,
+        )?;
+        assert_eq!(fs::metadata(writing_file)?.len(), sizes[0]);
+        fs::remove_file(writing_file)?;
+        Ok(())
+    }
+
+    #[test]
+    fn registered_decompressor() -> Result<()> {
+        use crate::mdfinfo::mdfinfo4::{register_decompressor, DZ_ZSTD};
+        let mut mdf = Mdf::new("test_files/test_basic.mf4")?;
         mdf.load_all_channels_data_in_memory()?;
-        if let Some(data) = mdf.get_channel_data(&"Time channel".to_string()) {
-            assert_eq!(expected_master, data.clone());
+        let writing_file = std::env::temp_dir().join("mdfr_registered_decompressor.mf4");
+        mdf.write(
+            writing_file.to_str().context("invalid temp path")?,
+            Compression::Zstd { level: 3 },
+        )?;
+        let mut bytes = fs::read(&writing_file)?;
+        fs::remove_file(&writing_file)?;
+        // zstd DZ blocks relabelled with a zip type unknown to the reader
+        let zip_type = 200u8;
+        let dz_blocks: Vec<usize> = bytes
+            .windows(4)
+            .enumerate()
+            .filter(|(_, id)| id == b"##DZ")
+            .map(|(position, _)| position)
+            .collect();
+        assert!(!dz_blocks.is_empty());
+        for position in dz_blocks {
+            assert_eq!(bytes[position + 26], DZ_ZSTD);
+            bytes[position + 26] = zip_type;
         }
-        let file_name = format!(
-            "{}{}{}",
-            BASE_PATH_MDF4, list_of_paths[0], "Vector_NoMasterChannel.mf4"
+        let channel_names = mdf.get_channel_names_set();
+        let mut read = Mdf::from_bytes(&bytes, "registered_decompressor.mf4")?;
+        assert!(read
+            .load_channels_data_from_bytes(&bytes, channel_names.clone())
+            .is_err());
+        register_decompressor(
+            zip_type,
+            |compressed, length| Ok(zstd::bulk::decompress(compressed, length)?),
+            false,
         );
-        let mut mdf = Mdf::new(&file_name)?;
-        mdf.load_all_channels_data_in_memory()?;
-        if let Some(data) = mdf.get_channel_data(&"Time channel".to_string()) {
-            assert_eq!(expected_master, data.clone());
+        let mut read = Mdf::from_bytes(&bytes, "registered_decompressor.mf4")?;
+        read.load_channels_data_from_bytes(&bytes, channel_names.clone())?;
+        for name in channel_names.iter() {
+            assert_eq!(read.get_channel_data(name), mdf.get_channel_data(name));
         }
+        Ok(())
+    }
 
-        // MLSD testing
-        let mut expected_string_result = LargeStringBuilder::with_capacity(10, 6);
-        expected_string_result.append_value("zero");
-        expected_string_result.append_value("one");
-        expected_string_result.append_value("two");
-        expected_string_result.append_value("three");
-        expected_string_result.append_value("four");
-        expected_string_result.append_value("five");
-        expected_string_result.append_value("six");
-        expected_string_result.append_value("seven");
-        expected_string_result.append_value("eight");
-        expected_string_result.append_value("nine");
-        let expected_string_result = ChannelData::Utf8(expected_string_result);
-        let file_name = format!(
-            "{}{}{}",
-            BASE_PATH_MDF4, list_of_paths[1], "Vector_MLSDStringUTF8.mf4"
-        );
-        let mut mdf = Mdf::new(&file_name)?;
+    #[test]
+    fn channel_group_batch() -> Result<()> {
+        let mut mdf = Mdf::new("test_files/test_basic.mf4")?;
+        assert!(mdf.get_channel_group("Time").is_err());
         mdf.load_all_channels_data_in_memory()?;
-        if let Some(data) = mdf.get_channel_data(&"Data channel".to_string()) {
-            assert_eq!(expected_string_result, data.clone());
-        }
-        let file_name = format!(
-            "{}{}{}",
-            BASE_PATH_MDF4, list_of_paths[1], "Vector_MLSDStringSBC.mf4"
+        let batch = mdf.get_channel_group("Time")?;
+        let schema = batch.schema();
+        let names: Vec<&String> = schema.fields().iter().map(|f| f.name()).collect();
+        assert_eq!(names, vec!["Time", "Value"]);
+        assert_eq!(batch.num_rows(), 100000);
+        assert_eq!(
+            Some(batch.column(1)),
+            mdf.get_channel_data("Value").map(|d| d.as_ref()).as_ref()
         );
-        let mut mdf = Mdf::new(&file_name)?;
+        assert!(mdf.get_channel_group("unknown").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn channel_decimation() -> Result<()> {
+        let mut mdf = Mdf::new("test_files/test_basic.mf4")?;
+        assert!(mdf
+            .get_channel_data_decimated("Value", 100, DecimationMethod::Nth)
+            .is_err());
         mdf.load_all_channels_data_in_memory()?;
-        if let Some(data) = mdf.get_channel_data(&"Data channel".to_string()) {
-            assert_eq!(expected_string_result, data.clone());
-        }
-        let file_name = format!(
-            "{}{}{}",
-            BASE_PATH_MDF4, list_of_paths[1], "Vector_MLSDStringUTF16_BE.mf4"
+        let data = mdf
+            .get_channel_data("Value")
+            .context("Value missing")?
+            .as_ref();
+        let values = cast(&data, &DataType::Float64)?;
+        let values = values.as_primitive::<Float64Type>();
+        let min_max = mdf.get_channel_data_decimated("Value", 1000, DecimationMethod::MinMax)?;
+        assert!(min_max.len() <= 1000);
+        assert_eq!(min_max.data_type(), data.data_type());
+        let min_max = cast(&min_max, &DataType::Float64)?;
+        let min_max = min_max.as_primitive::<Float64Type>();
+        let min = values.iter().flatten().fold(f64::INFINITY, f64::min);
+        let max = values.iter().flatten().fold(f64::NEG_INFINITY, f64::max);
+        assert_eq!(min_max.iter().flatten().fold(f64::INFINITY, f64::min), min);
+        assert_eq!(
+            min_max.iter().flatten().fold(f64::NEG_INFINITY, f64::max),
+            max
         );
-        let mut mdf = Mdf::new(&file_name)?;
-        mdf.load_all_channels_data_in_memory()?;
-        if let Some(data) = mdf.get_channel_data(&"Data channel".to_string()) {
-            assert_eq!(expected_string_result, data.clone());
-        }
-        let file_name = format!(
-            "{}{}{}",
-            BASE_PATH_MDF4, list_of_paths[1], "Vector_MLSDStringUTF16_LE.mf4"
+        let mean = mdf.get_channel_data_decimated("Value", 1000, DecimationMethod::Mean)?;
+        assert_eq!(mean.len(), 1000);
+        let mean = mean.as_primitive::<Float64Type>();
+        let expected = values.values()[..100].iter().sum::<f64>() / 100.0;
+        assert!((mean.value(0) - expected).abs() < 1e-9);
+        let nth = mdf.get_channel_data_decimated("Value", 1000, DecimationMethod::Nth)?;
+        let nth = cast(&nth, &DataType::Float64)?;
+        assert_eq!(nth.len(), 1000);
+        assert_eq!(
+            nth.as_primitive::<Float64Type>().value(1),
+            values.value(100)
         );
-        let mut mdf = Mdf::new(&file_name)?;
-        mdf.load_all_channels_data_in_memory()?;
-        if let Some(data) = mdf.get_channel_data(&"Data channel".to_string()) {
-            assert_eq!(expected_string_result, data.clone());
-        }
+        let time = mdf.get_channel_data_decimated("Time", 200000, DecimationMethod::MinMax)?;
+        assert_eq!(time.len(), 100000);
+        assert!(mdf
+            .get_channel_data_decimated("Value", 1, DecimationMethod::MinMax)
+            .is_err());
+        Ok(())
+    }
 
-        // Virtual data testing
-        let mut vect: Vec<u64> = vec![0; 200];
-        let mut counter: u64 = 0;
-        vect.iter_mut().for_each(|v| {
-            *v += counter;
-            counter += 1
-        });
-        let virtal_vect = UInt64Builder::new_from_buffer(vect.into(), None);
-        let file_name = format!(
-            "{}{}{}",
-            BASE_PATH_MDF4, list_of_paths[3], "Vector_VirtualDataChannelNoConversion.mf4"
+    #[test]
+    fn channel_stats() -> Result<()> {
+        let mut mdf = Mdf::new("test_files/test_basic.mf4")?;
+        assert!(mdf.get_channel_stats("unknown").is_err());
+        let stats = mdf.get_channel_stats("Value")?;
+        let data = mdf.get_channel_data("Value").context("Value not loaded")?;
+        let values = cast(&data.as_ref(), &DataType::Float64)?;
+        let values = values.as_primitive::<Float64Type>().values();
+        let mean = values.iter().sum::<f64>() / values.len() as f64;
+        let variance =
+            values.iter().map(|v| (v - mean) * (v - mean)).sum::<f64>() / values.len() as f64;
+        assert_eq!(stats.count, 100000);
+        assert_eq!(stats.nan_count, 0);
+        assert_eq!(stats.min, values.iter().copied().reduce(f64::min));
+        assert_eq!(stats.max, values.iter().copied().reduce(f64::max));
+        assert!((stats.mean.context("no mean")? - mean).abs() < 1e-6 * mean.abs().max(1.0));
+        assert!((stats.std.context("no std")? - variance.sqrt()).abs() < 1e-6);
+        mdf.set_channel_data(
+            "Value",
+            Arc::new(Float64Array::from(vec![
+                Some(1.0),
+                None,
+                Some(f64::NAN),
+                Some(3.0),
+            ])),
+        )?;
+        let stats = mdf.get_channel_stats("Value")?;
+        assert_eq!((stats.count, stats.nan_count), (2, 1));
+        assert_eq!((stats.mean, stats.std), (Some(2.0), Some(1.0)));
+        Ok(())
+    }
+
+    #[test]
+    fn broken_links_recovery() -> Result<()> {
+        let file_name = std::env::temp_dir().join("test_recovery.mf4");
+        let mut bytes = fs::read("test_files/test_basic.mf4")?;
+        // first data group link of HD block pointing outside of file
+        let outside = 2 * bytes.len() as u64;
+        bytes[88..96].copy_from_slice(&outside.to_le_bytes()[..]);
+        fs::write(&file_name, &bytes)?;
+        let file_name = file_name.to_string_lossy();
+        assert!(Mdf::new(&file_name).is_err());
+        let mut recovered = Mdf::new_with_recovery(&file_name)?;
+        let mut original = Mdf::new("test_files/test_basic.mf4")?;
+        assert_eq!(
+            recovered.get_channel_names_set(),
+            original.get_channel_names_set()
         );
-        let mut mdf = Mdf::new(&file_name)?;
-        mdf.load_all_channels_data_in_memory()?;
-        if let Some(data) = mdf.get_channel_data(&"Data channel".to_string()) {
-            assert_eq!(ChannelData::UInt64(virtal_vect), *data);
-        }
-        let mut vect: Vec<f64> = vec![100.0f64; 200];
-        let mut counter: f64 = 0.0;
-        vect.iter_mut().for_each(|v| {
-            *v += counter;
-            counter -= 2.0
-        });
-        let virtal_linear_vect = Float64Builder::new_from_buffer(vect.into(), None);
-        let file_name = format!(
-            "{}{}{}",
-            BASE_PATH_MDF4, list_of_paths[3], "Vector_VirtualDataChannelLinearConversion.mf4"
+        recovered.load_all_channels_data_in_memory()?;
+        original.load_all_channels_data_in_memory()?;
+        assert_eq!(
+            recovered.get_channel_data("Value"),
+            original.get_channel_data("Value")
         );
-        let mut mdf = Mdf::new(&file_name)?;
+        Ok(())
+    }
+
+    #[test]
+    fn anonymization() -> Result<()> {
+        let mut mdf = Mdf::new("test_files/test_basic.mf4")?;
         mdf.load_all_channels_data_in_memory()?;
-        if let Some(data) = mdf.get_channel_data(&"Data channel".to_string()) {
-            assert_eq!(ChannelData::Float64(virtal_linear_vect), *data);
-        }
-        let file_name = format!(
-            "{}{}{}",
-            BASE_PATH_MDF4, list_of_paths[3], "Vector_VirtualDataChannelConstantConversion.mf4"
+        mdf.set_channel_desc("Value", "measured by John Doe");
+        mdf.add_channel(
+            "GPS_Latitude".to_string(),
+            Arc::new(Float64Array::from(vec![48.85; 100000])),
+            Some("Time".to_string()),
+            None,
+            false,
+            None,
+            None,
+        )?;
+        let removed = mdf.anonymize(&AnonymizeOptions::default());
+        assert_eq!(removed, vec!["GPS_Latitude"]);
+        assert_eq!(mdf.get_channel_desc("Value")?, None);
+        let file_name = std::env::temp_dir().join("test_anonymized.mf4");
+        let file_name = file_name.to_string_lossy();
+        mdf.write(&file_name, false)?;
+        let written = Mdf::new(&file_name)?;
+        assert_eq!(
+            written.get_channel_names_set(),
+            HashSet::from(["Time".to_string(), "Value".to_string()])
         );
-        let mut mdf = Mdf::new(&file_name)?;
+        assert!(!fs::read(file_name.as_ref())?
+            .windows(8)
+            .any(|bytes| bytes == b"John Doe"));
+        Ok(())
+    }
+
+    #[test]
+    fn channel_mapping() -> Result<()> {
+        let mut mdf = Mdf::new("test_files/test_basic.mf4")?;
         mdf.load_all_channels_data_in_memory()?;
-        if let Some(data) = mdf.get_channel_data(&"Data channel".to_string()) {
-            assert_eq!(
-                ChannelData::Float64(Float64Builder::new_from_buffer(
-                    vec![42f64; 200].into(),
-                    None
-                )),
-                *data
-            );
-        }
-        // VLSD testing
-        let file_name = format!(
-            "{}{}{}",
-            BASE_PATH_MDF4, list_of_paths[2], "Vector_VLSDStringUTF8.mf4"
+        let value = mdf.get_channel_data("Value").cloned();
+        let conflict = HashMap::from([("Value".to_string(), "Time".to_string())]);
+        assert!(mdf.apply_channel_mapping(&conflict).is_err());
+        let mapping_file = std::env::temp_dir().join("test_mapping.csv");
+        fs::write(
+            &mapping_file,
+            "# current,new\n\"Value\"; Speed\nTime,t\nMissing,Other\n",
+        )?;
+        assert_eq!(
+            mdf.apply_channel_mapping_file(&mapping_file.to_string_lossy())?,
+            2
         );
-        let mut mdf = Mdf::new(&file_name)?;
+        assert_eq!(
+            mdf.get_channel_names_set(),
+            HashSet::from(["Speed".to_string(), "t".to_string()])
+        );
+        assert_eq!(mdf.get_channel_master("Speed"), Some("t".to_string()));
+        assert_eq!(mdf.get_channel_data("Speed").cloned(), value);
+        // names swapped
+        let swap = HashMap::from([
+            ("Speed".to_string(), "t".to_string()),
+            ("t".to_string(), "Speed".to_string()),
+        ]);
+        assert_eq!(mdf.apply_channel_mapping(&swap)?, 2);
+        assert_eq!(mdf.get_channel_data("t").cloned(), value);
+        Ok(())
+    }
+
+    #[test]
+    fn channel_search() -> Result<()> {
+        let mdf = Mdf::new("test_files/test_basic.mf4")?;
+        assert_eq!(mdf.search_channels("t?ME")?, vec!["Time"]);
+        assert_eq!(mdf.search_channels("*")?, vec!["Time", "Value"]);
+        assert!(mdf.search_channels("Val")?.is_empty());
+        assert_eq!(mdf.search_channels_regex("^Val")?, vec!["Value"]);
+        assert!(mdf.search_channels_regex("(").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn master_synthesis() -> Result<()> {
+        let mut mdf = Mdf::new("test_files/test_basic.mf4")?;
         mdf.load_all_channels_data_in_memory()?;
-        if let Some(data) = mdf.get_channel_data(&"Data channel".to_string()) {
-            assert_eq!(expected_string_result, data.clone());
-        }
-        let file_name = format!(
-            "{}{}{}",
-            BASE_PATH_MDF4, list_of_paths[2], "Vector_VLSDStringUTF16_LE.mf4"
+        mdf.add_channel(
+            "Alone".to_string(),
+            Arc::new(Float64Array::from(vec![1.0; 10])),
+            None,
+            None,
+            false,
+            None,
+            None,
+        )?;
+        assert!(mdf.synthesize_masters(None).is_err());
+        assert!(mdf.synthesize_masters(Some(0.0)).is_err());
+        assert_eq!(mdf.synthesize_masters(Some(100.0))?, vec!["time"]);
+        assert_eq!(mdf.get_channel_master("Alone"), Some("time".to_string()));
+        assert_eq!(mdf.get_channel_master("Value"), Some("Time".to_string()));
+        let expected = Float64Array::from((0..10).map(|i| i as f64 * 0.01).collect::<Vec<f64>>());
+        let time = mdf.get_channel_data("time").context("time missing")?;
+        assert_eq!(time.as_ref().as_primitive::<Float64Type>(), &expected);
+        assert_eq!(mdf.get_channel_unit("time")?, Some("s".to_string()));
+        assert!(mdf.synthesize_masters(Some(100.0))?.is_empty());
+        let file_name = std::env::temp_dir().join("test_master_synthesis.mf4");
+        let mut written = mdf.write(&file_name.to_string_lossy(), false)?;
+        written.load_all_channels_data_in_memory()?;
+        assert_eq!(
+            written.get_channel_master("Alone"),
+            Some("time".to_string())
         );
-        let mut mdf = Mdf::new(&file_name)?;
+        let time = written.get_channel_data("time").context("time missing")?;
+        assert_eq!(time.as_ref().as_primitive::<Float64Type>(), &expected);
+        Ok(())
+    }
+
+    #[test]
+    fn channel_source() -> Result<()> {
+        let mut mdf = Mdf::new("test_files/test_basic.mf4")?;
+        assert_eq!(mdf.get_channel_source("Value")?, None);
         mdf.load_all_channels_data_in_memory()?;
-        if let Some(data) = mdf.get_channel_data(&"Data channel".to_string()) {
-            assert_eq!(expected_string_result, data.clone());
-        }
-        let file_name = format!(
-            "{}{}{}",
-            BASE_PATH_MDF4, list_of_paths[2], "Vector_VLSDStringUTF16_BE.mf4"
+        let MdfInfo::V4(info) = &mut mdf.mdf_info else {
+            panic!("mdf4 file expected")
+        };
+        let (_master, dg_pos, (_cg_pos, rec_id), (_cn_pos, rec_pos)) = info
+            .get_channel_id("Value")
+            .cloned()
+            .context("channel missing")?;
+        let mut si = Si4Block::new(1, 2);
+        si.si_flags = 1;
+        si.si_tx_name = position_generator();
+        info.sharable.create_tx(si.si_tx_name, "Engine".to_string());
+        let si_position = position_generator();
+        info.sharable.si.insert(si_position, si);
+        info.dg
+            .get_mut(&dg_pos)
+            .and_then(|dg| dg.cg.get_mut(&rec_id))
+            .and_then(|cg| cg.cn.get_mut(&rec_pos))
+            .context("channel block missing")?
+            .block
+            .cn_si_source = si_position;
+        let writing_file = std::env::temp_dir().join("mdfr_channel_source.mf4");
+        let writing_file = writing_file.to_str().context("invalid temp path")?;
+        let _ = fs::remove_file(writing_file);
+        let written = mdf.write(writing_file, false)?;
+        fs::remove_file(writing_file)?;
+        let source = written
+            .get_channel_source("Value")?
+            .context("source missing")?;
+        assert_eq!(source.name.as_deref(), Some("Engine"));
+        assert_eq!(
+            (source.source_type_name(), source.bus_type_name()),
+            ("ECU", "CAN")
+        );
+        assert!(source.is_simulated());
+        assert!(written.to_string().contains("source Engine"));
+        assert_eq!(written.search_channels("eng*")?, vec!["Value"]);
+        Ok(())
+    }
+
+    #[test]
+    fn save_metadata_in_place() -> Result<()> {
+        let file_name = std::env::temp_dir().join("mdfr_save_metadata.mf4");
+        let file_name = file_name.to_str().context("invalid temp path")?;
+        fs::copy("test_files/test_basic.mf4", file_name)?;
+        let mut mdf = Mdf::new(file_name)?;
+        assert_eq!(mdf.save_metadata()?, 0);
+        let size = fs::metadata(file_name)?.len();
+        mdf.set_channel_unit("Value", "km/h");
+        mdf.set_channel_desc("Value", "vehicle speed");
+        mdf.set_channel_master_type("Time", 1)?;
+        assert!(mdf.save_metadata()? >= 1);
+        assert!(fs::metadata(file_name)?.len() > size);
+        assert!(validate_file(file_name)?.is_valid());
+        let mut saved = Mdf::new(file_name)?;
+        assert_eq!(saved.get_channel_unit("Value")?.as_deref(), Some("km/h"));
+        assert_eq!(
+            saved.get_channel_desc("Value")?.as_deref(),
+            Some("vehicle speed")
+        );
+        assert_eq!(saved.get_channel_master_type("Time"), 1);
+        // data blocks untouched
+        saved.load_all_channels_data_in_memory()?;
+        let mut original = Mdf::new("test_files/test_basic.mf4")?;
+        original.load_all_channels_data_in_memory()?;
+        assert_eq!(
+            saved.get_channel_data("Value").map(|d| d.as_ref()),
+            original.get_channel_data("Value").map(|d| d.as_ref())
+        );
+        fs::remove_file(file_name)?;
+        Ok(())
+    }
+
+    #[test]
+    fn record_iterator() -> Result<()> {
+        use crate::test_utils::{DataLayout, SyntheticMdf};
+        use arrow::array::ArrayRef;
+        use arrow::compute::concat;
+        // values of the channels of the group of channel_name, chunks concatenated
+        fn iterated<R: io::Read + io::Seek>(
+            mdf: &Mdf,
+            channel_name: &str,
+            reader: R,
+        ) -> Result<HashMap<String, ArrayRef>> {
+            let mut chunks: HashMap<String, Vec<ArrayRef>> = HashMap::new();
+            for chunk in mdf.iter_records_from_reader(channel_name, reader)? {
+                for (name, data) in chunk? {
+                    chunks.entry(name).or_default().push(data.as_ref());
+                }
+            }
+            chunks
+                .into_iter()
+                .map(|(name, arrays)| {
+                    let arrays: Vec<&dyn Array> =
+                        arrays.iter().map(|array| array.as_ref()).collect();
+                    Ok((name, concat(&arrays)?))
+                })
+                .collect()
+        }
+        let file = "test_files/test_basic.mf4";
+        let mdf = Mdf::new(file)?;
+        let mut loaded = Mdf::new(file)?;
+        loaded.load_all_channels_data_in_memory()?;
+        let bytes = fs::read(file)?;
+        for channel_names in mdf.get_master_channel_names_set().values() {
+            if let Some(channel_name) = channel_names.iter().next() {
+                for (name, data) in iterated(&mdf, channel_name, io::Cursor::new(&bytes))? {
+                    if let Some(expected) = loaded.get_channel_data(&name) {
+                        assert_eq!(&data, &expected.as_ref(), "{name}");
+                    }
+                }
+                // same records read from the file and from the file content in memory
+                let from_file: Vec<_> = mdf.iter_records(channel_name)?.collect::<Result<_>>()?;
+                let from_memory: Vec<_> = mdf
+                    .iter_records_from_reader(channel_name, io::Cursor::new(&bytes))?
+                    .collect::<Result<_>>()?;
+                assert_eq!(from_file, from_memory);
+            }
+        }
+        // sorted and unsorted records, in data blocks listed by DL blocks or compressed
+        let layouts = [
+            DataLayout::Dt,
+            DataLayout::Dl {
+                records_per_block: 7,
+            },
+            DataLayout::Dz,
+            DataLayout::Hl {
+                records_per_block: 13,
+            },
+        ];
+        let file_name = std::env::temp_dir().join("test_record_iterator.mf4");
+        let file_name = file_name.to_str().context("invalid temp path")?;
+        for layout in layouts {
+            for sorted in [true, false] {
+                let mut file = SyntheticMdf::mdf4()
+                    .with_group(&[DataType::UInt8, DataType::Int16, DataType::Float32])
+                    .with_group(&[DataType::UInt64, DataType::Float64, DataType::Int8])
+                    .with_records(120)
+                    .with_layout(layout);
+                if !sorted {
+                    file = file.unsorted();
+                }
+                file.write(file_name)?;
+                let mdf = Mdf::new(file_name)?;
+                let mut loaded = Mdf::new(file_name)?;
+                loaded.load_all_channels_data_in_memory()?;
+                for group in 0..2 {
+                    let master = SyntheticMdf::master_name(group);
+                    let values = iterated(&mdf, &master, fs::File::open(file_name)?)?;
+                    let mut names: Vec<&String> = values.keys().collect();
+                    names.sort();
+                    let mut expected_names: Vec<String> = loaded
+                        .get_master_channel_names_set()
+                        .remove(&Some(master.clone()))
+                        .context("group not found")?
+                        .into_iter()
+                        .collect();
+                    expected_names.sort();
+                    assert_eq!(names, expected_names.iter().collect::<Vec<_>>(), "{file:?}");
+                    for (name, data) in values {
+                        let expected = loaded
+                            .get_channel_data(&name)
+                            .with_context(|| format!("{name} not loaded"))?;
+                        assert_eq!(&data, &expected.as_ref(), "{name} of {file:?}");
+                        assert_eq!(
+                            &data,
+                            &file.expected_data(&name).context("no expected data")?,
+                            "{name} of {file:?}"
+                        );
+                    }
+                }
+            }
+        }
+        fs::remove_file(file_name)?;
+        Ok(())
+    }
+
+    #[test]
+    fn typed_records() -> Result<()> {
+        use crate::mdfreader::record4::{FromRecord, Record};
+        #[derive(Debug, PartialEq)]
+        struct Sample {
+            time: f64,
+            value: f32,
+            raw: Option<i64>,
+        }
+        impl FromRecord for Sample {
+            fn channel_names() -> Vec<String> {
+                vec!["Time".to_string(), "Value".to_string()]
+            }
+            fn from_record(record: &Record) -> Result<Self> {
+                Ok(Sample {
+                    time: record.get("Time")?,
+                    value: record.get("Value")?,
+                    raw: record.get("Value")?,
+                })
+            }
+        }
+        let mut mdf = Mdf::new("test_files/test_basic.mf4")?;
+        let samples: Vec<Sample> = mdf.iter_typed_records("Value")?.collect::<Result<_>>()?;
+        mdf.load_all_channels_data_in_memory()?;
+        let time = cast(
+            &mdf.get_channel_data("Time")
+                .context("Time missing")?
+                .as_ref(),
+            &DataType::Float64,
+        )?;
+        let value = cast(
+            &mdf.get_channel_data("Value")
+                .context("Value missing")?
+                .as_ref(),
+            &DataType::Float32,
+        )?;
+        assert_eq!(samples.len(), time.len());
+        for ((sample, time), value) in samples
+            .iter()
+            .zip(time.as_primitive::<Float64Type>().values())
+            .zip(value.as_primitive::<Float32Type>().values())
+        {
+            assert_eq!(sample.time, *time);
+            assert_eq!(sample.value, *value);
+            assert!(sample.raw.is_some());
+        }
+        // channel missing in record
+        struct Missing;
+        impl FromRecord for Missing {
+            fn channel_names() -> Vec<String> {
+                vec!["Missing".to_string()]
+            }
+            fn from_record(record: &Record) -> Result<Self> {
+                record.get::<f64>("Missing").map(|_| Missing)
+            }
+        }
+        assert!(mdf
+            .iter_typed_records::<Missing>("Value")?
+            .next()
+            .is_some_and(|record| record.is_err()));
+        Ok(())
+    }
+
+    #[test]
+    fn group_layout() -> Result<()> {
+        let mut mdf = Mdf::new("test_files/test_basic.mf4")?;
+        assert!(mdf.get_group_layout("Missing").is_none());
+        let layout = mdf.get_group_layout("Time").context("layout missing")?;
+        assert_eq!(Some(&layout), mdf.get_group_layout("Value").as_ref());
+        let names: HashSet<String> = layout
+            .channels
+            .iter()
+            .map(|channel| channel.name.clone())
+            .collect();
+        assert!(names.contains("Time") && names.contains("Value"));
+        assert!(layout
+            .channels
+            .windows(2)
+            .all(|pair| (pair[0].byte_offset, pair[0].bit_offset)
+                <= (pair[1].byte_offset, pair[1].bit_offset)));
+        for channel in layout.channels.iter() {
+            assert!(
+                channel.byte_offset as u64 * 8
+                    + channel.bit_offset as u64
+                    + channel.bit_count as u64
+                    <= layout.record_length * 8
+            );
+        }
+        mdf.load_all_channels_data_in_memory()?;
+        let time = mdf.get_channel_data("Time").context("Time missing")?;
+        assert_eq!(layout.cycle_count, time.len() as u64);
+        Ok(())
+    }
+
+    #[cfg(feature = "object-store")]
+    #[test]
+    fn range_reader() -> Result<()> {
+        use crate::mdfreader::range_reader::{ObjectStoreSource, RangeReader};
+        use crate::test_utils::{DataLayout, SyntheticMdf};
+        use arrow::compute::filter;
+        use object_store::memory::InMemory;
+        use object_store::path::Path as ObjectPath;
+        use object_store::{ObjectStore, PutPayload};
+        let store = Arc::new(InMemory::new());
+        let put = |location: &ObjectPath, bytes: Vec<u8>| -> Result<()> {
+            tokio::runtime::Builder::new_current_thread()
+                .build()?
+                .block_on(store.put(location, PutPayload::from(bytes)))?;
+            Ok(())
+        };
+        let file = "test_files/test_basic.mf4";
+        let channels = HashSet::from(["Value".to_string()]);
+        let mut expected = Mdf::new(file)?;
+        expected.load_channels_data_in_memory(channels.clone())?;
+        let location = ObjectPath::from("test_basic.mf4");
+        put(&location, fs::read(file)?)?;
+        let source = ObjectStoreSource::new(store.clone(), location)?;
+        let mut reader = RangeReader::new(source)?.with_block_size(256);
+        let mut mdf = Mdf::from_reader(&mut reader, file)?;
+        assert_eq!(
+            mdf.get_channel_names_set(),
+            expected.get_channel_names_set()
+        );
+        mdf.load_channels_data_from_reader(&mut reader, channels)?;
+        assert_eq!(
+            mdf.get_channel_data("Value"),
+            expected.get_channel_data("Value")
+        );
+        // time range loading by range requests, the index file of a local file with the same
+        // name and other data blocks being ignored
+        let local = std::env::temp_dir().join("mdfr_test_range_reader.mf4");
+        let local = local.to_str().context("invalid temp path")?;
+        let file = SyntheticMdf::mdf4()
+            .with_group(&[DataType::UInt16, DataType::Float32])
+            .with_records(100);
+        file.clone()
+            .with_layout(DataLayout::Dl {
+                records_per_block: 10,
+            })
+            .write(local)?;
+        Mdf::new(local)?.build_index()?;
+        let file = file.with_layout(DataLayout::Dl {
+            records_per_block: 7,
+        });
+        let location = ObjectPath::from("synthetic.mf4");
+        put(&location, file.to_bytes()?)?;
+        let mut reader = RangeReader::new(ObjectStoreSource::new(store, location)?)?;
+        let mut mdf = Mdf::from_reader(&mut reader, local)?;
+        let (t_start, t_stop) = (31.0 * 0.01, 64.0 * 0.01);
+        mdf.load_channels_data_from_reader_between(
+            &mut reader,
+            mdf.get_channel_names_set(),
+            t_start,
+            t_stop,
+        )?;
+        let time = file
+            .expected_data(&SyntheticMdf::master_name(0))
+            .context("no expected data")?;
+        let mask: BooleanArray = time
+            .as_primitive::<Float64Type>()
+            .iter()
+            .map(|t| t.map(|t| t_start <= t && t <= t_stop))
+            .collect();
+        for name in file.channel_names() {
+            let data = mdf.get_channel_data(&name).context("channel not loaded")?;
+            let expected = file.expected_data(&name).context("no expected data")?;
+            assert_eq!(&data.as_ref(), &filter(&expected, &mask)?, "{name}");
+        }
+        fs::remove_file(index_file_name(local))?;
+        fs::remove_file(local)?;
+        Ok(())
+    }
+
+    #[test]
+    fn time_range_loading() -> Result<()> {
+        use crate::test_utils::{DataLayout, SyntheticMdf};
+        use arrow::array::ArrayRef;
+        use arrow::compute::{concat, filter};
+        let file = "test_files/test_basic.mf4";
+        let mut loaded = Mdf::new(file)?;
+        loaded.load_all_channels_data_in_memory()?;
+        let mut tested = false;
+        for (master, channel_names) in loaded.get_master_channel_names_set() {
+            let Some(master) = master else {
+                continue;
+            };
+            let Some(master_data) = loaded.get_channel_data(&master) else {
+                continue;
+            };
+            let time = cast(&master_data.as_ref(), &DataType::Float64)?;
+            let time = time.as_primitive::<Float64Type>().values();
+            if time.len() < 4 {
+                continue;
+            }
+            // window from second to penultimate values
+            let (t_start, t_stop) = (time[1], time[time.len() - 2]);
+            let expected = time
+                .iter()
+                .filter(|t| **t >= t_start && **t <= t_stop)
+                .count();
+            let mut mdf = Mdf::new(file)?;
+            mdf.load_channels_data_in_memory_between(channel_names.clone(), t_start, t_stop)?;
+            for channel_name in channel_names.iter() {
+                if let Some(data) = mdf.get_channel_data(channel_name) {
+                    if !data.is_empty() {
+                        assert_eq!(data.len(), expected);
+                        tested = true;
+                    }
+                }
+            }
+        }
+        assert!(tested);
+        // DZ blocks of 10 records, the first one before the window is dropped and reading stops
+        // at the fourth one after it
+        struct Seeks<R> {
+            inner: R,
+            positions: HashSet<u64>,
+        }
+        impl<R: io::Read> io::Read for Seeks<R> {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                self.inner.read(buf)
+            }
+        }
+        impl<R: io::Seek> io::Seek for Seeks<R> {
+            fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
+                let position = self.inner.seek(pos)?;
+                self.positions.insert(position);
+                Ok(position)
+            }
+        }
+        let file = SyntheticMdf::mdf4()
+            .with_group(&[DataType::UInt16, DataType::Float32])
+            .with_records(100)
+            .with_layout(DataLayout::Hl {
+                records_per_block: 10,
+            });
+        let bytes = file.to_bytes()?;
+        let dz_blocks: HashSet<u64> = bytes
+            .windows(4)
+            .enumerate()
+            .filter(|(_, id)| id == b"##DZ")
+            .map(|(position, _)| position as u64)
+            .collect();
+        assert_eq!(dz_blocks.len(), 10);
+        let mdf = Mdf::from_bytes(&bytes, "time_range_dz.mf4")?;
+        let master = SyntheticMdf::master_name(0);
+        let (t_start, t_stop) = (12.0 * 0.01, 27.0 * 0.01);
+        let mut reader = Seeks {
+            inner: io::Cursor::new(&bytes),
+            positions: HashSet::new(),
+        };
+        let mut values: HashMap<String, Vec<ArrayRef>> = HashMap::new();
+        for chunk in mdf
+            .iter_records_from_reader(&master, &mut reader)?
+            .with_time_range(t_start, t_stop)
+        {
+            for (name, data) in chunk? {
+                values.entry(name).or_default().push(data.as_ref());
+            }
+        }
+        assert_eq!(reader.positions.intersection(&dz_blocks).count(), 4);
+        let time = file.expected_data(&master).context("no expected data")?;
+        let mask: BooleanArray = time
+            .as_primitive::<Float64Type>()
+            .iter()
+            .map(|t| t.map(|t| t_start <= t && t <= t_stop))
+            .collect();
+        assert_eq!(mask.true_count(), 16);
+        for name in file.channel_names() {
+            let chunks = values.get(&name).context("channel not iterated")?;
+            let arrays: Vec<&dyn Array> = chunks.iter().map(|array| array.as_ref()).collect();
+            let expected = file.expected_data(&name).context("no expected data")?;
+            assert_eq!(&concat(&arrays)?, &filter(&expected, &mask)?, "{name}");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn index_building() -> Result<()> {
+        let file = std::env::temp_dir().join("mdfr_test_index.mf4");
+        let file = file.to_str().context("invalid temp path")?;
+        fs::copy("test_files/test_basic.mf4", file)?;
+        let mut loaded = Mdf::new(file)?;
+        loaded.build_index()?;
+        let index = MdfIndex::load(file).context("index file not found")?;
+        assert!(!index.groups.is_empty());
+        loaded.load_all_channels_data_in_memory()?;
+        for (master, channel_names) in loaded.get_master_channel_names_set() {
+            let Some(master_data) = master.and_then(|m| loaded.get_channel_data(&m)) else {
+                continue;
+            };
+            let time = cast(&master_data.as_ref(), &DataType::Float64)?;
+            let time = time.as_primitive::<Float64Type>().values();
+            let Some(t_last) = time.last() else {
+                continue;
+            };
+            // window on last values, previous blocks are skipped with index
+            let t_start = t_last - 1.0;
+            let expected = time.iter().filter(|t| **t >= t_start).count();
+            let mut mdf = Mdf::new(file)?;
+            mdf.load_channels_data_in_memory_between(channel_names.clone(), t_start, *t_last)?;
+            for channel_name in channel_names.iter() {
+                if let Some(data) = mdf.get_channel_data(channel_name) {
+                    if !data.is_empty() {
+                        assert_eq!(data.len(), expected);
+                    }
+                }
+            }
+        }
+        // file rewritten with same size and modification time but another start time
+        let modified = fs::metadata(file)?.modified()?;
+        let mut bytes = fs::read(file)?;
+        bytes[64 + 72] ^= 0xFF;
+        fs::write(file, bytes)?;
+        fs::File::options()
+            .write(true)
+            .open(file)?
+            .set_modified(modified)?;
+        assert!(MdfIndex::load(file).is_none());
+        fs::remove_file(index_file_name(file))?;
+        fs::remove_file(file)?;
+        Ok(())
+    }
+
+    #[test]
+    fn data_types() -> Result<()> {
+        require_test_data!();
+        let list_of_paths = [
+            "DataTypes/ByteArray/".to_string(),
+            "DataTypes/CANopenTypes/".to_string(),
+            "DataTypes/IntegerTypes/".to_string(),
+            "DataTypes/RealTypes/".to_string(),
+            "DataTypes/StringTypes/".to_string(),
+        ];
+        let writing_mdf_file = format!("{}{}", *WRITING_MDF_FILE, "_test".to_owned()).to_owned();
+
+        // Integer testing
+        let file_name = format!(
+            "{}{}{}",
+            *BASE_PATH_MDF4, list_of_paths[2], "Vector_IntegerTypes.MF4"
+        );
+        let mut mdf = Mdf::new(&file_name)?;
+        mdf.load_all_channels_data_in_memory()?;
+        let mut vect: Vec<i64> = vec![100; 201];
+        let mut counter: i64 = 0;
+        vect.iter_mut().for_each(|v| {
+            *v -= counter;
+            counter += 1
+        });
+        if let Some(data) = mdf.get_channel_data(&"Counter_INT64_BE".to_string()) {
+            assert_eq!(
+                ChannelData::Int64(Int64Builder::new_from_buffer(vect.clone().into(), None)),
+                data.clone()
+            );
+        }
+        let mut mdf2 = mdf.write(&writing_mdf_file, false)?;
+        mdf2.load_all_channels_data_in_memory()?;
+        if let Some(data) = mdf2.get_channel_data(&"Counter_INT64_LE".to_string()) {
+            assert_eq!(
+                ChannelData::Int64(Int64Builder::new_from_buffer(vect.into(), None)),
+                data.clone()
+            );
+        }
+        let mut vect: Vec<i32> = vec![100; 201];
+        let mut counter: i32 = 0;
+        vect.iter_mut().for_each(|v| {
+            *v -= counter;
+            counter += 1
+        });
+        // error in file, cn data type is 1 but should be 3
+        // if let Some(data) = mdf.get_channel_data(&"Counter_INT32_BE".to_string()) {
+        //     assert_eq!(
+        //         PrimitiveArray::new(DataType::Int32, Buffer::from(vect.clone()), None).boxed(),
+        //         data
+        //     );
+        // }
+        if let Some(data) = mdf2.get_channel_data(&"Counter_INT32_LE".to_string()) {
+            assert_eq!(
+                ChannelData::Int32(Int32Builder::new_from_buffer(vect.into(), None)),
+                data.clone()
+            );
+        }
+        let mut vect: Vec<i16> = vec![100; 201];
+        let mut counter: i16 = 0;
+        vect.iter_mut().for_each(|v| {
+            *v -= counter;
+            counter += 1
+        });
+        if let Some(data) = mdf.get_channel_data(&"Counter_INT16_BE".to_string()) {
+            assert_eq!(
+                ChannelData::Int16(Int16Builder::new_from_buffer(vect.clone().into(), None)),
+                data.clone()
+            );
+        }
+        if let Some(data) = mdf2.get_channel_data(&"Counter_INT16_LE".to_string()) {
+            assert_eq!(
+                ChannelData::Int16(Int16Builder::new_from_buffer(vect.into(), None)),
+                data.clone()
+            );
+        }
+        // Real types
+        let file_name = format!(
+            "{}{}{}",
+            *BASE_PATH_MDF4, list_of_paths[3], "Vector_RealTypes.MF4"
+        );
+        let mut mdf = Mdf::new(&file_name)?;
+        mdf.load_all_channels_data_in_memory()?;
+        let file_name = format!(
+            "{}{}{}",
+            *BASE_PATH_MDF4, list_of_paths[3], "halffloat_sinus.mf4"
+        );
+        let mut mdf = Mdf::new(&file_name)?;
+        mdf.load_all_channels_data_in_memory()?;
+        let file_name = format!(
+            "{}{}{}",
+            *BASE_PATH_MDF4, list_of_paths[3], "dSPACE_RealTypes.mf4"
+        );
+        let mut mdf = Mdf::new(&file_name)?;
+        mdf.load_all_channels_data_in_memory()?;
+
+        // StringTypes testing
+        // UTF8
+        let mut expected_string_result = LargeStringBuilder::with_capacity(10, 6);
+        expected_string_result.append_value("zero");
+        expected_string_result.append_value("one");
+        expected_string_result.append_value("two");
+        expected_string_result.append_value("three");
+        expected_string_result.append_value("four");
+        expected_string_result.append_value("five");
+        expected_string_result.append_value("six");
+        expected_string_result.append_value("seven");
+        expected_string_result.append_value("eight");
+        expected_string_result.append_value("nine");
+        let expected_string_result = ChannelData::Utf8(expected_string_result);
+        let file_name = format!(
+            "{}{}{}",
+            *BASE_PATH_MDF4, list_of_paths[4], "Vector_FixedLengthStringUTF8.mf4"
         );
         let mut mdf = Mdf::new(&file_name)?;
         mdf.load_all_channels_data_in_memory()?;
+        if let Some(data) = mdf.get_channel_data(&"Time channel".to_string()) {
+            assert_eq!(
+                ChannelData::Float64(Float64Builder::new_from_buffer(
+                    vec![0., 1., 2., 3., 4., 5., 6., 7., 8., 9.].into(),
+                    None
+                )),
+                data.clone()
+            );
+        }
         if let Some(data) = mdf.get_channel_data(&"Data channel".to_string()) {
             assert_eq!(expected_string_result, data.clone());
         }
-
-        // Synchronization
+        let mut mdf2 = mdf.write(&writing_mdf_file, false)?;
+        mdf2.load_all_channels_data_in_memory()?;
+        if let Some(data) = mdf2.get_channel_data(&"Data channel".to_string()) {
+            assert_eq!(expected_string_result, data.clone());
+        }
+        //UTF16
+        let file_name = format!(
+            "{}{}{}",
+            *BASE_PATH_MDF4, list_of_paths[4], "Vector_FixedLengthStringUTF16_BE.mf4"
+        );
+        let mut mdf = Mdf::new(&file_name)?;
+        mdf.load_all_channels_data_in_memory()?;
+        if let Some(data) = mdf.get_channel_data(&"Data channel".to_string()) {
+            assert_eq!(expected_string_result, data.clone());
+        }
+        let mut mdf2 = mdf.write(&writing_mdf_file, false)?;
+        mdf2.load_all_channels_data_in_memory()?;
+        if let Some(data) = mdf2.get_channel_data(&"Data channel".to_string()) {
+            assert_eq!(expected_string_result, data.clone());
+        }
+        let file_name = format!(
+            "{}{}{}",
+            *BASE_PATH_MDF4, list_of_paths[4], "Vector_FixedLengthStringUTF16_LE.mf4"
+        );
+        let mut mdf = Mdf::new(&file_name)?;
+        mdf.load_all_channels_data_in_memory()?;
+        if let Some(data) = mdf.get_channel_data(&"Data channel".to_string()) {
+            assert_eq!(expected_string_result, data.clone());
+        }
+        //SBC
         let file_name = format!(
             "{}{}{}",
-            BASE_PATH_MDF4, list_of_paths[4], "Vector_SyncStreamChannel.mf4"
+            *BASE_PATH_MDF4, list_of_paths[4], "Vector_FixedLengthStringSBC.mf4"
         );
         let mut mdf = Mdf::new(&file_name)?;
         mdf.load_all_channels_data_in_memory()?;
+        if let Some(data) = mdf.get_channel_data(&"Data channel".to_string()) {
+            assert_eq!(expected_string_result, data.clone());
+        }
+        let mut mdf2 = mdf.write(&writing_mdf_file, false)?;
+        mdf2.load_all_channels_data_in_memory()?;
+        if let Some(data) = mdf2.get_channel_data(&"Data channel".to_string()) {
+            assert_eq!(expected_string_result, data.clone());
+        }
+
+        // byteArray testing
+        let file_name = format!(
+            "{}{}{}",
+            *BASE_PATH_MDF4, list_of_paths[0], "Vector_ByteArrayFixedLength.mf4"
+        );
+        let mut mdf = Mdf::new(&file_name)?;
+        let mut byte_array = FixedSizeBinaryBuilder::with_capacity(10, 5);
+        byte_array.append_value(vec![255, 255, 255, 255, 255])?;
+        byte_array.append_value(vec![18, 35, 52, 69, 86])?;
+        byte_array.append_value(vec![0, 1, 2, 3, 4])?;
+        byte_array.append_value(vec![4, 3, 2, 1, 0])?;
+        byte_array.append_value(vec![255, 254, 253, 252, 251])?;
+        byte_array.append_value(vec![250, 249, 248, 247, 246])?;
+        byte_array.append_value(vec![245, 244, 243, 242, 241])?;
+        byte_array.append_value(vec![240, 239, 238, 237, 236])?;
+        byte_array.append_value(vec![235, 234, 233, 232, 231])?;
+        byte_array.append_value(vec![255, 255, 255, 255, 255])?;
+        mdf.load_all_channels_data_in_memory()?;
+        if let Some(data) = mdf.get_channel_data(&"Time channel".to_string()) {
+            assert_eq!(
+                ChannelData::Float64(Float64Builder::new_from_buffer(
+                    vec![0., 1., 2., 3., 4., 5., 6., 7., 8., 9.].into(),
+                    None
+                )),
+                data.clone()
+            );
+        }
+        if let Some(data) = mdf.get_channel_data(&"Data channel".to_string()) {
+            assert_eq!(&ChannelData::FixedSizeByteArray(byte_array), data);
+        }
+        let mut mdf2 = mdf.write(&writing_mdf_file, false)?;
+        mdf2.load_all_channels_data_in_memory()?;
+        if let Some(data) = mdf2.get_channel_data(&"Data channel".to_string()) {
+            let mut byte_array = FixedSizeBinaryBuilder::with_capacity(10, 5);
+            byte_array.append_value(vec![255, 255, 255, 255, 255])?;
+            byte_array.append_value(vec![18, 35, 52, 69, 86])?;
+            byte_array.append_value(vec![0, 1, 2, 3, 4])?;
+            byte_array.append_value(vec![4, 3, 2, 1, 0])?;
+            byte_array.append_value(vec![255, 254, 253, 252, 251])?;
+            byte_array.append_value(vec![250, 249, 248, 247, 246])?;
+            byte_array.append_value(vec![245, 244, 243, 242, 241])?;
+            byte_array.append_value(vec![240, 239, 238, 237, 236])?;
+            byte_array.append_value(vec![235, 234, 233, 232, 231])?;
+            byte_array.append_value(vec![255, 255, 255, 255, 255])?;
+            assert_eq!(&ChannelData::FixedSizeByteArray(byte_array), data);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn channel_types() -> Result<()> {
+        require_test_data!();
+        let list_of_paths = [
+            "ChannelTypes/MasterChannels/".to_string(),
+            "ChannelTypes/MLSD/".to_string(),
+            "ChannelTypes/VLSD/".to_string(),
+            "ChannelTypes/VirtualData/".to_string(),
+            "ChannelTypes/Synchronization/".to_string(),
+        ];
+
+        // MasterTypes testing
+        let mut vect: Vec<f64> = vec![0.; 101];
+        let mut counter: f64 = 0.;
+        vect.iter_mut().for_each(|v| {
+            *v = counter.clone() * 0.03;
+            counter += 1.
+        });
+        let expected_master =
+            ChannelData::Float64(Float64Builder::new_from_buffer(vect.into(), None));
+        let file_name = format!(
+            "{}{}{}",
+            *BASE_PATH_MDF4, list_of_paths[0], "Vector_VirtualTimeMasterChannel.mf4"
+        );
+        let mut mdf = Mdf::new(&file_name)?;
+        mdf.load_all_channels_data_in_memory()?;
+        if let Some(data) = mdf.get_channel_data(&"Time channel".to_string()) {
+            assert_eq!(expected_master, data.clone());
+        }
+        let file_name = format!(
+            "{}{}{}",
+            *BASE_PATH_MDF4, list_of_paths[0], "Vector_DifferentMasterChannels.mf4"
+        );
+        let mut mdf = Mdf::new(&file_name)?;
+        mdf.load_all_channels_data_in_memory()?;
+        if let Some(data) = mdf.get_channel_data(&"Time channel".to_string()) {
+            assert_eq!(expected_master, data.clone());
+        }
+        let file_name = format!(
+            "{}{}{}",
+            *BASE_PATH_MDF4, list_of_paths[0], "Vector_NoMasterChannel.mf4"
+        );
+        let mut mdf = Mdf::new(&file_name)?;
+        mdf.load_all_channels_data_in_memory()?;
+        if let Some(data) = mdf.get_channel_data(&"Time channel".to_string()) {
+            assert_eq!(expected_master, data.clone());
+        }
+
+        // MLSD testing
+        let mut expected_string_result = LargeStringBuilder::with_capacity(10, 6);
+        expected_string_result.append_value("zero");
+        expected_string_result.append_value("one");
+        expected_string_result.append_value("two");
+        expected_string_result.append_value("three");
+        expected_string_result.append_value("four");
+        expected_string_result.append_value("five");
+        expected_string_result.append_value("six");
+        expected_string_result.append_value("seven");
+        expected_string_result.append_value("eight");
+        expected_string_result.append_value("nine");
+        let expected_string_result = ChannelData::Utf8(expected_string_result);
+        let file_name = format!(
+            "{}{}{}",
+            *BASE_PATH_MDF4, list_of_paths[1], "Vector_MLSDStringUTF8.mf4"
+        );
+        let mut mdf = Mdf::new(&file_name)?;
+        mdf.load_all_channels_data_in_memory()?;
+        if let Some(data) = mdf.get_channel_data(&"Data channel".to_string()) {
+            assert_eq!(expected_string_result, data.clone());
+        }
+        let file_name = format!(
+            "{}{}{}",
+            *BASE_PATH_MDF4, list_of_paths[1], "Vector_MLSDStringSBC.mf4"
+        );
+        let mut mdf = Mdf::new(&file_name)?;
+        mdf.load_all_channels_data_in_memory()?;
+        if let Some(data) = mdf.get_channel_data(&"Data channel".to_string()) {
+            assert_eq!(expected_string_result, data.clone());
+        }
+        let file_name = format!(
+            "{}{}{}",
+            *BASE_PATH_MDF4, list_of_paths[1], "Vector_MLSDStringUTF16_BE.mf4"
+        );
+        let mut mdf = Mdf::new(&file_name)?;
+        mdf.load_all_channels_data_in_memory()?;
+        if let Some(data) = mdf.get_channel_data(&"Data channel".to_string()) {
+            assert_eq!(expected_string_result, data.clone());
+        }
+        let file_name = format!(
+            "{}{}{}",
+            *BASE_PATH_MDF4, list_of_paths[1], "Vector_MLSDStringUTF16_LE.mf4"
+        );
+        let mut mdf = Mdf::new(&file_name)?;
+        mdf.load_all_channels_data_in_memory()?;
+        if let Some(data) = mdf.get_channel_data(&"Data channel".to_string()) {
+            assert_eq!(expected_string_result, data.clone());
+        }
+
+        // Virtual data testing
+        let mut vect: Vec<u64> = vec![0; 200];
+        let mut counter: u64 = 0;
+        vect.iter_mut().for_each(|v| {
+            *v += counter;
+            counter += 1
+        });
+        let virtal_vect = UInt64Builder::new_from_buffer(vect.into(), None);
+        let file_name = format!(
+            "{}{}{}",
+            *BASE_PATH_MDF4, list_of_paths[3], "Vector_VirtualDataChannelNoConversion.mf4"
+        );
+        let mut mdf = Mdf::new(&file_name)?;
+        mdf.load_all_channels_data_in_memory()?;
+        if let Some(data) = mdf.get_channel_data(&"Data channel".to_string()) {
+            assert_eq!(ChannelData::UInt64(virtal_vect), *data);
+        }
+        let mut vect: Vec<f64> = vec![100.0f64; 200];
+        let mut counter: f64 = 0.0;
+        vect.iter_mut().for_each(|v| {
+            *v += counter;
+            counter -= 2.0
+        });
+        let virtal_linear_vect = Float64Builder::new_from_buffer(vect.into(), None);
+        let file_name = format!(
+            "{}{}{}",
+            *BASE_PATH_MDF4, list_of_paths[3], "Vector_VirtualDataChannelLinearConversion.mf4"
+        );
+        let mut mdf = Mdf::new(&file_name)?;
+        mdf.load_all_channels_data_in_memory()?;
+        if let Some(data) = mdf.get_channel_data(&"Data channel".to_string()) {
+            assert_eq!(ChannelData::Float64(virtal_linear_vect), *data);
+        }
+        let file_name = format!(
+            "{}{}{}",
+            *BASE_PATH_MDF4, list_of_paths[3], "Vector_VirtualDataChannelConstantConversion.mf4"
+        );
+        let mut mdf = Mdf::new(&file_name)?;
+        mdf.load_all_channels_data_in_memory()?;
+        if let Some(data) = mdf.get_channel_data(&"Data channel".to_string()) {
+            assert_eq!(
+                ChannelData::Float64(Float64Builder::new_from_buffer(
+                    vec![42f64; 200].into(),
+                    None
+                )),
+                *data
+            );
+        }
+        // VLSD testing
+        let file_name = format!(
+            "{}{}{}",
+            *BASE_PATH_MDF4, list_of_paths[2], "Vector_VLSDStringUTF8.mf4"
+        );
+        let mut mdf = Mdf::new(&file_name)?;
+        mdf.load_all_channels_data_in_memory()?;
+        if let Some(data) = mdf.get_channel_data(&"Data channel".to_string()) {
+            assert_eq!(expected_string_result, data.clone());
+        }
+        let file_name = format!(
+            "{}{}{}",
+            *BASE_PATH_MDF4, list_of_paths[2], "Vector_VLSDStringUTF16_LE.mf4"
+        );
+        let mut mdf = Mdf::new(&file_name)?;
+        mdf.load_all_channels_data_in_memory()?;
+        if let Some(data) = mdf.get_channel_data(&"Data channel".to_string()) {
+            assert_eq!(expected_string_result, data.clone());
+        }
+        let file_name = format!(
+            "{}{}{}",
+            *BASE_PATH_MDF4, list_of_paths[2], "Vector_VLSDStringUTF16_BE.mf4"
+        );
+        let mut mdf = Mdf::new(&file_name)?;
+        mdf.load_all_channels_data_in_memory()?;
+        if let Some(data) = mdf.get_channel_data(&"Data channel".to_string()) {
+            assert_eq!(expected_string_result, data.clone());
+        }
+
+        // Synchronization
+        let file_name = format!(
+            "{}{}{}",
+            *BASE_PATH_MDF4, list_of_paths[4], "Vector_SyncStreamChannel.mf4"
+        );
+        let mut mdf = Mdf::new(&file_name)?;
+        mdf.load_all_channels_data_in_memory()?;
+        Ok(())
+    }
+    #[test]
+    fn record_layout() -> Result<()> {
+        require_test_data!();
+        // Overlapping signals
+        let file_name = format!(
+            "{}{}",
+            *BASE_PATH_MDF4, "RecordLayout/Vector_NotByteAligned.mf4"
+        );
+        let mut mdf = Mdf::new(&file_name)?;
+        mdf.load_all_channels_data_in_memory()?;
+        if let Some(data) = mdf.get_channel_data(&"Channel B".to_string()) {
+            let mut vect: Vec<u64> = vec![0; 30];
+            let mut counter: u64 = 0;
+            vect.iter_mut().for_each(|v| {
+                *v += counter;
+                counter += 1
+            });
+            assert_eq!(
+                ChannelData::UInt64(UInt64Builder::new_from_buffer(vect.into(), None)),
+                data.clone()
+            );
+        }
+
+        // Overlapping signals
+        let file_name = format!(
+            "{}{}",
+            *BASE_PATH_MDF4, "RecordLayout/Vector_OverlappingSignals.mf4"
+        );
+        let mut mdf = Mdf::new(&file_name)?;
+        mdf.load_all_channels_data_in_memory()?;
+        Ok(())
+    }
+    #[test]
+    fn data_list() -> Result<()> {
+        require_test_data!();
+        // Equal length
+        let file_name = format!("{}{}", *BASE_PATH_MDF4, "DataList/Vector_DT_EqualLen.MF4");
+        let mut mdf = Mdf::new(&file_name)?;
+        mdf.load_all_channels_data_in_memory()?;
+        if let Some(data) = mdf.get_channel_data(&"channel1".to_string()) {
+            assert_eq!(data.len(), 254552);
+        }
+        // Equal length
+        let file_name = format!(
+            "{}{}",
+            *BASE_PATH_MDF4, "DataList/Vector_DL_Linked_List.MF4"
+        );
+        let mut mdf = Mdf::new(&file_name)?;
+        mdf.load_all_channels_data_in_memory()?;
+        if let Some(data) = mdf.get_channel_data(&"channel1".to_string()) {
+            assert_eq!(data.len(), 254552);
+        }
+
+        // Empty data
+        let file_name = format!("{}{}", *BASE_PATH_MDF4, "DataList/ETAS_EmptyDL.mf4");
+        let mut mdf = Mdf::new(&file_name)?;
+        mdf.load_all_channels_data_in_memory()?;
+
+        // SD List
+        let file_name = format!("{}{}", *BASE_PATH_MDF4, "DataList/Vector_SD_List.MF4");
+        let mut mdf = Mdf::new(&file_name)?;
+        mdf.load_all_channels_data_in_memory()?;
+        Ok(())
+    }
+    #[test]
+    fn compressed_data() -> Result<()> {
+        require_test_data!();
+        // Single DZ deflate
+        let file_name = format!(
+            "{}{}",
+            *BASE_PATH_MDF4, "CompressedData/Simple/Vector_SingleDZ_Deflate.mf4"
+        );
+        let mut mdf = Mdf::new(&file_name)?;
+        mdf.load_all_channels_data_in_memory()?;
+
+        // Single DZ transpose deflate
+        let file_name = format!(
+            "{}{}",
+            *BASE_PATH_MDF4, "CompressedData/Simple/Vector_SingleDZ_TransposeDeflate.mf4"
+        );
+        let mut mdf = Mdf::new(&file_name)?;
+        mdf.load_all_channels_data_in_memory()?;
+
+        // deflate data list
+        let file_name = format!(
+            "{}{}",
+            *BASE_PATH_MDF4, "CompressedData/DataList/Vector_DataList_Deflate.mf4"
+        );
+        let mut mdf = Mdf::new(&file_name)?;
+        mdf.load_all_channels_data_in_memory()?;
+        if let Some(data) = mdf.get_channel_data(&"Time channel".to_string()) {
+            let mut vect: Vec<f64> = vec![0.; 10000];
+            let mut counter: u64 = 0;
+            vect.iter_mut().for_each(|v| {
+                *v = (counter.clone() as f64) / 10.0;
+                counter += 1;
+            });
+            assert_eq!(
+                &ChannelData::Float64(Float64Builder::new_from_buffer(vect.into(), None)),
+                data
+            );
+        }
+
+        // transpose deflate data list
+        let file_name = format!(
+            "{}{}",
+            *BASE_PATH_MDF4, "CompressedData/DataList/Vector_DataList_TransposeDeflate.mf4"
+        );
+        let mut mdf = Mdf::new(&file_name)?;
+        mdf.load_all_channels_data_in_memory()?;
+        if let Some(data) = mdf.get_channel_data(&"Time channel".to_string()) {
+            let mut vect: Vec<f64> = vec![0.; 10000];
+            let mut counter: u64 = 0;
+            vect.iter_mut().for_each(|v| {
+                *v = (counter.clone() as f64) / 10.0;
+                counter += 1;
+            });
+            assert_eq!(
+                &ChannelData::Float64(Float64Builder::new_from_buffer(vect.into(), None)),
+                data
+            );
+        }
+
+        // Unsorted
+        let file_name = format!(
+            "{}{}",
+            *BASE_PATH_MDF4, "CompressedData/Unsorted/Vector_SingleDZ_Unsorted.MF4"
+        );
+        let mut mdf = Mdf::new(&file_name)?;
+        mdf.load_all_channels_data_in_memory()?;
+        Ok(())
+    }
+
+    #[test]
+    fn transposed_dz_block() -> Result<()> {
+        // 7 records of 5 bytes and a tail of 3 bytes not transposed
+        let original: Vec<u8> = (0..38).collect();
+        let mut transposed: Vec<u8> = (0..5)
+            .flat_map(|column| (0..7).map(move |row| (row * 5 + column) as u8))
+            .collect();
+        transposed.extend_from_slice(&original[35..]);
+        let compressed = compress(&transposed, Format::Zlib, CompressionLevel::Default)
+            .map_err(|e| anyhow::anyhow!("{:?}", e))?;
+        let dz_block = |zip_type: u8| -> Vec<u8> {
+            let mut block = vec![0u8; 4];
+            block.extend_from_slice(&(48 + compressed.len() as u64).to_le_bytes());
+            block.extend_from_slice(&0u64.to_le_bytes());
+            block.extend_from_slice(b"DT");
+            block.extend_from_slice(&[zip_type, 0]);
+            block.extend_from_slice(&5u32.to_le_bytes());
+            block.extend_from_slice(&(original.len() as u64).to_le_bytes());
+            block.extend_from_slice(&(compressed.len() as u64).to_le_bytes());
+            block.extend_from_slice(&compressed);
+            block
+        };
+        let (data, _block) = parse_dz(&mut io::BufReader::new(io::Cursor::new(dz_block(1))))?;
+        assert_eq!(data, original);
+        let (data, _block) = parse_dz(&mut io::BufReader::new(io::Cursor::new(dz_block(0))))?;
+        assert_eq!(data, transposed);
+        let result = parse_dz(&mut io::BufReader::new(io::Cursor::new(dz_block(2))));
+        assert!(matches!(
+            result.map_err(|e| e.downcast::<MdfError>()),
+            Err(Ok(MdfError::UnsupportedBlock(_)))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn unsorted_data() -> Result<()> {
+        require_test_data!();
+        let file_name = format!(
+            "{}{}",
+            *BASE_PATH_MDF4, "UnsortedData/Vector_Unsorted_VLSD.MF4"
+        );
+        let mut mdf = Mdf::new(&file_name)?;
+        mdf.load_all_channels_data_in_memory()?;
+        Ok(())
+    }
+    #[test]
+    fn conversion() -> Result<()> {
+        require_test_data!();
+        let list_of_paths = [
+            "Conversion/LinearConversion/".to_string(),
+            "Conversion/LookUpConversion/".to_string(),
+            "Conversion/PartialConversion/".to_string(),
+            "Conversion/RationalConversion/".to_string(),
+            "Conversion/StringConversion/".to_string(),
+            "Conversion/TextConversion/".to_string(),
+        ];
+
+        // Lindear conversion testing
+        let file_name = format!(
+            "{}{}{}",
+            *BASE_PATH_MDF4, list_of_paths[0], "Vector_LinearConversion.mf4"
+        );
+        let mut mdf = Mdf::new(&file_name)?;
+        mdf.load_all_channels_data_in_memory()?;
+        if let Some(data) = mdf.get_channel_data(&"Data channel".to_string()) {
+            let mut vect: Vec<f64> = vec![0.; 10];
+            let mut counter: f64 = 0.;
+            vect.iter_mut().for_each(|v| {
+                *v = counter.clone() * -3.2 - 4.8;
+                counter += 1.
+            });
+            assert_eq!(
+                &ChannelData::Float64(Float64Builder::new_from_buffer(vect.into(), None)),
+                data
+            );
+        }
+        let file_name = format!(
+            "{}{}{}",
+            *BASE_PATH_MDF4, list_of_paths[0], "Vector_LinearConversionFactor0.mf4"
+        );
+        let mut mdf = Mdf::new(&file_name)?;
+        mdf.load_all_channels_data_in_memory()?;
+        if let Some(data) = mdf.get_channel_data(&"Data channel".to_string()) {
+            // integer offset and null factor keep integer raw data as integers
+            let vect: Vec<f64> = vec![3.; 10];
+            assert_eq!(
+                cast(&data.as_ref(), &DataType::Float64)?.as_ref(),
+                &Float64Array::from(vect) as &dyn Array
+            );
+        }
+        // Rational conversion
+        let file_name = format!(
+            "{}{}{}",
+            *BASE_PATH_MDF4, list_of_paths[3], "Vector_RationalConversionIntParams.mf4"
+        );
+        let mut mdf = Mdf::new(&file_name)?;
+        mdf.load_all_channels_data_in_memory()?;
+
+        // Text conversion
+        let file_name = format!(
+            "{}{}{}",
+            *BASE_PATH_MDF4, list_of_paths[5], "Vector_AlgebraicConversionQuadratic.mf4"
+        );
+        let mut mdf = Mdf::new(&file_name)?;
+        mdf.load_all_channels_data_in_memory()?;
+        if let Some(data) = mdf.get_channel_data(&"Data channel".to_string()) {
+            let vect = Vec::from([1., 2., 5., 10., 17., 26., 37., 50., 65., 82.]);
+            assert_eq!(
+                &ChannelData::Float64(Float64Builder::new_from_buffer(vect.into(), None)),
+                data
+            );
+        }
+
+        // Lookup conversion : Value to Value Table With Interpolation
+        let file_name = format!(
+            "{}{}{}",
+            *BASE_PATH_MDF4, list_of_paths[1], "Vector_Value2ValueConversionInterpolation.mf4"
+        );
+        let mut mdf = Mdf::new(&file_name)?;
+        mdf.load_all_channels_data_in_memory()?;
+        if let Some(data) = mdf.get_channel_data(&"Data channel".to_string()) {
+            let vect = Vec::from([
+                -5.,
+                -5.,
+                -5.,
+                -5.,
+                -4.5,
+                -4.,
+                -3.5,
+                -3.,
+                -2.5,
+                -2.,
+                -4. / 3.,
+                -2. / 3.,
+                0.,
+                1. / 3.,
+                2. / 3.,
+                1.,
+                1.5,
+                2.,
+                1.,
+                0.,
+                1.5,
+                3.,
+                4.5,
+                6.,
+                4.5,
+                3.,
+                1.5,
+                0.,
+                0.,
+                0.,
+            ]);
+            assert_eq!(
+                &ChannelData::Float64(Float64Builder::new_from_buffer(vect.into(), None)),
+                data
+            );
+        }
+
+        // Lookup conversion : Value to Value Table Without Interpolation
+        let file_name = format!(
+            "{}{}{}",
+            *BASE_PATH_MDF4, list_of_paths[1], "Vector_Value2ValueConversionNoInterpolation.mf4"
+        );
+        let mut mdf = Mdf::new(&file_name)?;
+        mdf.load_all_channels_data_in_memory()?;
+        if let Some(data) = mdf.get_channel_data(&"Data channel".to_string()) {
+            let vect = Vec::from([
+                -5., -5., -5., -5., -5., -5., -5., -2., -2., -2., -2., 0., 0., 0., 1., 1., 1., 2.,
+                2., 0., 0., 3., 3., 6., 6., 3., 3., 0., 0., 0.,
+            ]);
+            assert_eq!(
+                &ChannelData::Float64(Float64Builder::new_from_buffer(vect.into(), None)),
+                data
+            );
+        }
+
+        // Lookup conversion : Value Range to Value
+        let file_name = format!(
+            "{}{}{}",
+            *BASE_PATH_MDF4, list_of_paths[1], "Vector_ValueRange2ValueConversion.mf4"
+        );
+        let mut mdf = Mdf::new(&file_name)?;
+        mdf.load_all_channels_data_in_memory()?;
+        if let Some(data) = mdf.get_channel_data(&"Data channel".to_string()) {
+            let vect = Vec::from([
+                -1.0, -1.0, -1.0, -1.0, -1.0, -1.0, 0.0, 0.0, 0.0, 1.0, 1.0, 2.0, 2.0, 2.0, 2.0,
+                2.0, 3.0, 3.0, 5.0, 5.0, 5.0, 6.0, 7.0, 7.0, 8.0, 8.0, 9.0, 9.0, 9.0, 9.0,
+            ]);
+            assert_eq!(
+                &ChannelData::Float64(Float64Builder::new_from_buffer(vect.into(), None)),
+                data
+            );
+        }
+
+        // Lookup conversion : Value to Text
+        let file_name = format!(
+            "{}{}{}",
+            *BASE_PATH_MDF4, list_of_paths[1], "Vector_Value2TextConversion.mf4"
+        );
+        let mut mdf = Mdf::new(&file_name)?;
+        mdf.load_all_channels_data_in_memory()?;
+        if let Some(data) = mdf.get_channel_data(&"Data channel".to_string()) {
+            let target: DictionaryArray<UInt32Type> = [
+                "No match",
+                "first gear",
+                "second gear",
+                "third gear",
+                "fourth gear",
+                "fifth gear",
+                "No match",
+                "No match",
+                "No match",
+                "No match",
+            ]
+            .into_iter()
+            .collect();
+            assert_eq!(&ChannelData::Dictionary(target), data);
+        }
+
+        // Lookup conversion : Value range to Text
+        let file_name = format!(
+            "{}{}{}",
+            *BASE_PATH_MDF4, list_of_paths[1], "Vector_ValueRange2TextConversion.mf4"
+        );
+        let mut mdf = Mdf::new(&file_name)?;
+        mdf.load_all_channels_data_in_memory()?;
+        if let Some(data) = mdf.get_channel_data(&"Data channel".to_string()) {
+            let target: DictionaryArray<UInt32Type> = [
+                "Out of range",
+                "very low",
+                "very low",
+                "very low",
+                "low",
+                "low",
+                "medium",
+                "medium",
+                "high",
+                "high",
+            ]
+            .into_iter()
+            .collect();
+            assert_eq!(&ChannelData::Dictionary(target), data);
+        }
+
+        // Lookup conversion : Value range to Text,
+        let file_name = format!(
+            "{}{}{}",
+            *BASE_PATH_MDF4, list_of_paths[2], "Vector_StatusStringTableConversionAlgebraic.mf4"
+        );
+        let mut mdf = Mdf::new(&file_name)?;
+        mdf.load_all_channels_data_in_memory()?;
+        if let Some(data) = mdf.get_channel_data(&"Data channel".to_string()) {
+            // partial conversion: numeric values in data, text statuses apart
+            let data_values = data.finish_cloned();
+            let data_values = data_values.as_primitive::<Float64Type>();
+            let status = mdf
+                .get_channel_status("Data channel")
+                .context("status of partial conversion missing")?
+                .finish_cloned();
+            let status_values = status
+                .as_dictionary::<UInt32Type>()
+                .downcast_dict::<StringArray>()
+                .context("dictionary values are not strings")?
+                .into_iter()
+                .collect::<Vec<Option<&str>>>();
+            assert_eq!(data_values.value(0), 10.0 / (0.0 - 10.0));
+            assert_eq!(status_values[0], None);
+            assert!(data_values.is_null(101));
+            assert_eq!(status_values[101], Some("Illegal value"));
+            assert!(data_values.is_null(299));
+            assert_eq!(status_values[299], Some("Out of range"));
+        }
+
+        // Text conversion : Text to Value
+        let file_name = format!(
+            "{}{}{}",
+            *BASE_PATH_MDF4, list_of_paths[4], "Vector_Text2ValueConversion.mf4"
+        );
+        let mut mdf = Mdf::new(&file_name)?;
+        mdf.load_all_channels_data_in_memory()?;
+        if let Some(data) = mdf.get_channel_data(&"Data channel".to_string()) {
+            let vect = Vec::from([-50., 1., 2., 3., 4., 5., 6., 7., 8., 9.]);
+            assert_eq!(
+                &ChannelData::Float64(Float64Builder::new_from_buffer(vect.into(), None)),
+                data
+            );
+        }
+
+        // Text conversion : Text to Text
+        let file_name = format!(
+            "{}{}{}",
+            *BASE_PATH_MDF4, list_of_paths[4], "Vector_Text2TextConversion.mf4"
+        );
+        let mut mdf = Mdf::new(&file_name)?;
+        mdf.load_all_channels_data_in_memory()?;
+        if let Some(data) = mdf.get_channel_data(&"Data channel".to_string()) {
+            let mut target = LargeStringBuilder::with_capacity(10, 20);
+            target.append_value("No translation");
+            target.append_value("Eins");
+            target.append_value("Zwei");
+            target.append_value("Drei");
+            target.append_value("Vier");
+            target.append_value("Fünf");
+            target.append_value("Sechs");
+            target.append_value("Sieben");
+            target.append_value("Acht");
+            target.append_value("Neun");
+            assert_eq!(&ChannelData::Utf8(target), data);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn bus_logging() -> Result<()> {
+        require_test_data!();
+        // sort bus
+        let file_name = format!(
+            "{}{}",
+            *BASE_PATH_MDF4, "BusLogging/Vector_CAN_DataFrame_Sort_ID.MF4"
+        );
+        let mut mdf = Mdf::new(&file_name)?;
+        mdf.load_all_channels_data_in_memory()?;
+        if let Some(data) = mdf.get_channel_data(
+            &"CAN_DataFrame.ID CAN_DataFrame_101 CANReplay_7_5 Message".to_string(),
+        ) {
+            let vect: Vec<f64> = vec![101.; 79];
+            assert_eq!(
+                ChannelData::Float64(Float64Builder::new_from_buffer(vect.into(), None)),
+                *data
+            );
+        }
+        // decoding frames into signals
+        let dbc = Dbc::parse(
+            "BO_ 101 Message101: 8 Vector__XXX\n SG_ FirstByte : 0|8@1+ (1,0) [0|255] \"-\" Vector__XXX\n",
+        )?;
+        let added = decode_can_frames(&mut mdf, &dbc)?;
+        assert!(added.contains(&"FirstByte".to_string()));
+        if let Some(data) = mdf.get_channel_data("FirstByte") {
+            assert_eq!(data.len(), 79);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn can_dbc_decoding() -> Result<()> {
+        let dbc = Dbc::parse(
+            r#"VERSION ""
+
+BO_ 2364540158 EEC1: 8 Vector__XXX
+ SG_ EngineSpeed : 24|16@1+ (0.125,0) [0|8031.875] "rpm" Vector__XXX
+ SG_ Torque : 8|8@1- (1,-125) [-125|125] "%" Vector__XXX
+
+BO_ 256 Motorola: 8 Vector__XXX
+ SG_ Mux M : 7|8@0+ (1,0) [0|255] "" Vector__XXX
+ SG_ Value m1 : 15|12@0+ (0.5,10) [0|0] "V" Vector__XXX
+
+CM_ SG_ 2364540158 EngineSpeed "actual engine speed";
+"#,
+        )?;
+        let eec1 = dbc.messages.get(&0x0CF004FE).context("message not found")?;
+        assert_eq!(eec1.name, "EEC1");
+        let speed = &eec1.signals[0];
+        assert_eq!(speed.unit, "rpm");
+        assert_eq!(speed.comment.as_deref(), Some("actual engine speed"));
+        let payload = [0u8, 0xFE, 0, 0x40, 0x1F, 0, 0, 0];
+        let raw = speed.raw_value(&payload).context("payload too short")?;
+        assert_eq!(speed.physical_value(raw), 1000.0);
+        let torque = &eec1.signals[1];
+        let raw = torque.raw_value(&payload).context("payload too short")?;
+        assert_eq!(torque.physical_value(raw), -127.0);
+        // big endian signal spread over two bytes
+        let motorola = dbc.messages.get(&256).context("message not found")?;
+        let payload = [1u8, 0x12, 0x30];
+        assert_eq!(motorola.signals[0].raw_value(&payload), Some(1));
+        let value = &motorola.signals[1];
+        let raw = value.raw_value(&payload).context("payload too short")?;
+        assert_eq!(raw, 0x123);
+        assert_eq!(value.physical_value(raw), 0x123 as f64 * 0.5 + 10.0);
+        assert_eq!(value.raw_value(&payload[..2]), None);
+        Ok(())
+    }
+    #[test]
+    fn writing_mdf4() -> Result<()> {
+        require_test_data!();
+        // write file with invalid channels
+        let file = format!(
+            "{}{}",
+            *BASE_PATH_MDF4, &"Simple/PCV_iO_Gen3_LK1__3l_TDI.mf4"
+        );
+        let ref_channel = r"NO";
+        let mut mdf = Mdf::new(&file)?;
+        mdf.load_all_channels_data_in_memory()?;
+        // without compression
+        let mut info2 = mdf.write(&WRITING_MDF_FILE, false)?;
+        info2.load_all_channels_data_in_memory()?;
+        if let Some(data) = mdf.get_channel_data(&ref_channel.to_string()) {
+            if let Some(data2) = info2.get_channel_data(&ref_channel.to_string()) {
+                assert_eq!(*data2, *data);
+            } else {
+                panic!("Channel not found");
+            }
+        } else {
+            panic!("Channel not found");
+        }
+        // with compression
+        let mut info2 = mdf.write(&WRITING_MDF_FILE, true)?;
+        info2.load_all_channels_data_in_memory()?;
+        if let Some(data) = mdf.get_channel_data(&ref_channel.to_string()) {
+            if let Some(data2) = info2.get_channel_data(&ref_channel.to_string()) {
+                assert_eq!(*data2, *data);
+            } else {
+                panic!("Channel not found");
+            }
+        } else {
+            panic!("Channel not found");
+        }
+
+        // write file with many channels
+        let file = format!("{}{}", *BASE_PATH_MDF4, &"Simple/test.mf4");
+        let ref_channel = r"C90 CG21 in error.mdf";
+        let mut mdf = Mdf::new(&file)?;
+        mdf.load_all_channels_data_in_memory()?;
+        // with compression
+        let mut info2 = mdf.write(&WRITING_MDF_FILE, true)?;
+        info2.load_all_channels_data_in_memory()?;
+        if let Some(data) = mdf.get_channel_data(&ref_channel.to_string()) {
+            if let Some(data2) = info2.get_channel_data(&ref_channel.to_string()) {
+                assert_eq!(*data2, *data);
+            } else {
+                panic!("Channel not found");
+            }
+        } else {
+            panic!("Channel not found");
+        }
+        // without compression
+        let mut info2 = mdf.write(&WRITING_MDF_FILE, false)?;
+        info2.load_all_channels_data_in_memory()?;
+        if let Some(data) = mdf.get_channel_data(&ref_channel.to_string()) {
+            if let Some(data2) = info2.get_channel_data(&ref_channel.to_string()) {
+                assert_eq!(*data2, *data);
+            } else {
+                panic!("Channel not found");
+            }
+        } else {
+            panic!("Channel not found");
+        }
+        // TODO test write file with arrays
+
+        //mdf3 conversion
+        drop(mdf);
+        let file = format!(
+            "{}{}",
+            *BASE_PATH_MDF3, &"RJ_N16-12-363_BM-15C-0024_228_2_20170116094355_CAN.dat"
+        );
+        let mut mdf = Mdf::new(&file)?;
+        mdf.load_all_channels_data_in_memory()?;
+        let channel_name3 = r"TEMP_FUEL";
+        let mut mdf4 = mdf.write(&WRITING_MDF_FILE, true)?;
+        mdf4.load_all_channels_data_in_memory()?;
+        let mdf3_data = mdf.get_channel_data(&channel_name3);
+        let mdf4_data = mdf4.get_channel_data(&channel_name3);
+        assert_eq!(mdf3_data, mdf4_data);
+        Ok(())
+    }
+    #[test]
+    fn mdf3_source_conversion() -> Result<()> {
+        let file_name = "test_files/test_mdf3.mdf";
+        let mut mdf = Mdf::new(file_name)?;
+        let MdfInfo::V3(info3) = &mdf.mdf_info else {
+            panic!("mdf3 file expected")
+        };
+        // channel with a channel extension block and its master
+        let (channel, master) = info3
+            .dg
+            .values()
+            .flat_map(|dg| dg.cg.values())
+            .find_map(|cg| {
+                cg.cn
+                    .values()
+                    .find(|cn| {
+                        Some(&cn.unique_name) != cg.master_channel_name.as_ref()
+                            && info3.sharable.ce.contains_key(&cn.block1.cn_ce_source)
+                    })
+                    .map(|cn| (cn.unique_name.clone(), cg.master_channel_name.clone()))
+            })
+            .context("no channel with extension block")?;
+        let mut channels = HashSet::from([channel.clone()]);
+        channels.extend(master);
+        mdf.load_channels_data_in_memory(channels.clone())?;
+        let MdfInfo::V3(info3) = &mdf.mdf_info else {
+            panic!("mdf3 file expected")
+        };
+        let info4 = convert3to4(info3, file_name)?;
+        let mut mdf4 = Mdf {
+            mdf_info: MdfInfo::V4(Box::new(info4)),
+            stats: HashMap::new(),
+            master_fixes: Vec::new(),
+        };
+        for name in mdf4.get_channel_names_set().difference(&channels) {
+            mdf4.remove_channel(name);
+        }
+        let writing_file = std::env::temp_dir().join("mdfr_mdf3_source.mf4");
+        let writing_file = writing_file.to_str().context("invalid temp path")?;
+        mdf4.write(writing_file, false)?;
+        let written = Mdf::new(writing_file)?;
+        fs::remove_file(writing_file)?;
+        let MdfInfo::V4(info) = &written.mdf_info else {
+            panic!("mdf4 file expected")
+        };
+        let (_master, dg_pos, (_cg_pos, rec_id), (_cn_pos, rec_pos)) = info
+            .get_channel_id(&channel)
+            .context("converted channel missing")?;
+        let cn = &info.dg[dg_pos].cg[rec_id].cn[rec_pos];
+        let si = info
+            .sharable
+            .si
+            .get(&cn.block.cn_si_source)
+            .context("source information missing")?;
+        // DIM extension is an ECU, CAN extension a bus source
+        assert!(si.si_type == 1 || (si.si_type == 2 && si.si_bus_type == 2));
+        // same source information from both versions
+        assert!(mdf.get_channel_source(&channel)?.is_some());
+        assert_eq!(
+            written.get_channel_source(&channel)?,
+            mdf.get_channel_source(&channel)?
+        );
+        Ok(())
+    }
+    #[test]
+    fn writing_mdf3() -> Result<()> {
+        let file_name = "test_files/test_mdf3.mdf";
+        let mut mdf = Mdf::new(file_name)?;
+        let writing_file = std::env::temp_dir().join("mdfr_writing_mdf3.mdf");
+        let writing_file = writing_file.to_str().context("invalid temp path")?;
+        let mut mdf3 = mdf.write_mdf3(writing_file)?;
+        assert_eq!(mdf3.get_version(), mdf.get_version());
+        // duplicated channel names are made unique with block position, changed in new file
+        let channel_names = mdf.get_channel_names_set();
+        assert_eq!(mdf3.get_channel_names_set().len(), channel_names.len());
+        mdf.load_all_channels_data_in_memory()?;
+        mdf3.load_all_channels_data_in_memory()?;
+        for channel_name in channel_names.intersection(&mdf3.get_channel_names_set()) {
+            assert_eq!(
+                mdf.get_channel_data(&channel_name),
+                mdf3.get_channel_data(&channel_name)
+            );
+            assert_eq!(
+                mdf.get_channel_unit(&channel_name)?,
+                mdf3.get_channel_unit(&channel_name)?
+            );
+        }
+        assert!(mdf.write_mdf3(file_name).is_err());
+        Ok(())
+    }
+    #[test]
+    fn diff_files() -> Result<()> {
+        use crate::test_utils::SyntheticMdf;
+        let file = SyntheticMdf::mdf4()
+            .with_group(&[DataType::UInt16, DataType::Float64])
+            .with_records(10);
+        let load = |bytes: &[u8], file_name: &str| -> Result<Mdf> {
+            let mut mdf = Mdf::from_bytes(bytes, file_name)?;
+            mdf.load_channels_data_from_bytes(bytes, mdf.get_channel_names_set())?;
+            Ok(mdf)
+        };
+        let mut left = load(&file.to_bytes()?, "left.mf4")?;
+        // writer round trip
+        let output = std::env::temp_dir().join("mdfr_diff.mf4");
+        let output = output.to_string_lossy();
+        let mut written = left.write(&output, false)?;
+        written.load_all_channels_data_in_memory()?;
+        let report = left.diff(&written, 0.0)?;
+        assert!(report.is_identical(), "{report}");
+        fs::remove_file(&*output)?;
+
+        let mut right = load(
+            &file.with_linear_conversion(-3.5, 0.125).to_bytes()?,
+            "right.mf4",
+        )?;
+        right.remove_channel(&SyntheticMdf::channel_name(0, 1));
+        right.set_channel_unit(&SyntheticMdf::channel_name(0, 0), "V");
+        let report = left.diff(&right, 0.0)?;
+        assert_eq!(report.only_left, vec![SyntheticMdf::channel_name(0, 1)]);
+        assert!(report.only_right.is_empty());
+        assert!(report.metadata.iter().any(|diff| {
+            diff.channel == Some(SyntheticMdf::channel_name(0, 0))
+                && diff.property == "unit"
+                && diff.right.as_deref() == Some("V")
+        }));
+        assert_eq!(report.data.len(), 1);
+        let data = &report.data[0];
+        assert_eq!(data.channel, SyntheticMdf::channel_name(0, 0));
+        assert_eq!((data.left_len, data.right_len), (10, 10));
+        assert!(data.n_different > 0 && data.max_abs_error.is_some_and(|error| error > 0.0));
+        // differences within tolerance
+        assert!(left.diff(&right, f64::MAX)?.data.is_empty());
+        left.remove_channel(&SyntheticMdf::channel_name(0, 1));
+        right.set_channel_unit(&SyntheticMdf::channel_name(0, 0), "");
+        left.set_channel_unit(&SyntheticMdf::channel_name(0, 0), "");
+        assert!(left.diff(&right, f64::MAX)?.is_identical());
+        Ok(())
+    }
+
+    #[test]
+    fn set_header() -> Result<()> {
+        use crate::mdfreader::HeaderOptions;
+        use crate::test_utils::SyntheticMdf;
+        let start_time = chrono::DateTime::parse_from_rfc3339("2024-05-17T08:30:15+02:00")?;
+        let header = HeaderOptions {
+            program: Some("bench".to_string()),
+            author: Some("Jane Doe".to_string()),
+            project: Some("endurance & cold".to_string()),
+            subject: Some("vehicle 12".to_string()),
+            comment: Some("night run".to_string()),
+            start_time: Some(start_time),
+        };
+        let output = std::env::temp_dir().join("mdfr_set_header.mf4");
+        let output = output.to_string_lossy();
+        for (file, file_name) in [
+            (SyntheticMdf::mdf4(), "header.mf4"),
+            (SyntheticMdf::mdf3(), "header.dat"),
+        ] {
+            let bytes = file
+                .with_group(&[DataType::UInt16])
+                .with_records(10)
+                .to_bytes()?;
+            let mut mdf = Mdf::from_bytes(&bytes, file_name)?;
+            mdf.load_channels_data_from_bytes(&bytes, mdf.get_channel_names_set())?;
+            assert!(mdf
+                .set_header(&HeaderOptions {
+                    program: Some("too long program".to_string()),
+                    ..Default::default()
+                })
+                .is_err());
+            mdf.set_header(&header)?;
+            assert_eq!(mdf.get_start_time()?, start_time);
+            mdf.write(&output, false)?;
+            let written = Mdf::new(&output)?;
+            assert_eq!(written.get_start_time()?, start_time, "{file_name}");
+            let MdfInfo::V4(info) = &written.mdf_info else {
+                panic!("written file is not mdf4");
+            };
+            assert_eq!(&info.id_block.id_prog, b"bench   ");
+            let comments = info.sharable.get_hd_comments(info.hd_block.hd_md_comment);
+            for (key, value) in [
+                ("author", "Jane Doe"),
+                ("project", "endurance & cold"),
+                ("subject", "vehicle 12"),
+                ("TX", "night run"),
+            ] {
+                assert_eq!(comments.get(key).map(|v| v.as_str()), Some(value), "{key}");
+            }
+        }
+        fs::remove_file(&*output)?;
+        Ok(())
+    }
+
+    #[test]
+    fn file_history() -> Result<()> {
+        use crate::test_utils::SyntheticMdf;
+        let file = SyntheticMdf::mdf4()
+            .with_group(&[DataType::UInt16])
+            .with_records(10);
+        let bytes = file.to_bytes()?;
+        let mut mdf = Mdf::from_bytes(&bytes, "history.mf4")?;
+        assert_eq!(mdf.get_file_history()?.len(), 1);
+        let output = std::env::temp_dir().join("mdfr_file_history.mf4");
+        let output = output.to_string_lossy();
+        mdf.load_channels_data_from_bytes(&bytes, mdf.get_channel_names_set())?;
+        let written = mdf.write(&output, false)?;
+        let mut mdf = Mdf::new(&output)?;
+        assert_eq!(mdf.get_file_history()?, written.get_file_history()?);
+        let history = mdf.get_file_history()?;
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[1].comment.as_deref(), Some("created"));
+        assert_eq!(history[1].tool_id.as_deref(), Some("mdfr"));
+        assert_eq!(
+            history[1].tool_version.as_deref(),
+            Some(env!("CARGO_PKG_VERSION"))
+        );
+        // history kept and appended at each writing
+        mdf.load_all_channels_data_in_memory()?;
+        mdf.write_with_comment(&output, false, "<resampled> & filtered")?;
+        let appended = Mdf::new(&output)?.get_file_history()?;
+        assert_eq!(appended.len(), 3);
+        assert_eq!(appended[..2], history[..]);
+        assert_eq!(
+            appended[2].comment.as_deref(),
+            Some("<resampled> & filtered")
+        );
+        // mdf3 conversion recorded
+        let mdf3 = SyntheticMdf::mdf3()
+            .with_group(&[DataType::UInt16])
+            .with_records(10);
+        let bytes = mdf3.to_bytes()?;
+        let mut mdf = Mdf::from_bytes(&bytes, "history.dat")?;
+        assert!(mdf.get_file_history()?.is_empty());
+        mdf.load_channels_data_from_bytes(&bytes, mdf.get_channel_names_set())?;
+        let history = mdf.write(&output, false)?.get_file_history()?;
+        assert_eq!(
+            history
+                .iter()
+                .map(|entry| entry.comment.as_deref())
+                .collect::<Vec<_>>(),
+            vec![Some("converted from mdf version 3"), Some("created")]
+        );
+        fs::remove_file(&*output)?;
+        Ok(())
+    }
+
+    #[test]
+    fn events() -> Result<()> {
+        let mut mdf = Mdf::new("test_files/test_basic.mf4")?;
+        mdf.load_all_channels_data_in_memory()?;
+        // channel with data, written back
+        let channel_name = mdf
+            .get_channel_names_set()
+            .into_iter()
+            .find(|name| mdf.get_channel_data(name).is_some_and(|d| !d.is_empty()))
+            .context("no channel with data in file")?;
+        let marker = Event {
+            name: Some("marker".to_string()),
+            comment: Some("user marker".to_string()),
+            sync_type: 1,
+            cause: 4,
+            sync_value: 1.5,
+            scope: vec![EventScope::Channel(channel_name.clone())],
+            ..Default::default()
+        };
+        let trigger = Event {
+            name: Some("trigger".to_string()),
+            event_type: EventType::Trigger,
+            sync_type: 1,
+            sync_value: 0.25,
+            // channel group is referenced by its master channel
+            scope: vec![EventScope::ChannelGroup(
+                mdf.get_channel_master(&channel_name)
+                    .unwrap_or(channel_name.clone()),
+            )],
+            ..Default::default()
+        };
+        mdf.add_event(marker.clone())?;
+        mdf.add_event(trigger.clone())?;
+        assert_eq!(mdf.get_events()?, vec![trigger.clone(), marker.clone()]);
+        assert!(mdf
+            .add_event(Event {
+                scope: vec![EventScope::Channel("unknown channel".to_string())],
+                ..Default::default()
+            })
+            .is_err());
+        // events are written back
+        let writing_file = std::env::temp_dir().join("mdfr_events.mf4");
+        let writing_file = writing_file.to_str().context("invalid temp path")?;
+        mdf.write(writing_file, false)?;
+        let written = Mdf::new(writing_file)?;
+        let events = written.get_events()?;
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].name, trigger.name);
+        assert_eq!(events[0].event_type, EventType::Trigger);
+        assert_eq!(events[1], marker);
+        fs::remove_file(writing_file)?;
+        Ok(())
+    }
+    #[test]
+    fn column_storage_writing() -> Result<()> {
+        let mut mdf = Mdf::new("test_files/test_basic.mf4")?;
+        mdf.load_all_channels_data_in_memory()?;
+        let writing_file = std::env::temp_dir().join("mdfr_column_storage.mf4");
+        let writing_file = writing_file.to_str().context("invalid temp path")?;
+        for (compression, block_id) in [(false, b"##DV"), (true, b"##LD")] {
+            let written = mdf.write(writing_file, compression)?;
+            let MdfInfo::V4(info) = &written.mdf_info else {
+                panic!("mdf4 file expected")
+            };
+            let bytes = fs::read(writing_file)?;
+            for dg in info.dg.values() {
+                assert_eq!(dg.cg.len(), 1);
+                let position = dg.block.dg_data as usize;
+                assert_eq!(&bytes[position..position + 4], block_id);
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn vlsd_writing() -> Result<()> {
+        use arrow::array::{Array, LargeBinaryArray, LargeStringArray};
+        let mut mdf = Mdf::new("test_files/test_basic.mf4")?;
+        mdf.load_all_channels_data_in_memory()?;
+        let n = mdf
+            .get_channel_data("Time")
+            .context("Time not found")?
+            .len();
+        let labels: LargeStringArray = (0..n)
+            .map(|i| (i % 7 != 0).then(|| format!("label {}", i % 100)))
+            .collect();
+        let frames: LargeBinaryArray = (0..n).map(|i| Some(vec![(i % 256) as u8; i % 9])).collect();
+        for (name, data) in [
+            ("Label", Arc::new(labels.clone()) as Arc<dyn Array>),
+            ("Frame", Arc::new(frames.clone()) as Arc<dyn Array>),
+        ] {
+            mdf.add_channel(
+                name.to_string(),
+                data,
+                Some("Time".to_string()),
+                None,
+                false,
+                None,
+                None,
+            )?;
+        }
+        let writing_file = std::env::temp_dir().join("mdfr_vlsd_writing.mf4");
+        let writing_file = writing_file.to_str().context("invalid temp path")?;
+        for compression in [false, true] {
+            mdf.write(writing_file, compression)?;
+            let mut written = Mdf::new(writing_file)?;
+            written.load_all_channels_data_in_memory()?;
+            let label = written
+                .get_channel_data("Label")
+                .context("Label not found")?;
+            assert_eq!(label.as_ref().as_string::<i64>(), &labels);
+            let frame = written
+                .get_channel_data("Frame")
+                .context("Frame not found")?;
+            assert_eq!(frame.as_ref().as_binary::<i64>(), &frames);
+        }
+        fs::remove_file(writing_file)?;
+        Ok(())
+    }
+
+    #[test]
+    fn array_writing() -> Result<()> {
+        use crate::data_holder::tensor_arrow::{Order, TensorArrow};
+        use crate::mdfreader::{DataSignature, MasterSignature};
+        let mut mdf = Mdf::new("test_files/test_basic.mf4")?;
+        mdf.load_all_channels_data_in_memory()?;
+        let n = mdf
+            .get_channel_data("Time")
+            .context("Time not found")?
+            .len();
+        let map = ChannelData::ArrayDFloat64(TensorArrow::new_from_buffer(
+            (0..n * 6)
+                .map(|i| i as f64 * 0.5)
+                .collect::<Vec<f64>>()
+                .into(),
+            vec![n, 2, 3],
+            Order::RowMajor,
+        ));
+        let curve = ChannelData::ArrayDInt16(TensorArrow::new_from_buffer(
+            (0..n * 4)
+                .map(|i| (i % 1000) as i16 - 500)
+                .collect::<Vec<i16>>()
+                .into(),
+            vec![n, 4],
+            Order::ColumnMajor,
+        ));
+        for (name, data) in [("Map", map.clone()), ("Curve", curve.clone())] {
+            let data_signature = DataSignature {
+                len: n,
+                data_type: data.data_type(false),
+                bit_count: data.bit_count(),
+                byte_count: data.byte_count(),
+                ndim: data.ndim(),
+                shape: data.shape(),
+            };
+            let master_signature = MasterSignature {
+                master_channel: None,
+                master_type: None,
+                master_flag: false,
+            };
+            mdf.mdf_info.add_channel(
+                name.to_string(),
+                data,
+                data_signature,
+                master_signature,
+                None,
+                None,
+            )?;
+        }
+        let writing_file = std::env::temp_dir().join("mdfr_array_writing.mf4");
+        let writing_file = writing_file.to_str().context("invalid temp path")?;
+        for compression in [false, true] {
+            mdf.write(writing_file, compression)?;
+            let mut written = Mdf::new(writing_file)?;
+            written.load_all_channels_data_in_memory()?;
+            for (name, data) in [("Map", &map), ("Curve", &curve)] {
+                let read = written
+                    .get_channel_data(name)
+                    .with_context(|| format!("{name} not found"))?;
+                assert_eq!(read, data);
+                assert_eq!(read.shape(), data.shape());
+            }
+        }
+        fs::remove_file(writing_file)?;
+        Ok(())
+    }
+
+    #[test]
+    fn complex_writing() -> Result<()> {
+        use arrow::array::{Array, FixedSizeListArray};
+        use arrow::datatypes::Field;
+        let mut mdf = Mdf::new("test_files/test_basic.mf4")?;
+        mdf.load_all_channels_data_in_memory()?;
+        let n = mdf
+            .get_channel_data("Time")
+            .context("Time not found")?
+            .len();
+        let values = Float64Array::from((0..n * 2).map(|i| i as f64 * 0.25).collect::<Vec<_>>());
+        let impedance = FixedSizeListArray::new(
+            Arc::new(Field::new("item", DataType::Float64, false)),
+            2,
+            Arc::new(values),
+            None,
+        );
+        mdf.add_channel(
+            "Impedance".to_string(),
+            Arc::new(impedance.clone()),
+            None,
+            None,
+            false,
+            None,
+            None,
+        )?;
+        let data = mdf
+            .get_channel_data("Impedance")
+            .context("Impedance not found")?
+            .clone();
+        assert!(matches!(data, ChannelData::Complex64(_)));
+        assert_eq!(data.len(), n);
+        assert_eq!(data.arrow_data_type(), impedance.data_type().clone());
+        let writing_file = std::env::temp_dir().join("mdfr_complex_writing.mf4");
+        let writing_file = writing_file.to_str().context("invalid temp path")?;
+        mdf.write(writing_file, false)?;
+        let mut written = Mdf::new(writing_file)?;
+        written.load_all_channels_data_in_memory()?;
+        let read = written
+            .get_channel_data("Impedance")
+            .context("Impedance not found")?;
+        assert_eq!(read, &data);
+        // real and imaginary pairs
+        let pairs = read.finish_cloned();
+        let pairs = pairs.as_fixed_size_list();
+        assert_eq!(pairs, &impedance);
+        assert_eq!(
+            pairs.value(3).as_primitive::<Float64Type>().values(),
+            &[1.5, 1.75]
+        );
+        fs::remove_file(writing_file)?;
+        Ok(())
+    }
+
+    #[test]
+    fn start_time() -> Result<()> {
+        use chrono::{DateTime, FixedOffset};
+        let mut mdf = Mdf::new("test_files/test_basic.mf4")?;
+        let start_ns = match &mdf.mdf_info {
+            MdfInfo::V4(mdfinfo4) => mdfinfo4.hd_block.hd_start_time_ns as i64,
+            MdfInfo::V3(_) => unreachable!(),
+        };
+        let start_time = mdf.get_start_time()?;
+        assert_eq!(start_time, DateTime::from_timestamp_nanos(start_ns));
+        assert_eq!(start_time.offset(), &FixedOffset::east_opt(0).unwrap());
+        // time zone and daylight saving offsets, same instant
+        if let MdfInfo::V4(mdfinfo4) = &mut mdf.mdf_info {
+            mdfinfo4.hd_block.hd_time_flags = 0b10;
+            mdfinfo4.hd_block.hd_tz_offset_min = 60;
+            mdfinfo4.hd_block.hd_dst_offset_min = 60;
+        }
+        let start_time = mdf.get_start_time()?;
+        assert_eq!(start_time.offset(), &FixedOffset::east_opt(7200).unwrap());
+        assert_eq!(start_time.timestamp_nanos_opt(), Some(start_ns));
+        // absolute time of channel samples
+        mdf.load_all_channels_data_in_memory()?;
+        let channel = mdf
+            .get_master_channel_names_set()
+            .get(&Some("Time".to_string()))
+            .and_then(|channels| channels.iter().find(|name| *name != "Time").cloned())
+            .context("no channel with Time master")?;
+        let absolute_time = mdf.get_channel_absolute_time(&channel)?;
+        let time = mdf.get_channel_data("Time").context("Time not found")?;
+        let time = cast(&time.as_ref(), &DataType::Float64)?;
+        let time = time.as_primitive::<Float64Type>();
+        assert_eq!(absolute_time.len(), time.len());
+        assert_eq!(absolute_time.timezone(), Some("+02:00"));
+        let last = time.len() - 1;
+        assert_eq!(
+            absolute_time.value(last),
+            start_ns + (time.value(last) * 1e9).round() as i64
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn sample_reduction() -> Result<()> {
+        let mut mdf = Mdf::new("test_files/test_basic.mf4")?;
+        mdf.load_all_channels_data_in_memory()?;
+        let writing_file = std::env::temp_dir().join("mdfr_sample_reduction.mf4");
+        let writing_file = writing_file.to_str().context("invalid temp path")?;
+        let written = mdf.write_with_sample_reduction(writing_file, false, &[10, 100])?;
+        let MdfInfo::V4(info) = &written.mdf_info else {
+            panic!("mdf4 file expected")
+        };
+        let mut file = io::BufReader::new(fs::File::open(writing_file)?);
+        let mut tested = false;
+        for dg in info.dg.values() {
+            for cg in dg.cg.values() {
+                let Some(cn) = cg.cn.values().next() else {
+                    continue;
+                };
+                let Some(data) = mdf.get_channel_data(&cn.unique_name) else {
+                    continue;
+                };
+                if cn.block.cn_data_type != 4 || cn.n_bytes != 8 || cg.block.cg_sr_first == 0 {
+                    continue;
+                }
+                let values = cast(&data.as_ref(), &DataType::Float64)?;
+                let values = values.as_primitive::<Float64Type>().values();
+                // first sample reduction of 10 samples
+                file.seek(io::SeekFrom::Start(cg.block.cg_sr_first as u64 + 16))?;
+                let sr: Sr4Block = file.read_le()?;
+                assert_eq!(sr.sr_interval, 10.0);
+                assert_eq!(sr.sr_cycle_count as usize, values.len().div_ceil(10));
+                assert!(sr.sr_sr_next != 0);
+                file.seek(io::SeekFrom::Start(sr.sr_data as u64 + 24))?;
+                let record: [f64; 3] = file.read_le()?;
+                let first = &values[..10.min(values.len())];
+                let mean = first.iter().sum::<f64>() / first.len() as f64;
+                let min = first.iter().cloned().fold(f64::INFINITY, f64::min);
+                let max = first.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+                assert_eq!(record, [mean, min, max]);
+                tested = true;
+            }
+        }
+        assert!(tested);
+        // reduced file can still be read
+        let mut written = Mdf::new(writing_file)?;
+        written.load_all_channels_data_in_memory()?;
+        fs::remove_file(writing_file)?;
+        Ok(())
+    }
+    #[test]
+    fn concatenate_and_merge() -> Result<()> {
+        let file = "test_files/test_basic.mf4";
+        let mut single = Mdf::new(file)?;
+        single.load_all_channels_data_in_memory()?;
+        let length = single
+            .get_channel_data("Time")
+            .map(|d| d.len())
+            .unwrap_or(0);
+        assert!(length > 0);
+        // same file twice, start time offset is null
+        let mut concatenated = Mdf::concatenate(&[file, file])?;
+        let time = concatenated
+            .get_channel_data("Time")
+            .context("missing master")?
+            .as_ref();
+        assert_eq!(time.len(), 2 * length);
+        let time = time.as_primitive::<Float64Type>();
+        assert_eq!(time.value(0), time.value(length));
+        let writing_file = std::env::temp_dir().join("mdfr_concatenated.mf4");
+        let writing_file = writing_file.to_str().context("invalid temp path")?;
+        concatenated.write(writing_file, false)?;
+        let mut written = Mdf::new(writing_file)?;
+        written.load_all_channels_data_in_memory()?;
+        assert_eq!(
+            written.get_channel_data("Time").map(|d| d.len()),
+            Some(2 * length)
+        );
+        fs::remove_file(writing_file)?;
+        // mismatching channels are refused
+        assert!(Mdf::concatenate(&[file, "test_files/test_mdf3.mdf"]).is_err());
+
+        // channels of mdf3 file are added to the ones of the mdf4 file
+        let mut mdf3 = Mdf::new("test_files/test_mdf3.mdf")?;
+        mdf3.load_all_channels_data_in_memory()?;
+        let mut merged = Mdf::merge(&[file, "test_files/test_mdf3.mdf"])?;
+        let names = merged.get_channel_names_set();
+        assert_eq!(
+            names.len(),
+            single.get_channel_names_set().len() + mdf3.get_channel_names_set().len()
+        );
+        let writing_file = std::env::temp_dir().join("mdfr_merged.mf4");
+        let writing_file = writing_file.to_str().context("invalid temp path")?;
+        merged.write(writing_file, false)?;
+        let written = Mdf::new(writing_file)?;
+        // masters are written with each channel group, duplicates are renamed when reading
+        assert!(names.is_subset(&written.get_channel_names_set()));
+        fs::remove_file(writing_file)?;
+        Ok(())
+    }
+    #[test]
+    fn files_synchronization() -> Result<()> {
+        use crate::mdfwriter::merge4::SyncOptions;
+        let file = "test_files/test_basic.mf4";
+        let mut files = Vec::new();
+        for (delay, gps_offset) in [(0, 100.0), (5, 103.0)] {
+            let mut mdf = Mdf::new(file)?;
+            mdf.load_all_channels_data_in_memory()?;
+            if let MdfInfo::V4(mdfinfo4) = &mut mdf.mdf_info {
+                mdfinfo4.hd_block.hd_start_time_ns += delay * 1_000_000_000;
+            }
+            let time = cast(
+                &mdf.get_channel_data("Time")
+                    .context("Time not found")?
+                    .as_ref(),
+                &DataType::Float64,
+            )?;
+            let gps_time: Float64Array =
+                time.as_primitive::<Float64Type>().unary(|t| t + gps_offset);
+            mdf.add_channel(
+                "GpsTime".to_string(),
+                Arc::new(gps_time),
+                Some("Time".to_string()),
+                None,
+                false,
+                Some("s".to_string()),
+                None,
+            )?;
+            files.push(mdf);
+        }
+        // offsets from start times or from the common time channel
+        assert_eq!(Mdf::sync_files(&files, None)?, vec![0.0, 5.0]);
+        assert_eq!(Mdf::sync_files(&files, Some("GpsTime"))?, vec![0.0, 3.0]);
+        assert!(Mdf::sync_files(&files, Some("Unknown")).is_err());
+        // offsets not applied when concatenating
+        let options = SyncOptions {
+            apply_offsets: false,
+            sync_channel: None,
+        };
+        let concatenated = Mdf::concatenate_with_options(&[file, file], &options)?;
+        let time = concatenated
+            .get_channel_data("Time")
+            .context("missing master")?
+            .as_ref();
+        let time = time.as_primitive::<Float64Type>();
+        assert_eq!(time.value(0), time.value(time.len() / 2));
+        Ok(())
+    }
+    #[test]
+    fn resampling() -> Result<()> {
+        let mut mdf = Mdf::new("test_files/test_basic.mf4")?;
+        mdf.load_all_channels_data_in_memory()?;
+        let time = cast(
+            &mdf.get_channel_data("Time")
+                .context("missing master")?
+                .as_ref(),
+            &DataType::Float64,
+        )?;
+        let time = time.as_primitive::<Float64Type>();
+        let (first, last) = (time.value(0), time.value(time.len() - 1));
+        // raster covering the master range with 11 samples
+        let mut resampled = mdf.resample((last - first) / 10.0)?;
+        let raster = resampled
+            .get_channel_data("time")
+            .context("missing raster master")?
+            .as_ref();
+        assert!((10..=11).contains(&raster.len()));
+        assert_eq!(raster.as_primitive::<Float64Type>().value(0), first);
+        for name in mdf.get_channel_names_set() {
+            if name != "Time" {
+                let data = resampled
+                    .get_channel_data(&name)
+                    .context("missing channel")?;
+                assert_eq!(data.len(), raster.len());
+            }
+        }
+        let writing_file = std::env::temp_dir().join("mdfr_resampled.mf4");
+        let writing_file = writing_file.to_str().context("invalid temp path")?;
+        resampled.write(writing_file, false)?;
+        fs::remove_file(writing_file)?;
+        // resampling on its own master keeps values
+        let same = mdf.resample_on("Time")?;
+        for name in mdf.get_channel_names_set() {
+            assert_eq!(
+                same.get_channel_data(&name).map(|d| d.as_ref()),
+                mdf.get_channel_data(&name).map(|d| d.as_ref())
+            );
+        }
+        assert!(mdf.resample(0.0).is_err());
+
+        let mut mdf3 = Mdf::new("test_files/test_mdf3.mdf")?;
+        mdf3.load_all_channels_data_in_memory()?;
+        let resampled3 = mdf3.resample(0.1)?;
+        assert!(resampled3.get_channel_names_set().len() > 1);
+        Ok(())
+    }
+    #[test]
+    fn validation() -> Result<()> {
+        let mdf = Mdf::new("test_files/test_basic.mf4")?;
+        let report = mdf.validate()?;
+        assert!(report.is_valid(), "{report}");
+        assert!(report.n_blocks > 0);
+        let report = validate_file("test_files/test_mdf3.mdf")?;
+        assert!(report.is_valid(), "{report}");
+        // first data group link of header block pointing beyond file end
+        let mut bytes = fs::read("test_files/test_basic.mf4")?;
+        let size = bytes.len() as u64;
+        bytes[64 + 24..64 + 32].copy_from_slice(&(size + 8).to_le_bytes());
+        let report = validate_reader(io::Cursor::new(&bytes), "broken_link")?;
+        assert!(report
+            .problems
+            .iter()
+            .any(|p| p.kind == ProblemKind::BrokenLink && p.position == 64));
+        // header block length exceeding file size
+        let mut bytes = fs::read("test_files/test_basic.mf4")?;
+        bytes[64 + 8..64 + 16].copy_from_slice(&(size * 2).to_le_bytes());
+        let report = validate_reader(io::Cursor::new(&bytes), "block_length")?;
+        assert!(report
+            .problems
+            .iter()
+            .any(|p| p.kind == ProblemKind::BlockLength));
+        Ok(())
+    }
+    #[test]
+    fn unfinalized_repair() -> Result<()> {
+        let mut mdf = Mdf::new("test_files/test_basic.mf4")?;
+        mdf.load_all_channels_data_in_memory()?;
+        let writing_file = std::env::temp_dir().join("mdfr_unfinalized.mf4");
+        let writing_file = writing_file.to_str().context("invalid temp path")?;
+        let _ = fs::remove_file(writing_file);
+        mdf.write(writing_file, false)?;
+        // logger crash: cycle counts and last data block length not updated
+        let mut bytes = fs::read(writing_file)?;
+        bytes[..8].copy_from_slice(b"UnFinMF ");
+        bytes[60..62].copy_from_slice(&0b101u16.to_le_bytes());
+        let blocks = |id: &[u8]| -> Vec<usize> {
+            bytes
+                .windows(4)
+                .enumerate()
+                .filter(|(_, w)| *w == id)
+                .map(|(i, _)| i)
+                .collect()
+        };
+        let cgs = blocks(b"##CG");
+        let last_dv = *blocks(b"##DV").last().context("no DV block")?;
+        for cg in cgs {
+            let n_links = u64::from_le_bytes(bytes[cg + 16..cg + 24].try_into()?) as usize;
+            let cycle_count = cg + 24 + 8 * n_links + 8;
+            bytes[cycle_count..cycle_count + 8].copy_from_slice(&0u64.to_le_bytes());
+        }
+        // logger stopped writing at the end of the last record
+        let length = u64::from_le_bytes(bytes[last_dv + 8..last_dv + 16].try_into()?) as usize;
+        bytes.truncate(last_dv + length);
+        bytes[last_dv + 8..last_dv + 16].copy_from_slice(&24u64.to_le_bytes());
+        fs::write(writing_file, &bytes)?;
+        assert!(Mdf::new(writing_file).is_err());
+        let mut repaired = Mdf::repair_unfinalized(writing_file)?;
+        repaired.load_all_channels_data_in_memory()?;
+        for name in mdf.get_channel_names_set() {
+            assert_eq!(
+                repaired.get_channel_data(&name).map(|d| d.as_ref()),
+                mdf.get_channel_data(&name).map(|d| d.as_ref()),
+                "channel {name}"
+            );
+        }
+        assert!(validate_file(writing_file)?.is_valid());
+        // already finalized file is left untouched
+        let size = fs::metadata(writing_file)?.len();
+        Mdf::repair_unfinalized(writing_file)?;
+        assert_eq!(fs::metadata(writing_file)?.len(), size);
+        fs::remove_file(writing_file)?;
+        Ok(())
+    }
+    #[test]
+    fn plotting() -> Result<()> {
+        let mut mdf = Mdf::new("test_files/test_basic.mf4")?;
+        mdf.load_all_channels_data_in_memory()?;
+        let names = vec!["Value".to_string()];
+        let plot = plot_terminal(&mdf, &names, 60, 15)?;
+        assert!(plot.contains('*'));
+        assert!(plot.contains("Value"));
+        let writing_file = std::env::temp_dir().join("mdfr_plot.png");
+        let writing_file = writing_file.to_str().context("invalid temp path")?;
+        plot_png(&mdf, &names, writing_file, 320, 240)?;
+        let png = fs::read(writing_file)?;
+        assert_eq!(png[..8], [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A]);
+        assert_eq!(&png[12..16], b"IHDR");
+        fs::remove_file(writing_file)?;
+        assert!(plot_terminal(&mdf, &["unknown".to_string()], 60, 15).is_err());
+        Ok(())
+    }
+    #[test]
+    fn mdf_modifications() -> Result<()> {
+        require_test_data!();
+        // write file with invalid channels
+        let file = format!(
+            "{}{}",
+            *BASE_PATH_MDF4, &"Simple/PCV_iO_Gen3_LK1__3l_TDI.mf4"
+        );
+        let ref_channel = r"PANS";
+        let ref_desc = r"tralala";
+        let ref_unit = r"Bar";
+        let mut mdf = Mdf::new(&file)?;
+        mdf.load_all_channels_data_in_memory()?;
+        // modify data
+        if let Some(data) = mdf.get_channel_data(&ref_channel.to_string()) {
+            let mut new_data = PrimitiveBuilder::with_capacity(data.len());
+            data.finish_cloned()
+                .as_primitive::<Float32Type>()
+                .iter()
+                .for_each(|v| new_data.append_option(v));
+            new_data.values_slice_mut()[0] = 0.0f32;
+            mdf.set_channel_data(
+                &ref_channel.to_string(),
+                ChannelData::Float32(new_data).as_ref(),
+            )?;
+            mdf.set_channel_desc(&ref_channel.to_string(), ref_desc);
+            mdf.set_channel_unit(&ref_channel.to_string(), ref_unit);
+            mdf.set_channel_master_type(&ref_channel.to_string(), 1)?;
+        } else {
+            panic!("channel not found");
+        }
+        if let Some(data) = mdf.get_channel_data(&ref_channel.to_string()) {
+            let (minimum, _) = data.min_max();
+            if let Some(min) = minimum {
+                assert!(min < 1000.0f64);
+            }
+        } else {
+            panic!("channel not found");
+        }
+        if let Ok(Some(desc)) = mdf.get_channel_desc(&ref_channel.to_string()) {
+            assert_eq!(desc, ref_desc);
+        } else {
+            panic!("channel not found");
+        }
+        if let Ok(Some(unit)) = mdf.get_channel_unit(&ref_channel.to_string()) {
+            assert_eq!(unit, ref_unit);
+        } else {
+            panic!("channel not found");
+        }
+        assert_eq!(mdf.get_channel_master_type(&ref_channel.to_string()), 1);
+
+        // add new channel
+        drop(mdf);
+        let mut mdf = Mdf::new(&file)?;
+        mdf.load_all_channels_data_in_memory()?;
+        let channel_name = r"Fake_name".to_string();
+        let new_channel_name = r"New fake_name".to_string();
+        let new_data = Arc::new(Float64Array::try_new(vec![0f64; 3300].into(), None)?);
+        let master_channel = mdf.get_channel_master(&ref_channel.to_string());
+        let master_type = Some(0);
+        let master_flag = false;
+        let unit = Some(ref_unit.to_string());
+        let desc = Some(ref_desc.to_string());
+        mdf.add_channel(
+            channel_name.clone(),
+            new_data,
+            master_channel,
+            master_type,
+            master_flag,
+            unit,
+            desc,
+        )?;
+
+        if let Some(data) = mdf.get_channel_data(&channel_name.to_string()) {
+            let (minimum, _) = data.min_max();
+            if let Some(min) = minimum {
+                assert!(min == 0.0f64);
+            }
+        } else {
+            panic!("channel not found");
+        }
+        if let Ok(Some(desc)) = mdf.get_channel_desc(&channel_name.to_string()) {
+            assert_eq!(desc, ref_desc.to_string());
+        } else {
+            panic!("channel not found");
+        }
+        if let Ok(Some(unit)) = mdf.get_channel_unit(&channel_name.to_string()) {
+            assert_eq!(unit, ref_unit);
+        } else {
+            panic!("channel not found");
+        }
+        assert_eq!(mdf.get_channel_master_type(&channel_name.to_string()), 0);
+
+        //rename
+        assert!(mdf.get_channel_data(&channel_name.to_string()).is_some());
+        mdf.rename_channel(&channel_name.to_string(), &new_channel_name);
+        assert!(mdf.get_channel_data(&channel_name.to_string()).is_none());
+
+        //remove
+        assert!(mdf
+            .get_channel_data(&new_channel_name.to_string())
+            .is_some());
+        mdf.remove_channel(&new_channel_name);
+        assert!(mdf
+            .get_channel_data(&new_channel_name.to_string())
+            .is_none());
         Ok(())
     }
     #[test]
-    fn record_layout() -> Result<()> {
-        // Overlapping signals
-        let file_name = format!(
+    fn export_to_parquet() -> Result<()> {
+        require_test_data!();
+        // Export mdf4 to Parquet file
+        let file = format!(
             "{}{}",
-            BASE_PATH_MDF4, "RecordLayout/Vector_NotByteAligned.mf4"
+            *BASE_PATH_MDF4, &"Simple/PCV_iO_Gen3_LK1__3l_TDI.mf4"
         );
-        let mut mdf = Mdf::new(&file_name)?;
+        let extension = "*.parquet";
+        let mut mdf = Mdf::new(&file)?;
         mdf.load_all_channels_data_in_memory()?;
-        if let Some(data) = mdf.get_channel_data(&"Channel B".to_string()) {
-            let mut vect: Vec<u64> = vec![0; 30];
-            let mut counter: u64 = 0;
-            vect.iter_mut().for_each(|v| {
-                *v += counter;
-                counter += 1
-            });
-            assert_eq!(
-                ChannelData::UInt64(UInt64Builder::new_from_buffer(vect.into(), None)),
-                data.clone()
-            );
-        }
-
-        // Overlapping signals
-        let file_name = format!(
+        mdf.export_to_parquet(&WRITING_PARQUET_FILE, Some("zstd"))
+            .expect("failed writing mdf4 parquet file");
+        // Export mdf3 to Parquet file
+        let file = format!(
             "{}{}",
-            BASE_PATH_MDF4, "RecordLayout/Vector_OverlappingSignals.mf4"
+            *BASE_PATH_MDF3, &"RJ_N16-12-363_BM-15C-0024_228_2_20170116094355_CAN.dat"
         );
-        let mut mdf = Mdf::new(&file_name)?;
+        let mut mdf = Mdf::new(&file)?;
         mdf.load_all_channels_data_in_memory()?;
+        mdf.export_to_parquet(&WRITING_PARQUET_FILE, Some("snappy"))
+            .expect("failed writing mdf3 parquet file");
+        // remove all generated parquet files
+        let pattern = format!("{}/{}", *BASE_TEST_PATH, extension);
+        for path in glob(&pattern).unwrap().filter_map(Result::ok) {
+            fs::remove_file(path)?;
+        }
         Ok(())
     }
     #[test]
-    fn data_list() -> Result<()> {
-        // Equal length
-        let file_name = format!("{}{}", BASE_PATH_MDF4, "DataList/Vector_DT_EqualLen.MF4");
-        let mut mdf = Mdf::new(&file_name)?;
-        mdf.load_all_channels_data_in_memory()?;
-        if let Some(data) = mdf.get_channel_data(&"channel1".to_string()) {
-            assert_eq!(data.len(), 254552);
-        }
-        // Equal length
-        let file_name = format!("{}{}", BASE_PATH_MDF4, "DataList/Vector_DL_Linked_List.MF4");
-        let mut mdf = Mdf::new(&file_name)?;
+    fn tensor_to_parquet() -> Result<()> {
+        use crate::data_holder::tensor_arrow::{Order, TensorArrow};
+        use crate::mdfreader::{DataSignature, MasterSignature};
+        use arrow::array::{Array, FixedSizeListArray};
+        use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+        let mut mdf = Mdf::new("test_files/test_basic.mf4")?;
         mdf.load_all_channels_data_in_memory()?;
-        if let Some(data) = mdf.get_channel_data(&"channel1".to_string()) {
-            assert_eq!(data.len(), 254552);
+        let n = 10;
+        let map = ChannelData::ArrayDFloat64(TensorArrow::new_from_buffer(
+            (0..n * 6).map(|i| i as f64).collect::<Vec<f64>>().into(),
+            vec![n, 2, 3],
+            Order::RowMajor,
+        ));
+        let data_signature = DataSignature {
+            len: n,
+            data_type: map.data_type(false),
+            bit_count: map.bit_count(),
+            byte_count: map.byte_count(),
+            ndim: map.ndim(),
+            shape: map.shape(),
+        };
+        let master_signature = MasterSignature {
+            master_channel: None,
+            master_type: None,
+            master_flag: false,
+        };
+        mdf.mdf_info.add_channel(
+            "Map".to_string(),
+            map.clone(),
+            data_signature,
+            master_signature,
+            None,
+            None,
+        )?;
+        // one list per sample, nested per dimension
+        let array = map.finish_cloned();
+        assert_eq!(array.len(), n);
+        let samples = array
+            .as_any()
+            .downcast_ref::<FixedSizeListArray>()
+            .context("not a fixed size list")?;
+        assert_eq!(samples.value_length(), 2);
+        let rows = samples.value(1);
+        let rows = rows.as_fixed_size_list();
+        assert_eq!(rows.value_length(), 3);
+        assert_eq!(
+            rows.value(1).as_primitive::<Float64Type>().values(),
+            &[9.0, 10.0, 11.0]
+        );
+        // exported with its shape
+        let parquet_file = std::env::temp_dir().join("mdfr_tensor");
+        let parquet_file = parquet_file.to_str().context("invalid temp path")?;
+        mdf.export_to_parquet(parquet_file, None)?;
+        let map_file = std::env::temp_dir().join("mdfr_tensor_0.parquet");
+        let reader = ParquetRecordBatchReaderBuilder::try_new(fs::File::open(&map_file)?)?;
+        let field = reader.schema().field_with_name("Map")?.clone();
+        assert_eq!(field.data_type(), &map.arrow_data_type());
+        assert_eq!(field.metadata().get("shape"), Some(&"2,3".to_string()));
+        assert_eq!(field.metadata().get("order"), Some(&"RowMajor".to_string()));
+        let batch = reader.build()?.next().context("no record batch")??;
+        assert_eq!(
+            batch.column_by_name("Map").context("no Map column")?,
+            &array
+        );
+        let pattern = format!("{}*.parquet", parquet_file);
+        for path in glob(&pattern)?.filter_map(Result::ok) {
+            fs::remove_file(path)?;
         }
-
-        // Empty data
-        let file_name = format!("{}{}", BASE_PATH_MDF4, "DataList/ETAS_EmptyDL.mf4");
-        let mut mdf = Mdf::new(&file_name)?;
-        mdf.load_all_channels_data_in_memory()?;
-
-        // SD List
-        let file_name = format!("{}{}", BASE_PATH_MDF4, "DataList/Vector_SD_List.MF4");
-        let mut mdf = Mdf::new(&file_name)?;
-        mdf.load_all_channels_data_in_memory()?;
         Ok(())
     }
     #[test]
-    fn compressed_data() -> Result<()> {
-        // Single DZ deflate
-        let file_name = format!(
-            "{}{}",
-            BASE_PATH_MDF4, "CompressedData/Simple/Vector_SingleDZ_Deflate.mf4"
-        );
-        let mut mdf = Mdf::new(&file_name)?;
-        mdf.load_all_channels_data_in_memory()?;
-
-        // Single DZ transpose deflate
-        let file_name = format!(
-            "{}{}",
-            BASE_PATH_MDF4, "CompressedData/Simple/Vector_SingleDZ_TransposeDeflate.mf4"
-        );
-        let mut mdf = Mdf::new(&file_name)?;
-        mdf.load_all_channels_data_in_memory()?;
-
-        // deflate data list
-        let file_name = format!(
-            "{}{}",
-            BASE_PATH_MDF4, "CompressedData/DataList/Vector_DataList_Deflate.mf4"
-        );
-        let mut mdf = Mdf::new(&file_name)?;
+    fn byte_arrays_to_parquet() -> Result<()> {
+        use arrow::array::{Array, FixedSizeBinaryArray, LargeBinaryArray};
+        use arrow::datatypes::UInt8Type;
+        use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+        let mut mdf = Mdf::new("test_files/test_basic.mf4")?;
         mdf.load_all_channels_data_in_memory()?;
-        if let Some(data) = mdf.get_channel_data(&"Time channel".to_string()) {
-            let mut vect: Vec<f64> = vec![0.; 10000];
-            let mut counter: u64 = 0;
-            vect.iter_mut().for_each(|v| {
-                *v = (counter.clone() as f64) / 10.0;
-                counter += 1;
-            });
-            assert_eq!(
-                &ChannelData::Float64(Float64Builder::new_from_buffer(vect.into(), None)),
-                data
-            );
+        let n = mdf
+            .get_channel_data("Time")
+            .context("Time not found")?
+            .len();
+        let frames: LargeBinaryArray = (0..n).map(|i| Some(vec![(i % 256) as u8; i % 9])).collect();
+        let keys = FixedSizeBinaryArray::try_from_iter((0..n).map(|i| (i as u32).to_le_bytes()))?;
+        for (name, data) in [
+            ("Frame", Arc::new(frames.clone()) as Arc<dyn Array>),
+            ("Key", Arc::new(keys.clone()) as Arc<dyn Array>),
+        ] {
+            mdf.add_channel(
+                name.to_string(),
+                data,
+                Some("Time".to_string()),
+                None,
+                false,
+                None,
+                None,
+            )?;
         }
-
-        // transpose deflate data list
-        let file_name = format!(
-            "{}{}",
-            BASE_PATH_MDF4, "CompressedData/DataList/Vector_DataList_TransposeDeflate.mf4"
-        );
-        let mut mdf = Mdf::new(&file_name)?;
-        mdf.load_all_channels_data_in_memory()?;
-        if let Some(data) = mdf.get_channel_data(&"Time channel".to_string()) {
-            let mut vect: Vec<f64> = vec![0.; 10000];
-            let mut counter: u64 = 0;
-            vect.iter_mut().for_each(|v| {
-                *v = (counter.clone() as f64) / 10.0;
-                counter += 1;
-            });
-            assert_eq!(
-                &ChannelData::Float64(Float64Builder::new_from_buffer(vect.into(), None)),
-                data
-            );
+        // byte arrays read back from VLSD and fixed length records
+        let writing_file = std::env::temp_dir().join("mdfr_byte_arrays.mf4");
+        let writing_file = writing_file.to_str().context("invalid temp path")?;
+        let mut written = mdf.write(writing_file, false)?;
+        written.load_all_channels_data_in_memory()?;
+        for name in ["Frame", "Key"] {
+            let parquet_file = std::env::temp_dir().join(format!("mdfr_byte_arrays_{name}"));
+            let parquet_file = parquet_file.to_str().context("invalid temp path")?;
+            written.export_dataframe_to_parquet(name.to_string(), parquet_file, None)?;
+            let parquet_file = format!("{parquet_file}_Time.parquet");
+            let reader = ParquetRecordBatchReaderBuilder::try_new(fs::File::open(&parquet_file)?)?;
+            let batch = reader
+                .with_batch_size(n)
+                .build()?
+                .next()
+                .context("no record batch")??;
+            let column = batch.column_by_name(name).context("no byte array column")?;
+            if name == "Frame" {
+                let lists = column.as_list::<i64>();
+                assert_eq!(lists.len(), n);
+                assert_eq!(
+                    lists.value(10).as_primitive::<UInt8Type>().values(),
+                    &[10; 1]
+                );
+                assert_eq!(
+                    lists.value(17).as_primitive::<UInt8Type>().values(),
+                    &[17; 8]
+                );
+            } else {
+                let lists = column.as_fixed_size_list();
+                assert_eq!(lists.value_length(), 4);
+                assert_eq!(
+                    lists.value(258).as_primitive::<UInt8Type>().values(),
+                    &[2, 1, 0, 0]
+                );
+            }
+            fs::remove_file(parquet_file)?;
         }
-
-        // Unsorted
-        let file_name = format!(
-            "{}{}",
-            BASE_PATH_MDF4, "CompressedData/Unsorted/Vector_SingleDZ_Unsorted.MF4"
-        );
-        let mut mdf = Mdf::new(&file_name)?;
-        mdf.load_all_channels_data_in_memory()?;
+        fs::remove_file(writing_file)?;
         Ok(())
     }
-
     #[test]
-    fn unsorted_data() -> Result<()> {
-        let file_name = format!(
-            "{}{}",
-            BASE_PATH_MDF4, "UnsortedData/Vector_Unsorted_VLSD.MF4"
-        );
-        let mut mdf = Mdf::new(&file_name)?;
+    fn parquet_options() -> Result<()> {
+        use crate::export::parquet::ParquetOptions;
+        use arrow::array::StringArray;
+        use parquet::basic::Encoding;
+        use parquet::file::reader::{FileReader, SerializedFileReader};
+        let mut mdf = Mdf::new("test_files/test_basic.mf4")?;
         mdf.load_all_channels_data_in_memory()?;
+        let labels = StringArray::from_iter_values((0..100000).map(|i| ["low", "high"][i % 2]));
+        mdf.add_channel(
+            "Label".to_string(),
+            Arc::new(labels),
+            None,
+            None,
+            false,
+            None,
+            None,
+        )?;
+        let directory = std::env::temp_dir().join("mdfr_parquet_dataset");
+        let options = ParquetOptions {
+            compression: Some("snappy".to_string()),
+            dataset_directory: true,
+            max_row_group_size: 30000,
+            ..Default::default()
+        };
+        mdf.export_to_parquet_with_options(
+            directory.to_str().context("invalid temp path")?,
+            &options,
+        )?;
+        let parquet_file = directory.join("Time.parquet");
+        let reader = SerializedFileReader::new(fs::File::open(&parquet_file)?)?;
+        let metadata = reader.metadata();
+        assert_eq!(metadata.file_metadata().num_rows(), 100000);
+        assert_eq!(metadata.num_row_groups(), 4);
+        let row_group = metadata.row_group(0);
+        let column = |name: &str| {
+            (0..row_group.num_columns())
+                .map(|i| row_group.column(i))
+                .find(|column| column.column_path().string() == name)
+                .context("missing column")
+        };
+        assert!(!column("Value")?
+            .encodings()
+            .contains(&Encoding::RLE_DICTIONARY));
+        assert!(column("Time")?.statistics().is_some());
+        // text channel without master, file named by record id
+        let reader = SerializedFileReader::new(fs::File::open(directory.join("0.parquet"))?)?;
+        let label = reader.metadata().row_group(0).column(0);
+        assert_eq!(label.column_path().string(), "Label");
+        assert!(label.encodings().contains(&Encoding::RLE_DICTIONARY));
+        // no statistics
+        let options = ParquetOptions {
+            dataset_directory: true,
+            statistics: false,
+            ..Default::default()
+        };
+        mdf.export_to_parquet_with_options(
+            directory.to_str().context("invalid temp path")?,
+            &options,
+        )?;
+        let reader = SerializedFileReader::new(fs::File::open(&parquet_file)?)?;
+        let row_group = reader.metadata().row_group(0);
+        assert_eq!(reader.metadata().num_row_groups(), 1);
+        assert!(row_group.column(0).statistics().is_none());
+        // empty row groups are refused
+        let options = ParquetOptions {
+            max_row_group_size: 0,
+            ..options
+        };
+        assert!(mdf
+            .export_to_parquet_with_options(
+                directory.to_str().context("invalid temp path")?,
+                &options
+            )
+            .is_err());
+        fs::remove_dir_all(&directory)?;
         Ok(())
     }
     #[test]
-    fn conversion() -> Result<()> {
-        let list_of_paths = [
-            "Conversion/LinearConversion/".to_string(),
-            "Conversion/LookUpConversion/".to_string(),
-            "Conversion/PartialConversion/".to_string(),
-            "Conversion/RationalConversion/".to_string(),
-            "Conversion/StringConversion/".to_string(),
-            "Conversion/TextConversion/".to_string(),
-        ];
-
-        // Lindear conversion testing
-        let file_name = format!(
-            "{}{}{}",
-            BASE_PATH_MDF4, list_of_paths[0], "Vector_LinearConversion.mf4"
-        );
-        let mut mdf = Mdf::new(&file_name)?;
+    fn parquet_metadata() -> Result<()> {
+        use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+        let mut mdf = Mdf::new("test_files/test_basic.mf4")?;
         mdf.load_all_channels_data_in_memory()?;
-        if let Some(data) = mdf.get_channel_data(&"Data channel".to_string()) {
-            let mut vect: Vec<f64> = vec![0.; 10];
-            let mut counter: f64 = 0.;
-            vect.iter_mut().for_each(|v| {
-                *v = counter.clone() * -3.2 - 4.8;
-                counter += 1.
-            });
-            assert_eq!(
-                &ChannelData::Float64(Float64Builder::new_from_buffer(vect.into(), None)),
-                data
-            );
-        }
-        let file_name = format!(
-            "{}{}{}",
-            BASE_PATH_MDF4, list_of_paths[0], "Vector_LinearConversionFactor0.mf4"
+        let parquet_file = std::env::temp_dir().join("mdfr_metadata");
+        let parquet_file = parquet_file.to_str().context("invalid temp path")?;
+        mdf.export_to_parquet(parquet_file, None)?;
+        let parquet_file = format!("{parquet_file}_Time.parquet");
+        let reader = ParquetRecordBatchReaderBuilder::try_new(fs::File::open(&parquet_file)?)?;
+        // file header in parquet key value metadata
+        let key_values: HashMap<String, Option<String>> = reader
+            .metadata()
+            .file_metadata()
+            .key_value_metadata()
+            .context("no key value metadata")?
+            .iter()
+            .map(|kv| (kv.key.clone(), kv.value.clone()))
+            .collect();
+        assert_eq!(key_values["mdf_version"], Some("410".to_string()));
+        assert_eq!(key_values["master_channel"], Some("Time".to_string()));
+        assert_eq!(
+            key_values["start_time"],
+            Some(mdf.get_start_time()?.to_rfc3339())
         );
-        let mut mdf = Mdf::new(&file_name)?;
-        mdf.load_all_channels_data_in_memory()?;
-        if let Some(data) = mdf.get_channel_data(&"Data channel".to_string()) {
-            let vect: Vec<f64> = vec![3.; 10];
-            assert_eq!(
-                &ChannelData::Float64(Float64Builder::new_from_buffer(vect.into(), None)),
-                data
-            );
+        // channel master and conversion in field metadata
+        let value = reader.schema().field_with_name("Value")?.metadata().clone();
+        assert_eq!(value["master_channel"], "Time");
+        assert_eq!(value["conversion_type"], "1");
+        assert_eq!(value["conversion_values"], "-800,0.25");
+        fs::remove_file(parquet_file)?;
+        Ok(())
+    }
+    #[test]
+    fn import_csv_and_parquet() -> Result<()> {
+        use crate::import::csv::CsvOptions;
+        use arrow::datatypes::Int64Type;
+        // csv with time master, integer, float and text columns
+        let csv_file = std::env::temp_dir().join("mdfr_import.csv");
+        let mut content = "time;speed;torque;gear\n".to_string();
+        for i in 0..200 {
+            content.push_str(&format!(
+                "{};{};{};g{}\n",
+                i as f64 * 0.01,
+                i,
+                i as f64 * 1.5,
+                i % 5
+            ));
         }
-        // Rational conversion
-        let file_name = format!(
-            "{}{}{}",
-            BASE_PATH_MDF4, list_of_paths[3], "Vector_RationalConversionIntParams.mf4"
+        fs::write(&csv_file, content)?;
+        let options = CsvOptions {
+            delimiter: b';',
+            master_channel: Some("time".to_string()),
+            ..Default::default()
+        };
+        let mut mdf = Mdf::from_csv(csv_file.to_str().context("invalid temp path")?, &options)?;
+        assert_eq!(mdf.get_channel_names_set().len(), 4);
+        assert_eq!(mdf.get_channel_master("torque"), Some("time".to_string()));
+        // written as valid mdf4
+        let writing_file = std::env::temp_dir().join("mdfr_import.mf4");
+        let writing_file = writing_file.to_str().context("invalid temp path")?;
+        mdf.write(writing_file, false)?;
+        let mut written = Mdf::new(writing_file)?;
+        written.load_all_channels_data_in_memory()?;
+        assert_eq!(
+            written.get_channel_master("speed"),
+            Some("time".to_string())
         );
-        let mut mdf = Mdf::new(&file_name)?;
+        let speed = written.get_channel_data("speed").context("no speed")?;
+        assert_eq!(speed.as_ref().as_primitive::<Int64Type>().value(150), 150);
+        let torque = written.get_channel_data("torque").context("no torque")?;
+        assert_eq!(torque.as_ref().as_primitive::<Float64Type>().value(3), 4.5);
+        let gear = written.get_channel_data("gear").context("no gear")?;
+        assert_eq!(gear.as_ref().as_string::<i64>().value(7), "g2");
+        fs::remove_file(csv_file)?;
+        // parquet exported by mdfr keeps its master, units and descriptions
+        let mut mdf = Mdf::new("test_files/test_basic.mf4")?;
         mdf.load_all_channels_data_in_memory()?;
-
-        // Text conversion
-        let file_name = format!(
-            "{}{}{}",
-            BASE_PATH_MDF4, list_of_paths[5], "Vector_AlgebraicConversionQuadratic.mf4"
+        let parquet_file = std::env::temp_dir().join("mdfr_import");
+        let parquet_file = parquet_file.to_str().context("invalid temp path")?;
+        mdf.export_to_parquet(parquet_file, None)?;
+        let parquet_file = format!("{parquet_file}_Time.parquet");
+        let mut imported = Mdf::from_parquet(&parquet_file, None)?;
+        assert_eq!(
+            imported.get_channel_master("Value"),
+            Some("Time".to_string())
         );
-        let mut mdf = Mdf::new(&file_name)?;
+        assert_eq!(
+            imported.get_channel_unit("Value")?,
+            mdf.get_channel_unit("Value")?
+        );
+        assert_eq!(
+            imported.get_channel_data("Value"),
+            mdf.get_channel_data("Value")
+        );
+        imported.write(writing_file, false)?;
+        let mut written = Mdf::new(writing_file)?;
+        written.load_all_channels_data_in_memory()?;
+        assert_eq!(
+            written.get_channel_data("Time"),
+            mdf.get_channel_data("Time")
+        );
+        assert!(Mdf::from_parquet(&parquet_file, Some("Speed")).is_err());
+        fs::remove_file(parquet_file)?;
+        fs::remove_file(writing_file)?;
+        Ok(())
+    }
+    #[test]
+    fn export_to_hdf5() -> Result<()> {
+        require_test_data!();
+        // Export mdf4 to Parquet file
+        let file = format!(
+            "{}{}",
+            *BASE_PATH_MDF4, &"Simple/PCV_iO_Gen3_LK1__3l_TDI.mf4"
+        );
+        let extension = "*.hdf5";
+        let mut mdf = Mdf::new(&file)?;
         mdf.load_all_channels_data_in_memory()?;
-        if let Some(data) = mdf.get_channel_data(&"Data channel".to_string()) {
-            let vect = Vec::from([1., 2., 5., 10., 17., 26., 37., 50., 65., 82.]);
-            assert_eq!(
-                &ChannelData::Float64(Float64Builder::new_from_buffer(vect.into(), None)),
-                data
-            );
-        }
-
-        // Lookup conversion : Value to Value Table With Interpolation
-        let file_name = format!(
-            "{}{}{}",
-            BASE_PATH_MDF4, list_of_paths[1], "Vector_Value2ValueConversionInterpolation.mf4"
+        mdf.export_to_hdf5(&WRITING_HDF5_FILE, Some(&"lzf"))
+            .expect("failed writing mdf4 hdf5 file");
+        // Export mdf3 to Parquet file
+        let file = format!(
+            "{}{}",
+            *BASE_PATH_MDF3, &"RJ_N16-12-363_BM-15C-0024_228_2_20170116094355_CAN.dat"
         );
-        let mut mdf = Mdf::new(&file_name)?;
+        let mut mdf = Mdf::new(&file)?;
         mdf.load_all_channels_data_in_memory()?;
-        if let Some(data) = mdf.get_channel_data(&"Data channel".to_string()) {
-            let vect = Vec::from([
-                -5.,
-                -5.,
-                -5.,
-                -5.,
-                -4.5,
-                -4.,
-                -3.5,
-                -3.,
-                -2.5,
-                -2.,
-                -4. / 3.,
-                -2. / 3.,
-                0.,
-                1. / 3.,
-                2. / 3.,
-                1.,
-                1.5,
-                2.,
-                1.,
-                0.,
-                1.5,
-                3.,
-                4.5,
-                6.,
-                4.5,
-                3.,
-                1.5,
-                0.,
-                0.,
-                0.,
-            ]);
-            assert_eq!(
-                &ChannelData::Float64(Float64Builder::new_from_buffer(vect.into(), None)),
-                data
-            );
+        mdf.export_to_hdf5(&WRITING_HDF5_FILE, Some(&"deflate"))
+            .expect("failed writing mdf3 hdf5 file");
+        // remove all generated hdf5 files
+        let pattern = format!("{}/{}", *BASE_TEST_PATH, extension);
+        for path in glob(&pattern).unwrap().filter_map(Result::ok) {
+            fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn streaming_writer() -> Result<()> {
+        use crate::mdfwriter::streaming4::{MdfWriter, RecordValue, StreamChannel};
+        let file_name = std::env::temp_dir().join("test_streaming.mf4");
+        let file_name = file_name.to_str().context("invalid temp path")?;
+        let mut writer = MdfWriter::create(file_name)?;
+        let fast = writer.add_channel_group(&[
+            StreamChannel::master("fast_time", DataType::Float64),
+            StreamChannel::new("speed", DataType::UInt16).with_unit("km/h"),
+        ])?;
+        let slow = writer.add_channel_group(&[
+            StreamChannel::master("slow_time", DataType::Float32),
+            StreamChannel::new("temperature", DataType::Int32),
+        ])?;
+        // interleaved writes, the fast group spanning several DT blocks
+        let n_fast: usize = 500_000;
+        for chunk in 0..10 {
+            let records = (chunk * n_fast / 10..(chunk + 1) * n_fast / 10).map(|i| {
+                [
+                    RecordValue::from(i as f64 * 0.001),
+                    ((i % 300) as u16).into(),
+                ]
+            });
+            assert_eq!(writer.write_records(fast, records)?, (n_fast / 10) as u64);
+            writer.write_records(
+                slow,
+                [[RecordValue::from(chunk as f32), (-(chunk as i32)).into()]],
+            )?;
         }
+        assert!(writer
+            .write_records(slow, [[RecordValue::from(1.0)]])
+            .is_err());
+        writer.finalize()?;
 
-        // Lookup conversion : Value to Value Table Without Interpolation
-        let file_name = format!(
-            "{}{}{}",
-            BASE_PATH_MDF4, list_of_paths[1], "Vector_Value2ValueConversionNoInterpolation.mf4"
-        );
-        let mut mdf = Mdf::new(&file_name)?;
+        let mut mdf = Mdf::new(file_name)?;
         mdf.load_all_channels_data_in_memory()?;
-        if let Some(data) = mdf.get_channel_data(&"Data channel".to_string()) {
-            let vect = Vec::from([
-                -5., -5., -5., -5., -5., -5., -5., -2., -2., -2., -2., 0., 0., 0., 1., 1., 1., 2.,
-                2., 0., 0., 3., 3., 6., 6., 3., 3., 0., 0., 0.,
-            ]);
-            assert_eq!(
-                &ChannelData::Float64(Float64Builder::new_from_buffer(vect.into(), None)),
-                data
-            );
+        assert_eq!(
+            mdf.get_channel_master("speed"),
+            Some("fast_time".to_string())
+        );
+        assert_eq!(mdf.get_channel_unit("speed")?, Some("km/h".to_string()));
+        let speed = mdf.get_channel_data("speed").context("speed not found")?;
+        let speed = cast(&speed.as_ref(), &DataType::Float64)?;
+        let speed = speed.as_primitive::<Float64Type>().values();
+        assert_eq!(speed.len(), n_fast);
+        assert!(speed
+            .iter()
+            .enumerate()
+            .all(|(i, v)| *v == (i % 300) as f64));
+        let time = mdf
+            .get_channel_data("fast_time")
+            .context("time not found")?;
+        let time = cast(&time.as_ref(), &DataType::Float64)?;
+        assert_eq!(
+            time.as_primitive::<Float64Type>().value(n_fast - 1),
+            (n_fast - 1) as f64 * 0.001
+        );
+        let temperature = mdf
+            .get_channel_data("temperature")
+            .context("temperature not found")?;
+        let temperature = cast(&temperature.as_ref(), &DataType::Float64)?;
+        assert_eq!(
+            temperature.as_primitive::<Float64Type>().values().to_vec(),
+            (0..10).map(|i| -i as f64).collect::<Vec<f64>>()
+        );
+        fs::remove_file(file_name)?;
+        Ok(())
+    }
+
+    #[test]
+    fn unsorted_streaming_writer() -> Result<()> {
+        use crate::mdfwriter::streaming4::{MdfWriter, RecordValue, StreamChannel};
+        use arrow::array::Array;
+        let file_name = std::env::temp_dir().join("test_unsorted_streaming.mf4");
+        let file_name = file_name.to_str().context("invalid temp path")?;
+        let mut writer = MdfWriter::create(file_name)?;
+        let groups = writer.add_unsorted_data_group(&[
+            &[
+                StreamChannel::master("can_time", DataType::Float64),
+                StreamChannel::new("can_id", DataType::UInt32),
+                StreamChannel::new("can_payload", DataType::LargeBinary),
+            ],
+            &[
+                StreamChannel::master("log_time", DataType::Float64),
+                StreamChannel::new("log_message", DataType::LargeUtf8),
+            ],
+        ])?;
+        let notes = writer.add_channel_group(&[
+            StreamChannel::master("note_time", DataType::Float64),
+            StreamChannel::new("note", DataType::LargeUtf8),
+        ])?;
+        // asynchronous sources spanning several DT blocks
+        let n_frames: usize = 200_000;
+        for i in 0..n_frames {
+            let payload = vec![(i % 256) as u8; 1 + i % 8];
+            writer.write_records(
+                groups[0],
+                [[
+                    RecordValue::from(i as f64),
+                    ((i % 2048) as u32).into(),
+                    payload.into(),
+                ]],
+            )?;
+            if i % 1000 == 0 {
+                writer.write_records(
+                    groups[1],
+                    [[RecordValue::from(i as f64), format!("frame {i}").into()]],
+                )?;
+            }
         }
+        writer.write_records(notes, [[RecordValue::from(0.5), "start".into()]])?;
+        assert!(writer
+            .write_records(groups[1], [[RecordValue::from(0.0), 1u8.into()]])
+            .is_err());
+        writer.finalize()?;
 
-        // Lookup conversion : Value Range to Value
-        let file_name = format!(
-            "{}{}{}",
-            BASE_PATH_MDF4, list_of_paths[1], "Vector_ValueRange2ValueConversion.mf4"
-        );
-        let mut mdf = Mdf::new(&file_name)?;
+        let mut mdf = Mdf::new(file_name)?;
         mdf.load_all_channels_data_in_memory()?;
-        if let Some(data) = mdf.get_channel_data(&"Data channel".to_string()) {
-            let vect = Vec::from([
-                -1.0, -1.0, -1.0, -1.0, -1.0, -1.0, 0.0, 0.0, 0.0, 1.0, 1.0, 2.0, 2.0, 2.0, 2.0,
-                2.0, 3.0, 3.0, 5.0, 5.0, 5.0, 6.0, 7.0, 7.0, 8.0, 8.0, 9.0, 9.0, 9.0, 9.0,
-            ]);
-            assert_eq!(
-                &ChannelData::Float64(Float64Builder::new_from_buffer(vect.into(), None)),
-                data
-            );
-        }
+        assert_eq!(
+            mdf.get_channel_master("can_id"),
+            Some("can_time".to_string())
+        );
+        let ids = mdf.get_channel_data("can_id").context("can_id not found")?;
+        let ids = cast(&ids.as_ref(), &DataType::Float64)?;
+        let ids = ids.as_primitive::<Float64Type>().values();
+        assert_eq!(ids.len(), n_frames);
+        assert!(ids.iter().enumerate().all(|(i, v)| *v == (i % 2048) as f64));
+        let payloads = mdf
+            .get_channel_data("can_payload")
+            .context("can_payload not found")?
+            .as_ref();
+        let payloads = payloads.as_binary::<i64>();
+        assert_eq!(payloads.len(), n_frames);
+        assert_eq!(payloads.value(n_frames - 1), vec![(n_frames - 1) as u8; 8]);
+        let messages = mdf
+            .get_channel_data("log_message")
+            .context("log_message not found")?
+            .as_ref();
+        let messages = messages.as_string::<i64>();
+        assert_eq!(messages.len(), n_frames / 1000);
+        assert_eq!(messages.value(3), "frame 3000");
+        let note = mdf
+            .get_channel_data("note")
+            .context("note not found")?
+            .as_ref();
+        assert_eq!(note.as_string::<i64>().value(0), "start");
+        fs::remove_file(file_name)?;
+        Ok(())
+    }
 
-        // Lookup conversion : Value to Text
-        let file_name = format!(
-            "{}{}{}",
-            BASE_PATH_MDF4, list_of_paths[1], "Vector_Value2TextConversion.mf4"
+    #[test]
+    fn streaming_writer_crash() -> Result<()> {
+        use crate::mdfwriter::streaming4::{MdfWriter, RecordValue, StreamChannel};
+        let file_name = std::env::temp_dir().join("test_streaming_crash.mf4");
+        let file_name = file_name.to_str().context("invalid temp path")?;
+        let channels = [
+            StreamChannel::master("time", DataType::Float64),
+            StreamChannel::new("rpm", DataType::UInt32),
+        ];
+        let record = |i: usize| [RecordValue::from(i as f64 * 0.01), (i as u32 * 3).into()];
+        // recording interrupted after two flushes, buffered records are lost
+        let mut writer = MdfWriter::create(file_name)?;
+        let group = writer.add_channel_group(&channels)?;
+        writer.write_records(group, (0..1000).map(record))?;
+        writer.flush()?;
+        writer.write_records(group, (1000..1500).map(record))?;
+        writer.flush()?;
+        writer.write_records(group, (1500..1600).map(record))?;
+        std::mem::forget(writer);
+        assert_eq!(&fs::read(file_name)?[..8], b"UnFinMF ");
+        assert!(Mdf::new(file_name).is_err());
+        let mut mdf = Mdf::repair_unfinalized(file_name)?;
+        mdf.load_all_channels_data_in_memory()?;
+        let rpm = mdf.get_channel_data("rpm").context("rpm not found")?;
+        let rpm = cast(&rpm.as_ref(), &DataType::Float64)?;
+        assert_eq!(
+            rpm.as_primitive::<Float64Type>().values().to_vec(),
+            (0..1500).map(|i| (i * 3) as f64).collect::<Vec<f64>>()
         );
-        let mut mdf = Mdf::new(&file_name)?;
+
+        // finalized on close
+        let mut writer = MdfWriter::create(file_name)?;
+        let group = writer.add_channel_group(&channels)?;
+        writer.write_records(group, (0..10).map(record))?;
+        writer.finalize()?;
+        let bytes = fs::read(file_name)?;
+        assert_eq!(&bytes[..8], b"MDF     ");
+        assert_eq!(&bytes[60..62], &[0, 0]);
+        let mut mdf = Mdf::new(file_name)?;
         mdf.load_all_channels_data_in_memory()?;
-        if let Some(data) = mdf.get_channel_data(&"Data channel".to_string()) {
-            let mut target = LargeStringBuilder::with_capacity(10, 20);
-            target.append_value("No match");
-            target.append_value("first gear");
-            target.append_value("second gear");
-            target.append_value("third gear");
-            target.append_value("fourth gear");
-            target.append_value("fifth gear");
-            target.append_value("No match");
-            target.append_value("No match");
-            target.append_value("No match");
-            target.append_value("No match");
-            assert_eq!(&ChannelData::Utf8(target), data);
+        assert_eq!(
+            mdf.get_channel_data("rpm").context("rpm not found")?.len(),
+            10
+        );
+        fs::remove_file(file_name)?;
+        Ok(())
+    }
+
+    #[test]
+    fn mdf3_triggers() -> Result<()> {
+        let file = "test_files/test_mdf3.mdf";
+        let mut bytes = fs::read(file)?;
+        let mut mdf = Mdf::from_bytes(&bytes, file)?;
+        assert!(mdf.get_events()?.is_empty());
+        // master of the first data group
+        let dg_first = u32::from_le_bytes(bytes[68..72].try_into()?);
+        let MdfInfo::V3(info) = &mdf.mdf_info else {
+            panic!("mdf3 file expected")
+        };
+        let master = info
+            .dg
+            .values()
+            .find(|dg| dg.block_position == dg_first)
+            .and_then(|dg| dg.cg.values().find_map(|cg| cg.master_channel_name.clone()))
+            .context("no master in first data group")?;
+        mdf.load_channels_data_in_memory(HashSet::from([master.clone()]))?;
+        let time = mdf.get_channel_data(&master).context("master not loaded")?;
+        let time = cast(&time.as_ref(), &DataType::Float64)?;
+        let time = time.as_primitive::<Float64Type>().values().to_vec();
+        let trigger = time[time.len() / 2];
+        let (pre_time, post_time) = (trigger - time[1], time[time.len() - 2] - trigger);
+
+        // appends a trigger block with its comment, linked to the first data group
+        let tr_position = bytes.len() as u32;
+        let position = dg_first as usize + 12;
+        bytes[position..position + 4].copy_from_slice(&tr_position.to_le_bytes());
+        bytes.extend(b"TR");
+        bytes.extend(34u16.to_le_bytes());
+        bytes.extend((tr_position + 34).to_le_bytes());
+        bytes.extend(1u16.to_le_bytes());
+        for value in [trigger, pre_time, post_time] {
+            bytes.extend(value.to_le_bytes());
         }
+        bytes.extend(b"TX");
+        bytes.extend(12u16.to_le_bytes());
+        bytes.extend(b"manual\0\0");
 
-        // Lookup conversion : Value range to Text
-        let file_name = format!(
-            "{}{}{}",
-            BASE_PATH_MDF4, list_of_paths[1], "Vector_ValueRange2TextConversion.mf4"
+        let mut mdf = Mdf::from_bytes(&bytes, file)?;
+        let events = mdf.get_events()?;
+        assert_eq!(events.len(), 3);
+        assert!(events
+            .iter()
+            .all(|event| event.event_type == EventType::Trigger
+                && event.comment.as_deref() == Some("manual")));
+        assert_eq!(events[0].range_type, 1);
+        assert_eq!(events[1].sync_value, trigger);
+        assert_eq!(events[2].range_type, 2);
+        assert!(events[1]
+            .scope
+            .contains(&EventScope::ChannelGroup(master.clone())));
+        // cuts the data group within the trigger window
+        let (t_start, t_stop) = (events[0].sync_value, events[2].sync_value);
+        let expected = time
+            .iter()
+            .filter(|t| **t >= t_start && **t <= t_stop)
+            .count();
+        assert!(expected > 0 && expected < time.len());
+        mdf.load_channels_data_in_memory_between(HashSet::from([master.clone()]), t_start, t_stop)?;
+        assert_eq!(
+            mdf.get_channel_data(&master).map(|data| data.len()),
+            Some(expected)
         );
-        let mut mdf = Mdf::new(&file_name)?;
-        mdf.load_all_channels_data_in_memory()?;
-        if let Some(data) = mdf.get_channel_data(&"Data channel".to_string()) {
-            let mut target = LargeStringBuilder::with_capacity(10, 20);
-            target.append_value("Out of range");
-            target.append_value("very low");
-            target.append_value("very low");
-            target.append_value("very low");
-            target.append_value("low");
-            target.append_value("low");
-            target.append_value("medium");
-            target.append_value("medium");
-            target.append_value("high");
-            target.append_value("high");
-            assert_eq!(&ChannelData::Utf8(target), data);
-        }
+        Ok(())
+    }
 
-        // Lookup conversion : Value range to Text,
-        let file_name = format!(
-            "{}{}{}",
-            BASE_PATH_MDF4, list_of_paths[2], "Vector_StatusStringTableConversionAlgebraic.mf4"
+    #[test]
+    fn mdf3_program_block() -> Result<()> {
+        let file_name = "test_files/test_mdf3.mdf";
+        let mut mdf = Mdf::new(file_name)?;
+        let program = mdf.get_program_block().context("no program block")?;
+        assert_eq!(program.len(), 4208);
+        let (_master, channel_names) = mdf
+            .get_master_channel_names_set()
+            .into_iter()
+            .find(|(master, _)| master.is_some())
+            .context("no channel group with master")?;
+        mdf.load_channels_data_in_memory(channel_names.clone())?;
+        let MdfInfo::V3(info3) = &mdf.mdf_info else {
+            panic!("mdf3 file expected")
+        };
+        // program block kept as attachment of converted file
+        let mut mdf4 = Mdf {
+            mdf_info: MdfInfo::V4(Box::new(convert3to4(info3, file_name)?)),
+            stats: HashMap::new(),
+            master_fixes: Vec::new(),
+        };
+        for name in mdf4.get_channel_names_set().difference(&channel_names) {
+            mdf4.remove_channel(name);
+        }
+        let writing_file = std::env::temp_dir().join("mdfr_program_block.mf4");
+        let writing_file = writing_file.to_str().context("invalid temp path")?;
+        mdf4.write(writing_file, false)?;
+        let written = Mdf::new(writing_file)?;
+        fs::remove_file(writing_file)?;
+        assert_eq!(written.get_program_block(), None);
+        let MdfInfo::V4(info) = &written.mdf_info else {
+            panic!("mdf4 file expected")
+        };
+        let (position, block) = info
+            .get_attachment_blocks()
+            .into_iter()
+            .next()
+            .context("no attachment written")?;
+        assert_eq!(
+            info.sharable.get_tx(block.at_tx_filename)?.as_deref(),
+            Some(PROGRAM_BLOCK_FILE_NAME)
         );
-        let mut mdf = Mdf::new(&file_name)?;
+        assert_eq!(info.get_attachment_embedded_data(position), Some(program));
+        Ok(())
+    }
+
+    #[test]
+    fn string_policy() -> Result<()> {
+        use crate::mdfreader::StringPolicy;
+        use arrow::array::LargeStringArray;
+        let mut mdf = Mdf::new("test_files/test_basic.mf4")?;
         mdf.load_all_channels_data_in_memory()?;
-        if let Some(data) = mdf.get_channel_data(&"Data channel".to_string()) {
-            let mut vect: Vec<f64> = vec![0.; 300];
-            let mut counter: f64 = 0.;
-            vect.iter_mut().for_each(|v| {
-                *v = counter.clone();
-                counter += 0.1
-            });
-            let mut target = LargeStringBuilder::with_capacity(vect.len(), 32);
-            vect.iter().for_each(|v| {
-                if 9.9999 <= *v && *v <= 10.1001 {
-                    target.append_value("Illegal value".to_string())
-                } else if 20.0 <= *v && *v <= 30.0 {
-                    target.append_value("Out of range".to_string())
-                } else {
-                    target.append_value((10.0 / (v - 10.0)).to_string())
-                }
-            });
-            let data_values = data.finish_cloned();
-            let data_values = data_values
-                .as_string::<i64>()
-                .iter()
-                .collect::<Vec<Option<&str>>>();
-            let target_values = target.finish_cloned();
-            let target_values = target_values.iter().collect::<Vec<Option<&str>>>();
-            assert_eq!(target_values[0], data_values[0]);
-            assert_eq!(target_values[299], data_values[299]);
-            assert_eq!(target_values[101], data_values[101]);
+        let n = mdf
+            .get_channel_data("Time")
+            .context("Time not found")?
+            .len();
+        let labels: LargeStringArray = (0..n)
+            .map(|i| Some(if i == 1 { "mdfr_BAD_mdfr" } else { "ok" }))
+            .collect();
+        mdf.add_channel(
+            "Label".to_string(),
+            Arc::new(labels),
+            Some("Time".to_string()),
+            None,
+            false,
+            None,
+            None,
+        )?;
+        let writing_file = std::env::temp_dir().join("mdfr_string_policy.mf4");
+        let writing_file = writing_file.to_str().context("invalid temp path")?;
+        mdf.write(writing_file, false)?;
+        // corrupts the string with an invalid UTF-8 byte
+        let mut bytes = fs::read(writing_file)?;
+        let position = bytes
+            .windows(13)
+            .position(|window| window == b"mdfr_BAD_mdfr")
+            .context("string not found in file")?;
+        bytes[position + 5] = 0xFF;
+        fs::write(writing_file, bytes)?;
+        let channels = HashSet::from(["Label".to_string()]);
+        let mut written = Mdf::new(writing_file)?;
+        assert!(written
+            .load_channels_data_in_memory(channels.clone())
+            .is_err());
+        for (string_policy, expected) in [
+            (StringPolicy::Lossy, "mdfr_\u{FFFD}AD_mdfr"),
+            (StringPolicy::Latin1Fallback, "mdfr_\u{FF}AD_mdfr"),
+        ] {
+            let mut written = Mdf::new(writing_file)?;
+            let options = ReadOptions {
+                string_policy,
+                ..Default::default()
+            };
+            written.load_channels_data_in_memory_with_options(channels.clone(), &options)?;
+            let label = written
+                .get_channel_data("Label")
+                .context("Label not found")?
+                .as_ref();
+            let label = label.as_string::<i64>();
+            assert_eq!(label.value(0), "ok");
+            assert_eq!(label.value(1), expected);
         }
+        fs::remove_file(writing_file)?;
+        Ok(())
+    }
 
-        // Text conversion : Text to Value
-        let file_name = format!(
-            "{}{}{}",
-            BASE_PATH_MDF4, list_of_paths[4], "Vector_Text2ValueConversion.mf4"
+    #[test]
+    fn raw_data_and_conversion() -> Result<()> {
+        use arrow::compute::cast;
+        use arrow::datatypes::{DataType, Float64Type};
+        let channels = HashSet::from(["Value".to_string()]);
+        let mut mdf = Mdf::new("test_files/test_basic.mf4")?;
+        let conversion = mdf
+            .get_channel_conversion("Value")?
+            .context("Value conversion not found")?;
+        assert_eq!(conversion.conversion_type, 1);
+        assert_eq!(conversion.values, vec![-800.0, 0.25]);
+        mdf.load_channels_data_in_memory(channels.clone())?;
+        assert!(mdf.get_channel_raw_data("Value").is_none());
+        let mut mdf = Mdf::new("test_files/test_basic.mf4")?;
+        let options = ReadOptions {
+            keep_raw: true,
+            ..Default::default()
+        };
+        mdf.load_channels_data_in_memory_with_options(channels, &options)?;
+        let raw = mdf
+            .get_channel_raw_data("Value")
+            .context("Value raw data not found")?;
+        let raw = cast(&raw.as_ref(), &DataType::Float64)?;
+        let physical = mdf.get_channel_data("Value").context("Value not found")?;
+        let physical = cast(&physical.as_ref(), &DataType::Float64)?;
+        assert_eq!(raw.len(), physical.len());
+        assert!(raw
+            .as_primitive::<Float64Type>()
+            .iter()
+            .zip(physical.as_primitive::<Float64Type>().iter())
+            .all(|(raw, physical)| raw.map(|raw| -800.0 + 0.25 * raw) == physical));
+        Ok(())
+    }
+
+    #[test]
+    fn lazy_conversion() -> Result<()> {
+        let channels = HashSet::from(["Value".to_string()]);
+        let mut mdf = Mdf::new("test_files/test_basic.mf4")?;
+        mdf.load_channels_data_in_memory(channels.clone())?;
+        let expected = mdf
+            .get_channel_data("Value")
+            .context("Value not found")?
+            .as_ref();
+        let mut lazy = Mdf::new("test_files/test_basic.mf4")?;
+        let options = ReadOptions {
+            lazy_conversion: true,
+            ..Default::default()
+        };
+        lazy.load_channels_data_in_memory_with_options(channels, &options)?;
+        // raw values are kept until channel data is accessed
+        let raw = lazy
+            .get_channel_raw_data("Value")
+            .context("Value raw data not found")?;
+        assert_ne!(&raw.arrow_data_type(), expected.data_type());
+        let physical = lazy.get_channel_data("Value").context("Value not found")?;
+        assert_eq!(&physical.as_ref(), &expected);
+        // conversion result is cached
+        let cached = lazy.get_channel_data("Value").context("Value not found")?;
+        assert!(std::ptr::eq(physical, cached));
+        Ok(())
+    }
+
+    #[test]
+    fn dtype_overrides() -> Result<()> {
+        use crate::mdfinfo::mdfinfo4::CcVal;
+        use crate::mdfreader::DataTypeOverride;
+        use arrow::datatypes::Int64Type;
+        let channels = HashSet::from(["Value".to_string()]);
+        let mut mdf = Mdf::new("test_files/test_basic.mf4")?;
+        mdf.load_channels_data_in_memory(channels.clone())?;
+        let expected = mdf
+            .get_channel_data("Value")
+            .context("Value not found")?
+            .as_ref();
+        let mut options = ReadOptions {
+            keep_raw: true,
+            ..Default::default()
+        };
+        options
+            .dtype_overrides
+            .insert("Value".to_string(), DataTypeOverride::Float32);
+        let mut mdf = Mdf::new("test_files/test_basic.mf4")?;
+        mdf.load_channels_data_in_memory_with_options(channels.clone(), &options)?;
+        let data = mdf.get_channel_data("Value").context("Value not found")?;
+        assert!(matches!(data, ChannelData::Float32(_)));
+        assert_eq!(
+            data.as_ref().as_ref(),
+            cast(&expected, &DataType::Float32)?.as_ref()
         );
-        let mut mdf = Mdf::new(&file_name)?;
-        mdf.load_all_channels_data_in_memory()?;
-        if let Some(data) = mdf.get_channel_data(&"Data channel".to_string()) {
-            let vect = Vec::from([-50., 1., 2., 3., 4., 5., 6., 7., 8., 9.]);
+        // factor 0.25 is not an integer, channel is converted into f64
+        options
+            .dtype_overrides
+            .insert("Value".to_string(), DataTypeOverride::Integer);
+        let mut mdf = Mdf::new("test_files/test_basic.mf4")?;
+        mdf.load_channels_data_in_memory_with_options(channels.clone(), &options)?;
+        let data = mdf.get_channel_data("Value").context("Value not found")?;
+        assert_eq!(data.as_ref().as_ref(), expected.as_ref());
+        // integer factor and offset keep integers
+        let mut mdf = Mdf::new("test_files/test_basic.mf4")?;
+        let MdfInfo::V4(info) = &mut mdf.mdf_info else {
+            panic!("mdf4 file expected")
+        };
+        let (_master, dg_pos, (_cg_pos, rec_id), (_cn_pos, rec_pos)) = info
+            .get_channel_id("Value")
+            .cloned()
+            .context("channel missing")?;
+        let cc_position = info
+            .dg
+            .get(&dg_pos)
+            .and_then(|dg| dg.cg.get(&rec_id))
+            .and_then(|cg| cg.cn.get(&rec_pos))
+            .context("channel block missing")?
+            .block
+            .cn_cc_conversion;
+        info.sharable
+            .cc
+            .get_mut(&cc_position)
+            .context("conversion block missing")?
+            .cc_val = CcVal::Real(vec![-800.0, 2.0]);
+        mdf.load_channels_data_in_memory_with_options(channels, &options)?;
+        let raw = mdf
+            .get_channel_raw_data("Value")
+            .context("raw data missing")?
+            .as_ref();
+        let raw = cast(&raw, &DataType::Int64)?;
+        let data = mdf.get_channel_data("Value").context("Value not found")?;
+        assert!(data.arrow_data_type().is_integer());
+        assert!(cast(&data.as_ref(), &DataType::Int64)?
+            .as_primitive::<Int64Type>()
+            .iter()
+            .zip(raw.as_primitive::<Int64Type>().iter())
+            .all(|(value, raw)| value == raw.map(|raw| raw * 2 - 800)));
+        Ok(())
+    }
+
+    #[test]
+    fn float32_conversions() -> Result<()> {
+        let channels = HashSet::from(["Value".to_string()]);
+        let mut mdf = Mdf::new("test_files/test_basic.mf4")?;
+        mdf.load_channels_data_in_memory(channels.clone())?;
+        let expected = mdf
+            .get_channel_data("Value")
+            .context("Value not found")?
+            .as_ref();
+        let options = ReadOptions {
+            float32_conversions: true,
+            keep_raw: true,
+            ..Default::default()
+        };
+        let mut mdf = Mdf::new("test_files/test_basic.mf4")?;
+        mdf.load_channels_data_in_memory_with_options(channels, &options)?;
+        let raw = mdf
+            .get_channel_raw_data("Value")
+            .context("raw data missing")?;
+        assert!(raw
+            .arrow_data_type()
+            .primitive_width()
+            .is_some_and(|width| width <= 4));
+        let data = mdf.get_channel_data("Value").context("Value not found")?;
+        assert!(matches!(data, ChannelData::Float32(_)));
+        assert_eq!(
+            data.as_ref().as_ref(),
+            cast(&expected, &DataType::Float32)?.as_ref()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn integer_linear_conversion() -> Result<()> {
+        use crate::mdfinfo::mdfinfo4::{Cc4Block, Cn4, SharableBlocks};
+        use crate::mdfreader::conversions4::convert_channel;
+        use arrow::datatypes::UInt8Type;
+        let mut sharable = SharableBlocks::new(3);
+        sharable.cc.insert(1, Cc4Block::linear(10.0, 2.0));
+        sharable.cc.insert(2, Cc4Block::linear(10.0, 0.5));
+        let data = || {
+            ChannelData::UInt8(PrimitiveBuilder::<UInt8Type>::new_from_buffer(
+                vec![0u8, 1, 255].into(),
+                None,
+            ))
+        };
+        let mut cn = Cn4 {
+            data: data(),
+            ..Default::default()
+        };
+        cn.block.cn_cc_conversion = 1;
+        convert_channel(&mut cn, &sharable)?;
+        // u8 range converted does not fit in u8
+        let ChannelData::Int16(converted) = &cn.data else {
+            panic!("i16 data expected, got {:?}", cn.data.arrow_data_type())
+        };
+        assert_eq!(converted.values_slice(), &[10i16, 12, 520]);
+        let mut cn = Cn4 {
+            data: data(),
+            ..Default::default()
+        };
+        cn.block.cn_cc_conversion = 2;
+        convert_channel(&mut cn, &sharable)?;
+        assert_eq!(
+            cn.data.as_ref().as_primitive::<Float64Type>().values(),
+            &[10.0, 10.5, 137.5]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn text_table_dictionary() -> Result<()> {
+        use crate::data_holder::channel_data::try_from;
+        use crate::mdfinfo::mdfinfo4::{Cc4Block, CcVal, Cn4, SharableBlocks};
+        use crate::mdfreader::conversions4::convert_channel;
+        use arrow::datatypes::UInt8Type;
+        let mut sharable = SharableBlocks::new(3);
+        sharable.create_tx(10, "off".to_string());
+        sharable.create_tx(20, "on".to_string());
+        sharable.create_tx(30, "unknown".to_string());
+        let mut cc = Cc4Block::linear(0.0, 1.0);
+        cc.cc_type = 7;
+        cc.cc_val = CcVal::Real(vec![0.0, 1.0]);
+        cc.cc_ref = vec![10, 20, 30];
+        sharable.cc.insert(1, cc);
+        let mut cn = Cn4 {
+            data: ChannelData::UInt8(PrimitiveBuilder::<UInt8Type>::new_from_buffer(
+                vec![0u8, 1, 1, 5, 0].into(),
+                None,
+            )),
+            ..Default::default()
+        };
+        cn.block.cn_cc_conversion = 1;
+        convert_channel(&mut cn, &sharable)?;
+        let ChannelData::Dictionary(dictionary) = &cn.data else {
+            panic!("text table conversion shall return a dictionary");
+        };
+        // one label stored per text, not per sample
+        assert_eq!(dictionary.values().len(), 3);
+        let labels = dictionary
+            .downcast_dict::<StringArray>()
+            .context("dictionary values are not strings")?
+            .into_iter()
+            .collect::<Vec<Option<&str>>>();
+        assert_eq!(
+            labels,
+            vec![
+                Some("off"),
+                Some("on"),
+                Some("on"),
+                Some("unknown"),
+                Some("off")
+            ]
+        );
+        assert_eq!(cn.data.byte_count(), 7);
+        assert_eq!(cn.data.to_bytes()?.len(), 5 * 7);
+        assert_eq!(try_from(cn.data.as_ref().as_ref())?, cn.data);
+        Ok(())
+    }
+
+    #[test]
+    fn value_table_conversions() -> Result<()> {
+        use crate::mdfinfo::mdfinfo4::{Cc4Block, CcVal, Cn4, SharableBlocks};
+        use crate::mdfreader::conversions4::convert_channel;
+        use arrow::datatypes::UInt8Type;
+        let table = |cc_type: u8, cc_val: Vec<f64>, cc_ref: Vec<i64>| {
+            let mut cc = Cc4Block::linear(0.0, 1.0);
+            cc.cc_type = cc_type;
+            cc.cc_val = CcVal::Real(cc_val);
+            cc.cc_ref = cc_ref;
+            cc
+        };
+        let mut sharable = SharableBlocks::new(3);
+        // ranges [0, 10] and [20, 30], default value 999
+        let ranges = vec![0.0, 10.0, 100.0, 20.0, 30.0, 200.0, 999.0];
+        sharable.cc.insert(1, table(6, ranges, vec![]));
+        sharable
+            .cc
+            .insert(2, table(4, vec![1.0, 10.0, 3.0, 30.0], vec![]));
+        // text table of value 0, value 1 scaled by value to value table and others by
+        // value ranges [2, 4] and [5, 9] to value table, default value -1
+        sharable.create_tx(10, "off".to_string());
+        sharable
+            .cc
+            .insert(3, table(5, vec![1.0, 11.0, 3.0, 33.0], vec![]));
+        let ranges = vec![2.0, 4.0, 40.0, 5.0, 9.0, 90.0, -1.0];
+        sharable.cc.insert(4, table(6, ranges, vec![]));
+        sharable
+            .cc
+            .insert(5, table(7, vec![0.0, 1.0], vec![10, 3, 4]));
+        // last sample is invalid
+        let convert = |cc_position: i64, values: &[u8]| -> Result<Cn4> {
+            let mut data = PrimitiveBuilder::<UInt8Type>::new();
+            data.append_slice(values);
+            data.append_null();
+            let mut cn = Cn4 {
+                data: ChannelData::UInt8(data),
+                ..Default::default()
+            };
+            cn.block.cn_cc_conversion = cc_position;
+            convert_channel(&mut cn, &sharable)?;
+            Ok(cn)
+        };
+        let cn = convert(1, &[0, 10, 11, 19, 20, 30, 31])?;
+        assert_eq!(
+            cn.data.as_ref().as_primitive::<Float64Type>(),
+            &Float64Array::from(vec![
+                Some(100.0),
+                Some(100.0),
+                Some(999.0),
+                Some(999.0),
+                Some(200.0),
+                Some(200.0),
+                Some(999.0),
+                None
+            ])
+        );
+        let cn = convert(2, &[0, 1, 2, 3, 5])?;
+        assert_eq!(
+            cn.data.as_ref().as_primitive::<Float64Type>(),
+            &Float64Array::from(vec![
+                Some(10.0),
+                Some(10.0),
+                Some(20.0),
+                Some(30.0),
+                Some(30.0),
+                None
+            ])
+        );
+        let cn = convert(5, &[0, 1, 2, 4, 5, 9, 10])?;
+        assert_eq!(
+            cn.data.as_ref().as_primitive::<Float64Type>(),
+            &Float64Array::from(vec![
+                None,
+                Some(11.0),
+                Some(40.0),
+                Some(40.0),
+                Some(90.0),
+                Some(90.0),
+                Some(-1.0),
+                None
+            ])
+        );
+        let Some(ChannelData::Dictionary(status)) = &cn.status else {
+            panic!("partial text table conversion shall return a status dictionary");
+        };
+        let labels = status
+            .downcast_dict::<StringArray>()
+            .context("dictionary values are not strings")?
+            .into_iter()
+            .collect::<Vec<Option<&str>>>();
+        assert_eq!(
+            labels,
+            vec![Some("off"), None, None, None, None, None, None, None]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn channel_enum_map() -> Result<()> {
+        use crate::mdfinfo::mdfinfo4::CcVal;
+        let mut mdf = Mdf::new("test_files/test_basic.mf4")?;
+        assert_eq!(mdf.get_channel_enum_map("Value"), None);
+        let MdfInfo::V4(info) = &mut mdf.mdf_info else {
+            panic!("mdf4 file expected")
+        };
+        let (_master, dg_pos, (_cg_pos, rec_id), (_cn_pos, rec_pos)) = info
+            .get_channel_id("Value")
+            .cloned()
+            .context("channel missing")?;
+        let cc_position = info
+            .dg
+            .get(&dg_pos)
+            .and_then(|dg| dg.cg.get(&rec_id))
+            .and_then(|cg| cg.cn.get(&rec_pos))
+            .context("channel block missing")?
+            .block
+            .cn_cc_conversion;
+        let texts = ["off", "on", "default"].map(|text| {
+            let position = position_generator();
+            info.sharable.create_tx(position, text.to_string());
+            position
+        });
+        let cc = info
+            .sharable
+            .cc
+            .get_mut(&cc_position)
+            .context("conversion block missing")?;
+        cc.cc_type = 7;
+        cc.cc_val = CcVal::Real(vec![0.0, 1.0, 2.0]);
+        // value 2 has no text, nested conversion or nil link
+        cc.cc_ref = vec![texts[0], texts[1], 0, texts[2]];
+        let enum_map = mdf
+            .get_channel_enum_map("Value")
+            .context("enum map missing")?;
+        assert_eq!(
+            enum_map,
+            HashMap::from([(0, "off".to_string()), (1, "on".to_string())])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn partial_conversion() -> Result<()> {
+        use crate::mdfinfo::mdfinfo4::{Cc4Block, CcVal, Cn4, SharableBlocks};
+        use crate::mdfreader::conversions4::convert_channel;
+        use arrow::datatypes::UInt8Type;
+        let mut sharable = SharableBlocks::new(2);
+        sharable.create_tx(10, "off".to_string());
+        sharable.create_tx(20, "error".to_string());
+        // values not in table are converted by a linear conversion block
+        sharable.cc.insert(30, Cc4Block::linear(0.0, 2.0));
+        let mut cc = Cc4Block::linear(0.0, 1.0);
+        cc.cc_type = 7;
+        cc.cc_val = CcVal::Real(vec![0.0, 1.0]);
+        cc.cc_ref = vec![10, 20, 30];
+        sharable.cc.insert(1, cc);
+        let mut cn = Cn4 {
+            // fourth sample is invalid
+            data: ChannelData::UInt8(PrimitiveBuilder::<UInt8Type>::new_from_buffer(
+                vec![0u8, 1, 5, 3, 0].into(),
+                Some(vec![0b10111u8].into()),
+            )),
+            ..Default::default()
+        };
+        cn.block.cn_cc_conversion = 1;
+        convert_channel(&mut cn, &sharable)?;
+        let values = cn.data.finish_cloned();
+        assert_eq!(
+            values
+                .as_primitive::<Float64Type>()
+                .iter()
+                .collect::<Vec<_>>(),
+            vec![None, None, Some(10.0), None, None]
+        );
+        let status = cn.status.context("status missing")?.finish_cloned();
+        let status = status
+            .as_dictionary::<UInt32Type>()
+            .downcast_dict::<StringArray>()
+            .context("dictionary values are not strings")?
+            .into_iter()
+            .collect::<Vec<Option<&str>>>();
+        assert_eq!(
+            status,
+            vec![Some("off"), Some("error"), None, None, Some("off")]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn explode_bitfield() -> Result<()> {
+        use crate::mdfinfo::mdfinfo4::{Cc4Block, CcVal};
+        use arrow::datatypes::{UInt64Type, UInt8Type};
+        let mut mdf = Mdf::new("test_files/test_basic.mf4")?;
+        assert!(mdf.explode_bitfield_channel("Value").is_err());
+        let MdfInfo::V4(info) = &mut mdf.mdf_info else {
+            panic!("mdf4 file expected")
+        };
+        let (_master, dg_pos, (_cg_pos, rec_id), (_cn_pos, rec_pos)) = info
+            .get_channel_id("Value")
+            .cloned()
+            .context("channel missing")?;
+        let cc_position = info
+            .dg
+            .get(&dg_pos)
+            .and_then(|dg| dg.cg.get(&rec_id))
+            .and_then(|cg| cg.cn.get(&rec_pos))
+            .context("channel block missing")?
+            .block
+            .cn_cc_conversion;
+        // two named flags, the third one being unnamed
+        let mut flags = Vec::new();
+        for name in [Some("Active"), Some("Warning"), None] {
+            let mut flag = Cc4Block::linear(0.0, 1.0);
+            flag.cc_type = 7;
+            flag.cc_val = CcVal::Real(vec![1.0]);
+            if let Some(name) = name {
+                flag.cc_tx_name = position_generator();
+                info.sharable.create_tx(flag.cc_tx_name, name.to_string());
+            }
+            let text = position_generator();
+            info.sharable.create_tx(text, "set".to_string());
+            flag.cc_ref = vec![text, 0];
+            let flag_position = position_generator();
+            info.sharable.cc.insert(flag_position, flag);
+            flags.push(flag_position);
+        }
+        let cc = info
+            .sharable
+            .cc
+            .get_mut(&cc_position)
+            .context("conversion block missing")?;
+        cc.cc_type = 11;
+        cc.cc_val = CcVal::Uint(vec![1, 2, 4]);
+        cc.cc_ref = flags;
+        let names = mdf.explode_bitfield_channel("Value")?;
+        assert_eq!(names, vec!["Value.Active", "Value.Warning", "Value.0x4"]);
+        let raw = mdf
+            .get_channel_raw_data("Value")
+            .context("raw data missing")?
+            .as_ref();
+        let raw = cast(&raw, &DataType::UInt64)?;
+        let raw = raw.as_primitive::<UInt64Type>();
+        for (name, mask) in names.iter().zip([1u64, 2, 4]) {
+            let flag = mdf
+                .get_channel_data(name)
+                .context("flag channel missing")?
+                .as_ref();
+            assert!(flag
+                .as_primitive::<UInt8Type>()
+                .values()
+                .iter()
+                .zip(raw.values().iter())
+                .all(|(flag, raw)| (*flag == 1) == (raw & mask != 0)));
             assert_eq!(
-                &ChannelData::Float64(Float64Builder::new_from_buffer(vect.into(), None)),
-                data
+                mdf.get_channel_master(name),
+                mdf.get_channel_master("Value")
             );
         }
+        Ok(())
+    }
 
-        // Text conversion : Text to Text
-        let file_name = format!(
-            "{}{}{}",
-            BASE_PATH_MDF4, list_of_paths[4], "Vector_Text2TextConversion.mf4"
+    #[test]
+    fn structure_channel() -> Result<()> {
+        use crate::mdfinfo::mdfinfo4::{Cn4, Compo, Composition};
+        let mut mdf = Mdf::new("test_files/test_basic.mf4")?;
+        assert!(mdf.get_channel_struct("Value")?.is_none());
+        let MdfInfo::V4(info) = &mut mdf.mdf_info else {
+            panic!("mdf4 file expected")
+        };
+        let (master, dg_pos, (cg_pos, rec_id), (cn_pos, time_pos)) = info
+            .get_channel_id("Time")
+            .cloned()
+            .context("channel missing")?;
+        let (_, _, _, (_, value_pos)) = info
+            .get_channel_id("Value")
+            .cloned()
+            .context("channel missing")?;
+        let cg = info
+            .dg
+            .get_mut(&dg_pos)
+            .and_then(|dg| dg.cg.get_mut(&rec_id))
+            .context("channel group missing")?;
+        // structure Frame composed of Time followed by Value
+        let value_position = cg.cn.get(&value_pos).context("cn missing")?.block_position;
+        cg.cn
+            .get_mut(&value_pos)
+            .context("cn missing")?
+            .block
+            .cn_cn_next = 0;
+        let time = cg.cn.get_mut(&time_pos).context("cn missing")?;
+        time.block.cn_cn_next = value_position;
+        let frame = Cn4 {
+            unique_name: "Frame".to_string(),
+            composition: Some(Composition {
+                block: Compo::CN(Box::new(time.clone())),
+                compo: None,
+            }),
+            ..Default::default()
+        };
+        assert!(frame.is_structure());
+        cg.cn.insert(-1, frame);
+        info.channel_names_set.insert(
+            "Frame".to_string(),
+            (master, dg_pos, (cg_pos, rec_id), (cn_pos, -1)),
         );
-        let mut mdf = Mdf::new(&file_name)?;
-        mdf.load_all_channels_data_in_memory()?;
-        if let Some(data) = mdf.get_channel_data(&"Data channel".to_string()) {
-            let mut target = LargeStringBuilder::with_capacity(10, 20);
-            target.append_value("No translation");
-            target.append_value("Eins");
-            target.append_value("Zwei");
-            target.append_value("Drei");
-            target.append_value("Vier");
-            target.append_value("Fünf");
-            target.append_value("Sechs");
-            target.append_value("Sieben");
-            target.append_value("Acht");
-            target.append_value("Neun");
-            assert_eq!(&ChannelData::Utf8(target), data);
+        let frame = mdf
+            .get_channel_struct("Frame")?
+            .context("structure missing")?;
+        assert_eq!(frame.column_names(), vec!["Time", "Value"]);
+        for name in ["Time", "Value"] {
+            let member = mdf
+                .get_channel_data(name)
+                .context("member missing")?
+                .as_ref();
+            assert_eq!(
+                frame
+                    .column_by_name(name)
+                    .context("field missing")?
+                    .as_ref(),
+                member.as_ref()
+            );
         }
         Ok(())
     }
-
     #[test]
-    fn bus_logging() -> Result<()> {
-        // sort bus
-        let file_name = format!(
-            "{}{}",
-            BASE_PATH_MDF4, "BusLogging/Vector_CAN_DataFrame_Sort_ID.MF4"
+    fn file_hash() -> Result<()> {
+        use crate::test_utils::SyntheticMdf;
+        let file = SyntheticMdf::mdf4()
+            .with_group(&[DataType::Float64, DataType::UInt8])
+            .with_records(20);
+        let bytes = file.to_bytes()?;
+        let mut mdf = Mdf::from_bytes(&bytes, "hash.mf4")?;
+        mdf.load_channels_data_from_bytes(&bytes, mdf.get_channel_names_set())?;
+        let output = std::env::temp_dir().join("mdfr_file_hash.mf4");
+        let output = output.to_string_lossy();
+        let mut written = mdf.write_verified(&output, false)?;
+        assert_eq!(written.verify_file_hash()?, None);
+        let hash = written.store_file_hash()?;
+        assert_eq!(hash.len(), 32);
+        let mut reopened = Mdf::new(&output)?;
+        assert_eq!(reopened.verify_file_hash()?, Some(true));
+        assert!(reopened.validate()?.is_valid());
+        reopened.load_all_channels_data_in_memory()?;
+        assert_eq!(
+            reopened.get_channel_data(&SyntheticMdf::channel_name(0, 0)),
+            mdf.get_channel_data(&SyntheticMdf::channel_name(0, 0))
         );
-        let mut mdf = Mdf::new(&file_name)?;
-        mdf.load_all_channels_data_in_memory()?;
-        if let Some(data) = mdf.get_channel_data(
-            &"CAN_DataFrame.ID CAN_DataFrame_101 CANReplay_7_5 Message".to_string(),
-        ) {
-            let vect: Vec<f64> = vec![101.; 79];
+        // any change of the file detected
+        let mut content = std::fs::read(output.as_ref())?;
+        content[16] ^= 0xFF;
+        std::fs::write(output.as_ref(), content)?;
+        assert_eq!(Mdf::new(&output)?.verify_file_hash()?, Some(false));
+        Ok(())
+    }
+    #[test]
+    fn spill_to_disk() -> Result<()> {
+        use crate::test_utils::SyntheticMdf;
+        let file = SyntheticMdf::mdf4()
+            .with_group(&[DataType::Float64, DataType::UInt16, DataType::Int32])
+            .with_group(&[DataType::UInt8])
+            .with_linear_conversion(1.0, 0.5)
+            .with_records(100);
+        let file_name = std::env::temp_dir().join("mdfr_spill_to_disk.mf4");
+        let file_name = file_name.to_str().context("invalid temp path")?;
+        fs::write(file_name, file.to_bytes()?)?;
+        let spill_directory = std::env::temp_dir().join("mdfr_spill_to_disk");
+        let _ = fs::remove_dir_all(&spill_directory);
+        fs::create_dir(&spill_directory)?;
+        let mut in_memory = Mdf::new(file_name)?;
+        in_memory.load_all_channels_data_in_memory()?;
+        let mut spilled = Mdf::new(file_name)?;
+        let options = ReadOptions {
+            spill_directory: Some(spill_directory.clone()),
+            ..Default::default()
+        };
+        spilled
+            .load_channels_data_in_memory_with_options(spilled.get_channel_names_set(), &options)?;
+        // one spill file per channel group
+        assert_eq!(fs::read_dir(&spill_directory)?.count(), 2);
+        for name in in_memory.get_channel_names_set() {
             assert_eq!(
-                ChannelData::Float64(Float64Builder::new_from_buffer(vect.into(), None)),
-                *data
+                spilled.get_channel_data(&name).map(|d| d.as_ref()),
+                in_memory.get_channel_data(&name).map(|d| d.as_ref())
             );
         }
+        // spilled data written like data in memory
+        let output = std::env::temp_dir().join("mdfr_spill_to_disk_written.mf4");
+        let output = output.to_str().context("invalid temp path")?;
+        let mut written = spilled.write(output, false)?;
+        written.load_all_channels_data_in_memory()?;
+        assert_eq!(
+            written.get_channel_data(&SyntheticMdf::channel_name(0, 1)),
+            in_memory.get_channel_data(&SyntheticMdf::channel_name(0, 1))
+        );
+        // spill files removed with their channels
+        drop(spilled);
+        assert_eq!(fs::read_dir(&spill_directory)?.count(), 0);
+        fs::remove_dir(&spill_directory)?;
         Ok(())
     }
     #[test]
-    fn writing_mdf4() -> Result<()> {
-        // write file with invalid channels
-        let file = format!(
-            "{}{}",
-            BASE_PATH_MDF4, &"Simple/PCV_iO_Gen3_LK1__3l_TDI.mf4"
-        );
-        let ref_channel = r"NO";
-        let mut mdf = Mdf::new(&file)?;
-        mdf.load_all_channels_data_in_memory()?;
-        // without compression
-        let mut info2 = mdf.write(WRITING_MDF_FILE, false)?;
-        info2.load_all_channels_data_in_memory()?;
-        if let Some(data) = mdf.get_channel_data(&ref_channel.to_string()) {
-            if let Some(data2) = info2.get_channel_data(&ref_channel.to_string()) {
-                assert_eq!(*data2, *data);
+    fn spill_export_uncached() -> Result<()> {
+        use crate::test_utils::SyntheticMdf;
+        let file = SyntheticMdf::mdf4()
+            .with_group(&[DataType::Float64, DataType::Int32])
+            .with_group(&[DataType::UInt16])
+            .with_records(200);
+        let file_name = std::env::temp_dir().join("mdfr_spill_export_uncached.mf4");
+        let file_name = file_name.to_str().context("invalid temp path")?;
+        fs::write(file_name, file.to_bytes()?)?;
+        let spill_directory = std::env::temp_dir().join("mdfr_spill_export_uncached");
+        let _ = fs::remove_dir_all(&spill_directory);
+        fs::create_dir(&spill_directory)?;
+        let mut spilled = Mdf::new(file_name)?;
+        let options = ReadOptions {
+            spill_directory: Some(spill_directory.clone()),
+            ..Default::default()
+        };
+        spilled
+            .load_channels_data_in_memory_with_options(spilled.get_channel_names_set(), &options)?;
+        let n_cached = |mdf: &Mdf| -> usize {
+            let MdfInfo::V4(info) = &mdf.mdf_info else {
+                return 0;
+            };
+            info.dg
+                .values()
+                .flat_map(|dg| dg.cg.values())
+                .flat_map(|cg| cg.cn.values())
+                .filter(|cn| cn.spilled.as_ref().is_some_and(|s| s.is_cached()))
+                .count()
+        };
+        // exports and writing read back each channel without keeping it in memory
+        let parquet_file = std::env::temp_dir().join("mdfr_spill_export_uncached.parquet");
+        let parquet_file = parquet_file.to_str().context("invalid temp path")?;
+        spilled.export_to_parquet(parquet_file, None)?;
+        assert_eq!(n_cached(&spilled), 0);
+        let output = std::env::temp_dir().join("mdfr_spill_export_uncached_written.mf4");
+        let output = output.to_str().context("invalid temp path")?;
+        let mut written = spilled.write(output, false)?;
+        assert_eq!(n_cached(&spilled), 0);
+        written.load_all_channels_data_in_memory()?;
+        for name in file.channel_names() {
+            let data = written
+                .get_channel_data(&name)
+                .with_context(|| format!("{name} not written"))?;
+            let expected = file.expected_data(&name).context("no expected data")?;
+            assert_eq!(&data.as_ref(), &expected, "{name}");
+        }
+        // access by name keeps the data read back
+        assert!(spilled
+            .get_channel_data(&SyntheticMdf::channel_name(0, 1))
+            .is_some());
+        assert_eq!(n_cached(&spilled), 1);
+        drop(spilled);
+        fs::remove_dir(&spill_directory)?;
+        fs::remove_file(parquet_file)?;
+        fs::remove_file(output)?;
+        Ok(())
+    }
+    #[test]
+    fn projection_record_walk() -> Result<()> {
+        use crate::test_utils::SyntheticMdf;
+        let data_types = [
+            DataType::UInt8,
+            DataType::Int16,
+            DataType::UInt32,
+            DataType::Float32,
+            DataType::Int64,
+            DataType::Float64,
+        ];
+        let file = SyntheticMdf::mdf4()
+            .with_group(&[&data_types[..]; 4].concat())
+            .with_records(1000);
+        let bytes = file.to_bytes()?;
+        let mut full = Mdf::from_bytes(&bytes, "wide.mf4")?;
+        full.load_channels_data_from_bytes(&bytes, full.get_channel_names_set())?;
+        // a few channels of the wide record read together record by record
+        let names: HashSet<String> = [0, 7, 14, 23]
+            .iter()
+            .map(|i| SyntheticMdf::channel_name(0, *i))
+            .collect();
+        let mut projected = Mdf::from_bytes(&bytes, "wide.mf4")?;
+        projected.load_channels_data_from_bytes(&bytes, names.clone())?;
+        for name in projected.get_channel_names_set() {
+            if names.contains(&name) {
+                assert_eq!(
+                    projected.get_channel_data(&name).map(|d| d.as_ref()),
+                    full.get_channel_data(&name).map(|d| d.as_ref())
+                );
             } else {
-                panic!("Channel not found");
+                assert!(projected.get_channel_data(&name).is_none());
             }
-        } else {
-            panic!("Channel not found");
         }
-        // with compression
-        let mut info2 = mdf.write(WRITING_MDF_FILE, true)?;
-        info2.load_all_channels_data_in_memory()?;
-        if let Some(data) = mdf.get_channel_data(&ref_channel.to_string()) {
-            if let Some(data2) = info2.get_channel_data(&ref_channel.to_string()) {
-                assert_eq!(*data2, *data);
-            } else {
-                panic!("Channel not found");
+        Ok(())
+    }
+    #[test]
+    fn column_storage() -> Result<()> {
+        use crate::test_utils::{DataLayout, SyntheticMdf};
+        let file = SyntheticMdf::mdf4()
+            .with_group(&[DataType::Float64, DataType::UInt16, DataType::Int8])
+            .with_group(&[DataType::Int64, DataType::Float32])
+            .with_records(1000);
+        let bytes = file.to_bytes()?;
+        let mut reference = Mdf::from_bytes(&bytes, "rows.mf4")?;
+        reference.load_channels_data_from_bytes(&bytes, reference.get_channel_names_set())?;
+        // written channels are stored one per channel group, in DV or LD and DZ blocks
+        for compression in [Compression::None, Compression::Zstd { level: 3 }] {
+            let output = std::env::temp_dir().join("mdfr_column_storage_reading.mf4");
+            let output = output.to_str().context("invalid temp path")?;
+            reference.write(output, compression)?;
+            let mut columns = Mdf::new(output)?;
+            columns.load_all_channels_data_in_memory()?;
+            for name in reference.get_channel_names_set() {
+                assert_eq!(
+                    columns.get_channel_data(&name).map(|d| d.as_ref()),
+                    reference.get_channel_data(&name).map(|d| d.as_ref())
+                );
             }
-        } else {
-            panic!("Channel not found");
         }
-
-        // write file with many channels
-        let file = format!("{}{}", BASE_PATH_MDF4, &"Simple/test.mf4");
-        let ref_channel = r"C90 CG21 in error.mdf";
-        let mut mdf = Mdf::new(&file)?;
-        mdf.load_all_channels_data_in_memory()?;
-        // with compression
-        let mut info2 = mdf.write(WRITING_MDF_FILE, true)?;
-        info2.load_all_channels_data_in_memory()?;
-        if let Some(data) = mdf.get_channel_data(&ref_channel.to_string()) {
-            if let Some(data2) = info2.get_channel_data(&ref_channel.to_string()) {
-                assert_eq!(*data2, *data);
-            } else {
-                panic!("Channel not found");
+        // master only channel groups in data lists of DT and DZ blocks
+        let masters = SyntheticMdf::mdf4()
+            .with_group(&[])
+            .with_group(&[])
+            .with_records(1000);
+        let bytes = masters.to_bytes()?;
+        let mut reference = Mdf::from_bytes(&bytes, "masters.mf4")?;
+        reference.load_channels_data_from_bytes(&bytes, reference.get_channel_names_set())?;
+        for layout in [
+            DataLayout::Dl {
+                records_per_block: 300,
+            },
+            DataLayout::Hl {
+                records_per_block: 300,
+            },
+        ] {
+            let bytes = masters.clone().with_layout(layout).to_bytes()?;
+            let mut columns = Mdf::from_bytes(&bytes, "masters.mf4")?;
+            columns.load_channels_data_from_bytes(&bytes, columns.get_channel_names_set())?;
+            for group in 0..2 {
+                let name = SyntheticMdf::master_name(group);
+                assert_eq!(
+                    columns.get_channel_data(&name).map(|d| d.as_ref()),
+                    reference.get_channel_data(&name).map(|d| d.as_ref())
+                );
             }
-        } else {
-            panic!("Channel not found");
         }
-        // without compression
-        let mut info2 = mdf.write(WRITING_MDF_FILE, false)?;
-        info2.load_all_channels_data_in_memory()?;
-        if let Some(data) = mdf.get_channel_data(&ref_channel.to_string()) {
-            if let Some(data2) = info2.get_channel_data(&ref_channel.to_string()) {
-                assert_eq!(*data2, *data);
-            } else {
-                panic!("Channel not found");
+        Ok(())
+    }
+    #[test]
+    fn thread_pool() -> Result<()> {
+        use crate::parallel::{current_num_threads, install};
+        use crate::test_utils::SyntheticMdf;
+        // one thread whatever the feature
+        assert_eq!(install(Some(1), || Ok(current_num_threads()))?, 1);
+        let file = TempFile::synthetic(
+            "thread_pool.mf4",
+            &SyntheticMdf::mdf4()
+                .with_group(&[DataType::UInt32, DataType::Float32])
+                .with_records(100),
+        )?;
+        let mut mdf = Mdf::new(file.path())?;
+        let options = ReadOptions {
+            max_parallelism: Some(0),
+            ..Default::default()
+        };
+        assert!(mdf
+            .load_channels_data_in_memory_with_options(mdf.get_channel_names_set(), &options)
+            .is_err());
+        let options = ReadOptions {
+            max_parallelism: Some(1),
+            ..Default::default()
+        };
+        mdf.load_channels_data_in_memory_with_options(mdf.get_channel_names_set(), &options)?;
+        assert_eq!(
+            mdf.get_channel_data(&SyntheticMdf::channel_name(0, 1))
+                .map(|data| data.len()),
+            Some(100)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn core_block_parsing() -> Result<()> {
+        use crate::mdfinfo::core4::{hd4_block, id_block, structure4, BlockError, HD_POSITION};
+        use crate::test_utils::SyntheticMdf;
+        let bytes = SyntheticMdf::mdf4()
+            .with_group(&[DataType::UInt16, DataType::Float64])
+            .with_group(&[DataType::Int32])
+            .with_records(10)
+            .to_bytes()?;
+        // bytes received as needed by the parser, as over a transport
+        let mut received = 0;
+        let structure = loop {
+            match structure4(&bytes[..received]) {
+                Ok(structure) => break structure,
+                Err(BlockError::Incomplete { end }) => {
+                    assert!(end as usize > received && end as usize <= bytes.len());
+                    received = end as usize;
+                }
+                Err(error) => return Err(error.into()),
             }
-        } else {
-            panic!("Channel not found");
-        }
-        // TODO test write file with arrays
+        };
+        assert!(received < bytes.len());
+        assert!(id_block(&bytes)?.id_ver >= 400);
+        assert_eq!(
+            hd4_block(&bytes, HD_POSITION)?.hd_dg_first,
+            structure[0].position as i64
+        );
+        assert_eq!(structure.len(), 2);
+        let names: HashSet<String> = structure
+            .iter()
+            .flat_map(|dg| dg.channel_groups.iter())
+            .flat_map(|cg| cg.channels.iter())
+            .map(|cn| cn.name.clone())
+            .collect();
+        let mdf = Mdf::from_bytes(&bytes, "core_block_parsing.mf4")?;
+        assert_eq!(names, mdf.get_channel_names_set());
+        assert!(matches!(
+            hd4_block(&bytes, structure[0].position),
+            Err(BlockError::UnexpectedBlock { .. })
+        ));
+        Ok(())
+    }
 
-        //mdf3 conversion
-        drop(mdf);
-        let file = format!(
-            "{}{}",
-            BASE_PATH_MDF3, &"RJ_N16-12-363_BM-15C-0024_228_2_20170116094355_CAN.dat"
+    #[test]
+    fn typed_channel_accessors() -> Result<()> {
+        use crate::test_utils::SyntheticMdf;
+        use arrow::array::LargeStringArray;
+        let bytes = SyntheticMdf::mdf4()
+            .with_group(&[DataType::Float64, DataType::Int64, DataType::Float32])
+            .with_records(20)
+            .to_bytes()?;
+        let mut mdf = Mdf::from_bytes(&bytes, "typed_channel_accessors.mf4")?;
+        mdf.load_channels_data_from_bytes(&bytes, mdf.get_channel_names_set())?;
+        let float_name = SyntheticMdf::channel_name(0, 0);
+        let values = mdf.get_channel_f64(&float_name).context("no f64 channel")?;
+        assert_eq!(values.len(), 20);
+        assert_eq!(
+            values,
+            mdf.get_channel_data(&float_name)
+                .context("no data")?
+                .as_ref()
+                .as_primitive::<Float64Type>()
+                .values()
+                .as_ref()
+        );
+        let integer_name = SyntheticMdf::channel_name(0, 1);
+        assert_eq!(
+            mdf.get_channel_i64(&integer_name)
+                .map(|values| values.len()),
+            Some(20)
+        );
+        assert!(mdf.get_channel_f64(&integer_name).is_none());
+        assert!(mdf
+            .get_channel_f64(&SyntheticMdf::channel_name(0, 2))
+            .is_none());
+        assert!(mdf.get_channel_f64("unknown").is_none());
+        assert!(mdf.get_channel_str_iter(&float_name).is_none());
+
+        let master = Some(SyntheticMdf::master_name(0));
+        let texts: Vec<Option<&str>> = (0..20)
+            .map(|i| if i % 5 == 0 { None } else { Some("text") })
+            .collect();
+        mdf.add_channel(
+            "texts".to_string(),
+            Arc::new(LargeStringArray::from(texts.clone())),
+            master.clone(),
+            None,
+            false,
+            None,
+            None,
+        )?;
+        assert_eq!(
+            mdf.get_channel_str_iter("texts")
+                .context("no texts")?
+                .collect::<Vec<_>>(),
+            texts
+        );
+        let labels: DictionaryArray<UInt32Type> = texts.iter().copied().collect();
+        mdf.add_channel(
+            "labels".to_string(),
+            Arc::new(labels),
+            master,
+            None,
+            false,
+            None,
+            None,
+        )?;
+        assert_eq!(
+            mdf.get_channel_str_iter("labels")
+                .context("no labels")?
+                .collect::<Vec<_>>(),
+            texts
         );
-        let mut mdf = Mdf::new(&file)?;
-        mdf.load_all_channels_data_in_memory()?;
-        let channel_name3 = r"TEMP_FUEL";
-        let mut mdf4 = mdf.write(WRITING_MDF_FILE, true)?;
-        mdf4.load_all_channels_data_in_memory()?;
-        let mdf3_data = mdf.get_channel_data(&channel_name3);
-        let mdf4_data = mdf4.get_channel_data(&channel_name3);
-        assert_eq!(mdf3_data, mdf4_data);
         Ok(())
     }
+
     #[test]
-    fn mdf_modifications() -> Result<()> {
-        // write file with invalid channels
-        let file = format!(
-            "{}{}",
-            BASE_PATH_MDF4, &"Simple/PCV_iO_Gen3_LK1__3l_TDI.mf4"
+    fn channel_samples() -> Result<()> {
+        use crate::data_holder::samples::Value;
+        use crate::test_utils::SyntheticMdf;
+        use arrow::array::{Int32Array, LargeStringArray};
+        let bytes = SyntheticMdf::mdf4()
+            .with_group(&[DataType::UInt16, DataType::Float64])
+            .with_records(20)
+            .to_bytes()?;
+        let mut mdf = Mdf::from_bytes(&bytes, "channel_samples.mf4")?;
+        let name = SyntheticMdf::channel_name(0, 1);
+        assert!(mdf.iter_channel(&name).is_err());
+        mdf.load_channels_data_from_bytes(&bytes, mdf.get_channel_names_set())?;
+        let master = mdf
+            .get_channel_f64(&SyntheticMdf::master_name(0))
+            .context("no master")?
+            .to_vec();
+        let values = mdf.get_channel_f64(&name).context("no data")?.to_vec();
+        let samples: Vec<(f64, Value)> = mdf.iter_channel(&name)?.collect();
+        assert_eq!(
+            samples,
+            master
+                .iter()
+                .zip(values.iter())
+                .map(|(t, v)| (*t, Value::Float(*v)))
+                .collect::<Vec<_>>()
         );
-        let ref_channel = r"PANS";
-        let ref_desc = r"tralala";
-        let ref_unit = r"Bar";
-        let mut mdf = Mdf::new(&file)?;
-        mdf.load_all_channels_data_in_memory()?;
-        // modify data
-        if let Some(data) = mdf.get_channel_data(&ref_channel.to_string()) {
-            let mut new_data = PrimitiveBuilder::with_capacity(data.len());
-            data.finish_cloned()
-                .as_primitive::<Float32Type>()
+        assert!(mdf
+            .iter_channel(&SyntheticMdf::channel_name(0, 0))?
+            .all(|(_, value)| matches!(value, Value::UInt(_))));
+
+        // invalid samples
+        let master_name = Some(SyntheticMdf::master_name(0));
+        mdf.add_channel(
+            "with_invalid".to_string(),
+            Arc::new(Int32Array::from_iter(
+                (0..20).map(|i| (i % 4 != 0).then_some(i)),
+            )),
+            master_name.clone(),
+            None,
+            false,
+            None,
+            None,
+        )?;
+        let samples: Vec<(f64, Value)> = mdf.iter_channel("with_invalid")?.collect();
+        assert_eq!(samples.len(), 20);
+        assert_eq!(samples[0], (master[0], Value::Invalid));
+        assert_eq!(samples[1], (master[1], Value::Int(1)));
+        assert_eq!(
+            samples
                 .iter()
-                .for_each(|v| new_data.append_option(v));
-            new_data.values_slice_mut()[0] = 0.0f32;
-            mdf.set_channel_data(
-                &ref_channel.to_string(),
-                ChannelData::Float32(new_data).as_ref(),
+                .filter(|(_, value)| *value == Value::Invalid)
+                .count(),
+            5
+        );
+        mdf.add_channel(
+            "texts".to_string(),
+            Arc::new(LargeStringArray::from_iter_values(
+                (0..20).map(|i| format!("text {i}")),
+            )),
+            master_name,
+            None,
+            false,
+            None,
+            None,
+        )?;
+        assert_eq!(
+            mdf.iter_channel("texts")?.nth(3),
+            Some((master[3], Value::Str("text 3".to_string())))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn gps_track_export() -> Result<()> {
+        use crate::export::geo::GeoChannels;
+        use crate::test_utils::SyntheticMdf;
+        let bytes = SyntheticMdf::mdf4()
+            .with_group(&[DataType::UInt8])
+            .with_records(10)
+            .to_bytes()?;
+        let mut mdf = Mdf::from_bytes(&bytes, "gps_track_export.mf4")?;
+        mdf.load_channels_data_from_bytes(&bytes, mdf.get_channel_names_set())?;
+        assert!(mdf.get_geo_channels().is_none());
+        let master = Some(SyntheticMdf::master_name(0));
+        for (name, unit, values) in [
+            (
+                "GPS_Latitude",
+                "deg",
+                (0..10)
+                    .map(|i| (i != 3).then_some(48.0 + i as f64 * 1e-3))
+                    .collect::<Float64Array>(),
+            ),
+            (
+                "gps.lon",
+                "°",
+                (0..10).map(|i| Some(2.0 + i as f64 * 1e-3)).collect(),
+            ),
+            (
+                "GPS_Altitude",
+                "m",
+                (0..10).map(|i| Some(i as f64)).collect(),
+            ),
+            ("lat_accel", "m/s^2", (0..10).map(|_| Some(0.1)).collect()),
+        ] {
+            mdf.add_channel(
+                name.to_string(),
+                Arc::new(values),
+                master.clone(),
+                None,
+                false,
+                Some(unit.to_string()),
+                None,
             )?;
-            mdf.set_channel_desc(&ref_channel.to_string(), ref_desc);
-            mdf.set_channel_unit(&ref_channel.to_string(), ref_unit);
-            mdf.set_channel_master_type(&ref_channel.to_string(), 1)?;
-        } else {
-            panic!("channel not found");
         }
-        if let Some(data) = mdf.get_channel_data(&ref_channel.to_string()) {
-            let (minimum, _) = data.min_max();
-            if let Some(min) = minimum {
-                assert!(min < 1000.0f64);
+        let channels = mdf.get_geo_channels().context("GPS channels not found")?;
+        assert_eq!(
+            channels,
+            GeoChannels {
+                latitude: "GPS_Latitude".to_string(),
+                longitude: "gps.lon".to_string(),
+                altitude: Some("GPS_Altitude".to_string()),
             }
-        } else {
-            panic!("channel not found");
-        }
-        if let Ok(Some(desc)) = mdf.get_channel_desc(&ref_channel.to_string()) {
-            assert_eq!(desc, ref_desc);
-        } else {
-            panic!("channel not found");
-        }
-        if let Ok(Some(unit)) = mdf.get_channel_unit(&ref_channel.to_string()) {
-            assert_eq!(unit, ref_unit);
-        } else {
-            panic!("channel not found");
-        }
-        assert_eq!(mdf.get_channel_master_type(&ref_channel.to_string()), 1);
+        );
 
-        // add new channel
-        drop(mdf);
-        let mut mdf = Mdf::new(&file)?;
-        mdf.load_all_channels_data_in_memory()?;
-        let channel_name = r"Fake_name".to_string();
-        let new_channel_name = r"New fake_name".to_string();
-        let new_data = Arc::new(Float64Array::try_new(vec![0f64; 3300].into(), None)?);
-        let master_channel = mdf.get_channel_master(&ref_channel.to_string());
-        let master_type = Some(0);
-        let master_flag = false;
-        let unit = Some(ref_unit.to_string());
-        let desc = Some(ref_desc.to_string());
-        mdf.add_channel(
-            channel_name.clone(),
-            new_data,
-            master_channel,
-            master_type,
-            master_flag,
-            unit,
-            desc,
+        let gpx_file = TempFile::new("gps_track_export.gpx");
+        mdf.export_to_gpx(gpx_file.path(), None)?;
+        let gpx = fs::read_to_string(gpx_file.path())?;
+        let document = roxmltree::Document::parse(&gpx)?;
+        let points: Vec<_> = document
+            .descendants()
+            .filter(|node| node.has_tag_name("trkpt"))
+            .collect();
+        // invalid latitude skipped
+        assert_eq!(points.len(), 9);
+        assert_eq!(points[0].attribute("lat"), Some("48"));
+        assert_eq!(points[0].attribute("lon"), Some("2"));
+        assert!(points[3]
+            .children()
+            .any(|node| node.has_tag_name("ele") && node.text() == Some("4")));
+        assert!(points
+            .iter()
+            .all(|point| point.children().any(|node| node.has_tag_name("time"))));
+
+        let kml_file = TempFile::new("gps_track_export.kml");
+        mdf.export_to_kml(
+            kml_file.path(),
+            Some(&GeoChannels {
+                altitude: None,
+                ..channels
+            }),
         )?;
+        let kml = fs::read_to_string(kml_file.path())?;
+        let document = roxmltree::Document::parse(&kml)?;
+        let coordinates = document
+            .descendants()
+            .find(|node| node.has_tag_name("coordinates"))
+            .and_then(|node| node.text())
+            .context("no coordinates")?;
+        let coordinates: Vec<&str> = coordinates.split_whitespace().collect();
+        assert_eq!(coordinates.len(), 9);
+        assert_eq!(coordinates[0], "2,48");
+        Ok(())
+    }
 
-        if let Some(data) = mdf.get_channel_data(&channel_name.to_string()) {
-            let (minimum, _) = data.min_max();
-            if let Some(min) = minimum {
-                assert!(min == 0.0f64);
-            }
-        } else {
-            panic!("channel not found");
+    #[test]
+    fn video_synchronisation() -> Result<()> {
+        use crate::test_utils::SyntheticMdf;
+        let synthetic = SyntheticMdf::mdf4()
+            .with_group(&[DataType::UInt8])
+            .with_group(&[DataType::UInt32, DataType::Float32])
+            .with_records(10)
+            .with_video_sync(1, "camera_front.mp4");
+        let file_name = std::env::temp_dir().join("mdfr_video_synchronisation.mf4");
+        let file_name = file_name.to_str().context("invalid temp path")?;
+        synthetic.write(file_name)?;
+        let mut mdf = Mdf::new(file_name)?;
+        let channels = mdf.get_sync_channels()?;
+        assert_eq!(channels.len(), 1);
+        let channel = &channels[0];
+        assert_eq!(channel.name, SyntheticMdf::channel_name(1, 0));
+        assert_eq!(channel.master, Some(SyntheticMdf::master_name(1)));
+        assert_eq!(channel.sync_type, 4);
+        assert_eq!(channel.file_name.as_deref(), Some("camera_front.mp4"));
+        assert!(channel.is_video());
+        assert!(mdf.get_video_frames().is_err());
+
+        mdf.load_all_channels_data_in_memory()?;
+        let frames = mdf.get_video_frames()?;
+        assert_eq!(frames.len(), 10);
+        let times = cast(
+            &synthetic
+                .expected_data(&SyntheticMdf::master_name(1))
+                .context("no master")?,
+            &DataType::Float64,
+        )?;
+        let indexes = cast(
+            &synthetic
+                .expected_data(&channel.name)
+                .context("no channel")?,
+            &DataType::Float64,
+        )?;
+        for (i, frame) in frames.iter().enumerate() {
+            assert_eq!(frame.file_name, "camera_front.mp4");
+            assert_eq!(frame.sync_type, 4);
+            assert_eq!(frame.time, times.as_primitive::<Float64Type>().value(i));
+            assert_eq!(frame.frame, indexes.as_primitive::<Float64Type>().value(i));
         }
-        if let Ok(Some(desc)) = mdf.get_channel_desc(&channel_name.to_string()) {
-            assert_eq!(desc, ref_desc.to_string());
-        } else {
-            panic!("channel not found");
+        Ok(())
+    }
+
+    #[test]
+    fn channels_by_source() -> Result<()> {
+        use crate::test_utils::SyntheticMdf;
+        let bytes = SyntheticMdf::mdf4()
+            .with_group(&[DataType::UInt8, DataType::UInt16])
+            .with_group(&[DataType::Int32])
+            .with_records(5)
+            .to_bytes()?;
+        let mut mdf = Mdf::from_bytes(&bytes, "channels_by_source.mf4")?;
+        let groups = mdf.get_channels_by_source()?;
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].source, None);
+        assert_eq!(groups[0].channels.len(), 5);
+
+        let MdfInfo::V4(info) = &mut mdf.mdf_info else {
+            panic!("mdf4 file expected")
+        };
+        let mut source = |name: &str, source_type: u8, bus_type: u8| {
+            let mut si = Si4Block::new(source_type, bus_type);
+            si.si_tx_name = position_generator();
+            info.sharable.create_tx(si.si_tx_name, name.to_string());
+            let si_position = position_generator();
+            info.sharable.si.insert(si_position, si);
+            si_position
+        };
+        let engine = source("Engine", 1, 0);
+        let can = source("CAN2", 2, 2);
+        for (channel_name, si_position) in [
+            (SyntheticMdf::channel_name(0, 0), engine),
+            (SyntheticMdf::channel_name(1, 0), can),
+            (SyntheticMdf::master_name(1), can),
+        ] {
+            let (_master, dg_pos, (_cg_pos, rec_id), (_cn_pos, rec_pos)) = info
+                .get_channel_id(&channel_name)
+                .cloned()
+                .context("channel missing")?;
+            info.dg
+                .get_mut(&dg_pos)
+                .and_then(|dg| dg.cg.get_mut(&rec_id))
+                .and_then(|cg| cg.cn.get_mut(&rec_pos))
+                .context("channel block missing")?
+                .block
+                .cn_si_source = si_position;
         }
-        if let Ok(Some(unit)) = mdf.get_channel_unit(&channel_name.to_string()) {
-            assert_eq!(unit, ref_unit);
-        } else {
-            panic!("channel not found");
+
+        let groups = mdf.get_channels_by_source()?;
+        let summary: Vec<(Option<String>, Vec<String>)> = groups
+            .into_iter()
+            .map(|group| (group.source.and_then(|source| source.name), group.channels))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (
+                    Some("CAN2".to_string()),
+                    vec![
+                        SyntheticMdf::channel_name(1, 0),
+                        SyntheticMdf::master_name(1)
+                    ]
+                ),
+                (
+                    Some("Engine".to_string()),
+                    vec![SyntheticMdf::channel_name(0, 0)]
+                ),
+                (
+                    None,
+                    vec![
+                        SyntheticMdf::channel_name(0, 1),
+                        SyntheticMdf::master_name(0)
+                    ]
+                ),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn computed_channels() -> Result<()> {
+        use crate::test_utils::SyntheticMdf;
+        let bytes = SyntheticMdf::mdf4()
+            .with_group(&[DataType::Float64, DataType::Float64])
+            .with_group(&[DataType::Float64])
+            .with_records(30)
+            .to_bytes()?;
+        let mut mdf = Mdf::from_bytes(&bytes, "computed_channels.mf4")?;
+        mdf.load_channels_data_from_bytes(&bytes, mdf.get_channel_names_set())?;
+        let master = SyntheticMdf::master_name(0);
+        let torque = mdf.get_channel_f64("g0_c0").context("no torque")?.to_vec();
+        let speed = mdf.get_channel_f64("g0_c1").context("no speed")?.to_vec();
+
+        // channels of the same master
+        mdf.add_computed_channel("Power", "Power = g0_c0 * g0_c1 / 9.55", &master)?;
+        let power = mdf.get_channel_f64("Power").context("no power")?;
+        assert_eq!(power.len(), 30);
+        for (i, value) in power.iter().enumerate() {
+            assert!((value - torque[i] * speed[i] / 9.55).abs() < 1e-9);
         }
-        assert_eq!(mdf.get_channel_master_type(&channel_name.to_string()), 0);
+        assert_eq!(mdf.get_channel_master("Power"), Some(master.clone()));
 
-        //rename
-        assert!(mdf.get_channel_data(&channel_name.to_string()).is_some());
-        mdf.rename_channel(&channel_name.to_string(), &new_channel_name);
-        assert!(mdf.get_channel_data(&channel_name.to_string()).is_none());
+        // channel of another master between braces, resampled
+        let other = mdf.get_channel_f64("g1_c0").context("no other")?.to_vec();
+        mdf.add_computed_channel("sum", "{g1_c0} + 2 * g0_c0", &master)?;
+        let sum = mdf.get_channel_f64("sum").context("no sum")?;
+        for (i, value) in sum.iter().enumerate() {
+            assert!((value - (other[i] + 2.0 * torque[i])).abs() < 1e-9);
+        }
 
-        //remove
+        // unknown channel and invalid expression
         assert!(mdf
-            .get_channel_data(&new_channel_name.to_string())
-            .is_some());
-        mdf.remove_channel(&new_channel_name);
+            .add_computed_channel("unknown", "g0_c0 + missing", &master)
+            .is_err());
         assert!(mdf
-            .get_channel_data(&new_channel_name.to_string())
-            .is_none());
+            .add_computed_channel("invalid", "g0_c0 * (g0_c1", &master)
+            .is_err());
+        assert!(mdf.get_channel_data("invalid").is_none());
         Ok(())
     }
+
     #[test]
-    fn export_to_parquet() -> Result<()> {
-        // Export mdf4 to Parquet file
-        let file = format!(
-            "{}{}",
-            BASE_PATH_MDF4, &"Simple/PCV_iO_Gen3_LK1__3l_TDI.mf4"
-        );
-        let extension = "*.parquet";
-        let mut mdf = Mdf::new(&file)?;
-        mdf.load_all_channels_data_in_memory()?;
-        mdf.export_to_parquet(&WRITING_PARQUET_FILE, Some("zstd"))
-            .expect("failed writing mdf4 parquet file");
-        // Export mdf3 to Parquet file
-        let file = format!(
-            "{}{}",
-            BASE_PATH_MDF3, &"RJ_N16-12-363_BM-15C-0024_228_2_20170116094355_CAN.dat"
+    fn channel_filters() -> Result<()> {
+        use crate::data_holder::filter::Filter;
+        use crate::test_utils::SyntheticMdf;
+        use arrow::array::Float64Array;
+        let bytes = SyntheticMdf::mdf4()
+            .with_group(&[DataType::Float64])
+            .with_records(200)
+            .to_bytes()?;
+        let mut mdf = Mdf::from_bytes(&bytes, "channel_filters.mf4")?;
+        mdf.load_channels_data_from_bytes(&bytes, mdf.get_channel_names_set())?;
+        let master_name = SyntheticMdf::master_name(0);
+        let master = mdf
+            .get_channel_f64(&master_name)
+            .context("no master")?
+            .to_vec();
+        let sample_rate = (master.len() - 1) as f64 / (master[master.len() - 1] - master[0]);
+
+        // slow sine with a fast sine on top, a spike and one invalid sample
+        let slow = |i: usize| (i as f64 * 0.02).sin();
+        let signal = Float64Array::from_iter((0..200).map(|i| {
+            let spike = if i == 50 { 100.0 } else { 0.0 };
+            (i != 120).then_some(slow(i) + 0.2 * (i as f64 * 2.5).sin() + spike)
+        }));
+        mdf.add_channel(
+            "noisy".to_string(),
+            Arc::new(signal),
+            Some(master_name.clone()),
+            None,
+            false,
+            Some("V".to_string()),
+            None,
+        )?;
+
+        // moving average of constant neighbours
+        mdf.add_filtered_channel("noisy", "average", Filter::MovingAverage { window: 1 })?;
+        assert_eq!(
+            mdf.get_channel_f64("average")
+                .context("no average")?
+                .to_vec(),
+            mdf.get_channel_f64("noisy").context("no noisy")?.to_vec()
         );
-        let mut mdf = Mdf::new(&file)?;
-        mdf.load_all_channels_data_in_memory()?;
-        mdf.export_to_parquet(&WRITING_PARQUET_FILE, Some("snappy"))
-            .expect("failed writing mdf3 parquet file");
-        // remove all generated parquet files
-        let pattern = format!("{}/{}", BASE_TEST_PATH, extension);
-        for path in glob(&pattern).unwrap().filter_map(Result::ok) {
-            fs::remove_file(path)?;
+        mdf.add_filtered_channel("noisy", "average", Filter::MovingAverage { window: 5 })?;
+        let average = mdf
+            .get_channel_data("average")
+            .context("no average")?
+            .as_ref();
+        assert!(average.is_null(120));
+        assert_eq!(mdf.get_channel_master("average"), Some(master_name.clone()));
+        assert_eq!(mdf.get_channel_unit("average")?, Some("V".to_string()));
+
+        // median removes the spike
+        mdf.add_filtered_channel("noisy", "median", Filter::Median { window: 5 })?;
+        let median = mdf.get_channel_f64("median").context("no median")?;
+        assert!(median[50].abs() < 2.0);
+
+        // low-pass removes the fast sine
+        let cutoff = sample_rate * 0.02;
+        mdf.add_filtered_channel("noisy", "low_pass", Filter::LowPass { cutoff, order: 4 })?;
+        let low_pass = mdf
+            .get_channel_data("low_pass")
+            .context("no low pass")?
+            .as_ref();
+        assert!(low_pass.is_null(120));
+        let low_pass = mdf.get_channel_f64("low_pass").context("no low pass")?;
+        for (i, value) in low_pass.iter().enumerate().take(180).skip(140) {
+            assert!((value - slow(i)).abs() < 0.05, "sample {i}");
         }
+        mdf.add_filtered_channel("noisy", "odd", Filter::LowPass { cutoff, order: 3 })?;
+
+        // invalid parameters
+        assert!(mdf
+            .add_filtered_channel("noisy", "zero", Filter::Median { window: 0 })
+            .is_err());
+        assert!(mdf
+            .add_filtered_channel(
+                "noisy",
+                "nyquist",
+                Filter::LowPass {
+                    cutoff: sample_rate,
+                    order: 2
+                }
+            )
+            .is_err());
         Ok(())
     }
+
     #[test]
-    fn export_to_hdf5() -> Result<()> {
-        // Export mdf4 to Parquet file
-        let file = format!(
-            "{}{}",
-            BASE_PATH_MDF4, &"Simple/PCV_iO_Gen3_LK1__3l_TDI.mf4"
+    fn value_lookup() -> Result<()> {
+        use crate::data_holder::samples::{Interpolation, Value};
+        use crate::test_utils::SyntheticMdf;
+        use arrow::array::Float64Array;
+        let bytes = SyntheticMdf::mdf4()
+            .with_group(&[DataType::Float64, DataType::UInt16])
+            .with_records(10)
+            .to_bytes()?;
+        let mut mdf = Mdf::from_bytes(&bytes, "value_lookup.mf4")?;
+        mdf.load_channels_data_from_bytes(&bytes, mdf.get_channel_names_set())?;
+        let master_name = SyntheticMdf::master_name(0);
+        let master = mdf
+            .get_channel_f64(&master_name)
+            .context("no master")?
+            .to_vec();
+        let name = SyntheticMdf::channel_name(0, 0);
+        let values = mdf.get_channel_f64(&name).context("no data")?.to_vec();
+
+        // on samples and between them
+        let at = |time: f64, method: Interpolation| mdf.get_value_at(&name, time, method);
+        assert_eq!(
+            at(master[3], Interpolation::Linear)?,
+            Some(Value::Float(values[3]))
         );
-        let extension = "*.hdf5";
-        let mut mdf = Mdf::new(&file)?;
-        mdf.load_all_channels_data_in_memory()?;
-        mdf.export_to_hdf5(&WRITING_HDF5_FILE, Some(&"lzf"))
-            .expect("failed writing mdf4 hdf5 file");
-        // Export mdf3 to Parquet file
-        let file = format!(
-            "{}{}",
-            BASE_PATH_MDF3, &"RJ_N16-12-363_BM-15C-0024_228_2_20170116094355_CAN.dat"
+        let time = master[3] + (master[4] - master[3]) * 0.25;
+        assert_eq!(
+            at(time, Interpolation::Previous)?,
+            Some(Value::Float(values[3]))
         );
-        let mut mdf = Mdf::new(&file)?;
+        assert_eq!(
+            at(time, Interpolation::Nearest)?,
+            Some(Value::Float(values[3]))
+        );
+        let time = master[3] + (master[4] - master[3]) * 0.75;
+        assert_eq!(
+            at(time, Interpolation::Nearest)?,
+            Some(Value::Float(values[4]))
+        );
+        let Some(Value::Float(linear)) = at(time, Interpolation::Linear)? else {
+            panic!("no linear value");
+        };
+        assert!((linear - (values[3] + (values[4] - values[3]) * 0.75)).abs() < 1e-9);
+        assert_eq!(
+            at(master[9], Interpolation::Linear)?,
+            Some(Value::Float(values[9]))
+        );
+        assert_eq!(at(master[0] - 1.0, Interpolation::Previous)?, None);
+        assert_eq!(at(master[9] + 1.0, Interpolation::Previous)?, None);
+
+        // integers hold their type except when interpolated
+        let integer = SyntheticMdf::channel_name(0, 1);
+        assert!(matches!(
+            mdf.get_value_at(&integer, time, Interpolation::Previous)?,
+            Some(Value::UInt(_))
+        ));
+        assert!(matches!(
+            mdf.get_value_at(&integer, time, Interpolation::Linear)?,
+            Some(Value::Float(_))
+        ));
+
+        // invalid samples
+        mdf.add_channel(
+            "with_invalid".to_string(),
+            Arc::new(Float64Array::from_iter(
+                (0..10).map(|i| (i != 4).then_some(i as f64)),
+            )),
+            Some(master_name),
+            None,
+            false,
+            None,
+            None,
+        )?;
+        assert_eq!(
+            mdf.get_value_at("with_invalid", time, Interpolation::Linear)?,
+            Some(Value::Invalid)
+        );
+        assert_eq!(
+            mdf.get_value_at("with_invalid", time, Interpolation::Previous)?,
+            Some(Value::Float(3.0))
+        );
+        assert!(mdf
+            .get_value_at("unknown", master[0], Interpolation::Previous)
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn alignment_report() -> Result<()> {
+        use crate::mdfreader::alignment::TimeJump;
+        use crate::test_utils::SyntheticMdf;
+        use arrow::array::Float64Array;
+        let file = TempFile::synthetic(
+            "alignment_report.mf4",
+            &SyntheticMdf::mdf4()
+                .with_group(&[DataType::Float64])
+                .with_group(&[DataType::Int16])
+                .with_records(100),
+        )?;
+        let mut mdf = Mdf::new(file.path())?;
+        assert!(mdf.check_alignment(1.0).is_err());
+
+        // masters loaded and aligned
+        let report = mdf.check_alignment(10.0)?;
+        assert_eq!(report.groups.len(), 2);
+        assert!(!report.has_issues());
+        let group = &report.groups[0];
+        assert_eq!(group.master, SyntheticMdf::master_name(0));
+        assert_eq!(group.n_samples, 100);
+        assert_eq!((group.start, group.end), (Some(0.0), Some(0.99)));
+        assert!((group.period.context("no period")? - 0.01).abs() < 1e-9);
+        assert!((group.sample_rate.context("no rate")? - 100.0).abs() < 1e-6);
+        assert_eq!(report.common_range, Some((0.0, 0.99)));
+
+        // clock reset, duplicate and gap in the second group
+        let master: Vec<f64> = (0..100)
+            .map(|i| match i {
+                0..40 => i as f64 * 0.01 + 0.5,
+                40 => 0.89,
+                41..70 => (i - 41) as f64 * 0.01 + 0.2,
+                _ => (i - 70) as f64 * 0.01 + 1.2,
+            })
+            .collect();
+        mdf.set_channel_data(
+            &SyntheticMdf::master_name(1),
+            Arc::new(Float64Array::from(master)),
+        )?;
+        let report = mdf.check_alignment(10.0)?;
+        assert!(report.has_issues());
+        let group = &report.groups[1];
+        assert!(!group.is_monotonic());
+        assert_eq!(
+            group.backward,
+            vec![TimeJump {
+                index: 41,
+                from: 0.89,
+                to: 0.2
+            }]
+        );
+        assert_eq!(group.n_duplicates, 1);
+        assert_eq!(group.gaps.len(), 1);
+        assert_eq!(group.gaps[0].index, 70);
+        assert_eq!(report.common_range, Some((0.5, 0.99)));
+        assert!(report.to_string().contains("not monotonic at sample 41"));
+        Ok(())
+    }
+
+    #[test]
+    fn recording_gaps_split() -> Result<()> {
+        use crate::test_utils::SyntheticMdf;
+        use arrow::array::Float64Array;
+        let file = TempFile::synthetic(
+            "recording_gaps.mf4",
+            &SyntheticMdf::mdf4()
+                .with_group(&[DataType::Float64])
+                .with_group(&[DataType::Int16])
+                .with_records(100),
+        )?;
+        let mut mdf = Mdf::new(file.path())?;
+        assert!(mdf.detect_recording_gaps(0.0).is_err());
+        assert!(mdf.detect_recording_gaps(0.5)?.is_empty());
+
+        // logger paused 5s in the middle of the recording, groups interleaved
         mdf.load_all_channels_data_in_memory()?;
-        mdf.export_to_hdf5(&WRITING_HDF5_FILE, Some(&"deflate"))
-            .expect("failed writing mdf3 hdf5 file");
-        // remove all generated hdf5 files
-        let pattern = format!("{}/{}", BASE_TEST_PATH, extension);
-        for path in glob(&pattern).unwrap().filter_map(Result::ok) {
-            fs::remove_file(path)?;
+        for group in 0..2 {
+            let master: Vec<f64> = (0..100)
+                .map(|i| match i {
+                    0..50 => i as f64 * 0.01 + group as f64 * 0.005,
+                    _ => i as f64 * 0.01 + 5.0 + group as f64 * 0.005,
+                })
+                .collect();
+            mdf.set_channel_data(
+                &SyntheticMdf::master_name(group),
+                Arc::new(Float64Array::from(master)),
+            )?;
+        }
+        let gaps = mdf.detect_recording_gaps(1.0)?;
+        assert_eq!(gaps.len(), 1);
+        assert!((gaps[0].0 - 0.495).abs() < 1e-9);
+        assert!((gaps[0].1 - 5.5).abs() < 1e-9);
+
+        let segments = mdf.split_on_gaps(1.0)?;
+        assert_eq!(segments.len(), 2);
+        for (segment, offset) in segments.iter().zip([0.0, 5.5]) {
+            let master = segment
+                .get_channel_f64(&SyntheticMdf::master_name(0))
+                .context("master not in segment")?;
+            assert_eq!(master.len(), 50);
+            assert!((master[0] - offset).abs() < 1e-9);
+            let channel = SyntheticMdf::channel_name(1, 0);
+            assert_eq!(
+                segment.get_channel_master(&channel),
+                Some(SyntheticMdf::master_name(1))
+            );
+            assert_eq!(
+                segment.get_channel_data(&channel).map(|data| data.len()),
+                Some(50)
+            );
         }
+        let channel = SyntheticMdf::channel_name(0, 0);
+        assert_eq!(
+            segments[1].get_channel_f64(&channel),
+            mdf.get_channel_f64(&channel).map(|data| &data[50..])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn monotonic_masters() -> Result<()> {
+        use crate::mdfreader::monotonic::MonotonicFix;
+        use crate::test_utils::SyntheticMdf;
+        use arrow::array::Float64Array;
+        let bytes = SyntheticMdf::mdf4()
+            .with_group(&[DataType::Float64])
+            .with_records(100)
+            .to_bytes()?;
+        let mut mdf = Mdf::from_bytes(&bytes, "monotonic_masters.mf4")?;
+        mdf.load_channels_data_from_bytes(&bytes, mdf.get_channel_names_set())?;
+        assert!(mdf
+            .enforce_monotonic_masters(MonotonicFix::Sort)?
+            .is_empty());
+        assert!(mdf.get_master_fixes().is_empty());
+
+        // clock reset at sample 60 written in a file
+        let master: Vec<f64> = (0..100)
+            .map(|i| match i {
+                0..60 => i as f64 * 0.01,
+                _ => (i - 60) as f64 * 0.01,
+            })
+            .collect();
+        let master_name = SyntheticMdf::master_name(0);
+        let channel = SyntheticMdf::channel_name(0, 0);
+        mdf.set_channel_data(&master_name, Arc::new(Float64Array::from(master.clone())))?;
+        let values = mdf
+            .get_channel_f64(&channel)
+            .context("channel not loaded")?
+            .to_vec();
+        let broken = TempFile::new("monotonic_masters_reset.mf4");
+        mdf.write(broken.path(), false)?;
+
+        // master values offset from the reset, channel unchanged
+        let options = ReadOptions {
+            monotonic_masters: Some(MonotonicFix::Offset),
+            ..Default::default()
+        };
+        let mut fixed = Mdf::new(broken.path())?;
+        fixed.load_channels_data_in_memory_with_options(
+            HashSet::from([channel.clone()]),
+            &options,
+        )?;
+        let fixes = fixed.get_master_fixes();
+        assert_eq!(fixes.len(), 1);
+        assert_eq!(fixes[0].master, master_name);
+        assert_eq!(fixes[0].offsets.len(), 1);
+        assert_eq!(fixes[0].offsets[0].0, 60);
+        assert!((fixes[0].offsets[0].1 - 0.6).abs() < 1e-9);
+        let time = fixed
+            .get_channel_f64(&master_name)
+            .context("master not loaded")?;
+        assert!(time.windows(2).all(|pair| pair[1] > pair[0]));
+        assert!((time[60] - 0.6).abs() < 1e-9);
+        assert_eq!(fixed.get_channel_f64(&channel), Some(values.as_slice()));
+
+        // records sorted by master value, channel following its master
+        let options = ReadOptions {
+            monotonic_masters: Some(MonotonicFix::Sort),
+            ..Default::default()
+        };
+        let mut sorted = Mdf::new(broken.path())?;
+        sorted.load_channels_data_in_memory_with_options(
+            HashSet::from([channel.clone()]),
+            &options,
+        )?;
+        let fixes = sorted.get_master_fixes();
+        assert_eq!(fixes.len(), 1);
+        assert_eq!(fixes[0].n_duplicates, 40);
+        assert!(fixes[0].to_string().contains("40 duplicate master values"));
+        let time = sorted
+            .get_channel_f64(&master_name)
+            .context("master not loaded")?;
+        let data = sorted
+            .get_channel_f64(&channel)
+            .context("channel not loaded")?;
+        assert!(time.windows(2).all(|pair| pair[1] >= pair[0]));
+        assert_eq!((time[0], data[0]), (master[0], values[0]));
+        assert_eq!((time[1], data[1]), (master[60], values[60]));
+        assert_eq!((time[99], data[99]), (master[59], values[59]));
         Ok(())
     }
 }